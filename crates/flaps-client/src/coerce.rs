@@ -3,28 +3,13 @@
 //! Every function returns `None` on type mismatch so callers can emit
 //! [`EvaluationErrorCode::TypeMismatch`] without panicking.
 
+use flaps_eval::FlagValue;
 use open_feature::StructValue;
 
-/// `i64::MIN` expressed as `f64` (exactly representable).
-///
-/// Shared with [`crate::metadata_mapper`], which applies the same
-/// integer-compatibility check to ruleset metadata numbers.
-pub(crate) const I64_MIN_F64: f64 = -9_223_372_036_854_775_808.0_f64;
-
-/// Largest `f64` value that can be cast to `i64` without truncation.
-///
-/// `i64::MAX` (2^63 - 1) rounds up to 2^63 when converted to `f64`, which
-/// overflows on cast back. The previous exactly-representable `f64` is
-/// 2^63 - 1024.
-///
-/// Shared with [`crate::metadata_mapper`], which applies the same
-/// integer-compatibility check to ruleset metadata numbers.
-pub(crate) const I64_MAX_SAFE_F64: f64 = 9_223_372_036_854_774_784.0_f64;
-
 /// Coerces a JSON value to `bool`. Returns `None` when the value is not a boolean.
 #[must_use]
 pub(crate) fn to_bool(value: &serde_json::Value) -> Option<bool> {
-    value.as_bool()
+    FlagValue::from_flag_value(value)
 }
 
 /// Coerces a JSON value to `i64`.
@@ -32,35 +17,23 @@ pub(crate) fn to_bool(value: &serde_json::Value) -> Option<bool> {
 /// Accepts JSON numbers that are already stored as integers, and JSON numbers
 /// stored as `f64` whose value is exactly representable as `i64` (e.g. `1.0`).
 /// Returns `None` on type mismatch or when the floating-point value has a
-/// fractional part.
+/// fractional part. The rule is [`flaps_eval::FlagValue`]'s, shared with
+/// [`flaps_eval::Resolution::value_or`].
 #[must_use]
 pub(crate) fn to_int(value: &serde_json::Value) -> Option<i64> {
-    if let Some(i) = value.as_i64() {
-        return Some(i);
-    }
-    // flagd stores all numeric variants as f64 internally; a value such as
-    // `1.0` round-trips through serde_json as a float-typed Number. Accept it
-    // when the float is an exact integer.
-    value.as_f64().and_then(|f| {
-        if f.fract() == 0.0 && (I64_MIN_F64..=I64_MAX_SAFE_F64).contains(&f) {
-            #[allow(clippy::cast_possible_truncation)]
-            Some(f as i64)
-        } else {
-            None
-        }
-    })
+    FlagValue::from_flag_value(value)
 }
 
 /// Coerces a JSON value to `f64`. Returns `None` when the value is not a number.
 #[must_use]
 pub(crate) fn to_float(value: &serde_json::Value) -> Option<f64> {
-    value.as_f64()
+    FlagValue::from_flag_value(value)
 }
 
 /// Coerces a JSON value to `String`. Returns `None` when the value is not a string.
 #[must_use]
 pub(crate) fn to_string(value: &serde_json::Value) -> Option<String> {
-    value.as_str().map(ToOwned::to_owned)
+    FlagValue::from_flag_value(value)
 }
 
 /// Coerces a JSON value to [`StructValue`].
@@ -3,7 +3,10 @@
 //! Synchronizes the compiled flagd ruleset over HTTP and SSE, evaluates flags
 //! locally through the flaps-eval engine, and survives server outages by
 //! serving the last-known-good ruleset. An optional disk snapshot enables
-//! warm-start even when the server is unreachable at startup.
+//! warm-start even when the server is unreachable at startup. Applications
+//! embedding Flaps can feed the ruleset from a [`FlagSource`] of their own
+//! instead of the server, and thin clients can have the server evaluate each
+//! flag, falling back to local evaluation while it is degraded.
 //!
 //! # Quick start
 //!
@@ -28,7 +31,14 @@ mod supervisor;
 mod sync;
 
 pub mod provider;
+pub mod reconcile;
+pub mod remote;
+pub mod source;
 pub mod status;
 
-pub use provider::{FlapsProvider, FlapsProviderConfig};
+pub use flaps_eval::{Fallback, Fallbacks};
+pub use provider::{FlapsProvider, FlapsProviderConfig, STALE_METADATA_KEY, VERSION_METADATA_KEY};
+pub use reconcile::{ReconciliationConfig, ReconciliationStats};
+pub use remote::{BreakerState, RemoteEvaluationConfig};
+pub use source::{ConfigSnapshot, FlagSource, SourceError};
 pub use status::SyncStatus;
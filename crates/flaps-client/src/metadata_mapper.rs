@@ -5,16 +5,15 @@
 //! and the equivalent OFREP response agree on both the entries present and
 //! their JSON-ish type (bool, string, integer or float).
 
-use flaps_eval::{Metadata, MetadataValue};
+use flaps_eval::{I64_MAX_SAFE_F64, I64_MIN_F64, Metadata, MetadataValue};
 use open_feature::{FlagMetadata, FlagMetadataValue};
-
-use crate::coerce::{I64_MAX_SAFE_F64, I64_MIN_F64};
+use serde_json::{Map, Value};
 
 /// Converts flaps-eval ruleset [`Metadata`] to an OpenFeature [`FlagMetadata`].
 ///
-/// Returns `None` when `metadata` is empty, matching the OFREP response
-/// contract where the `metadata` field is omitted rather than serialized as
-/// an empty object (see `flaps_server::routes::ofrep::metadata_field`).
+/// Returns `None` when `metadata` is empty, matching
+/// `flaps_server::routes::ofrep::metadata_field`. The provider adds the
+/// ruleset version on top, as the OFREP success responses do.
 #[must_use]
 pub(crate) fn map_metadata(metadata: &Metadata) -> Option<FlagMetadata> {
     if metadata.is_empty() {
@@ -28,6 +27,30 @@ pub(crate) fn map_metadata(metadata: &Metadata) -> Option<FlagMetadata> {
     Some(flag_metadata)
 }
 
+/// Converts the `metadata` object of an OFREP success response to an
+/// OpenFeature [`FlagMetadata`], typed as [`map_metadata`] types a local
+/// resolution.
+///
+/// Returns `None` when the object holds no scalar entry; arrays, objects and
+/// `null`, which the server never sends, are skipped.
+#[must_use]
+pub(crate) fn map_ofrep_metadata(metadata: &Map<String, Value>) -> Option<FlagMetadata> {
+    let mut flag_metadata = FlagMetadata::default();
+    for (key, value) in metadata {
+        let value = match value {
+            Value::Bool(value) => FlagMetadataValue::Bool(*value),
+            Value::String(value) => FlagMetadataValue::String(value.clone()),
+            Value::Number(number) => match number.as_i64() {
+                Some(value) => FlagMetadataValue::Int(value),
+                None => number_value(number.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::Null | Value::Array(_) | Value::Object(_) => continue,
+        };
+        flag_metadata.add_value(key.clone(), value);
+    }
+    (!flag_metadata.values.is_empty()).then_some(flag_metadata)
+}
+
 /// Converts a single flaps-eval metadata value, preserving its type.
 fn map_value(value: &MetadataValue) -> FlagMetadataValue {
     match value {
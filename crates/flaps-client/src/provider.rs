@@ -5,22 +5,30 @@
 
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use flaps_eval::{Fallback, Fallbacks};
 use open_feature::async_trait;
 use open_feature::provider::ResolutionDetails;
 use open_feature::provider::{FeatureProvider, ProviderMetadata, ProviderStatus};
 use open_feature::{
     EvaluationContext, EvaluationError, EvaluationErrorCode, EvaluationReason, EvaluationResult,
-    FlagMetadata, StructValue,
+    FlagMetadata, FlagMetadataValue, StructValue,
 };
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
+use tracing::warn;
 
 use crate::coerce;
 use crate::context_mapper;
 use crate::metadata_mapper;
 use crate::reason_mapper;
+use crate::reconcile::{
+    Observation, Reconciliation, ReconciliationConfig, ReconciliationStats, spawn_reconciler,
+};
+use crate::remote::{self, Breaker, BreakerState, RemoteEvaluationConfig, RemoteFailure};
 use crate::shared::ProviderShared;
+use crate::source::{FlagSource, spawn_source_sync};
 use crate::status::SyncStatus;
 use crate::supervisor::spawn_supervisor;
 
@@ -34,6 +42,27 @@ const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
 const DEFAULT_BACKOFF_BASE: Duration = Duration::from_secs(1);
 /// Default backoff ceiling.
 const DEFAULT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// Default lifetime of an idle pooled connection.
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Default TCP keep-alive probe interval.
+const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Flag metadata entry set to `true` on a resolution served from a stale
+/// ruleset (see [`FlapsProviderConfig::staleness_threshold`]).
+///
+/// Absent on fresh resolutions. The resolved value is unaffected: a stale
+/// ruleset is still served, this entry only lets callers and telemetry tell
+/// the two apart.
+pub const STALE_METADATA_KEY: &str = "flaps.stale";
+
+/// Flag metadata entry holding the version of the ruleset a resolution was
+/// evaluated against, as an integer.
+///
+/// The same entry OFREP responses carry (see
+/// [`flaps_eval::RULESET_VERSION_METADATA`]), so an exposure recorded from
+/// a local or a remote resolution is attributed to the same configuration.
+/// Absent when the ruleset source reported no version.
+pub const VERSION_METADATA_KEY: &str = flaps_eval::RULESET_VERSION_METADATA;
 
 /// Configuration for a [`FlapsProvider`].
 ///
@@ -52,15 +81,77 @@ pub struct FlapsProviderConfig {
     pub request_timeout: Duration,
     /// Path to write/read the disk snapshot. `None` disables snapshotting.
     pub snapshot_path: Option<PathBuf>,
-    /// Age threshold after which the provider reports [`ProviderStatus::STALE`].
+    /// Age threshold after which the provider reports [`ProviderStatus::STALE`]
+    /// and marks resolutions with [`STALE_METADATA_KEY`].
     /// `None` means the provider never reports `STALE` due to age.
     pub staleness_threshold: Option<Duration>,
+    /// When set, [`initialize`] waits up to this long for the first ruleset
+    /// (see [`FlapsProvider::wait_for_initialization`]) before returning.
+    /// `None`, the default, returns as soon as the background sync is spawned.
+    ///
+    /// [`initialize`]: FeatureProvider::initialize
+    pub initialization_timeout: Option<Duration>,
+    /// Granularity of the `$flagd.timestamp` seen by local evaluations.
+    ///
+    /// The clock is rounded down to a multiple of this interval so processes
+    /// with slightly skewed clocks flip time-based rules together. Defaults to
+    /// [`Duration::ZERO`] (whole seconds); match the server's
+    /// `evaluation_timestamp_quantum_secs` to keep local and remote
+    /// evaluation in agreement.
+    pub timestamp_quantum: Duration,
     /// Interval for the background polling fallback. Defaults to 5 min.
     pub poll_interval: Duration,
     /// Initial backoff delay after a failed SSE reconnect. Defaults to 1 s.
     pub backoff_base: Duration,
     /// Maximum backoff delay. Defaults to 30 s.
     pub backoff_max: Duration,
+    /// How long an idle pooled connection is kept for reuse. Defaults to
+    /// 90 s; `None` keeps idle connections until the server closes them.
+    ///
+    /// The provider builds one HTTP client and reuses its connection pool for
+    /// every ruleset fetch and SSE reconnect, so a polling or reconnecting
+    /// provider skips the TCP and TLS handshakes while a connection is idle.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Maximum number of idle connections kept per host. Defaults to
+    /// `usize::MAX` (no cap); `0` disables connection reuse.
+    pub pool_max_idle_per_host: usize,
+    /// Interval of TCP keep-alive probes on open connections, so idle pooled
+    /// connections are not silently dropped by middleboxes. Defaults to 60 s;
+    /// `None` disables keep-alive probes.
+    pub tcp_keepalive: Option<Duration>,
+    /// Number of consecutive failed ruleset fetches after which the provider
+    /// reports itself degraded: [`SyncStatus::degraded`] is set and the
+    /// provider status is [`ProviderStatus::STALE`]. The last good ruleset is
+    /// still served. `None`, the default, never reports degradation.
+    pub degraded_after_failures: Option<u32>,
+    /// Age past which evaluations log a warning that the ruleset is out of
+    /// date (see [`FlapsProvider::snapshot_age`]). The ruleset is still
+    /// served, and the warning is logged once per ruleset update. `None`, the
+    /// default, never warns.
+    ///
+    /// Unlike [`Self::staleness_threshold`], the age of a ruleset
+    /// warm-started from a disk snapshot counts from when the snapshot was
+    /// written, so a provider running offline from an old snapshot warns
+    /// straight away.
+    pub max_staleness: Option<Duration>,
+    /// When set, a background task has the server re-evaluate a random
+    /// sample of recent local evaluations and logs every disagreement (see
+    /// [`FlapsProvider::reconciliation_stats`]). Served values are never
+    /// affected. `None`, the default, disables reconciliation.
+    pub reconciliation: Option<ReconciliationConfig>,
+    /// Fallback policy of each flag key. A boolean flag registered as
+    /// [`Fallback::Closed`], a gate, resolves to `false` instead of an error
+    /// when it is missing, disabled or killed, fails to evaluate or is not a
+    /// boolean, so the caller's default never opens it. Every other flag
+    /// returns the error and the SDK serves the caller's default. Defaults to
+    /// [`Fallback::CallerDefault`] for every flag.
+    pub fallbacks: Fallbacks,
+    /// When set, flags are evaluated by the server's OFREP endpoint, and
+    /// locally from the synced ruleset only when the server is slow or
+    /// erroring, as decided by a circuit breaker (see
+    /// [`FlapsProvider::breaker_state`]). `None`, the default, evaluates
+    /// every flag locally.
+    pub remote_evaluation: Option<RemoteEvaluationConfig>,
 }
 
 impl FlapsProviderConfig {
@@ -74,13 +165,37 @@ impl FlapsProviderConfig {
             request_timeout: DEFAULT_REQUEST_TIMEOUT,
             snapshot_path: None,
             staleness_threshold: None,
+            initialization_timeout: None,
+            timestamp_quantum: Duration::ZERO,
             poll_interval: DEFAULT_POLL_INTERVAL,
             backoff_base: DEFAULT_BACKOFF_BASE,
             backoff_max: DEFAULT_BACKOFF_MAX,
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            pool_max_idle_per_host: usize::MAX,
+            tcp_keepalive: Some(DEFAULT_TCP_KEEPALIVE),
+            degraded_after_failures: None,
+            max_staleness: None,
+            reconciliation: None,
+            fallbacks: Fallbacks::new(),
+            remote_evaluation: None,
         }
     }
 }
 
+/// Builds the HTTP client a provider uses for its whole lifetime, applying
+/// the timeouts and connection pool settings of `config`, unless one is
+/// supplied through [`FlapsProvider::with_http_client`].
+fn http_client(config: &FlapsProviderConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.request_timeout)
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .tcp_keepalive(config.tcp_keepalive)
+        .build()
+        .unwrap_or_default()
+}
+
 /// OpenFeature provider that evaluates flags locally against a ruleset fetched
 /// from the Flaps server.
 ///
@@ -94,13 +209,31 @@ impl FlapsProviderConfig {
 /// the ruleset is available immediately at [`initialize`] time even when the
 /// server is unreachable (warm-start).
 ///
+/// Evaluation never performs I/O: it reads the held ruleset only. A flag the
+/// ruleset lacks resolves to [`EvaluationErrorCode::FlagNotFound`] rather
+/// than being fetched, so with [`Self::wait_for_initialization`] deciding
+/// readiness up front, no network call happens on the evaluation path.
+///
+/// The ruleset can also come from an application-supplied [`FlagSource`]
+/// instead of a Flaps server (see [`Self::with_source`]).
+///
+/// With [`FlapsProviderConfig::remote_evaluation`] set, the provider asks the
+/// server to evaluate each flag instead, and falls back to the held ruleset
+/// while the server is degraded (see [`crate::remote`]).
+///
 /// [`initialize`]: FeatureProvider::initialize
 pub struct FlapsProvider {
     config: FlapsProviderConfig,
     http_client: reqwest::Client,
+    /// Custom ruleset source; `None` syncs from the Flaps server.
+    source: Option<Arc<dyn FlagSource>>,
     shared: Arc<ProviderShared>,
     metadata: ProviderMetadata,
     task: Option<JoinHandle<()>>,
+    reconciliation: Option<Arc<Reconciliation>>,
+    reconciler: Option<JoinHandle<()>>,
+    /// Circuit breaker of server evaluation; `None` evaluates locally.
+    breaker: Option<Breaker>,
 }
 
 impl FlapsProvider {
@@ -112,21 +245,64 @@ impl FlapsProvider {
     /// [`initialize`]: FeatureProvider::initialize
     #[must_use]
     pub fn new(config: FlapsProviderConfig) -> Self {
-        let http_client = reqwest::Client::builder()
-            .connect_timeout(config.connect_timeout)
-            .timeout(config.request_timeout)
-            .build()
-            .unwrap_or_default();
+        let http_client = http_client(&config);
+        Self::with_http_client(config, http_client)
+    }
+
+    /// Creates a new provider from `config` that sends every request with
+    /// `http_client` instead of a client built from `config`.
+    ///
+    /// Use it for HTTP settings the config does not cover: default headers
+    /// (tracing, tenant routing), a proxy, or an mTLS client certificate.
+    /// The provider still adds its own `Authorization` and conditional
+    /// headers to each request.
+    ///
+    /// [`FlapsProviderConfig::connect_timeout`],
+    /// [`FlapsProviderConfig::request_timeout`] and the connection pool
+    /// settings only shape the client [`Self::new`] builds, so they are not
+    /// applied here: build `http_client` with a request timeout of its own,
+    /// such as `request_timeout`, or a hung server stalls ruleset fetches
+    /// until the connection drops.
+    #[must_use]
+    pub fn with_http_client(config: FlapsProviderConfig, http_client: reqwest::Client) -> Self {
+        let reconciliation = config
+            .reconciliation
+            .map(|reconciliation| Arc::new(Reconciliation::new(reconciliation.sample_size)));
+        let breaker = config.remote_evaluation.map(Breaker::new);
 
         Self {
             config,
             http_client,
+            source: None,
             shared: Arc::new(ProviderShared::new()),
             metadata: ProviderMetadata::new("flaps"),
             task: None,
+            reconciliation,
+            reconciler: None,
+            breaker,
         }
     }
 
+    /// Creates a new provider from `config` that reads its ruleset from
+    /// `source` instead of a Flaps server.
+    ///
+    /// `source` is fetched when the SDK calls [`initialize`], then every
+    /// [`FlapsProviderConfig::poll_interval`] and whenever its
+    /// [`FlagSource::subscribe`] stream pushes a ruleset. The SSE and backoff
+    /// settings do not apply; [`FlapsProviderConfig::base_url`] and
+    /// [`FlapsProviderConfig::sdk_key`] are only used by
+    /// [`FlapsProviderConfig::reconciliation`] and
+    /// [`FlapsProviderConfig::remote_evaluation`], which still ask the
+    /// server.
+    ///
+    /// [`initialize`]: FeatureProvider::initialize
+    #[must_use]
+    pub fn with_source(config: FlapsProviderConfig, source: impl FlagSource) -> Self {
+        let mut provider = Self::new(config);
+        provider.source = Some(Arc::new(source));
+        provider
+    }
+
     /// Returns a snapshot of provider freshness metrics.
     #[must_use]
     pub fn sync_status(&self) -> SyncStatus {
@@ -135,14 +311,153 @@ impl FlapsProvider {
             .sync_state
             .lock()
             .unwrap_or_else(std::sync::PoisonError::into_inner);
-        SyncStatus::from_state(&state)
+        SyncStatus::from_state(&state, self.config.degraded_after_failures)
+    }
+
+    /// Returns how long ago the held ruleset was last known current: the
+    /// time since the last successful sync, or since the disk snapshot it
+    /// was warm-started from was written. `None` before any ruleset is held.
+    #[must_use]
+    pub fn snapshot_age(&self) -> Option<Duration> {
+        self.shared
+            .sync_state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .age(SystemTime::now())
+    }
+
+    /// Returns the counters of the reconciliation task, or `None` when
+    /// [`FlapsProviderConfig::reconciliation`] is not set.
+    #[must_use]
+    pub fn reconciliation_stats(&self) -> Option<ReconciliationStats> {
+        self.reconciliation
+            .as_ref()
+            .map(|reconciliation| reconciliation.stats())
+    }
+
+    /// Returns the state of the circuit breaker guarding server evaluation,
+    /// or `None` when [`FlapsProviderConfig::remote_evaluation`] is not set.
+    #[must_use]
+    pub fn breaker_state(&self) -> Option<BreakerState> {
+        self.breaker.as_ref().map(Breaker::state)
+    }
+
+    /// Returns a receiver that observes `true` once the first ruleset is
+    /// available, from a successful sync or a disk snapshot.
+    #[must_use]
+    pub fn initialized(&self) -> watch::Receiver<bool> {
+        self.shared.initialized.subscribe()
+    }
+
+    /// Waits until the first ruleset is available, for at most `timeout`.
+    ///
+    /// Resolves immediately when a ruleset is already loaded, including one
+    /// warm-started from a disk snapshot. Returns `true` when the provider is
+    /// initialized and `false` when `timeout` elapsed first; on `false` the
+    /// background sync keeps retrying and evaluations return
+    /// [`EvaluationErrorCode::ProviderNotReady`] until it succeeds.
+    pub async fn wait_for_initialization(&self, timeout: Duration) -> bool {
+        let mut initialized = self.initialized();
+        // `wait_for` only fails when the sender is dropped, which cannot happen
+        // while `self` holds the shared state.
+        tokio::time::timeout(timeout, initialized.wait_for(|ready| *ready))
+            .await
+            .is_ok_and(|ready| ready.is_ok())
+    }
+
+    /// Returns `true` when a [`staleness_threshold`] is configured and the
+    /// held ruleset is older than it, or was loaded from a snapshot and not
+    /// yet confirmed by the server.
+    ///
+    /// [`staleness_threshold`]: FlapsProviderConfig::staleness_threshold
+    fn is_stale(&self) -> bool {
+        let Some(threshold) = self.config.staleness_threshold else {
+            return false;
+        };
+        let state = self
+            .shared
+            .sync_state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        state.loaded_from_snapshot
+            || state
+                .last_successful_sync
+                .is_none_or(|t| t.elapsed() > threshold)
+    }
+
+    /// Logs a warning when the held ruleset is older than
+    /// [`FlapsProviderConfig::max_staleness`], once per ruleset update.
+    fn warn_if_past_max_staleness(&self) {
+        let Some(max_staleness) = self.config.max_staleness else {
+            return;
+        };
+        let age = self
+            .shared
+            .sync_state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .staleness_warning(max_staleness, SystemTime::now());
+        if let Some(age) = age {
+            warn!(
+                ?age,
+                ?max_staleness,
+                "serving a ruleset older than max_staleness"
+            );
+        }
+    }
+
+    /// Evaluates a flag on the server when remote evaluation is configured
+    /// and its breaker lets the call through, and from the current ruleset
+    /// otherwise.
+    async fn resolve(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+    ) -> EvaluationResult<(
+        serde_json::Value,
+        Option<String>,
+        EvaluationReason,
+        Option<FlagMetadata>,
+    )> {
+        if let Some(breaker) = self.breaker.as_ref().filter(|breaker| breaker.allow()) {
+            let eval_ctx = context_mapper::map_context(evaluation_context)?;
+            let answer = remote::evaluate_remotely(
+                &self.http_client,
+                &self.config.base_url,
+                &self.config.sdk_key,
+                flag_key,
+                &eval_ctx,
+                breaker.config().slow_call_threshold,
+            )
+            .await;
+            match answer {
+                Ok(resolution) => {
+                    breaker.record_success();
+                    let reason = resolution
+                        .reason
+                        .as_deref()
+                        .map_or(EvaluationReason::Unknown, reason_mapper::map_ofrep_reason);
+                    return Ok((
+                        resolution.value,
+                        resolution.variant,
+                        reason,
+                        resolution.metadata,
+                    ));
+                }
+                Err(RemoteFailure::Refused) => breaker.record_success(),
+                Err(RemoteFailure::Unavailable) => breaker.record_failure(),
+            }
+        }
+        self.evaluate_raw(flag_key, evaluation_context)
     }
 
     /// Evaluates a flag from the current ruleset.
     ///
     /// Returns the resolved value, variant, reason and the OpenFeature
     /// [`FlagMetadata`] converted from the merged flag-set and flag metadata
-    /// (`None` when the merged metadata is empty).
+    /// (`None` when the merged metadata is empty), with
+    /// [`VERSION_METADATA_KEY`] added when the ruleset version is known and
+    /// [`STALE_METADATA_KEY`] when the ruleset is stale.
     fn evaluate_raw(
         &self,
         flag_key: &str,
@@ -154,14 +469,17 @@ impl FlapsProvider {
         Option<FlagMetadata>,
     )> {
         let guard = self.shared.ruleset.load();
-        let flag_set = guard.as_ref().as_ref().ok_or_else(|| EvaluationError {
+        let held = guard.as_ref().as_ref().ok_or_else(|| EvaluationError {
             code: EvaluationErrorCode::ProviderNotReady,
             message: Some("No ruleset loaded; sync may have failed during initialize".to_owned()),
         })?;
 
-        let eval_ctx = context_mapper::map_context(evaluation_context)?;
+        self.warn_if_past_max_staleness();
 
-        let resolution = flag_set.evaluate(flag_key, &eval_ctx).map_err(|e| {
+        let eval_ctx = context_mapper::map_context(evaluation_context)?
+            .with_timestamp_quantum(self.config.timestamp_quantum);
+
+        let resolution = held.flag_set.evaluate(flag_key, &eval_ctx).map_err(|e| {
             use flaps_eval::EvaluationError as EvalErr;
             let (code, msg) = match e {
                 EvalErr::FlagNotFound { flag_key: ref k } => (
@@ -179,6 +497,30 @@ impl FlapsProvider {
                     EvaluationErrorCode::General("UNSUPPORTED_OPERATION".to_owned()),
                     format!("unsupported operator `{operator}`"),
                 ),
+                EvalErr::BudgetExceeded {
+                    ref flag_key,
+                    max_steps,
+                } => (
+                    EvaluationErrorCode::General("BUDGET_EXCEEDED".to_owned()),
+                    format!("flag `{flag_key}` targeting exceeds {max_steps} evaluation steps"),
+                ),
+                EvalErr::UnresolvedEvaluator {
+                    ref flag_key,
+                    ref reference,
+                } => (
+                    EvaluationErrorCode::General("UNRESOLVED_EVALUATOR".to_owned()),
+                    format!(
+                        "flag `{flag_key}` targeting references unloaded evaluator `{reference}`"
+                    ),
+                ),
+                EvalErr::TypeMismatch {
+                    ref flag_key,
+                    operator,
+                    ref operand,
+                } => (
+                    EvaluationErrorCode::General("ATTRIBUTE_TYPE_MISMATCH".to_owned()),
+                    format!("flag `{flag_key}` targeting applies `{operator}` to {operand}"),
+                ),
             };
             EvaluationError {
                 code,
@@ -186,22 +528,90 @@ impl FlapsProvider {
             }
         })?;
 
-        let value = resolution.value.ok_or_else(|| EvaluationError {
-            code: EvaluationErrorCode::General("DISABLED_OR_NO_VARIANT".to_owned()),
-            message: Some(format!(
-                "flag `{flag_key}` is disabled or has no variant; caller default applies"
-            )),
+        // A killed or version-gated flag falls back like a disabled one,
+        // under its own code so telemetry can tell an emergency stop or an
+        // outdated app from a flag that is off.
+        let value = resolution.value.ok_or_else(|| match resolution.reason {
+            flaps_eval::Reason::Killed => EvaluationError {
+                code: EvaluationErrorCode::General("KILLED".to_owned()),
+                message: Some(format!(
+                    "flag `{flag_key}` is killed ({}); caller default applies",
+                    kill_reason(&resolution.metadata)
+                )),
+            },
+            flaps_eval::Reason::VersionGated => EvaluationError {
+                code: EvaluationErrorCode::General("VERSION_GATED".to_owned()),
+                message: Some(format!(
+                    "flag `{flag_key}` requires a newer app version; caller default applies"
+                )),
+            },
+            _ => EvaluationError {
+                code: EvaluationErrorCode::General("DISABLED_OR_NO_VARIANT".to_owned()),
+                message: Some(format!(
+                    "flag `{flag_key}` is disabled or has no variant; caller default applies"
+                )),
+            },
         })?;
 
+        if let Some(reconciliation) = &self.reconciliation {
+            reconciliation.record(|| Observation {
+                flag_key: flag_key.to_owned(),
+                context: eval_ctx,
+                value: value.clone(),
+                variant: resolution.variant.clone(),
+            });
+        }
+
         let reason = reason_mapper::map_reason(resolution.reason);
-        let flag_metadata = metadata_mapper::map_metadata(&resolution.metadata);
+        let mut flag_metadata = metadata_mapper::map_metadata(&resolution.metadata);
+        if let Some(version) = held.version.and_then(|version| i64::try_from(version).ok()) {
+            flag_metadata
+                .get_or_insert_with(FlagMetadata::default)
+                .add_value(VERSION_METADATA_KEY, FlagMetadataValue::Int(version));
+        }
+        if self.is_stale() {
+            flag_metadata
+                .get_or_insert_with(FlagMetadata::default)
+                .add_value(STALE_METADATA_KEY, FlagMetadataValue::Bool(true));
+        }
         Ok((value, resolution.variant, reason, flag_metadata))
     }
 }
 
+/// The resolution of a [`Fallback::Closed`] gate that failed with `error`:
+/// `false`, with the `DISABLED` reason for a disabled, killed or
+/// version-gated flag and `ERROR` otherwise.
+fn closed_gate(error: &EvaluationError) -> ResolutionDetails<bool> {
+    let reason = match &error.code {
+        EvaluationErrorCode::General(code)
+            if code == "KILLED" || code == "VERSION_GATED" || code == "DISABLED_OR_NO_VARIANT" =>
+        {
+            EvaluationReason::Disabled
+        }
+        _ => EvaluationReason::Error,
+    };
+    ResolutionDetails {
+        value: false,
+        variant: None,
+        reason: Some(reason),
+        flag_metadata: None,
+    }
+}
+
+/// Reads the kill reason a killed flag carries in its metadata.
+fn kill_reason(metadata: &flaps_eval::Metadata) -> &str {
+    match metadata.get(flaps_eval::KILL_REASON_METADATA) {
+        Some(flaps_eval::MetadataValue::String(reason)) => reason,
+        _ => "no reason given",
+    }
+}
+
 impl Drop for FlapsProvider {
     fn drop(&mut self) {
-        if let Some(handle) = self.task.take() {
+        for handle in [self.task.take(), self.reconciler.take()]
+            .into_iter()
+            .flatten()
+        {
             handle.abort();
         }
     }
@@ -215,13 +625,45 @@ impl FeatureProvider for FlapsProvider {
             crate::snapshot::load_snapshot(path, &self.shared).await;
         }
 
-        // Step 2: spawn the background supervisor (SSE + polling fallback).
-        let handle = spawn_supervisor(
-            self.http_client.clone(),
-            self.config.clone(),
-            Arc::clone(&self.shared),
-        );
+        // Step 2: spawn the background sync: the supervisor (SSE + polling
+        // fallback), or the custom source.
+        let handle = match &self.source {
+            Some(source) => spawn_source_sync(
+                Arc::clone(source),
+                self.config.poll_interval,
+                self.config.snapshot_path.clone(),
+                Arc::clone(&self.shared),
+            ),
+            None => spawn_supervisor(
+                self.http_client.clone(),
+                self.config.clone(),
+                Arc::clone(&self.shared),
+            ),
+        };
         self.task = Some(handle);
+
+        // Step 3: spawn the reconciliation task when configured.
+        if let (Some(config), Some(reconciliation)) =
+            (self.config.reconciliation, &self.reconciliation)
+        {
+            self.reconciler = Some(spawn_reconciler(
+                self.http_client.clone(),
+                self.config.base_url.clone(),
+                self.config.sdk_key.clone(),
+                config,
+                Arc::clone(reconciliation),
+            ));
+        }
+
+        // Step 4: optionally hold the caller until the first ruleset lands.
+        if let Some(timeout) = self.config.initialization_timeout {
+            if !self.wait_for_initialization(timeout).await {
+                warn!(
+                    ?timeout,
+                    "no ruleset available before the initialization timeout"
+                );
+            }
+        }
     }
 
     fn metadata(&self) -> &ProviderMetadata {
@@ -236,22 +678,8 @@ impl FeatureProvider for FlapsProvider {
             return ProviderStatus::NotReady;
         }
 
-        if let Some(threshold) = self.config.staleness_threshold {
-            let state = self
-                .shared
-                .sync_state
-                .lock()
-                .unwrap_or_else(std::sync::PoisonError::into_inner);
-
-            // Stale when: snapshot not yet confirmed by network, or last sync too old.
-            let is_stale = state.loaded_from_snapshot
-                || state
-                    .last_successful_sync
-                    .is_none_or(|t| t.elapsed() > threshold);
-
-            if is_stale {
-                return ProviderStatus::STALE;
-            }
+        if self.is_stale() || self.sync_status().degraded {
+            return ProviderStatus::STALE;
         }
 
         ProviderStatus::Ready
@@ -262,18 +690,26 @@ impl FeatureProvider for FlapsProvider {
         flag_key: &str,
         evaluation_context: &EvaluationContext,
     ) -> EvaluationResult<ResolutionDetails<bool>> {
-        let (value, variant, reason, flag_metadata) =
-            self.evaluate_raw(flag_key, evaluation_context)?;
-        let typed = coerce::to_bool(&value).ok_or_else(|| EvaluationError {
-            code: EvaluationErrorCode::TypeMismatch,
-            message: Some(format!("flag `{flag_key}` value is not a boolean")),
-        })?;
-        Ok(ResolutionDetails {
-            value: typed,
-            variant,
-            reason: Some(reason),
-            flag_metadata,
-        })
+        let resolved = self.resolve(flag_key, evaluation_context).await.and_then(
+            |(value, variant, reason, flag_metadata)| {
+                let typed = coerce::to_bool(&value).ok_or_else(|| EvaluationError {
+                    code: EvaluationErrorCode::TypeMismatch,
+                    message: Some(format!("flag `{flag_key}` value is not a boolean")),
+                })?;
+                Ok(ResolutionDetails {
+                    value: typed,
+                    variant,
+                    reason: Some(reason),
+                    flag_metadata,
+                })
+            },
+        );
+        match resolved {
+            Err(error) if self.config.fallbacks.for_flag(flag_key) == Fallback::Closed => {
+                Ok(closed_gate(&error))
+            }
+            resolved => resolved,
+        }
     }
 
     async fn resolve_int_value(
@@ -282,7 +718,7 @@ impl FeatureProvider for FlapsProvider {
         evaluation_context: &EvaluationContext,
     ) -> EvaluationResult<ResolutionDetails<i64>> {
         let (value, variant, reason, flag_metadata) =
-            self.evaluate_raw(flag_key, evaluation_context)?;
+            self.resolve(flag_key, evaluation_context).await?;
         let typed = coerce::to_int(&value).ok_or_else(|| EvaluationError {
             code: EvaluationErrorCode::TypeMismatch,
             message: Some(format!("flag `{flag_key}` value is not an integer")),
@@ -301,7 +737,7 @@ impl FeatureProvider for FlapsProvider {
         evaluation_context: &EvaluationContext,
     ) -> EvaluationResult<ResolutionDetails<f64>> {
         let (value, variant, reason, flag_metadata) =
-            self.evaluate_raw(flag_key, evaluation_context)?;
+            self.resolve(flag_key, evaluation_context).await?;
         let typed = coerce::to_float(&value).ok_or_else(|| EvaluationError {
             code: EvaluationErrorCode::TypeMismatch,
             message: Some(format!("flag `{flag_key}` value is not a float")),
@@ -320,7 +756,7 @@ impl FeatureProvider for FlapsProvider {
         evaluation_context: &EvaluationContext,
     ) -> EvaluationResult<ResolutionDetails<String>> {
         let (value, variant, reason, flag_metadata) =
-            self.evaluate_raw(flag_key, evaluation_context)?;
+            self.resolve(flag_key, evaluation_context).await?;
         let typed = coerce::to_string(&value).ok_or_else(|| EvaluationError {
             code: EvaluationErrorCode::TypeMismatch,
             message: Some(format!("flag `{flag_key}` value is not a string")),
@@ -339,7 +775,7 @@ impl FeatureProvider for FlapsProvider {
         evaluation_context: &EvaluationContext,
     ) -> EvaluationResult<ResolutionDetails<StructValue>> {
         let (value, variant, reason, flag_metadata) =
-            self.evaluate_raw(flag_key, evaluation_context)?;
+            self.resolve(flag_key, evaluation_context).await?;
         let typed = coerce::to_struct(&value).ok_or_else(|| EvaluationError {
             code: EvaluationErrorCode::TypeMismatch,
             message: Some(format!("flag `{flag_key}` value is not a struct/object")),
@@ -355,6 +791,14 @@ impl FeatureProvider for FlapsProvider {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use std::net::SocketAddr;
+    use std::sync::Mutex;
+
+    use axum::extract::ConnectInfo;
+
+    use super::*;
+
     /// Verifies that `Drop` calls `abort()` without panicking.
     ///
     /// The [`tokio::task::JoinHandle`] abort path is exercised implicitly by every test that
@@ -365,4 +809,61 @@ mod tests {
         // Design intent: `Drop for FlapsProvider` calls `handle.abort()`.
         // The integration tests exercise this path when they drop the provider.
     }
+
+    /// Serves `GET /` and records the client port of every request, so
+    /// distinct ports count the connections opened.
+    async fn spawn_port_recorder() -> (SocketAddr, Arc<Mutex<HashSet<u16>>>) {
+        let ports = Arc::new(Mutex::new(HashSet::new()));
+        let recorded = Arc::clone(&ports);
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::get(move |ConnectInfo(peer): ConnectInfo<SocketAddr>| {
+                recorded.lock().unwrap().insert(peer.port());
+                async {}
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+        (addr, ports)
+    }
+
+    /// Sends three sequential requests with the client built from `config`
+    /// and returns the number of connections they used.
+    async fn connections_used(config: &FlapsProviderConfig) -> usize {
+        let (addr, ports) = spawn_port_recorder().await;
+        let client = http_client(config);
+        for _ in 0..3 {
+            client
+                .get(format!("http://{addr}/"))
+                .send()
+                .await
+                .unwrap()
+                .error_for_status()
+                .unwrap();
+        }
+        ports.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn sequential_requests_reuse_one_pooled_connection() {
+        let config = FlapsProviderConfig::new("http://unused", "key");
+        assert_eq!(connections_used(&config).await, 1);
+    }
+
+    #[tokio::test]
+    async fn pool_settings_are_applied_to_the_client() {
+        let config = FlapsProviderConfig {
+            pool_max_idle_per_host: 0,
+            ..FlapsProviderConfig::new("http://unused", "key")
+        };
+        assert_eq!(connections_used(&config).await, 3);
+    }
 }
@@ -11,6 +11,25 @@ pub(crate) fn map_reason(reason: Reason) -> EvaluationReason {
         Reason::TargetingMatch => EvaluationReason::TargetingMatch,
         Reason::Default => EvaluationReason::Default,
         Reason::Disabled => EvaluationReason::Disabled,
+        Reason::Killed => EvaluationReason::Other("KILLED".to_owned()),
+        Reason::VersionGated => EvaluationReason::Other("VERSION_GATED".to_owned()),
+    }
+}
+
+/// Converts an OFREP reason string, as answered by the server's evaluate
+/// endpoint, to an OpenFeature evaluation reason.
+#[must_use]
+pub(crate) fn map_ofrep_reason(reason: &str) -> EvaluationReason {
+    match reason {
+        "STATIC" => EvaluationReason::Static,
+        "TARGETING_MATCH" => EvaluationReason::TargetingMatch,
+        "DEFAULT" => EvaluationReason::Default,
+        "DISABLED" => EvaluationReason::Disabled,
+        "SPLIT" => EvaluationReason::Split,
+        "CACHED" => EvaluationReason::Cached,
+        "ERROR" => EvaluationReason::Error,
+        "UNKNOWN" => EvaluationReason::Unknown,
+        other => EvaluationReason::Other(other.to_owned()),
     }
 }
 
@@ -40,4 +59,33 @@ mod tests {
     fn map_disabled() {
         assert_eq!(map_reason(Reason::Disabled), EvaluationReason::Disabled);
     }
+
+    #[test]
+    fn map_killed() {
+        assert_eq!(
+            map_reason(Reason::Killed),
+            EvaluationReason::Other("KILLED".to_owned())
+        );
+    }
+
+    #[test]
+    fn map_version_gated() {
+        assert_eq!(
+            map_reason(Reason::VersionGated),
+            EvaluationReason::Other("VERSION_GATED".to_owned())
+        );
+    }
+
+    #[test]
+    fn map_ofrep_reasons() {
+        assert_eq!(
+            map_ofrep_reason("TARGETING_MATCH"),
+            EvaluationReason::TargetingMatch
+        );
+        assert_eq!(map_ofrep_reason("SPLIT"), EvaluationReason::Split);
+        assert_eq!(
+            map_ofrep_reason("KILLED"),
+            EvaluationReason::Other("KILLED".to_owned())
+        );
+    }
 }
@@ -0,0 +1,413 @@
+//! Reconciliation of local evaluations against the server.
+//!
+//! A provider configured with [`FlapsProviderConfig::reconciliation`] keeps a
+//! uniform random sample of the flag evaluations it served (flag key,
+//! context, value and variant) since the last round. A background task wakes
+//! every [`ReconciliationConfig::interval`], takes the sample and
+//! re-evaluates each sampled pair through the server's OFREP endpoint
+//! (`POST /ofrep/v1/evaluate/flags/{key}`). A server answer with a
+//! different value or variant is logged as a warning and counted in
+//! [`ReconciliationStats::mismatches`].
+//!
+//! Reconciliation only observes: the value served to the caller is always
+//! the local one, and a slow or unreachable server never delays an
+//! evaluation. Evaluations are sampled as they are served, so one left out
+//! of the sample is never copied or stored. Some differences are expected
+//! rather than bugs: the server evaluates at its own clock and may enrich
+//! contexts with geolocation, so rules on `$flagd.timestamp` or on `country`
+//! can legitimately disagree, as can any evaluation made just before a
+//! ruleset update.
+//!
+//! [`FlapsProviderConfig::reconciliation`]: crate::FlapsProviderConfig::reconciliation
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::task::JoinHandle;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::warn;
+
+use crate::backoff::clock_seeded_jitter;
+use crate::remote::post_evaluation;
+
+/// Settings of the reconciliation task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconciliationConfig {
+    /// Time between two reconciliation rounds. Defaults to 60 s.
+    pub interval: Duration,
+    /// Number of evaluations served since the previous round that the
+    /// server re-evaluates per round. Defaults to 10.
+    pub sample_size: usize,
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            sample_size: 10,
+        }
+    }
+}
+
+/// Counters of the reconciliation task since the provider was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReconciliationStats {
+    /// Evaluations the server re-evaluated.
+    pub checked: u64,
+    /// Evaluations the server resolved to a different value or variant.
+    pub mismatches: u64,
+    /// Evaluations the server could not re-evaluate: network error, non-2xx
+    /// answer or unreadable body.
+    pub failed: u64,
+}
+
+/// One evaluation served locally and picked for reconciliation.
+#[derive(Debug, Clone)]
+pub(crate) struct Observation {
+    pub(crate) flag_key: String,
+    pub(crate) context: flaps_eval::EvaluationContext,
+    pub(crate) value: Value,
+    pub(crate) variant: Option<String>,
+}
+
+/// The sampled evaluations and counters shared by the provider and its
+/// reconciliation task.
+pub(crate) struct Reconciliation {
+    sampled: Mutex<Vec<Observation>>,
+    sample_size: usize,
+    /// Evaluations served since the sample was last taken.
+    seen: AtomicU64,
+    seed: u64,
+    draws: AtomicU64,
+    checked: AtomicU64,
+    mismatches: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl Reconciliation {
+    /// Creates an empty sample of at most `sample_size` evaluations.
+    pub(crate) fn new(sample_size: usize) -> Self {
+        Self {
+            sampled: Mutex::new(Vec::new()),
+            sample_size,
+            seen: AtomicU64::new(0),
+            seed: clock_seeded_jitter()(),
+            draws: AtomicU64::new(0),
+            checked: AtomicU64::new(0),
+            mismatches: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    /// Records an evaluation when it enters the sample.
+    ///
+    /// `observe` only runs for an evaluation that enters the sample, so one
+    /// left out costs two atomic increments and nothing else.
+    pub(crate) fn record(&self, observe: impl FnOnce() -> Observation) {
+        let Some(slot) = self.pick_slot() else {
+            return;
+        };
+        let observation = observe();
+        // Slots are handed out before the lock is taken, so evaluations
+        // filling the sample may arrive out of order: push while there is
+        // room rather than trusting the slot.
+        let mut sampled = self.lock();
+        if sampled.len() < self.sample_size {
+            sampled.push(observation);
+        } else {
+            sampled[slot] = observation;
+        }
+    }
+
+    /// Picks the sample slot of the next evaluation, if it enters the
+    /// sample (reservoir sampling).
+    ///
+    /// The `n`th evaluation since the sample was taken (from 0) fills a free
+    /// slot while there is one, and otherwise replaces a random slot with
+    /// probability `sample_size / (n + 1)`, which leaves every evaluation of
+    /// the round equally likely to be sampled.
+    fn pick_slot(&self) -> Option<usize> {
+        if self.sample_size == 0 {
+            return None;
+        }
+        let n = self.seen.fetch_add(1, Ordering::Relaxed);
+        let slot = if n < self.sample_size as u64 {
+            n
+        } else {
+            self.random() % (n + 1)
+        };
+        usize::try_from(slot)
+            .ok()
+            .filter(|slot| *slot < self.sample_size)
+    }
+
+    /// Returns the next number of a `SplitMix64` sequence, which only takes
+    /// an atomic increment to draw.
+    fn random(&self) -> u64 {
+        let draw = self.draws.fetch_add(1, Ordering::Relaxed);
+        let mut z = self
+            .seed
+            .wrapping_add(draw.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the counters.
+    pub(crate) fn stats(&self) -> ReconciliationStats {
+        ReconciliationStats {
+            checked: self.checked.load(Ordering::Relaxed),
+            mismatches: self.mismatches.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Removes and returns the sample, starting a new round.
+    fn drain(&self) -> Vec<Observation> {
+        let mut sampled = self.lock();
+        self.seen.store(0, Ordering::Relaxed);
+        std::mem::take(&mut *sampled)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Observation>> {
+        self.sampled
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Spawns the reconciliation task and returns its [`JoinHandle`].
+pub(crate) fn spawn_reconciler(
+    client: reqwest::Client,
+    base_url: String,
+    sdk_key: String,
+    config: ReconciliationConfig,
+    reconciliation: Arc<Reconciliation>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = interval(config.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately; nothing is recorded yet.
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            reconcile_once(&client, &base_url, &sdk_key, &reconciliation).await;
+        }
+    })
+}
+
+/// Runs one reconciliation round: takes the sampled evaluations and has the
+/// server re-evaluate each of them.
+pub(crate) async fn reconcile_once(
+    client: &reqwest::Client,
+    base_url: &str,
+    sdk_key: &str,
+    reconciliation: &Reconciliation,
+) {
+    for observation in reconciliation.drain() {
+        match remote_evaluation(client, base_url, sdk_key, &observation).await {
+            Some((value, variant)) => {
+                reconciliation.checked.fetch_add(1, Ordering::Relaxed);
+                if value != observation.value || variant != observation.variant {
+                    reconciliation.mismatches.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        flag_key = %observation.flag_key,
+                        targeting_key = ?observation.context.targeting_key,
+                        local_value = %observation.value,
+                        local_variant = ?observation.variant,
+                        server_value = %value,
+                        server_variant = ?variant,
+                        "local evaluation disagrees with the server"
+                    );
+                }
+            }
+            None => {
+                reconciliation.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Asks the server to evaluate the flag and context of `observation`.
+///
+/// Returns the server's value and variant, or `None` after logging why the
+/// server could not answer.
+async fn remote_evaluation(
+    client: &reqwest::Client,
+    base_url: &str,
+    sdk_key: &str,
+    observation: &Observation,
+) -> Option<(Value, Option<String>)> {
+    let response = post_evaluation(
+        client,
+        base_url,
+        sdk_key,
+        &observation.flag_key,
+        &observation.context,
+    )
+    .await
+    .and_then(reqwest::Response::error_for_status);
+    let response = match response {
+        Ok(response) => response,
+        Err(err) => {
+            warn!(flag_key = %observation.flag_key, error = %err, "reconciliation request failed");
+            return None;
+        }
+    };
+    let mut answer: Value = match response.json().await {
+        Ok(answer) => answer,
+        Err(err) => {
+            warn!(flag_key = %observation.flag_key, error = %err, "unreadable reconciliation answer");
+            return None;
+        }
+    };
+    let value = answer.get_mut("value").map(Value::take)?;
+    let variant = answer
+        .get("variant")
+        .and_then(Value::as_str)
+        .map(str::to_owned);
+    Some((value, variant))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Json, Router, routing::post};
+
+    use super::*;
+
+    /// Serves the OFREP evaluation of `checkout`: `true` for every user but
+    /// `bob`, who gets `false`.
+    async fn spawn_server() -> String {
+        let app = Router::new().route(
+            "/ofrep/v1/evaluate/flags/{key}",
+            post(|Json(body): Json<Value>| async move {
+                let on = body["context"]["targetingKey"] != "bob";
+                Json(serde_json::json!({
+                    "key": "checkout",
+                    "value": on,
+                    "variant": if on { "on" } else { "off" },
+                    "reason": "TARGETING_MATCH",
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn served_on(user: &str) -> Observation {
+        Observation {
+            flag_key: "checkout".to_owned(),
+            context: flaps_eval::EvaluationContext {
+                targeting_key: Some(user.to_owned()),
+                ..flaps_eval::EvaluationContext::default()
+            },
+            value: Value::Bool(true),
+            variant: Some("on".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_sampled_pair_the_server_resolves_differently_is_a_mismatch() {
+        let base_url = spawn_server().await;
+        let reconciliation = Reconciliation::new(3);
+        for user in ["alice", "bob", "carol"] {
+            reconciliation.record(|| served_on(user));
+        }
+
+        reconcile_once(&reqwest::Client::new(), &base_url, "key", &reconciliation).await;
+
+        assert_eq!(
+            reconciliation.stats(),
+            ReconciliationStats {
+                checked: 3,
+                mismatches: 1,
+                failed: 0,
+            }
+        );
+        assert!(
+            reconciliation.drain().is_empty(),
+            "a round takes the sample"
+        );
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_server_counts_failures() {
+        let reconciliation = Reconciliation::new(5);
+        reconciliation.record(|| served_on("alice"));
+        reconcile_once(
+            &reqwest::Client::new(),
+            "http://127.0.0.1:9",
+            "key",
+            &reconciliation,
+        )
+        .await;
+        assert_eq!(reconciliation.stats().failed, 1);
+        assert_eq!(reconciliation.stats().checked, 0);
+    }
+
+    #[test]
+    fn samples_are_distinct_and_bounded() {
+        let reconciliation = Reconciliation::new(5);
+        for i in 0..20 {
+            reconciliation.record(|| served_on(&format!("user-{i}")));
+        }
+        let mut users: Vec<_> = reconciliation
+            .drain()
+            .into_iter()
+            .map(|observation| observation.context.targeting_key.unwrap())
+            .collect();
+        users.sort();
+        users.dedup();
+        assert_eq!(users.len(), 5);
+    }
+
+    #[test]
+    fn concurrent_evaluations_filling_the_sample_are_all_kept() {
+        let reconciliation = Reconciliation::new(64);
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let reconciliation = &reconciliation;
+                scope.spawn(move || {
+                    for i in 0..8 {
+                        reconciliation.record(|| served_on(&format!("user-{thread}-{i}")));
+                    }
+                });
+            }
+        });
+        let mut users: Vec<_> = reconciliation
+            .drain()
+            .into_iter()
+            .map(|observation| observation.context.targeting_key.unwrap())
+            .collect();
+        users.sort();
+        users.dedup();
+        assert_eq!(users.len(), 64);
+    }
+
+    #[test]
+    fn evaluations_left_out_of_the_sample_are_never_built() {
+        let reconciliation = Reconciliation::new(1);
+        let mut built = 0;
+        for _ in 0..10_000 {
+            reconciliation.record(|| {
+                built += 1;
+                served_on("alice")
+            });
+        }
+        // The nth evaluation is built with probability 1/n: about 10 of them.
+        assert!(built < 100, "{built} observations built");
+        assert_eq!(reconciliation.drain().len(), 1);
+
+        built = 0;
+        reconciliation.record(|| {
+            built += 1;
+            served_on("bob")
+        });
+        assert_eq!(built, 1, "a new round samples its first evaluation");
+    }
+}
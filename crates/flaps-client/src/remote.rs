@@ -0,0 +1,340 @@
+//! Server evaluation with a circuit-breaking local fallback.
+//!
+//! A provider configured with [`FlapsProviderConfig::remote_evaluation`]
+//! asks the server to evaluate each flag through its OFREP endpoint
+//! (`POST /ofrep/v1/evaluate/flags/{key}`), so rules that depend on
+//! server-side data (geolocation, the server clock) resolve exactly as the
+//! server sees them. The ruleset is still synced in the background, and
+//! serves as the fallback.
+//!
+//! A circuit breaker guards the server calls. A call that fails (network
+//! error, 5xx answer, unreadable body) or takes longer than
+//! [`RemoteEvaluationConfig::slow_call_threshold`] is abandoned and the flag
+//! is evaluated locally instead. After
+//! [`RemoteEvaluationConfig::failure_threshold`] consecutive such calls the
+//! breaker opens: evaluations go straight to the local ruleset, without
+//! waiting on the server, for [`RemoteEvaluationConfig::open_duration`].
+//! The next evaluation then probes the server (half-open); a successful
+//! probe closes the breaker, a failed one opens it again. A 4xx answer, such
+//! as an unknown flag, is the server being healthy: it falls back locally
+//! without counting as a failure.
+//!
+//! [`FlapsProviderConfig::remote_evaluation`]: crate::FlapsProviderConfig::remote_evaluation
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use open_feature::FlagMetadata;
+use serde_json::{Map, Value};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::metadata_mapper;
+
+/// OFREP single-flag evaluation path, followed by the flag key.
+const EVALUATE_PATH: &str = "/ofrep/v1/evaluate/flags/";
+
+/// Settings of server evaluation and its circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteEvaluationConfig {
+    /// Number of consecutive failed or slow server calls that opens the
+    /// breaker. Defaults to 5.
+    pub failure_threshold: u32,
+    /// Time after which a server call is abandoned for a local evaluation,
+    /// and counted as a failure. Defaults to 500 ms.
+    pub slow_call_threshold: Duration,
+    /// How long an open breaker serves local evaluations before probing the
+    /// server again. Defaults to 30 s.
+    pub open_duration: Duration,
+}
+
+impl Default for RemoteEvaluationConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            slow_call_threshold: Duration::from_millis(500),
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// State of the circuit breaker guarding server evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// The server is healthy: flags are evaluated by the server.
+    Closed,
+    /// The server is degraded: flags are evaluated locally.
+    Open,
+    /// The open duration elapsed and one evaluation is probing the server;
+    /// the others are evaluated locally until it answers.
+    HalfOpen,
+}
+
+/// A server evaluation: value, variant, OFREP reason and metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RemoteResolution {
+    pub(crate) value: Value,
+    pub(crate) variant: Option<String>,
+    pub(crate) reason: Option<String>,
+    /// The flag-set and flag metadata the server reported, ruleset version
+    /// included.
+    pub(crate) metadata: Option<FlagMetadata>,
+}
+
+/// Why a server evaluation is not available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RemoteFailure {
+    /// The server is unreachable, erroring or too slow: counts against it.
+    Unavailable,
+    /// The server answered, but without a value (4xx): it is healthy.
+    Refused,
+}
+
+/// The circuit breaker of a provider.
+pub(crate) struct Breaker {
+    config: RemoteEvaluationConfig,
+    state: Mutex<BreakerInner>,
+}
+
+struct BreakerInner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    /// When the breaker last opened, or its current probe started.
+    since: Instant,
+}
+
+impl Breaker {
+    /// Creates a closed breaker.
+    pub(crate) fn new(config: RemoteEvaluationConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                since: Instant::now(),
+            }),
+        }
+    }
+
+    /// The settings the breaker was created with.
+    pub(crate) fn config(&self) -> RemoteEvaluationConfig {
+        self.config
+    }
+
+    /// Returns the current state.
+    pub(crate) fn state(&self) -> BreakerState {
+        self.lock().state
+    }
+
+    /// Returns `true` when the next evaluation should ask the server. An
+    /// open breaker whose open duration elapsed turns half-open and lets
+    /// this one call through as the probe. A probe that neither succeeded
+    /// nor failed within the slow call threshold was dropped by its caller,
+    /// and the next call probes in its place.
+    pub(crate) fn allow(&self) -> bool {
+        let mut inner = self.lock();
+        let waited = match inner.state {
+            BreakerState::Closed => return true,
+            BreakerState::HalfOpen => self.config.slow_call_threshold,
+            BreakerState::Open => self.config.open_duration,
+        };
+        if inner.since.elapsed() < waited {
+            return false;
+        }
+        inner.state = BreakerState::HalfOpen;
+        inner.since = Instant::now();
+        true
+    }
+
+    /// Records a server call that answered: closes the breaker.
+    pub(crate) fn record_success(&self) {
+        let mut inner = self.lock();
+        if inner.state != BreakerState::Closed {
+            info!("evaluate endpoint recovered; evaluating on the server again");
+        }
+        inner.state = BreakerState::Closed;
+        inner.consecutive_failures = 0;
+    }
+
+    /// Records a failed or slow server call: opens the breaker once
+    /// [`RemoteEvaluationConfig::failure_threshold`] are consecutive, or
+    /// straight away for a failed probe.
+    pub(crate) fn record_failure(&self) {
+        let mut inner = self.lock();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let trips = inner.state == BreakerState::HalfOpen
+            || inner.consecutive_failures >= self.config.failure_threshold;
+        if trips && inner.state != BreakerState::Open {
+            warn!(
+                consecutive_failures = inner.consecutive_failures,
+                open_duration = ?self.config.open_duration,
+                "evaluate endpoint degraded; evaluating locally"
+            );
+            inner.state = BreakerState::Open;
+            inner.since = Instant::now();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerInner> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Sends the OFREP evaluation request of `flag_key` for `context`.
+pub(crate) async fn post_evaluation(
+    client: &reqwest::Client,
+    base_url: &str,
+    sdk_key: &str,
+    flag_key: &str,
+    context: &flaps_eval::EvaluationContext,
+) -> reqwest::Result<reqwest::Response> {
+    let mut attributes: Map<String, Value> = context
+        .attributes
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    if let Some(targeting_key) = &context.targeting_key {
+        attributes.insert(
+            "targetingKey".to_owned(),
+            Value::String(targeting_key.clone()),
+        );
+    }
+    let body = serde_json::json!({ "context": attributes });
+
+    client
+        .post(format!("{base_url}{EVALUATE_PATH}{flag_key}"))
+        .header("Authorization", format!("Bearer {sdk_key}"))
+        .json(&body)
+        .send()
+        .await
+}
+
+/// Asks the server to evaluate `flag_key` for `context`, abandoning the
+/// call after `slow_call_threshold`.
+pub(crate) async fn evaluate_remotely(
+    client: &reqwest::Client,
+    base_url: &str,
+    sdk_key: &str,
+    flag_key: &str,
+    context: &flaps_eval::EvaluationContext,
+    slow_call_threshold: Duration,
+) -> Result<RemoteResolution, RemoteFailure> {
+    let call = async {
+        let response = post_evaluation(client, base_url, sdk_key, flag_key, context)
+            .await
+            .map_err(|err| {
+                warn!(flag_key, error = %err, "server evaluation request failed");
+                RemoteFailure::Unavailable
+            })?;
+        let status = response.status();
+        if status.is_client_error() {
+            return Err(RemoteFailure::Refused);
+        }
+        if !status.is_success() {
+            warn!(flag_key, %status, "server evaluation returned non-2xx status");
+            return Err(RemoteFailure::Unavailable);
+        }
+        let mut answer: Value = response.json().await.map_err(|err| {
+            warn!(flag_key, error = %err, "unreadable server evaluation answer");
+            RemoteFailure::Unavailable
+        })?;
+        // An answer without a value is the caller default at work: the flag
+        // is disabled or resolved no variant. The local ruleset decides.
+        let value = answer
+            .get_mut("value")
+            .map(Value::take)
+            .ok_or(RemoteFailure::Refused)?;
+        Ok(RemoteResolution {
+            value,
+            variant: answer
+                .get("variant")
+                .and_then(Value::as_str)
+                .map(str::to_owned),
+            reason: answer
+                .get("reason")
+                .and_then(Value::as_str)
+                .map(str::to_owned),
+            metadata: answer
+                .get("metadata")
+                .and_then(Value::as_object)
+                .and_then(metadata_mapper::map_ofrep_metadata),
+        })
+    };
+    tokio::time::timeout(slow_call_threshold, call)
+        .await
+        .unwrap_or_else(|_| {
+            warn!(flag_key, ?slow_call_threshold, "server evaluation too slow");
+            Err(RemoteFailure::Unavailable)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(failure_threshold: u32) -> Breaker {
+        Breaker::new(RemoteEvaluationConfig {
+            failure_threshold,
+            slow_call_threshold: Duration::from_millis(100),
+            open_duration: Duration::from_secs(10),
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn consecutive_failures_open_the_breaker_until_a_probe_succeeds() {
+        let breaker = breaker(2);
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allow(), "an open breaker evaluates locally");
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(breaker.allow(), "the first call after open_duration probes");
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(!breaker.allow(), "one probe at a time");
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_dropped_probe_is_replaced_after_the_slow_call_threshold() {
+        let breaker = breaker(1);
+        breaker.record_failure();
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(breaker.allow());
+
+        tokio::time::advance(Duration::from_millis(100)).await;
+        assert!(breaker.allow(), "the probe never reported back");
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_probe_reopens_the_breaker() {
+        let breaker = breaker(3);
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(breaker.allow());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(!breaker.allow(), "the open duration restarts");
+    }
+
+    #[test]
+    fn a_success_resets_the_failure_count() {
+        let breaker = breaker(2);
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
@@ -1,23 +1,44 @@
 //! Shared state between the provider and its background supervisor task.
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Instant, SystemTime};
 
 use arc_swap::ArcSwap;
 use flaps_eval::FlagSet;
+use tokio::sync::watch;
+use tracing::warn;
 
 use crate::status::SyncState;
 
+/// A ruleset the provider serves, with the version it was published under.
+///
+/// The two are swapped in together, so a resolution never reports the
+/// version of another ruleset than the one it was evaluated against.
+#[derive(Debug, PartialEq)]
+pub(crate) struct HeldRuleset {
+    /// The compiled ruleset.
+    pub(crate) flag_set: Arc<FlagSet>,
+    /// The ruleset version (`X-Flaps-Version`), when the source reported one.
+    pub(crate) version: Option<u64>,
+}
+
 /// State shared by [`super::provider::FlapsProvider`] and the background
 /// supervisor task via an [`Arc`].
 ///
 /// The ruleset is stored in an [`ArcSwap`] for lock-free reads on the
-/// evaluation hot path. The [`SyncState`] is protected by a [`Mutex`] and
+/// evaluation hot path: a refresh publishes a whole new [`FlagSet`] (flags
+/// and the segments compiled into them) with one atomic pointer swap, so
+/// evaluations never wait on a refresh and never see half of one. The
+/// [`SyncState`] is protected by a [`Mutex`] and
 /// updated only after successful network syncs or snapshot loads.
 pub(crate) struct ProviderShared {
     /// Current compiled ruleset; `None` until the first successful sync.
-    pub(crate) ruleset: ArcSwap<Option<Arc<FlagSet>>>,
+    pub(crate) ruleset: ArcSwap<Option<HeldRuleset>>,
     /// Metadata about the last sync (version, ETag, timestamps).
     pub(crate) sync_state: Mutex<SyncState>,
+    /// Flips to `true` once a ruleset is first available, from a successful
+    /// sync or a snapshot load. Never flips back.
+    pub(crate) initialized: watch::Sender<bool>,
 }
 
 impl ProviderShared {
@@ -26,6 +47,112 @@ impl ProviderShared {
         Self {
             ruleset: ArcSwap::new(Arc::new(None)),
             sync_state: Mutex::new(SyncState::default()),
+            initialized: watch::Sender::new(false),
+        }
+    }
+
+    /// Marks the provider as initialized, waking every waiter.
+    pub(crate) fn mark_initialized(&self) {
+        self.initialized
+            .send_if_modified(|initialized| !std::mem::replace(initialized, true));
+    }
+
+    /// Publishes a freshly synced ruleset: swaps it in, records its version
+    /// and ETag as current, resets the failure count and marks the provider
+    /// initialized.
+    pub(crate) fn publish(
+        &self,
+        flag_set: Arc<FlagSet>,
+        version: Option<u64>,
+        etag: Option<String>,
+    ) {
+        self.ruleset
+            .store(Arc::new(Some(HeldRuleset { flag_set, version })));
+        {
+            let mut state = self.lock_state();
+            state.version = version;
+            state.last_successful_sync = Some(Instant::now());
+            state.mark_updated(SystemTime::now());
+            state.etag = etag;
+            state.loaded_from_snapshot = false;
+            state.consecutive_failures = 0;
+        }
+        self.mark_initialized();
+    }
+
+    /// Counts a failed sync and logs it; the held ruleset keeps being served.
+    pub(crate) fn record_failure(&self) {
+        let mut state = self.lock_state();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        warn!(
+            consecutive_failures = state.consecutive_failures,
+            "ruleset sync failed; serving the last good ruleset"
+        );
+    }
+
+    /// Locks the sync state, recovering it from a poisoned lock.
+    fn lock_state(&self) -> MutexGuard<'_, SyncState> {
+        self.sync_state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ruleset whose flags `a` and `b` both serve `generation`, published
+    /// as version `generation`.
+    fn generation(generation: u64) -> HeldRuleset {
+        let document = format!(
+            r#"{{"flags":{{
+                "a":{{"state":"ENABLED","defaultVariant":"g","variants":{{"g":{generation}}}}},
+                "b":{{"state":"ENABLED","defaultVariant":"g","variants":{{"g":{generation}}}}}
+            }}}}"#
+        );
+        HeldRuleset {
+            flag_set: Arc::new(FlagSet::from_json(&document).expect("valid document")),
+            version: Some(generation),
+        }
+    }
+
+    /// Readers racing a stream of refreshes always evaluate both flags from
+    /// the same generation, read that generation's version, and generations
+    /// only move forward.
+    #[test]
+    fn readers_never_observe_a_partial_swap() {
+        const GENERATIONS: u64 = 2_000;
+        let shared = Arc::new(ProviderShared::new());
+        shared.ruleset.store(Arc::new(Some(generation(0))));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                std::thread::spawn(move || {
+                    let context = flaps_eval::EvaluationContext::default();
+                    let mut last = 0;
+                    while last < GENERATIONS {
+                        let guard = shared.ruleset.load();
+                        let held = guard.as_ref().as_ref().expect("ruleset loaded");
+                        let flag_set = &held.flag_set;
+                        let a = flag_set.evaluate("a", &context).expect("evaluates");
+                        let b = flag_set.evaluate("b", &context).expect("evaluates");
+                        assert_eq!(a.value, b.value, "flags from different generations");
+                        let seen = u64::try_from(a.value_or(0_i64)).expect("generation");
+                        assert_eq!(held.version, Some(seen), "version of another ruleset");
+                        assert!(seen >= last, "generation went back from {last} to {seen}");
+                        last = seen;
+                    }
+                })
+            })
+            .collect();
+
+        for next in 1..=GENERATIONS {
+            shared.ruleset.store(Arc::new(Some(generation(next))));
+        }
+        for reader in readers {
+            reader.join().expect("reader panicked");
         }
     }
 }
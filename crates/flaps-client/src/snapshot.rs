@@ -1,8 +1,16 @@
 //! Atomic disk snapshot for warm-start resilience.
 //!
-//! Writes `{ "version": <u64 | null>, "document": "<flagd json>" }` to
-//! `<path>.tmp` then renames it to `<path>` (atomic within the same file
-//! system). Errors are logged as warnings and never propagate to the caller.
+//! Writes a binary bootstrap file to `<path>.tmp` then renames it to `<path>`
+//! (atomic within the same file system). Errors are logged as warnings and
+//! never propagate to the caller.
+//!
+//! The file is [`BOOTSTRAP_MAGIC`], the ruleset version (a presence byte and
+//! a little-endian `u64`), then the flag set encoded by
+//! [`FlagSet::to_snapshot`]. Files written by earlier releases, the JSON
+//! object `{ "version": <u64 | null>, "document": "<flagd json>" }`, are still
+//! read. A snapshot in a format version this build cannot read is ignored:
+//! the provider stays `NotReady` until the first network sync, which fetches
+//! a fresh ruleset.
 //!
 //! The resolve hot-path always reads from the in-memory [`ArcSwap`]; the
 //! snapshot is only used at provider startup for a warm-start when the server
@@ -11,38 +19,60 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tracing::warn;
 
 use flaps_eval::FlagSet;
 
-use crate::shared::ProviderShared;
+use crate::shared::{HeldRuleset, ProviderShared};
+
+/// Leading bytes identifying a binary bootstrap file.
+const BOOTSTRAP_MAGIC: &[u8; 4] = b"FLPS";
+
+/// Length of the header preceding the flag set snapshot.
+const HEADER_LEN: usize = BOOTSTRAP_MAGIC.len() + 1 + 8;
 
-/// On-disk representation of a ruleset snapshot.
-#[derive(Debug, Serialize, Deserialize)]
-struct SnapshotFile {
+/// Legacy JSON representation of a ruleset snapshot.
+#[derive(Debug, Deserialize)]
+struct JsonSnapshotFile {
     /// Ruleset version, if known.
     version: Option<u64>,
     /// Raw flagd JSON document.
     document: String,
 }
 
-/// Writes `version` and `document` to `path` atomically (tmp + rename).
-///
-/// Errors are logged as warnings; this function never panics.
-pub(crate) async fn write_snapshot(path: &Path, version: Option<u64>, document: &str) {
-    let snapshot = SnapshotFile {
-        version,
-        document: document.to_owned(),
-    };
+/// Encodes `version` and `flag_set` as a binary bootstrap file.
+fn encode(version: Option<u64>, flag_set: &FlagSet) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(BOOTSTRAP_MAGIC);
+    bytes.push(u8::from(version.is_some()));
+    bytes.extend_from_slice(&version.unwrap_or_default().to_le_bytes());
+    bytes.extend_from_slice(&flag_set.to_snapshot());
+    bytes
+}
 
-    let json = match serde_json::to_string(&snapshot) {
-        Ok(j) => j,
-        Err(err) => {
-            warn!(error = %err, "failed to serialize ruleset snapshot");
-            return;
-        }
+/// Decodes a bootstrap file, binary or legacy JSON.
+fn decode(bytes: &[u8]) -> Result<(Option<u64>, FlagSet), String> {
+    let Some(rest) = bytes.strip_prefix(BOOTSTRAP_MAGIC) else {
+        let legacy: JsonSnapshotFile =
+            serde_json::from_slice(bytes).map_err(|err| err.to_string())?;
+        let flag_set = FlagSet::from_json(&legacy.document).map_err(|err| err.to_string())?;
+        return Ok((legacy.version, flag_set));
     };
+    let (&has_version, rest) = rest.split_first().ok_or("truncated snapshot header")?;
+    let (version, payload) = rest
+        .split_first_chunk::<8>()
+        .ok_or("truncated snapshot header")?;
+    let version = (has_version != 0).then(|| u64::from_le_bytes(*version));
+    let flag_set = FlagSet::from_snapshot(payload).map_err(|err| err.to_string())?;
+    Ok((version, flag_set))
+}
+
+/// Writes `version` and `flag_set` to `path` atomically (tmp + rename).
+///
+/// Errors are logged as warnings; this function never panics.
+pub(crate) async fn write_snapshot(path: &Path, version: Option<u64>, flag_set: &FlagSet) {
+    let bytes = encode(version, flag_set);
 
     // Derive a sibling `.tmp` path.
     let tmp_path = {
@@ -59,7 +89,7 @@ pub(crate) async fn write_snapshot(path: &Path, version: Option<u64>, document:
         p
     };
 
-    if let Err(err) = tokio::fs::write(&tmp_path, json.as_bytes()).await {
+    if let Err(err) = tokio::fs::write(&tmp_path, &bytes).await {
         warn!(error = %err, path = %tmp_path.display(), "failed to write snapshot tmp file");
         return;
     }
@@ -73,8 +103,8 @@ pub(crate) async fn write_snapshot(path: &Path, version: Option<u64>, document:
 
 /// Loads a snapshot from `path` into `shared`.
 ///
-/// On success the ruleset [`arc_swap::ArcSwap`] is populated and `loaded_from_snapshot`
-/// is set to `true`. Errors are logged as warnings; the provider falls back to
+/// On success the ruleset [`arc_swap::ArcSwap`] is populated, `loaded_from_snapshot`
+/// is set to `true` and `last_updated` is set to the write time of the file. Errors are logged as warnings; the provider falls back to
 /// `None` ruleset (`NotReady`) until the first successful network sync.
 pub(crate) async fn load_snapshot(path: &Path, shared: &Arc<ProviderShared>) {
     let bytes = match tokio::fs::read(path).await {
@@ -85,32 +115,40 @@ pub(crate) async fn load_snapshot(path: &Path, shared: &Arc<ProviderShared>) {
         }
     };
 
-    let snapshot: SnapshotFile = match serde_json::from_slice(&bytes) {
-        Ok(s) => s,
+    let (version, flag_set) = match decode(&bytes) {
+        Ok(decoded) => decoded,
         Err(err) => {
             warn!(error = %err, "failed to parse snapshot file");
             return;
         }
     };
 
-    let flag_set = match FlagSet::from_json(&snapshot.document) {
-        Ok(fs) => fs,
-        Err(err) => {
-            warn!(error = %err, "snapshot document failed to parse as flagd ruleset");
-            return;
-        }
-    };
+    shared.ruleset.store(Arc::new(Some(HeldRuleset {
+        flag_set: Arc::new(flag_set),
+        version,
+    })));
 
-    shared.ruleset.store(Arc::new(Some(Arc::new(flag_set))));
+    // The snapshot is rewritten after every update, so its write time tells
+    // how old the warm-start ruleset is.
+    let written = tokio::fs::metadata(path)
+        .await
+        .and_then(|metadata| metadata.modified())
+        .ok();
 
     let mut state = shared
         .sync_state
         .lock()
         .unwrap_or_else(std::sync::PoisonError::into_inner);
-    state.version = snapshot.version;
+    state.version = version;
     state.loaded_from_snapshot = true;
+    if let Some(written) = written {
+        state.mark_updated(written);
+    }
     // `last_successful_sync` remains `None`: the snapshot is a warm-start hint,
     // not evidence of a successful network sync this session.
+    drop(state);
+    // A warm-started provider can evaluate, so it counts as initialized.
+    shared.mark_initialized();
 }
 
 #[cfg(test)]
@@ -124,19 +162,29 @@ mod tests {
         std::env::temp_dir().join(format!("flaps_snapshot_test_{name}_{id:?}"))
     }
 
+    /// A minimal valid flagd document.
+    const DOCUMENT: &str = r#"{"flags":{"my-flag":{"state":"ENABLED","defaultVariant":"on","variants":{"on":true,"off":false}}}}"#;
+
+    fn flag_set() -> FlagSet {
+        FlagSet::from_json(DOCUMENT).expect("valid document")
+    }
+
     #[tokio::test]
     async fn round_trip_write_then_read() {
         let path = tmp_path("round_trip");
 
-        let document = r#"{"flags":{}}"#;
-        write_snapshot(&path, Some(42), document).await;
+        write_snapshot(&path, Some(42), &flag_set()).await;
 
         assert!(path.exists(), "snapshot file must exist after write");
 
         let bytes = std::fs::read(&path).expect("read snapshot");
-        let parsed: SnapshotFile = serde_json::from_slice(&bytes).expect("parse snapshot");
-        assert_eq!(parsed.version, Some(42));
-        assert_eq!(parsed.document, document);
+        assert!(
+            bytes.starts_with(BOOTSTRAP_MAGIC),
+            "snapshot must be binary"
+        );
+        let (version, parsed) = decode(&bytes).expect("parse snapshot");
+        assert_eq!(version, Some(42));
+        assert_eq!(parsed, flag_set());
 
         let _ = std::fs::remove_file(&path);
     }
@@ -145,12 +193,11 @@ mod tests {
     async fn round_trip_none_version() {
         let path = tmp_path("none_version");
 
-        let document = r#"{"flags":{}}"#;
-        write_snapshot(&path, None, document).await;
+        write_snapshot(&path, None, &flag_set()).await;
 
         let bytes = std::fs::read(&path).expect("read snapshot");
-        let parsed: SnapshotFile = serde_json::from_slice(&bytes).expect("parse snapshot");
-        assert_eq!(parsed.version, None);
+        let (version, _) = decode(&bytes).expect("parse snapshot");
+        assert_eq!(version, None);
 
         let _ = std::fs::remove_file(&path);
     }
@@ -159,9 +206,7 @@ mod tests {
     async fn load_populates_shared_state() {
         let path = tmp_path("load_populated");
 
-        // Write a minimal valid flagd document.
-        let document = r#"{"flags":{"my-flag":{"state":"ENABLED","defaultVariant":"on","variants":{"on":true,"off":false}}}}"#;
-        write_snapshot(&path, Some(7), document).await;
+        write_snapshot(&path, Some(7), &flag_set()).await;
 
         let shared = Arc::new(ProviderShared::new());
         load_snapshot(&path, &shared).await;
@@ -170,6 +215,11 @@ mod tests {
         assert_eq!(state.version, Some(7));
         assert!(state.loaded_from_snapshot);
         assert!(state.last_successful_sync.is_none());
+        let written = std::fs::metadata(&path)
+            .expect("stat")
+            .modified()
+            .expect("mtime");
+        assert_eq!(state.last_updated, Some(written));
         drop(state);
 
         let guard = shared.ruleset.load();
@@ -178,6 +228,36 @@ mod tests {
         let _ = std::fs::remove_file(&path);
     }
 
+    #[tokio::test]
+    async fn load_accepts_legacy_json_snapshot() {
+        let path = tmp_path("legacy_json");
+        let legacy = serde_json::json!({ "version": 3, "document": DOCUMENT });
+        std::fs::write(&path, legacy.to_string()).expect("write");
+
+        let shared = Arc::new(ProviderShared::new());
+        load_snapshot(&path, &shared).await;
+
+        assert_eq!(shared.sync_state.lock().expect("lock").version, Some(3));
+        assert!(shared.ruleset.load().is_some());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn load_unsupported_format_version_leaves_shared_unchanged() {
+        let path = tmp_path("unsupported_version");
+        let mut bytes = encode(Some(1), &flag_set());
+        bytes[HEADER_LEN] = flaps_eval::SNAPSHOT_FORMAT_VERSION + 1;
+        std::fs::write(&path, bytes).expect("write");
+
+        let shared = Arc::new(ProviderShared::new());
+        load_snapshot(&path, &shared).await;
+
+        assert!(shared.ruleset.load().is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[tokio::test]
     async fn load_missing_file_leaves_shared_unchanged() {
         let path = tmp_path("missing_file_xyz_nonexistent_abc");
@@ -0,0 +1,184 @@
+//! Pluggable ruleset sources.
+//!
+//! By default a [`FlapsProvider`] syncs its ruleset from a Flaps server: an
+//! HTTP fetch of `GET /sync/v1/ruleset`, refetched on SSE notifications and
+//! on a polling fallback. An application embedding Flaps can instead supply
+//! a [`FlagSource`] of its own (a watched file, an embedded repository, a
+//! message bus) through [`FlapsProvider::with_source`].
+//!
+//! A custom source is driven like the built-in one: fetched once when the
+//! provider is initialized, then again every
+//! [`FlapsProviderConfig::poll_interval`], and in between whenever its
+//! [`FlagSource::subscribe`] stream pushes a snapshot. Evaluation, staleness
+//! and degradation reporting and disk snapshots work the same whichever
+//! source feeds the ruleset.
+//!
+//! [`FlapsProvider`]: crate::FlapsProvider
+//! [`FlapsProvider::with_source`]: crate::FlapsProvider::with_source
+//! [`FlapsProviderConfig::poll_interval`]: crate::FlapsProviderConfig::poll_interval
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use flaps_eval::{FlagSet, ParseError};
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use open_feature::async_trait;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::shared::ProviderShared;
+use crate::supervisor::poll_interval;
+
+/// Error returned by a [`FlagSource`] that cannot produce a ruleset.
+pub type SourceError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A ruleset produced by a [`FlagSource`].
+#[derive(Debug, Clone)]
+pub struct ConfigSnapshot {
+    /// The compiled flags.
+    pub flag_set: Arc<FlagSet>,
+    /// Version of the ruleset, reported by
+    /// [`SyncStatus::version`](crate::SyncStatus::version). `None` when the
+    /// source does not version its rulesets.
+    pub version: Option<u64>,
+}
+
+impl ConfigSnapshot {
+    /// Creates an unversioned snapshot of `flag_set`.
+    #[must_use]
+    pub fn new(flag_set: FlagSet) -> Self {
+        Self {
+            flag_set: Arc::new(flag_set),
+            version: None,
+        }
+    }
+
+    /// Parses a flagd JSON document, as served by `GET /sync/v1/ruleset`,
+    /// into an unversioned snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`ParseError`] of [`FlagSet::from_json`].
+    pub fn from_json(document: &str) -> Result<Self, ParseError> {
+        FlagSet::from_json(document).map(Self::new)
+    }
+
+    /// Sets the version of the snapshot.
+    #[must_use]
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = Some(version);
+        self
+    }
+}
+
+/// Where a provider reads its ruleset from, in place of a Flaps server.
+///
+/// Implement [`Self::fetch`] to read the current ruleset. Sources that learn
+/// of changes as they happen also implement [`Self::subscribe`]; the others
+/// are only refetched on the polling interval.
+#[async_trait]
+pub trait FlagSource: Send + Sync + 'static {
+    /// Returns the current ruleset.
+    ///
+    /// # Errors
+    ///
+    /// A failed fetch is counted towards
+    /// [`FlapsProviderConfig::degraded_after_failures`] and never replaces
+    /// the held ruleset: evaluations keep serving the last good one.
+    ///
+    /// [`FlapsProviderConfig::degraded_after_failures`]: crate::FlapsProviderConfig::degraded_after_failures
+    async fn fetch(&self) -> Result<ConfigSnapshot, SourceError>;
+
+    /// Returns a stream of rulesets pushed as they change, or `None`, the
+    /// default, for a source that is only polled.
+    ///
+    /// Called once, when the provider is initialized. Polling carries on
+    /// after the stream ends.
+    fn subscribe(&self) -> Option<BoxStream<'static, ConfigSnapshot>> {
+        None
+    }
+}
+
+/// Spawns the task keeping the ruleset of `shared` in sync with `source`,
+/// and returns its [`JoinHandle`].
+pub(crate) fn spawn_source_sync(
+    source: Arc<dyn FlagSource>,
+    poll_period: Duration,
+    snapshot_path: Option<PathBuf>,
+    shared: Arc<ProviderShared>,
+) -> JoinHandle<()> {
+    tokio::spawn(run_source_sync(source, poll_period, snapshot_path, shared))
+}
+
+/// Fetches from `source` on start and on every poll tick, and publishes
+/// every snapshot its subscription pushes in between, until aborted.
+async fn run_source_sync(
+    source: Arc<dyn FlagSource>,
+    poll_period: Duration,
+    snapshot_path: Option<PathBuf>,
+    shared: Arc<ProviderShared>,
+) {
+    let snapshot_path = snapshot_path.as_deref();
+    let mut updates = source.subscribe();
+    fetch_and_publish(&*source, &shared, snapshot_path).await;
+
+    let mut poll_tick = poll_interval(poll_period);
+    // The first tick fires immediately; the initial fetch above covers it.
+    poll_tick.tick().await;
+
+    loop {
+        tokio::select! {
+            update = next_update(&mut updates) => {
+                if let Some(snapshot) = update {
+                    publish(&shared, snapshot, snapshot_path).await;
+                } else {
+                    warn!("flag source subscription ended; polling only");
+                    updates = None;
+                }
+            }
+            _ = poll_tick.tick() => {
+                fetch_and_publish(&*source, &shared, snapshot_path).await;
+            }
+        }
+    }
+}
+
+/// Waits for the next pushed snapshot; never resolves without a
+/// subscription.
+async fn next_update(
+    updates: &mut Option<BoxStream<'static, ConfigSnapshot>>,
+) -> Option<ConfigSnapshot> {
+    match updates {
+        Some(stream) => stream.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Fetches from `source` and publishes the result, or counts the failure.
+async fn fetch_and_publish(
+    source: &dyn FlagSource,
+    shared: &ProviderShared,
+    snapshot_path: Option<&std::path::Path>,
+) {
+    match source.fetch().await {
+        Ok(snapshot) => publish(shared, snapshot, snapshot_path).await,
+        Err(error) => {
+            warn!(%error, "flag source fetch failed");
+            shared.record_failure();
+        }
+    }
+}
+
+/// Publishes `snapshot` and writes the disk snapshot when configured.
+async fn publish(
+    shared: &ProviderShared,
+    snapshot: ConfigSnapshot,
+    snapshot_path: Option<&std::path::Path>,
+) {
+    shared.publish(Arc::clone(&snapshot.flag_set), snapshot.version, None);
+    if let Some(path) = snapshot_path {
+        crate::snapshot::write_snapshot(path, snapshot.version, &snapshot.flag_set).await;
+    }
+}
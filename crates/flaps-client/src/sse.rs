@@ -1,15 +1,62 @@
 //! Incremental SSE (Server-Sent Events) decoder for the Flaps sync stream.
 //!
 //! Parses `text/event-stream` frames from a byte buffer without any external
-//! crate dependency. Only the `data:` field is processed; `event:`, `id:` and
-//! `retry:` lines are silently ignored. Comment lines (starting with `:`) are
-//! also ignored (keep-alive).
+//! crate dependency. Only the `data:` and `id:` fields are processed; `event:`
+//! and `retry:` lines are silently ignored. Comment lines (starting with `:`)
+//! are also ignored (keep-alive).
 //!
 //! The decoder is intentionally stateful: partial frames across chunk boundaries
 //! are accumulated until a blank line terminates the event.
 
+use std::fmt;
+
 use serde::Deserialize;
 
+/// A ruleset cache key, `{version}-{content_hash}`: the event id of a sync
+/// notification and the value quoted in the ruleset `ETag`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RulesetKey {
+    /// Monotone version of the ruleset.
+    pub(crate) version: u64,
+    /// Hex digest of the ruleset document.
+    pub(crate) content_hash: String,
+}
+
+impl RulesetKey {
+    /// Parses a `{version}-{content_hash}` key; `None` when malformed.
+    pub(crate) fn parse(key: &str) -> Option<Self> {
+        let (version, content_hash) = key.trim().split_once('-')?;
+        if content_hash.is_empty() {
+            return None;
+        }
+        Some(Self {
+            version: version.parse().ok()?,
+            content_hash: content_hash.to_owned(),
+        })
+    }
+
+    /// Reads the key out of a ruleset `ETag`, of either representation
+    /// (`"{key}"` or `"{key}-snapshot"`).
+    pub(crate) fn from_etag(etag: &str) -> Option<Self> {
+        let key = etag.trim().trim_matches('"');
+        Self::parse(key.strip_suffix("-snapshot").unwrap_or(key))
+    }
+
+    /// Whether a ruleset announced under this key replaces one held under
+    /// `held`: a higher version does, and so does the same version with
+    /// other content. The hash only breaks ties; it is never ordered.
+    pub(crate) fn supersedes(&self, held: &Self) -> bool {
+        self.version > held.version
+            || (self.version == held.version && self.content_hash != held.content_hash)
+    }
+}
+
+impl fmt::Display for RulesetKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.version, self.content_hash)
+    }
+}
+
 /// A parsed SSE notification from `GET /sync/v1/events`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct SseNotification {
@@ -17,6 +64,20 @@ pub(crate) struct SseNotification {
     pub(crate) environment: String,
     /// Monotone version of the new ruleset.
     pub(crate) version: u64,
+    /// Cache key of the new ruleset, from the event id, when it carried one.
+    pub(crate) key: Option<RulesetKey>,
+}
+
+impl SseNotification {
+    /// Whether this notification announces a ruleset that replaces the one
+    /// held under `held`. Without an event id, only the version is compared.
+    pub(crate) fn supersedes(&self, held: Option<&RulesetKey>) -> bool {
+        match (held, &self.key) {
+            (None, _) => true,
+            (Some(held), Some(key)) => key.supersedes(held),
+            (Some(held), None) => self.version > held.version,
+        }
+    }
 }
 
 /// Serde helper for the SSE `data:` JSON payload.
@@ -36,6 +97,9 @@ pub(crate) struct SseDecoder {
     line_buf: String,
     /// `data:` value accumulated for the current event.
     data_buf: Option<String>,
+    /// Last `id:` value received; per the SSE spec it carries over to the
+    /// following events until replaced.
+    last_id: Option<String>,
 }
 
 impl SseDecoder {
@@ -96,6 +160,7 @@ impl SseDecoder {
                     notifications.push(SseNotification {
                         environment: payload.environment,
                         version: payload.version,
+                        key: self.last_id.as_deref().and_then(RulesetKey::parse),
                     });
                 }
                 // Invalid JSON -> silently skip this event.
@@ -104,8 +169,11 @@ impl SseDecoder {
             // RFC 8895: optional leading space after the colon.
             let value = value.strip_prefix(' ').unwrap_or(value);
             self.data_buf = Some(value.to_owned());
+        } else if let Some(value) = line.strip_prefix("id:") {
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            self.last_id = Some(value.to_owned());
         }
-        // Lines starting with `:` (keep-alive), `event:`, `id:`, `retry:` -> ignore.
+        // Lines starting with `:` (keep-alive), `event:`, `retry:` -> ignore.
     }
 
     /// Signals the end of the byte stream (no trailing newline).
@@ -221,4 +289,72 @@ mod tests {
         let notifs = decode(b"");
         assert!(notifs.is_empty());
     }
+
+    #[test]
+    fn event_id_is_parsed_as_ruleset_key() {
+        let frame = b"id: 7-abc\ndata:{\"environment\":\"prod\",\"version\":7}\n\n";
+        let notifs = decode(frame);
+        assert_eq!(
+            notifs[0].key,
+            Some(RulesetKey {
+                version: 7,
+                content_hash: "abc".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn event_id_carries_over_to_later_events() {
+        let frame = b"id: 7-abc\ndata:{\"environment\":\"prod\",\"version\":7}\n\n\
+                      data:{\"environment\":\"prod\",\"version\":7}\n\n";
+        let notifs = decode(frame);
+        assert_eq!(notifs.len(), 2);
+        assert_eq!(notifs[1].key, notifs[0].key);
+    }
+
+    #[test]
+    fn event_without_id_has_no_key() {
+        let notifs = decode(b"data:{\"environment\":\"prod\",\"version\":7}\n\n");
+        assert_eq!(notifs[0].key, None);
+    }
+
+    #[test]
+    fn ruleset_key_reads_both_etag_forms() {
+        let key = RulesetKey::parse("3-ff00").unwrap();
+        assert_eq!(RulesetKey::from_etag("\"3-ff00\""), Some(key.clone()));
+        assert_eq!(
+            RulesetKey::from_etag("\"3-ff00-snapshot\""),
+            Some(key.clone())
+        );
+        assert_eq!(key.to_string(), "3-ff00");
+        assert_eq!(RulesetKey::parse("3"), None);
+        assert_eq!(RulesetKey::parse("x-ff00"), None);
+    }
+
+    #[test]
+    fn higher_version_supersedes_regardless_of_hash() {
+        let held = RulesetKey::parse("3-ff00").unwrap();
+        assert!(RulesetKey::parse("4-0000").unwrap().supersedes(&held));
+        assert!(!RulesetKey::parse("2-ffff").unwrap().supersedes(&held));
+    }
+
+    #[test]
+    fn hash_only_breaks_a_version_tie() {
+        let held = RulesetKey::parse("3-ff00").unwrap();
+        assert!(RulesetKey::parse("3-0000").unwrap().supersedes(&held));
+        assert!(!RulesetKey::parse("3-ff00").unwrap().supersedes(&held));
+    }
+
+    #[test]
+    fn notification_without_id_compares_versions() {
+        let held = RulesetKey::parse("3-ff00").unwrap();
+        let notification = |version| SseNotification {
+            environment: "prod".to_owned(),
+            version,
+            key: None,
+        };
+        assert!(notification(4).supersedes(Some(&held)));
+        assert!(!notification(3).supersedes(Some(&held)));
+        assert!(notification(1).supersedes(None));
+    }
 }
@@ -1,8 +1,8 @@
 //! Provider freshness metrics exposed via [`crate::provider::FlapsProvider::sync_status`].
 
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
-/// Internal mutable state updated after each successful sync.
+/// Internal mutable state updated after each sync attempt.
 #[derive(Debug, Default)]
 pub(crate) struct SyncState {
     /// Version tag received from the server (`X-Flaps-Version` header).
@@ -15,6 +15,44 @@ pub(crate) struct SyncState {
     /// `true` when the ruleset was loaded from a disk snapshot and has not yet
     /// been confirmed by a successful network sync this session.
     pub(crate) loaded_from_snapshot: bool,
+    /// Number of ruleset fetches that failed since the last successful one.
+    pub(crate) consecutive_failures: u32,
+    /// Wall-clock time the held ruleset was last known current: the last
+    /// successful sync, or the write time of the snapshot it was loaded from.
+    pub(crate) last_updated: Option<SystemTime>,
+    /// `true` once the `max_staleness` warning was logged for the held
+    /// ruleset, so it is logged once per update rather than per evaluation.
+    pub(crate) max_staleness_warned: bool,
+}
+
+impl SyncState {
+    /// Records that the held ruleset was current at `at`.
+    pub(crate) fn mark_updated(&mut self, at: SystemTime) {
+        self.last_updated = Some(at);
+        self.max_staleness_warned = false;
+    }
+
+    /// Age of the held ruleset at `now`; zero when `last_updated` is ahead of
+    /// the clock.
+    pub(crate) fn age(&self, now: SystemTime) -> Option<Duration> {
+        self.last_updated
+            .map(|at| now.duration_since(at).unwrap_or(Duration::ZERO))
+    }
+
+    /// Returns the age of the held ruleset when it exceeds `max_staleness`
+    /// and no warning was logged for it yet, and records the warning.
+    pub(crate) fn staleness_warning(
+        &mut self,
+        max_staleness: Duration,
+        now: SystemTime,
+    ) -> Option<Duration> {
+        let age = self.age(now).filter(|age| *age > max_staleness)?;
+        if self.max_staleness_warned {
+            return None;
+        }
+        self.max_staleness_warned = true;
+        Some(age)
+    }
 }
 
 /// Snapshot of provider freshness metrics.
@@ -30,18 +68,78 @@ pub struct SyncStatus {
     pub last_successful_sync: Option<Instant>,
     /// Age of the currently loaded ruleset, computed at call time.
     pub ruleset_age: Option<Duration>,
+    /// Wall-clock time the loaded ruleset was last known current: the last
+    /// successful sync, or the write time of the disk snapshot it was
+    /// warm-started from. See [`crate::provider::FlapsProvider::snapshot_age`].
+    pub last_updated: Option<SystemTime>,
+    /// Number of ruleset fetches that failed since the last successful one.
+    ///
+    /// A failed fetch never replaces the held ruleset: evaluations keep
+    /// serving the last good one.
+    pub consecutive_failures: u32,
+    /// `true` once [`Self::consecutive_failures`] reaches
+    /// [`FlapsProviderConfig::degraded_after_failures`]. Cleared by the next
+    /// successful fetch.
+    ///
+    /// [`FlapsProviderConfig::degraded_after_failures`]: crate::FlapsProviderConfig::degraded_after_failures
+    pub degraded: bool,
 }
 
 impl SyncStatus {
-    /// Creates a [`SyncStatus`] from the current [`SyncState`].
+    /// Creates a [`SyncStatus`] from the current [`SyncState`], degraded
+    /// after `degraded_after_failures` consecutive failures (never when
+    /// `None`).
     #[must_use]
-    pub(crate) fn from_state(state: &SyncState) -> Self {
+    pub(crate) fn from_state(state: &SyncState, degraded_after_failures: Option<u32>) -> Self {
         let last_successful_sync = state.last_successful_sync;
         let ruleset_age = last_successful_sync.map(|t| t.elapsed());
         Self {
             version: state.version,
             last_successful_sync,
             ruleset_age,
+            last_updated: state.last_updated,
+            consecutive_failures: state.consecutive_failures,
+            degraded: degraded_after_failures
+                .is_some_and(|threshold| state.consecutive_failures >= threshold),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staleness_warning_fires_once_past_the_threshold_until_the_next_update() {
+        let updated = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let max_staleness = Duration::from_secs(60);
+        let mut state = SyncState::default();
+        assert_eq!(state.staleness_warning(max_staleness, updated), None);
+
+        state.mark_updated(updated);
+        let within = updated + Duration::from_secs(60);
+        assert_eq!(state.staleness_warning(max_staleness, within), None);
+
+        let past = updated + Duration::from_secs(61);
+        assert_eq!(
+            state.staleness_warning(max_staleness, past),
+            Some(Duration::from_secs(61))
+        );
+        assert_eq!(state.staleness_warning(max_staleness, past), None);
+
+        state.mark_updated(past);
+        let later = past + Duration::from_secs(120);
+        assert_eq!(
+            state.staleness_warning(max_staleness, later),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn age_is_zero_when_the_update_is_ahead_of_the_clock() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut state = SyncState::default();
+        state.mark_updated(now + Duration::from_secs(5));
+        assert_eq!(state.age(now), Some(Duration::ZERO));
+    }
+}
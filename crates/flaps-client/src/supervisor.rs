@@ -1,9 +1,19 @@
 //! Background supervisor task: SSE connection + polling fallback.
 //!
 //! The task holds an `Arc<ProviderShared>` and keeps the ruleset fresh by:
-//! 1. Opening `GET /sync/v1/events` (SSE stream).
-//! 2. Fetching the ruleset immediately on (re)connect.
-//! 3. Fetching again on each SSE notification.
+//! 1. Opening `GET /sync/v1/events` (SSE stream), with `Last-Event-ID` set
+//!    to the cache key (`{version}-{content_hash}`) of the held ruleset,
+//!    once a network sync confirmed it.
+//! 2. Fetching the ruleset immediately on (re)connect when there is no such
+//!    key. Otherwise the server opens the stream with a catch-up
+//!    notification only when the held key is no longer current, so an
+//!    up-to-date client reconnects without refetching. The first batch of a
+//!    resumed stream is always fetched (conditionally on the `ETag`): the
+//!    server only sends it when the keys differ, including after a restart
+//!    reset its versions below the one held.
+//! 3. Fetching again on each SSE notification of a ruleset that supersedes
+//!    the one held: a higher version, or the same version with other
+//!    content. The content hash only breaks version ties.
 //! 4. Falling back to a periodic poll every `poll_interval`, running
 //!    unconditionally rather than only while SSE is connected. A client that
 //!    cannot hold an SSE subscription (for instance because the server's
@@ -27,7 +37,7 @@ use tracing::warn;
 use crate::backoff::Backoff;
 use crate::provider::FlapsProviderConfig;
 use crate::shared::ProviderShared;
-use crate::sse::SseDecoder;
+use crate::sse::{RulesetKey, SseDecoder};
 use crate::sync::fetch_and_store;
 
 /// SSE endpoint path.
@@ -48,20 +58,12 @@ async fn run_supervisor(
     config: FlapsProviderConfig,
     shared: Arc<ProviderShared>,
 ) {
-    let snapshot_path = config.snapshot_path.as_deref();
     let mut backoff = Backoff::new(config.backoff_base, config.backoff_max);
 
     // Initial fetch before opening SSE: ensures the ruleset is available even
     // when the SSE endpoint is unavailable. This also runs after each backoff
     // reconnect attempt so the ruleset stays fresh regardless of SSE health.
-    fetch_and_store(
-        &client,
-        &config.base_url,
-        &config.sdk_key,
-        &shared,
-        snapshot_path,
-    )
-    .await;
+    refresh(&client, &config, &shared).await;
 
     // The poll interval is created once, outside the connect/reconnect loop,
     // and ticked unconditionally: whether SSE is connected, disconnected, or
@@ -75,20 +77,26 @@ async fn run_supervisor(
 
     loop {
         // Attempt to open the SSE stream.
-        match open_event_stream(&client, &config.base_url, &config.sdk_key).await {
+        let resume_from = resumable_key(&shared);
+        match open_event_stream(
+            &client,
+            &config.base_url,
+            &config.sdk_key,
+            resume_from.as_ref(),
+        )
+        .await
+        {
             Ok(mut stream) => {
                 backoff.reset();
 
-                // Fetch again immediately after (re)connecting to catch any events
-                // that arrived during the disconnected window.
-                fetch_and_store(
-                    &client,
-                    &config.base_url,
-                    &config.sdk_key,
-                    &shared,
-                    snapshot_path,
-                )
-                .await;
+                // Without a key to resume from, fetch immediately after
+                // (re)connecting to catch any events that arrived during the
+                // disconnected window. With one, the server's catch-up
+                // notification does.
+                let mut catching_up = resume_from.is_some();
+                if !catching_up {
+                    refresh(&client, &config, &shared).await;
+                }
 
                 let mut decoder = SseDecoder::new();
 
@@ -98,16 +106,15 @@ async fn run_supervisor(
                             match chunk {
                                 Some(Ok(bytes)) => {
                                     let notifs = decoder.push(&bytes);
-                                    if !notifs.is_empty() {
+                                    if notifs.is_empty() {
+                                        continue;
+                                    }
+                                    let held = held_key(&shared);
+                                    if std::mem::take(&mut catching_up)
+                                        || notifs.iter().any(|n| n.supersedes(held.as_ref()))
+                                    {
                                         // One fetch per batch of notifications.
-                                        fetch_and_store(
-                                            &client,
-                                            &config.base_url,
-                                            &config.sdk_key,
-                                            &shared,
-                                            snapshot_path,
-                                        )
-                                        .await;
+                                        refresh(&client, &config, &shared).await;
                                     }
                                 }
                                 Some(Err(err)) => {
@@ -121,14 +128,7 @@ async fn run_supervisor(
                             }
                         }
                         _ = poll_tick.tick() => {
-                            fetch_and_store(
-                                &client,
-                                &config.base_url,
-                                &config.sdk_key,
-                                &shared,
-                                snapshot_path,
-                            )
-                            .await;
+                            refresh(&client, &config, &shared).await;
                         }
                     }
                 }
@@ -152,21 +152,15 @@ async fn run_supervisor(
             tokio::select! {
                 () = &mut sleep => break,
                 _ = poll_tick.tick() => {
-                    fetch_and_store(
-                        &client,
-                        &config.base_url,
-                        &config.sdk_key,
-                        &shared,
-                        snapshot_path,
-                    )
-                    .await;
+                    refresh(&client, &config, &shared).await;
                 }
             }
         }
     }
 }
 
-/// Builds the polling-fallback interval used by [`run_supervisor`].
+/// Builds the polling-fallback interval used by [`run_supervisor`] and by
+/// custom [`FlagSource`](crate::FlagSource) syncs.
 ///
 /// Sets [`MissedTickBehavior::Delay`], overriding `tokio::time::interval`'s
 /// default of `Burst`. This interval is a periodic refresh fallback that
@@ -178,25 +172,66 @@ async fn run_supervisor(
 /// default (`poll_interval` 300s vs `request_timeout` 10s), but reachable for
 /// an operator who configures a poll interval shorter than the request
 /// timeout.
-fn poll_interval(period: Duration) -> Interval {
+pub(crate) fn poll_interval(period: Duration) -> Interval {
     let mut poll_tick = interval(period);
     poll_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
     poll_tick
 }
 
-/// Opens `GET /sync/v1/events` and returns the raw byte stream.
+/// Fetches the ruleset into `shared`, conditionally on the held `ETag`.
+async fn refresh(
+    client: &reqwest::Client,
+    config: &FlapsProviderConfig,
+    shared: &Arc<ProviderShared>,
+) {
+    fetch_and_store(
+        client,
+        &config.base_url,
+        &config.sdk_key,
+        shared,
+        config.snapshot_path.as_deref(),
+    )
+    .await;
+}
+
+/// Returns the cache key of the held ruleset, read from its `ETag`, if any.
+fn held_key(shared: &ProviderShared) -> Option<RulesetKey> {
+    let state = shared
+        .sync_state
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    state.etag.as_deref().and_then(RulesetKey::from_etag)
+}
+
+/// Returns the key the event stream can resume from: the held key, once a
+/// network sync confirmed it. A ruleset warm-started from a disk snapshot is
+/// always fetched on connect, which confirms it.
+fn resumable_key(shared: &ProviderShared) -> Option<RulesetKey> {
+    let confirmed = !shared
+        .sync_state
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .loaded_from_snapshot;
+    held_key(shared).filter(|_| confirmed)
+}
+
+/// Opens `GET /sync/v1/events`, resuming from `last_event_id` when set, and
+/// returns the raw byte stream.
 async fn open_event_stream(
     client: &reqwest::Client,
     base_url: &str,
     sdk_key: &str,
+    last_event_id: Option<&RulesetKey>,
 ) -> Result<impl futures_util::Stream<Item = Result<Bytes, reqwest::Error>>, reqwest::Error> {
     let url = format!("{base_url}{EVENTS_PATH}");
-    let response = client
+    let mut request = client
         .get(&url)
         .header("Authorization", format!("Bearer {sdk_key}"))
-        .header("Accept", "text/event-stream")
-        .send()
-        .await?;
+        .header("Accept", "text/event-stream");
+    if let Some(key) = last_event_id {
+        request = request.header("Last-Event-ID", key.to_string());
+    }
+    let response = request.send().await?;
 
     let response = response.error_for_status()?;
 
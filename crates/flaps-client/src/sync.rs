@@ -1,10 +1,14 @@
 //! HTTP sync logic: fetches the compiled ruleset from the Flaps server.
+//!
+//! The ruleset is requested as a binary snapshot first, with JSON as the
+//! fallback representation. A snapshot written in a format version this build
+//! cannot read triggers one immediate re-fetch as JSON.
 
 use std::sync::Arc;
 
 use tracing::warn;
 
-use flaps_eval::FlagSet;
+use flaps_eval::{FlagSet, SNAPSHOT_MEDIA_TYPE, SnapshotError};
 
 use crate::shared::ProviderShared;
 
@@ -14,13 +18,37 @@ const RULESET_PATH: &str = "/sync/v1/ruleset";
 /// Header carrying the ruleset version.
 const VERSION_HEADER: &str = "X-Flaps-Version";
 
+/// `Accept` value preferring the binary snapshot, with JSON as fallback.
+const ACCEPT_SNAPSHOT: &str = "application/x-flaps-snapshot, application/json;q=0.5";
+
+/// `Accept` value requesting the JSON representation only.
+const ACCEPT_JSON: &str = "application/json";
+
+/// Outcome of a single ruleset request.
+enum Fetched {
+    /// 304: the held ruleset is current.
+    NotModified,
+    /// 200 with a decoded ruleset.
+    Updated {
+        flag_set: FlagSet,
+        version: Option<u64>,
+        etag: Option<String>,
+    },
+    /// 200 carrying a snapshot in a format version this build cannot read.
+    UnsupportedSnapshot,
+    /// Any other failure, already logged.
+    Failed,
+}
+
 /// Fetches the ruleset from `base_url` using `sdk_key` as Bearer token.
 ///
 /// Sends `If-None-Match` with the stored ETag when available. On 304 the
-/// ruleset is unchanged but `last_successful_sync` is refreshed. On 200 the
-/// ruleset, version, and ETag are stored. On any other non-2xx, network, or
-/// parse error the function logs a warning and leaves `shared` unchanged, so
-/// callers continue to serve the last-known-good ruleset.
+/// ruleset is unchanged but `last_successful_sync` and `last_updated` are
+/// refreshed. On 200 the ruleset, version, and ETag are stored. Both reset
+/// the consecutive failure count. On any other non-2xx, network, or parse error the function logs a
+/// warning, counts the failure and leaves the ruleset unchanged, so callers
+/// continue to serve the last-known-good ruleset: it is only replaced once a
+/// response has been fully received and parsed.
 ///
 /// Returns `true` when a 200 or 304 was received (i.e. the server is reachable
 /// and the key is valid), `false` on error.
@@ -42,33 +70,80 @@ pub(crate) async fn fetch_and_store(
         state.etag.clone()
     };
 
+    let mut fetched =
+        request_ruleset(client, &url, sdk_key, etag.as_deref(), ACCEPT_SNAPSHOT).await;
+    if matches!(fetched, Fetched::UnsupportedSnapshot) {
+        // No `If-None-Match`: the held ETag names the other representation
+        // and could never match the JSON one.
+        fetched = request_ruleset(client, &url, sdk_key, None, ACCEPT_JSON).await;
+    }
+
+    let (flag_set, version, new_etag) = match fetched {
+        // 304 Not Modified: ruleset unchanged, but refresh the sync timestamp.
+        Fetched::NotModified => {
+            let mut state = shared
+                .sync_state
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            state.last_successful_sync = Some(std::time::Instant::now());
+            state.mark_updated(std::time::SystemTime::now());
+            state.loaded_from_snapshot = false;
+            state.consecutive_failures = 0;
+            drop(state);
+            shared.mark_initialized();
+            return true;
+        }
+        Fetched::Updated {
+            flag_set,
+            version,
+            etag,
+        } => (Arc::new(flag_set), version, etag),
+        Fetched::UnsupportedSnapshot | Fetched::Failed => {
+            shared.record_failure();
+            return false;
+        }
+    };
+
+    shared.publish(Arc::clone(&flag_set), version, new_etag);
+
+    // Write snapshot if configured.
+    if let Some(path) = snapshot_path {
+        crate::snapshot::write_snapshot(path, version, &flag_set).await;
+    }
+
+    true
+}
+
+/// Issues one ruleset request with the given `Accept` value and decodes the
+/// body according to the `Content-Type` the server chose.
+async fn request_ruleset(
+    client: &reqwest::Client,
+    url: &str,
+    sdk_key: &str,
+    etag: Option<&str>,
+    accept: &str,
+) -> Fetched {
     let mut request = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {sdk_key}"));
+        .get(url)
+        .header("Authorization", format!("Bearer {sdk_key}"))
+        .header(reqwest::header::ACCEPT, accept);
 
-    if let Some(ref tag) = etag {
-        request = request.header("If-None-Match", tag.as_str());
+    if let Some(tag) = etag {
+        request = request.header("If-None-Match", tag);
     }
 
     let response = match request.send().await {
         Ok(r) => r,
         Err(err) => {
             warn!(error = %err, "ruleset sync request failed");
-            return false;
+            return Fetched::Failed;
         }
     };
 
     let status = response.status();
 
-    // 304 Not Modified: ruleset unchanged, but refresh the sync timestamp.
     if status == reqwest::StatusCode::NOT_MODIFIED {
-        let mut state = shared
-            .sync_state
-            .lock()
-            .unwrap_or_else(std::sync::PoisonError::into_inner);
-        state.last_successful_sync = Some(std::time::Instant::now());
-        state.loaded_from_snapshot = false;
-        return true;
+        return Fetched::NotModified;
     }
 
     // Extract version before consuming the response.
@@ -79,7 +154,7 @@ pub(crate) async fn fetch_and_store(
         .and_then(|s| s.parse::<u64>().ok());
 
     // Extract ETag before consuming the response.
-    let new_etag = response
+    let etag = response
         .headers()
         .get(reqwest::header::ETAG)
         .and_then(|v| v.to_str().ok())
@@ -87,42 +162,160 @@ pub(crate) async fn fetch_and_store(
 
     if !status.is_success() {
         warn!(%status, "ruleset sync returned non-2xx status");
-        return false;
+        return Fetched::Failed;
     }
 
-    let body = match response.text().await {
+    let is_snapshot = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with(SNAPSHOT_MEDIA_TYPE));
+
+    let body = match response.bytes().await {
         Ok(b) => b,
         Err(err) => {
             warn!(error = %err, "failed to read ruleset response body");
-            return false;
+            return Fetched::Failed;
         }
     };
 
-    let flag_set = match FlagSet::from_json(&body) {
-        Ok(fs) => fs,
+    let parsed = if is_snapshot {
+        match FlagSet::from_snapshot(&body) {
+            Err(SnapshotError::UnsupportedVersion { found, expected }) => {
+                warn!(found, expected, "unsupported snapshot format, using JSON");
+                return Fetched::UnsupportedSnapshot;
+            }
+            other => other.map_err(|err| err.to_string()),
+        }
+    } else {
+        std::str::from_utf8(&body)
+            .map_err(|err| err.to_string())
+            .and_then(|text| FlagSet::from_json(text).map_err(|err| err.to_string()))
+    };
+
+    match parsed {
+        Ok(flag_set) => Fetched::Updated {
+            flag_set,
+            version,
+            etag,
+        },
         Err(err) => {
             warn!(error = %err, "failed to parse ruleset document");
-            return false;
+            Fetched::Failed
         }
-    };
+    }
+}
 
-    shared.ruleset.store(Arc::new(Some(Arc::new(flag_set))));
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU8, Ordering};
 
-    {
-        let mut state = shared
-            .sync_state
-            .lock()
-            .unwrap_or_else(std::sync::PoisonError::into_inner);
-        state.version = version;
-        state.last_successful_sync = Some(std::time::Instant::now());
-        state.etag = new_etag;
-        state.loaded_from_snapshot = false;
+    use axum::{Router, http::StatusCode, response::IntoResponse, routing::get};
+
+    use super::*;
+    use crate::status::SyncStatus;
+
+    /// Server mode: serve the ruleset.
+    const HEALTHY: u8 = 0;
+    /// Server mode: answer 500.
+    const DOWN: u8 = 1;
+    /// Server mode: answer 200 with a body that is not a ruleset.
+    const GARBLED: u8 = 2;
+
+    const DOCUMENT: &str = r#"{"flags":{"my-flag":{"state":"ENABLED","defaultVariant":"on","variants":{"on":true,"off":false}}}}"#;
+
+    /// Serves the ruleset endpoint in the mode held by the returned switch.
+    async fn spawn_server() -> (String, Arc<AtomicU8>) {
+        let mode = Arc::new(AtomicU8::new(HEALTHY));
+        let current = Arc::clone(&mode);
+        let app = Router::new().route(
+            RULESET_PATH,
+            get(move || {
+                let mode = current.load(Ordering::SeqCst);
+                async move {
+                    match mode {
+                        HEALTHY => {
+                            ([("content-type", "application/json")], DOCUMENT).into_response()
+                        }
+                        DOWN => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+                        _ => ([("content-type", "application/json")], "{not json").into_response(),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}"), mode)
     }
 
-    // Write snapshot if configured.
-    if let Some(path) = snapshot_path {
-        crate::snapshot::write_snapshot(path, version, &body).await;
+    async fn fetch(base_url: &str, shared: &Arc<ProviderShared>) -> bool {
+        fetch_and_store(&reqwest::Client::new(), base_url, "key", shared, None).await
     }
 
-    true
+    fn status(shared: &ProviderShared) -> SyncStatus {
+        SyncStatus::from_state(&shared.sync_state.lock().unwrap(), Some(3))
+    }
+
+    #[tokio::test]
+    async fn failed_fetches_keep_the_last_good_ruleset() {
+        let (base_url, mode) = spawn_server().await;
+        let shared = Arc::new(ProviderShared::new());
+        assert!(fetch(&base_url, &shared).await);
+        let good = shared.ruleset.load_full();
+
+        for failure in [DOWN, GARBLED] {
+            mode.store(failure, Ordering::SeqCst);
+            assert!(!fetch(&base_url, &shared).await);
+            assert_eq!(*shared.ruleset.load_full(), *good, "mode {failure}");
+        }
+        assert!(
+            good.as_ref()
+                .as_ref()
+                .unwrap()
+                .flag_set
+                .flags
+                .contains_key("my-flag")
+        );
+    }
+
+    #[tokio::test]
+    async fn degraded_after_consecutive_failures_until_a_success() {
+        let (base_url, mode) = spawn_server().await;
+        let shared = Arc::new(ProviderShared::new());
+        assert!(fetch(&base_url, &shared).await);
+
+        mode.store(DOWN, Ordering::SeqCst);
+        for expected in 1..=3 {
+            fetch(&base_url, &shared).await;
+            let status = status(&shared);
+            assert_eq!(status.consecutive_failures, expected);
+            assert_eq!(status.degraded, expected == 3);
+        }
+
+        mode.store(HEALTHY, Ordering::SeqCst);
+        assert!(fetch(&base_url, &shared).await);
+        let status = status(&shared);
+        assert_eq!(status.consecutive_failures, 0);
+        assert!(!status.degraded);
+    }
+
+    #[tokio::test]
+    async fn last_updated_is_refreshed_by_successful_fetches_only() {
+        let (base_url, mode) = spawn_server().await;
+        let shared = Arc::new(ProviderShared::new());
+        assert!(status(&shared).last_updated.is_none());
+
+        assert!(fetch(&base_url, &shared).await);
+        let first = status(&shared).last_updated.expect("set by the fetch");
+
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        mode.store(DOWN, Ordering::SeqCst);
+        assert!(!fetch(&base_url, &shared).await);
+        assert_eq!(status(&shared).last_updated, Some(first));
+
+        mode.store(HEALTHY, Ordering::SeqCst);
+        assert!(fetch(&base_url, &shared).await);
+        assert!(status(&shared).last_updated.expect("refreshed") > first);
+    }
 }
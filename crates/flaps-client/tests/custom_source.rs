@@ -0,0 +1,130 @@
+//! Integration tests for [`FlapsProvider::with_source`].
+//!
+//! Tests cover:
+//! - The first fetch of a custom source initializes the provider.
+//! - A ruleset pushed through the source's subscription replaces the held one.
+//! - A failing source leaves the provider not ready and counts the failures.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use open_feature::EvaluationContext;
+use open_feature::async_trait;
+use open_feature::provider::{FeatureProvider, ProviderStatus};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use flaps_client::{ConfigSnapshot, FlagSource, FlapsProvider, FlapsProviderConfig, SourceError};
+
+/// A ruleset whose `greeting` flag serves `greeting`, at `version`.
+fn snapshot(greeting: &str, version: u64) -> ConfigSnapshot {
+    let document = format!(
+        r#"{{"flags":{{"greeting":{{
+            "state":"ENABLED",
+            "defaultVariant":"current",
+            "variants":{{"current":"{greeting}"}}
+        }}}}}}"#
+    );
+    ConfigSnapshot::from_json(&document)
+        .expect("valid document")
+        .with_version(version)
+}
+
+/// An in-memory source: serves a fixed ruleset and pushes whatever is sent
+/// on the sender returned by [`InMemorySource::new`].
+struct InMemorySource {
+    current: ConfigSnapshot,
+    updates: Mutex<Option<mpsc::UnboundedReceiver<ConfigSnapshot>>>,
+}
+
+impl InMemorySource {
+    fn new(current: ConfigSnapshot) -> (Self, mpsc::UnboundedSender<ConfigSnapshot>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let source = Self {
+            current,
+            updates: Mutex::new(Some(receiver)),
+        };
+        (source, sender)
+    }
+}
+
+#[async_trait]
+impl FlagSource for InMemorySource {
+    async fn fetch(&self) -> Result<ConfigSnapshot, SourceError> {
+        Ok(self.current.clone())
+    }
+
+    fn subscribe(&self) -> Option<BoxStream<'static, ConfigSnapshot>> {
+        let receiver = self.updates.lock().unwrap().take()?;
+        let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|snapshot| (snapshot, receiver))
+        });
+        Some(stream.boxed())
+    }
+}
+
+/// A source whose every fetch fails.
+struct FailingSource;
+
+#[async_trait]
+impl FlagSource for FailingSource {
+    async fn fetch(&self) -> Result<ConfigSnapshot, SourceError> {
+        Err("source unavailable".into())
+    }
+}
+
+/// A provider config whose server is never contacted.
+fn config() -> FlapsProviderConfig {
+    FlapsProviderConfig::new("http://unused.invalid", "unused")
+}
+
+async fn greeting(provider: &FlapsProvider) -> String {
+    provider
+        .resolve_string_value("greeting", &EvaluationContext::default())
+        .await
+        .expect("greeting resolves")
+        .value
+}
+
+#[tokio::test]
+async fn pushed_ruleset_replaces_the_fetched_one() {
+    let (source, updates) = InMemorySource::new(snapshot("hello", 1));
+    let mut provider = FlapsProvider::with_source(config(), source);
+    provider.initialize(&EvaluationContext::default()).await;
+
+    assert!(
+        provider
+            .wait_for_initialization(Duration::from_secs(2))
+            .await,
+        "the first fetch initializes the provider"
+    );
+    assert_eq!(greeting(&provider).await, "hello");
+    assert_eq!(provider.sync_status().version, Some(1));
+
+    updates.send(snapshot("bonjour", 2)).unwrap();
+    timeout(Duration::from_secs(2), async {
+        while greeting(&provider).await != "bonjour" {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("the pushed ruleset is applied");
+    assert_eq!(provider.sync_status().version, Some(2));
+    assert!(matches!(provider.status(), ProviderStatus::Ready));
+}
+
+#[tokio::test]
+async fn failing_source_leaves_the_provider_not_ready() {
+    let mut provider = FlapsProvider::with_source(config(), FailingSource);
+    provider.initialize(&EvaluationContext::default()).await;
+
+    assert!(
+        !provider
+            .wait_for_initialization(Duration::from_millis(100))
+            .await
+    );
+    assert!(matches!(provider.status(), ProviderStatus::NotReady));
+    assert!(provider.sync_status().consecutive_failures >= 1);
+}
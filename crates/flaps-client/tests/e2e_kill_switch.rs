@@ -6,13 +6,16 @@
 //!   -> `SyncEvent` (broadcast) -> `SSE` `GET /sync/v1/events` -> refetch `GET /sync/v1/ruleset`
 //!   -> `ArcSwap<FlagSet>` -> `resolve_bool_value` bascule.
 
+mod shared;
+
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
 
-use flaps_client::{FlapsProvider, FlapsProviderConfig};
+use flaps_client::FlapsProvider;
 use flaps_domain::{
     Environment, EnvironmentKey, Flag, FlagEnvConfig, FlagKey, FlagType, ManagedBy, Project,
-    ProjectKey, SdkKeyKind, ServeTarget, ValueType, VariantKey, VariantValue, Variants,
+    ProjectKey, RolloutScope, SdkKeyKind, ServeTarget, ValueType, VariantKey, VariantValue,
+    Variants,
 };
 use flaps_server::state::AppState;
 use flaps_store::hash::KeyHasher;
@@ -129,6 +132,7 @@ async fn seed_active_boolean_flag(
                 external_ref: None,
                 managed_by: ManagedBy::Local,
                 metadata: flaps_domain::Metadata::new(),
+                inherits_from: None,
             },
         )
         .await
@@ -170,6 +174,12 @@ async fn seed_active_boolean_flag(
                 enabled: true,
                 rules: vec![],
                 default_rule: ServeTarget::Fixed(on.clone()),
+                inherit_default_rule: false,
+                salt: None,
+                bucket_by: None,
+                rollout_scope: RolloutScope::Shared,
+                kill_reason: None,
+                min_app_version: None,
             },
         )
         .await
@@ -198,17 +208,7 @@ async fn seed_active_boolean_flag(
 /// propagation can only come from the SSE push path, never from the polling
 /// fallback.
 async fn start_synced_provider(addr: SocketAddr, sdk_key: &str) -> FlapsProvider {
-    let config = FlapsProviderConfig {
-        base_url: format!("http://{addr}"),
-        sdk_key: sdk_key.to_owned(),
-        connect_timeout: Duration::from_secs(2),
-        request_timeout: Duration::from_secs(5),
-        snapshot_path: None,
-        staleness_threshold: None,
-        poll_interval: Duration::from_secs(3600),
-        backoff_base: Duration::from_millis(10),
-        backoff_max: Duration::from_millis(50),
-    };
+    let config = shared::fast_config(format!("http://{addr}"), sdk_key);
     let mut provider = FlapsProvider::new(config);
     let ctx = EvaluationContext::default();
     timeout(Duration::from_secs(10), provider.initialize(&ctx))
@@ -9,14 +9,16 @@
 //! the exact same entries, with the same types, as the OFREP HTTP response
 //! (remote path) for the same flag.
 
+mod shared;
+
 use std::net::SocketAddr;
 use std::time::Duration;
 
-use flaps_client::{FlapsProvider, FlapsProviderConfig};
+use flaps_client::FlapsProvider;
 use flaps_domain::{
     Environment, EnvironmentKey, Flag, FlagEnvConfig, FlagKey, FlagType, ManagedBy,
     Metadata as DomainMetadata, MetadataValue as DomainMetadataValue, Project, ProjectKey,
-    SdkKeyKind, ServeTarget, ValueType, VariantKey, VariantValue, Variants,
+    RolloutScope, SdkKeyKind, ServeTarget, ValueType, VariantKey, VariantValue, Variants,
 };
 use flaps_server::state::AppState;
 use flaps_store::hash::KeyHasher;
@@ -43,6 +45,7 @@ const SDK_SECRET: &str = "sv_0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f";
 /// metadata, one boolean flag whose metadata overrides one environment-level
 /// entry and adds entries of every supported scalar type, and one server SDK
 /// key scoped to that project and environment.
+#[allow(clippy::too_many_lines)]
 async fn seed_flag_with_metadata(store: &SqliteStore) {
     let project_key = ProjectKey::new(PROJECT).expect("valid project key");
     let env_key = EnvironmentKey::new(ENVIRONMENT).expect("valid environment key");
@@ -83,6 +86,7 @@ async fn seed_flag_with_metadata(store: &SqliteStore) {
                 external_ref: None,
                 managed_by: ManagedBy::Local,
                 metadata: environment_metadata,
+                inherits_from: None,
             },
         )
         .await
@@ -133,6 +137,12 @@ async fn seed_flag_with_metadata(store: &SqliteStore) {
                 enabled: true,
                 rules: vec![],
                 default_rule: ServeTarget::Fixed(vk_on.clone()),
+                inherit_default_rule: false,
+                salt: None,
+                bucket_by: None,
+                rollout_scope: RolloutScope::Shared,
+                kill_reason: None,
+                min_app_version: None,
             },
         )
         .await
@@ -286,17 +296,7 @@ async fn wait_until_ready(provider: &FlapsProvider, budget: Duration) {
 async fn local_resolution_metadata_matches_ofrep() {
     let addr = spawn_server_with_metadata().await;
 
-    let config = FlapsProviderConfig {
-        base_url: format!("http://{addr}"),
-        sdk_key: SDK_SECRET.to_owned(),
-        connect_timeout: Duration::from_secs(2),
-        request_timeout: Duration::from_secs(5),
-        snapshot_path: None,
-        staleness_threshold: None,
-        poll_interval: Duration::from_secs(3600),
-        backoff_base: Duration::from_millis(10),
-        backoff_max: Duration::from_millis(50),
-    };
+    let config = shared::fast_config(format!("http://{addr}"), SDK_SECRET);
     let mut provider = FlapsProvider::new(config);
     let ctx = EvaluationContext::default();
     timeout(Duration::from_secs(10), provider.initialize(&ctx))
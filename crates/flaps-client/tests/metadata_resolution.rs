@@ -9,7 +9,12 @@
 //! - flag-set-level metadata still reaches a flag with no metadata of its
 //!   own,
 //! - integer-compatible and floating-point numeric metadata keep their type,
-//! - empty metadata (no flag-set and no flag entries) maps to `None`.
+//! - every resolution carries the ruleset version under
+//!   `VERSION_METADATA_KEY`, the only entry when the ruleset has no metadata,
+//! - a resolution served past `staleness_threshold` carries
+//!   `STALE_METADATA_KEY`, a fresh one does not.
+
+mod shared;
 
 use std::net::SocketAddr;
 use std::time::Duration;
@@ -22,7 +27,7 @@ use open_feature::{EvaluationContext, FlagMetadataValue};
 use tokio::net::TcpListener;
 use tokio::time::timeout;
 
-use flaps_client::{FlapsProvider, FlapsProviderConfig};
+use flaps_client::{FlapsProvider, FlapsProviderConfig, STALE_METADATA_KEY, VERSION_METADATA_KEY};
 
 // ---------------------------------------------------------------------------
 // Fixtures
@@ -131,17 +136,7 @@ fn document_response(document: &'static str) -> Response {
 /// Builds a minimal config pointed at `addr` with fast timeouts and no
 /// background interference.
 fn fast_config(addr: SocketAddr) -> FlapsProviderConfig {
-    FlapsProviderConfig {
-        base_url: format!("http://{addr}"),
-        sdk_key: "test-key".to_owned(),
-        connect_timeout: Duration::from_secs(2),
-        request_timeout: Duration::from_secs(5),
-        snapshot_path: None,
-        staleness_threshold: None,
-        poll_interval: Duration::from_secs(3600),
-        backoff_base: Duration::from_millis(10),
-        backoff_max: Duration::from_millis(50),
-    }
+    shared::fast_config(format!("http://{addr}"), "test-key")
 }
 
 /// Builds a provider, calls `initialize`, and returns it once the first sync
@@ -152,7 +147,12 @@ fn fast_config(addr: SocketAddr) -> FlapsProviderConfig {
 /// complete, and a fixed sleep is flaky under CPU contention (e.g. other
 /// crates compiling concurrently in the same `cargo test` run).
 async fn synced_provider(addr: SocketAddr) -> FlapsProvider {
-    let mut provider = FlapsProvider::new(fast_config(addr));
+    synced_provider_with(fast_config(addr)).await
+}
+
+/// Same as [`synced_provider`] with an explicit `config`.
+async fn synced_provider_with(config: FlapsProviderConfig) -> FlapsProvider {
+    let mut provider = FlapsProvider::new(config);
     let ctx = EvaluationContext::default();
     timeout(Duration::from_secs(10), provider.initialize(&ctx))
         .await
@@ -335,11 +335,11 @@ async fn flag_set_level_metadata_reaches_flag_without_own_metadata() {
 }
 
 // ---------------------------------------------------------------------------
-// AC4: empty metadata maps to the absent OpenFeature representation
+// AC4: without ruleset metadata, only the ruleset version is reported
 // ---------------------------------------------------------------------------
 
 #[tokio::test]
-async fn empty_metadata_maps_to_none() {
+async fn empty_metadata_carries_only_the_ruleset_version() {
     let addr = spawn_document_server(NO_METADATA_DOCUMENT).await;
     let provider = synced_provider(addr).await;
     let ctx = EvaluationContext::default();
@@ -348,8 +348,80 @@ async fn empty_metadata_maps_to_none() {
         .resolve_bool_value("plain-flag", &ctx)
         .await
         .expect("plain-flag must resolve");
+    let metadata = result
+        .flag_metadata
+        .expect("the ruleset version is always reported");
+    assert_eq!(
+        metadata.values,
+        std::collections::HashMap::from([(
+            VERSION_METADATA_KEY.to_owned(),
+            FlagMetadataValue::Int(1)
+        )])
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Stale serving is flagged in metadata, the value is unchanged
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn fresh_resolution_is_not_marked_stale() {
+    let addr = spawn_document_server(NO_METADATA_DOCUMENT).await;
+    let provider = synced_provider_with(FlapsProviderConfig {
+        staleness_threshold: Some(Duration::from_secs(60)),
+        ..fast_config(addr)
+    })
+    .await;
+    let ctx = EvaluationContext::default();
+
+    let result = provider
+        .resolve_bool_value("plain-flag", &ctx)
+        .await
+        .expect("plain-flag must resolve");
+    assert!(result.value);
+    let metadata = result.flag_metadata.expect("version metadata");
     assert!(
-        result.flag_metadata.is_none(),
-        "metadata absent everywhere must map to None, not Some(empty)"
+        !metadata.values.contains_key(STALE_METADATA_KEY),
+        "a fresh resolution must not carry `{STALE_METADATA_KEY}`"
+    );
+}
+
+#[tokio::test]
+async fn resolution_past_the_staleness_threshold_is_marked_stale() {
+    // The mock server has no SSE route and the poll interval is an hour, so
+    // nothing refreshes the ruleset after the first sync.
+    let addr = spawn_document_server(METADATA_DOCUMENT).await;
+    let provider = synced_provider_with(FlapsProviderConfig {
+        staleness_threshold: Some(Duration::from_millis(200)),
+        ..fast_config(addr)
+    })
+    .await;
+    let ctx = EvaluationContext::default();
+
+    let start = std::time::Instant::now();
+    while provider.status() != ProviderStatus::STALE {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "provider did not become STALE within 5s"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let result = provider
+        .resolve_bool_value("bool-flag", &ctx)
+        .await
+        .expect("a stale ruleset must keep resolving");
+    assert!(result.value, "stale serving must not change the value");
+    let metadata = result
+        .flag_metadata
+        .expect("stale metadata must be present");
+    assert_eq!(
+        metadata.values.get(STALE_METADATA_KEY),
+        Some(&FlagMetadataValue::Bool(true))
+    );
+    // Ruleset metadata is still merged alongside the stale marker.
+    assert_eq!(
+        metadata.values.get("owner"),
+        Some(&FlagMetadataValue::String("team-flags".to_owned()))
     );
 }
@@ -6,6 +6,13 @@
 //! - AC3: warm-start from disk snapshot when server is unreachable (Lot B).
 //! - AC4: second fetch with If-None-Match -> 304 -> ruleset unchanged, sync ts refreshed (Lot B).
 //! - AC5: SSE decoder tested on fixed buffers (unit tests in sse.rs cover this).
+//! - Initialization signal: `wait_for_initialization` and `initialization_timeout`.
+//! - Binary snapshot negotiation, with JSON fallback on an unknown format version.
+//! - A caller-supplied HTTP client is used for ruleset fetches.
+//! - Evaluation is local only: no fetch, even for a flag the ruleset lacks.
+//! - A boolean flag registered as a closed gate resolves to `false` on error.
+
+mod shared;
 
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -25,7 +32,7 @@ use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tokio::time::timeout;
 
-use flaps_client::{FlapsProvider, FlapsProviderConfig};
+use flaps_client::{Fallback, Fallbacks, FlapsProvider, FlapsProviderConfig};
 
 // ---------------------------------------------------------------------------
 // Shared flagd document
@@ -194,18 +201,7 @@ async fn etag_handler(State(state): State<MockState>, req_headers: HeaderMap) ->
 
 /// Builds a minimal config pointed at `addr` with fast timeouts.
 fn fast_config(addr: SocketAddr) -> FlapsProviderConfig {
-    FlapsProviderConfig {
-        base_url: format!("http://{addr}"),
-        sdk_key: "test-key".to_owned(),
-        connect_timeout: Duration::from_secs(2),
-        request_timeout: Duration::from_secs(5),
-        snapshot_path: None,
-        staleness_threshold: None,
-        // Use a short poll interval so the background task does not interfere.
-        poll_interval: Duration::from_secs(3600),
-        backoff_base: Duration::from_millis(10),
-        backoff_max: Duration::from_millis(50),
-    }
+    shared::fast_config(format!("http://{addr}"), "test-key")
 }
 
 /// Builds a provider, calls `initialize`, and returns it.
@@ -233,6 +229,25 @@ async fn no_ruleset_bool_returns_err_without_panic() {
     assert!(result.is_err(), "expected Err when ruleset is absent");
 }
 
+#[tokio::test]
+async fn closed_gate_resolves_to_false_where_other_flags_err() {
+    let config = FlapsProviderConfig {
+        fallbacks: Fallbacks::new().with_flag("new-checkout", Fallback::Closed),
+        ..FlapsProviderConfig::new("http://127.0.0.1:1", "bad-key")
+    };
+    let provider = FlapsProvider::new(config);
+    let ctx = EvaluationContext::default();
+
+    let gate = provider
+        .resolve_bool_value("new-checkout", &ctx)
+        .await
+        .expect("a closed gate resolves");
+    assert!(!gate.value);
+    assert_eq!(gate.reason, Some(open_feature::EvaluationReason::Error));
+    // Unregistered: the error surfaces and the SDK serves the caller default.
+    assert!(provider.resolve_bool_value("any-flag", &ctx).await.is_err());
+}
+
 #[tokio::test]
 async fn no_ruleset_string_returns_err_without_panic() {
     let config = FlapsProviderConfig::new("http://127.0.0.1:1", "bad-key");
@@ -630,6 +645,267 @@ async fn ac4_second_fetch_304_refreshes_sync_timestamp() {
     assert_eq!(status_after.version, Some(1));
 }
 
+// ---------------------------------------------------------------------------
+// Initialization signal
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn wait_for_initialization_resolves_after_first_sync() {
+    let addr = spawn_mock_server().await;
+    let mut provider = FlapsProvider::new(fast_config(addr));
+    assert!(!*provider.initialized().borrow());
+
+    provider.initialize(&EvaluationContext::default()).await;
+    assert!(
+        provider
+            .wait_for_initialization(Duration::from_secs(5))
+            .await,
+        "the first sync must mark the provider initialized"
+    );
+    assert!(*provider.initialized().borrow());
+}
+
+#[tokio::test]
+async fn wait_for_initialization_times_out_when_server_never_responds() {
+    // Bound but never accepted: connections queue in the backlog and no
+    // response ever arrives.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut provider = FlapsProvider::new(fast_config(addr));
+    provider.initialize(&EvaluationContext::default()).await;
+    assert!(
+        !provider
+            .wait_for_initialization(Duration::from_millis(300))
+            .await
+    );
+    drop(listener);
+}
+
+#[tokio::test]
+async fn initialization_timeout_makes_initialize_wait_for_the_first_sync() {
+    let addr = spawn_mock_server().await;
+    let mut config = fast_config(addr);
+    config.initialization_timeout = Some(Duration::from_secs(5));
+
+    let mut provider = FlapsProvider::new(config);
+    provider.initialize(&EvaluationContext::default()).await;
+
+    // No sleep: `initialize` returned only once the ruleset was loaded.
+    let result = provider
+        .resolve_bool_value("bool-flag", &EvaluationContext::default())
+        .await
+        .expect("ruleset must be loaded when initialize returns");
+    assert!(result.value);
+}
+
+#[tokio::test]
+async fn snapshot_warm_start_is_initialized_immediately() {
+    let path = tmp_snapshot_path("init_warm_start");
+    write_raw_snapshot(&path, Some(1), FLAGD_DOCUMENT);
+
+    let mut config = FlapsProviderConfig::new("http://127.0.0.1:1", "bad-key");
+    config.snapshot_path = Some(path.clone());
+    config.initialization_timeout = Some(Duration::from_secs(5));
+    config.backoff_base = Duration::from_millis(10);
+    config.backoff_max = Duration::from_millis(50);
+
+    let mut provider = FlapsProvider::new(config);
+    timeout(
+        Duration::from_secs(1),
+        provider.initialize(&EvaluationContext::default()),
+    )
+    .await
+    .expect("a warm-started provider must not wait for the server");
+    assert!(*provider.initialized().borrow());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+// ---------------------------------------------------------------------------
+// Local-only evaluation
+// ---------------------------------------------------------------------------
+
+/// Spawns a mock server serving `FLAGD_DOCUMENT` and counting the ruleset
+/// fetches it receives.
+async fn spawn_counting_server() -> (SocketAddr, Arc<AtomicU32>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let fetches = Arc::new(AtomicU32::new(0));
+
+    let counter = Arc::clone(&fetches);
+    let app = Router::new().route(
+        "/sync/v1/ruleset",
+        get(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            ruleset_handler()
+        }),
+    );
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    (addr, fetches)
+}
+
+#[tokio::test]
+async fn evaluation_never_fetches_even_for_unknown_flags() {
+    let (addr, fetches) = spawn_counting_server().await;
+    let mut provider = FlapsProvider::new(fast_config(addr));
+    provider.initialize(&EvaluationContext::default()).await;
+    assert!(
+        provider
+            .wait_for_initialization(Duration::from_secs(5))
+            .await,
+        "the bootstrap must succeed"
+    );
+    let bootstrap_fetches = fetches.load(Ordering::SeqCst);
+
+    let ctx = EvaluationContext::default();
+    for _ in 0..50 {
+        assert!(provider.resolve_bool_value("bool-flag", &ctx).await.is_ok());
+        let missing = provider
+            .resolve_bool_value("not-in-the-ruleset", &ctx)
+            .await
+            .expect_err("an unknown flag must fail closed");
+        assert_eq!(
+            missing.code,
+            open_feature::EvaluationErrorCode::FlagNotFound
+        );
+    }
+
+    // The events route is absent, so the supervisor only retries SSE; the
+    // hourly poll is not due. Any new fetch would come from evaluation.
+    assert_eq!(fetches.load(Ordering::SeqCst), bootstrap_fetches);
+}
+
+// ---------------------------------------------------------------------------
+// Binary snapshot negotiation
+// ---------------------------------------------------------------------------
+
+/// Serves the binary snapshot to clients accepting it, JSON otherwise.
+///
+/// With `format_version` set to an unknown value, the snapshot body is
+/// unreadable, which must push the provider onto the JSON fallback.
+async fn spawn_snapshot_server(format_version: u8) -> (SocketAddr, Arc<AtomicU32>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let json_requests = Arc::new(AtomicU32::new(0));
+
+    let counter = Arc::clone(&json_requests);
+    let app = Router::new().route(
+        "/sync/v1/ruleset",
+        get(move |headers: HeaderMap| {
+            let counter = Arc::clone(&counter);
+            async move {
+                let wants_snapshot = headers
+                    .get(header::ACCEPT)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.contains(flaps_eval::SNAPSHOT_MEDIA_TYPE));
+                let (content_type, body) = if wants_snapshot {
+                    let mut bytes = flaps_eval::FlagSet::json_to_snapshot(FLAGD_DOCUMENT)
+                        .expect("valid document");
+                    bytes[0] = format_version;
+                    (flaps_eval::SNAPSHOT_MEDIA_TYPE, bytes)
+                } else {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    ("application/json", FLAGD_DOCUMENT.as_bytes().to_vec())
+                };
+                let mut response = Response::new(axum::body::Body::from(body));
+                let h = response.headers_mut();
+                h.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+                h.insert("X-Flaps-Version", "42".parse().unwrap());
+                response
+            }
+        }),
+    );
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    (addr, json_requests)
+}
+
+#[tokio::test]
+async fn binary_snapshot_is_preferred_when_served() {
+    let (addr, json_requests) = spawn_snapshot_server(flaps_eval::SNAPSHOT_FORMAT_VERSION).await;
+    let provider = synced_provider(addr).await;
+
+    let ctx = EvaluationContext::default();
+    let result = provider
+        .resolve_bool_value("bool-flag", &ctx)
+        .await
+        .expect("should resolve from the binary snapshot");
+    assert!(result.value);
+    assert_eq!(json_requests.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn unsupported_snapshot_version_falls_back_to_json() {
+    let (addr, json_requests) =
+        spawn_snapshot_server(flaps_eval::SNAPSHOT_FORMAT_VERSION.wrapping_add(1)).await;
+    let provider = synced_provider(addr).await;
+
+    let ctx = EvaluationContext::default();
+    let result = provider
+        .resolve_bool_value("bool-flag", &ctx)
+        .await
+        .expect("should resolve from the JSON fallback");
+    assert!(result.value);
+    assert!(json_requests.load(Ordering::SeqCst) >= 1);
+}
+
+// ---------------------------------------------------------------------------
+// Custom HTTP client
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn a_supplied_http_client_sends_its_default_headers() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tenant_tx, mut tenant_rx) = tokio::sync::mpsc::unbounded_channel();
+    let app = Router::new().route(
+        "/sync/v1/ruleset",
+        get(move |headers: HeaderMap| {
+            let tenant = headers
+                .get("x-tenant")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned);
+            let authorized = headers.contains_key(header::AUTHORIZATION);
+            tenant_tx.send((tenant, authorized)).ok();
+            ruleset_handler()
+        }),
+    );
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let mut default_headers = HeaderMap::new();
+    default_headers.insert("x-tenant", HeaderValue::from_static("acme"));
+    let http_client = reqwest::Client::builder()
+        .default_headers(default_headers)
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+    let config = FlapsProviderConfig {
+        poll_interval: Duration::from_secs(3600),
+        ..FlapsProviderConfig::new(format!("http://{addr}"), "test-key")
+    };
+    let mut provider = FlapsProvider::with_http_client(config, http_client);
+    provider.initialize(&EvaluationContext::default()).await;
+    assert!(
+        provider
+            .wait_for_initialization(Duration::from_secs(5))
+            .await
+    );
+
+    let (tenant, authorized) = tenant_rx.recv().await.expect("a ruleset request");
+    assert_eq!(tenant.as_deref(), Some("acme"));
+    assert!(authorized, "the provider still authenticates the request");
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -0,0 +1,195 @@
+//! Integration tests for [`FlapsProviderConfig::remote_evaluation`].
+//!
+//! Tests cover:
+//! - A healthy server evaluates flags; an erroring one trips the breaker to
+//!   local evaluation, which stops calling the server until the open
+//!   duration elapses and a probe finds it healthy again.
+//! - A slow server call is abandoned for the local value and counted as a
+//!   failure.
+//! - A provider without remote evaluation reports no breaker state.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use open_feature::provider::FeatureProvider;
+use open_feature::{EvaluationContext, EvaluationReason, FlagMetadataValue, async_trait};
+use tokio::net::TcpListener;
+
+use flaps_client::{
+    BreakerState, ConfigSnapshot, FlagSource, FlapsProvider, FlapsProviderConfig,
+    RemoteEvaluationConfig, SourceError, VERSION_METADATA_KEY,
+};
+
+const HEALTHY: u8 = 0;
+const ERRORING: u8 = 1;
+const SLOW: u8 = 2;
+
+/// Behaviour of the mock evaluate endpoint, and the calls it received.
+#[derive(Default)]
+struct Server {
+    mode: AtomicU8,
+    calls: AtomicU32,
+}
+
+impl Server {
+    fn set(&self, mode: u8) {
+        self.mode.store(mode, Ordering::SeqCst);
+    }
+
+    fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+/// Answers every evaluation with `"server"` while healthy, with a 500 while
+/// erroring, and after two seconds while slow.
+async fn evaluate(State(server): State<Arc<Server>>) -> Response {
+    server.calls.fetch_add(1, Ordering::SeqCst);
+    match server.mode.load(Ordering::SeqCst) {
+        ERRORING => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        SLOW => tokio::time::sleep(Duration::from_secs(2)).await,
+        _ => {}
+    }
+    axum::Json(serde_json::json!({
+        "key": "greeting",
+        "value": "server",
+        "variant": "remote",
+        "reason": "TARGETING_MATCH",
+        "metadata": { "flaps.version": 7, "team": "growth" },
+    }))
+    .into_response()
+}
+
+async fn spawn_server(server: Arc<Server>) -> String {
+    let app = Router::new()
+        .route("/ofrep/v1/evaluate/flags/{key}", post(evaluate))
+        .with_state(server);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// The local ruleset: `greeting` serves `"local"`.
+struct LocalRuleset;
+
+#[async_trait]
+impl FlagSource for LocalRuleset {
+    async fn fetch(&self) -> Result<ConfigSnapshot, SourceError> {
+        let snapshot = ConfigSnapshot::from_json(
+            r#"{"flags":{"greeting":{
+                "state":"ENABLED",
+                "defaultVariant":"current",
+                "variants":{"current":"local"}
+            }}}"#,
+        )?;
+        Ok(snapshot)
+    }
+}
+
+async fn provider(base_url: String, remote: RemoteEvaluationConfig) -> FlapsProvider {
+    let mut config = FlapsProviderConfig::new(base_url, "sdk-key");
+    config.remote_evaluation = Some(remote);
+    let mut provider = FlapsProvider::with_source(config, LocalRuleset);
+    provider.initialize(&EvaluationContext::default()).await;
+    assert!(
+        provider
+            .wait_for_initialization(Duration::from_secs(2))
+            .await
+    );
+    provider
+}
+
+async fn greeting(provider: &FlapsProvider) -> String {
+    provider
+        .resolve_string_value("greeting", &EvaluationContext::default())
+        .await
+        .expect("greeting resolves")
+        .value
+}
+
+#[tokio::test]
+async fn erroring_server_trips_to_local_evaluation_and_recovers() {
+    let server = Arc::new(Server::default());
+    let base_url = spawn_server(Arc::clone(&server)).await;
+    let provider = provider(
+        base_url,
+        RemoteEvaluationConfig {
+            failure_threshold: 2,
+            slow_call_threshold: Duration::from_secs(1),
+            open_duration: Duration::from_millis(300),
+        },
+    )
+    .await;
+
+    let resolved = provider
+        .resolve_string_value("greeting", &EvaluationContext::default())
+        .await
+        .unwrap();
+    assert_eq!(resolved.value, "server");
+    assert_eq!(resolved.variant.as_deref(), Some("remote"));
+    assert_eq!(resolved.reason, Some(EvaluationReason::TargetingMatch));
+    let metadata = resolved.flag_metadata.unwrap().values;
+    assert_eq!(
+        metadata.get(VERSION_METADATA_KEY),
+        Some(&FlagMetadataValue::Int(7))
+    );
+    assert_eq!(
+        metadata.get("team"),
+        Some(&FlagMetadataValue::String("growth".to_owned()))
+    );
+    assert_eq!(provider.breaker_state(), Some(BreakerState::Closed));
+
+    server.set(ERRORING);
+    assert_eq!(greeting(&provider).await, "local");
+    assert_eq!(provider.breaker_state(), Some(BreakerState::Closed));
+    assert_eq!(greeting(&provider).await, "local");
+    assert_eq!(provider.breaker_state(), Some(BreakerState::Open));
+    assert_eq!(server.calls(), 3);
+
+    assert_eq!(greeting(&provider).await, "local");
+    assert_eq!(
+        server.calls(),
+        3,
+        "an open breaker does not call the server"
+    );
+
+    server.set(HEALTHY);
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(greeting(&provider).await, "server");
+    assert_eq!(provider.breaker_state(), Some(BreakerState::Closed));
+    assert_eq!(server.calls(), 4);
+}
+
+#[tokio::test]
+async fn slow_server_call_falls_back_to_the_local_value() {
+    let server = Arc::new(Server::default());
+    server.set(SLOW);
+    let base_url = spawn_server(Arc::clone(&server)).await;
+    let provider = provider(
+        base_url,
+        RemoteEvaluationConfig {
+            failure_threshold: 1,
+            slow_call_threshold: Duration::from_millis(100),
+            open_duration: Duration::from_secs(30),
+        },
+    )
+    .await;
+
+    let started = tokio::time::Instant::now();
+    assert_eq!(greeting(&provider).await, "local");
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(provider.breaker_state(), Some(BreakerState::Open));
+}
+
+#[test]
+fn local_only_provider_has_no_breaker() {
+    let provider = FlapsProvider::new(FlapsProviderConfig::new("http://unused.invalid", "unused"));
+    assert_eq!(provider.breaker_state(), None);
+}
@@ -0,0 +1,25 @@
+//! Provider configuration shared by the integration tests.
+
+use std::time::Duration;
+
+use flaps_client::FlapsProviderConfig;
+
+/// Builds a config pointed at `base_url` with fast timeouts and backoff, and
+/// an hourly poll so the polling fallback does not interfere.
+///
+/// Every other setting keeps its [`FlapsProviderConfig::new`] default, so a
+/// test overriding one of them spreads this config rather than listing every
+/// field.
+pub(crate) fn fast_config(
+    base_url: impl Into<String>,
+    sdk_key: impl Into<String>,
+) -> FlapsProviderConfig {
+    FlapsProviderConfig {
+        connect_timeout: Duration::from_secs(2),
+        request_timeout: Duration::from_secs(5),
+        poll_interval: Duration::from_secs(3600),
+        backoff_base: Duration::from_millis(10),
+        backoff_max: Duration::from_millis(50),
+        ..FlapsProviderConfig::new(base_url, sdk_key)
+    }
+}
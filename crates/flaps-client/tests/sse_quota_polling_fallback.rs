@@ -12,13 +12,16 @@
 //! chain end to end (real server, real quota, real HTTP) rather than
 //! asserting the fix by reading the source.
 
+mod shared;
+
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
 
 use flaps_client::{FlapsProvider, FlapsProviderConfig};
 use flaps_domain::{
     Environment, EnvironmentKey, Flag, FlagEnvConfig, FlagKey, FlagType, ManagedBy, Project,
-    ProjectKey, SdkKeyKind, ServeTarget, ValueType, VariantKey, VariantValue, Variants,
+    ProjectKey, RolloutScope, SdkKeyKind, ServeTarget, ValueType, VariantKey, VariantValue,
+    Variants,
 };
 use flaps_server::sse_quota::{SseQuota, SseQuotaConfig};
 use flaps_server::state::AppState;
@@ -135,6 +138,7 @@ async fn seed_active_boolean_flag(
                 external_ref: None,
                 managed_by: ManagedBy::Local,
                 metadata: flaps_domain::Metadata::new(),
+                inherits_from: None,
             },
         )
         .await
@@ -176,6 +180,12 @@ async fn seed_active_boolean_flag(
                 enabled: true,
                 rules: vec![],
                 default_rule: ServeTarget::Fixed(on.clone()),
+                inherit_default_rule: false,
+                salt: None,
+                bucket_by: None,
+                rollout_scope: RolloutScope::Shared,
+                kill_reason: None,
+                min_app_version: None,
             },
         )
         .await
@@ -281,15 +291,10 @@ async fn quota_rejected_client_still_observes_ruleset_change_via_polling() {
     // between polls, but the property under test is exercised purely through
     // the poll path: the SSE stream never opens even once in this test.
     let config = FlapsProviderConfig {
-        base_url: format!("http://{}", handle.addr),
-        sdk_key: SDK_SECRET.to_owned(),
-        connect_timeout: Duration::from_secs(2),
-        request_timeout: Duration::from_secs(5),
-        snapshot_path: None,
-        staleness_threshold: None,
         poll_interval: Duration::from_millis(50),
         backoff_base: Duration::from_millis(20),
         backoff_max: Duration::from_millis(60),
+        ..shared::fast_config(format!("http://{}", handle.addr), SDK_SECRET)
     };
 
     let mut provider = FlapsProvider::new(config);
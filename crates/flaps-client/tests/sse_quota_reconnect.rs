@@ -19,6 +19,8 @@
 //! out a paused `tokio` clock, which is otherwise the usual way this suite
 //! avoids wall-clock dependence.
 
+mod shared;
+
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -91,6 +93,7 @@ async fn spawn_quota_exhausted_server() -> (SocketAddr, Arc<AtomicUsize>) {
                 external_ref: None,
                 managed_by: ManagedBy::Local,
                 metadata: flaps_domain::Metadata::new(),
+                inherits_from: None,
             },
         )
         .await
@@ -145,17 +148,12 @@ async fn quota_rejection_backs_off_instead_of_spinning() {
     let (addr, counter) = spawn_quota_exhausted_server().await;
 
     let config = FlapsProviderConfig {
-        base_url: format!("http://{addr}"),
-        sdk_key: SDK_SECRET.to_owned(),
-        connect_timeout: Duration::from_secs(2),
-        request_timeout: Duration::from_secs(5),
-        snapshot_path: None,
-        staleness_threshold: None,
         // Long enough that any observed SSE attempts are exclusively from
         // the reconnect-on-error path, never from the polling fallback.
         poll_interval: Duration::from_secs(3600),
         backoff_base: Duration::from_millis(30),
         backoff_max: Duration::from_millis(80),
+        ..shared::fast_config(format!("http://{addr}"), SDK_SECRET)
     };
 
     let mut provider = FlapsProvider::new(config);
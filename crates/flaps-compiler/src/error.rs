@@ -48,6 +48,15 @@ pub enum CompileError {
         operator: String,
     },
 
+    /// A predicate value is well-formed JSON but outside the operator's domain.
+    #[error("operator `{operator}` rejects its values: {reason}")]
+    InvalidPredicateValue {
+        /// Name of the operator.
+        operator: String,
+        /// Why the values were rejected.
+        reason: String,
+    },
+
     /// The compiled document was rejected by the `flaps-eval` parser.
     ///
     /// This indicates an internal compiler bug; the produced document is not
@@ -81,4 +90,26 @@ pub enum CompileError {
         /// Underlying serialization error message.
         reason: String,
     },
+
+    /// A minimum app version is not a semantic version.
+    #[error("minimum app version `{version}` in flag `{flag}` is not a semantic version")]
+    InvalidMinAppVersion {
+        /// Key of the flag.
+        flag: String,
+        /// The rejected version.
+        version: String,
+    },
+
+    /// A flag or segment exceeds one of the configured [`Limits`](crate::Limits).
+    #[error("`{subject}` exceeds {limit}: {actual} > {max}")]
+    LimitExceeded {
+        /// Key of the offending flag or segment.
+        subject: String,
+        /// Name of the exceeded limit (e.g. `max_rules_per_config`).
+        limit: &'static str,
+        /// The configured ceiling.
+        max: usize,
+        /// The value that was found.
+        actual: usize,
+    },
 }
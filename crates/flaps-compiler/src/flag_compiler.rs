@@ -3,14 +3,21 @@
 use std::collections::BTreeMap;
 
 use flaps_domain::{
-    flag_env_config::{FlagEnvConfig, ServeTarget},
+    flag_env_config::{
+        FlagEnvConfig, HashRingRollout, Rollout, RolloutScope, ServeTarget, TargetingRule,
+    },
     key::{FlagKey, SegmentKey},
     metadata::{Metadata as DomainMetadata, MetadataValue as DomainMetadataValue},
     variant::{ValueType, Variants as DomainVariants},
 };
-use flaps_eval::{Bucket, Flag, Literal, Metadata, MetadataValue, Rule, State, Variants};
+use flaps_eval::{
+    Bucket, Flag, HashRing, KILL_REASON_METADATA, Literal, MIN_APP_VERSION_METADATA, Metadata,
+    MetadataValue, Rule, State, Variants,
+};
 
-use crate::{error::CompileError, input::Segments, segment_compiler::compile_segment_match};
+use crate::{
+    error::CompileError, input::Segments, limits::Limits, segment_compiler::compile_segment_match,
+};
 
 /// Converts a single domain metadata value to its `flaps-eval` counterpart.
 ///
@@ -78,7 +85,9 @@ fn extract_entries(
     })
 }
 
-/// Validates that a [`ServeTarget`] only names variants declared in the flag.
+/// Validates that a [`ServeTarget`] only names variants declared in the flag,
+/// and that a consistent-hash ring stays within
+/// [`HashRing::MAX_TOTAL_WEIGHT`].
 fn validate_serve_target(
     flag: &str,
     serve: &ServeTarget,
@@ -93,7 +102,10 @@ fn validate_serve_target(
                 });
             }
         }
-        ServeTarget::Rollout(rollout) => {
+        ServeTarget::Rollout(rollout)
+        | ServeTarget::ConsistentHash(HashRingRollout {
+            weights: rollout, ..
+        }) => {
             for wv in rollout.weights() {
                 if !domain.contains(&wv.variant) {
                     return Err(CompileError::UnknownVariant {
@@ -104,6 +116,22 @@ fn validate_serve_target(
             }
         }
     }
+    if let ServeTarget::ConsistentHash(ring) = serve {
+        let total: u64 = ring
+            .weights
+            .weights()
+            .iter()
+            .map(|w| u64::from(w.weight))
+            .sum();
+        if total > HashRing::MAX_TOTAL_WEIGHT {
+            return Err(CompileError::LimitExceeded {
+                subject: flag.to_owned(),
+                limit: "max_ring_weight",
+                max: usize::try_from(HashRing::MAX_TOTAL_WEIGHT).unwrap_or(usize::MAX),
+                actual: usize::try_from(total).unwrap_or(usize::MAX),
+            });
+        }
+    }
     Ok(())
 }
 
@@ -191,13 +219,47 @@ fn compile_variants(flag: &str, domain: &DomainVariants) -> Result<Variants, Com
     }
 }
 
-/// Compiles the condition for a single targeting rule: AND of inlined segments.
+/// Compiles the condition for a single targeting rule: AND of its inlined
+/// segments and, when it has groups, of an OR over its groups, each an AND
+/// of inlined segments.
+///
+/// A segment referenced by several rules is inlined into each of them; there
+/// is no membership memo. Conditions are pure, so a later rule re-judging a
+/// segment sees the same answer, and the `if` chain stops at the first
+/// matching rule. The cost of a shared segment is bounded by
+/// [`Limits::max_segment_depth`] and [`Limits::max_rules_per_config`], and
+/// a rule's segments, groups included, by
+/// [`Limits::max_conditions_per_rule`].
 fn compile_condition(
     flag: &str,
-    segment_keys: &[SegmentKey],
+    rule: &TargetingRule,
     segments: &Segments<'_>,
+    limits: &Limits,
 ) -> Result<Rule, CompileError> {
-    let mut rules: Vec<Rule> = segment_keys
+    limits.check_condition_count(flag, rule.referenced_segments().count())?;
+    let mut rules = compile_segments(flag, &rule.segments, segments, limits)?;
+    if !rule.segment_groups.is_empty() {
+        let mut groups: Vec<Rule> = rule
+            .segment_groups
+            .iter()
+            .map(|group| compile_segments(flag, group, segments, limits).map(conjunction))
+            .collect::<Result<_, _>>()?;
+        rules.push(match groups.len() {
+            1 => groups.remove(0),
+            _ => Rule::Or(groups),
+        });
+    }
+    Ok(conjunction(rules))
+}
+
+/// Inlines each of `segment_keys`.
+fn compile_segments(
+    flag: &str,
+    segment_keys: &[SegmentKey],
+    segments: &Segments<'_>,
+    limits: &Limits,
+) -> Result<Vec<Rule>, CompileError> {
+    segment_keys
         .iter()
         .map(|sk| {
             let match_expr = segments
@@ -206,76 +268,207 @@ fn compile_condition(
                     flag: flag.to_owned(),
                     segment: sk.as_str().to_owned(),
                 })?;
+            limits.check_segment(sk.as_str(), match_expr)?;
             compile_segment_match(match_expr)
         })
-        .collect::<Result<_, _>>()?;
+        .collect()
+}
 
-    Ok(match rules.len() {
+/// ANDs `rules`: `true` when there are none, the rule itself when alone.
+fn conjunction(mut rules: Vec<Rule>) -> Rule {
+    match rules.len() {
         0 => Rule::Literal(Literal::Bool(true)),
         1 => rules.remove(0),
         _ => Rule::And(rules),
-    })
+    }
 }
 
 /// Compiles a [`ServeTarget`] into a targeting [`Rule`] arm.
-fn compile_serve(serve: &ServeTarget) -> Rule {
+///
+/// A rollout under a `salt` buckets on `salt + targetingKey` instead of the
+/// flagd default of `flagKey + targetingKey`; a rollout in a `namespace`
+/// inserts it between the two. A rollout with a `bucket_by` attribute hashes
+/// that attribute in place of the targeting key, falling back to the
+/// targeting key when the context lacks it. A consistent-hash rollout
+/// buckets the same way on its own attribute and ignores `bucket_by`.
+fn compile_serve(
+    serve: &ServeTarget,
+    salt: Option<&str>,
+    namespace: Option<&str>,
+    bucket_by: Option<&str>,
+) -> Rule {
     match serve {
         ServeTarget::Fixed(vk) => Rule::Literal(Literal::String(vk.as_str().to_owned())),
-        ServeTarget::Rollout(rollout) => {
-            let buckets = rollout
-                .weights()
-                .iter()
-                .map(|wv| Bucket {
-                    variant: wv.variant.as_str().to_owned(),
-                    weight: wv.weight,
-                })
-                .collect();
-            Rule::Fractional {
-                bucket_by: None,
-                buckets,
-            }
-        }
+        ServeTarget::Rollout(rollout) => Rule::Fractional {
+            bucket_by: (salt.is_some() || namespace.is_some() || bucket_by.is_some()).then(|| {
+                let subject = bucket_by.map_or_else(targeting_key, bucketing_attribute);
+                Box::new(bucketing_key(salt, namespace, subject))
+            }),
+            buckets: compile_buckets(rollout),
+        },
+        ServeTarget::ConsistentHash(ring) => Rule::ConsistentHash {
+            bucket_by: Box::new(bucketing_key(
+                salt,
+                namespace,
+                attribute_or_empty(&ring.attribute),
+            )),
+            ring: HashRing::new(compile_buckets(&ring.weights)),
+        },
     }
 }
 
+/// Compiles rollout weights into `fractional` style buckets.
+fn compile_buckets(rollout: &Rollout) -> Vec<Bucket> {
+    rollout
+        .weights()
+        .iter()
+        .map(|wv| Bucket {
+            variant: wv.variant.as_str().to_owned(),
+            weight: wv.weight,
+        })
+        .collect()
+}
+
+/// Builds the `cat` expression `(salt or flagKey) + namespace + subject`
+/// used as a rollout's bucketing value.
+///
+/// Prefixing the flag key keeps flags ramped to the same weights from
+/// serving the same users or hosts.
+fn bucketing_key(salt: Option<&str>, namespace: Option<&str>, subject: Rule) -> Rule {
+    let prefix = match salt {
+        Some(salt) => Rule::Literal(Literal::String(salt.to_owned())),
+        None => Rule::Var {
+            path: "$flagd.flagKey".to_owned(),
+            default: None,
+        },
+    };
+    let namespace = namespace.map(|ns| Rule::Literal(Literal::String(ns.to_owned())));
+    Rule::Cat(
+        std::iter::once(prefix)
+            .chain(namespace)
+            .chain(std::iter::once(subject))
+            .collect(),
+    )
+}
+
+/// Reads `attribute`, or an empty string when the context lacks it, as a
+/// missing targeting key reads in the flagd default.
+fn attribute_or_empty(attribute: &str) -> Rule {
+    Rule::Var {
+        path: attribute.to_owned(),
+        default: Some(Literal::String(String::new())),
+    }
+}
+
+/// Reads the targeting key, or an empty string without one.
+fn targeting_key() -> Rule {
+    attribute_or_empty("targetingKey")
+}
+
+/// Reads `attribute`, or the targeting key when the context lacks it or
+/// sets it to `null` or `""`, so a context without the attribute keeps a
+/// bucket of its own.
+fn bucketing_attribute(attribute: &str) -> Rule {
+    Rule::If(vec![
+        Rule::Missing(vec![Rule::Literal(Literal::String(attribute.to_owned()))]),
+        targeting_key(),
+        Rule::Var {
+            path: attribute.to_owned(),
+            default: None,
+        },
+    ])
+}
+
+/// Returns the bucketing namespace of a rule's rollout under
+/// [`RolloutScope::PerRule`]: `/rule:` followed by the rule's sorted segment
+/// keys and a closing `/`, e.g. `/rule:beta,eu/`. A rule with groups lists
+/// them after a `|`, each sorted and separated by `;`, e.g.
+/// `/rule:beta|fr,mobile;pro/`.
+///
+/// Rules are identified by their segment set, as in the audit diff, so
+/// reordering rules keeps every user in place; two rules listing the same
+/// segments and groups share a namespace.
+fn rule_namespace(rule: &TargetingRule) -> String {
+    let sorted = |segments: &[SegmentKey]| {
+        let mut keys: Vec<&str> = segments.iter().map(SegmentKey::as_str).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys.join(",")
+    };
+    let mut namespace = format!("/rule:{}", sorted(&rule.segments));
+    let mut groups: Vec<String> = rule.segment_groups.iter().map(|g| sorted(g)).collect();
+    groups.sort_unstable();
+    groups.dedup();
+    if !groups.is_empty() {
+        namespace.push('|');
+        namespace.push_str(&groups.join(";"));
+    }
+    namespace.push('/');
+    namespace
+}
+
 /// Compiles targeting rules and default variant for a flag in one environment.
 fn compile_targeting(
     flag: &str,
     config: &FlagEnvConfig,
     segments: &Segments<'_>,
+    limits: &Limits,
 ) -> Result<(Option<Rule>, Option<String>), CompileError> {
+    let salt = config.salt.as_deref();
     // Simple case: no explicit rules and a Fixed default -> skip the targeting tree.
     if config.rules.is_empty() {
         match &config.default_rule {
             ServeTarget::Fixed(vk) => {
                 return Ok((None, Some(vk.as_str().to_owned())));
             }
-            ServeTarget::Rollout(_) => {
+            ServeTarget::Rollout(_) | ServeTarget::ConsistentHash(_) => {
                 // No rules, just a rollout fallback: emit the Fractional rule directly
                 // without wrapping in Rule::If (which requires at least 2 arguments).
-                return Ok((Some(compile_serve(&config.default_rule)), None));
+                let default = compile_serve(
+                    &config.default_rule,
+                    salt,
+                    None,
+                    config.bucket_by.as_deref(),
+                );
+                return Ok((Some(default), None));
             }
         }
     }
 
+    limits.check_rule_count(flag, config.rules.len())?;
+
     // General case (at least one targeting rule):
     // Rule::If([cond1, serve1, ..., condN, serveN, serve_default])
     let mut if_arms: Vec<Rule> = Vec::new();
 
     for rule in &config.rules {
-        let cond = compile_condition(flag, &rule.segments, segments)?;
-        let serve = compile_serve(&rule.serve);
+        let cond = compile_condition(flag, rule, segments, limits)?;
+        let namespace = match config.rollout_scope {
+            RolloutScope::Shared => None,
+            RolloutScope::PerRule => Some(rule_namespace(rule)),
+        };
+        let serve = compile_serve(
+            &rule.serve,
+            salt,
+            namespace.as_deref(),
+            config.bucket_by_of(rule),
+        );
         if_arms.push(cond);
         if_arms.push(serve);
     }
 
     // Trailing else arm (the default)
-    if_arms.push(compile_serve(&config.default_rule));
+    if_arms.push(compile_serve(
+        &config.default_rule,
+        salt,
+        None,
+        config.bucket_by.as_deref(),
+    ));
 
     // default_variant: present only when the fallback is Fixed
     let default_variant = match &config.default_rule {
         ServeTarget::Fixed(vk) => Some(vk.as_str().to_owned()),
-        ServeTarget::Rollout(_) => None,
+        ServeTarget::Rollout(_) | ServeTarget::ConsistentHash(_) => None,
     };
 
     Ok((Some(Rule::If(if_arms)), default_variant))
@@ -288,20 +481,48 @@ fn compile_targeting(
 /// - [`CompileError::UnknownSegment`] when a rule references an unknown segment.
 /// - [`CompileError::ObjectVariantNotObject`] when an Object-typed variant value is not a JSON object.
 /// - [`CompileError::PredicateArity`] / [`CompileError::NonScalarPredicateValue`] from segment inlining.
+/// - [`CompileError::InvalidMinAppVersion`] when the minimum app version is not a semantic version.
+/// - [`CompileError::LimitExceeded`] when the config exceeds one of `limits`.
 pub(crate) fn compile_flag(
     flag_key: &FlagKey,
     domain_variants: &DomainVariants,
     config: &FlagEnvConfig,
     segments: &Segments<'_>,
     flag_metadata: &DomainMetadata,
+    limits: &Limits,
 ) -> Result<Flag, CompileError> {
     let flag_str = flag_key.as_str();
 
-    let state = if config.enabled {
+    // A kill switch overrides `enabled`; `KILL_REASON_METADATA` tells the
+    // evaluator a killed flag from a disabled one, and is reserved.
+    let state = if config.enabled && !config.is_killed() {
         State::Enabled
     } else {
         State::Disabled
     };
+    let mut metadata = compile_metadata(flag_metadata);
+    metadata.remove(KILL_REASON_METADATA);
+    if let Some(reason) = &config.kill_reason {
+        metadata.insert(
+            KILL_REASON_METADATA.to_owned(),
+            MetadataValue::String(reason.clone()),
+        );
+    }
+    // `MIN_APP_VERSION_METADATA` is the version gate the evaluator applies
+    // before targeting; reserved as well.
+    metadata.remove(MIN_APP_VERSION_METADATA);
+    if let Some(version) = &config.min_app_version {
+        if !flaps_eval::is_sem_ver(version) {
+            return Err(CompileError::InvalidMinAppVersion {
+                flag: flag_str.to_owned(),
+                version: version.clone(),
+            });
+        }
+        metadata.insert(
+            MIN_APP_VERSION_METADATA.to_owned(),
+            MetadataValue::String(version.clone()),
+        );
+    }
 
     let variants = compile_variants(flag_str, domain_variants)?;
 
@@ -311,13 +532,13 @@ pub(crate) fn compile_flag(
         validate_serve_target(flag_str, &rule.serve, domain_variants)?;
     }
 
-    let (targeting, default_variant) = compile_targeting(flag_str, config, segments)?;
+    let (targeting, default_variant) = compile_targeting(flag_str, config, segments, limits)?;
 
     Ok(Flag {
         state,
         variants,
         default_variant,
         targeting,
-        metadata: compile_metadata(flag_metadata),
+        metadata,
     })
 }
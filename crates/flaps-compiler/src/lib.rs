@@ -20,6 +20,9 @@
 
 pub mod error;
 pub mod input;
+pub mod limits;
+pub mod membership;
+pub mod precompile;
 pub mod ruleset;
 
 mod flag_compiler;
@@ -31,10 +34,14 @@ use sha2::{Digest, Sha256};
 
 use flaps_domain::key::{EnvironmentKey, SegmentKey};
 use flaps_domain::metadata::Metadata as DomainMetadata;
-use flaps_eval::FlagSet;
+use flaps_domain::segment::SegmentMatch;
+use flaps_eval::{Flag, FlagSet, Literal, Rule, State, Variants};
 
 pub use error::CompileError;
 pub use input::{FlagConfig, Segments};
+pub use limits::Limits;
+pub use membership::SegmentMembership;
+pub use precompile::{CompileReport, CompileWarning, precompile};
 pub use ruleset::CompiledRuleset;
 
 /// Compiles all flags configured in one environment into a canonical [`CompiledRuleset`].
@@ -53,12 +60,40 @@ pub use ruleset::CompiledRuleset;
 /// - invalid predicate arity or non-scalar values,
 /// - non-object JSON values for `Object`-typed variants,
 /// - or if the produced document is rejected by the evaluator (internal bug guard).
+///
+/// Applies the default [`Limits`]; use [`compile_environment_with_limits`]
+/// to enforce custom ones.
 pub fn compile_environment(
     environment: &EnvironmentKey,
     flags: &[FlagConfig<'_>],
     segments: &Segments<'_>,
     environment_metadata: &DomainMetadata,
     previous: Option<&CompiledRuleset>,
+) -> Result<CompiledRuleset, CompileError> {
+    compile_environment_with_limits(
+        environment,
+        flags,
+        segments,
+        environment_metadata,
+        previous,
+        &Limits::default(),
+    )
+}
+
+/// Same as [`compile_environment`], enforcing the given structural `limits`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`compile_environment`], plus
+/// [`CompileError::LimitExceeded`] when a flag config or a referenced segment
+/// exceeds `limits`.
+pub fn compile_environment_with_limits(
+    environment: &EnvironmentKey,
+    flags: &[FlagConfig<'_>],
+    segments: &Segments<'_>,
+    environment_metadata: &DomainMetadata,
+    previous: Option<&CompiledRuleset>,
+    limits: &Limits,
 ) -> Result<CompiledRuleset, CompileError> {
     // Build the flag map; BTreeMap guarantees stable key ordering.
     let mut flag_map = BTreeMap::new();
@@ -70,6 +105,7 @@ pub fn compile_environment(
             fc.config,
             segments,
             &fc.flag.metadata,
+            limits,
         )?;
         flag_map.insert(fc.flag.key.as_str().to_owned(), compiled);
     }
@@ -109,6 +145,58 @@ pub fn compile_environment(
     })
 }
 
+/// Key of the single flag of a [`compile_segment_probe`] flag set.
+pub const SEGMENT_PROBE_FLAG: &str = "segment-probe";
+
+/// Compiles `segment` on its own into a flag set whose single boolean flag,
+/// [`SEGMENT_PROBE_FLAG`], resolves to the `true` variant for contexts in
+/// the segment and to `false` otherwise.
+///
+/// The segment compiles exactly as it would inside a targeting rule, so a
+/// proposed segment can be evaluated against contexts before any flag
+/// references it.
+///
+/// # Errors
+///
+/// Returns [`CompileError::LimitExceeded`] when `segment` exceeds `limits`,
+/// and the predicate errors of [`compile_environment`].
+pub fn compile_segment_probe(
+    key: &SegmentKey,
+    segment: &SegmentMatch,
+    limits: &Limits,
+) -> Result<FlagSet, CompileError> {
+    let flag = segment_probe_flag(key, segment, limits)?;
+    Ok(FlagSet {
+        flags: BTreeMap::from([(SEGMENT_PROBE_FLAG.to_owned(), flag)]),
+        metadata: flaps_eval::Metadata::new(),
+    })
+}
+
+/// Compiles `segment` into a boolean flag resolving to the `true` variant
+/// for contexts in the segment and to `false` otherwise.
+fn segment_probe_flag(
+    key: &SegmentKey,
+    segment: &SegmentMatch,
+    limits: &Limits,
+) -> Result<Flag, CompileError> {
+    limits.check_segment(key.as_str(), segment)?;
+    let rule = segment_compiler::compile_segment_match(segment)?;
+    Ok(Flag {
+        state: State::Enabled,
+        variants: Variants::Boolean(BTreeMap::from([
+            ("true".to_owned(), true),
+            ("false".to_owned(), false),
+        ])),
+        default_variant: Some("false".to_owned()),
+        targeting: Some(Rule::If(vec![
+            rule,
+            Rule::Literal(Literal::String("true".to_owned())),
+            Rule::Literal(Literal::String("false".to_owned())),
+        ])),
+        metadata: flaps_eval::Metadata::new(),
+    })
+}
+
 /// Returns the set of environments whose flags reference `segment`.
 ///
 /// When a segment is edited, only the environments returned here need to be
@@ -123,7 +211,7 @@ pub fn environments_referencing_segment<S: ::std::hash::BuildHasher>(
     for (env, flag_configs) in flags_by_environment {
         'flag_loop: for fc in flag_configs {
             for rule in &fc.config.rules {
-                for sk in &rule.segments {
+                for sk in rule.referenced_segments() {
                     if sk == segment {
                         result.insert(env.clone());
                         break 'flag_loop;
@@ -142,7 +230,9 @@ mod tests {
 
     use flaps_domain::{
         flag::{Flag, FlagType},
-        flag_env_config::{FlagEnvConfig, ServeTarget, TargetingRule, WeightedVariant},
+        flag_env_config::{
+            FlagEnvConfig, RolloutScope, ServeTarget, TargetingRule, WeightedVariant,
+        },
         key::{EnvironmentKey, FlagKey, SegmentKey, VariantKey},
         segment::{MatchOperator, Predicate, Segment, SegmentMatch},
         variant::{ValueType, VariantValue, Variants as DomainVariants},
@@ -214,6 +304,12 @@ mod tests {
             enabled: true,
             rules: vec![],
             default_rule: ServeTarget::Fixed(vk(variant)),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         }
     }
 
@@ -222,6 +318,12 @@ mod tests {
             enabled: false,
             rules: vec![],
             default_rule: ServeTarget::Fixed(vk(variant)),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         }
     }
 
@@ -258,6 +360,50 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn boolean_variant_values_are_independent_of_their_keys() {
+        // A boolean flag's values are its declared variants, so an inverted
+        // feature can serve `true` from its "off" variant; the resolution
+        // still names the variant served.
+        let mut inverted = bool_flag("legacy-checkout");
+        inverted.variants = DomainVariants::new(
+            ValueType::Boolean,
+            [
+                (vk("on"), VariantValue::Bool(false)),
+                (vk("off"), VariantValue::Bool(true)),
+            ],
+        )
+        .unwrap();
+        let plain = bool_flag("new-checkout");
+        let config = simple_config("off");
+        let flag_set = compile_environment(
+            &ek("prod"),
+            &[
+                FlagConfig {
+                    flag: &inverted,
+                    config: &config,
+                },
+                FlagConfig {
+                    flag: &plain,
+                    config: &config,
+                },
+            ],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+        )
+        .map(|ruleset| FlagSet::from_json(&ruleset.document).unwrap())
+        .unwrap();
+
+        let context = flaps_eval::EvaluationContext::default();
+        let inverted = flag_set.evaluate("legacy-checkout", &context).unwrap();
+        assert_eq!(inverted.value, Some(serde_json::json!(true)));
+        assert_eq!(inverted.variant.as_deref(), Some("off"));
+        let plain = flag_set.evaluate("new-checkout", &context).unwrap();
+        assert_eq!(plain.value, Some(serde_json::json!(false)));
+        assert_eq!(plain.variant.as_deref(), Some("off"));
+    }
+
     #[test]
     fn string_variants_compile() {
         let flag = string_flag("str-flag");
@@ -427,6 +573,12 @@ mod tests {
                 },
             ])
             .unwrap(),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let env = ek("prod");
         let result = compile_environment(
@@ -447,6 +599,321 @@ mod tests {
         assert!(f.targeting.is_some(), "rollout should produce targeting");
     }
 
+    fn half_rollout(salt: Option<&str>) -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled: true,
+            rules: vec![],
+            default_rule: ServeTarget::rollout(vec![
+                WeightedVariant {
+                    variant: vk("on"),
+                    weight: 50,
+                },
+                WeightedVariant {
+                    variant: vk("off"),
+                    weight: 50,
+                },
+            ])
+            .unwrap(),
+            inherit_default_rule: false,
+            salt: salt.map(str::to_owned),
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    /// Counts the users out of 1000 assigned the same variant by both flags.
+    fn co_assigned(salt_a: Option<&str>, salt_b: Option<&str>) -> usize {
+        let (flag_a, flag_b) = (bool_flag("checkout-a"), bool_flag("checkout-b"));
+        let (config_a, config_b) = (half_rollout(salt_a), half_rollout(salt_b));
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[
+                FlagConfig {
+                    flag: &flag_a,
+                    config: &config_a,
+                },
+                FlagConfig {
+                    flag: &flag_b,
+                    config: &config_b,
+                },
+            ],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let flag_set = FlagSet::from_json(&ruleset.document).unwrap();
+        (0..1000)
+            .filter(|i| {
+                let context = flaps_eval::EvaluationContext {
+                    targeting_key: Some(format!("user-{i}")),
+                    ..Default::default()
+                };
+                let a = flag_set.evaluate("checkout-a", &context).unwrap();
+                let b = flag_set.evaluate("checkout-b", &context).unwrap();
+                a.variant == b.variant
+            })
+            .count()
+    }
+
+    #[test]
+    fn shared_salt_co_assigns_users_across_flags() {
+        assert_eq!(co_assigned(Some("checkout"), Some("checkout")), 1000);
+    }
+
+    #[test]
+    fn distinct_salts_diverge_across_flags() {
+        let same = co_assigned(Some("checkout-1"), Some("checkout-2"));
+        assert!((350..650).contains(&same), "{same} of 1000 co-assigned");
+    }
+
+    #[test]
+    fn unsalted_rollout_keeps_the_flagd_default_bucketing() {
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &bool_flag("checkout-a"),
+                config: &half_rollout(None),
+            }],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let parsed = FlagSet::from_json(&ruleset.document).unwrap();
+        assert!(matches!(
+            parsed.flags["checkout-a"].targeting,
+            Some(flaps_eval::Rule::Fractional {
+                bucket_by: None,
+                ..
+            })
+        ));
+        let same = co_assigned(None, None);
+        assert!((350..650).contains(&same), "{same} of 1000 co-assigned");
+    }
+
+    #[test]
+    fn consistent_hash_rollout_buckets_on_the_attribute() {
+        let config = FlagEnvConfig {
+            default_rule: ServeTarget::consistent_hash(
+                "host",
+                vec![
+                    WeightedVariant {
+                        variant: vk("on"),
+                        weight: 25,
+                    },
+                    WeightedVariant {
+                        variant: vk("off"),
+                        weight: 75,
+                    },
+                ],
+            )
+            .unwrap(),
+            ..half_rollout(None)
+        };
+        let (flag_a, flag_b) = (bool_flag("checkout-a"), bool_flag("checkout-b"));
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[
+                FlagConfig {
+                    flag: &flag_a,
+                    config: &config,
+                },
+                FlagConfig {
+                    flag: &flag_b,
+                    config: &config,
+                },
+            ],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let flag_set = FlagSet::from_json(&ruleset.document).unwrap();
+        assert!(matches!(
+            flag_set.flags["checkout-a"].targeting,
+            Some(flaps_eval::Rule::ConsistentHash { .. })
+        ));
+
+        let host = |i: usize| flaps_eval::EvaluationContext {
+            targeting_key: Some("same-user".to_owned()),
+            attributes: [("host".to_owned(), serde_json::json!(format!("web-{i}")))].into(),
+            ..Default::default()
+        };
+        let variant = |flag: &str, i| flag_set.evaluate(flag, &host(i)).unwrap().variant;
+        let on = (0..1000)
+            .filter(|&i| variant("checkout-a", i).as_deref() == Some("on"))
+            .count();
+        assert!((150..350).contains(&on), "{on} of 1000 hosts on");
+        let both = (0..1000)
+            .filter(|&i| variant("checkout-a", i) == variant("checkout-b", i))
+            .count();
+        assert!(both < 1000, "flags must place hosts independently");
+    }
+
+    /// Compiles `config` for a `checkout-a` flag and returns a closure
+    /// evaluating it for a user, with the given `company_id` if any.
+    fn company_rollout(
+        config: &FlagEnvConfig,
+    ) -> impl Fn(&str, Option<&str>) -> Option<String> + use<> {
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &bool_flag("checkout-a"),
+                config,
+            }],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let flag_set = FlagSet::from_json(&ruleset.document).unwrap();
+        move |user, company| {
+            let context = flaps_eval::EvaluationContext {
+                targeting_key: Some(user.to_owned()),
+                attributes: company
+                    .map(|id| ("company_id".to_owned(), serde_json::json!(id)))
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            };
+            flag_set.evaluate("checkout-a", &context).unwrap().variant
+        }
+    }
+
+    #[test]
+    fn users_sharing_the_bucketing_attribute_share_a_bucket() {
+        let variant = company_rollout(&FlagEnvConfig {
+            bucket_by: Some("company_id".to_owned()),
+            ..half_rollout(None)
+        });
+
+        let mut on_companies = 0;
+        for company in 0..200 {
+            let company = format!("company-{company}");
+            let first = variant("user-0", Some(&company));
+            for user in 1..10 {
+                assert_eq!(
+                    variant(&format!("user-{user}"), Some(&company)),
+                    first,
+                    "{company} split across variants"
+                );
+            }
+            on_companies += usize::from(first.as_deref() == Some("on"));
+        }
+        assert!(
+            (60..140).contains(&on_companies),
+            "{on_companies} of 200 companies on"
+        );
+    }
+
+    #[test]
+    fn a_missing_bucketing_attribute_falls_back_to_the_targeting_key() {
+        let variant = company_rollout(&FlagEnvConfig {
+            bucket_by: Some("company_id".to_owned()),
+            ..half_rollout(None)
+        });
+        let unbucketed = company_rollout(&half_rollout(None));
+
+        for user in 0..200 {
+            let user = format!("user-{user}");
+            assert_eq!(variant(&user, None), unbucketed(&user, None), "{user}");
+        }
+    }
+
+    #[test]
+    fn a_rule_bucketing_attribute_overrides_the_config_one() {
+        let config = FlagEnvConfig {
+            bucket_by: Some("device_id".to_owned()),
+            rules: vec![TargetingRule {
+                segments: vec![],
+                segment_groups: Vec::new(),
+                serve: half_rollout(None).default_rule,
+                bucket_by: Some("company_id".to_owned()),
+                description: None,
+            }],
+            ..half_rollout(None)
+        };
+        let variant = company_rollout(&config);
+
+        let split = (0..200)
+            .map(|company| format!("company-{company}"))
+            .filter(|company| {
+                let first = variant("user-0", Some(company));
+                (1..10).any(|user| variant(&format!("user-{user}"), Some(company)) != first)
+            })
+            .count();
+        assert_eq!(split, 0, "{split} companies split across variants");
+    }
+
+    /// Evaluates, for 1000 users, a flag whose `beta-users` rule and default
+    /// both roll `on` out to half of the users, once as a beta user (rule
+    /// gate) and once as a free user (default gate).
+    fn rule_and_default_gates(scope: RolloutScope) -> Vec<(Option<String>, Option<String>)> {
+        let flag = bool_flag("checkout-a");
+        let half = half_rollout(None).default_rule;
+        let config = FlagEnvConfig {
+            rules: vec![TargetingRule {
+                segments: vec![sk("beta-users")],
+                segment_groups: Vec::new(),
+                serve: half.clone(),
+                bucket_by: None,
+                description: None,
+            }],
+            rollout_scope: scope,
+            ..half_rollout(None)
+        };
+        let segment = beta_segment("beta-users");
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new([(sk("beta-users"), &segment.match_expr)]),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let flag_set = FlagSet::from_json(&ruleset.document).unwrap();
+        let variant = |i: usize, tier: &str| {
+            let context = flaps_eval::EvaluationContext {
+                targeting_key: Some(format!("user-{i}")),
+                attributes: [("tier".to_owned(), serde_json::json!(tier))].into(),
+                ..Default::default()
+            };
+            flag_set.evaluate("checkout-a", &context).unwrap().variant
+        };
+        (0..1000)
+            .map(|i| (variant(i, "beta"), variant(i, "free")))
+            .collect()
+    }
+
+    #[test]
+    fn shared_scope_correlates_rule_and_default_rollouts() {
+        let gates = rule_and_default_gates(RolloutScope::Shared);
+        assert!(gates.iter().all(|(rule, default)| rule == default));
+    }
+
+    #[test]
+    fn per_rule_scope_decorrelates_rule_and_default_rollouts() {
+        let gates = rule_and_default_gates(RolloutScope::PerRule);
+        let same = gates
+            .iter()
+            .filter(|(rule, default)| rule == default)
+            .count();
+        assert!((350..650).contains(&same), "{same} of 1000 gated alike");
+
+        let shared = rule_and_default_gates(RolloutScope::Shared);
+        let defaults_kept = gates
+            .iter()
+            .zip(&shared)
+            .all(|((_, per_rule), (_, shared))| per_rule == shared);
+        assert!(defaults_kept, "the default rollout must keep its users");
+    }
+
     #[test]
     fn disabled_flag_has_disabled_state() {
         let flag = bool_flag("my-flag");
@@ -467,6 +934,57 @@ mod tests {
         assert_eq!(parsed.flags["my-flag"].state, flaps_eval::State::Disabled);
     }
 
+    /// Users left out of a string flag's rollout rule get the configured
+    /// default variant, not the first declared one.
+    #[test]
+    fn users_outside_a_rollout_rule_get_the_configured_default() {
+        let flag = string_flag("banner");
+        let rollout = ServeTarget::rollout(vec![
+            WeightedVariant {
+                variant: vk("a"),
+                weight: 30,
+            },
+            WeightedVariant {
+                variant: vk("b"),
+                weight: 70,
+            },
+        ])
+        .unwrap();
+        let config = FlagEnvConfig {
+            rules: vec![TargetingRule {
+                segments: vec![sk("beta-users")],
+                segment_groups: Vec::new(),
+                serve: rollout,
+                bucket_by: None,
+                description: None,
+            }],
+            ..simple_config("b")
+        };
+        let segment = beta_segment("beta-users");
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new([(sk("beta-users"), &segment.match_expr)]),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let flag_set = FlagSet::from_json(&ruleset.document).unwrap();
+        for i in 0..200 {
+            let context = flaps_eval::EvaluationContext {
+                targeting_key: Some(format!("user-{i}")),
+                attributes: [("tier".to_owned(), serde_json::json!("free"))].into(),
+                ..Default::default()
+            };
+            let resolution = flag_set.evaluate("banner", &context).unwrap();
+            assert_eq!(resolution.variant.as_deref(), Some("b"));
+            assert_eq!(resolution.value, Some("beta".into()));
+        }
+    }
+
     // -------------------------------------------------------------------------
     // 3. Segment inlining: And/Or/Not/Predicate -> flagd targeting
     // -------------------------------------------------------------------------
@@ -479,6 +997,7 @@ mod tests {
                 attribute: "tier".into(),
                 operator: MatchOperator::Equals,
                 values: vec![serde_json::json!("beta")],
+                case_insensitive: false,
             }),
         }
     }
@@ -491,9 +1010,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![sk("beta-users")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let segs = Segments::new([(sk("beta-users"), &seg.match_expr)]);
         let env = ek("prod");
@@ -526,6 +1054,7 @@ mod tests {
                 attribute: "role".into(),
                 operator: MatchOperator::Equals,
                 values: vec![serde_json::json!("admin")],
+                case_insensitive: false,
             }),
         };
         let flag = bool_flag("my-flag");
@@ -533,9 +1062,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![sk("seg1"), sk("seg2")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let segment_lookup = Segments::new([
             (sk("seg1"), &seg1.match_expr),
@@ -561,6 +1099,116 @@ mod tests {
         );
     }
 
+    #[test]
+    fn segment_groups_or_their_alternatives_on_top_of_the_segments() {
+        let predicate = |key: &str, attribute: &str, value: &str| Segment {
+            key: sk(key),
+            name: key.into(),
+            match_expr: SegmentMatch::Predicate(Predicate {
+                attribute: attribute.into(),
+                operator: MatchOperator::Equals,
+                values: vec![serde_json::json!(value)],
+                case_insensitive: false,
+            }),
+        };
+        let defined = [
+            predicate("staff", "role", "staff"),
+            predicate("pro", "plan", "pro"),
+            predicate("fr", "country", "FR"),
+            predicate("mobile", "platform", "mobile"),
+        ];
+        let flag = bool_flag("my-flag");
+        // staff AND (pro OR (fr AND mobile))
+        let config = FlagEnvConfig {
+            rules: vec![TargetingRule {
+                segments: vec![sk("staff")],
+                segment_groups: vec![vec![sk("pro")], vec![sk("fr"), sk("mobile")]],
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            ..simple_config("off")
+        };
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new(defined.iter().map(|s| (s.key.clone(), &s.match_expr))),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let flag_set = FlagSet::from_json(&ruleset.document).unwrap();
+
+        let cases: &[(&[(&str, &str)], &str)] = &[
+            (&[("role", "staff"), ("plan", "pro")], "on"),
+            (
+                &[("role", "staff"), ("country", "FR"), ("platform", "mobile")],
+                "on",
+            ),
+            (
+                &[
+                    ("role", "staff"),
+                    ("plan", "pro"),
+                    ("country", "FR"),
+                    ("platform", "mobile"),
+                ],
+                "on",
+            ),
+            (&[("role", "staff"), ("country", "FR")], "off"),
+            (&[("role", "staff"), ("platform", "mobile")], "off"),
+            (&[("role", "staff")], "off"),
+            (&[("plan", "pro")], "off"),
+            (&[("country", "FR"), ("platform", "mobile")], "off"),
+        ];
+        for (attributes, expected) in cases {
+            let context = flaps_eval::EvaluationContext {
+                attributes: attributes
+                    .iter()
+                    .map(|(key, value)| ((*key).to_owned(), serde_json::json!(value)))
+                    .collect(),
+                ..Default::default()
+            };
+            let resolution = flag_set.evaluate("my-flag", &context).unwrap();
+            assert_eq!(
+                resolution.variant.as_deref(),
+                Some(*expected),
+                "{attributes:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_segment_group_naming_an_unknown_segment_fails() {
+        let flag = bool_flag("my-flag");
+        let config = FlagEnvConfig {
+            rules: vec![TargetingRule {
+                segments: vec![],
+                segment_groups: vec![vec![sk("ghost")]],
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            ..simple_config("off")
+        };
+        let result = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+        );
+        assert!(
+            matches!(result, Err(CompileError::UnknownSegment { ref segment, .. }) if segment == "ghost"),
+            "{result:?}"
+        );
+    }
+
     #[test]
     fn zero_segments_produces_literal_true_condition() {
         let flag = bool_flag("my-flag");
@@ -568,9 +1216,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![], // zero segments -> always match
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let env = ek("prod");
         let result = compile_environment(
@@ -597,95 +1254,739 @@ mod tests {
             attribute: "email".into(),
             operator: MatchOperator::Equals, // expects exactly 1
             values: vec![],                  // got 0
+            case_insensitive: false,
         });
         let flag = bool_flag("my-flag");
         let config = FlagEnvConfig {
             enabled: true,
             rules: vec![TargetingRule {
-                segments: vec![sk("bad")],
+                segments: vec![sk("bad")],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let segs = Segments::new([(sk("bad"), &bad_segment)]);
+        let env = ek("prod");
+        let result = compile_environment(
+            &env,
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &segs,
+            &DomainMetadata::new(),
+            None,
+        );
+        assert!(
+            matches!(result, Err(CompileError::PredicateArity { .. })),
+            "expected PredicateArity, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn in_operator_with_multiple_values() {
+        let seg = SegmentMatch::Predicate(Predicate {
+            attribute: "tier".into(),
+            operator: MatchOperator::In,
+            values: vec![serde_json::json!("beta"), serde_json::json!("alpha")],
+            case_insensitive: false,
+        });
+        let flag = bool_flag("my-flag");
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: vec![sk("tier-check")],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let segs = Segments::new([(sk("tier-check"), &seg)]);
+        let env = ek("prod");
+        let result = compile_environment(
+            &env,
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &segs,
+            &DomainMetadata::new(),
+            None,
+        );
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn contains_operator_flips_args() {
+        // Contains -> In(Literal(v), Var) -- haystack/needle order is inverted
+        let seg = SegmentMatch::Predicate(Predicate {
+            attribute: "email".into(),
+            operator: MatchOperator::Contains,
+            values: vec![serde_json::json!("@example.com")],
+            case_insensitive: false,
+        });
+        let flag = bool_flag("my-flag");
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: vec![sk("email-check")],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let segs = Segments::new([(sk("email-check"), &seg)]);
+        let env = ek("prod");
+        let result = compile_environment(
+            &env,
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &segs,
+            &DomainMetadata::new(),
+            None,
+        );
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    /// Compiles a `ModuloRollout` segment with `values` over `account_id`,
+    /// serving `on` to members.
+    fn modulo_cohort(values: serde_json::Value) -> Result<FlagSet, CompileError> {
+        let seg = SegmentMatch::Predicate(Predicate {
+            attribute: "account_id".into(),
+            operator: MatchOperator::ModuloRollout,
+            values: serde_json::from_value(values).unwrap(),
+            case_insensitive: false,
+        });
+        let flag = bool_flag("cohort-flag");
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: vec![sk("cohort")],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new([(sk("cohort"), &seg)]),
+            &DomainMetadata::new(),
+            None,
+        )?;
+        Ok(FlagSet::from_json(&ruleset.document).unwrap())
+    }
+
+    fn cohort_variant(flag_set: &FlagSet, account_id: serde_json::Value) -> String {
+        let context = flaps_eval::EvaluationContext {
+            attributes: [("account_id".to_owned(), account_id)].into(),
+            ..Default::default()
+        };
+        flag_set
+            .evaluate("cohort-flag", &context)
+            .unwrap()
+            .variant
+            .unwrap()
+    }
+
+    #[test]
+    fn modulo_rollout_selects_ids_by_their_last_digits() {
+        let flag_set = modulo_cohort(serde_json::json!([100, 10])).unwrap();
+        let members: Vec<u64> = (0..10_000_u64)
+            .filter(|id| cohort_variant(&flag_set, serde_json::json!(id)) == "on")
+            .collect();
+        assert_eq!(members.len(), 1_000, "exactly 10% of a contiguous range");
+        assert!(members.iter().all(|id| id % 100 < 10));
+        assert_eq!(
+            cohort_variant(&flag_set, serde_json::json!(4_200_000_007_u64)),
+            "on"
+        );
+    }
+
+    #[test]
+    fn modulo_rollout_fails_closed_on_non_numeric_attributes() {
+        let flag_set = modulo_cohort(serde_json::json!([100, 10])).unwrap();
+        for account_id in [
+            serde_json::json!("5"),
+            serde_json::json!("acct-5"),
+            serde_json::json!(true),
+            serde_json::json!(null),
+            serde_json::json!(5.5),
+            serde_json::json!(-95),
+            serde_json::json!([5]),
+        ] {
+            assert_eq!(
+                cohort_variant(&flag_set, account_id.clone()),
+                "off",
+                "{account_id}"
+            );
+        }
+        // An absent attribute fails closed as well.
+        let absent = flag_set
+            .evaluate("cohort-flag", &flaps_eval::EvaluationContext::default())
+            .unwrap();
+        assert_eq!(absent.variant.as_deref(), Some("off"));
+    }
+
+    #[test]
+    fn modulo_rollout_rejects_an_invalid_modulus_or_threshold() {
+        for values in [
+            serde_json::json!([0, 0]),
+            serde_json::json!([100, 101]),
+            serde_json::json!([100, -1]),
+            serde_json::json!([100, 2.5]),
+            serde_json::json!(["100", 10]),
+        ] {
+            assert!(
+                matches!(
+                    modulo_cohort(values.clone()),
+                    Err(CompileError::InvalidPredicateValue { .. })
+                ),
+                "{values} must be rejected"
+            );
+        }
+        assert!(matches!(
+            modulo_cohort(serde_json::json!([100])),
+            Err(CompileError::PredicateArity { .. })
+        ));
+    }
+
+    /// Compiles a one-predicate segment over `plan` and reports whether it
+    /// matches a context where `plan` is `attribute`, or absent for `None`.
+    fn predicate_matches(
+        operator: MatchOperator,
+        values: &[serde_json::Value],
+        attribute: Option<serde_json::Value>,
+    ) -> bool {
+        plan_matches(
+            Predicate {
+                attribute: "plan".into(),
+                operator,
+                values: values.to_vec(),
+                case_insensitive: false,
+            },
+            attribute,
+        )
+    }
+
+    /// Compiles `predicate`, which tests `plan`, as the only segment of a
+    /// flag and reports whether it matches a context where `plan` is
+    /// `attribute`, or absent for `None`.
+    fn plan_matches(predicate: Predicate, attribute: Option<serde_json::Value>) -> bool {
+        let seg = SegmentMatch::Predicate(predicate);
+        let flag = bool_flag("plan-flag");
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: vec![sk("plan")],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let ruleset = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new([(sk("plan"), &seg)]),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap();
+        let context = flaps_eval::EvaluationContext {
+            attributes: attribute
+                .map(|value| ("plan".to_owned(), value))
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let resolution = FlagSet::from_json(&ruleset.document)
+            .unwrap()
+            .evaluate("plan-flag", &context)
+            .unwrap();
+        resolution.variant.as_deref() == Some("on")
+    }
+
+    #[test]
+    fn equals_any_compares_each_value_loosely() {
+        use serde_json::json;
+
+        let codes = [json!(200), json!(204)];
+        assert!(predicate_matches(
+            MatchOperator::EqualsAny,
+            &codes,
+            Some(json!(204))
+        ));
+        assert!(predicate_matches(
+            MatchOperator::EqualsAny,
+            &codes,
+            Some(json!("204"))
+        ));
+        assert!(predicate_matches(
+            MatchOperator::EqualsAny,
+            &codes,
+            Some(json!(204.0))
+        ));
+        assert!(!predicate_matches(
+            MatchOperator::EqualsAny,
+            &codes,
+            Some(json!(201))
+        ));
+        assert!(!predicate_matches(
+            MatchOperator::NotEqualsAny,
+            &codes,
+            Some(json!(200))
+        ));
+
+        let statuses = [json!("active"), json!("trialing")];
+        assert!(!predicate_matches(
+            MatchOperator::EqualsAny,
+            &statuses,
+            Some(json!("canceled"))
+        ));
+        assert!(predicate_matches(
+            MatchOperator::NotEqualsAny,
+            &statuses,
+            Some(json!("canceled"))
+        ));
+        assert!(!predicate_matches(
+            MatchOperator::EqualsAny,
+            &statuses,
+            None
+        ));
+    }
+
+    #[test]
+    fn equals_any_matches_booleans_against_their_canonical_strings() {
+        use serde_json::json;
+
+        let flags = [json!("true"), json!("yes")];
+        assert!(predicate_matches(
+            MatchOperator::EqualsAny,
+            &flags,
+            Some(json!(true))
+        ));
+        assert!(!predicate_matches(
+            MatchOperator::EqualsAny,
+            &flags,
+            Some(json!(false))
+        ));
+        assert!(!predicate_matches(
+            MatchOperator::NotEqualsAny,
+            &flags,
+            Some(json!(true))
+        ));
+        assert!(predicate_matches(
+            MatchOperator::NotEqualsAny,
+            &flags,
+            Some(json!(false))
+        ));
+        // The other way round: a boolean value matches a string attribute.
+        assert!(predicate_matches(
+            MatchOperator::EqualsAny,
+            &[json!(false)],
+            Some(json!("false"))
+        ));
+        // Only the lowercase form is canonical.
+        assert!(!predicate_matches(
+            MatchOperator::EqualsAny,
+            &[json!("True")],
+            Some(json!(true))
+        ));
+        // `in` keeps comparing types strictly.
+        assert!(!predicate_matches(
+            MatchOperator::In,
+            &flags,
+            Some(json!(true))
+        ));
+    }
+
+    #[test]
+    fn user_id_in_targets_listed_targeting_keys_only() {
+        let seg = SegmentMatch::Predicate(Predicate::user_id_in(["alice", "bob", "carol"]));
+        let flag = bool_flag("beta-flag");
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: vec![sk("testers")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
-        let segs = Segments::new([(sk("bad"), &bad_segment)]);
-        let env = ek("prod");
-        let result = compile_environment(
-            &env,
+        let ruleset = compile_environment(
+            &ek("prod"),
             &[FlagConfig {
                 flag: &flag,
                 config: &config,
             }],
-            &segs,
+            &Segments::new([(sk("testers"), &seg)]),
             &DomainMetadata::new(),
             None,
-        );
-        assert!(
-            matches!(result, Err(CompileError::PredicateArity { .. })),
-            "expected PredicateArity, got {result:?}"
-        );
+        )
+        .unwrap();
+        let flag_set = FlagSet::from_json(&ruleset.document).unwrap();
+        let variant = |targeting_key: Option<&str>| {
+            let context = flaps_eval::EvaluationContext {
+                targeting_key: targeting_key.map(str::to_owned),
+                // A `user_id` attribute is not the user id the operator reads.
+                attributes: [("user_id".to_owned(), serde_json::json!("alice"))].into(),
+                ..Default::default()
+            };
+            flag_set
+                .evaluate("beta-flag", &context)
+                .unwrap()
+                .variant
+                .unwrap()
+        };
+
+        assert_eq!(variant(Some("bob")), "on");
+        assert_eq!(variant(Some("dave")), "off");
+        assert_eq!(variant(None), "off", "anonymous contexts fail closed");
     }
 
     #[test]
-    fn in_operator_with_multiple_values() {
-        let seg = SegmentMatch::Predicate(Predicate {
-            attribute: "tier".into(),
-            operator: MatchOperator::In,
-            values: vec![serde_json::json!("beta"), serde_json::json!("alpha")],
-        });
-        let flag = bool_flag("my-flag");
-        let config = FlagEnvConfig {
-            enabled: true,
-            rules: vec![TargetingRule {
-                segments: vec![sk("tier-check")],
-                serve: ServeTarget::Fixed(vk("on")),
-            }],
-            default_rule: ServeTarget::Fixed(vk("off")),
+    fn null_attribute_matches_only_null_and_negations() {
+        use serde_json::json;
+
+        let cases = [
+            (MatchOperator::Equals, json!(["pro"]), false),
+            (MatchOperator::Equals, json!([null]), true),
+            (MatchOperator::Equals, json!([""]), false),
+            (MatchOperator::Equals, json!([0]), false),
+            (MatchOperator::Equals, json!([false]), false),
+            (MatchOperator::NotEquals, json!(["pro"]), true),
+            (MatchOperator::NotEquals, json!([null]), false),
+            (MatchOperator::In, json!(["pro", "team"]), false),
+            (MatchOperator::NotIn, json!(["pro", "team"]), true),
+            (MatchOperator::StartsWith, json!(["n"]), false),
+            (MatchOperator::EndsWith, json!(["l"]), false),
+            (MatchOperator::Contains, json!(["u"]), false),
+            (MatchOperator::NotContains, json!(["u"]), true),
+            (MatchOperator::Regex, json!([".*"]), false),
+            (MatchOperator::SemVerEq, json!(["1.0.0"]), false),
+            (MatchOperator::SemVerGte, json!(["0.0.0"]), false),
+            (MatchOperator::SemVerCaret, json!(["1.0.0"]), false),
+            (MatchOperator::ModuloRollout, json!([1, 1]), false),
+            (MatchOperator::EqualsAny, json!(["pro", 0]), false),
+            (MatchOperator::EqualsAny, json!(["pro", null]), true),
+            (MatchOperator::NotEqualsAny, json!(["pro", 0]), true),
+            (MatchOperator::NotEqualsAny, json!(["pro", null]), false),
+            (MatchOperator::Exists, json!([]), false),
+            (MatchOperator::NotExists, json!([]), true),
+        ];
+        for (operator, values, expected) in cases {
+            let values = values.as_array().unwrap();
+            assert_eq!(
+                predicate_matches(operator, values, Some(json!(null))),
+                expected,
+                "{operator:?} {values:?} against null"
+            );
+            // Targeting reads an absent attribute as null: the two are
+            // indistinguishable to every operator.
+            assert_eq!(
+                predicate_matches(operator, values, None),
+                expected,
+                "{operator:?} {values:?} against an absent attribute"
+            );
+        }
+    }
+
+    #[test]
+    fn in_compares_numbers_by_value_and_types_strictly() {
+        use serde_json::json;
+
+        let tiers = [json!(1), json!(2), json!(3)];
+        let cases = [
+            (json!(2), true),
+            (json!(2.0), true),
+            (json!(4), false),
+            (json!("2"), false),
+            (json!(true), false),
+        ];
+        for (attribute, expected) in cases {
+            assert_eq!(
+                predicate_matches(MatchOperator::In, &tiers, Some(attribute.clone())),
+                expected,
+                "{attribute} in [1, 2, 3]"
+            );
+            assert_eq!(
+                predicate_matches(MatchOperator::NotIn, &tiers, Some(attribute.clone())),
+                !expected,
+                "{attribute} not in [1, 2, 3]"
+            );
+        }
+        // A mixed list matches each member by its own type only.
+        let mixed = [json!(1), json!("2")];
+        assert!(predicate_matches(MatchOperator::In, &mixed, Some(json!(1))));
+        assert!(!predicate_matches(
+            MatchOperator::In,
+            &mixed,
+            Some(json!(2))
+        ));
+        assert!(!predicate_matches(
+            MatchOperator::In,
+            &mixed,
+            Some(json!("1"))
+        ));
+    }
+
+    #[test]
+    fn exists_matches_any_present_non_null_value() {
+        use serde_json::json;
+
+        let presence = |values: &[serde_json::Value], value: Option<serde_json::Value>| {
+            (
+                predicate_matches(MatchOperator::Exists, values, value.clone()),
+                predicate_matches(MatchOperator::NotExists, values, value),
+            )
         };
-        let segs = Segments::new([(sk("tier-check"), &seg)]);
-        let env = ek("prod");
-        let result = compile_environment(
-            &env,
-            &[FlagConfig {
-                flag: &flag,
-                config: &config,
-            }],
-            &segs,
-            &DomainMetadata::new(),
-            None,
-        );
-        assert!(result.is_ok(), "{result:?}");
+        for value in [
+            json!("+33 6 12 34 56 78"),
+            json!(""),
+            json!(0),
+            json!(false),
+        ] {
+            assert_eq!(presence(&[], Some(value.clone())), (true, false), "{value}");
+        }
+        for value in [None, Some(json!(null))] {
+            assert_eq!(presence(&[], value.clone()), (false, true), "{value:?}");
+        }
+        // The values are ignored, even one naming the attribute's value.
+        assert_eq!(presence(&[json!("x")], Some(json!("y"))), (true, false));
+        assert_eq!(presence(&[json!(null)], None), (false, true));
+    }
+
+    fn predicate_matches_ignoring_case(
+        operator: MatchOperator,
+        value: &str,
+        case_insensitive: bool,
+        attribute: serde_json::Value,
+    ) -> bool {
+        plan_matches(
+            Predicate {
+                attribute: "plan".into(),
+                operator,
+                values: vec![serde_json::json!(value)],
+                case_insensitive,
+            },
+            Some(attribute),
+        )
     }
 
     #[test]
-    fn contains_operator_flips_args() {
-        // Contains -> In(Literal(v), Var) -- haystack/needle order is inverted
+    fn case_insensitive_string_operators_match_mixed_casing() {
+        use serde_json::json;
+
+        let cases = [
+            (MatchOperator::Equals, "fr", json!("FR")),
+            (MatchOperator::Contains, "Example", json!("ann@EXAMPLE.com")),
+            (MatchOperator::StartsWith, "ANN", json!("ann@example.com")),
+            (MatchOperator::EndsWith, ".COM", json!("ann@example.com")),
+        ];
+        for (operator, value, attribute) in cases {
+            assert!(
+                predicate_matches_ignoring_case(operator, value, true, attribute.clone()),
+                "{operator:?} {value:?} ignoring case against {attribute}"
+            );
+            assert!(
+                !predicate_matches_ignoring_case(operator, value, false, attribute.clone()),
+                "{operator:?} {value:?} against {attribute}"
+            );
+        }
+        // The value is a literal, not a pattern, and equality is anchored.
+        assert!(!predicate_matches_ignoring_case(
+            MatchOperator::Equals,
+            "f.",
+            true,
+            json!("FR")
+        ));
+        assert!(!predicate_matches_ignoring_case(
+            MatchOperator::Equals,
+            "fr",
+            true,
+            json!("FRA")
+        ));
+        // Non-string attributes compare as they would without the flag.
+        assert!(predicate_matches_ignoring_case(
+            MatchOperator::Equals,
+            "200",
+            true,
+            json!(200)
+        ));
+        assert!(predicate_matches_ignoring_case(
+            MatchOperator::Contains,
+            "admin",
+            true,
+            json!(["admin", "dev"])
+        ));
+    }
+
+    #[test]
+    fn case_insensitive_is_rejected_on_other_operators() {
         let seg = SegmentMatch::Predicate(Predicate {
-            attribute: "email".into(),
-            operator: MatchOperator::Contains,
-            values: vec![serde_json::json!("@example.com")],
+            case_insensitive: true,
+            ..Predicate::not_contains("email", "test")
         });
-        let flag = bool_flag("my-flag");
+        assert!(matches!(
+            segment_compiler::compile_segment_match(&seg),
+            Err(CompileError::InvalidPredicateValue { .. })
+        ));
+    }
+
+    #[test]
+    fn not_contains_negates_contains_and_matches_non_strings() {
+        use serde_json::json;
+
+        let email = |value: Option<serde_json::Value>| {
+            (
+                predicate_matches(MatchOperator::Contains, &[json!("test")], value.clone()),
+                predicate_matches(MatchOperator::NotContains, &[json!("test")], value),
+            )
+        };
+        assert_eq!(email(Some(json!("qa+test@example.com"))), (true, false));
+        assert_eq!(email(Some(json!("ann@example.com"))), (false, true));
+        // Neither absent nor non-string attributes contain anything.
+        for value in [None, Some(json!(null)), Some(json!(42)), Some(json!(true))] {
+            assert_eq!(email(value.clone()), (false, true), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn regex_matches_strings_and_rejects_invalid_patterns() {
+        use serde_json::json;
+
+        let plan = |pattern: &str, value| {
+            predicate_matches(MatchOperator::Regex, &[json!(pattern)], Some(value))
+        };
+        assert!(plan("^pro", json!("pro-annual")));
+        assert!(plan("annual", json!("pro-annual")));
+        assert!(!plan("^annual", json!("pro-annual")));
+        assert!(!plan("1", json!(1)), "non-string attributes never match");
+
+        let seg = |values: serde_json::Value| {
+            SegmentMatch::Predicate(Predicate {
+                attribute: "plan".into(),
+                operator: MatchOperator::Regex,
+                values: values.as_array().unwrap().clone(),
+                case_insensitive: false,
+            })
+        };
+        let flag = bool_flag("plan-flag");
         let config = FlagEnvConfig {
             enabled: true,
             rules: vec![TargetingRule {
-                segments: vec![sk("email-check")],
+                segments: vec![sk("plan")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
-        let segs = Segments::new([(sk("email-check"), &seg)]);
-        let env = ek("prod");
-        let result = compile_environment(
-            &env,
-            &[FlagConfig {
-                flag: &flag,
-                config: &config,
-            }],
-            &segs,
-            &DomainMetadata::new(),
-            None,
-        );
-        assert!(result.is_ok(), "{result:?}");
+        for values in [json!(["(unclosed"]), json!([1])] {
+            let segment = seg(values.clone());
+            let result = compile_environment(
+                &ek("prod"),
+                &[FlagConfig {
+                    flag: &flag,
+                    config: &config,
+                }],
+                &Segments::new([(sk("plan"), &segment)]),
+                &DomainMetadata::new(),
+                None,
+            );
+            assert!(
+                matches!(result, Err(CompileError::InvalidPredicateValue { .. })),
+                "{values} must be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn inclusive_semver_operators_match_their_bound() {
+        use serde_json::json;
+
+        let cases = [
+            (MatchOperator::SemVerEq, "1.9.9", false),
+            (MatchOperator::SemVerEq, "2.0.0", true),
+            (MatchOperator::SemVerEq, "2.0.1", false),
+            (MatchOperator::SemVerGte, "1.9.9", false),
+            (MatchOperator::SemVerGte, "2.0.0", true),
+            (MatchOperator::SemVerGte, "10.0.0", true),
+            (MatchOperator::SemVerLte, "1.9.9", true),
+            (MatchOperator::SemVerLte, "2.0.0", true),
+            (MatchOperator::SemVerLte, "2.0.1", false),
+        ];
+        for (operator, version, expected) in cases {
+            assert_eq!(
+                predicate_matches(operator, &[json!("2.0.0")], Some(json!(version))),
+                expected,
+                "{version} {operator:?} 2.0.0"
+            );
+        }
     }
 
     #[test]
@@ -694,15 +1995,25 @@ mod tests {
             attribute: "app-version".into(),
             operator: MatchOperator::SemVerGte,
             values: vec![serde_json::json!("2.0.0")],
+            case_insensitive: false,
         });
         let flag = bool_flag("my-flag");
         let config = FlagEnvConfig {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![sk("version-check")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let segs = Segments::new([(sk("version-check"), &seg)]);
         let env = ek("prod");
@@ -730,15 +2041,25 @@ mod tests {
             attribute: "email".into(),
             operator: MatchOperator::Equals,
             values: vec![serde_json::json!({"nested": "object"})],
+            case_insensitive: false,
         });
         let flag = bool_flag("my-flag");
         let config = FlagEnvConfig {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![sk("bad")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let segs = Segments::new([(sk("bad"), &bad_segment)]);
         let env = ek("prod");
@@ -768,11 +2089,13 @@ mod tests {
             attribute: "tier".into(),
             operator: MatchOperator::Equals,
             values: vec![serde_json::json!("beta")],
+            case_insensitive: false,
         });
         let seg_alpha = SegmentMatch::Predicate(Predicate {
             attribute: "tier".into(),
             operator: MatchOperator::Equals,
             values: vec![serde_json::json!("alpha")],
+            case_insensitive: false,
         });
         let flag = string_flag("my-flag");
         let config = FlagEnvConfig {
@@ -780,14 +2103,26 @@ mod tests {
             rules: vec![
                 TargetingRule {
                     segments: vec![sk("beta")],
+                    segment_groups: Vec::new(),
                     serve: ServeTarget::Fixed(vk("b")),
+                    bucket_by: None,
+                    description: None,
                 },
                 TargetingRule {
                     segments: vec![sk("alpha")],
+                    segment_groups: Vec::new(),
                     serve: ServeTarget::Fixed(vk("a")),
+                    bucket_by: None,
+                    description: None,
                 },
             ],
             default_rule: ServeTarget::Fixed(vk("a")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let segs = Segments::new([(sk("beta"), &seg_beta), (sk("alpha"), &seg_alpha)]);
         let env = ek("prod");
@@ -814,6 +2149,86 @@ mod tests {
         );
     }
 
+    /// A segment shared by two rules is inlined into each rule's condition
+    /// and judged against the context of the current evaluation only: the
+    /// compiled ruleset holds no membership state carried from one
+    /// evaluation to the next.
+    #[test]
+    fn segment_shared_by_two_rules_is_judged_per_evaluation() {
+        let beta = beta_segment("beta-users");
+        let mobile = SegmentMatch::Predicate(Predicate {
+            attribute: "device".into(),
+            operator: MatchOperator::Equals,
+            values: vec![serde_json::json!("mobile")],
+            case_insensitive: false,
+        });
+        let flag = string_flag("my-flag");
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![
+                TargetingRule {
+                    segments: vec![sk("beta-users"), sk("mobile")],
+                    segment_groups: Vec::new(),
+                    serve: ServeTarget::Fixed(vk("b")),
+                    bucket_by: None,
+                    description: None,
+                },
+                TargetingRule {
+                    segments: vec![sk("beta-users")],
+                    segment_groups: Vec::new(),
+                    serve: ServeTarget::Fixed(vk("a")),
+                    bucket_by: None,
+                    description: None,
+                },
+            ],
+            default_rule: ServeTarget::Fixed(vk("b")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let segs = Segments::new([
+            (sk("beta-users"), &beta.match_expr),
+            (sk("mobile"), &mobile),
+        ]);
+        let doc = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &segs,
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap()
+        .document;
+        assert_eq!(doc.matches("\"tier\"").count(), 2, "{doc}");
+
+        let flag_set = FlagSet::from_json(&doc).unwrap();
+        let resolve = |attributes: serde_json::Value| {
+            let context = flaps_eval::EvaluationContext {
+                attributes: serde_json::from_value(attributes).unwrap(),
+                ..flaps_eval::EvaluationContext::default()
+            };
+            flag_set
+                .evaluate("my-flag", &context)
+                .unwrap()
+                .variant
+                .unwrap()
+        };
+        let beta_mobile = serde_json::json!({"tier": "beta", "device": "mobile"});
+        let beta_desktop = serde_json::json!({"tier": "beta", "device": "desktop"});
+        let free_mobile = serde_json::json!({"tier": "free", "device": "mobile"});
+        for _ in 0..2 {
+            assert_eq!(resolve(beta_mobile.clone()), "b");
+            assert_eq!(resolve(free_mobile.clone()), "b");
+            assert_eq!(resolve(beta_desktop.clone()), "a");
+        }
+    }
+
     // -------------------------------------------------------------------------
     // 5. Override by environment (snapshot: two envs, different configs)
     // -------------------------------------------------------------------------
@@ -890,6 +2305,44 @@ mod tests {
         assert_eq!(r1.document, r2.document);
     }
 
+    #[test]
+    fn flag_input_order_does_not_change_the_document() {
+        let (alpha, beta) = (bool_flag("alpha"), string_flag("beta"));
+        let (on, b) = (simple_config("on"), simple_config("b"));
+        let compile = |flags: &[FlagConfig<'_>]| {
+            compile_environment(
+                &ek("prod"),
+                flags,
+                &no_segments(),
+                &DomainMetadata::new(),
+                None,
+            )
+            .unwrap()
+        };
+        let forward = compile(&[
+            FlagConfig {
+                flag: &alpha,
+                config: &on,
+            },
+            FlagConfig {
+                flag: &beta,
+                config: &b,
+            },
+        ]);
+        let reversed = compile(&[
+            FlagConfig {
+                flag: &beta,
+                config: &b,
+            },
+            FlagConfig {
+                flag: &alpha,
+                config: &on,
+            },
+        ]);
+        assert_eq!(forward.document, reversed.document);
+        assert_eq!(forward.content_hash, reversed.content_hash);
+    }
+
     // -------------------------------------------------------------------------
     // 7. Version monotone: stable when unchanged, +1 when changed
     // -------------------------------------------------------------------------
@@ -991,9 +2444,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![sk("beta-users")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let config_without_seg = simple_config("off");
 
@@ -1051,6 +2513,12 @@ mod tests {
             enabled: true,
             rules: vec![],
             default_rule: ServeTarget::Fixed(vk("bad")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let env = ek("prod");
         let result = compile_environment(
@@ -1117,11 +2585,13 @@ mod tests {
                 attribute: "tier".into(),
                 operator: MatchOperator::In,
                 values: vec![serde_json::json!("beta"), serde_json::json!("alpha")],
+                case_insensitive: false,
             }),
             SegmentMatch::Not(Box::new(SegmentMatch::Predicate(Predicate {
                 attribute: "blocked".into(),
                 operator: MatchOperator::Equals,
                 values: vec![serde_json::json!(true)],
+                case_insensitive: false,
             }))),
         ]);
         let flag = bool_flag("complex-flag");
@@ -1129,9 +2599,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![sk("complex-seg")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let segs = Segments::new([(sk("complex-seg"), &seg)]);
         let env = ek("prod");
@@ -1160,9 +2639,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![sk("ghost-segment")],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let env = ek("prod");
         let result = compile_environment(
@@ -1188,6 +2676,12 @@ mod tests {
             enabled: true,
             rules: vec![],
             default_rule: ServeTarget::Fixed(VariantKey::new("nonexistent").unwrap()),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let env = ek("prod");
         let result = compile_environment(
@@ -1223,6 +2717,12 @@ mod tests {
                 },
             ])
             .unwrap(),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let env = ek("prod");
         let result = compile_environment(
@@ -1279,6 +2779,112 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_killed_flag_compiles_disabled_with_its_reason() {
+        let mut flag = bool_flag("my-flag");
+        // The entry is reserved: a flag's own one never marks it killed.
+        flag.metadata.insert(
+            flaps_eval::KILL_REASON_METADATA.to_owned(),
+            flaps_domain::metadata::MetadataValue::String("forged".into()),
+        );
+        let compile = |config: &FlagEnvConfig| {
+            let ruleset = compile_environment(
+                &ek("prod"),
+                &[FlagConfig {
+                    flag: &flag,
+                    config,
+                }],
+                &no_segments(),
+                &DomainMetadata::new(),
+                None,
+            )
+            .unwrap();
+            FlagSet::from_json(&ruleset.document)
+                .unwrap()
+                .evaluate("my-flag", &flaps_eval::EvaluationContext::default())
+                .unwrap()
+        };
+
+        // The kill switch overrides `enabled`.
+        let killed = compile(&FlagEnvConfig {
+            kill_reason: Some("incident 42".to_owned()),
+            ..simple_config("on")
+        });
+        assert_eq!(killed.reason, flaps_eval::Reason::Killed);
+        assert_eq!(killed.value, None);
+        assert_eq!(
+            killed.metadata.get(flaps_eval::KILL_REASON_METADATA),
+            Some(&flaps_eval::MetadataValue::String("incident 42".into()))
+        );
+
+        let disabled = compile(&disabled_config("on"));
+        assert_eq!(disabled.reason, flaps_eval::Reason::Disabled);
+        assert_eq!(
+            disabled.metadata.get(flaps_eval::KILL_REASON_METADATA),
+            None
+        );
+    }
+
+    #[test]
+    fn a_min_app_version_compiles_into_the_version_gate() {
+        let mut flag = bool_flag("my-flag");
+        // The entry is reserved: a flag's own one never gates it.
+        flag.metadata.insert(
+            flaps_eval::MIN_APP_VERSION_METADATA.to_owned(),
+            flaps_domain::metadata::MetadataValue::String("99.0.0".into()),
+        );
+        let compile = |config: &FlagEnvConfig| {
+            compile_environment(
+                &ek("prod"),
+                &[FlagConfig {
+                    flag: &flag,
+                    config,
+                }],
+                &no_segments(),
+                &DomainMetadata::new(),
+                None,
+            )
+        };
+        let evaluate = |config: &FlagEnvConfig, app_version: &str| {
+            let ruleset = compile(config).unwrap();
+            let context = flaps_eval::EvaluationContext {
+                attributes: [(
+                    flaps_eval::APP_VERSION_ATTRIBUTE.to_owned(),
+                    app_version.into(),
+                )]
+                .into(),
+                ..flaps_eval::EvaluationContext::default()
+            };
+            FlagSet::from_json(&ruleset.document)
+                .unwrap()
+                .evaluate("my-flag", &context)
+                .unwrap()
+        };
+
+        let gated = FlagEnvConfig {
+            min_app_version: Some("4.2.0".to_owned()),
+            ..simple_config("on")
+        };
+        assert_eq!(
+            evaluate(&gated, "4.1.0").reason,
+            flaps_eval::Reason::VersionGated
+        );
+        assert_eq!(evaluate(&gated, "4.2.0").value, Some(true.into()));
+        assert_eq!(
+            evaluate(&simple_config("on"), "1.0.0").reason,
+            flaps_eval::Reason::Static
+        );
+
+        let invalid = FlagEnvConfig {
+            min_app_version: Some("four".to_owned()),
+            ..simple_config("on")
+        };
+        assert!(matches!(
+            compile(&invalid),
+            Err(CompileError::InvalidMinAppVersion { version, .. }) if version == "four"
+        ));
+    }
+
     #[test]
     fn environment_metadata_is_carried_into_flag_set_metadata() {
         let flag = bool_flag("my-flag");
@@ -1354,4 +2960,220 @@ mod tests {
             Some(&flaps_eval::MetadataValue::String("flagset-owner".into()))
         );
     }
+
+    // -------------------------------------------------------------------------
+    // Structural limits
+    // -------------------------------------------------------------------------
+
+    fn config_with_rules(rules: usize) -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled: true,
+            rules: (0..rules)
+                .map(|_| TargetingRule {
+                    segments: vec![],
+                    segment_groups: Vec::new(),
+                    serve: ServeTarget::Fixed(vk("on")),
+                    bucket_by: None,
+                    description: None,
+                })
+                .collect(),
+            default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    #[test]
+    fn rule_count_over_the_limit_is_rejected() {
+        let flag = bool_flag("my-flag");
+        let config = config_with_rules(limits::DEFAULT_MAX_RULES_PER_CONFIG + 1);
+        let err = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                CompileError::LimitExceeded {
+                    subject,
+                    limit: "max_rules_per_config",
+                    actual,
+                    ..
+                } if subject == "my-flag" && *actual == limits::DEFAULT_MAX_RULES_PER_CONFIG + 1
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn custom_limits_override_the_defaults() {
+        let flag = bool_flag("my-flag");
+        let config = config_with_rules(3);
+        let limits = Limits {
+            max_rules_per_config: 2,
+            ..Limits::default()
+        };
+        let result = compile_environment_with_limits(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &no_segments(),
+            &DomainMetadata::new(),
+            None,
+            &limits,
+        );
+        assert!(matches!(
+            result,
+            Err(CompileError::LimitExceeded {
+                limit: "max_rules_per_config",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn too_many_segments_in_one_rule_is_rejected() {
+        let segments: Vec<Segment> = (0..=limits::DEFAULT_MAX_CONDITIONS_PER_RULE)
+            .map(|i| beta_segment(&format!("seg-{i}")))
+            .collect();
+        let lookup = Segments::new(segments.iter().map(|s| (s.key.clone(), &s.match_expr)));
+        let flag = bool_flag("my-flag");
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: segments.iter().map(|s| s.key.clone()).collect(),
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let result = compile_environment(
+            &ek("prod"),
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &lookup,
+            &DomainMetadata::new(),
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(CompileError::LimitExceeded {
+                limit: "max_conditions_per_rule",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn segment_probe_resolves_true_for_contexts_in_the_segment() {
+        let seg = beta_segment("beta-users");
+        let probe = compile_segment_probe(&seg.key, &seg.match_expr, &Limits::default()).unwrap();
+        let variant = |tier: &str| {
+            let context = flaps_eval::EvaluationContext {
+                attributes: [("tier".to_owned(), serde_json::json!(tier))].into(),
+                ..Default::default()
+            };
+            probe
+                .evaluate(SEGMENT_PROBE_FLAG, &context)
+                .unwrap()
+                .variant
+        };
+        assert_eq!(variant("beta").as_deref(), Some("true"));
+        assert_eq!(variant("stable").as_deref(), Some("false"));
+    }
+
+    #[test]
+    fn segment_probe_enforces_the_segment_depth_limit() {
+        let seg = beta_segment("beta-users");
+        let limits = Limits {
+            max_segment_depth: 0,
+            ..Limits::default()
+        };
+        assert!(matches!(
+            compile_segment_probe(&seg.key, &seg.match_expr, &limits),
+            Err(CompileError::LimitExceeded { .. })
+        ));
+    }
+
+    /// Segments are compiled by one function whether they sit in a flag rule
+    /// or in a preview probe, so an operator cannot behave differently in the
+    /// two: lock that in for a spread of operators, matches and misses alike.
+    #[test]
+    fn segment_probe_and_flag_rule_agree_on_every_operator() {
+        use serde_json::json;
+
+        let cases = [
+            (MatchOperator::EqualsAny, vec![json!("true")], json!(true)),
+            (MatchOperator::EqualsAny, vec![json!(200)], json!("200")),
+            (
+                MatchOperator::NotEqualsAny,
+                vec![json!(false)],
+                json!("false"),
+            ),
+            (MatchOperator::In, vec![json!("200")], json!(200)),
+            (
+                MatchOperator::StartsWith,
+                vec![json!("pro")],
+                json!("pro-annual"),
+            ),
+            (MatchOperator::Contains, vec![json!("ann")], json!("free")),
+            (
+                MatchOperator::SemVerGte,
+                vec![json!("1.2.0")],
+                json!("1.10.0"),
+            ),
+            (
+                MatchOperator::ModuloRollout,
+                vec![json!(100), json!(10)],
+                json!(1205),
+            ),
+        ];
+        for (operator, values, attribute) in cases {
+            let seg = SegmentMatch::Predicate(Predicate {
+                attribute: "plan".into(),
+                operator,
+                values: values.clone(),
+                case_insensitive: false,
+            });
+            let probe = compile_segment_probe(&sk("plan"), &seg, &Limits::default()).unwrap();
+            let context = flaps_eval::EvaluationContext {
+                attributes: [("plan".to_owned(), attribute.clone())].into(),
+                ..Default::default()
+            };
+            let in_probe = probe
+                .evaluate(SEGMENT_PROBE_FLAG, &context)
+                .unwrap()
+                .variant
+                .as_deref()
+                == Some("true");
+            assert_eq!(
+                predicate_matches(operator, &values, Some(attribute.clone())),
+                in_probe,
+                "{operator:?} {values:?} against {attribute}"
+            );
+        }
+    }
 }
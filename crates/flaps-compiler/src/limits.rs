@@ -0,0 +1,239 @@
+//! Structural limits enforced at compile time.
+//!
+//! A pathological flag (thousands of targeting rules, a rule combining
+//! hundreds of segments, a deeply nested segment expression, a huge regular
+//! expression) is expensive to evaluate on every request. [`Limits`] bounds
+//! those dimensions; the compiler rejects anything beyond them with
+//! [`CompileError::LimitExceeded`], so an over-limit definition never reaches
+//! the store.
+//!
+//! The evaluator applies its own, independent depth guard when it parses a
+//! ruleset (see `flaps_eval::MAX_RULE_DEPTH`), so a hand-crafted ruleset that
+//! bypassed the compiler is refused rather than evaluated.
+
+use flaps_domain::segment::{MatchOperator, SegmentMatch};
+
+use crate::error::CompileError;
+
+/// Default ceiling on the number of targeting rules in one
+/// [`FlagEnvConfig`](flaps_domain::FlagEnvConfig).
+pub const DEFAULT_MAX_RULES_PER_CONFIG: usize = 100;
+
+/// Default ceiling on the number of segments (conditions) one targeting rule
+/// combines.
+pub const DEFAULT_MAX_CONDITIONS_PER_RULE: usize = 20;
+
+/// Default ceiling on the nesting depth of a segment match expression.
+///
+/// A lone predicate has depth 1; each `and` / `or` / `not` level adds one.
+/// Kept well under `flaps_eval::MAX_RULE_DEPTH` so that every compilable
+/// flag stays evaluable once wrapped in its targeting `if`.
+pub const DEFAULT_MAX_SEGMENT_DEPTH: usize = 16;
+
+/// Default ceiling on the length, in bytes, of a `regex` predicate's pattern.
+pub const DEFAULT_MAX_PATTERN_LENGTH: usize = 1_024;
+
+/// Configurable structural limits applied by the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of targeting rules in one flag environment config.
+    pub max_rules_per_config: usize,
+    /// Maximum number of segments one targeting rule may reference, its
+    /// segment groups included.
+    pub max_conditions_per_rule: usize,
+    /// Maximum nesting depth of a segment match expression.
+    pub max_segment_depth: usize,
+    /// Maximum length, in bytes, of the pattern of a `regex` predicate.
+    pub max_pattern_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_rules_per_config: DEFAULT_MAX_RULES_PER_CONFIG,
+            max_conditions_per_rule: DEFAULT_MAX_CONDITIONS_PER_RULE,
+            max_segment_depth: DEFAULT_MAX_SEGMENT_DEPTH,
+            max_pattern_length: DEFAULT_MAX_PATTERN_LENGTH,
+        }
+    }
+}
+
+impl Limits {
+    /// Checks a segment match expression against [`Self::max_segment_depth`]
+    /// and [`Self::max_pattern_length`].
+    ///
+    /// Called for every segment a compiled flag references, and directly by
+    /// callers that want to reject an over-limit segment before any flag
+    /// uses it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CompileError::LimitExceeded`] when the expression is nested
+    /// deeper than allowed, or holds a `regex` pattern longer than allowed.
+    pub fn check_segment(&self, segment: &str, expr: &SegmentMatch) -> Result<(), CompileError> {
+        let depth = segment_depth(expr);
+        if depth > self.max_segment_depth {
+            return Err(CompileError::LimitExceeded {
+                subject: segment.to_owned(),
+                limit: "max_segment_depth",
+                max: self.max_segment_depth,
+                actual: depth,
+            });
+        }
+        let length = longest_pattern(expr);
+        if length > self.max_pattern_length {
+            return Err(CompileError::LimitExceeded {
+                subject: segment.to_owned(),
+                limit: "max_pattern_length",
+                max: self.max_pattern_length,
+                actual: length,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the rule count of a flag environment config.
+    pub(crate) fn check_rule_count(&self, flag: &str, rules: usize) -> Result<(), CompileError> {
+        if rules > self.max_rules_per_config {
+            return Err(CompileError::LimitExceeded {
+                subject: flag.to_owned(),
+                limit: "max_rules_per_config",
+                max: self.max_rules_per_config,
+                actual: rules,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the number of segments a single targeting rule combines.
+    pub(crate) fn check_condition_count(
+        &self,
+        flag: &str,
+        conditions: usize,
+    ) -> Result<(), CompileError> {
+        if conditions > self.max_conditions_per_rule {
+            return Err(CompileError::LimitExceeded {
+                subject: flag.to_owned(),
+                limit: "max_conditions_per_rule",
+                max: self.max_conditions_per_rule,
+                actual: conditions,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the nesting depth of a segment match expression (a predicate is 1).
+fn segment_depth(expr: &SegmentMatch) -> usize {
+    match expr {
+        SegmentMatch::And(children) | SegmentMatch::Or(children) => {
+            1 + children.iter().map(segment_depth).max().unwrap_or(0)
+        }
+        SegmentMatch::Not(inner) => 1 + segment_depth(inner),
+        SegmentMatch::Predicate(_) => 1,
+    }
+}
+
+/// Returns the length of the longest `regex` pattern in a segment match
+/// expression, or 0 without one.
+fn longest_pattern(expr: &SegmentMatch) -> usize {
+    match expr {
+        SegmentMatch::And(children) | SegmentMatch::Or(children) => {
+            children.iter().map(longest_pattern).max().unwrap_or(0)
+        }
+        SegmentMatch::Not(inner) => longest_pattern(inner),
+        SegmentMatch::Predicate(p) if p.operator == MatchOperator::Regex => p
+            .values
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .map(str::len)
+            .max()
+            .unwrap_or(0),
+        SegmentMatch::Predicate(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_domain::segment::Predicate;
+
+    use super::*;
+
+    fn predicate() -> SegmentMatch {
+        SegmentMatch::Predicate(Predicate {
+            attribute: "country".to_owned(),
+            operator: MatchOperator::Equals,
+            values: vec![serde_json::json!("FR")],
+            case_insensitive: false,
+        })
+    }
+
+    fn nested_not(depth: usize) -> SegmentMatch {
+        (1..depth).fold(predicate(), |inner, _| SegmentMatch::Not(Box::new(inner)))
+    }
+
+    #[test]
+    fn predicate_has_depth_one() {
+        assert_eq!(segment_depth(&predicate()), 1);
+    }
+
+    #[test]
+    fn depth_counts_the_deepest_branch() {
+        let expr = SegmentMatch::And(vec![predicate(), nested_not(3)]);
+        assert_eq!(segment_depth(&expr), 4);
+    }
+
+    #[test]
+    fn segment_at_the_limit_is_accepted() {
+        let limits = Limits::default();
+        assert!(
+            limits
+                .check_segment("deep", &nested_not(DEFAULT_MAX_SEGMENT_DEPTH))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn segment_past_the_limit_is_rejected() {
+        let limits = Limits::default();
+        let err = limits
+            .check_segment("deep", &nested_not(DEFAULT_MAX_SEGMENT_DEPTH + 1))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            CompileError::LimitExceeded {
+                limit: "max_segment_depth",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn regex_pattern_past_the_limit_is_rejected() {
+        let regex = |pattern: &str| {
+            SegmentMatch::Predicate(Predicate {
+                attribute: "email".to_owned(),
+                operator: MatchOperator::Regex,
+                values: vec![serde_json::json!(pattern)],
+                case_insensitive: false,
+            })
+        };
+        let limits = Limits {
+            max_pattern_length: 8,
+            ..Limits::default()
+        };
+        assert!(limits.check_segment("short", &regex("^a+$")).is_ok());
+        let err = limits
+            .check_segment("long", &SegmentMatch::Not(Box::new(regex("^(a|b|c)+@x$"))))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            CompileError::LimitExceeded {
+                limit: "max_pattern_length",
+                max: 8,
+                actual: 12,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Segment membership: which of a project's segments a context belongs to.
+//!
+//! Flag targeting asks whether a context is in the segments a rule lists;
+//! debugging often needs the inverse question. A [`SegmentMembership`]
+//! compiles every segment of a project on its own, exactly as it would
+//! compile inside a targeting rule (see [`crate::compile_segment_probe`]),
+//! and evaluates them all against one context. It is separate from flag
+//! evaluation: no ruleset is read and no variant is served.
+
+use std::collections::BTreeMap;
+
+use flaps_domain::key::SegmentKey;
+use flaps_domain::segment::SegmentMatch;
+use flaps_eval::{EvaluationContext, EvaluationError, FlagSet};
+
+use crate::{CompileError, Limits, segment_probe_flag};
+
+/// The compiled segments of a project, ready to be matched against contexts.
+#[derive(Debug, Clone)]
+pub struct SegmentMembership {
+    /// The segment keys, sorted.
+    segments: Vec<SegmentKey>,
+    /// One boolean flag per segment, keyed by the segment key.
+    probes: FlagSet,
+}
+
+impl SegmentMembership {
+    /// Compiles `segments`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CompileError::LimitExceeded`] when a segment exceeds
+    /// `limits`, and the predicate errors of [`crate::compile_environment`].
+    pub fn compile<'a>(
+        segments: impl IntoIterator<Item = (&'a SegmentKey, &'a SegmentMatch)>,
+        limits: &Limits,
+    ) -> Result<Self, CompileError> {
+        let mut flags = BTreeMap::new();
+        let mut keys = Vec::new();
+        for (key, segment) in segments {
+            flags.insert(
+                key.as_str().to_owned(),
+                segment_probe_flag(key, segment, limits)?,
+            );
+            keys.push(key.clone());
+        }
+        keys.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        keys.dedup_by(|a, b| a.as_str() == b.as_str());
+        Ok(Self {
+            segments: keys,
+            probes: FlagSet {
+                flags,
+                metadata: flaps_eval::Metadata::new(),
+            },
+        })
+    }
+
+    /// The segments `context` belongs to, sorted by key.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`EvaluationError`] of a segment whose evaluation fails,
+    /// such as one exceeding the evaluation budget.
+    pub fn matching(
+        &self,
+        context: &EvaluationContext,
+    ) -> Result<Vec<SegmentKey>, EvaluationError> {
+        let mut matching = Vec::new();
+        for segment in &self.segments {
+            let resolution = self.probes.evaluate(segment.as_str(), context)?;
+            if resolution.variant.as_deref() == Some("true") {
+                matching.push(segment.clone());
+            }
+        }
+        Ok(matching)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_domain::segment::{MatchOperator, Predicate};
+    use serde_json::json;
+
+    use super::*;
+
+    fn equals(attribute: &str, value: &str) -> SegmentMatch {
+        SegmentMatch::Predicate(Predicate {
+            attribute: attribute.to_owned(),
+            operator: MatchOperator::Equals,
+            values: vec![json!(value)],
+            case_insensitive: false,
+        })
+    }
+
+    /// `beta`, `eu` and `pro`, where `pro` excludes the user `banned`.
+    fn membership() -> SegmentMembership {
+        let segments = [
+            (SegmentKey::new("eu").unwrap(), equals("country", "FR")),
+            (
+                SegmentKey::new("pro").unwrap(),
+                SegmentMatch::And(vec![
+                    equals("plan", "pro"),
+                    SegmentMatch::Not(Box::new(SegmentMatch::Predicate(Predicate::user_id_in([
+                        "banned",
+                    ])))),
+                ]),
+            ),
+            (SegmentKey::new("beta").unwrap(), equals("tier", "beta")),
+        ];
+        SegmentMembership::compile(
+            segments.iter().map(|(key, segment)| (key, segment)),
+            &Limits::default(),
+        )
+        .unwrap()
+    }
+
+    fn matching(targeting_key: &str, attributes: serde_json::Value) -> Vec<String> {
+        let context = EvaluationContext {
+            targeting_key: Some(targeting_key.to_owned()),
+            attributes: serde_json::from_value(attributes).unwrap(),
+            ..EvaluationContext::default()
+        };
+        membership()
+            .matching(&context)
+            .unwrap()
+            .iter()
+            .map(|key| key.as_str().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn a_context_matches_the_segments_it_belongs_to() {
+        assert_eq!(
+            matching("user-1", json!({ "country": "FR", "plan": "pro" })),
+            ["eu", "pro"]
+        );
+    }
+
+    #[test]
+    fn an_excluded_context_matches_none() {
+        assert!(matching("banned", json!({ "country": "US", "plan": "pro" })).is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+//! Load-time diagnostics for constructs that compile but can never match.
+//!
+//! [`compile_environment`](crate::compile_environment) stops at the first
+//! hard error. [`precompile`] instead walks every flag config and every
+//! segment it references and collects a [`CompileReport`] of warnings: a
+//! dangling segment reference, or a predicate whose value type the evaluator
+//! can never match (a semver comparison against a number, a `starts_with`
+//! against a boolean). Such rules do not fail at evaluation time, they
+//! silently evaluate to "no match"; surfacing them at load time turns a
+//! runtime mystery into a diagnostic.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use flaps_domain::segment::{MatchOperator, SegmentMatch};
+
+use crate::input::{FlagConfig, Segments};
+
+/// A construct that compiles (or would be refused) but never matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileWarning {
+    /// A targeting rule references a segment that is not in the segment set.
+    DanglingSegment {
+        /// Key of the flag that owns the rule.
+        flag: String,
+        /// The unresolved segment key.
+        segment: String,
+    },
+
+    /// A predicate compares against a value type its operator never matches.
+    OperatorValueMismatch {
+        /// Key of the segment owning the predicate.
+        segment: String,
+        /// Context attribute the predicate tests.
+        attribute: String,
+        /// The operator, as spelled in the domain model.
+        operator: MatchOperator,
+        /// JSON type of the offending value (`number`, `bool`, ...).
+        value_type: &'static str,
+    },
+}
+
+impl fmt::Display for CompileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DanglingSegment { flag, segment } => {
+                write!(f, "flag `{flag}` references unknown segment `{segment}`")
+            }
+            Self::OperatorValueMismatch {
+                segment,
+                attribute,
+                operator,
+                value_type,
+            } => write!(
+                f,
+                "segment `{segment}`: `{operator:?}` on `{attribute}` compares against a \
+                 {value_type} value and never matches"
+            ),
+        }
+    }
+}
+
+/// The outcome of [`precompile`]: every warning found, in a stable order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompileReport {
+    /// Warnings, flags in input order, each segment reported once.
+    pub warnings: Vec<CompileWarning>,
+}
+
+impl CompileReport {
+    /// Returns `true` when no warning was found.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// Validates flag configs and the segments they reference without compiling.
+///
+/// Never fails: every finding is collected as a [`CompileWarning`]. Hard
+/// errors such as an unknown variant are still the job of
+/// [`compile_environment`](crate::compile_environment).
+#[must_use]
+pub fn precompile(flags: &[FlagConfig<'_>], segments: &Segments<'_>) -> CompileReport {
+    let mut report = CompileReport::default();
+    let mut checked_segments = BTreeSet::new();
+
+    for fc in flags {
+        for rule in &fc.config.rules {
+            for segment_key in rule.referenced_segments() {
+                let Some(expr) = segments.get(segment_key) else {
+                    report.warnings.push(CompileWarning::DanglingSegment {
+                        flag: fc.flag.key.as_str().to_owned(),
+                        segment: segment_key.as_str().to_owned(),
+                    });
+                    continue;
+                };
+                if checked_segments.insert(segment_key.as_str()) {
+                    check_segment(segment_key.as_str(), expr, &mut report);
+                }
+            }
+        }
+    }
+
+    report
+}
+
+/// Collects the operator/value mismatches of one segment expression.
+fn check_segment(segment: &str, expr: &SegmentMatch, report: &mut CompileReport) {
+    match expr {
+        SegmentMatch::And(children) | SegmentMatch::Or(children) => {
+            for child in children {
+                check_segment(segment, child, report);
+            }
+        }
+        SegmentMatch::Not(inner) => check_segment(segment, inner, report),
+        SegmentMatch::Predicate(p) => {
+            if !requires_string(p.operator) {
+                return;
+            }
+            for value in &p.values {
+                if !value.is_string() {
+                    report.warnings.push(CompileWarning::OperatorValueMismatch {
+                        segment: segment.to_owned(),
+                        attribute: p.read_attribute().to_owned(),
+                        operator: p.operator,
+                        value_type: json_type(value),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Operators the evaluator only ever matches against string operands.
+fn requires_string(operator: MatchOperator) -> bool {
+    matches!(
+        operator,
+        MatchOperator::StartsWith
+            | MatchOperator::EndsWith
+            | MatchOperator::Regex
+            | MatchOperator::SemVerEq
+            | MatchOperator::SemVerNeq
+            | MatchOperator::SemVerLt
+            | MatchOperator::SemVerLte
+            | MatchOperator::SemVerGt
+            | MatchOperator::SemVerGte
+            | MatchOperator::SemVerCaret
+            | MatchOperator::SemVerTilde
+            | MatchOperator::UserIdIn
+    )
+}
+
+/// Names the JSON type of a value for diagnostics.
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "bool",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_domain::{
+        flag::{Flag, FlagType},
+        flag_env_config::{FlagEnvConfig, RolloutScope, ServeTarget, TargetingRule},
+        key::{FlagKey, SegmentKey, VariantKey},
+        metadata::Metadata,
+        segment::Predicate,
+        variant::{ValueType, VariantValue, Variants},
+    };
+
+    use super::*;
+
+    fn bool_flag() -> Flag {
+        Flag {
+            key: FlagKey::new("my-flag").unwrap(),
+            name: "my-flag".to_owned(),
+            description: None,
+            flag_type: FlagType::Release,
+            value_type: ValueType::Boolean,
+            variants: Variants::new(
+                ValueType::Boolean,
+                [
+                    (VariantKey::new("on").unwrap(), VariantValue::Bool(true)),
+                    (VariantKey::new("off").unwrap(), VariantValue::Bool(false)),
+                ],
+            )
+            .unwrap(),
+            metadata: Metadata::new(),
+        }
+    }
+
+    fn config_using(segments: &[&str]) -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: segments
+                    .iter()
+                    .map(|s| SegmentKey::new(*s).unwrap())
+                    .collect(),
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(VariantKey::new("on").unwrap()),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(VariantKey::new("off").unwrap()),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    fn predicate(operator: MatchOperator, value: serde_json::Value) -> SegmentMatch {
+        SegmentMatch::Predicate(Predicate {
+            attribute: "app-version".to_owned(),
+            operator,
+            values: vec![value],
+            case_insensitive: false,
+        })
+    }
+
+    #[test]
+    fn dangling_segment_reference_is_reported() {
+        let flag = bool_flag();
+        let config = config_using(&["ghost"]);
+        let report = precompile(
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new([]),
+        );
+        assert_eq!(
+            report.warnings,
+            vec![CompileWarning::DanglingSegment {
+                flag: "my-flag".to_owned(),
+                segment: "ghost".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn semver_against_a_number_is_reported() {
+        let flag = bool_flag();
+        let config = config_using(&["modern"]);
+        let expr = SegmentMatch::Not(Box::new(predicate(
+            MatchOperator::SemVerGte,
+            serde_json::json!(2),
+        )));
+        let report = precompile(
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new([(SegmentKey::new("modern").unwrap(), &expr)]),
+        );
+        assert_eq!(
+            report.warnings,
+            vec![CompileWarning::OperatorValueMismatch {
+                segment: "modern".to_owned(),
+                attribute: "app-version".to_owned(),
+                operator: MatchOperator::SemVerGte,
+                value_type: "number",
+            }]
+        );
+    }
+
+    #[test]
+    fn well_typed_config_is_clean() {
+        let flag = bool_flag();
+        let config = config_using(&["modern", "modern"]);
+        let expr = predicate(MatchOperator::SemVerGte, serde_json::json!("2.0.0"));
+        let report = precompile(
+            &[FlagConfig {
+                flag: &flag,
+                config: &config,
+            }],
+            &Segments::new([(SegmentKey::new("modern").unwrap(), &expr)]),
+        );
+        assert!(report.is_clean(), "{report:?}");
+    }
+}
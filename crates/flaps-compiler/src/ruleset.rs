@@ -20,3 +20,41 @@ pub struct CompiledRuleset {
     /// Monotone version: unchanged when the hash is unchanged.
     pub version: u64,
 }
+
+impl CompiledRuleset {
+    /// Returns the cache key of this ruleset: `<version>-<content_hash>`.
+    ///
+    /// Served as the ETag of the sync and OFREP bulk endpoints. Because the
+    /// version takes part in the key, every version bump produces a new key,
+    /// even when the content reverts to an earlier state: a client holding an
+    /// older key misses and refetches, instead of revalidating its stale
+    /// version number with a `304`.
+    #[must_use]
+    pub fn cache_key(&self) -> String {
+        format!("{}-{}", self.version, self.content_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ruleset(version: u64, content_hash: &str) -> CompiledRuleset {
+        CompiledRuleset {
+            environment: EnvironmentKey::new("prod").unwrap(),
+            document: String::new(),
+            content_hash: content_hash.to_owned(),
+            version,
+        }
+    }
+
+    #[test]
+    fn cache_key_combines_version_and_hash() {
+        assert_eq!(ruleset(3, "abc").cache_key(), "3-abc");
+    }
+
+    #[test]
+    fn version_bump_changes_the_key_for_identical_content() {
+        assert_ne!(ruleset(1, "abc").cache_key(), ruleset(2, "abc").cache_key());
+    }
+}
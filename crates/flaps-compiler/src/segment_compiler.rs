@@ -1,7 +1,7 @@
 //! Translates domain [`SegmentMatch`] expressions into `flaps-eval` [`Rule`]s.
 
 use flaps_domain::segment::{MatchOperator, Predicate, SegmentMatch};
-use flaps_eval::{Literal, Rule, SemVerOp};
+use flaps_eval::{Literal, Pattern, Rule, SemVerOp};
 
 use crate::error::CompileError;
 
@@ -10,6 +10,7 @@ use crate::error::CompileError;
 /// # Errors
 /// - [`CompileError::PredicateArity`] when a predicate has the wrong number of values.
 /// - [`CompileError::NonScalarPredicateValue`] when a scalar operator receives an array or object.
+/// - [`CompileError::InvalidPredicateValue`] when a value is outside the operator's domain.
 pub(crate) fn compile_segment_match(m: &SegmentMatch) -> Result<Rule, CompileError> {
     match m {
         SegmentMatch::And(children) => {
@@ -63,9 +64,18 @@ fn json_array_to_rule_array(
 
 /// Compiles a [`Predicate`] into its flagd [`Rule`] equivalent.
 fn compile_predicate(p: &Predicate) -> Result<Rule, CompileError> {
+    let rule = compile_case_sensitive(p)?;
+    if p.case_insensitive {
+        return ignore_case(p, rule);
+    }
+    Ok(rule)
+}
+
+/// Compiles a [`Predicate`] ignoring its `case_insensitive` flag.
+fn compile_case_sensitive(p: &Predicate) -> Result<Rule, CompileError> {
     let op_name = format!("{:?}", p.operator);
     let attr_rule = Box::new(Rule::Var {
-        path: p.attribute.clone(),
+        path: p.read_attribute().to_owned(),
         default: None,
     });
 
@@ -97,6 +107,16 @@ fn compile_predicate(p: &Predicate) -> Result<Rule, CompileError> {
             let lit = json_to_literal(&p.values[0], &op_name)?;
             Ok(Rule::In(Box::new(Rule::Literal(lit)), attr_rule))
         }
+        // NotContains: Not(In(Literal(v), Var)) -- matches non-strings
+        MatchOperator::NotContains => {
+            require_arity(&p.values, 1, &op_name)?;
+            let lit = json_to_literal(&p.values[0], &op_name)?;
+            Ok(Rule::Not(Box::new(Rule::In(
+                Box::new(Rule::Literal(lit)),
+                attr_rule,
+            ))))
+        }
+        MatchOperator::Regex => compile_regex(p, attr_rule, &op_name),
         // Arity = >= 1 (any list)
         MatchOperator::In => {
             require_arity_min(&p.values, 1, &op_name)?;
@@ -108,6 +128,18 @@ fn compile_predicate(p: &Predicate) -> Result<Rule, CompileError> {
             let arr = json_array_to_rule_array(&p.values, &op_name)?;
             Ok(Rule::Not(Box::new(Rule::In(attr_rule, Box::new(arr)))))
         }
+        // Arity >= 1 scalar values, compared with the loose `==` of `Equals`,
+        // booleans also matching their canonical strings
+        MatchOperator::EqualsAny => {
+            require_arity_min(&p.values, 1, &op_name)?;
+            let rules = equalities(p, &attr_rule, &op_name, false)?;
+            Ok(Rule::Or(rules))
+        }
+        MatchOperator::NotEqualsAny => {
+            require_arity_min(&p.values, 1, &op_name)?;
+            let rules = equalities(p, &attr_rule, &op_name, true)?;
+            Ok(Rule::And(rules))
+        }
         // SemVer operators: arity = exactly 1 scalar string value
         MatchOperator::SemVerEq => compile_semver(p, SemVerOp::Eq, attr_rule, &op_name),
         MatchOperator::SemVerNeq => compile_semver(p, SemVerOp::Neq, attr_rule, &op_name),
@@ -117,7 +149,206 @@ fn compile_predicate(p: &Predicate) -> Result<Rule, CompileError> {
         MatchOperator::SemVerGte => compile_semver(p, SemVerOp::Gte, attr_rule, &op_name),
         MatchOperator::SemVerCaret => compile_semver(p, SemVerOp::CaretMatch, attr_rule, &op_name),
         MatchOperator::SemVerTilde => compile_semver(p, SemVerOp::TildeMatch, attr_rule, &op_name),
+        MatchOperator::ModuloRollout => compile_modulo_rollout(p, &attr_rule, &op_name),
+        // The targeting key must be set: an anonymous context fails closed.
+        MatchOperator::UserIdIn => {
+            require_arity_min(&p.values, 1, &op_name)?;
+            let arr = json_array_to_rule_array(&p.values, &op_name)?;
+            Ok(Rule::And(vec![
+                Rule::StrictNeq(attr_rule.clone(), Box::new(Rule::Literal(Literal::Null))),
+                Rule::In(attr_rule, Box::new(arr)),
+            ]))
+        }
+        // Presence: the values are ignored, and the strict comparison keeps
+        // `""`, `0` and `false` present where the loose `==` would not.
+        MatchOperator::Exists => Ok(Rule::StrictNeq(
+            attr_rule,
+            Box::new(Rule::Literal(Literal::Null)),
+        )),
+        MatchOperator::NotExists => Ok(Rule::StrictEq(
+            attr_rule,
+            Box::new(Rule::Literal(Literal::Null)),
+        )),
+    }
+}
+
+/// Builds one loose comparison of the attribute against each predicate
+/// value: `==`, or `!=` when `negated`.
+///
+/// A boolean and its canonical string form (`true` and `"true"`, `false` and
+/// `"false"`) also compare equal, which the JsonLogic `==` alone does not do:
+/// such a value gets a second, strict comparison against its other form,
+/// joined with `or` (with `and` when `negated`).
+fn equalities(
+    p: &Predicate,
+    attr_rule: &Rule,
+    op_name: &str,
+    negated: bool,
+) -> Result<Vec<Rule>, CompileError> {
+    let attr = || Box::new(attr_rule.clone());
+    p.values
+        .iter()
+        .map(|value| {
+            let lit = Box::new(Rule::Literal(json_to_literal(value, op_name)?));
+            let Some(other_form) = boolean_counterpart(value) else {
+                return Ok(if negated {
+                    Rule::Neq(attr(), lit)
+                } else {
+                    Rule::Eq(attr(), lit)
+                });
+            };
+            let other_form = Box::new(Rule::Literal(other_form));
+            Ok(if negated {
+                Rule::And(vec![
+                    Rule::Neq(attr(), lit),
+                    Rule::StrictNeq(attr(), other_form),
+                ])
+            } else {
+                Rule::Or(vec![
+                    Rule::Eq(attr(), lit),
+                    Rule::StrictEq(attr(), other_form),
+                ])
+            })
+        })
+        .collect()
+}
+
+/// Returns the other form of a boolean predicate value: the canonical string
+/// of a boolean, or the boolean a canonical string names. Only the lowercase
+/// `"true"` and `"false"` are canonical.
+fn boolean_counterpart(value: &serde_json::Value) -> Option<Literal> {
+    match value {
+        serde_json::Value::Bool(b) => Some(Literal::String(b.to_string())),
+        serde_json::Value::String(s) => match s.as_str() {
+            "true" => Some(Literal::Bool(true)),
+            "false" => Some(Literal::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Builds the cohort test of [`MatchOperator::ModuloRollout`]:
+///
+/// ```json
+/// {"and": [
+///     {"!==": [attr, null]},
+///     {"===": [{"+": [attr]}, attr]},
+///     {"===": [{"%": [attr, 1]}, 0]},
+///     {"<=": [0, {"%": [attr, modulus]}, threshold - 1]}
+/// ]}
+/// ```
+///
+/// The first two clauses fail closed on anything but a JSON number (unary
+/// `+` parses strings, so only a number equals its own cast), the third on
+/// fractions, the range on negatives.
+fn compile_modulo_rollout(
+    p: &Predicate,
+    attr_rule: &Rule,
+    op_name: &str,
+) -> Result<Rule, CompileError> {
+    require_arity(&p.values, 2, op_name)?;
+    let invalid = |reason: &str| CompileError::InvalidPredicateValue {
+        operator: op_name.to_owned(),
+        reason: reason.to_owned(),
+    };
+    let (Some(modulus), Some(threshold)) = (p.values[0].as_u64(), p.values[1].as_u64()) else {
+        return Err(invalid(
+            "modulus and threshold must be non-negative integers",
+        ));
+    };
+    if modulus == 0 {
+        return Err(invalid("modulus must be at least 1"));
     }
+    if threshold > modulus {
+        return Err(invalid("threshold must not exceed the modulus"));
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    // Cohort moduli are small; above 2^53 the evaluator's f64 arithmetic is
+    // inexact anyway.
+    let (modulus, threshold) = (modulus as f64, threshold as f64);
+    let number = |n: f64| Box::new(Rule::Literal(Literal::Number(n)));
+    let attr = || Box::new(attr_rule.clone());
+    Ok(Rule::And(vec![
+        Rule::StrictNeq(attr(), Box::new(Rule::Literal(Literal::Null))),
+        Rule::StrictEq(Box::new(Rule::Add(vec![attr_rule.clone()])), attr()),
+        Rule::StrictEq(Box::new(Rule::Mod(attr(), number(1.0))), number(0.0)),
+        Rule::Lte(vec![
+            Rule::Literal(Literal::Number(0.0)),
+            Rule::Mod(attr(), number(modulus)),
+            Rule::Literal(Literal::Number(threshold - 1.0)),
+        ]),
+    ]))
+}
+
+/// Builds a [`Rule::Regex`] node after validating that the single value is
+/// a pattern that compiles: an invalid pattern would never match.
+fn compile_regex(p: &Predicate, attr_rule: Box<Rule>, op_name: &str) -> Result<Rule, CompileError> {
+    require_arity(&p.values, 1, op_name)?;
+    let Some(source) = p.values[0].as_str() else {
+        return Err(CompileError::InvalidPredicateValue {
+            operator: op_name.to_owned(),
+            reason: "the pattern must be a string".to_owned(),
+        });
+    };
+    let Ok(pattern) = Pattern::new(source) else {
+        return Err(CompileError::InvalidPredicateValue {
+            operator: op_name.to_owned(),
+            reason: format!("`{source}` is not a valid regular expression"),
+        });
+    };
+    Ok(Rule::Regex {
+        value: attr_rule,
+        pattern,
+    })
+}
+
+/// Widens the case-sensitive `rule` of a string operator to also match
+/// in any casing.
+///
+/// flagd has no lowercasing operator, so the value is matched as an escaped
+/// literal by a `(?i)` [`Rule::Regex`]. The regex only ever matches strings,
+/// and is joined to `rule` with `or` so that an attribute of another type
+/// (a number loosely equal to `"200"`, an array containing the value) still
+/// compares as it would without the flag.
+fn ignore_case(p: &Predicate, rule: Rule) -> Result<Rule, CompileError> {
+    let op_name = format!("{:?}", p.operator);
+    let (prefix, suffix) = match p.operator {
+        MatchOperator::Equals => (r"\A", r"\z"),
+        MatchOperator::Contains => ("", ""),
+        MatchOperator::StartsWith => (r"\A", ""),
+        MatchOperator::EndsWith => ("", r"\z"),
+        _ => {
+            return Err(CompileError::InvalidPredicateValue {
+                operator: op_name,
+                reason: "only equals, contains, starts_with and ends_with can ignore case"
+                    .to_owned(),
+            });
+        }
+    };
+    // A value of another type has no case to ignore.
+    let Some(value) = p.values[0].as_str() else {
+        return Ok(rule);
+    };
+    // An escaped literal always parses, but may still exceed the regex size
+    // limit.
+    let Ok(pattern) = Pattern::new(format!("(?i){prefix}{}{suffix}", regex::escape(value))) else {
+        return Err(CompileError::InvalidPredicateValue {
+            operator: op_name,
+            reason: "the value is too long to match ignoring case".to_owned(),
+        });
+    };
+    Ok(Rule::Or(vec![
+        rule,
+        Rule::Regex {
+            value: Box::new(Rule::Var {
+                path: p.read_attribute().to_owned(),
+                default: None,
+            }),
+            pattern,
+        },
+    ]))
 }
 
 /// Builds a [`Rule::SemVer`] node after validating the arity.
@@ -0,0 +1,372 @@
+//! Evaluation conformance suite for the Flaps domain model.
+//!
+//! Discovers every `*.json` fixture under `conformance/`, compiles its flag
+//! configuration into a ruleset through the public compiler API, evaluates
+//! each of its cases against the compiled document with `flaps-eval`, and
+//! accumulates all failures before reporting them in a single panic. A
+//! missing or empty fixture directory is itself a failure.
+//!
+//! The fixtures are the evaluation contract: which variant a context gets
+//! for a given configuration. Other SDKs evaluating a compiled ruleset must
+//! reproduce every case; see `conformance/README.md`.
+//!
+//! ## Oracle guarantee
+//!
+//! As in the `flaps-eval` golden corpus, every `expected` value is derived
+//! from an external oracle (the `MurmurHash3` reference algorithm, the
+//! JsonLogic, flagd and SemVer specifications, or the documented semantics
+//! of a Flaps operator), never copied from the output of this engine.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use flaps_compiler::{FlagConfig, Segments, compile_environment};
+use flaps_domain::flag::Flag;
+use flaps_domain::flag_env_config::FlagEnvConfig;
+use flaps_domain::key::{EnvironmentKey, SegmentKey};
+use flaps_domain::metadata::Metadata;
+use flaps_domain::segment::{MatchOperator, SegmentMatch};
+use flaps_eval::{EvaluationContext, FlagSet, Reason};
+use serde::Deserialize;
+
+// ---------------------------------------------------------------------------
+// Data model
+// ---------------------------------------------------------------------------
+
+/// One flag configuration and the contexts evaluated against it, read from a
+/// `conformance/**/*.json` file.
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    name: String,
+    /// Human-readable rationale and oracle derivation; not compared.
+    #[allow(dead_code)]
+    description: String,
+    flag: Flag,
+    config: FlagEnvConfig,
+    /// Configuration of the environment this one inherits from, if any.
+    #[serde(default)]
+    inherits: Option<FlagEnvConfig>,
+    #[serde(default)]
+    segments: HashMap<SegmentKey, SegmentMatch>,
+    cases: Vec<Case>,
+}
+
+/// A single context and the resolution the configuration must give it.
+#[derive(Debug, Deserialize)]
+struct Case {
+    name: String,
+    #[serde(default)]
+    context: ContextCase,
+    expected: ExpectedResolution,
+}
+
+/// The evaluation context section of a case.
+#[derive(Debug, Default, Deserialize)]
+struct ContextCase {
+    #[serde(rename = "targetingKey")]
+    targeting_key: Option<String>,
+    #[serde(default)]
+    attributes: BTreeMap<String, serde_json::Value>,
+}
+
+/// Expected fields of the resolution; `null` variant and value when the
+/// flag serves nothing.
+#[derive(Debug, Deserialize)]
+struct ExpectedResolution {
+    variant: Option<String>,
+    reason: String,
+    value: serde_json::Value,
+}
+
+// ---------------------------------------------------------------------------
+// Discovery
+// ---------------------------------------------------------------------------
+
+/// Recursively collects and parses every `*.json` fixture under `root`,
+/// sorted by path for reproducible reports.
+fn discover_fixtures(root: &Path) -> Vec<(PathBuf, Fixture)> {
+    let mut files = collect_json_files(root);
+    files.sort();
+    files
+        .into_iter()
+        .map(|path| {
+            let raw = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| panic!("cannot read fixture {}: {e}", path.display()));
+            let fixture = serde_json::from_str::<Fixture>(&raw).unwrap_or_else(|e| {
+                panic!(
+                    "fixture {} does not match the Fixture schema: {e}",
+                    path.display()
+                )
+            });
+            (path, fixture)
+        })
+        .collect()
+}
+
+/// Walks `dir` recursively and collects every `*.json` file path.
+fn collect_json_files(dir: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let read = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("cannot read fixture directory {}: {e}", dir.display()));
+    for entry in read {
+        let entry = entry.unwrap_or_else(|e| panic!("fixture directory entry error: {e}"));
+        let path = entry.path();
+        if path.is_dir() {
+            out.extend(collect_json_files(&path));
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            out.push(path);
+        }
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Evaluation
+// ---------------------------------------------------------------------------
+
+/// Maps a [`Reason`] to its OFREP string representation.
+fn reason_to_ofrep(reason: Reason) -> &'static str {
+    match reason {
+        Reason::Static => "STATIC",
+        Reason::TargetingMatch => "TARGETING_MATCH",
+        Reason::Default => "DEFAULT",
+        Reason::Disabled => "DISABLED",
+        Reason::Killed => "KILLED",
+        Reason::VersionGated => "VERSION_GATED",
+    }
+}
+
+/// Validates and compiles a fixture's configuration into an evaluable
+/// [`FlagSet`], as the server does for the environment it belongs to.
+fn compile_fixture(fixture: &Fixture) -> Result<FlagSet, String> {
+    fixture
+        .flag
+        .validate()
+        .map_err(|e| format!("invalid flag: {e}"))?;
+    let config = match &fixture.inherits {
+        Some(base) => fixture.config.inheriting(base),
+        None => fixture.config.clone(),
+    };
+    config
+        .validate_for(&fixture.flag)
+        .map_err(|e| format!("invalid config: {e}"))?;
+    let segments = Segments::new(
+        fixture
+            .segments
+            .iter()
+            .map(|(key, expression)| (key.clone(), expression)),
+    );
+    let environment = EnvironmentKey::new("conformance").expect("valid environment key");
+    let ruleset = compile_environment(
+        &environment,
+        &[FlagConfig {
+            flag: &fixture.flag,
+            config: &config,
+        }],
+        &segments,
+        &Metadata::new(),
+        None,
+    )
+    .map_err(|e| format!("compilation failed: {e}"))?;
+    FlagSet::from_json(&ruleset.document)
+        .map_err(|e| format!("compiled document failed to parse: {e}"))
+}
+
+/// Evaluates one case and returns `Err(message)` when the resolution
+/// diverges from the oracle.
+fn run_case(flag_set: &FlagSet, flag_key: &str, case: &Case) -> Result<(), String> {
+    let context = EvaluationContext {
+        targeting_key: case.context.targeting_key.clone(),
+        attributes: case.context.attributes.clone(),
+        timestamp: 0,
+    };
+    let expected = &case.expected;
+    let resolution = flag_set.evaluate(flag_key, &context).map_err(|e| {
+        format!(
+            "expected variant={:?} reason={} but evaluation failed: {e}",
+            expected.variant, expected.reason,
+        )
+    })?;
+
+    let mut failures = Vec::new();
+    let actual_reason = reason_to_ofrep(resolution.reason);
+    if actual_reason != expected.reason {
+        failures.push(format!(
+            "reason: expected={} actual={actual_reason}",
+            expected.reason,
+        ));
+    }
+    if resolution.variant != expected.variant {
+        failures.push(format!(
+            "variant: expected={:?} actual={:?}",
+            expected.variant, resolution.variant,
+        ));
+    }
+    let actual_value = resolution.value.unwrap_or(serde_json::Value::Null);
+    if actual_value != expected.value {
+        failures.push(format!(
+            "value: expected={} actual={actual_value}",
+            expected.value,
+        ));
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Coverage
+// ---------------------------------------------------------------------------
+
+/// Every [`MatchOperator`], each of which the suite must exercise.
+///
+/// The exhaustive `match` stops an operator from being added without a
+/// thought for this list, and so for its conformance fixture.
+fn all_operators() -> Vec<MatchOperator> {
+    let all = vec![
+        MatchOperator::Equals,
+        MatchOperator::NotEquals,
+        MatchOperator::In,
+        MatchOperator::NotIn,
+        MatchOperator::StartsWith,
+        MatchOperator::EndsWith,
+        MatchOperator::Contains,
+        MatchOperator::NotContains,
+        MatchOperator::Regex,
+        MatchOperator::SemVerEq,
+        MatchOperator::SemVerNeq,
+        MatchOperator::SemVerLt,
+        MatchOperator::SemVerLte,
+        MatchOperator::SemVerGt,
+        MatchOperator::SemVerGte,
+        MatchOperator::SemVerCaret,
+        MatchOperator::SemVerTilde,
+        MatchOperator::ModuloRollout,
+        MatchOperator::UserIdIn,
+        MatchOperator::EqualsAny,
+        MatchOperator::NotEqualsAny,
+        MatchOperator::Exists,
+        MatchOperator::NotExists,
+    ];
+    for operator in &all {
+        match operator {
+            MatchOperator::Equals
+            | MatchOperator::NotEquals
+            | MatchOperator::In
+            | MatchOperator::NotIn
+            | MatchOperator::StartsWith
+            | MatchOperator::EndsWith
+            | MatchOperator::Contains
+            | MatchOperator::NotContains
+            | MatchOperator::Regex
+            | MatchOperator::SemVerEq
+            | MatchOperator::SemVerNeq
+            | MatchOperator::SemVerLt
+            | MatchOperator::SemVerLte
+            | MatchOperator::SemVerGt
+            | MatchOperator::SemVerGte
+            | MatchOperator::SemVerCaret
+            | MatchOperator::SemVerTilde
+            | MatchOperator::ModuloRollout
+            | MatchOperator::UserIdIn
+            | MatchOperator::EqualsAny
+            | MatchOperator::NotEqualsAny
+            | MatchOperator::Exists
+            | MatchOperator::NotExists => {}
+        }
+    }
+    all
+}
+
+/// Collects the operator of every predicate in `expression`.
+fn collect_operators(expression: &SegmentMatch, operators: &mut Vec<MatchOperator>) {
+    match expression {
+        SegmentMatch::And(children) | SegmentMatch::Or(children) => {
+            for child in children {
+                collect_operators(child, operators);
+            }
+        }
+        SegmentMatch::Not(inner) => collect_operators(inner, operators),
+        SegmentMatch::Predicate(predicate) => operators.push(predicate.operator),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Test entry points
+// ---------------------------------------------------------------------------
+
+fn fixtures() -> Vec<(PathBuf, Fixture)> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("conformance");
+    let fixtures = discover_fixtures(&root);
+    assert!(
+        !fixtures.is_empty(),
+        "conformance directory is empty or missing -- a suite with zero fixtures must not pass silently"
+    );
+    fixtures
+}
+
+#[test]
+fn evaluation_conformance() {
+    let fixtures = fixtures();
+    let mut names = BTreeSet::new();
+    let mut failures = Vec::new();
+    let mut case_count = 0;
+
+    for (path, fixture) in &fixtures {
+        let rel = path
+            .strip_prefix(env!("CARGO_MANIFEST_DIR"))
+            .unwrap_or(path);
+        if !names.insert(fixture.name.as_str()) {
+            failures.push(format!("[FAIL] {}: duplicate name", rel.display()));
+        }
+        if fixture.cases.is_empty() {
+            failures.push(format!("[FAIL] {}: no cases", rel.display()));
+        }
+        let flag_set = match compile_fixture(fixture) {
+            Ok(flag_set) => flag_set,
+            Err(msg) => {
+                failures.push(format!("[FAIL] {}: {msg}", rel.display()));
+                continue;
+            }
+        };
+        for case in &fixture.cases {
+            case_count += 1;
+            if let Err(msg) = run_case(&flag_set, fixture.flag.key.as_str(), case) {
+                failures.push(format!(
+                    "[FAIL] {} (\"{}\"): {msg}",
+                    rel.display(),
+                    case.name,
+                ));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} failures across {} fixtures and {case_count} cases:\n{}",
+        failures.len(),
+        fixtures.len(),
+        failures.join("\n"),
+    );
+}
+
+#[test]
+fn every_operator_has_a_fixture() {
+    let mut used = Vec::new();
+    for (_, fixture) in fixtures() {
+        for expression in fixture.segments.values() {
+            collect_operators(expression, &mut used);
+        }
+    }
+    let missing: Vec<_> = all_operators()
+        .into_iter()
+        .filter(|operator| !used.contains(operator))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "operators without a conformance fixture: {missing:?}"
+    );
+}
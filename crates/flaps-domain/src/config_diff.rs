@@ -0,0 +1,701 @@
+//! Field-level differences between two [`FlagEnvConfig`]s.
+//!
+//! The audit log stores whole before/after snapshots; [`diff_configs`] turns
+//! a pair of them into the list of changes a reader cares about ("enabled
+//! true → false", "rollout on:10 → on:50", "rule [beta] added").
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    flag_env_config::{FlagEnvConfig, Rollout, RolloutScope, ServeTarget, TargetingRule},
+    key::SegmentKey,
+};
+
+/// One field-level change between two configurations.
+///
+/// Rules carry no identifier of their own: a rule is identified by its set
+/// of segments, so `segments` is sorted and deduplicated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ConfigChange {
+    /// The flag was switched on or off.
+    Enabled {
+        /// Previous state.
+        from: bool,
+        /// New state.
+        to: bool,
+    },
+    /// The kill switch was engaged, released or given another reason.
+    KillReason {
+        /// Previous reason; `None` when the flag was not killed.
+        from: Option<String>,
+        /// New reason; `None` when the flag is no longer killed.
+        to: Option<String>,
+    },
+    /// The minimum app version was set, changed or cleared.
+    MinAppVersion {
+        /// Previous minimum; `None` when the flag was not version gated.
+        from: Option<String>,
+        /// New minimum; `None` when the flag is no longer version gated.
+        to: Option<String>,
+    },
+    /// The rollout salt changed.
+    Salt {
+        /// Previous salt.
+        from: Option<String>,
+        /// New salt.
+        to: Option<String>,
+    },
+    /// The attribute rollouts bucket on changed.
+    BucketBy {
+        /// Previous attribute; `None` for the targeting key.
+        from: Option<String>,
+        /// New attribute; `None` for the targeting key.
+        to: Option<String>,
+    },
+    /// Whether rule rollouts bucket independently changed.
+    RolloutScope {
+        /// Previous scope.
+        from: RolloutScope,
+        /// New scope.
+        to: RolloutScope,
+    },
+    /// The default serve target changed.
+    DefaultServe {
+        /// Previous target.
+        from: ServeTarget,
+        /// New target.
+        to: ServeTarget,
+    },
+    /// Whether the default is inherited from the base environment changed.
+    InheritDefaultRule {
+        /// Previous setting.
+        from: bool,
+        /// New setting.
+        to: bool,
+    },
+    /// A rule targeting `segments` was added.
+    RuleAdded {
+        /// Segments identifying the rule.
+        segments: Vec<SegmentKey>,
+        /// What the rule serves.
+        serve: ServeTarget,
+    },
+    /// The rule targeting `segments` was removed.
+    RuleRemoved {
+        /// Segments identifying the rule.
+        segments: Vec<SegmentKey>,
+        /// What the rule served.
+        serve: ServeTarget,
+    },
+    /// The rule targeting `segments` now serves something else.
+    RuleServe {
+        /// Segments identifying the rule.
+        segments: Vec<SegmentKey>,
+        /// Previous target.
+        from: ServeTarget,
+        /// New target.
+        to: ServeTarget,
+    },
+    /// The alternative segment groups of the rule targeting `segments`
+    /// changed.
+    RuleSegmentGroups {
+        /// Segments identifying the rule.
+        segments: Vec<SegmentKey>,
+        /// Previous groups.
+        from: Vec<Vec<SegmentKey>>,
+        /// New groups.
+        to: Vec<Vec<SegmentKey>>,
+    },
+    /// The attribute the rollout of the rule targeting `segments` buckets
+    /// on changed.
+    RuleBucketBy {
+        /// Segments identifying the rule.
+        segments: Vec<SegmentKey>,
+        /// Previous attribute; `None` when the rule had none of its own.
+        from: Option<String>,
+        /// New attribute; `None` when the rule has none of its own.
+        to: Option<String>,
+    },
+    /// The rule targeting `segments` was described differently.
+    RuleDescription {
+        /// Segments identifying the rule.
+        segments: Vec<SegmentKey>,
+        /// Previous description; `None` when the rule had none.
+        from: Option<String>,
+        /// New description; `None` when it was removed.
+        to: Option<String>,
+    },
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Enabled { from, to } => write!(f, "enabled: {from} → {to}"),
+            Self::KillReason { from, to } => write!(
+                f,
+                "killed: {} → {}",
+                from.as_deref().unwrap_or("no"),
+                to.as_deref().unwrap_or("no")
+            ),
+            Self::MinAppVersion { from, to } => write!(
+                f,
+                "min app version: {} → {}",
+                from.as_deref().unwrap_or("none"),
+                to.as_deref().unwrap_or("none")
+            ),
+            Self::Salt { from, to } => write!(
+                f,
+                "salt: {} → {}",
+                from.as_deref().unwrap_or("none"),
+                to.as_deref().unwrap_or("none")
+            ),
+            Self::BucketBy { from, to } => write!(
+                f,
+                "bucket by: {} → {}",
+                from.as_deref().unwrap_or("targeting key"),
+                to.as_deref().unwrap_or("targeting key")
+            ),
+            Self::RolloutScope { from, to } => {
+                write!(f, "rollout scope: {} → {}", scope(*from), scope(*to))
+            }
+            Self::DefaultServe { from, to } => {
+                write!(f, "default: {} → {}", Serve(from), Serve(to))
+            }
+            Self::InheritDefaultRule { from, to } => {
+                write!(f, "inherit default: {from} → {to}")
+            }
+            Self::RuleAdded { segments, serve } => {
+                write!(f, "rule {} added: {}", Segments(segments), Serve(serve))
+            }
+            Self::RuleRemoved { segments, serve } => {
+                write!(f, "rule {} removed: {}", Segments(segments), Serve(serve))
+            }
+            Self::RuleServe { segments, from, to } => write!(
+                f,
+                "rule {}: {} → {}",
+                Segments(segments),
+                Serve(from),
+                Serve(to)
+            ),
+            Self::RuleSegmentGroups { segments, from, to } => write!(
+                f,
+                "rule {} segment groups: {} → {}",
+                Segments(segments),
+                Groups(from),
+                Groups(to)
+            ),
+            Self::RuleBucketBy { segments, from, to } => write!(
+                f,
+                "rule {} bucket by: {} → {}",
+                Segments(segments),
+                from.as_deref().unwrap_or("inherited"),
+                to.as_deref().unwrap_or("inherited")
+            ),
+            Self::RuleDescription { segments, from, to } => write!(
+                f,
+                "rule {} description: {} → {}",
+                Segments(segments),
+                Description(from.as_deref()),
+                Description(to.as_deref())
+            ),
+        }
+    }
+}
+
+/// Formats a serve target as `on`, `on:10, off:90` or
+/// `ring(host) on:10, off:90`.
+struct Serve<'a>(&'a ServeTarget);
+
+impl fmt::Display for Serve<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ServeTarget::Fixed(variant) => f.write_str(variant.as_str()),
+            ServeTarget::Rollout(rollout) => write_weights(f, rollout),
+            ServeTarget::ConsistentHash(ring) => {
+                write!(f, "ring({}) ", ring.attribute)?;
+                write_weights(f, &ring.weights)
+            }
+        }
+    }
+}
+
+/// Same rendering as in [`ConfigChange`] messages.
+impl fmt::Display for ServeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Serve(self).fmt(f)
+    }
+}
+
+/// Writes rollout weights as `on:10, off:90`.
+fn write_weights(f: &mut fmt::Formatter<'_>, rollout: &Rollout) -> fmt::Result {
+    for (i, w) in rollout.weights().iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}:{}", w.variant.as_str(), w.weight)?;
+    }
+    Ok(())
+}
+
+/// Names a rollout scope as it is serialized.
+fn scope(scope: RolloutScope) -> &'static str {
+    match scope {
+        RolloutScope::Shared => "shared",
+        RolloutScope::PerRule => "per_rule",
+    }
+}
+
+/// Names the scope as it is serialized: `shared` or `per_rule`.
+impl fmt::Display for RolloutScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(scope(*self))
+    }
+}
+
+/// Formats a rule description quoted, or `none`.
+struct Description<'a>(Option<&'a str>);
+
+impl fmt::Display for Description<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(description) => write!(f, "{description:?}"),
+            None => f.write_str("none"),
+        }
+    }
+}
+
+/// Formats segment groups as `[pro] | [fr, mobile]`, or `none`.
+struct Groups<'a>(&'a [Vec<SegmentKey>]);
+
+impl fmt::Display for Groups<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("none");
+        }
+        for (i, group) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" | ")?;
+            }
+            Segments(group).fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a rule identity as `[beta, eu]`.
+struct Segments<'a>(&'a [SegmentKey]);
+
+impl fmt::Display for Segments<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, segment) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(segment.as_str())?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Lists the changes turning `before` into `after`.
+///
+/// Scalar fields come first (`enabled`, `kill_reason`, `min_app_version`,
+/// `salt`, `bucket_by`, `rollout_scope`, default target,
+/// `inherit_default_rule`), then rule changes ordered by rule identity, a
+/// rule's serve change before its group, bucketing and description changes.
+/// Rules are matched by segment set, not by position, so reordering rules
+/// alone yields no change; when several rules share a segment set they are
+/// paired in order. Identical configs yield an empty list.
+#[must_use]
+pub fn diff_configs(before: &FlagEnvConfig, after: &FlagEnvConfig) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    if before.enabled != after.enabled {
+        changes.push(ConfigChange::Enabled {
+            from: before.enabled,
+            to: after.enabled,
+        });
+    }
+    if before.kill_reason != after.kill_reason {
+        changes.push(ConfigChange::KillReason {
+            from: before.kill_reason.clone(),
+            to: after.kill_reason.clone(),
+        });
+    }
+    if before.min_app_version != after.min_app_version {
+        changes.push(ConfigChange::MinAppVersion {
+            from: before.min_app_version.clone(),
+            to: after.min_app_version.clone(),
+        });
+    }
+    if before.salt != after.salt {
+        changes.push(ConfigChange::Salt {
+            from: before.salt.clone(),
+            to: after.salt.clone(),
+        });
+    }
+    if before.bucket_by != after.bucket_by {
+        changes.push(ConfigChange::BucketBy {
+            from: before.bucket_by.clone(),
+            to: after.bucket_by.clone(),
+        });
+    }
+    if before.rollout_scope != after.rollout_scope {
+        changes.push(ConfigChange::RolloutScope {
+            from: before.rollout_scope,
+            to: after.rollout_scope,
+        });
+    }
+    if before.default_rule != after.default_rule {
+        changes.push(ConfigChange::DefaultServe {
+            from: before.default_rule.clone(),
+            to: after.default_rule.clone(),
+        });
+    }
+    if before.inherit_default_rule != after.inherit_default_rule {
+        changes.push(ConfigChange::InheritDefaultRule {
+            from: before.inherit_default_rule,
+            to: after.inherit_default_rule,
+        });
+    }
+
+    let (old_rules, new_rules) = (rules_by_identity(before), rules_by_identity(after));
+    let mut identities: Vec<&Vec<&str>> = old_rules.keys().chain(new_rules.keys()).collect();
+    identities.sort();
+    identities.dedup();
+    for identity in identities {
+        let (old, new) = (old_rules.get(identity), new_rules.get(identity));
+        let Some((segments, _)) = old.or(new) else {
+            continue;
+        };
+        let old = old.map_or(&[][..], |(_, rules)| rules.as_slice());
+        let new = new.map_or(&[][..], |(_, rules)| rules.as_slice());
+        for i in 0..old.len().max(new.len()) {
+            match (old.get(i), new.get(i)) {
+                (Some(from), Some(to)) => rule_changes(segments, from, to, &mut changes),
+                (Some(rule), None) => changes.push(ConfigChange::RuleRemoved {
+                    segments: segments.clone(),
+                    serve: rule.serve.clone(),
+                }),
+                (None, Some(rule)) => changes.push(ConfigChange::RuleAdded {
+                    segments: segments.clone(),
+                    serve: rule.serve.clone(),
+                }),
+                (None, None) => {}
+            }
+        }
+    }
+    changes
+}
+
+/// Appends the changes between two rules targeting `segments`: serve first,
+/// then groups, bucketing and description.
+fn rule_changes(
+    segments: &[SegmentKey],
+    from: &TargetingRule,
+    to: &TargetingRule,
+    changes: &mut Vec<ConfigChange>,
+) {
+    if from.serve != to.serve {
+        changes.push(ConfigChange::RuleServe {
+            segments: segments.to_vec(),
+            from: from.serve.clone(),
+            to: to.serve.clone(),
+        });
+    }
+    if from.segment_groups != to.segment_groups {
+        changes.push(ConfigChange::RuleSegmentGroups {
+            segments: segments.to_vec(),
+            from: from.segment_groups.clone(),
+            to: to.segment_groups.clone(),
+        });
+    }
+    if from.bucket_by != to.bucket_by {
+        changes.push(ConfigChange::RuleBucketBy {
+            segments: segments.to_vec(),
+            from: from.bucket_by.clone(),
+            to: to.bucket_by.clone(),
+        });
+    }
+    if from.description != to.description {
+        changes.push(ConfigChange::RuleDescription {
+            segments: segments.to_vec(),
+            from: from.description.clone(),
+            to: to.description.clone(),
+        });
+    }
+}
+
+/// Rules grouped by identity: sorted, deduplicated segment names (since
+/// [`SegmentKey`] is not `Ord`) mapped to the matching keys and the rules
+/// in order.
+type RulesByIdentity<'a> = BTreeMap<Vec<&'a str>, (Vec<SegmentKey>, Vec<&'a TargetingRule>)>;
+
+/// Groups `config`'s rules by identity.
+fn rules_by_identity(config: &FlagEnvConfig) -> RulesByIdentity<'_> {
+    let mut grouped = RulesByIdentity::new();
+    for rule in &config.rules {
+        let mut keys: Vec<&SegmentKey> = rule.segments.iter().collect();
+        keys.sort_by_key(|key| key.as_str());
+        keys.dedup();
+        let names = keys.iter().map(|key| key.as_str()).collect();
+        grouped
+            .entry(names)
+            .or_insert_with(|| (keys.into_iter().cloned().collect(), Vec::new()))
+            .1
+            .push(rule);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flag_env_config::WeightedVariant, key::VariantKey};
+
+    fn sk(s: &str) -> SegmentKey {
+        SegmentKey::new(s).unwrap()
+    }
+
+    fn fixed(variant: &str) -> ServeTarget {
+        ServeTarget::Fixed(VariantKey::new(variant).unwrap())
+    }
+
+    /// `on` at `percent`, `off` for the rest.
+    fn rollout(percent: u32) -> ServeTarget {
+        ServeTarget::rollout(vec![
+            WeightedVariant {
+                variant: VariantKey::new("on").unwrap(),
+                weight: percent,
+            },
+            WeightedVariant {
+                variant: VariantKey::new("off").unwrap(),
+                weight: 100 - percent,
+            },
+        ])
+        .unwrap()
+    }
+
+    fn rule(segments: &[&str], serve: ServeTarget) -> TargetingRule {
+        TargetingRule {
+            segments: segments.iter().map(|s| sk(s)).collect(),
+            segment_groups: Vec::new(),
+            serve,
+            bucket_by: None,
+            description: None,
+        }
+    }
+
+    fn config() -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled: true,
+            rules: vec![
+                rule(&["beta"], fixed("on")),
+                rule(&["eu", "mobile"], rollout(10)),
+            ],
+            default_rule: fixed("off"),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    #[test]
+    fn identical_configs_have_no_changes() {
+        assert!(diff_configs(&config(), &config()).is_empty());
+    }
+
+    #[test]
+    fn reordered_rules_and_segments_have_no_changes() {
+        let mut after = config();
+        after.rules.reverse();
+        after.rules[0].segments.reverse();
+        assert!(diff_configs(&config(), &after).is_empty());
+    }
+
+    #[test]
+    fn scalar_fields_are_reported_in_order() {
+        let after = FlagEnvConfig {
+            enabled: false,
+            default_rule: rollout(50),
+            inherit_default_rule: true,
+            salt: Some("checkout".to_owned()),
+            bucket_by: Some("company_id".to_owned()),
+            rollout_scope: RolloutScope::PerRule,
+            kill_reason: Some("incident 42".to_owned()),
+            min_app_version: Some("4.2.0".to_owned()),
+            ..config()
+        };
+        assert_eq!(
+            diff_configs(&config(), &after),
+            vec![
+                ConfigChange::Enabled {
+                    from: true,
+                    to: false
+                },
+                ConfigChange::KillReason {
+                    from: None,
+                    to: Some("incident 42".to_owned())
+                },
+                ConfigChange::MinAppVersion {
+                    from: None,
+                    to: Some("4.2.0".to_owned())
+                },
+                ConfigChange::Salt {
+                    from: None,
+                    to: Some("checkout".to_owned())
+                },
+                ConfigChange::BucketBy {
+                    from: None,
+                    to: Some("company_id".to_owned())
+                },
+                ConfigChange::RolloutScope {
+                    from: RolloutScope::Shared,
+                    to: RolloutScope::PerRule
+                },
+                ConfigChange::DefaultServe {
+                    from: fixed("off"),
+                    to: rollout(50)
+                },
+                ConfigChange::InheritDefaultRule {
+                    from: false,
+                    to: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rule_segment_group_changes_are_reported() {
+        let mut after = config();
+        after.rules[0].segment_groups = vec![vec![sk("pro")], vec![sk("fr"), sk("mobile")]];
+        let changes = diff_configs(&config(), &after);
+        assert_eq!(
+            changes,
+            vec![ConfigChange::RuleSegmentGroups {
+                segments: vec![sk("beta")],
+                from: Vec::new(),
+                to: vec![vec![sk("pro")], vec![sk("fr"), sk("mobile")]],
+            }]
+        );
+        assert_eq!(
+            changes[0].to_string(),
+            "rule [beta] segment groups: none → [pro] | [fr, mobile]"
+        );
+    }
+
+    #[test]
+    fn rule_bucket_by_changes_are_reported() {
+        let mut after = config();
+        after.rules[0].bucket_by = Some("company_id".to_owned());
+        let changes = diff_configs(&config(), &after);
+        assert_eq!(
+            changes,
+            vec![ConfigChange::RuleBucketBy {
+                segments: vec![sk("beta")],
+                from: None,
+                to: Some("company_id".to_owned()),
+            }]
+        );
+        assert_eq!(
+            changes[0].to_string(),
+            "rule [beta] bucket by: inherited → company_id"
+        );
+    }
+
+    #[test]
+    fn rule_additions_removals_and_serve_changes_are_reported() {
+        let mut after = config();
+        after.rules.remove(0);
+        after.rules[0].serve = rollout(50);
+        after.rules.push(rule(&["staff"], fixed("on")));
+        let changes = diff_configs(&config(), &after);
+        assert_eq!(
+            changes,
+            vec![
+                ConfigChange::RuleRemoved {
+                    segments: vec![sk("beta")],
+                    serve: fixed("on"),
+                },
+                ConfigChange::RuleServe {
+                    segments: vec![sk("eu"), sk("mobile")],
+                    from: rollout(10),
+                    to: rollout(50),
+                },
+                ConfigChange::RuleAdded {
+                    segments: vec![sk("staff")],
+                    serve: fixed("on"),
+                },
+            ]
+        );
+        let rendered: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            rendered,
+            [
+                "rule [beta] removed: on",
+                "rule [eu, mobile]: on:10, off:90 → on:50, off:50",
+                "rule [staff] added: on",
+            ]
+        );
+    }
+
+    #[test]
+    fn rule_description_changes_are_reported() {
+        let mut after = config();
+        after.rules[0].description = Some("beta testers first".to_owned());
+        after.rules[0].serve = fixed("off");
+        let changes = diff_configs(&config(), &after);
+        assert_eq!(
+            changes,
+            vec![
+                ConfigChange::RuleServe {
+                    segments: vec![sk("beta")],
+                    from: fixed("on"),
+                    to: fixed("off"),
+                },
+                ConfigChange::RuleDescription {
+                    segments: vec![sk("beta")],
+                    from: None,
+                    to: Some("beta testers first".to_owned()),
+                },
+            ]
+        );
+        assert_eq!(
+            changes[1].to_string(),
+            "rule [beta] description: none → \"beta testers first\""
+        );
+    }
+
+    #[test]
+    fn rules_sharing_segments_are_paired_in_order() {
+        let mut before = config();
+        before.rules.push(rule(&["beta"], fixed("off")));
+        let after = config();
+        assert_eq!(
+            diff_configs(&before, &after),
+            vec![ConfigChange::RuleRemoved {
+                segments: vec![sk("beta")],
+                serve: fixed("off"),
+            }]
+        );
+    }
+
+    #[test]
+    fn changes_serialize_with_a_change_tag() {
+        let change = ConfigChange::Enabled {
+            from: true,
+            to: false,
+        };
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({"change": "enabled", "from": true, "to": false})
+        );
+    }
+}
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    error::DomainError,
     federation::{ExternalRef, ManagedBy},
     key::EnvironmentKey,
     metadata::Metadata,
@@ -26,6 +27,94 @@ pub struct Environment {
     /// at evaluation time (flag entries win over these on collision).
     #[serde(default, skip_serializing_if = "Metadata::is_empty")]
     pub metadata: Metadata,
+    /// Base environment of the same project whose targeting this one falls
+    /// back to for flags it configures without rules (see
+    /// [`FlagEnvConfig::inheriting`](crate::flag_env_config::FlagEnvConfig::inheriting)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<EnvironmentKey>,
+}
+
+impl Environment {
+    /// A locally managed environment with no metadata.
+    #[must_use]
+    pub fn local(key: EnvironmentKey, name: impl Into<String>) -> Self {
+        Self {
+            key,
+            name: name.into(),
+            external_ref: None,
+            managed_by: ManagedBy::Local,
+            metadata: Metadata::new(),
+            inherits_from: None,
+        }
+    }
+
+    /// The standard `dev` environment.
+    #[must_use]
+    pub fn development() -> Self {
+        Self::standard("dev", "Development")
+    }
+
+    /// The standard `staging` environment.
+    #[must_use]
+    pub fn staging() -> Self {
+        Self::standard("staging", "Staging")
+    }
+
+    /// The standard `prod` environment.
+    #[must_use]
+    pub fn production() -> Self {
+        Self::standard("prod", "Production")
+    }
+
+    /// The environments a new project is provisioned with: development,
+    /// staging and production, in promotion order.
+    #[must_use]
+    pub fn defaults() -> [Self; 3] {
+        [Self::development(), Self::staging(), Self::production()]
+    }
+
+    fn standard(key: &str, name: &str) -> Self {
+        Self::local(
+            EnvironmentKey::new(key).expect("standard environment keys are kebab-case"),
+            name,
+        )
+    }
+}
+
+/// Returns the bases `environment` inherits from, nearest first, following
+/// [`Environment::inherits_from`] through `environments`.
+///
+/// The chain stops at a base missing from `environments`, so a dangling
+/// reference inherits nothing.
+///
+/// # Errors
+/// Returns [`DomainError::InheritanceCycle`] when the chain leads back to an
+/// environment already on it, `environment` included.
+pub fn inheritance_chain(
+    environment: &EnvironmentKey,
+    environments: &[Environment],
+) -> Result<Vec<EnvironmentKey>, DomainError> {
+    let base_of = |key: &EnvironmentKey| {
+        environments
+            .iter()
+            .find(|env| &env.key == key)
+            .and_then(|env| env.inherits_from.as_ref())
+    };
+    let mut chain: Vec<EnvironmentKey> = Vec::new();
+    let mut current = environment;
+    while let Some(base) = base_of(current) {
+        if base == environment || chain.contains(base) {
+            return Err(DomainError::InheritanceCycle {
+                environment: environment.as_str().to_owned(),
+            });
+        }
+        if !environments.iter().any(|env| &env.key == base) {
+            break;
+        }
+        chain.push(base.clone());
+        current = base;
+    }
+    Ok(chain)
 }
 
 #[cfg(test)]
@@ -44,6 +133,7 @@ mod tests {
             external_ref: None,
             managed_by: ManagedBy::Local,
             metadata: Metadata::new(),
+            inherits_from: None,
         };
         assert!(env.external_ref.is_none());
     }
@@ -56,10 +146,24 @@ mod tests {
             external_ref: Some(ExternalRef::new("urn:env:staging")),
             managed_by: ManagedBy::Federated,
             metadata: Metadata::new(),
+            inherits_from: None,
         };
         assert_eq!(env.managed_by, ManagedBy::Federated);
     }
 
+    #[test]
+    fn defaults_are_local_dev_staging_prod() {
+        let defaults = Environment::defaults();
+        let keys: Vec<&str> = defaults.iter().map(|env| env.key.as_str()).collect();
+        assert_eq!(keys, ["dev", "staging", "prod"]);
+        assert!(
+            defaults
+                .iter()
+                .all(|env| env.managed_by == ManagedBy::Local)
+        );
+        assert_eq!(Environment::production().name, "Production");
+    }
+
     #[test]
     fn serde_round_trip() {
         let env = Environment {
@@ -68,6 +172,7 @@ mod tests {
             external_ref: None,
             managed_by: ManagedBy::Local,
             metadata: Metadata::new(),
+            inherits_from: None,
         };
         let json = serde_json::to_string(&env).unwrap();
         let back: Environment = serde_json::from_str(&json).unwrap();
@@ -82,6 +187,7 @@ mod tests {
             external_ref: None,
             managed_by: ManagedBy::Local,
             metadata: Metadata::new(),
+            inherits_from: None,
         };
         env.metadata.insert(
             "region".to_owned(),
@@ -120,6 +226,7 @@ mod tests {
             external_ref: None,
             managed_by: ManagedBy::Local,
             metadata: Metadata::new(),
+            inherits_from: None,
         };
         let json = serde_json::to_string(&env).unwrap();
         assert!(
@@ -127,4 +234,63 @@ mod tests {
             "empty metadata must not be serialized: {json}"
         );
     }
+
+    fn inheriting(key: &str, base: Option<&str>) -> Environment {
+        Environment {
+            inherits_from: base.map(|base| EnvironmentKey::new(base).unwrap()),
+            ..Environment::local(EnvironmentKey::new(key).unwrap(), key)
+        }
+    }
+
+    #[test]
+    fn inheritance_chain_lists_bases_nearest_first() {
+        let environments = [
+            inheriting("dev", None),
+            inheriting("staging", Some("dev")),
+            inheriting("prod", Some("staging")),
+        ];
+        let chain = inheritance_chain(&EnvironmentKey::new("prod").unwrap(), &environments);
+        let keys: Vec<String> = chain
+            .unwrap()
+            .iter()
+            .map(|key| key.as_str().to_owned())
+            .collect();
+        assert_eq!(keys, ["staging", "dev"]);
+    }
+
+    #[test]
+    fn inheritance_chain_stops_at_a_missing_base() {
+        let environments = [inheriting("staging", Some("gone"))];
+        let chain = inheritance_chain(&EnvironmentKey::new("staging").unwrap(), &environments);
+        assert!(chain.unwrap().is_empty());
+    }
+
+    #[test]
+    fn inheritance_cycles_are_rejected() {
+        let environments = [
+            inheriting("dev", Some("prod")),
+            inheriting("staging", Some("dev")),
+            inheriting("prod", Some("staging")),
+        ];
+        for key in ["dev", "staging", "prod"] {
+            let chain = inheritance_chain(&EnvironmentKey::new(key).unwrap(), &environments);
+            assert!(
+                matches!(chain, Err(DomainError::InheritanceCycle { .. })),
+                "{key}: {chain:?}"
+            );
+        }
+        let itself = [inheriting("dev", Some("dev"))];
+        assert!(inheritance_chain(&EnvironmentKey::new("dev").unwrap(), &itself).is_err());
+    }
+
+    #[test]
+    fn a_chain_ending_in_a_cycle_is_rejected() {
+        let environments = [
+            inheriting("dev", Some("staging")),
+            inheriting("staging", Some("dev")),
+            inheriting("preview", Some("dev")),
+        ];
+        let chain = inheritance_chain(&EnvironmentKey::new("preview").unwrap(), &environments);
+        assert!(matches!(chain, Err(DomainError::InheritanceCycle { .. })));
+    }
 }
@@ -0,0 +1,174 @@
+//! Replayable change sets bringing an environment's flag configurations to
+//! a desired state.
+//!
+//! An [`EnvironmentPatch`] lists, flag by flag, the configuration an
+//! environment had when the patch was made and the one it should have. It is
+//! produced by diffing two environments ("make prod match staging"), can be
+//! reviewed as a file, and applied later: each [`FlagPatch`] knows whether it
+//! still applies cleanly ([`FlagPatch::status`]), so a patch whose target
+//! moved on in the meantime is refused rather than silently overwriting the
+//! newer configuration, and a patch applied twice changes nothing the second
+//! time.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config_diff::{ConfigChange, diff_configs},
+    flag_env_config::FlagEnvConfig,
+    key::{EnvironmentKey, FlagKey, ProjectKey},
+};
+
+/// Per-flag configuration changes to one environment of a project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentPatch {
+    /// Project of the environment.
+    pub project: ProjectKey,
+    /// Environment the patch applies to.
+    pub environment: EnvironmentKey,
+    /// One entry per flag whose configuration changes, sorted by flag key.
+    pub flags: Vec<FlagPatch>,
+}
+
+impl EnvironmentPatch {
+    /// Whether the patch changes nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+}
+
+/// The change of one flag's configuration in the patched environment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlagPatch {
+    /// The flag whose configuration changes.
+    pub flag: FlagKey,
+    /// Configuration the patch replaces; `None` when the flag had none.
+    pub before: Option<FlagEnvConfig>,
+    /// Configuration the patch installs; `None` removes the flag's.
+    pub after: Option<FlagEnvConfig>,
+}
+
+/// Where an environment stands with respect to a [`FlagPatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchStatus {
+    /// The environment still has the configuration the patch replaces.
+    Pending,
+    /// The environment already has the configuration the patch installs.
+    Applied,
+    /// The environment has neither: it changed since the patch was made.
+    Conflict,
+}
+
+impl FlagPatch {
+    /// The patch turning `before` into `after`, or `None` when they are the
+    /// same.
+    #[must_use]
+    pub fn new(
+        flag: FlagKey,
+        before: Option<FlagEnvConfig>,
+        after: Option<FlagEnvConfig>,
+    ) -> Option<Self> {
+        (before != after).then_some(Self {
+            flag,
+            before,
+            after,
+        })
+    }
+
+    /// Where `current`, the flag's configuration in the environment, stands
+    /// with respect to this patch.
+    #[must_use]
+    pub fn status(&self, current: Option<&FlagEnvConfig>) -> PatchStatus {
+        if current == self.after.as_ref() {
+            PatchStatus::Applied
+        } else if current == self.before.as_ref() {
+            PatchStatus::Pending
+        } else {
+            PatchStatus::Conflict
+        }
+    }
+
+    /// The field-level changes of a configuration that is replaced; empty
+    /// when one is created or removed.
+    #[must_use]
+    pub fn changes(&self) -> Vec<ConfigChange> {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => diff_configs(before, after),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        flag_env_config::{RolloutScope, ServeTarget},
+        key::VariantKey,
+    };
+
+    fn config(enabled: bool) -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled,
+            rules: vec![],
+            default_rule: ServeTarget::Fixed(VariantKey::new("on").unwrap()),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    fn flag() -> FlagKey {
+        FlagKey::new("checkout").unwrap()
+    }
+
+    #[test]
+    fn identical_configs_need_no_patch() {
+        assert_eq!(FlagPatch::new(flag(), None, None), None);
+        assert_eq!(
+            FlagPatch::new(flag(), Some(config(true)), Some(config(true))),
+            None
+        );
+    }
+
+    #[test]
+    fn status_tells_pending_applied_and_conflicting_targets() {
+        let patch = FlagPatch::new(flag(), Some(config(false)), Some(config(true))).unwrap();
+        assert_eq!(patch.status(Some(&config(false))), PatchStatus::Pending);
+        assert_eq!(patch.status(Some(&config(true))), PatchStatus::Applied);
+        assert_eq!(patch.status(None), PatchStatus::Conflict);
+
+        let removal = FlagPatch::new(flag(), Some(config(true)), None).unwrap();
+        assert_eq!(removal.status(None), PatchStatus::Applied);
+        assert!(removal.changes().is_empty());
+    }
+
+    #[test]
+    fn changes_diff_a_replaced_config() {
+        let patch = FlagPatch::new(flag(), Some(config(false)), Some(config(true))).unwrap();
+        assert_eq!(
+            patch.changes(),
+            vec![ConfigChange::Enabled {
+                from: false,
+                to: true
+            }]
+        );
+    }
+
+    #[test]
+    fn a_patch_round_trips_through_json() {
+        let patch = EnvironmentPatch {
+            project: ProjectKey::new("shop").unwrap(),
+            environment: EnvironmentKey::new("prod").unwrap(),
+            flags: vec![FlagPatch::new(flag(), None, Some(config(true))).unwrap()],
+        };
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EnvironmentPatch>(&json).unwrap(),
+            patch
+        );
+    }
+}
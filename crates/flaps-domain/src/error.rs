@@ -25,4 +25,55 @@ pub enum DomainError {
     /// Rollout weights do not sum to a positive total.
     #[error("rollout weights must sum to a positive total")]
     InvalidRollout,
+
+    /// A flag's declared value type differs from the type of its variant set.
+    #[error("flag declares value type `{declared:?}` but its variants are `{variants:?}`")]
+    FlagValueTypeMismatch {
+        /// The flag's declared value type.
+        declared: ValueType,
+        /// The value type of the flag's variant set.
+        variants: ValueType,
+    },
+
+    /// A string flag declares a variant whose value is the empty string.
+    #[error("string variant `{variant}` has an empty value")]
+    EmptyStringVariant {
+        /// The variant with the empty value.
+        variant: String,
+    },
+
+    /// A string flag declares two variants with the same value, which no
+    /// evaluation could tell apart.
+    #[error("string variants `{variant}` and `{other}` have the same value")]
+    DuplicateVariantValue {
+        /// The first of the two variants, by key.
+        variant: String,
+        /// The second of the two variants, by key.
+        other: String,
+    },
+
+    /// A serve target references a variant the flag does not declare.
+    #[error("serve target references undeclared variant `{variant}`")]
+    UnknownVariant {
+        /// The undeclared variant key.
+        variant: String,
+    },
+
+    /// An environment's inheritance chain leads back to an environment
+    /// already on it.
+    #[error("the inheritance chain of environment `{environment}` loops")]
+    InheritanceCycle {
+        /// The environment whose chain was followed.
+        environment: String,
+    },
+
+    /// A [`PinnedValue`](crate::segment::PinnedValue) cannot be read as its
+    /// pinned type.
+    #[error("value `{value}` cannot be read as `{value_type:?}`")]
+    PinnedValueMismatch {
+        /// The pinned type.
+        value_type: ValueType,
+        /// The value, as JSON.
+        value: String,
+    },
 }
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    error::DomainError,
     key::FlagKey,
     metadata::Metadata,
     variant::{ValueType, Variants},
@@ -46,6 +47,48 @@ pub struct Flag {
     pub metadata: Metadata,
 }
 
+impl Flag {
+    /// Checks that the declared `value_type` matches the variant set, and
+    /// that the variants of a string flag are told apart by their values.
+    ///
+    /// [`Variants::new`] guarantees every variant value matches the set's own
+    /// type; this closes the remaining gap, a boolean flag carrying a string
+    /// variant set, which would otherwise evaluate to values of the wrong type.
+    /// A string flag whose variants share a value, or carry an empty one,
+    /// would run an A/B test whose arms an application cannot distinguish.
+    ///
+    /// # Errors
+    /// - [`DomainError::FlagValueTypeMismatch`] when the two types differ.
+    /// - [`DomainError::EmptyStringVariant`] when a string variant is empty.
+    /// - [`DomainError::DuplicateVariantValue`] when two string variants
+    ///   share a value.
+    pub fn validate(&self) -> Result<(), DomainError> {
+        if self.variants.value_type() != self.value_type {
+            return Err(DomainError::FlagValueTypeMismatch {
+                declared: self.value_type,
+                variants: self.variants.value_type(),
+            });
+        }
+        self.variants.check_distinct_strings()
+    }
+
+    /// Reads a serialized flag whose variant values may be bare JSON
+    /// booleans or strings, tagging them into the current shape; see
+    /// [`Variants::from_legacy_value`]. A flag in the current format reads
+    /// unchanged.
+    ///
+    /// # Errors
+    /// Returns the deserialization error when `json` is not a flag once its
+    /// variant values are tagged.
+    pub fn from_legacy_json(json: &str) -> Result<Self, serde_json::Error> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        if let Some(variants) = value.get_mut("variants") {
+            crate::variant::upgrade_legacy_values(variants);
+        }
+        serde_json::from_value(value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,6 +120,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn validate_accepts_matching_value_type() {
+        assert!(make_flag().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_boolean_flag_with_string_variants() {
+        let mut flag = make_flag();
+        flag.variants = Variants::new(
+            ValueType::String,
+            [(
+                VariantKey::new("on").unwrap(),
+                VariantValue::String("yes".into()),
+            )],
+        )
+        .unwrap();
+        assert!(matches!(
+            flag.validate(),
+            Err(DomainError::FlagValueTypeMismatch {
+                declared: ValueType::Boolean,
+                variants: ValueType::String,
+            })
+        ));
+    }
+
+    fn string_flag(values: &[(&str, &str)]) -> Flag {
+        let variants = Variants::new(
+            ValueType::String,
+            values.iter().map(|(key, value)| {
+                (
+                    VariantKey::new(*key).unwrap(),
+                    VariantValue::String((*value).to_owned()),
+                )
+            }),
+        )
+        .unwrap();
+        Flag {
+            value_type: ValueType::String,
+            variants,
+            ..make_flag()
+        }
+    }
+
+    #[test]
+    fn validate_accepts_distinct_string_variants() {
+        assert!(
+            string_flag(&[("blue", "#00f"), ("red", "#f00")])
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn validate_rejects_empty_and_duplicate_string_variants() {
+        assert!(matches!(
+            string_flag(&[("blue", "#00f"), ("none", "")]).validate(),
+            Err(DomainError::EmptyStringVariant { variant }) if variant == "none"
+        ));
+        assert!(matches!(
+            string_flag(&[("red", "#f00"), ("blue", "#00f"), ("crimson", "#f00")]).validate(),
+            Err(DomainError::DuplicateVariantValue { variant, other })
+                if variant == "crimson" && other == "red"
+        ));
+    }
+
     #[test]
     fn full_construction() {
         let flag = make_flag();
@@ -172,4 +280,32 @@ mod tests {
             "empty metadata must not be serialized: {json}"
         );
     }
+
+    #[test]
+    fn legacy_flag_is_upgraded_on_read() {
+        let json = serde_json::json!({
+            "key": "my-flag",
+            "name": "My Flag",
+            "description": "A test flag",
+            "flag_type": "release",
+            "value_type": "boolean",
+            "variants": { "value_type": "boolean", "entries": { "on": true, "off": false } }
+        })
+        .to_string();
+        let flag = Flag::from_legacy_json(&json).unwrap();
+        assert_eq!(flag, make_flag());
+        assert!(
+            serde_json::to_string(&flag)
+                .unwrap()
+                .contains(r#"{"bool":true}"#),
+            "an upgraded flag is written in the current format"
+        );
+    }
+
+    #[test]
+    fn current_flag_survives_the_legacy_reader() {
+        let flag = make_flag();
+        let json = serde_json::to_string(&flag).unwrap();
+        assert_eq!(Flag::from_legacy_json(&json).unwrap(), flag);
+    }
 }
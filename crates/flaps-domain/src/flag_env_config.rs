@@ -1,12 +1,20 @@
 //! Per-environment flag configuration: targeting rules and rollout weights.
 
+use std::collections::BTreeSet;
+
 use serde::{Deserialize, Serialize};
 
 use crate::{
     error::DomainError,
+    flag::Flag,
     key::{SegmentKey, VariantKey},
+    segment::Segment,
 };
 
+/// Context attribute holding the app version compared with
+/// [`FlagEnvConfig::min_app_version`].
+pub const APP_VERSION_ATTRIBUTE: &str = "app_version";
+
 /// A variant paired with a non-negative integer weight for rollout distribution.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WeightedVariant {
@@ -51,6 +59,24 @@ impl From<Rollout> for Vec<WeightedVariant> {
     }
 }
 
+/// A rollout allocated on a consistent-hash ring keyed by a context attribute.
+///
+/// Meant for infrastructure flags rolled out by host or pod rather than by
+/// user. Like a plain [`Rollout`], each context is placed on its own, so
+/// scaling the fleet never moves the hosts already running. Unlike it, a
+/// weight change only moves hosts onto or off the variants whose weight
+/// changed: ramping `on` from 10 to 20 turns hosts on and never trades one
+/// `on` host for another. Shares follow the weights approximately rather
+/// than exactly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashRingRollout {
+    /// Context attribute whose value places a context on the ring, e.g.
+    /// `host` or `pod`. Contexts without it share one position.
+    pub attribute: String,
+    /// The weighted variants.
+    pub weights: Rollout,
+}
+
 /// Determines which variant to serve when a rule matches.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -61,6 +87,10 @@ pub enum ServeTarget {
     ///
     /// Use [`ServeTarget::rollout`] to construct a validated rollout.
     Rollout(Rollout),
+    /// Distribute contexts across variants on a consistent-hash ring.
+    ///
+    /// Use [`ServeTarget::consistent_hash`] to construct a validated ring.
+    ConsistentHash(HashRingRollout),
 }
 
 impl ServeTarget {
@@ -71,16 +101,102 @@ impl ServeTarget {
     pub fn rollout(weights: Vec<WeightedVariant>) -> Result<Self, DomainError> {
         Rollout::try_from(weights).map(Self::Rollout)
     }
+
+    /// Constructs a `ConsistentHash` target bucketing on `attribute`,
+    /// validating that the total weight is positive.
+    ///
+    /// # Errors
+    /// Returns [`DomainError::InvalidRollout`] when the sum of weights is zero.
+    pub fn consistent_hash(
+        attribute: impl Into<String>,
+        weights: Vec<WeightedVariant>,
+    ) -> Result<Self, DomainError> {
+        Ok(Self::ConsistentHash(HashRingRollout {
+            attribute: attribute.into(),
+            weights: Rollout::try_from(weights)?,
+        }))
+    }
+
+    /// Returns every variant key this target may serve.
+    fn variants(&self) -> Vec<&VariantKey> {
+        match self {
+            Self::Fixed(variant) => vec![variant],
+            Self::Rollout(rollout)
+            | Self::ConsistentHash(HashRingRollout {
+                weights: rollout, ..
+            }) => rollout.weights().iter().map(|w| &w.variant).collect(),
+        }
+    }
 }
 
 /// A targeting rule: the flag is served via `serve` when the evaluation context
-/// belongs to **all** segments listed in `segments`.
+/// belongs to **all** segments listed in `segments` and, when the rule has
+/// `segment_groups`, to all segments of at least one group.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TargetingRule {
     /// The segments that must all match for this rule to fire.
     pub segments: Vec<SegmentKey>,
+    /// Alternatives, one of which must also match for this rule to fire: a
+    /// group matches when all its segments do.
+    ///
+    /// `(pro) OR (fr AND mobile)` is two groups, `[[pro], [fr, mobile]]`,
+    /// where it would otherwise take two rules. A rule without groups
+    /// depends on `segments` alone; an empty group matches every context.
+    /// Omitted from JSON when empty, so rules without groups keep their
+    /// shape.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segment_groups: Vec<Vec<SegmentKey>>,
     /// How to serve the flag when this rule fires.
     pub serve: ServeTarget,
+    /// Context attribute this rule's rollout buckets on instead of the
+    /// configuration's [`FlagEnvConfig::bucket_by`]; see there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bucket_by: Option<String>,
+    /// Why the rule exists, in words ("beta testers get the new checkout").
+    ///
+    /// Documentation only: it never changes evaluation, and it is omitted
+    /// from JSON when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl TargetingRule {
+    /// Every segment the rule lists, in [`Self::segments`] then in each of
+    /// its [`Self::segment_groups`], duplicates included.
+    pub fn referenced_segments(&self) -> impl Iterator<Item = &SegmentKey> {
+        self.segments
+            .iter()
+            .chain(self.segment_groups.iter().flatten())
+    }
+}
+
+/// How the rollouts of one configuration share their buckets.
+///
+/// Every rollout hashes a bucketing value into `[0, 100)`. When rule rollouts
+/// and the default rollout hash the same value, their gates are correlated: a
+/// user just outside a 50% rule rollout also lands just outside a 50% default
+/// rollout, so the users a rule leaves out are the ones the default leaves
+/// out too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RolloutScope {
+    /// Every rollout of the configuration buckets on the same value, so a
+    /// user holds the same position in each of them. This is how
+    /// configurations created before scopes existed behave.
+    #[default]
+    Shared,
+    /// Each rule's rollout buckets in a namespace of its own, derived from
+    /// the rule's segments, so rule gates are independent of one another and
+    /// of the default rollout. The default rollout keeps the shared value.
+    PerRule,
+}
+
+impl RolloutScope {
+    /// Whether this is the [`RolloutScope::Shared`] default.
+    #[must_use]
+    pub fn is_shared(&self) -> bool {
+        *self == Self::Shared
+    }
 }
 
 /// Per-environment flag configuration.
@@ -95,17 +211,580 @@ pub struct FlagEnvConfig {
     pub rules: Vec<TargetingRule>,
     /// Fallback serve target applied when no rule matches.
     pub default_rule: ServeTarget,
+    /// Whether an inheriting environment serves its base's default instead
+    /// of [`Self::default_rule`].
+    ///
+    /// Off by default, so a default written for this environment is always
+    /// served. When set, `default_rule` is only served if no base configures
+    /// the flag. See [`Self::inheriting`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inherit_default_rule: bool,
+    /// Salt mixed into the bucketing key of this config's rollouts.
+    ///
+    /// Without a salt, rollouts bucket on the flag key followed by the
+    /// targeting key, so each flag allocates users on its own hash. With a
+    /// salt, the salt takes the place of the flag key: flags sharing a salt
+    /// (and the same rollout weights) put every user in the same bucket,
+    /// while distinct salts allocate independently. Changing the salt of a
+    /// live rollout reshuffles its users.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// Context attribute rollouts bucket on instead of the targeting key,
+    /// e.g. `company_id`.
+    ///
+    /// Every context sharing the attribute's value lands in the same bucket,
+    /// so a rollout reaches a whole organization or device at once. A
+    /// context without the attribute buckets on its targeting key. A rule
+    /// may set its own ([`TargetingRule::bucket_by`]); consistent-hash
+    /// rollouts name their attribute themselves and ignore both. Changing
+    /// it reshuffles a live rollout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bucket_by: Option<String>,
+    /// Whether rule rollouts bucket independently of the default rollout.
+    ///
+    /// Switching a live configuration to [`RolloutScope::PerRule`]
+    /// reshuffles the users of its rule rollouts; the default rollout keeps
+    /// its users.
+    #[serde(default, skip_serializing_if = "RolloutScope::is_shared")]
+    pub rollout_scope: RolloutScope,
+    /// Why the flag was killed, when its kill switch is engaged.
+    ///
+    /// A killed flag serves like a disabled one whatever [`Self::enabled`]
+    /// says, but its evaluations report the `KILLED` reason, with this
+    /// reason in their metadata, instead of `DISABLED`: an emergency stop
+    /// calls for a different follow-up than a flag that is simply off.
+    /// Clearing it releases the kill switch and restores `enabled`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kill_reason: Option<String>,
+    /// Oldest app version this flag is served to, as a semantic version.
+    ///
+    /// Evaluations whose `app_version` context attribute is older serve
+    /// like a disabled flag, before any rule is evaluated, and report the
+    /// `VERSION_GATED` reason: a feature shipped in a mobile release stays
+    /// off for the installs that predate it. An evaluation without a
+    /// parseable [`APP_VERSION_ATTRIBUTE`] is gated too, since clients too
+    /// old to send one are also too old for the feature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_app_version: Option<String>,
+}
+
+impl FlagEnvConfig {
+    /// Checks this configuration against the flag it belongs to.
+    ///
+    /// Every serve target (each rule's and the default) may only name
+    /// variants the flag declares, so the served value always has the
+    /// flag's declared type.
+    ///
+    /// # Errors
+    /// - [`DomainError::FlagValueTypeMismatch`] when the flag itself is
+    ///   type-incoherent (see [`Flag::validate`]).
+    /// - [`DomainError::UnknownVariant`] when a serve target names a variant
+    ///   the flag does not declare.
+    pub fn validate_for(&self, flag: &Flag) -> Result<(), DomainError> {
+        flag.validate()?;
+        let targets = self
+            .rules
+            .iter()
+            .map(|rule| &rule.serve)
+            .chain(std::iter::once(&self.default_rule));
+        for target in targets {
+            for variant in target.variants() {
+                if !flag.variants.contains(variant) {
+                    return Err(DomainError::UnknownVariant {
+                        variant: variant.as_str().to_owned(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the kill switch is engaged (see [`Self::kill_reason`]).
+    #[must_use]
+    pub fn is_killed(&self) -> bool {
+        self.kill_reason.is_some()
+    }
+
+    /// The configuration an environment inheriting from `base` serves.
+    ///
+    /// A configuration with rules of its own keeps its targeting. One
+    /// without takes the rules, salt, bucketing attribute and rollout scope
+    /// of `base`. The default is taken from `base` only when
+    /// [`Self::inherit_default_rule`] is set; otherwise this configuration's
+    /// own [`Self::default_rule`] is kept. [`Self::enabled`],
+    /// [`Self::kill_reason`] and [`Self::min_app_version`] always stay this
+    /// environment's.
+    #[must_use]
+    pub fn inheriting(&self, base: &Self) -> Self {
+        let mut effective = if self.rules.is_empty() {
+            Self {
+                enabled: self.enabled,
+                default_rule: self.default_rule.clone(),
+                inherit_default_rule: false,
+                kill_reason: self.kill_reason.clone(),
+                min_app_version: self.min_app_version.clone(),
+                ..base.clone()
+            }
+        } else {
+            self.clone()
+        };
+        if self.inherit_default_rule {
+            effective.default_rule = base.default_rule.clone();
+            effective.inherit_default_rule = base.inherit_default_rule;
+        }
+        effective
+    }
+
+    /// Returns the name of every context attribute evaluating this
+    /// configuration may read: the predicates of the segments its rules
+    /// reference, looked up in `segments`, the attribute of each
+    /// consistent-hash rollout, the bucketing attribute of each other
+    /// rollout, and [`APP_VERSION_ATTRIBUTE`] when the configuration is
+    /// version gated.
+    ///
+    /// Segment references themselves are not attributes. A segment listed by
+    /// several rules is walked once; one missing from `segments` contributes
+    /// nothing, as it matches nothing once compiled. The targeting key that
+    /// plain rollouts bucket on is not an attribute either.
+    #[must_use]
+    pub fn referenced_attributes(&self, segments: &[Segment]) -> BTreeSet<String> {
+        let mut attributes = BTreeSet::new();
+        let mut walked: BTreeSet<&str> = BTreeSet::new();
+        for key in self
+            .rules
+            .iter()
+            .flat_map(TargetingRule::referenced_segments)
+        {
+            if !walked.insert(key.as_str()) {
+                continue;
+            }
+            if let Some(segment) = segments.iter().find(|segment| &segment.key == key) {
+                segment.match_expr.collect_attributes(&mut attributes);
+            }
+        }
+        let targets = self
+            .rules
+            .iter()
+            .map(|rule| (&rule.serve, self.bucket_by_of(rule)))
+            .chain(std::iter::once((
+                &self.default_rule,
+                self.bucket_by.as_deref(),
+            )));
+        for (target, bucket_by) in targets {
+            match (target, bucket_by) {
+                (ServeTarget::ConsistentHash(ring), _) => {
+                    attributes.insert(ring.attribute.clone());
+                }
+                (ServeTarget::Rollout(_), Some(attribute)) => {
+                    attributes.insert(attribute.to_owned());
+                }
+                _ => {}
+            }
+        }
+        if self.min_app_version.is_some() {
+            attributes.insert(APP_VERSION_ATTRIBUTE.to_owned());
+        }
+        attributes
+    }
+
+    /// The attribute `rule`'s rollout buckets on: its own
+    /// [`TargetingRule::bucket_by`], else the configuration's, else `None`
+    /// for the targeting key.
+    #[must_use]
+    pub fn bucket_by_of<'a>(&'a self, rule: &'a TargetingRule) -> Option<&'a str> {
+        rule.bucket_by.as_deref().or(self.bucket_by.as_deref())
+    }
+
+    /// Whether any rule lists `segment`, in its segments or in a group.
+    #[must_use]
+    pub fn references_segment(&self, segment: &SegmentKey) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.referenced_segments().any(|key| key == segment))
+    }
+
+    /// Removes `segment` from the conditions of every rule.
+    ///
+    /// The rule then behaves as if `segment` matched every context. A group
+    /// left empty would match every context, so it satisfies the rule's
+    /// alternatives on its own and the rule's groups are dropped. A rule
+    /// whose only condition was `segment` is removed entirely: left with no
+    /// segment it would match every context, widening the rule instead of
+    /// detaching it. Returns whether the configuration changed.
+    pub fn detach_segment(&mut self, segment: &SegmentKey) -> bool {
+        let mut changed = false;
+        self.rules.retain_mut(|rule| {
+            let count = rule.referenced_segments().count();
+            rule.segments.retain(|key| key != segment);
+            for group in &mut rule.segment_groups {
+                group.retain(|key| key != segment);
+            }
+            if rule.segment_groups.iter().any(Vec::is_empty) {
+                rule.segment_groups.clear();
+            }
+            if rule.referenced_segments().count() == count {
+                return true;
+            }
+            changed = true;
+            rule.referenced_segments().next().is_some()
+        });
+        changed
+    }
+
+    /// Removes every rule that can no longer fire without `segment`.
+    ///
+    /// A segment that no longer exists matches nothing, so a rule listing it
+    /// in its segments can never fire, and neither can a group listing it.
+    /// This makes that fail-closed outcome explicit: such groups are
+    /// dropped, and so are rules requiring `segment` or left without a
+    /// group. Returns whether the configuration changed.
+    pub fn remove_rules_with_segment(&mut self, segment: &SegmentKey) -> bool {
+        let mut changed = false;
+        self.rules.retain_mut(|rule| {
+            if rule.segments.contains(segment) {
+                changed = true;
+                return false;
+            }
+            let groups = rule.segment_groups.len();
+            rule.segment_groups.retain(|group| !group.contains(segment));
+            if rule.segment_groups.len() == groups {
+                return true;
+            }
+            changed = true;
+            !rule.segment_groups.is_empty()
+        });
+        changed
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::key::{SegmentKey, VariantKey};
+    use crate::{
+        flag::FlagType,
+        key::{FlagKey, SegmentKey, VariantKey},
+        metadata::Metadata,
+        segment::{MatchOperator, Predicate, SegmentMatch},
+        variant::{ValueType, VariantValue, Variants},
+    };
 
     fn vk(s: &str) -> VariantKey {
         VariantKey::new(s).unwrap()
     }
 
+    fn string_flag() -> Flag {
+        Flag {
+            key: FlagKey::new("banner").unwrap(),
+            name: "Banner".into(),
+            description: None,
+            flag_type: FlagType::Experiment,
+            value_type: ValueType::String,
+            variants: Variants::new(
+                ValueType::String,
+                [
+                    (vk("blue"), VariantValue::String("blue".into())),
+                    (vk("red"), VariantValue::String("red".into())),
+                ],
+            )
+            .unwrap(),
+            metadata: Metadata::new(),
+        }
+    }
+
+    fn rule(segments: &[&str], serve: &str) -> TargetingRule {
+        TargetingRule {
+            segments: segments
+                .iter()
+                .map(|key| SegmentKey::new(*key).unwrap())
+                .collect(),
+            segment_groups: Vec::new(),
+            serve: ServeTarget::Fixed(vk(serve)),
+            bucket_by: None,
+            description: None,
+        }
+    }
+
+    fn config_with_rules(rules: Vec<TargetingRule>) -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled: true,
+            rules,
+            default_rule: ServeTarget::Fixed(vk("blue")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    #[test]
+    fn detach_segment_drops_the_condition_and_emptied_rules() {
+        let beta = SegmentKey::new("beta").unwrap();
+        let mut config = config_with_rules(vec![
+            rule(&["beta", "staff"], "red"),
+            rule(&["beta"], "red"),
+            rule(&["staff"], "blue"),
+            rule(&[], "red"),
+        ]);
+        assert!(config.references_segment(&beta));
+        assert!(config.detach_segment(&beta));
+        assert_eq!(
+            config.rules,
+            [
+                rule(&["staff"], "red"),
+                rule(&["staff"], "blue"),
+                rule(&[], "red")
+            ]
+        );
+        assert!(!config.references_segment(&beta));
+        assert!(!config.detach_segment(&beta));
+    }
+
+    #[test]
+    fn remove_rules_with_segment_drops_whole_rules() {
+        let beta = SegmentKey::new("beta").unwrap();
+        let mut config = config_with_rules(vec![
+            rule(&["beta", "staff"], "red"),
+            rule(&["staff"], "blue"),
+        ]);
+        assert!(config.remove_rules_with_segment(&beta));
+        assert_eq!(config.rules, [rule(&["staff"], "blue")]);
+        assert!(!config.remove_rules_with_segment(&beta));
+    }
+
+    /// A rule serving `serve` to `segments` and one of `groups`.
+    fn grouped(segments: &[&str], groups: &[&[&str]], serve: &str) -> TargetingRule {
+        TargetingRule {
+            segment_groups: groups
+                .iter()
+                .map(|group| {
+                    group
+                        .iter()
+                        .map(|key| SegmentKey::new(*key).unwrap())
+                        .collect()
+                })
+                .collect(),
+            ..rule(segments, serve)
+        }
+    }
+
+    #[test]
+    fn detach_segment_reaches_into_groups() {
+        let beta = SegmentKey::new("beta").unwrap();
+        let mut config = config_with_rules(vec![
+            grouped(&["staff"], &[&["beta", "eu"], &["pro"]], "red"),
+            grouped(&["staff"], &[&["beta"], &["pro"]], "blue"),
+            grouped(&[], &[&["beta"]], "red"),
+        ]);
+        assert!(config.references_segment(&beta));
+        assert!(config.detach_segment(&beta));
+        assert_eq!(
+            config.rules,
+            [
+                grouped(&["staff"], &[&["eu"], &["pro"]], "red"),
+                rule(&["staff"], "blue"),
+            ],
+            "an emptied group satisfies the alternatives; an emptied rule goes"
+        );
+        assert!(!config.references_segment(&beta));
+    }
+
+    #[test]
+    fn remove_rules_with_segment_drops_groups_that_can_no_longer_match() {
+        let beta = SegmentKey::new("beta").unwrap();
+        let mut config = config_with_rules(vec![
+            grouped(&["staff"], &[&["beta", "eu"], &["pro"]], "red"),
+            grouped(&["staff"], &[&["beta"]], "blue"),
+            grouped(&["beta"], &[&["pro"]], "red"),
+        ]);
+        assert!(config.remove_rules_with_segment(&beta));
+        assert_eq!(config.rules, [grouped(&["staff"], &[&["pro"]], "red")]);
+        assert!(!config.remove_rules_with_segment(&beta));
+    }
+
+    #[test]
+    fn segment_groups_are_additive_in_json() {
+        let legacy = serde_json::json!({
+            "segments": ["beta"],
+            "serve": { "fixed": "red" }
+        });
+        let rule: TargetingRule = serde_json::from_value(legacy.clone()).unwrap();
+        assert!(rule.segment_groups.is_empty());
+        assert_eq!(serde_json::to_value(&rule).unwrap(), legacy);
+
+        let rule = grouped(&["beta"], &[&["pro"], &["eu", "staff"]], "red");
+        let json = serde_json::to_value(&rule).unwrap();
+        assert_eq!(
+            json["segment_groups"],
+            serde_json::json!([["pro"], ["eu", "staff"]])
+        );
+        assert_eq!(serde_json::from_value::<TargetingRule>(json).unwrap(), rule);
+        assert_eq!(rule.referenced_segments().count(), 4);
+    }
+
+    #[test]
+    fn inheriting_takes_the_base_targeting_but_keeps_enabled() {
+        let base = FlagEnvConfig {
+            salt: Some("dev-salt".into()),
+            ..config_with_rules(vec![rule(&["beta"], "red")])
+        };
+        let own = FlagEnvConfig {
+            enabled: false,
+            default_rule: ServeTarget::Fixed(vk("red")),
+            ..config_with_rules(vec![])
+        };
+        let effective = own.inheriting(&base);
+        assert!(!effective.enabled);
+        assert_eq!(effective.rules, base.rules);
+        assert_eq!(effective.default_rule, ServeTarget::Fixed(vk("red")));
+        assert_eq!(effective.salt.as_deref(), Some("dev-salt"));
+    }
+
+    #[test]
+    fn inheriting_takes_the_base_default_only_when_asked() {
+        let base = FlagEnvConfig {
+            default_rule: ServeTarget::Fixed(vk("blue")),
+            ..config_with_rules(vec![rule(&["beta"], "red")])
+        };
+        let own = FlagEnvConfig {
+            default_rule: ServeTarget::Fixed(vk("red")),
+            inherit_default_rule: true,
+            ..config_with_rules(vec![])
+        };
+        let effective = own.inheriting(&base);
+        assert_eq!(effective.default_rule, base.default_rule);
+        assert!(!effective.inherit_default_rule);
+
+        let with_rules = FlagEnvConfig {
+            rules: vec![rule(&["staff"], "red")],
+            ..own
+        };
+        let effective = with_rules.inheriting(&base);
+        assert_eq!(effective.rules, with_rules.rules);
+        assert_eq!(effective.default_rule, base.default_rule);
+    }
+
+    #[test]
+    fn inheriting_keeps_the_own_kill_switch() {
+        let base = FlagEnvConfig {
+            kill_reason: Some("base incident".into()),
+            ..config_with_rules(vec![rule(&["beta"], "red")])
+        };
+        let own = config_with_rules(vec![]);
+        assert!(!own.inheriting(&base).is_killed());
+
+        let killed = FlagEnvConfig {
+            kill_reason: Some("incident 42".into()),
+            ..own
+        };
+        let effective = killed.inheriting(&base);
+        assert_eq!(effective.kill_reason.as_deref(), Some("incident 42"));
+        assert_eq!(effective.rules, base.rules);
+    }
+
+    #[test]
+    fn inheriting_keeps_the_own_version_gate() {
+        let base = FlagEnvConfig {
+            min_app_version: Some("5.0.0".into()),
+            ..config_with_rules(vec![rule(&["beta"], "red")])
+        };
+        let own = FlagEnvConfig {
+            min_app_version: Some("4.2.0".into()),
+            ..config_with_rules(vec![])
+        };
+        let effective = own.inheriting(&base);
+        assert_eq!(effective.min_app_version.as_deref(), Some("4.2.0"));
+        assert_eq!(effective.rules, base.rules);
+        assert_eq!(
+            config_with_rules(vec![]).inheriting(&base).min_app_version,
+            None
+        );
+    }
+
+    #[test]
+    fn inheriting_keeps_a_config_with_its_own_rules() {
+        let base = config_with_rules(vec![rule(&["beta"], "red")]);
+        let own = config_with_rules(vec![rule(&["staff"], "blue")]);
+        assert_eq!(own.inheriting(&base), own);
+    }
+
+    #[test]
+    fn validate_for_accepts_declared_variants() {
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: vec![],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::Fixed(vk("red")),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("blue")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        assert!(config.validate_for(&string_flag()).is_ok());
+    }
+
+    #[test]
+    fn validate_for_rejects_default_on_undeclared_variant() {
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![],
+            default_rule: ServeTarget::Fixed(vk("green")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        assert!(matches!(
+            config.validate_for(&string_flag()),
+            Err(DomainError::UnknownVariant { variant }) if variant == "green"
+        ));
+    }
+
+    #[test]
+    fn validate_for_rejects_rollout_on_undeclared_variant() {
+        let config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![TargetingRule {
+                segments: vec![],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::rollout(vec![
+                    WeightedVariant {
+                        variant: vk("red"),
+                        weight: 50,
+                    },
+                    WeightedVariant {
+                        variant: vk("green"),
+                        weight: 50,
+                    },
+                ])
+                .unwrap(),
+                bucket_by: None,
+                description: None,
+            }],
+            default_rule: ServeTarget::Fixed(vk("blue")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        assert!(matches!(
+            config.validate_for(&string_flag()),
+            Err(DomainError::UnknownVariant { variant }) if variant == "green"
+        ));
+    }
+
     #[test]
     fn rollout_ok_positive_total() {
         let weights = vec![
@@ -175,7 +854,10 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![SegmentKey::new("beta-users").unwrap()],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk("on")),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::rollout(vec![
                 WeightedVariant {
@@ -188,12 +870,80 @@ mod tests {
                 },
             ])
             .unwrap(),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let json = serde_json::to_string(&config).unwrap();
         let back: FlagEnvConfig = serde_json::from_str(&json).unwrap();
         assert_eq!(back, config);
     }
 
+    #[test]
+    fn rollout_scope_is_omitted_when_shared() {
+        let mut config = FlagEnvConfig {
+            enabled: true,
+            rules: vec![],
+            default_rule: ServeTarget::Fixed(vk("on")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        };
+        let json = serde_json::to_value(&config).unwrap();
+        assert!(json.get("rollout_scope").is_none(), "{json}");
+        assert!(json.get("bucket_by").is_none(), "{json}");
+        assert!(json.get("kill_reason").is_none(), "{json}");
+        assert_eq!(
+            serde_json::from_value::<FlagEnvConfig>(json).unwrap(),
+            config
+        );
+
+        config.rollout_scope = RolloutScope::PerRule;
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["rollout_scope"], "per_rule");
+        assert_eq!(
+            serde_json::from_value::<FlagEnvConfig>(json).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn consistent_hash_target_serde_shape() {
+        let target = ServeTarget::consistent_hash(
+            "host",
+            vec![WeightedVariant {
+                variant: vk("on"),
+                weight: 10,
+            }],
+        )
+        .unwrap();
+        let json = serde_json::to_value(&target).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"consistent_hash": {
+                "attribute": "host",
+                "weights": [{"variant": "on", "weight": 10}]
+            }})
+        );
+        assert_eq!(serde_json::from_value::<ServeTarget>(json).unwrap(), target);
+        assert!(
+            ServeTarget::consistent_hash(
+                "host",
+                vec![WeightedVariant {
+                    variant: vk("on"),
+                    weight: 0,
+                }]
+            )
+            .is_err()
+        );
+    }
+
     #[test]
     fn default_rule_is_required_field() {
         // Structural: FlagEnvConfig::default_rule field must be present in serde JSON
@@ -201,6 +951,12 @@ mod tests {
             enabled: false,
             rules: vec![],
             default_rule: ServeTarget::Fixed(vk("off")),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         let json = serde_json::to_string(&config).unwrap();
         assert!(json.contains("default_rule"));
@@ -234,4 +990,112 @@ mod tests {
         let back: ServeTarget = serde_json::from_str(&json).unwrap();
         assert_eq!(back, target);
     }
+
+    fn segment(key: &str, attributes: &[&str]) -> Segment {
+        let predicate = |attribute: &&str| {
+            SegmentMatch::Predicate(Predicate {
+                attribute: (*attribute).to_owned(),
+                operator: MatchOperator::Equals,
+                values: vec![serde_json::json!("x")],
+                case_insensitive: false,
+            })
+        };
+        Segment {
+            key: SegmentKey::new(key).unwrap(),
+            name: key.to_owned(),
+            match_expr: SegmentMatch::And(vec![
+                SegmentMatch::Or(attributes.iter().map(predicate).collect()),
+                SegmentMatch::Not(Box::new(predicate(&"blocked"))),
+            ]),
+        }
+    }
+
+    #[test]
+    fn referenced_attributes_cover_rules_segments_and_hash_rings() {
+        let config = config_with_rules(vec![
+            rule(&["beta", "eu"], "blue"),
+            rule(&["beta", "missing"], "red"),
+            TargetingRule {
+                segments: vec![SegmentKey::new("eu").unwrap()],
+                segment_groups: Vec::new(),
+                serve: ServeTarget::consistent_hash(
+                    "host",
+                    vec![WeightedVariant {
+                        variant: vk("blue"),
+                        weight: 1,
+                    }],
+                )
+                .unwrap(),
+                bucket_by: None,
+                description: None,
+            },
+        ]);
+        let segments = [
+            segment("beta", &["plan", "tier"]),
+            segment("eu", &["country"]),
+            segment("unused", &["email"]),
+        ];
+
+        let attributes: Vec<String> = config
+            .referenced_attributes(&segments)
+            .into_iter()
+            .collect();
+        assert_eq!(attributes, ["blocked", "country", "host", "plan", "tier"]);
+    }
+
+    #[test]
+    fn rollouts_reference_their_bucketing_attribute() {
+        let half = ServeTarget::rollout(vec![WeightedVariant {
+            variant: vk("blue"),
+            weight: 1,
+        }])
+        .unwrap();
+        let mut config = config_with_rules(vec![
+            TargetingRule {
+                bucket_by: Some("device_id".into()),
+                ..rule(&[], "blue")
+            },
+            TargetingRule {
+                serve: half.clone(),
+                bucket_by: Some("company_id".into()),
+                ..rule(&[], "blue")
+            },
+        ]);
+        config.bucket_by = Some("region".into());
+        assert!(
+            config.referenced_attributes(&[]).contains("company_id"),
+            "a rule rollout's own attribute"
+        );
+        assert!(
+            !config.referenced_attributes(&[]).contains("device_id"),
+            "a fixed serve buckets on nothing"
+        );
+        assert!(
+            !config.referenced_attributes(&[]).contains("region"),
+            "the default is fixed and the only rollout buckets on its own attribute"
+        );
+
+        config.default_rule = half;
+        assert!(config.referenced_attributes(&[]).contains("region"));
+    }
+
+    #[test]
+    fn a_config_without_rules_references_no_attribute() {
+        let config = config_with_rules(vec![]);
+        assert!(
+            config
+                .referenced_attributes(&[segment("beta", &["plan"])])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn a_version_gate_references_the_app_version() {
+        let config = FlagEnvConfig {
+            min_app_version: Some("4.2.0".into()),
+            ..config_with_rules(vec![])
+        };
+        let attributes: Vec<String> = config.referenced_attributes(&[]).into_iter().collect();
+        assert_eq!(attributes, [APP_VERSION_ATTRIBUTE]);
+    }
 }
@@ -16,13 +16,17 @@
 //! | [`flag`] | [`Flag`], [`FlagType`] |
 //! | [`variant`] | [`ValueType`], [`VariantValue`], [`Variants`] |
 //! | [`flag_env_config`] | [`FlagEnvConfig`], [`TargetingRule`], [`ServeTarget`], [`WeightedVariant`] |
-//! | [`segment`] | [`Segment`], [`SegmentMatch`], [`Predicate`], [`MatchOperator`] |
+//! | [`config_diff`] | [`diff_configs`], [`ConfigChange`] |
+//! | [`environment_patch`] | [`EnvironmentPatch`], [`FlagPatch`], [`PatchStatus`] |
+//! | [`segment`] | [`Segment`], [`SegmentMatch`], [`Predicate`], [`PinnedValue`], [`MatchOperator`] |
 //! | [`sdk_key`] | [`SdkKey`], [`SdkKeyKind`] |
 //! | [`audit`] | [`AuditEntry`] |
 //! | [`metadata`] | [`Metadata`], [`MetadataValue`] |
 
 pub mod audit;
+pub mod config_diff;
 pub mod environment;
+pub mod environment_patch;
 pub mod error;
 pub mod federation;
 pub mod flag;
@@ -36,14 +40,19 @@ pub mod variant;
 
 // Convenience re-exports of the most frequently used types.
 pub use audit::AuditEntry;
-pub use environment::Environment;
+pub use config_diff::{ConfigChange, diff_configs};
+pub use environment::{Environment, inheritance_chain};
+pub use environment_patch::{EnvironmentPatch, FlagPatch, PatchStatus};
 pub use error::DomainError;
 pub use federation::{ExternalRef, ManagedBy};
 pub use flag::{Flag, FlagType};
-pub use flag_env_config::{FlagEnvConfig, ServeTarget, TargetingRule, WeightedVariant};
+pub use flag_env_config::{
+    APP_VERSION_ATTRIBUTE, FlagEnvConfig, HashRingRollout, RolloutScope, ServeTarget,
+    TargetingRule, WeightedVariant,
+};
 pub use key::{EnvironmentKey, FlagKey, ProjectKey, SegmentKey, VariantKey};
 pub use metadata::{Metadata, MetadataValue};
 pub use project::Project;
 pub use sdk_key::{SdkKey, SdkKeyKind};
-pub use segment::{MatchOperator, Predicate, Segment, SegmentMatch};
+pub use segment::{MatchOperator, PinnedValue, Predicate, Segment, SegmentMatch};
 pub use variant::{ValueType, VariantValue, Variants};
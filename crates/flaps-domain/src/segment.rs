@@ -1,10 +1,23 @@
 //! Segments: reusable targeting predicates with recursive boolean composition.
 
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::error::DomainError;
 use crate::key::SegmentKey;
+use crate::variant::ValueType;
 
 /// Comparison operator applied to a context attribute.
+///
+/// A context attribute may be JSON `null`. Targeting reads an absent
+/// attribute as `null` too, so "absent" and "present and null" are the same
+/// to every operator: `Equals [null]` matches both and no other value (not
+/// `""`, `0` or `false`), `NotEquals` and `NotIn` match both unless `null` is
+/// among their values, `NotContains` and `NotExists` always match both, and
+/// `Exists` and the other string, SemVer and modulo operators never match
+/// them.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum MatchOperator {
@@ -13,6 +26,10 @@ pub enum MatchOperator {
     /// Attribute does not equal any of the values.
     NotEquals,
     /// Attribute is contained in the value list.
+    ///
+    /// Membership is strict on types: numbers compare by value (`2` is in
+    /// `[1, 2.0]`), and a number is never in a list of strings nor a string
+    /// in a list of numbers. See [`Predicate::in_numbers`].
     In,
     /// Attribute is not contained in the value list.
     NotIn,
@@ -22,6 +39,17 @@ pub enum MatchOperator {
     EndsWith,
     /// Attribute contains the value as a substring.
     Contains,
+    /// Attribute does not contain the value as a substring: the exact
+    /// negation of [`MatchOperator::Contains`].
+    ///
+    /// Unlike `Contains`, it matches an attribute that is absent, `null` or
+    /// not a string, as [`MatchOperator::NotEquals`] and
+    /// [`MatchOperator::NotIn`] do: a number never contains the value. See
+    /// [`Predicate::not_contains`].
+    NotContains,
+    /// Attribute matches the value, a regular expression, anywhere in the
+    /// string unless the pattern is anchored (`^...$`).
+    Regex,
     /// SemVer equality.
     SemVerEq,
     /// SemVer inequality.
@@ -34,21 +62,366 @@ pub enum MatchOperator {
     SemVerGt,
     /// SemVer greater than or equal.
     SemVerGte,
-    /// SemVer caret range (compatible).
+    /// SemVer caret: the same major version, as the flagd `^` (not the npm
+    /// caret range: `1.1.9` matches `^1.2.0`).
     SemVerCaret,
-    /// SemVer tilde range (patch-level compatible).
+    /// SemVer tilde: the same major and minor version, as the flagd `~`.
     SemVerTilde,
+    /// Deterministic numeric cohort: takes `[modulus, threshold]` and matches
+    /// when the attribute is a non-negative integer with
+    /// `attribute % modulus < threshold` (`[100, 10]` selects 10% of ids).
+    ///
+    /// Membership derives from the attribute value, not from a hash, so it
+    /// can reproduce a cohort defined by an external system. Non-numeric,
+    /// fractional, negative or missing attributes never match.
+    ModuloRollout,
+    /// The context's targeting key (its user id) is one of the values.
+    ///
+    /// The predicate's `attribute` is ignored: the operator always reads the
+    /// targeting key, so an explicit list of users can be targeted without a
+    /// dedicated attribute. A context without a targeting key never matches.
+    /// See [`Predicate::user_id_in`].
+    UserIdIn,
+    /// Attribute equals any of the values, with the loose equality of
+    /// [`MatchOperator::Equals`]: the values may mix types, and numbers and
+    /// numeric strings compare by value (`200` equals `"200"`), where
+    /// [`MatchOperator::In`] compares types strictly. Booleans also equal
+    /// their canonical string form, the lowercase `"true"` and `"false"`, so
+    /// a boolean attribute matches a list of strings and the other way round.
+    /// See [`Predicate::equals_any`].
+    EqualsAny,
+    /// Attribute equals none of the values, with the equality of
+    /// [`MatchOperator::EqualsAny`]. See [`Predicate::not_equals_any`].
+    NotEqualsAny,
+    /// Attribute is present and not `null`, whatever its value: `""`, `0`
+    /// and `false` all exist. The values are ignored. See
+    /// [`Predicate::exists`].
+    Exists,
+    /// Attribute is absent or `null`: the exact negation of
+    /// [`MatchOperator::Exists`]. The values are ignored. See
+    /// [`Predicate::not_exists`].
+    NotExists,
 }
 
+/// Context attribute under which evaluation exposes the targeting key.
+pub const TARGETING_KEY_ATTRIBUTE: &str = "targetingKey";
+
 /// A single attribute comparison against a list of reference values.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Predicate {
     /// Name of the evaluation context attribute to test.
+    ///
+    /// A dotted path such as `subscription.tier` reads a member of a nested
+    /// object, or an array element by index (`roles.0`). At each level, a
+    /// key equal to the whole remaining path is read first, so a flat
+    /// attribute named `app.version` still works; there is no escaping. A
+    /// path whose intermediate key is missing reads as an absent attribute.
     pub attribute: String,
     /// Comparison operator.
     pub operator: MatchOperator,
     /// Reference values used by the operator.
-    pub values: Vec<serde_json::Value>,
+    ///
+    /// Each value is a bare JSON scalar or a [`PinnedValue`] object
+    /// (`{"type": "string", "value": "01234"}`), which is converted to its
+    /// type on deserialization. Values always serialize bare.
+    #[serde(deserialize_with = "deserialize_values")]
+    pub values: Vec<Value>,
+    /// Compares strings ignoring case, so `"FR"` matches `"fr"`.
+    ///
+    /// Only [`MatchOperator::Equals`], [`MatchOperator::Contains`],
+    /// [`MatchOperator::StartsWith`] and [`MatchOperator::EndsWith`] honor
+    /// it; the compiler rejects it on any other operator. Attributes that
+    /// are not strings compare as they would without it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub case_insensitive: bool,
+}
+
+impl Predicate {
+    /// Builds a [`MatchOperator::UserIdIn`] predicate matching the listed
+    /// user ids.
+    ///
+    /// The ids are sorted and deduplicated, as [`Self::normalize_user_ids`]
+    /// does.
+    #[must_use]
+    pub fn user_id_in<I, S>(user_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let user_ids: BTreeSet<String> = user_ids.into_iter().map(Into::into).collect();
+        Self {
+            attribute: TARGETING_KEY_ATTRIBUTE.to_owned(),
+            operator: MatchOperator::UserIdIn,
+            values: user_ids.into_iter().map(Value::String).collect(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Sorts and deduplicates the values of a [`MatchOperator::UserIdIn`]
+    /// predicate; other predicates are left alone.
+    ///
+    /// Membership does not depend on the order of the ids, so sorting keeps
+    /// stored and exported lists stable, and a repeated id only bloats them.
+    pub fn normalize_user_ids(&mut self) {
+        if self.operator != MatchOperator::UserIdIn {
+            return;
+        }
+        self.values.sort_by(|a, b| match (a.as_str(), b.as_str()) {
+            (Some(a), Some(b)) => a.cmp(b),
+            _ => a.to_string().cmp(&b.to_string()),
+        });
+        self.values.dedup();
+    }
+
+    /// Builds a [`MatchOperator::EqualsAny`] predicate matching when
+    /// `attribute` equals any of `values`.
+    #[must_use]
+    pub fn equals_any<I, V>(attribute: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Self {
+            attribute: attribute.into(),
+            operator: MatchOperator::EqualsAny,
+            values: values.into_iter().map(Into::into).collect(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Builds a [`MatchOperator::NotEqualsAny`] predicate matching when
+    /// `attribute` equals none of `values`.
+    #[must_use]
+    pub fn not_equals_any<I, V>(attribute: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        Self {
+            operator: MatchOperator::NotEqualsAny,
+            ..Self::equals_any(attribute, values)
+        }
+    }
+
+    /// Builds a [`MatchOperator::In`] predicate matching when `attribute` is
+    /// a number among `values`.
+    ///
+    /// Non-finite values, which JSON cannot hold, are left out.
+    #[must_use]
+    pub fn in_numbers(attribute: impl Into<String>, values: impl IntoIterator<Item = f64>) -> Self {
+        Self {
+            attribute: attribute.into(),
+            operator: MatchOperator::In,
+            values: values
+                .into_iter()
+                .filter_map(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .collect(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Builds a case-insensitive [`MatchOperator::Equals`] predicate
+    /// matching when `attribute` is `value` in any casing.
+    #[must_use]
+    pub fn equals_ignore_case(attribute: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            attribute: attribute.into(),
+            operator: MatchOperator::Equals,
+            values: vec![Value::String(value.into())],
+            case_insensitive: true,
+        }
+    }
+
+    /// Builds a [`MatchOperator::NotContains`] predicate matching when
+    /// `attribute` does not contain `value`, or is not a string.
+    #[must_use]
+    pub fn not_contains(attribute: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            attribute: attribute.into(),
+            operator: MatchOperator::NotContains,
+            values: vec![Value::String(value.into())],
+            case_insensitive: false,
+        }
+    }
+
+    /// Builds a [`MatchOperator::Exists`] predicate matching when
+    /// `attribute` is present and not `null`.
+    #[must_use]
+    pub fn exists(attribute: impl Into<String>) -> Self {
+        Self {
+            attribute: attribute.into(),
+            operator: MatchOperator::Exists,
+            values: Vec::new(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Builds a [`MatchOperator::NotExists`] predicate matching when
+    /// `attribute` is absent or `null`.
+    #[must_use]
+    pub fn not_exists(attribute: impl Into<String>) -> Self {
+        Self {
+            attribute: attribute.into(),
+            operator: MatchOperator::NotExists,
+            values: Vec::new(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Builds a [`MatchOperator::SemVerEq`] predicate matching when
+    /// `attribute` is the version `version`.
+    #[must_use]
+    pub fn semver_eq(attribute: impl Into<String>, version: impl Into<String>) -> Self {
+        Self::semver(attribute, MatchOperator::SemVerEq, version)
+    }
+
+    /// Builds a [`MatchOperator::SemVerGte`] predicate matching when
+    /// `attribute` is `version` or a later version ("at least 2.0.0").
+    #[must_use]
+    pub fn semver_gte(attribute: impl Into<String>, version: impl Into<String>) -> Self {
+        Self::semver(attribute, MatchOperator::SemVerGte, version)
+    }
+
+    /// Builds a [`MatchOperator::SemVerLte`] predicate matching when
+    /// `attribute` is `version` or an earlier version ("at most 2.0.0").
+    #[must_use]
+    pub fn semver_lte(attribute: impl Into<String>, version: impl Into<String>) -> Self {
+        Self::semver(attribute, MatchOperator::SemVerLte, version)
+    }
+
+    fn semver(
+        attribute: impl Into<String>,
+        operator: MatchOperator,
+        version: impl Into<String>,
+    ) -> Self {
+        Self {
+            attribute: attribute.into(),
+            operator,
+            values: vec![Value::String(version.into())],
+            case_insensitive: false,
+        }
+    }
+
+    /// Returns the name of the context attribute this predicate reads.
+    #[must_use]
+    pub fn read_attribute(&self) -> &str {
+        match self.operator {
+            MatchOperator::UserIdIn => TARGETING_KEY_ATTRIBUTE,
+            _ => &self.attribute,
+        }
+    }
+}
+
+/// A predicate value pinned to a [`ValueType`].
+///
+/// A bare JSON value is compared as the type it was written with, so an id
+/// written as `"42"` never compares as a number. The tagged form
+/// `{"type": "number", "value": "42"}` states the type instead, and
+/// [`Self::resolve`] converts the value to it. Pinning only fixes the type:
+/// `{"type": "string", "value": "01234"}` compares as the string `"01234"`,
+/// while `{"type": "string", "value": 1234}` compares as `"1234"`, since a
+/// JSON number has no leading zeros to keep.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinnedValue {
+    /// The type the value is converted to. [`ValueType::Object`] is never
+    /// valid: predicate values are scalars.
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+    /// The value, as written.
+    pub value: Value,
+}
+
+impl PinnedValue {
+    /// Pins `value` to `value_type`.
+    #[must_use]
+    pub fn new(value_type: ValueType, value: impl Into<Value>) -> Self {
+        Self {
+            value_type,
+            value: value.into(),
+        }
+    }
+
+    /// Pins `value` to [`ValueType::String`].
+    #[must_use]
+    pub fn string(value: impl Into<Value>) -> Self {
+        Self::new(ValueType::String, value)
+    }
+
+    /// Pins `value` to [`ValueType::Number`].
+    #[must_use]
+    pub fn number(value: impl Into<Value>) -> Self {
+        Self::new(ValueType::Number, value)
+    }
+
+    /// Pins `value` to [`ValueType::Boolean`].
+    #[must_use]
+    pub fn boolean(value: impl Into<Value>) -> Self {
+        Self::new(ValueType::Boolean, value)
+    }
+
+    /// Converts the value to its pinned type.
+    ///
+    /// Numbers and booleans convert to their JSON text as strings; strings
+    /// convert to numbers when they parse as one (`"042"` is `42`) and to
+    /// booleans when they are `"true"` or `"false"`.
+    ///
+    /// # Errors
+    /// Returns [`DomainError::PinnedValueMismatch`] when the value cannot be
+    /// read as its type, and always for `null`, arrays, objects and
+    /// [`ValueType::Object`].
+    pub fn resolve(&self) -> Result<Value, DomainError> {
+        let resolved = match (self.value_type, &self.value) {
+            (ValueType::String, Value::String(_))
+            | (ValueType::Number, Value::Number(_))
+            | (ValueType::Boolean, Value::Bool(_)) => Some(self.value.clone()),
+            (ValueType::String, Value::Number(n)) => Some(Value::String(n.to_string())),
+            (ValueType::String, Value::Bool(b)) => Some(Value::String(b.to_string())),
+            (ValueType::Number, Value::String(s)) => parse_number(s.trim()),
+            (ValueType::Boolean, Value::String(s)) => {
+                s.trim().parse::<bool>().ok().map(Value::Bool)
+            }
+            _ => None,
+        };
+        resolved.ok_or_else(|| DomainError::PinnedValueMismatch {
+            value_type: self.value_type,
+            value: self.value.to_string(),
+        })
+    }
+}
+
+/// Parses an integer, exactly, or a finite float.
+fn parse_number(s: &str) -> Option<Value> {
+    if let Ok(n) = s.parse::<i64>() {
+        return Some(Value::from(n));
+    }
+    if let Ok(n) = s.parse::<u64>() {
+        return Some(Value::from(n));
+    }
+    s.parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(Value::Number)
+}
+
+/// A predicate value as written: pinned or bare.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WrittenValue {
+    Pinned(PinnedValue),
+    Bare(Value),
+}
+
+/// Deserializes [`Predicate::values`], resolving [`PinnedValue`]s.
+fn deserialize_values<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Value>, D::Error> {
+    Vec::<WrittenValue>::deserialize(deserializer)?
+        .into_iter()
+        .map(|written| match written {
+            WrittenValue::Pinned(pinned) => pinned.resolve().map_err(serde::de::Error::custom),
+            WrittenValue::Bare(value) => Ok(value),
+        })
+        .collect()
 }
 
 /// A recursive boolean expression over [`Predicate`]s.
@@ -68,6 +441,47 @@ pub enum SegmentMatch {
     Predicate(Predicate),
 }
 
+impl SegmentMatch {
+    /// Returns the name of every context attribute a predicate of this
+    /// expression reads.
+    #[must_use]
+    pub fn referenced_attributes(&self) -> BTreeSet<String> {
+        let mut attributes = BTreeSet::new();
+        self.collect_attributes(&mut attributes);
+        attributes
+    }
+
+    /// Sorts and deduplicates the user ids of every
+    /// [`MatchOperator::UserIdIn`] predicate in this expression; see
+    /// [`Predicate::normalize_user_ids`].
+    pub fn normalize_user_ids(&mut self) {
+        match self {
+            Self::And(children) | Self::Or(children) => {
+                for child in children {
+                    child.normalize_user_ids();
+                }
+            }
+            Self::Not(inner) => inner.normalize_user_ids(),
+            Self::Predicate(predicate) => predicate.normalize_user_ids(),
+        }
+    }
+
+    /// Adds the attributes read by this expression to `attributes`.
+    pub(crate) fn collect_attributes(&self, attributes: &mut BTreeSet<String>) {
+        match self {
+            Self::And(children) | Self::Or(children) => {
+                for child in children {
+                    child.collect_attributes(attributes);
+                }
+            }
+            Self::Not(inner) => inner.collect_attributes(attributes),
+            Self::Predicate(predicate) => {
+                attributes.insert(predicate.read_attribute().to_owned());
+            }
+        }
+    }
+}
+
 /// A named, reusable targeting segment.
 ///
 /// Flags reference segments by [`SegmentKey`]; predicates live here, not
@@ -93,6 +507,7 @@ mod tests {
             attribute: attr.into(),
             operator: MatchOperator::Equals,
             values: vec![serde_json::json!("beta")],
+            case_insensitive: false,
         })
     }
 
@@ -129,6 +544,258 @@ mod tests {
         assert_eq!(back, segment);
     }
 
+    fn values_of(values: &serde_json::Value) -> Result<Vec<Value>, serde_json::Error> {
+        serde_json::from_value::<Predicate>(serde_json::json!({
+            "attribute": "zip",
+            "operator": "in",
+            "values": values,
+        }))
+        .map(|predicate| predicate.values)
+    }
+
+    #[test]
+    fn zero_padded_string_survives_a_round_trip() {
+        let predicate: Predicate = serde_json::from_value(serde_json::json!({
+            "attribute": "zip",
+            "operator": "equals",
+            "values": [{"type": "string", "value": "01234"}],
+        }))
+        .unwrap();
+        assert_eq!(predicate.values, [serde_json::json!("01234")]);
+
+        let json = serde_json::to_string(&predicate).unwrap();
+        assert!(json.contains(r#""values":["01234"]"#), "{json}");
+        let back: Predicate = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, predicate);
+    }
+
+    #[test]
+    fn pinned_values_are_converted_to_their_type() {
+        let values = values_of(&serde_json::json!([
+            {"type": "string", "value": 1234},
+            {"type": "number", "value": "042"},
+            {"type": "number", "value": "2.5"},
+            {"type": "boolean", "value": "true"},
+            "bare",
+            7,
+        ]))
+        .unwrap();
+        assert_eq!(
+            values,
+            serde_json::json!(["1234", 42, 2.5, true, "bare", 7])
+                .as_array()
+                .unwrap()
+                .clone()
+        );
+    }
+
+    #[test]
+    fn unconvertible_pinned_values_are_rejected() {
+        for pinned in [
+            serde_json::json!({"type": "number", "value": "4x"}),
+            serde_json::json!({"type": "boolean", "value": 1}),
+            serde_json::json!({"type": "string", "value": null}),
+            serde_json::json!({"type": "object", "value": "{}"}),
+        ] {
+            assert!(values_of(&serde_json::json!([pinned])).is_err(), "{pinned}");
+        }
+        assert!(matches!(
+            PinnedValue::number("zip").resolve(),
+            Err(DomainError::PinnedValueMismatch {
+                value_type: ValueType::Number,
+                ..
+            })
+        ));
+        assert_eq!(PinnedValue::string(12).resolve().unwrap(), "12");
+    }
+
+    #[test]
+    fn user_id_in_reads_the_targeting_key() {
+        let predicate = Predicate::user_id_in(["alice", "bob"]);
+        assert_eq!(
+            serde_json::to_value(&predicate).unwrap(),
+            serde_json::json!({
+                "attribute": "targetingKey",
+                "operator": "user_id_in",
+                "values": ["alice", "bob"],
+            })
+        );
+
+        let written: Predicate = serde_json::from_value(serde_json::json!({
+            "attribute": "user_id",
+            "operator": "user_id_in",
+            "values": ["alice"],
+        }))
+        .unwrap();
+        assert_eq!(
+            SegmentMatch::Predicate(written).referenced_attributes(),
+            BTreeSet::from([TARGETING_KEY_ATTRIBUTE.to_owned()])
+        );
+    }
+
+    #[test]
+    fn user_id_in_dedups_and_sorts_on_insert() {
+        let predicate = Predicate::user_id_in(["carol", "alice", "bob", "alice", "carol"]);
+        assert_eq!(
+            serde_json::to_value(&predicate).unwrap()["values"],
+            serde_json::json!(["alice", "bob", "carol"])
+        );
+    }
+
+    #[test]
+    fn normalize_user_ids_gives_a_stable_order_to_written_lists() {
+        let written = |ids: serde_json::Value| -> SegmentMatch {
+            SegmentMatch::Not(Box::new(SegmentMatch::Predicate(
+                serde_json::from_value(serde_json::json!({
+                    "attribute": "targetingKey",
+                    "operator": "user_id_in",
+                    "values": ids,
+                }))
+                .unwrap(),
+            )))
+        };
+        let mut first = written(serde_json::json!(["bob", "alice", "bob"]));
+        let mut second = written(serde_json::json!(["alice", "bob", "alice"]));
+        first.normalize_user_ids();
+        second.normalize_user_ids();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            serde_json::to_string(&second).unwrap()
+        );
+        let SegmentMatch::Not(inner) = first else {
+            panic!("expected Not");
+        };
+        let SegmentMatch::Predicate(predicate) = *inner else {
+            panic!("expected Predicate");
+        };
+        assert_eq!(predicate.values, ["alice", "bob"]);
+
+        let mut other = Predicate {
+            attribute: "plan".into(),
+            operator: MatchOperator::In,
+            values: vec![Value::from("pro"), Value::from("free"), Value::from("pro")],
+            case_insensitive: false,
+        };
+        other.normalize_user_ids();
+        assert_eq!(other.values, ["pro", "free", "pro"]);
+    }
+
+    #[test]
+    fn equals_any_takes_a_mixed_list() {
+        let predicate = Predicate::equals_any(
+            "status",
+            [Value::from("active"), Value::from(200), Value::Null],
+        );
+        let json = serde_json::json!({
+            "attribute": "status",
+            "operator": "equals_any",
+            "values": ["active", 200, null],
+        });
+        assert_eq!(serde_json::to_value(&predicate).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<Predicate>(json).unwrap(),
+            predicate
+        );
+
+        // Pinned values resolve in the list like anywhere else.
+        let pinned: Predicate = serde_json::from_value(serde_json::json!({
+            "attribute": "code",
+            "operator": "not_equals_any",
+            "values": [{"type": "number", "value": "204"}, 200.5],
+        }))
+        .unwrap();
+        assert_eq!(
+            pinned,
+            Predicate::not_equals_any("code", [Value::from(204), Value::from(200.5)])
+        );
+    }
+
+    #[test]
+    fn in_numbers_keeps_finite_values() {
+        assert_eq!(
+            serde_json::to_value(Predicate::in_numbers("tier", [1.0, f64::NAN, 2.5])).unwrap(),
+            serde_json::json!({
+                "attribute": "tier",
+                "operator": "in",
+                "values": [1.0, 2.5],
+            })
+        );
+    }
+
+    #[test]
+    fn not_contains_builder_serializes_in_snake_case() {
+        assert_eq!(
+            serde_json::to_value(Predicate::not_contains("email", "test")).unwrap(),
+            serde_json::json!({
+                "attribute": "email",
+                "operator": "not_contains",
+                "values": ["test"],
+            })
+        );
+    }
+
+    #[test]
+    fn case_insensitive_defaults_to_false_and_is_omitted() {
+        let json = serde_json::json!({
+            "attribute": "country",
+            "operator": "equals",
+            "values": ["fr"],
+        });
+        let predicate: Predicate = serde_json::from_value(json.clone()).unwrap();
+        assert!(!predicate.case_insensitive);
+        assert_eq!(serde_json::to_value(&predicate).unwrap(), json);
+
+        let ignoring_case = Predicate::equals_ignore_case("country", "fr");
+        assert_eq!(
+            serde_json::to_value(&ignoring_case).unwrap(),
+            serde_json::json!({
+                "attribute": "country",
+                "operator": "equals",
+                "values": ["fr"],
+                "case_insensitive": true,
+            })
+        );
+    }
+
+    #[test]
+    fn presence_builders_serialize_without_values() {
+        for (predicate, operator) in [
+            (Predicate::exists("phone"), "exists"),
+            (Predicate::not_exists("phone"), "not_exists"),
+        ] {
+            assert_eq!(
+                serde_json::to_value(predicate).unwrap(),
+                serde_json::json!({
+                    "attribute": "phone",
+                    "operator": operator,
+                    "values": [],
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn semver_builders_serialize_in_snake_case() {
+        for (predicate, operator) in [
+            (Predicate::semver_eq("app-version", "2.0.0"), "sem_ver_eq"),
+            (Predicate::semver_gte("app-version", "2.0.0"), "sem_ver_gte"),
+            (Predicate::semver_lte("app-version", "2.0.0"), "sem_ver_lte"),
+        ] {
+            let json = serde_json::json!({
+                "attribute": "app-version",
+                "operator": operator,
+                "values": ["2.0.0"],
+            });
+            assert_eq!(serde_json::to_value(&predicate).unwrap(), json);
+            assert_eq!(
+                serde_json::from_value::<Predicate>(json).unwrap(),
+                predicate
+            );
+        }
+    }
+
     #[test]
     fn all_operators_serialize() {
         let ops = [
@@ -139,6 +806,8 @@ mod tests {
             MatchOperator::StartsWith,
             MatchOperator::EndsWith,
             MatchOperator::Contains,
+            MatchOperator::NotContains,
+            MatchOperator::Regex,
             MatchOperator::SemVerEq,
             MatchOperator::SemVerNeq,
             MatchOperator::SemVerLt,
@@ -147,6 +816,12 @@ mod tests {
             MatchOperator::SemVerGte,
             MatchOperator::SemVerCaret,
             MatchOperator::SemVerTilde,
+            MatchOperator::ModuloRollout,
+            MatchOperator::UserIdIn,
+            MatchOperator::EqualsAny,
+            MatchOperator::NotEqualsAny,
+            MatchOperator::Exists,
+            MatchOperator::NotExists,
         ];
         for op in ops {
             let json = serde_json::to_string(&op).unwrap();
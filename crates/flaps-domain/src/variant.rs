@@ -122,6 +122,22 @@ impl Variants {
         })
     }
 
+    /// Reads a serialized variant set whose values may be bare JSON booleans
+    /// or strings, tagging each such value into the current shape.
+    ///
+    /// A bare `true` becomes `{"bool": true}` and a bare `"on"` becomes
+    /// `{"string": "on"}`. Tagged values are objects and pass through as they
+    /// are, so a set already in the current format reads unchanged.
+    ///
+    /// # Errors
+    /// Returns the deserialization error when `value` is not a variant set
+    /// once tagged, or when the set breaks the invariants of
+    /// [`Variants::new`].
+    pub fn from_legacy_value(mut value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        upgrade_legacy_values(&mut value);
+        serde_json::from_value(value)
+    }
+
     /// Returns the declared value type for all variants in this set.
     #[must_use]
     pub fn value_type(&self) -> ValueType {
@@ -139,6 +155,56 @@ impl Variants {
     pub fn contains(&self, key: &VariantKey) -> bool {
         self.entries.contains_key(key)
     }
+
+    /// Checks that string values are non-empty and pairwise distinct.
+    ///
+    /// Variants are checked in key order, so the error names the same
+    /// variants for the same set.
+    pub(crate) fn check_distinct_strings(&self) -> Result<(), DomainError> {
+        let mut entries: Vec<(&VariantKey, &str)> = self
+            .entries
+            .iter()
+            .filter_map(|(key, value)| match value {
+                VariantValue::String(value) => Some((key, value.as_str())),
+                _ => None,
+            })
+            .collect();
+        entries.sort_by_key(|(key, _)| key.as_str());
+        for (i, (key, value)) in entries.iter().enumerate() {
+            if value.is_empty() {
+                return Err(DomainError::EmptyStringVariant {
+                    variant: key.as_str().to_owned(),
+                });
+            }
+            if let Some((other, _)) = entries[i + 1..].iter().find(|(_, v)| v == value) {
+                return Err(DomainError::DuplicateVariantValue {
+                    variant: key.as_str().to_owned(),
+                    other: other.as_str().to_owned(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tags every bare boolean or string value of a serialized variant set in
+/// place; see [`Variants::from_legacy_value`].
+pub(crate) fn upgrade_legacy_values(variants: &mut serde_json::Value) {
+    let Some(entries) = variants
+        .get_mut("entries")
+        .and_then(serde_json::Value::as_object_mut)
+    else {
+        return;
+    };
+    for value in entries.values_mut() {
+        let tag = match value {
+            serde_json::Value::Bool(_) => "bool",
+            serde_json::Value::String(_) => "string",
+            _ => continue,
+        };
+        let legacy = value.take();
+        *value = serde_json::json!({ tag: legacy });
+    }
 }
 
 #[cfg(test)]
@@ -246,4 +312,31 @@ mod tests {
         let back: Variants = serde_json::from_str(&json).unwrap();
         assert_eq!(back, variants);
     }
+
+    #[test]
+    fn legacy_untagged_values_are_upgraded() {
+        let json = serde_json::json!({
+            "value_type": "string",
+            "entries": {"a": "alpha", "b": {"string": "beta"}}
+        });
+        let variants = Variants::from_legacy_value(json).unwrap();
+        assert_eq!(
+            variants.get(&vk("a")),
+            Some(&VariantValue::String("alpha".into()))
+        );
+        assert_eq!(
+            variants.get(&vk("b")),
+            Some(&VariantValue::String("beta".into()))
+        );
+    }
+
+    #[test]
+    fn legacy_reading_keeps_the_set_invariants() {
+        for json in [
+            serde_json::json!({"value_type": "boolean", "entries": {"on": "yes"}}),
+            serde_json::json!({"value_type": "number", "entries": {"one": 1}}),
+        ] {
+            assert!(Variants::from_legacy_value(json).is_err());
+        }
+    }
 }
@@ -0,0 +1,75 @@
+//! Evaluation benchmarks: a targeted flag evaluated against contexts of
+//! growing size, the hot path of a server resolving rich user contexts.
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion};
+use flaps_eval::{EvaluationContext, FlagSet};
+use serde_json::json;
+
+/// A flag set with one string flag behind a rule reading two attributes.
+fn flag_set() -> FlagSet {
+    FlagSet::from_json(
+        r#"{
+            "flags": {
+                "checkout": {
+                    "state": "ENABLED",
+                    "variants": { "old": "v1", "new": "v2" },
+                    "defaultVariant": "old",
+                    "targeting": {
+                        "if": [
+                            {"and": [
+                                {"==": [{"var": "country"}, "FR"]},
+                                {"in": [{"var": "user.plan"}, ["pro", "team"]]}
+                            ]},
+                            "new",
+                            {"fractional": [["old", 50], ["new", 50]]}
+                        ]
+                    }
+                }
+            }
+        }"#,
+    )
+    .expect("valid flag set")
+}
+
+/// A context with `size` attributes, among them the two the rule reads.
+fn context(size: usize) -> EvaluationContext {
+    let mut attributes = BTreeMap::from([
+        ("country".to_owned(), json!("FR")),
+        (
+            "user".to_owned(),
+            json!({ "plan": "pro", "email": "a@example.com" }),
+        ),
+    ]);
+    for i in attributes.len()..size {
+        attributes.insert(
+            format!("attribute-{i}"),
+            json!({ "value": i, "tags": ["a", "b"] }),
+        );
+    }
+    EvaluationContext {
+        targeting_key: Some("user-1".to_owned()),
+        attributes,
+        ..EvaluationContext::default()
+    }
+}
+
+fn evaluate(c: &mut Criterion) {
+    let set = flag_set();
+    let mut group = c.benchmark_group("evaluate");
+    for size in [2, 16, 128] {
+        let context = context(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &context, |b, context| {
+            b.iter(|| set.evaluate(black_box("checkout"), black_box(context)));
+        });
+    }
+    group.finish();
+}
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    evaluate(&mut criterion);
+    criterion.final_summary();
+}
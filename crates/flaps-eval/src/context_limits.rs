@@ -0,0 +1,153 @@
+//! Size and cardinality guards for evaluation contexts received over the wire.
+//!
+//! Evaluation itself is bounded by the ruleset, but a context comes from the
+//! caller: a buggy client could send thousands of attributes or megabyte
+//! strings. [`EvaluationContext::validate_limits`] checks a context against
+//! [`ContextLimits`] so a server can refuse it before evaluating, and a
+//! remote SDK can catch the same mistake before sending.
+
+use serde_json::Value;
+
+use crate::eval::EvaluationContext;
+
+/// Default for [`ContextLimits::max_attributes`].
+pub const DEFAULT_MAX_CONTEXT_ATTRIBUTES: usize = 256;
+
+/// Default for [`ContextLimits::max_key_length`].
+pub const DEFAULT_MAX_CONTEXT_KEY_LENGTH: usize = 256;
+
+/// Default for [`ContextLimits::max_value_length`].
+pub const DEFAULT_MAX_CONTEXT_VALUE_LENGTH: usize = 4096;
+
+/// Default for [`ContextLimits::max_list_length`].
+pub const DEFAULT_MAX_CONTEXT_LIST_LENGTH: usize = 1024;
+
+/// Ceilings applied by [`EvaluationContext::validate_limits`].
+///
+/// Lengths are in bytes of UTF-8. Nested objects and lists are checked at
+/// every level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextLimits {
+    /// Maximum number of attributes, and of members in any nested object.
+    pub max_attributes: usize,
+    /// Maximum length of an attribute name or nested object key.
+    pub max_key_length: usize,
+    /// Maximum length of a string value, including the targeting key.
+    pub max_value_length: usize,
+    /// Maximum number of elements in a list value.
+    pub max_list_length: usize,
+}
+
+impl Default for ContextLimits {
+    fn default() -> Self {
+        Self {
+            max_attributes: DEFAULT_MAX_CONTEXT_ATTRIBUTES,
+            max_key_length: DEFAULT_MAX_CONTEXT_KEY_LENGTH,
+            max_value_length: DEFAULT_MAX_CONTEXT_VALUE_LENGTH,
+            max_list_length: DEFAULT_MAX_CONTEXT_LIST_LENGTH,
+        }
+    }
+}
+
+/// A context exceeding one of its [`ContextLimits`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("context `{path}` exceeds {limit}: {actual} > {max}")]
+pub struct ContextLimitError {
+    /// Name of the exceeded limit (e.g. `max_value_length`).
+    pub limit: &'static str,
+    /// Path of the offending attribute (`plan.tags[3]`), empty for the
+    /// attribute count of the context itself.
+    pub path: String,
+    /// The configured ceiling.
+    pub max: usize,
+    /// The value that was found.
+    pub actual: usize,
+}
+
+impl EvaluationContext {
+    /// Checks this context against `limits`.
+    ///
+    /// The walk is iterative and stops at the first violation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextLimitError`] naming the first limit exceeded.
+    pub fn validate_limits(&self, limits: &ContextLimits) -> Result<(), ContextLimitError> {
+        let check = |limit, path: &str, max, actual| {
+            if actual > max {
+                Err(ContextLimitError {
+                    limit,
+                    path: path.to_owned(),
+                    max,
+                    actual,
+                })
+            } else {
+                Ok(())
+            }
+        };
+
+        if let Some(key) = &self.targeting_key {
+            check(
+                "max_value_length",
+                "targetingKey",
+                limits.max_value_length,
+                key.len(),
+            )?;
+        }
+        check(
+            "max_attributes",
+            "",
+            limits.max_attributes,
+            self.attributes.len(),
+        )?;
+
+        let mut stack: Vec<(String, &Value)> = Vec::new();
+        for (name, value) in &self.attributes {
+            check("max_key_length", name, limits.max_key_length, name.len())?;
+            stack.push((name.clone(), value));
+        }
+        while let Some((path, value)) = stack.pop() {
+            match value {
+                Value::String(text) => {
+                    check(
+                        "max_value_length",
+                        &path,
+                        limits.max_value_length,
+                        text.len(),
+                    )?;
+                }
+                Value::Array(items) => {
+                    check(
+                        "max_list_length",
+                        &path,
+                        limits.max_list_length,
+                        items.len(),
+                    )?;
+                    for (i, item) in items.iter().enumerate() {
+                        stack.push((format!("{path}[{i}]"), item));
+                    }
+                }
+                Value::Object(members) => {
+                    check(
+                        "max_attributes",
+                        &path,
+                        limits.max_attributes,
+                        members.len(),
+                    )?;
+                    for (key, member) in members {
+                        let member_path = format!("{path}.{key}");
+                        check(
+                            "max_key_length",
+                            &member_path,
+                            limits.max_key_length,
+                            key.len(),
+                        )?;
+                        stack.push((member_path, member));
+                    }
+                }
+                Value::Null | Value::Bool(_) | Value::Number(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+//! Evaluation contexts written as flat `key=value` pairs.
+//!
+//! Debugging tools take a context on the command line or in a query string,
+//! where every value is text. [`EvaluationContext::from_pairs`] reads those
+//! pairs with one set of typing rules so a value means the same thing
+//! wherever it is typed, and [`EvaluationContext::to_pairs`] writes a context
+//! back in a form that reads to the same context.
+//!
+//! # Typing
+//!
+//! Without a suffix, a value is inferred as:
+//!
+//! - `true` / `false`: a boolean;
+//! - `null`: null, an attribute that is present but unset;
+//! - a JSON number (`12`, `-0.5`, `1e3`): a number. `007` is not one and
+//!   stays a string;
+//! - a JSON string, array or object (`"007"`, `["a","b"]`, `{"k":1}`):
+//!   that value;
+//! - text containing a comma (`a,b`): a list of strings, split on commas;
+//! - anything else: a string.
+//!
+//! A suffix on the key forces the type: `zip:string=02134` is a string,
+//! `tags:list=solo` a one-element list. The suffixes are `string`, `number`,
+//! `bool`, `list` and `json`. A key ending in anything else after its last
+//! `:` is taken whole, so `app:env=prod` sets the attribute `app:env`.
+//!
+//! The key `targetingKey` sets [`EvaluationContext::targeting_key`], always
+//! as a string.
+
+use serde_json::Value;
+
+use crate::eval::EvaluationContext;
+
+/// The pair key that sets the targeting key.
+const TARGETING_KEY: &str = "targetingKey";
+
+/// A pair that cannot be read into a context.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ContextPairError {
+    /// The key is empty, or only a type suffix.
+    #[error("empty attribute name in pair `{0}`")]
+    EmptyKey(String),
+    /// The value does not parse as the type its suffix forces.
+    #[error("`{key}`: `{value}` is not a valid {expected}")]
+    InvalidValue {
+        /// The attribute name, without suffix.
+        key: String,
+        /// The rejected value.
+        value: String,
+        /// The forced type (`number`, `bool` or `json`).
+        expected: &'static str,
+    },
+}
+
+/// A type a key suffix can force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PairType {
+    String,
+    Number,
+    Bool,
+    List,
+    Json,
+}
+
+impl PairType {
+    fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "string" => Some(Self::String),
+            "number" => Some(Self::Number),
+            "bool" => Some(Self::Bool),
+            "list" => Some(Self::List),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::List => "list",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl EvaluationContext {
+    /// Builds a context from `key=value` pairs, typing each value per the
+    /// [module rules](self). A later pair for the same attribute wins. The
+    /// timestamp is left at `0` for the caller to stamp.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextPairError`] for an empty key, or a value that does
+    /// not parse as the type its suffix forces.
+    pub fn from_pairs(pairs: &[(String, String)]) -> Result<Self, ContextPairError> {
+        let mut context = Self::default();
+        for (key, raw) in pairs {
+            let (name, forced) = split_suffix(key);
+            if name.is_empty() {
+                return Err(ContextPairError::EmptyKey(format!("{key}={raw}")));
+            }
+            if name == TARGETING_KEY {
+                context.targeting_key = Some(raw.clone());
+                continue;
+            }
+            let value = match forced {
+                Some(forced) => parse_as(name, raw, forced)?,
+                None => infer(raw),
+            };
+            context.attributes.insert(name.to_owned(), value);
+        }
+        Ok(context)
+    }
+
+    /// Writes this context as pairs that [`Self::from_pairs`] reads back to
+    /// the same targeting key and attributes.
+    ///
+    /// The targeting key comes first, then attributes in key order. A suffix
+    /// is added only where inference would read the value differently
+    /// (`zip:string=02134`). The timestamp is not written.
+    #[must_use]
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        let targeting = self
+            .targeting_key
+            .iter()
+            .map(|key| (TARGETING_KEY.to_owned(), key.clone()));
+        let attributes = self
+            .attributes
+            .iter()
+            .filter(|(name, _)| name.as_str() != TARGETING_KEY)
+            .map(|(name, value)| {
+                let (raw, kind) = render(value);
+                let ambiguous_name = split_suffix(name).1.is_some();
+                if ambiguous_name || infer(&raw) != *value {
+                    (format!("{name}:{}", kind.suffix()), raw)
+                } else {
+                    (name.clone(), raw)
+                }
+            });
+        targeting.chain(attributes).collect()
+    }
+}
+
+/// Splits a `name:type` key into the name and the forced type, when the
+/// part after the last `:` is a known type.
+fn split_suffix(key: &str) -> (&str, Option<PairType>) {
+    key.rsplit_once(':')
+        .and_then(|(name, suffix)| PairType::from_suffix(suffix).map(|kind| (name, Some(kind))))
+        .unwrap_or((key, None))
+}
+
+/// Reads `raw` as the type its suffix forces.
+fn parse_as(name: &str, raw: &str, forced: PairType) -> Result<Value, ContextPairError> {
+    let invalid = |expected| ContextPairError::InvalidValue {
+        key: name.to_owned(),
+        value: raw.to_owned(),
+        expected,
+    };
+    match forced {
+        PairType::String => Ok(Value::String(raw.to_owned())),
+        PairType::List => Ok(split_list(raw)),
+        PairType::Bool => match raw {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(invalid("bool")),
+        },
+        PairType::Number => match serde_json::from_str(raw) {
+            Ok(number @ Value::Number(_)) => Ok(number),
+            _ => Err(invalid("number")),
+        },
+        PairType::Json => serde_json::from_str(raw).map_err(|_| invalid("json")),
+    }
+}
+
+/// Infers the type of an unsuffixed value.
+fn infer(raw: &str) -> Value {
+    match raw {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        "null" => return Value::Null,
+        _ => {}
+    }
+    if let Ok(value) = serde_json::from_str::<Value>(raw) {
+        if !matches!(value, Value::Bool(_) | Value::Null) {
+            return value;
+        }
+    }
+    if raw.contains(',') {
+        return split_list(raw);
+    }
+    Value::String(raw.to_owned())
+}
+
+/// Splits comma-separated text into a list of strings; empty text is an
+/// empty list.
+fn split_list(raw: &str) -> Value {
+    if raw.is_empty() {
+        return Value::Array(Vec::new());
+    }
+    raw.split(',')
+        .map(|item| Value::String(item.to_owned()))
+        .collect()
+}
+
+/// Writes `value` as text with the type that reads it back exactly.
+fn render(value: &Value) -> (String, PairType) {
+    match value {
+        Value::String(text) => (text.clone(), PairType::String),
+        Value::Bool(flag) => (flag.to_string(), PairType::Bool),
+        Value::Number(number) => (number.to_string(), PairType::Number),
+        Value::Array(items) => {
+            let texts: Option<Vec<&str>> = items
+                .iter()
+                .map(|item| item.as_str().filter(|text| !text.contains(',')))
+                .collect();
+            match texts {
+                // An empty string item would read back as nothing in a
+                // single-item list: JSON keeps it exact.
+                Some(texts) if texts != [""] => (texts.join(","), PairType::List),
+                _ => (value.to_string(), PairType::Json),
+            }
+        }
+        Value::Null | Value::Object(_) => (value.to_string(), PairType::Json),
+    }
+}
@@ -0,0 +1,109 @@
+//! Embedder-defined targeting operators.
+//!
+//! Some matching needs ("within business hours in the user's timezone") do
+//! not fit JsonLogic or the flagd operations. An embedder registers them by
+//! name in [`CustomOperators`] and parses rulesets with
+//! [`FlagSet::from_json_with_operators`]; a rule then calls one as
+//! `{"name": [left, right]}` and it resolves to a boolean.
+//!
+//! Operators are bound into the rule tree at parse time, so evaluation pays
+//! one indirect call per custom node and nothing on built-in ones. A name the
+//! registry does not know is refused like any unknown operator, and a
+//! registered name never shadows a built-in one.
+//!
+//! [`FlagSet::from_json_with_operators`]: crate::FlagSet::from_json_with_operators
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::targeting::Rule;
+
+/// The function behind a custom operator: receives its two evaluated
+/// operands, returns whether they match.
+pub type CustomOperatorFn = dyn Fn(&Value, &Value) -> bool + Send + Sync;
+
+/// Custom operators available to a ruleset, by name.
+///
+/// Registration happens before parsing: a parsed [`FlagSet`] holds the
+/// operators it uses and is immutable, so it can be shared across threads
+/// like any other. Operators must therefore be `Send + Sync`, and should be
+/// pure: the same operands must always give the same answer for evaluation
+/// to stay deterministic.
+///
+/// [`FlagSet`]: crate::FlagSet
+#[derive(Clone, Default)]
+pub struct CustomOperators {
+    operators: BTreeMap<String, Arc<CustomOperatorFn>>,
+}
+
+impl CustomOperators {
+    /// Creates an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `operator` under `name`, replacing any previous one.
+    ///
+    /// A name already used by a JsonLogic or flagd operator is accepted but
+    /// never dispatched to: built-in operators take precedence.
+    pub fn register_operator(
+        &mut self,
+        name: impl Into<String>,
+        operator: impl Fn(&Value, &Value) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.operators.insert(name.into(), Arc::new(operator));
+        self
+    }
+
+    /// Returns the operator registered under `name`.
+    pub(crate) fn get(&self, name: &str) -> Option<&Arc<CustomOperatorFn>> {
+        self.operators.get(name)
+    }
+}
+
+impl fmt::Debug for CustomOperators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.operators.keys()).finish()
+    }
+}
+
+/// A call to a registered custom operator, bound at parse time.
+#[derive(Clone)]
+pub struct CustomCall {
+    /// The operator name, as written in the ruleset.
+    pub name: String,
+    /// The left operand.
+    pub left: Box<Rule>,
+    /// The right operand.
+    pub right: Box<Rule>,
+    pub(crate) operator: Arc<CustomOperatorFn>,
+}
+
+impl CustomCall {
+    /// Applies the operator to evaluated operands.
+    pub(crate) fn call(&self, left: &Value, right: &Value) -> bool {
+        (self.operator)(left, right)
+    }
+}
+
+impl fmt::Debug for CustomCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomCall")
+            .field("name", &self.name)
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Calls compare by name and operands: two rulesets parsed with different
+/// registries are equal when their rules read the same.
+impl PartialEq for CustomCall {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.left == other.left && self.right == other.right
+    }
+}
@@ -56,6 +56,19 @@ pub enum ParseError {
         reference: String,
     },
 
+    /// A targeting rule is nested deeper than
+    /// [`MAX_RULE_DEPTH`](crate::MAX_RULE_DEPTH) levels.
+    ///
+    /// Checked once, while parsing, so evaluation never recurses into an
+    /// unbounded rule.
+    #[error("rule at `{path}` is nested deeper than {max_depth} levels")]
+    RuleTooDeep {
+        /// JSON path of the rule past the limit.
+        path: String,
+        /// The depth ceiling that was exceeded.
+        max_depth: usize,
+    },
+
     /// Evaluator references form a cycle and cannot be inlined.
     #[error("evaluator reference cycle involving `{reference}`")]
     EvaluatorCycle {
@@ -63,3 +76,32 @@ pub enum ParseError {
         reference: String,
     },
 }
+
+/// An error encountered while decoding a binary flag set snapshot.
+///
+/// See [`FlagSet::from_snapshot`](crate::FlagSet::from_snapshot). A
+/// [`SnapshotError::UnsupportedVersion`] is the signal for a consumer to fall
+/// back to the JSON representation.
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    /// The snapshot is empty; not even the format version byte is present.
+    #[error("empty snapshot")]
+    Empty,
+
+    /// The snapshot was written in a format version this build cannot read.
+    #[error("unsupported snapshot format version {found} (expected {expected})")]
+    UnsupportedVersion {
+        /// Version byte found at the start of the snapshot.
+        found: u8,
+        /// The only version this build reads.
+        expected: u8,
+    },
+
+    /// The payload is not a valid encoding of a document.
+    #[error("invalid snapshot payload: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+
+    /// The decoded document does not describe a valid flag set.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
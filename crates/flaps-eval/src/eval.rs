@@ -7,11 +7,18 @@
 //! or fall back to the default variant with reason [`Reason::Default`] when
 //! the rule returns `null`.
 
+use std::cell::Cell;
 use std::collections::BTreeMap;
+use std::time::Duration;
 
-use serde_json::{Value, json};
+use serde_json::Value;
 
-use crate::model::{FlagSet, Metadata, State, Variants};
+use crate::limits::{Budget, MAX_EVALUATION_STEPS};
+use crate::model::{
+    APP_VERSION_ATTRIBUTE, FlagSet, KILL_REASON_METADATA, MIN_APP_VERSION_METADATA, Metadata,
+    MetadataValue, State, Variants,
+};
+use crate::value::{Fallback, Fallbacks, FlagValue};
 
 /// The context a targeting rule evaluates against.
 ///
@@ -28,6 +35,75 @@ pub struct EvaluationContext {
     pub timestamp: u64,
 }
 
+impl EvaluationContext {
+    /// Rounds [`Self::timestamp`] down to a multiple of `quantum`.
+    ///
+    /// Evaluators whose clocks disagree by less than `quantum` then see the
+    /// same `$flagd.timestamp` for as long as no multiple of `quantum` lies
+    /// between their clocks, so a time-based rule flips on every node at the
+    /// same boundary rather than skew-dependently. Near a boundary, nodes can
+    /// still disagree for up to the skew. A larger quantum makes that rarer
+    /// at the cost of coarser activation times. A `quantum` under one second
+    /// leaves the timestamp unchanged.
+    #[must_use]
+    pub fn with_timestamp_quantum(mut self, quantum: Duration) -> Self {
+        let step = quantum.as_secs();
+        if step > 1 {
+            self.timestamp -= self.timestamp % step;
+        }
+        self
+    }
+
+    /// Layers `other` over this context: where both set a value, `other`'s
+    /// wins. Same as [`Self::merge_with`] with [`MergePrecedence::Other`].
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        self.merge_with(other, MergePrecedence::Other)
+    }
+
+    /// Fills in from `other` only what this context leaves unset: attributes
+    /// it lacks, and its targeting key and timestamp when absent. Same as
+    /// [`Self::merge_with`] with [`MergePrecedence::Base`].
+    #[must_use]
+    pub fn merge_fill(self, other: Self) -> Self {
+        self.merge_with(other, MergePrecedence::Base)
+    }
+
+    /// Merges two contexts, resolving every value both set by `precedence`.
+    ///
+    /// The merge is shallow: a top-level attribute present on both sides is
+    /// taken whole from the winning side, object values included. A targeting
+    /// key of `None` and a timestamp of `0` count as unset, so the other
+    /// side's value is kept whatever the precedence.
+    #[must_use]
+    pub fn merge_with(self, other: Self, precedence: MergePrecedence) -> Self {
+        let (mut winner, loser) = match precedence {
+            MergePrecedence::Other => (other, self),
+            MergePrecedence::Base => (self, other),
+        };
+        for (key, value) in loser.attributes {
+            winner.attributes.entry(key).or_insert(value);
+        }
+        if winner.targeting_key.is_none() {
+            winner.targeting_key = loser.targeting_key;
+        }
+        if winner.timestamp == 0 {
+            winner.timestamp = loser.timestamp;
+        }
+        winner
+    }
+}
+
+/// Which side of [`EvaluationContext::merge_with`] wins a value both set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePrecedence {
+    /// The merged-in context wins, as with a per-request context layered
+    /// over service defaults.
+    Other,
+    /// The receiving context wins; the other only fills in what it lacks.
+    Base,
+}
+
 /// Why an evaluation resolved the way it did.
 ///
 /// Mirrors the OpenFeature resolution reasons produced by flagd providers.
@@ -44,6 +120,26 @@ pub enum Reason {
     Default,
     /// The flag is disabled; the caller serves its own code default.
     Disabled,
+    /// The flag's kill switch is engaged. It serves like [`Self::Disabled`];
+    /// the kill reason is in the resolution metadata, under
+    /// [`KILL_REASON_METADATA`](crate::KILL_REASON_METADATA).
+    Killed,
+    /// The flag requires a newer app than the context's
+    /// [`APP_VERSION_ATTRIBUTE`](crate::APP_VERSION_ATTRIBUTE), or the
+    /// context has none. It serves like [`Self::Disabled`]; the minimum is
+    /// in the resolution metadata, under
+    /// [`MIN_APP_VERSION_METADATA`](crate::MIN_APP_VERSION_METADATA).
+    VersionGated,
+}
+
+impl Reason {
+    /// Returns `true` for the reasons of a flag that is off, disabled,
+    /// killed or version gated: no rule was evaluated and the caller serves
+    /// its own code default.
+    #[must_use]
+    pub fn is_disabled(self) -> bool {
+        matches!(self, Self::Disabled | Self::Killed | Self::VersionGated)
+    }
 }
 
 /// The outcome of a successful evaluation.
@@ -63,6 +159,39 @@ pub struct Resolution {
     pub metadata: Metadata,
 }
 
+impl Resolution {
+    /// Returns `true` when the caller must serve its own code default: the
+    /// flag is disabled, or no variant was resolved and the flag defines no
+    /// default variant.
+    #[must_use]
+    pub fn uses_caller_default(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// Returns the value to serve for a typed accessor: the resolved value
+    /// when it converts to `T` (see [`FlagValue`]), `default` otherwise.
+    ///
+    /// This is the single fail-closed default policy: whatever the
+    /// [`Reason`], a missing value (see [`Self::uses_caller_default`]) and a
+    /// value of another type both serve `default`. A [`Reason::Default`]
+    /// resolution that carries the flag's default variant serves that
+    /// variant, not `default`.
+    #[must_use]
+    pub fn value_or<T: FlagValue>(&self, default: T) -> T {
+        self.value_or_with(default, Fallback::CallerDefault)
+    }
+
+    /// Returns the value to serve like [`Self::value_or`], applying
+    /// `fallback` when there is none (see [`Fallback::apply`]).
+    #[must_use]
+    pub fn value_or_with<T: FlagValue>(&self, default: T, fallback: Fallback) -> T {
+        self.value
+            .as_ref()
+            .and_then(T::from_flag_value)
+            .unwrap_or_else(|| fallback.apply(default))
+    }
+}
+
 /// An error produced while evaluating a flag.
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum EvaluationError {
@@ -88,13 +217,166 @@ pub enum EvaluationError {
 
     /// The rule uses a flagd custom operation not implemented yet.
     ///
-    /// Temporary variant covering `fractional`, `sem_ver`, `starts_with`
-    /// and `ends_with` until the custom operations land.
+    /// Every flagd custom operation is implemented now, so evaluation no
+    /// longer produces this variant; an unresolved `$ref`, which used to, is
+    /// reported as [`EvaluationError::UnresolvedEvaluator`].
     #[error("custom operation `{operator}` is not implemented yet")]
     UnsupportedOperation {
         /// Name of the unimplemented operator.
         operator: &'static str,
     },
+
+    /// Evaluating the targeting rule took more than `max_steps` reductions.
+    ///
+    /// Raised as soon as the budget runs out (see
+    /// [`MAX_EVALUATION_STEPS`]), so targeting iterating over large context
+    /// lists fails closed instead of stalling its caller.
+    ///
+    /// [`MAX_EVALUATION_STEPS`]: crate::MAX_EVALUATION_STEPS
+    #[error("targeting of flag `{flag_key}` exceeded its budget of {max_steps} evaluation steps")]
+    BudgetExceeded {
+        /// Key of the offending flag.
+        flag_key: String,
+        /// The step budget that was exhausted.
+        max_steps: usize,
+    },
+
+    /// The targeting rule reached a `$ref` to a shared evaluator that was
+    /// never resolved.
+    ///
+    /// Parsing a document inlines every reference, so this only happens to
+    /// a [`FlagSet`] whose rules were assembled without the evaluators they
+    /// reference. The flag fails closed rather than treating the missing
+    /// rule as a non-match.
+    #[error(
+        "targeting of flag `{flag_key}` references evaluator `{reference}`, which is not loaded"
+    )]
+    UnresolvedEvaluator {
+        /// Key of the offending flag.
+        flag_key: String,
+        /// Name of the missing evaluator.
+        reference: String,
+    },
+
+    /// A strict evaluation compared an operand of a type the operator cannot
+    /// compare, such as a non-numeric string ordered against a number.
+    ///
+    /// Only an evaluation under [`EvaluationOptions::strict`] raises this;
+    /// [`FlagSet::evaluate`] treats the comparison as a non-match, per the
+    /// JsonLogic semantics.
+    #[error(
+        "targeting of flag `{flag_key}` applied `{operator}` to {operand}, which it cannot compare"
+    )]
+    TypeMismatch {
+        /// Key of the offending flag.
+        flag_key: String,
+        /// Name of the operator.
+        operator: &'static str,
+        /// The operand of the wrong type.
+        operand: serde_json::Value,
+    },
+}
+
+impl EvaluationError {
+    /// Attributes an error raised while reducing a targeting rule, which
+    /// does not know the flag it belongs to, to `flag_key`.
+    fn in_flag(self, flag_key: &str) -> Self {
+        match self {
+            Self::UnresolvedEvaluator { reference, .. } => Self::UnresolvedEvaluator {
+                flag_key: flag_key.to_owned(),
+                reference,
+            },
+            Self::BudgetExceeded { max_steps, .. } => Self::BudgetExceeded {
+                flag_key: flag_key.to_owned(),
+                max_steps,
+            },
+            Self::TypeMismatch {
+                operator, operand, ..
+            } => Self::TypeMismatch {
+                flag_key: flag_key.to_owned(),
+                operator,
+                operand,
+            },
+            other => other,
+        }
+    }
+}
+
+/// Options an evaluation runs under.
+///
+/// The default is what [`FlagSet::evaluate`] uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvaluationOptions {
+    /// Fail with [`EvaluationError::TypeMismatch`] when targeting compares a
+    /// context value of the wrong type, instead of treating the comparison
+    /// as a non-match.
+    pub strict: bool,
+}
+
+/// Where an evaluation landed in a `fractional` rollout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RolloutPosition {
+    /// The bucket, in `[0, total_weight)`; see [`crate::bucket_for`].
+    pub bucket: u64,
+    /// The sum of the rollout's weights.
+    pub total_weight: u64,
+}
+
+/// A resolution together with how targeting reached it, returned by
+/// [`FlagSet::explain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// The evaluation outcome, as [`FlagSet::evaluate`] returns it.
+    pub resolution: Resolution,
+    /// Zero-based index of the top-level `if` pair whose condition held:
+    /// for compiled targeting, the targeting rule that matched. `None` when
+    /// the default was served or the targeting is not an `if`.
+    pub matched_rule: Option<usize>,
+    /// Where the evaluation landed in the last `fractional` rule reduced,
+    /// when one was.
+    pub rollout: Option<RolloutPosition>,
+}
+
+/// One evaluation in progress: the steps it may still take, the options it
+/// runs under, and what [`FlagSet::explain`] reports of it.
+#[derive(Debug)]
+pub(crate) struct Run {
+    budget: Budget,
+    options: EvaluationOptions,
+    matched_rule: Cell<Option<usize>>,
+    rollout: Cell<Option<RolloutPosition>>,
+}
+
+impl Run {
+    /// A run allowing `max_steps` reductions under `options`.
+    fn new(max_steps: usize, options: EvaluationOptions) -> Self {
+        Self {
+            budget: Budget::new(max_steps),
+            options,
+            matched_rule: Cell::new(None),
+            rollout: Cell::new(None),
+        }
+    }
+
+    /// Records the top-level targeting branch that matched.
+    pub(crate) fn record_matched_rule(&self, index: Option<usize>) {
+        self.matched_rule.set(index);
+    }
+
+    /// Records where the evaluation landed in a rollout.
+    pub(crate) fn record_rollout(&self, position: RolloutPosition) {
+        self.rollout.set(Some(position));
+    }
+
+    /// Accounts for one reduction; see [`Budget::spend`].
+    pub(crate) fn spend(&self) -> Result<(), EvaluationError> {
+        self.budget.spend()
+    }
+
+    /// Whether type mismatches are errors rather than non-matches.
+    pub(crate) fn is_strict(&self) -> bool {
+        self.options.strict
+    }
 }
 
 impl FlagSet {
@@ -110,11 +392,128 @@ impl FlagSet {
     /// [`EvaluationError::InvalidVariant`] when targeting resolves to a
     /// value that selects no variant, and
     /// [`EvaluationError::UnsupportedOperation`] when the rule reaches a
-    /// custom operation that is not implemented yet.
+    /// custom operation that is not implemented yet,
+    /// [`EvaluationError::BudgetExceeded`] when it takes more than
+    /// [`MAX_EVALUATION_STEPS`] reductions, and
+    /// [`EvaluationError::UnresolvedEvaluator`] when it reaches a `$ref`
+    /// that was never resolved. Every error names the flag and the point of
+    /// failure in its message.
     pub fn evaluate(
         &self,
         flag_key: &str,
         context: &EvaluationContext,
+    ) -> Result<Resolution, EvaluationError> {
+        self.evaluate_with_budget(flag_key, context, MAX_EVALUATION_STEPS)
+    }
+
+    /// Evaluates a flag and returns the value a typed accessor serves,
+    /// falling back as `fallbacks` registers for `flag_key`.
+    ///
+    /// An evaluation error, a missing flag included, falls back like a flag
+    /// without a value (see [`Resolution::value_or_with`]), so a gate
+    /// registered as [`Fallback::Closed`] stays off and a configuration flag
+    /// serves `default`.
+    #[must_use]
+    pub fn value_or<T: FlagValue>(
+        &self,
+        flag_key: &str,
+        context: &EvaluationContext,
+        default: T,
+        fallbacks: &Fallbacks,
+    ) -> T {
+        let fallback = fallbacks.for_flag(flag_key);
+        match self.evaluate(flag_key, context) {
+            Ok(resolution) => resolution.value_or_with(default, fallback),
+            Err(_) => fallback.apply(default),
+        }
+    }
+
+    /// Evaluates a flag like [`Self::evaluate`], allowing its targeting
+    /// `max_steps` rule reductions instead of [`MAX_EVALUATION_STEPS`].
+    ///
+    /// # Errors
+    ///
+    /// As [`Self::evaluate`], with [`EvaluationError::BudgetExceeded`]
+    /// reporting `max_steps`.
+    pub fn evaluate_with_budget(
+        &self,
+        flag_key: &str,
+        context: &EvaluationContext,
+        max_steps: usize,
+    ) -> Result<Resolution, EvaluationError> {
+        self.evaluate_on(
+            flag_key,
+            context,
+            &Run::new(max_steps, EvaluationOptions::default()),
+        )
+    }
+
+    /// Evaluates a flag like [`Self::evaluate`], but fails when targeting
+    /// compares a context value of the wrong type instead of treating the
+    /// comparison as a non-match.
+    ///
+    /// A numeric comparison against a string that does not parse as a
+    /// number, or `starts_with` on a number, silently never matches under
+    /// [`Self::evaluate`]. Staging and CI can evaluate strictly to surface
+    /// such a misconfigured rule or context; production stays lenient. An
+    /// absent or `null` attribute is never a mismatch.
+    ///
+    /// # Errors
+    ///
+    /// As [`Self::evaluate`], and [`EvaluationError::TypeMismatch`] naming
+    /// the operator and the offending operand.
+    pub fn evaluate_strict(
+        &self,
+        flag_key: &str,
+        context: &EvaluationContext,
+    ) -> Result<Resolution, EvaluationError> {
+        self.evaluate_with_options(flag_key, context, EvaluationOptions { strict: true })
+    }
+
+    /// Evaluates a flag like [`Self::evaluate`], under `options`.
+    ///
+    /// # Errors
+    ///
+    /// As [`Self::evaluate`], and [`EvaluationError::TypeMismatch`] when
+    /// `options` is strict.
+    pub fn evaluate_with_options(
+        &self,
+        flag_key: &str,
+        context: &EvaluationContext,
+        options: EvaluationOptions,
+    ) -> Result<Resolution, EvaluationError> {
+        self.evaluate_on(flag_key, context, &Run::new(MAX_EVALUATION_STEPS, options))
+    }
+
+    /// Evaluates a flag like [`Self::evaluate`], also reporting the targeting
+    /// rule that matched and the rollout bucket the context landed in.
+    ///
+    /// Meant for debugging a rule, as `flapsd eval` does; serving paths use
+    /// [`Self::evaluate`].
+    ///
+    /// # Errors
+    ///
+    /// As [`Self::evaluate`].
+    pub fn explain(
+        &self,
+        flag_key: &str,
+        context: &EvaluationContext,
+    ) -> Result<Explanation, EvaluationError> {
+        let run = Run::new(MAX_EVALUATION_STEPS, EvaluationOptions::default());
+        let resolution = self.evaluate_on(flag_key, context, &run)?;
+        Ok(Explanation {
+            resolution,
+            matched_rule: run.matched_rule.get(),
+            rollout: run.rollout.get(),
+        })
+    }
+
+    /// Evaluates a flag, reducing its targeting in `run`.
+    fn evaluate_on(
+        &self,
+        flag_key: &str,
+        context: &EvaluationContext,
+        run: &Run,
     ) -> Result<Resolution, EvaluationError> {
         let flag = self
             .flags
@@ -123,20 +522,48 @@ impl FlagSet {
                 flag_key: flag_key.to_owned(),
             })?;
         let mut metadata = self.metadata.clone();
-        metadata.extend(flag.metadata.clone());
+        metadata.extend(
+            flag.metadata
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
         if flag.state == State::Disabled {
+            let killed = matches!(
+                flag.metadata.get(KILL_REASON_METADATA),
+                Some(MetadataValue::String(_))
+            );
             return Ok(Resolution {
                 value: None,
                 variant: None,
-                reason: Reason::Disabled,
+                reason: if killed {
+                    Reason::Killed
+                } else {
+                    Reason::Disabled
+                },
+                metadata,
+            });
+        }
+        if let Some(MetadataValue::String(min_version)) =
+            flag.metadata.get(MIN_APP_VERSION_METADATA)
+            && crate::semver::below_min_version(
+                context.attributes.get(APP_VERSION_ATTRIBUTE),
+                min_version,
+            )
+        {
+            return Ok(Resolution {
+                value: None,
+                variant: None,
+                reason: Reason::VersionGated,
                 metadata,
             });
         }
         let (variant, reason) = match &flag.targeting {
             None => (flag.default_variant.clone(), Reason::Static),
             Some(targeting) => {
-                let scope = evaluation_scope(flag_key, context);
-                match crate::logic::apply(targeting, &scope)? {
+                let scope = crate::scope::evaluation_scope(flag_key, targeting, context);
+                let outcome = crate::logic::apply_targeting(targeting, &scope, run)
+                    .map_err(|err| err.in_flag(flag_key))?;
+                match outcome {
                     Value::String(name) => (Some(name), Reason::TargetingMatch),
                     Value::Bool(boolean) => (Some(boolean.to_string()), Reason::TargetingMatch),
                     Value::Null => (flag.default_variant.clone(), Reason::Default),
@@ -172,24 +599,6 @@ impl FlagSet {
     }
 }
 
-/// Builds the data scope rules evaluate against: the context attributes,
-/// the targeting key under `targetingKey`, and the reserved `$flagd`
-/// object carrying the flag key and the timestamp.
-fn evaluation_scope(flag_key: &str, context: &EvaluationContext) -> Value {
-    let mut scope = serde_json::Map::new();
-    for (key, value) in &context.attributes {
-        scope.insert(key.clone(), value.clone());
-    }
-    if let Some(targeting_key) = &context.targeting_key {
-        scope.insert("targetingKey".to_owned(), targeting_key.clone().into());
-    }
-    scope.insert(
-        "$flagd".to_owned(),
-        json!({ "flagKey": flag_key, "timestamp": context.timestamp }),
-    );
-    Value::Object(scope)
-}
-
 /// Looks a variant key up and converts its value to JSON.
 fn variant_value(variants: &Variants, name: &str) -> Option<Value> {
     match variants {
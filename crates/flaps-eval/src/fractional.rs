@@ -18,7 +18,7 @@
 
 use serde_json::Value;
 
-use crate::eval::EvaluationError;
+use crate::eval::{EvaluationError, RolloutPosition, Run};
 use crate::logic::apply;
 use crate::targeting::{Bucket, Rule};
 
@@ -26,7 +26,7 @@ use crate::targeting::{Bucket, Rule};
 ///
 /// Canonical Austin Appleby algorithm, matching `twmb/murmur3` `Sum32` used by
 /// the flagd reference implementation so bucketing agrees across languages.
-fn murmur3_x86_32(data: &[u8], seed: u32) -> u32 {
+pub(crate) fn murmur3_x86_32(data: &[u8], seed: u32) -> u32 {
     const C1: u32 = 0xcc9e_2d51;
     const C2: u32 = 0x1b87_3593;
 
@@ -75,9 +75,13 @@ fn murmur3_x86_32(data: &[u8], seed: u32) -> u32 {
 /// When `bucket_by` is absent or does not evaluate to a string, falls back to
 /// the flagd default: the flag key concatenated with the targeting key
 /// (flag key first, no separator).
-fn bucketing_value(bucket_by: Option<&Rule>, data: &Value) -> Result<String, EvaluationError> {
+fn bucketing_value(
+    bucket_by: Option<&Rule>,
+    data: &Value,
+    run: &Run,
+) -> Result<String, EvaluationError> {
     if let Some(rule) = bucket_by {
-        let evaluated = apply(rule, data)?;
+        let evaluated = apply(rule, data, run)?;
         if let Value::String(text) = evaluated {
             return Ok(text);
         }
@@ -98,15 +102,23 @@ fn bucketing_value(bucket_by: Option<&Rule>, data: &Value) -> Result<String, Eva
     Ok(format!("{flag_key}{targeting_key}"))
 }
 
-/// Hashes `value` with `MurmurHash3` x86 32-bit seed 0 and maps the result
-/// into `[0, total_weight)` using the high-precision integer formula.
-///
-/// Formula: `(hash as u64 * total_weight as u64) >> 32`
+/// Returns the bucket, in `[0, total_weight)`, a `fractional` rule assigns
+/// to `bucketing_value`: the value hashed with `MurmurHash3` x86 32-bit seed
+/// 0 and mapped with the high-precision integer formula
+/// `(hash as u64 * total_weight) >> 32`.
 ///
 /// This matches the Go reference implementation and avoids the float-division
-/// rounding errors present in older SDK implementations.
-fn murmur3_bucket(value: &str, total_weight: u64) -> u64 {
-    let hash = murmur3_x86_32(value.as_bytes(), 0);
+/// rounding errors present in older SDK implementations. A rule over buckets
+/// of weights `w1, w2, ...` serves the first whose cumulative weight exceeds
+/// the bucket, so with `total_weight = 100` a user is in the first variant of
+/// a `p`/`100 - p` rollout exactly when the bucket is below `p`: raising `p`
+/// only ever adds users.
+///
+/// Without `bucket_by` the bucketing value is the flag key followed by the
+/// targeting key. `total_weight` 0 yields 0.
+#[must_use]
+pub fn bucket_for(bucketing_value: &str, total_weight: u64) -> u64 {
+    let hash = murmur3_x86_32(bucketing_value.as_bytes(), 0);
     (u64::from(hash) * total_weight) >> 32
 }
 
@@ -118,6 +130,7 @@ pub(crate) fn eval_fractional(
     bucket_by: Option<&Rule>,
     buckets: &[Bucket],
     data: &Value,
+    run: &Run,
 ) -> Result<Value, EvaluationError> {
     let total_weight: u64 = buckets.iter().map(|b| u64::from(b.weight)).sum();
 
@@ -125,8 +138,12 @@ pub(crate) fn eval_fractional(
         return Ok(Value::Null);
     }
 
-    let value = bucketing_value(bucket_by, data)?;
-    let bucket = murmur3_bucket(&value, total_weight);
+    let value = bucketing_value(bucket_by, data, run)?;
+    let bucket = bucket_for(&value, total_weight);
+    run.record_rollout(RolloutPosition {
+        bucket,
+        total_weight,
+    });
 
     let mut range_end: u64 = 0;
     for b in buckets {
@@ -0,0 +1,102 @@
+//! Evaluation of the `consistent_hash` operator, a Flaps extension to the
+//! flagd schema.
+//!
+//! `fractional` maps a hash onto cumulative weight ranges: resizing one
+//! bucket shifts the boundary of every bucket after it, so a weight change
+//! moves keys between variants the change did not touch. `consistent_hash`
+//! places each variant on a hash ring as virtual nodes instead, and serves
+//! the variant owning the first node at or after the key's hash:
+//!
+//! 1. Each variant gets `weight * NODES_PER_WEIGHT` nodes, the `i`-th
+//!    hashed from `variant#i` with `MurmurHash3` x86 32-bit, seed 0. The
+//!    count depends on the variant's own weight only, and a variant's nodes
+//!    are always a prefix of the same sequence.
+//! 2. The bucketing value is hashed the same way and served the owner of
+//!    the next node clockwise, wrapping past `u32::MAX`.
+//!
+//! Raising a variant's weight only adds its nodes, which take keys from
+//! their immediate neighbours and from no one else; adding a variant only
+//! moves keys onto it. The price is precision: shares follow the weights
+//! within a few percent, where `fractional` is exact, and the total weight
+//! is capped at [`HashRing::MAX_TOTAL_WEIGHT`] to bound the ring size. Like
+//! `fractional`, each key is placed on its own, so adding or removing a key
+//! never moves another.
+
+use serde_json::Value;
+
+use crate::eval::EvaluationError;
+use crate::eval::Run;
+use crate::fractional::murmur3_x86_32;
+use crate::logic::apply;
+use crate::targeting::{Bucket, Rule};
+
+/// Virtual nodes placed per unit of weight.
+const NODES_PER_WEIGHT: u32 = 10;
+
+/// Weighted variants placed on a consistent-hash ring.
+///
+/// The ring is derived from the buckets when it is built, so two rings with
+/// the same buckets are equal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashRing {
+    buckets: Vec<Bucket>,
+    /// `(hash, index into buckets)`, sorted by hash.
+    nodes: Vec<(u32, usize)>,
+}
+
+impl HashRing {
+    /// Largest total weight a `consistent_hash` rule may declare; parsing
+    /// refuses more.
+    pub const MAX_TOTAL_WEIGHT: u64 = 10_000;
+
+    /// Places `buckets` on a ring. A ring whose weights are all zero is
+    /// empty and serves nothing.
+    ///
+    /// The ring holds ten nodes per unit of weight: keep the total within
+    /// [`Self::MAX_TOTAL_WEIGHT`].
+    #[must_use]
+    pub fn new(buckets: Vec<Bucket>) -> Self {
+        let mut nodes = Vec::new();
+        for (index, bucket) in buckets.iter().enumerate() {
+            for node in 0..bucket.weight.saturating_mul(NODES_PER_WEIGHT) {
+                let label = format!("{}#{node}", bucket.variant);
+                nodes.push((murmur3_x86_32(label.as_bytes(), 0), index));
+            }
+        }
+        nodes.sort_unstable();
+        Self { buckets, nodes }
+    }
+
+    /// Returns the weighted variants on this ring, in declaration order.
+    #[must_use]
+    pub fn buckets(&self) -> &[Bucket] {
+        &self.buckets
+    }
+
+    /// Returns the variant owning `key`, or `None` on an empty ring.
+    #[must_use]
+    pub fn locate(&self, key: &str) -> Option<&str> {
+        let hash = murmur3_x86_32(key.as_bytes(), 0);
+        let next = self.nodes.partition_point(|&(node, _)| node < hash);
+        let (_, index) = self.nodes.get(next).or_else(|| self.nodes.first())?;
+        Some(&self.buckets[*index].variant)
+    }
+}
+
+/// Evaluates a `consistent_hash` rule against the evaluation scope.
+///
+/// Returns `Value::Null` when the bucketing value is not a string or the
+/// ring is empty.
+pub(crate) fn eval_consistent_hash(
+    bucket_by: &Rule,
+    ring: &HashRing,
+    data: &Value,
+    run: &Run,
+) -> Result<Value, EvaluationError> {
+    let Value::String(key) = apply(bucket_by, data, run)? else {
+        return Ok(Value::Null);
+    };
+    Ok(ring
+        .locate(&key)
+        .map_or(Value::Null, |variant| Value::String(variant.to_owned())))
+}
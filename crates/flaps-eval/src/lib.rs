@@ -14,23 +14,61 @@
 //! and `ends_with`, and the reusable targeting rules declared under
 //! `$evaluators` (resolved and inlined at parse time).
 //!
+//! Beyond the schema, the built-in `consistent_hash` operator distributes
+//! variants on a [`HashRing`], the built-in `regex` operator matches a
+//! string against a [`Pattern`], and an embedder can register its own operators
+//! in [`CustomOperators`] and parse with [`FlagSet::from_json_with_operators`].
+//!
 //! Disabled flags follow the upstream semantics: evaluation succeeds with
 //! reason `DISABLED` and carries no value or variant, so the caller serves
 //! its own code default.
 
+mod context_limits;
+mod context_pairs;
+mod custom_operator;
 mod error;
 mod eval;
 mod fractional;
+mod hash_ring;
+mod limits;
 mod logic;
 mod model;
 mod parse;
+mod regex_match;
+mod sampling;
+mod scope;
 mod semver;
 mod serialize;
+mod snapshot;
 mod string_comparison;
+mod string_set;
 mod targeting;
+mod value;
 
-pub use error::ParseError;
-pub use eval::{EvaluationContext, EvaluationError, Reason, Resolution};
-pub use model::{Flag, FlagSet, Metadata, MetadataValue, State, Variants};
+pub use context_limits::{
+    ContextLimitError, ContextLimits, DEFAULT_MAX_CONTEXT_ATTRIBUTES,
+    DEFAULT_MAX_CONTEXT_KEY_LENGTH, DEFAULT_MAX_CONTEXT_LIST_LENGTH,
+    DEFAULT_MAX_CONTEXT_VALUE_LENGTH,
+};
+pub use context_pairs::ContextPairError;
+pub use custom_operator::{CustomCall, CustomOperatorFn, CustomOperators};
+pub use error::{ParseError, SnapshotError};
+pub use eval::{
+    EvaluationContext, EvaluationError, EvaluationOptions, Explanation, MergePrecedence, Reason,
+    Resolution, RolloutPosition,
+};
+pub use fractional::bucket_for;
+pub use hash_ring::HashRing;
+pub use limits::{MAX_EVALUATION_STEPS, MAX_RULE_DEPTH};
+pub use model::{
+    APP_VERSION_ATTRIBUTE, Flag, FlagSet, KILL_REASON_METADATA, MIN_APP_VERSION_METADATA, Metadata,
+    MetadataValue, RULESET_VERSION_METADATA, State, Variants,
+};
+pub use regex_match::Pattern;
+pub use sampling::ExposureSampler;
+pub use semver::is_sem_ver;
 pub use serialize::metadata_to_json;
+pub use snapshot::{SNAPSHOT_FORMAT_VERSION, SNAPSHOT_MEDIA_TYPE};
+pub use string_set::StringSet;
 pub use targeting::{Bucket, Literal, Rule, SemVerOp};
+pub use value::{Fallback, Fallbacks, FlagValue, I64_MAX_SAFE_F64, I64_MIN_F64};
@@ -0,0 +1,67 @@
+//! Defensive guards bounding the work of evaluating a targeting rule.
+//!
+//! Rulesets produced by `flaps-compiler` are bounded at compile time, but a
+//! hand-crafted or corrupted document could nest rules arbitrarily deep.
+//! Parsing refuses such a document with
+//! [`ParseError::RuleTooDeep`](crate::ParseError::RuleTooDeep), once per
+//! ruleset, so evaluation never recurses into an unbounded rule.
+//!
+//! Depth alone does not bound the time an evaluation takes: `map`, `filter`,
+//! `reduce`, `all`, `none` and `some` reduce their sub-rule once per element,
+//! so iterations nested over context lists multiply. Every evaluation
+//! therefore also runs on a [`Budget`] of rule reductions, and one that runs
+//! out fails with [`EvaluationError::BudgetExceeded`].
+
+use std::cell::Cell;
+
+use crate::eval::EvaluationError;
+
+/// Maximum nesting depth of a targeting rule the parser accepts.
+///
+/// A literal or `var` has depth 1; every operator adds one level, and so
+/// does every array. An inlined `$evaluators` reference counts at the depth
+/// it is inlined at.
+pub const MAX_RULE_DEPTH: usize = 64;
+
+/// Default number of rule reductions one evaluation may perform.
+///
+/// Every operator, literal and `var` reduced counts as one step, including
+/// each reduction of an iteration's sub-rule. Compiled targeting stays far
+/// below this; see [`FlagSet::evaluate_with_budget`] for a different cap.
+///
+/// [`FlagSet::evaluate_with_budget`]: crate::FlagSet::evaluate_with_budget
+pub const MAX_EVALUATION_STEPS: usize = 100_000;
+
+/// Rule reductions left to one evaluation.
+#[derive(Debug)]
+pub(crate) struct Budget {
+    max_steps: usize,
+    remaining: Cell<usize>,
+}
+
+impl Budget {
+    /// A budget allowing `max_steps` reductions.
+    pub(crate) fn new(max_steps: usize) -> Self {
+        Self {
+            max_steps,
+            remaining: Cell::new(max_steps),
+        }
+    }
+
+    /// Accounts for one reduction.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EvaluationError::BudgetExceeded`], with an empty flag key
+    /// the caller fills in, once every step has been spent.
+    pub(crate) fn spend(&self) -> Result<(), EvaluationError> {
+        let Some(remaining) = self.remaining.get().checked_sub(1) else {
+            return Err(EvaluationError::BudgetExceeded {
+                flag_key: String::new(),
+                max_steps: self.max_steps,
+            });
+        };
+        self.remaining.set(remaining);
+        Ok(())
+    }
+}
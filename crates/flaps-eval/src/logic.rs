@@ -19,9 +19,13 @@
 use serde_json::{Value, json};
 
 use crate::eval::EvaluationError;
+use crate::eval::Run;
 use crate::fractional::eval_fractional;
+use crate::hash_ring::eval_consistent_hash;
+use crate::regex_match::eval_regex;
 use crate::semver::eval_sem_ver;
 use crate::string_comparison::{Affix, eval_string_comparison};
+use crate::string_set::eval_in_set;
 use crate::targeting::{Literal, Rule};
 
 /// Reduces a rule against the current data scope.
@@ -32,69 +36,97 @@ use crate::targeting::{Literal, Rule};
 ///
 /// # Errors
 ///
-/// Returns [`EvaluationError::UnsupportedOperation`] when the rule reaches
-/// a flagd custom operation that is not implemented yet. The JsonLogic
-/// operators themselves never fail.
-pub(crate) fn apply(rule: &Rule, data: &Value) -> Result<Value, EvaluationError> {
+/// Returns [`EvaluationError::UnresolvedEvaluator`], with an empty flag key
+/// the caller fills in, when the rule reaches an unresolved `$ref`, and
+/// [`EvaluationError::BudgetExceeded`] when the reduction spends the last
+/// step of the `run`'s budget. The JsonLogic operators themselves never
+/// fail, unless a strict `run` turns a comparison of mismatched types into
+/// [`EvaluationError::TypeMismatch`].
+pub(crate) fn apply(rule: &Rule, data: &Value, run: &Run) -> Result<Value, EvaluationError> {
+    run.spend()?;
     match rule {
         Rule::Literal(literal) => Ok(literal_value(literal)),
-        Rule::Array(items) => Ok(Value::Array(apply_all(items, data)?)),
+        Rule::Array(items) => Ok(Value::Array(apply_all(items, data, run)?)),
         Rule::Var { path, default } => Ok(eval_var(path, default.as_ref(), data)),
-        Rule::Missing(keys) => Ok(Value::Array(eval_missing(keys, data)?.1)),
-        Rule::MissingSome { min, keys } => eval_missing_some(*min, keys, data),
-        Rule::If(branches) => eval_if(branches, data),
-        Rule::And(operands) => eval_and(operands, data),
-        Rule::Or(operands) => eval_or(operands, data),
-        Rule::Not(operand) => Ok(Value::Bool(!truthy(&apply(operand, data)?))),
-        Rule::Truthy(operand) => Ok(Value::Bool(truthy(&apply(operand, data)?))),
+        Rule::Missing(keys) => Ok(Value::Array(eval_missing(keys, data, run)?.1)),
+        Rule::MissingSome { min, keys } => eval_missing_some(*min, keys, data, run),
+        Rule::If(branches) => eval_if(branches, data, run),
+        Rule::And(operands) => eval_and(operands, data, run),
+        Rule::Or(operands) => eval_or(operands, data, run),
+        Rule::Not(operand) => Ok(Value::Bool(!truthy(&apply(operand, data, run)?))),
+        Rule::Truthy(operand) => Ok(Value::Bool(truthy(&apply(operand, data, run)?))),
         Rule::Eq(left, right) => Ok(Value::Bool(loose_eq(
-            &apply(left, data)?,
-            &apply(right, data)?,
+            &apply(left, data, run)?,
+            &apply(right, data, run)?,
         ))),
         Rule::StrictEq(left, right) => Ok(Value::Bool(strict_eq(
-            &apply(left, data)?,
-            &apply(right, data)?,
+            &apply(left, data, run)?,
+            &apply(right, data, run)?,
         ))),
         Rule::Neq(left, right) => Ok(Value::Bool(!loose_eq(
-            &apply(left, data)?,
-            &apply(right, data)?,
+            &apply(left, data, run)?,
+            &apply(right, data, run)?,
         ))),
         Rule::StrictNeq(left, right) => Ok(Value::Bool(!strict_eq(
-            &apply(left, data)?,
-            &apply(right, data)?,
+            &apply(left, data, run)?,
+            &apply(right, data, run)?,
         ))),
-        Rule::Gt(left, right) => Ok(Value::Bool(lt(&apply(right, data)?, &apply(left, data)?))),
-        Rule::Gte(left, right) => Ok(Value::Bool(lte(&apply(right, data)?, &apply(left, data)?))),
-        Rule::Lt(operands) => eval_chain(operands, data, lt),
-        Rule::Lte(operands) => eval_chain(operands, data, lte),
-        Rule::Add(operands) => eval_add(operands, data),
-        Rule::Sub(operands) => eval_sub(operands, data),
-        Rule::Mul(operands) => eval_mul(operands, data),
+        Rule::Gt(left, right) => {
+            let (left, right) = (apply(left, data, run)?, apply(right, data, run)?);
+            check_ordered(">", &left, &right, run)?;
+            Ok(Value::Bool(lt(&right, &left)))
+        }
+        Rule::Gte(left, right) => {
+            let (left, right) = (apply(left, data, run)?, apply(right, data, run)?);
+            check_ordered(">=", &left, &right, run)?;
+            Ok(Value::Bool(lte(&right, &left)))
+        }
+        Rule::Lt(operands) => eval_chain("<", operands, data, lt, run),
+        Rule::Lte(operands) => eval_chain("<=", operands, data, lte, run),
+        Rule::Add(operands) => eval_add(operands, data, run),
+        Rule::Sub(operands) => eval_sub(operands, data, run),
+        Rule::Mul(operands) => eval_mul(operands, data, run),
         Rule::Div(left, right) => Ok(number_value(
-            to_number(&apply(left, data)?) / to_number(&apply(right, data)?),
+            to_number(&apply(left, data, run)?) / to_number(&apply(right, data, run)?),
         )),
         Rule::Mod(left, right) => Ok(number_value(
-            to_number(&apply(left, data)?) % to_number(&apply(right, data)?),
+            to_number(&apply(left, data, run)?) % to_number(&apply(right, data, run)?),
         )),
-        Rule::Min(operands) => eval_extreme(operands, data, f64::min),
-        Rule::Max(operands) => eval_extreme(operands, data, f64::max),
-        Rule::Cat(operands) => eval_cat(operands, data),
-        Rule::Substr(operands) => eval_substr(operands, data),
-        Rule::In(needle, haystack) => eval_in(needle, haystack, data),
-        Rule::Merge(operands) => eval_merge(operands, data),
-        Rule::Map(array, logic) => eval_map(array, logic, data),
-        Rule::Filter(array, logic) => eval_filter(array, logic, data),
-        Rule::Reduce(array, logic, initial) => eval_reduce(array, logic, initial, data),
-        Rule::All(array, test) => eval_all(array, test, data),
-        Rule::None(array, test) => Ok(Value::Bool(!truthy(&eval_some(array, test, data)?))),
-        Rule::Some(array, test) => eval_some(array, test, data),
-        Rule::StartsWith(left, right) => eval_string_comparison(Affix::Prefix, left, right, data),
-        Rule::EndsWith(left, right) => eval_string_comparison(Affix::Suffix, left, right, data),
-        Rule::SemVer { value, op, version } => eval_sem_ver(value, *op, version, data),
+        Rule::Min(operands) => eval_extreme(operands, data, f64::min, run),
+        Rule::Max(operands) => eval_extreme(operands, data, f64::max, run),
+        Rule::Cat(operands) => eval_cat(operands, data, run),
+        Rule::Substr(operands) => eval_substr(operands, data, run),
+        Rule::In(needle, haystack) => eval_in(needle, haystack, data, run),
+        Rule::InSet { needle, set } => eval_in_set(needle, set, data, run),
+        Rule::Merge(operands) => eval_merge(operands, data, run),
+        Rule::Map(array, logic) => eval_map(array, logic, data, run),
+        Rule::Filter(array, logic) => eval_filter(array, logic, data, run),
+        Rule::Reduce(array, logic, initial) => eval_reduce(array, logic, initial, data, run),
+        Rule::All(array, test) => eval_all(array, test, data, run),
+        Rule::None(array, test) => Ok(Value::Bool(!truthy(&eval_some(array, test, data, run)?))),
+        Rule::Some(array, test) => eval_some(array, test, data, run),
+        Rule::StartsWith(left, right) => {
+            eval_string_comparison(Affix::Prefix, left, right, data, run)
+        }
+        Rule::EndsWith(left, right) => {
+            eval_string_comparison(Affix::Suffix, left, right, data, run)
+        }
+        Rule::Regex { value, pattern } => eval_regex(value, pattern, data, run),
+        Rule::SemVer { value, op, version } => eval_sem_ver(value, *op, version, data, run),
         Rule::Fractional { bucket_by, buckets } => {
-            eval_fractional(bucket_by.as_deref(), buckets, data)
+            eval_fractional(bucket_by.as_deref(), buckets, data, run)
+        }
+        Rule::ConsistentHash { bucket_by, ring } => {
+            eval_consistent_hash(bucket_by, ring, data, run)
         }
-        Rule::Ref(_) => Err(unsupported("$ref")),
+        Rule::Custom(call) => Ok(Value::Bool(call.call(
+            &apply(&call.left, data, run)?,
+            &apply(&call.right, data, run)?,
+        ))),
+        Rule::Ref(reference) => Err(EvaluationError::UnresolvedEvaluator {
+            flag_key: String::new(),
+            reference: reference.clone(),
+        }),
     }
 }
 
@@ -114,13 +146,8 @@ pub(crate) fn truthy(value: &Value) -> bool {
 }
 
 /// Evaluates every rule of a slice against the same scope.
-fn apply_all(rules: &[Rule], data: &Value) -> Result<Vec<Value>, EvaluationError> {
-    rules.iter().map(|rule| apply(rule, data)).collect()
-}
-
-/// Builds the temporary error for a custom operation pending implementation.
-fn unsupported(operator: &'static str) -> EvaluationError {
-    EvaluationError::UnsupportedOperation { operator }
+fn apply_all(rules: &[Rule], data: &Value, run: &Run) -> Result<Vec<Value>, EvaluationError> {
+    rules.iter().map(|rule| apply(rule, data, run)).collect()
 }
 
 /// Resolves a `var` rule: empty paths yield the whole scope, and absent or
@@ -131,14 +158,18 @@ fn eval_var(path: &str, default: Option<&Literal>, data: &Value) -> Value {
     }
     match lookup(path, data) {
         Some(Value::Null) | None => default.map_or(Value::Null, literal_value),
-        Some(value) => value,
+        Some(value) => value.clone(),
     }
 }
 
 /// Resolves the key list of `missing`, then splits it into the resolved
 /// keys and the keys whose value is absent, `null` or the empty string.
-fn eval_missing(keys: &[Rule], data: &Value) -> Result<(Vec<Value>, Vec<Value>), EvaluationError> {
-    let evaluated = apply_all(keys, data)?;
+fn eval_missing(
+    keys: &[Rule],
+    data: &Value,
+    run: &Run,
+) -> Result<(Vec<Value>, Vec<Value>), EvaluationError> {
+    let evaluated = apply_all(keys, data, run)?;
     let resolved = match evaluated.first() {
         Some(Value::Array(items)) => items.clone(),
         _ => evaluated,
@@ -158,8 +189,13 @@ fn eval_missing(keys: &[Rule], data: &Value) -> Result<(Vec<Value>, Vec<Value>),
 
 /// Evaluates `missing_some`: enough present keys yield an empty array,
 /// otherwise the missing keys are returned.
-fn eval_missing_some(min: u64, keys: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
-    let (resolved, absent) = eval_missing(keys, data)?;
+fn eval_missing_some(
+    min: u64,
+    keys: &[Rule],
+    data: &Value,
+    run: &Run,
+) -> Result<Value, EvaluationError> {
+    let (resolved, absent) = eval_missing(keys, data, run)?;
     let present = resolved.len().saturating_sub(absent.len());
     if u64::try_from(present).unwrap_or(u64::MAX) >= min {
         Ok(Value::Array(Vec::new()))
@@ -168,26 +204,59 @@ fn eval_missing_some(min: u64, keys: &[Rule], data: &Value) -> Result<Value, Eva
     }
 }
 
+/// Reduces a flag's targeting like [`apply`], recording in `run` which
+/// branch of a top-level `if` matched.
+///
+/// A compiled targeting is `if` pairs, one per targeting rule, followed by
+/// the default, so the branch is the index of the rule that matched.
+///
+/// # Errors
+///
+/// As [`apply`].
+pub(crate) fn apply_targeting(
+    rule: &Rule,
+    data: &Value,
+    run: &Run,
+) -> Result<Value, EvaluationError> {
+    let Rule::If(branches) = rule else {
+        return apply(rule, data, run);
+    };
+    run.spend()?;
+    let (value, matched) = eval_if_branch(branches, data, run)?;
+    run.record_matched_rule(matched);
+    Ok(value)
+}
+
 /// Evaluates `if` branches as condition and outcome pairs followed by an
 /// optional else; exhausted branches yield `null`.
-fn eval_if(branches: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_if(branches: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
+    eval_if_branch(branches, data, run).map(|(value, _)| value)
+}
+
+/// Evaluates `if` branches like [`eval_if`], also returning the index of the
+/// pair whose condition held, or `None` when the else or nothing was taken.
+fn eval_if_branch(
+    branches: &[Rule],
+    data: &Value,
+    run: &Run,
+) -> Result<(Value, Option<usize>), EvaluationError> {
     let mut pairs = branches.chunks_exact(2);
-    for pair in pairs.by_ref() {
-        if truthy(&apply(&pair[0], data)?) {
-            return apply(&pair[1], data);
+    for (index, pair) in pairs.by_ref().enumerate() {
+        if truthy(&apply(&pair[0], data, run)?) {
+            return Ok((apply(&pair[1], data, run)?, Some(index)));
         }
     }
     match pairs.remainder() {
-        [fallback] => apply(fallback, data),
-        _ => Ok(Value::Null),
+        [fallback] => Ok((apply(fallback, data, run)?, None)),
+        _ => Ok((Value::Null, None)),
     }
 }
 
 /// Evaluates `and`: the first falsy operand wins, otherwise the last one.
-fn eval_and(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_and(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     let mut last = Value::Null;
     for operand in operands {
-        last = apply(operand, data)?;
+        last = apply(operand, data, run)?;
         if !truthy(&last) {
             return Ok(last);
         }
@@ -196,10 +265,10 @@ fn eval_and(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
 }
 
 /// Evaluates `or`: the first truthy operand wins, otherwise the last one.
-fn eval_or(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_or(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     let mut last = Value::Null;
     for operand in operands {
-        last = apply(operand, data)?;
+        last = apply(operand, data, run)?;
         if truthy(&last) {
             return Ok(last);
         }
@@ -210,16 +279,19 @@ fn eval_or(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
 /// Evaluates a comparison chain: the binary form compares two operands,
 /// the ternary form tests betweenness.
 fn eval_chain(
+    operator: &'static str,
     operands: &[Rule],
     data: &Value,
     ordered: fn(&Value, &Value) -> bool,
+    run: &Run,
 ) -> Result<Value, EvaluationError> {
     let Some((first, rest)) = operands.split_first() else {
         return Ok(Value::Bool(false));
     };
-    let mut previous = apply(first, data)?;
+    let mut previous = apply(first, data, run)?;
     for operand in rest {
-        let next = apply(operand, data)?;
+        let next = apply(operand, data, run)?;
+        check_ordered(operator, &previous, &next, run)?;
         if !ordered(&previous, &next) {
             return Ok(Value::Bool(false));
         }
@@ -230,30 +302,30 @@ fn eval_chain(
 
 /// Evaluates `+`: variadic addition with a zero seed, so the unary form
 /// casts its operand to a number.
-fn eval_add(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_add(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     let mut sum = 0.0;
     for operand in operands {
-        sum += parse_float(&apply(operand, data)?);
+        sum += parse_float(&apply(operand, data, run)?);
     }
     Ok(number_value(sum))
 }
 
 /// Evaluates `-`: binary subtraction, or arithmetic negation when unary.
-fn eval_sub(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_sub(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     match operands {
-        [operand] => Ok(number_value(-to_number(&apply(operand, data)?))),
+        [operand] => Ok(number_value(-to_number(&apply(operand, data, run)?))),
         [left, right] => Ok(number_value(
-            to_number(&apply(left, data)?) - to_number(&apply(right, data)?),
+            to_number(&apply(left, data, run)?) - to_number(&apply(right, data, run)?),
         )),
         _ => Ok(Value::Null),
     }
 }
 
 /// Evaluates `*`: variadic multiplication.
-fn eval_mul(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_mul(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     let mut product = 1.0;
     for operand in operands {
-        product *= parse_float(&apply(operand, data)?);
+        product *= parse_float(&apply(operand, data, run)?);
     }
     Ok(number_value(product))
 }
@@ -263,10 +335,11 @@ fn eval_extreme(
     operands: &[Rule],
     data: &Value,
     pick: fn(f64, f64) -> f64,
+    run: &Run,
 ) -> Result<Value, EvaluationError> {
     let mut extreme: Option<f64> = None;
     for operand in operands {
-        let number = to_number(&apply(operand, data)?);
+        let number = to_number(&apply(operand, data, run)?);
         if number.is_nan() {
             return Ok(Value::Null);
         }
@@ -276,10 +349,10 @@ fn eval_extreme(
 }
 
 /// Evaluates `cat`: concatenates the string form of every operand.
-fn eval_cat(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_cat(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     let mut text = String::new();
     for operand in operands {
-        text.push_str(&to_string(&apply(operand, data)?));
+        text.push_str(&to_string(&apply(operand, data, run)?));
     }
     Ok(Value::String(text))
 }
@@ -288,14 +361,14 @@ fn eval_cat(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
 /// negative position counts back from the end, and a negative length stops
 /// that many characters before the end. Counts characters, not UTF-16 code
 /// units.
-fn eval_substr(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_substr(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     let Some((subject, indexes)) = operands.split_first() else {
         return Ok(Value::Null);
     };
-    let chars: Vec<char> = to_string(&apply(subject, data)?).chars().collect();
+    let chars: Vec<char> = to_string(&apply(subject, data, run)?).chars().collect();
     let length = i64::try_from(chars.len()).unwrap_or(i64::MAX);
     let start = match indexes.first() {
-        Some(operand) => to_integer(&apply(operand, data)?),
+        Some(operand) => to_integer(&apply(operand, data, run)?),
         None => 0,
     };
     let begin = if start < 0 {
@@ -305,7 +378,7 @@ fn eval_substr(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError
     };
     let end = match indexes.get(1) {
         Some(operand) => {
-            let span = to_integer(&apply(operand, data)?);
+            let span = to_integer(&apply(operand, data, run)?);
             if span < 0 {
                 length + span
             } else {
@@ -322,9 +395,14 @@ fn eval_substr(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError
 
 /// Evaluates `in`: substring search when the haystack is a string, strict
 /// membership when it is an array.
-fn eval_in(needle: &Rule, haystack: &Rule, data: &Value) -> Result<Value, EvaluationError> {
-    let needle = apply(needle, data)?;
-    let found = match apply(haystack, data)? {
+fn eval_in(
+    needle: &Rule,
+    haystack: &Rule,
+    data: &Value,
+    run: &Run,
+) -> Result<Value, EvaluationError> {
+    let needle = apply(needle, data, run)?;
+    let found = match apply(haystack, data, run)? {
         Value::String(text) => text.contains(&to_string(&needle)),
         Value::Array(items) => items.iter().any(|item| strict_eq(item, &needle)),
         _ => false,
@@ -333,10 +411,10 @@ fn eval_in(needle: &Rule, haystack: &Rule, data: &Value) -> Result<Value, Evalua
 }
 
 /// Evaluates `merge`: flattens array operands and wraps scalar operands.
-fn eval_merge(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError> {
+fn eval_merge(operands: &[Rule], data: &Value, run: &Run) -> Result<Value, EvaluationError> {
     let mut merged = Vec::new();
     for operand in operands {
-        match apply(operand, data)? {
+        match apply(operand, data, run)? {
             Value::Array(items) => merged.extend(items),
             scalar => merged.push(scalar),
         }
@@ -346,8 +424,8 @@ fn eval_merge(operands: &[Rule], data: &Value) -> Result<Value, EvaluationError>
 
 /// Evaluates the array operand of an iteration operator; non arrays
 /// iterate as empty.
-fn iteration_items(array: &Rule, data: &Value) -> Result<Vec<Value>, EvaluationError> {
-    match apply(array, data)? {
+fn iteration_items(array: &Rule, data: &Value, run: &Run) -> Result<Vec<Value>, EvaluationError> {
+    match apply(array, data, run)? {
         Value::Array(items) => Ok(items),
         _ => Ok(Vec::new()),
     }
@@ -355,19 +433,24 @@ fn iteration_items(array: &Rule, data: &Value) -> Result<Vec<Value>, EvaluationE
 
 /// Evaluates `map`: applies the logic to every element, rebinding the
 /// scope to the element.
-fn eval_map(array: &Rule, logic: &Rule, data: &Value) -> Result<Value, EvaluationError> {
-    let mapped = iteration_items(array, data)?
+fn eval_map(array: &Rule, logic: &Rule, data: &Value, run: &Run) -> Result<Value, EvaluationError> {
+    let mapped = iteration_items(array, data, run)?
         .iter()
-        .map(|item| apply(logic, item))
+        .map(|item| apply(logic, item, run))
         .collect::<Result<Vec<_>, _>>()?;
     Ok(Value::Array(mapped))
 }
 
 /// Evaluates `filter`: keeps the elements with truthy outcomes.
-fn eval_filter(array: &Rule, logic: &Rule, data: &Value) -> Result<Value, EvaluationError> {
+fn eval_filter(
+    array: &Rule,
+    logic: &Rule,
+    data: &Value,
+    run: &Run,
+) -> Result<Value, EvaluationError> {
     let mut kept = Vec::new();
-    for item in iteration_items(array, data)? {
-        if truthy(&apply(logic, &item)?) {
+    for item in iteration_items(array, data, run)? {
+        if truthy(&apply(logic, &item, run)?) {
             kept.push(item);
         }
     }
@@ -381,24 +464,25 @@ fn eval_reduce(
     logic: &Rule,
     initial: &Rule,
     data: &Value,
+    run: &Run,
 ) -> Result<Value, EvaluationError> {
-    let mut accumulator = apply(initial, data)?;
-    for item in iteration_items(array, data)? {
+    let mut accumulator = apply(initial, data, run)?;
+    for item in iteration_items(array, data, run)? {
         let scope = json!({ "current": item, "accumulator": accumulator });
-        accumulator = apply(logic, &scope)?;
+        accumulator = apply(logic, &scope, run)?;
     }
     Ok(accumulator)
 }
 
 /// Evaluates `all`: every element satisfies the test, and empty arrays do
 /// not.
-fn eval_all(array: &Rule, test: &Rule, data: &Value) -> Result<Value, EvaluationError> {
-    let items = iteration_items(array, data)?;
+fn eval_all(array: &Rule, test: &Rule, data: &Value, run: &Run) -> Result<Value, EvaluationError> {
+    let items = iteration_items(array, data, run)?;
     if items.is_empty() {
         return Ok(Value::Bool(false));
     }
     for item in items {
-        if !truthy(&apply(test, &item)?) {
+        if !truthy(&apply(test, &item, run)?) {
             return Ok(Value::Bool(false));
         }
     }
@@ -406,9 +490,9 @@ fn eval_all(array: &Rule, test: &Rule, data: &Value) -> Result<Value, Evaluation
 }
 
 /// Evaluates `some`: at least one element satisfies the test.
-fn eval_some(array: &Rule, test: &Rule, data: &Value) -> Result<Value, EvaluationError> {
-    for item in iteration_items(array, data)? {
-        if truthy(&apply(test, &item)?) {
+fn eval_some(array: &Rule, test: &Rule, data: &Value, run: &Run) -> Result<Value, EvaluationError> {
+    for item in iteration_items(array, data, run)? {
+        if truthy(&apply(test, &item, run)?) {
             return Ok(Value::Bool(true));
         }
     }
@@ -471,6 +555,32 @@ fn lt(left: &Value, right: &Value) -> bool {
     to_number(left) < to_number(right)
 }
 
+/// In a strict run, refuses to order two values [`lt`] cannot compare:
+/// unless both are strings, an operand that does not coerce to a number is
+/// a [`EvaluationError::TypeMismatch`]. `null`, which an absent attribute
+/// reads as, is never a mismatch.
+fn check_ordered(
+    operator: &'static str,
+    left: &Value,
+    right: &Value,
+    run: &Run,
+) -> Result<(), EvaluationError> {
+    if !run.is_strict() || matches!((left, right), (Value::String(_), Value::String(_))) {
+        return Ok(());
+    }
+    match [left, right]
+        .into_iter()
+        .find(|operand| !operand.is_null() && to_number(operand).is_nan())
+    {
+        Some(operand) => Err(EvaluationError::TypeMismatch {
+            flag_key: String::new(),
+            operator,
+            operand: operand.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
 /// Orders two values inclusively, with the same coercion as [`lt`].
 fn lte(left: &Value, right: &Value) -> bool {
     if let (Value::String(a), Value::String(b)) = (left, right) {
@@ -621,14 +731,33 @@ fn literal_value(literal: &Literal) -> Value {
 
 /// Resolves a dotted path against the data scope, descending objects by
 /// key and arrays by numeric index.
-fn lookup(path: &str, data: &Value) -> Option<Value> {
+///
+/// At each object, a key equal to the whole remaining path wins over
+/// descending at its first dot, so a flat attribute whose name contains dots
+/// (`"app.version"`) stays reachable. There is no escape syntax. A missing
+/// intermediate key resolves to nothing. The value is borrowed: callers
+/// that only test it, like `missing`, copy nothing.
+fn lookup<'a>(path: &str, data: &'a Value) -> Option<&'a Value> {
     let mut current = data;
-    for segment in path.split('.') {
+    let mut rest = path;
+    loop {
+        if let Value::Object(entries) = current {
+            if let Some(value) = entries.get(rest) {
+                return Some(value);
+            }
+        }
+        let (segment, tail) = match rest.split_once('.') {
+            Some((segment, tail)) => (segment, Some(tail)),
+            None => (rest, None),
+        };
         current = match current {
             Value::Object(entries) => entries.get(segment)?,
             Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
             _ => return None,
         };
+        match tail {
+            Some(tail) => rest = tail,
+            None => return Some(current),
+        }
     }
-    Some(current.clone())
 }
@@ -2,6 +2,7 @@
 
 use std::collections::BTreeMap;
 
+use crate::custom_operator::CustomOperators;
 use crate::error::ParseError;
 use crate::targeting::Rule;
 
@@ -28,7 +29,22 @@ impl FlagSet {
     /// are rejected with a structured [`ParseError`].
     pub fn from_json(document: &str) -> Result<Self, ParseError> {
         let value: serde_json::Value = serde_json::from_str(document)?;
-        crate::parse::flag_set(&value)
+        crate::parse::flag_set(&value, None)
+    }
+
+    /// Parses a flagd JSON document whose targeting may call the operators
+    /// registered in `operators`, with the same validation as
+    /// [`Self::from_json`].
+    ///
+    /// An operator that is neither built in nor registered is refused with
+    /// [`ParseError::UnknownOperator`]: the document is rejected rather than
+    /// evaluated with a rule that can never be decided.
+    pub fn from_json_with_operators(
+        document: &str,
+        operators: &CustomOperators,
+    ) -> Result<Self, ParseError> {
+        let value: serde_json::Value = serde_json::from_str(document)?;
+        crate::parse::flag_set(&value, Some(operators))
     }
 
     /// Serializes the flag set back to canonical flagd JSON.
@@ -36,6 +52,21 @@ impl FlagSet {
     pub fn to_json(&self) -> String {
         crate::serialize::flag_set_value(self).to_string()
     }
+
+    /// Serializes the flag set to canonical flagd JSON, indented with one
+    /// entry per line.
+    ///
+    /// Meant for rulesets committed to version control: keys are ordered, so
+    /// two flag sets that are equal serialize to identical bytes whatever the
+    /// key order of their source documents, and a change to one flag only
+    /// touches that flag's lines.
+    #[must_use]
+    pub fn to_pretty_json(&self) -> String {
+        let value = crate::serialize::flag_set_value(self);
+        // A `serde_json::Value` always serializes: its map keys are strings.
+        serde_json::to_string_pretty(&value)
+            .unwrap_or_else(|err| unreachable!("flag set serialization failed: {err}"))
+    }
 }
 
 /// A single feature flag definition.
@@ -56,10 +87,45 @@ pub struct Flag {
     pub metadata: Metadata,
 }
 
+/// Flag metadata entry marking a disabled flag as killed, with the kill
+/// reason as its string value.
+///
+/// flagd has no killed state: a killed flag is compiled as
+/// [`State::Disabled`] with this entry, so any flagd evaluator serves it as
+/// disabled while [`FlagSet::evaluate`] reports [`Reason::Killed`]. The
+/// entry reaches resolution metadata like any other, carrying the reason to
+/// the caller.
+///
+/// [`Reason::Killed`]: crate::Reason::Killed
+pub const KILL_REASON_METADATA: &str = "killReason";
+
+/// Flag metadata entry holding the oldest app version an enabled flag is
+/// served to, as a semantic version string.
+///
+/// flagd has no version gate either: [`FlagSet::evaluate`] compares this
+/// entry with the [`APP_VERSION_ATTRIBUTE`] of the context before any rule,
+/// and resolves an older or missing version like a disabled flag, with
+/// [`Reason::VersionGated`]. A flagd evaluator ignores the entry.
+///
+/// [`Reason::VersionGated`]: crate::Reason::VersionGated
+pub const MIN_APP_VERSION_METADATA: &str = "minAppVersion";
+
+/// Resolution metadata entry holding the version of the ruleset a flag was
+/// evaluated against, as an integer.
+///
+/// A ruleset never carries it: OFREP responses and `flaps-client` add it to
+/// every resolution they serve, so an exposure can be attributed to the
+/// exact configuration that produced it.
+pub const RULESET_VERSION_METADATA: &str = "flaps.version";
+
+/// Context attribute compared with [`MIN_APP_VERSION_METADATA`].
+pub const APP_VERSION_ATTRIBUTE: &str = "app_version";
+
 /// Operational state of a flag.
 ///
-/// A disabled flag evaluates successfully with reason `DISABLED` and no
-/// value or variant; the caller serves its own code default.
+/// A disabled flag evaluates successfully with reason `DISABLED` (`KILLED`
+/// when it carries [`KILL_REASON_METADATA`]) and no value or variant; the
+/// caller serves its own code default.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum State {
     /// The flag is evaluated normally.
@@ -9,8 +9,13 @@ use std::collections::BTreeMap;
 
 use serde_json::Value;
 
+use crate::custom_operator::{CustomCall, CustomOperators};
 use crate::error::ParseError;
+use crate::hash_ring::HashRing;
+use crate::limits::MAX_RULE_DEPTH;
 use crate::model::{Flag, FlagSet, Metadata, MetadataValue, State, Variants};
+use crate::regex_match::Pattern;
+use crate::string_set::StringSet;
 use crate::targeting::{Bucket, Literal, Rule, SemVerOp};
 
 type RulePair = (Box<Rule>, Box<Rule>);
@@ -19,13 +24,22 @@ type RuleTriple = (Box<Rule>, Box<Rule>, Box<Rule>);
 /// Parses targeting rules, resolving `$evaluators` references on the fly.
 ///
 /// Without an evaluator table (standalone rule deserialization) references
-/// are kept verbatim as [`Rule::Ref`].
+/// are kept verbatim as [`Rule::Ref`]. Operators unknown to the flagd format
+/// are looked up in `operators`, and refused when absent from it. A rule
+/// nested deeper than [`MAX_RULE_DEPTH`] is refused, so evaluation never has
+/// to check depth.
 struct RuleParser<'a> {
     evaluators: Option<&'a serde_json::Map<String, Value>>,
+    operators: Option<&'a CustomOperators>,
     resolving: Vec<String>,
+    /// Depth of the rule being parsed; 0 between rules.
+    depth: usize,
 }
 
-pub(crate) fn flag_set(value: &Value) -> Result<FlagSet, ParseError> {
+pub(crate) fn flag_set(
+    value: &Value,
+    operators: Option<&CustomOperators>,
+) -> Result<FlagSet, ParseError> {
     let Value::Object(root) = value else {
         return Err(invalid("$", "the document root must be an object"));
     };
@@ -40,7 +54,9 @@ pub(crate) fn flag_set(value: &Value) -> Result<FlagSet, ParseError> {
     let empty = serde_json::Map::new();
     let mut parser = RuleParser {
         evaluators: Some(evaluators.unwrap_or(&empty)),
+        operators,
         resolving: Vec::new(),
+        depth: 0,
     };
 
     let flags_value = root
@@ -67,7 +83,9 @@ pub(crate) fn flag_set(value: &Value) -> Result<FlagSet, ParseError> {
 pub(crate) fn standalone_rule(path: &str, value: &Value) -> Result<Rule, ParseError> {
     RuleParser {
         evaluators: None,
+        operators: None,
         resolving: Vec::new(),
+        depth: 0,
     }
     .rule(path, value)
 }
@@ -221,6 +239,19 @@ fn metadata(path: &str, value: &Value) -> Result<Metadata, ParseError> {
 
 impl RuleParser<'_> {
     fn rule(&mut self, path: &str, value: &Value) -> Result<Rule, ParseError> {
+        if self.depth == MAX_RULE_DEPTH {
+            return Err(ParseError::RuleTooDeep {
+                path: path.to_owned(),
+                max_depth: MAX_RULE_DEPTH,
+            });
+        }
+        self.depth += 1;
+        let rule = self.node(path, value);
+        self.depth -= 1;
+        rule
+    }
+
+    fn node(&mut self, path: &str, value: &Value) -> Result<Rule, ParseError> {
         match value {
             Value::Null => Ok(Rule::Literal(Literal::Null)),
             Value::Bool(value) => Ok(Rule::Literal(Literal::Bool(*value))),
@@ -268,13 +299,24 @@ impl RuleParser<'_> {
             "+" | "-" | "*" | "/" | "%" | "min" | "max" => self.arithmetic(path, operator, args),
             "cat" | "substr" | "in" | "merge" | "map" | "filter" | "reduce" | "all" | "none"
             | "some" => self.collection(path, operator, args),
-            "starts_with" | "ends_with" | "sem_ver" | "fractional" => {
-                self.custom(path, operator, args)
-            }
-            _ => Err(ParseError::UnknownOperator {
-                path: path.to_owned(),
-                operator: operator.clone(),
-            }),
+            "starts_with" | "ends_with" | "regex" | "sem_ver" | "fractional"
+            | "consistent_hash" => self.custom(path, operator, args),
+            _ => match self.operators.and_then(|operators| operators.get(operator)) {
+                Some(function) => {
+                    let path = format!("{path}.{operator}");
+                    let (left, right) = self.binary(&path, operator, args)?;
+                    Ok(Rule::Custom(CustomCall {
+                        name: operator.clone(),
+                        left,
+                        right,
+                        operator: function.clone(),
+                    }))
+                }
+                None => Err(ParseError::UnknownOperator {
+                    path: path.to_owned(),
+                    operator: operator.clone(),
+                }),
+            },
         }
     }
 
@@ -377,7 +419,7 @@ impl RuleParser<'_> {
             "substr" => Ok(Rule::Substr(self.bounded(&path, operator, args, 2, 3)?)),
             "in" => self
                 .binary(&path, operator, args)
-                .map(|(a, b)| Rule::In(a, b)),
+                .map(|(needle, haystack)| in_rule(needle, *haystack)),
             "merge" => Ok(Rule::Merge(self.variadic(&path, operator, args, 1)?)),
             "map" => self
                 .binary(&path, operator, args)
@@ -410,8 +452,10 @@ impl RuleParser<'_> {
             "ends_with" => self
                 .binary(&path, operator, args)
                 .map(|(a, b)| Rule::EndsWith(a, b)),
+            "regex" => self.regex(&path, args),
             "sem_ver" => self.sem_ver(&path, args),
             "fractional" => self.fractional(&path, args),
+            "consistent_hash" => self.consistent_hash(&path, args),
             _ => unreachable!("dispatched operators are exhaustive"),
         }
     }
@@ -433,6 +477,27 @@ impl RuleParser<'_> {
         })
     }
 
+    fn regex(&mut self, path: &str, args: &Value) -> Result<Rule, ParseError> {
+        let [value, Value::String(pattern)] = op_args(args) else {
+            return Err(bad_args(
+                path,
+                "regex",
+                "expects a value and a string literal pattern",
+            ));
+        };
+        let Ok(pattern) = Pattern::new(pattern.as_str()) else {
+            return Err(bad_args(
+                path,
+                "regex",
+                "the pattern is not a valid regular expression",
+            ));
+        };
+        Ok(Rule::Regex {
+            value: Box::new(self.rule(&format!("{path}[0]"), value)?),
+            pattern,
+        })
+    }
+
     fn sem_ver(&mut self, path: &str, args: &Value) -> Result<Rule, ParseError> {
         let [value, operator, version] = op_args(args) else {
             return Err(bad_args(
@@ -494,11 +559,46 @@ impl RuleParser<'_> {
 
         let mut buckets = Vec::with_capacity(bucket_items.len());
         for (index, item) in bucket_items.iter().enumerate() {
-            buckets.push(bucket(&format!("{path}[{index}]"), item)?);
+            buckets.push(bucket(&format!("{path}[{index}]"), "fractional", item)?);
         }
         Ok(Rule::Fractional { bucket_by, buckets })
     }
 
+    fn consistent_hash(&mut self, path: &str, args: &Value) -> Result<Rule, ParseError> {
+        const EXPECTS: &str = "expects a bucketing expression and at least one bucket";
+        let Value::Array(items) = args else {
+            return Err(bad_args(path, "consistent_hash", EXPECTS));
+        };
+        let Some((expression, bucket_items)) = items.split_first() else {
+            return Err(bad_args(path, "consistent_hash", EXPECTS));
+        };
+        if expression.is_array() || bucket_items.is_empty() {
+            return Err(bad_args(path, "consistent_hash", EXPECTS));
+        }
+
+        let bucket_by = Box::new(self.rule(&format!("{path}[0]"), expression)?);
+        let mut buckets = Vec::with_capacity(bucket_items.len());
+        for (index, item) in bucket_items.iter().enumerate() {
+            let item_path = format!("{path}[{}]", index + 1);
+            buckets.push(bucket(&item_path, "consistent_hash", item)?);
+        }
+        let total: u64 = buckets.iter().map(|b| u64::from(b.weight)).sum();
+        if total > HashRing::MAX_TOTAL_WEIGHT {
+            return Err(bad_args(
+                path,
+                "consistent_hash",
+                &format!(
+                    "the weights total {total}, above the maximum of {}",
+                    HashRing::MAX_TOTAL_WEIGHT
+                ),
+            ));
+        }
+        Ok(Rule::ConsistentHash {
+            bucket_by,
+            ring: HashRing::new(buckets),
+        })
+    }
+
     fn unary(&mut self, path: &str, operator: &str, args: &Value) -> Result<Box<Rule>, ParseError> {
         let [argument] = op_args(args) else {
             return Err(bad_args(path, operator, "expects exactly one argument"));
@@ -592,10 +692,10 @@ fn var(path: &str, args: &Value) -> Result<Rule, ParseError> {
     }
 }
 
-fn bucket(path: &str, value: &Value) -> Result<Bucket, ParseError> {
+fn bucket(path: &str, operator: &str, value: &Value) -> Result<Bucket, ParseError> {
     const EXPECTS: &str = "a bucket pairs a variant name with an optional integer weight";
     let Value::Array(pair) = value else {
-        return Err(bad_args(path, "fractional", EXPECTS));
+        return Err(bad_args(path, operator, EXPECTS));
     };
     match pair.as_slice() {
         [Value::String(variant)] => Ok(Bucket {
@@ -606,13 +706,13 @@ fn bucket(path: &str, value: &Value) -> Result<Bucket, ParseError> {
             let weight = weight
                 .as_u64()
                 .and_then(|weight| u32::try_from(weight).ok())
-                .ok_or_else(|| bad_args(path, "fractional", EXPECTS))?;
+                .ok_or_else(|| bad_args(path, operator, EXPECTS))?;
             Ok(Bucket {
                 variant: variant.clone(),
                 weight,
             })
         }
-        _ => Err(bad_args(path, "fractional", EXPECTS)),
+        _ => Err(bad_args(path, operator, EXPECTS)),
     }
 }
 
@@ -650,3 +750,28 @@ fn invalid(path: &str, reason: &str) -> ParseError {
         reason: reason.to_owned(),
     }
 }
+
+/// Builds an `in` rule, indexing a long literal array of strings as a
+/// [`StringSet`].
+fn in_rule(needle: Box<Rule>, haystack: Rule) -> Rule {
+    let Rule::Array(items) = haystack else {
+        return Rule::In(needle, Box::new(haystack));
+    };
+    if items.len() < StringSet::MIN_LEN {
+        return Rule::In(needle, Box::new(Rule::Array(items)));
+    }
+    let strings: Option<Vec<String>> = items
+        .iter()
+        .map(|item| match item {
+            Rule::Literal(Literal::String(value)) => Some(value.clone()),
+            _ => None,
+        })
+        .collect();
+    match strings {
+        Some(strings) => Rule::InSet {
+            needle,
+            set: StringSet::new(strings),
+        },
+        None => Rule::In(needle, Box::new(Rule::Array(items))),
+    }
+}
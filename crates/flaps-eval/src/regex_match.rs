@@ -0,0 +1,77 @@
+//! Evaluation of the `regex` operator, a Flaps extension to the flagd
+//! schema.
+//!
+//! The pattern is a string literal of the rule, so it is compiled once when
+//! the ruleset is parsed, never per evaluation. Matching uses the `regex`
+//! crate, whose matching time is linear in the input: a pattern cannot be
+//! made to backtrack catastrophically on a hostile attribute. An invalid
+//! pattern fails the parse, like any other malformed operator: under `!` a
+//! pattern that never matched would otherwise match everything. Rulesets
+//! compiled by Flaps never hold one, since the compiler rejects invalid
+//! patterns first.
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::eval::EvaluationError;
+use crate::eval::Run;
+use crate::logic::apply;
+use crate::targeting::Rule;
+
+/// The pattern of a `regex` rule, with its compiled form.
+///
+/// Two patterns are equal when their sources are.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Compiles `source`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `regex` crate's error when `source` is not a valid
+    /// regular expression.
+    pub fn new(source: impl Into<String>) -> Result<Self, regex::Error> {
+        let source = source.into();
+        let regex = Regex::new(&source)?;
+        Ok(Self { source, regex })
+    }
+
+    /// Returns the pattern as written.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the pattern matches somewhere in `haystack`.
+    #[must_use]
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.regex.is_match(haystack)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Pattern {}
+
+/// Evaluates `regex`: reduces `value` and matches it against `pattern`.
+///
+/// Returns `Value::Null` when the value is not a string.
+pub(crate) fn eval_regex(
+    value: &Rule,
+    pattern: &Pattern,
+    data: &Value,
+    run: &Run,
+) -> Result<Value, EvaluationError> {
+    match apply(value, data, run)? {
+        Value::String(haystack) => Ok(Value::Bool(pattern.is_match(&haystack))),
+        _ => Ok(Value::Null),
+    }
+}
@@ -0,0 +1,70 @@
+//! Deterministic sampling of evaluation exposures.
+//!
+//! Logging an exposure for every evaluation is too much data at high volume.
+//! An [`ExposureSampler`] keeps a fixed fraction of them, chosen by hashing
+//! the flag key with the evaluation subject, so a given user is either always
+//! or never sampled for a given flag and per-user funnels stay complete.
+//! Evaluation itself is never sampled: the sampler only decides whether an
+//! exposure is recorded, and each recorded exposure should carry
+//! [`ExposureSampler::rate`] so downstream counts can be scaled by `1 / rate`.
+
+use crate::eval::EvaluationContext;
+use crate::fractional::murmur3_x86_32;
+
+/// Seed of the sampling hash.
+///
+/// Differs from the `fractional` seed (0) so that which users are sampled is
+/// independent of which rollout bucket they land in: with a shared hash, a
+/// 1% sample of a flag would only ever see the users of its first 1% bucket.
+const SAMPLING_SEED: u32 = 0x5EED_0E1F;
+
+/// Keeps a deterministic fraction of exposures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExposureSampler {
+    rate: f64,
+    /// Hashes strictly below this value are sampled, out of `2^32`.
+    threshold: u64,
+}
+
+impl ExposureSampler {
+    /// Creates a sampler keeping `rate` of exposures, between `0.0` (none)
+    /// and `1.0` (all).
+    ///
+    /// Returns `None` when `rate` is outside `[0.0, 1.0]` or not a number.
+    #[must_use]
+    pub fn new(rate: f64) -> Option<Self> {
+        if !(0.0..=1.0).contains(&rate) {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        // `rate` is in [0, 1], so the product is in [0, 2^32] and exact
+        // enough: a rate step of 2^-32 is far below any meaningful sample.
+        let threshold = (rate * 4_294_967_296.0) as u64;
+        Some(Self { rate, threshold })
+    }
+
+    /// The fraction of exposures kept, to record alongside each one.
+    #[must_use]
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Whether the exposure of `flag_key` to `context` is sampled.
+    ///
+    /// The subject is the targeting key; a context without one is identified
+    /// by its attributes instead, so anonymous contexts are still spread
+    /// across the sample rather than all kept or all dropped together.
+    #[must_use]
+    pub fn sampled(&self, flag_key: &str, context: &EvaluationContext) -> bool {
+        let subject = match &context.targeting_key {
+            Some(key) => key.clone(),
+            None => serde_json::to_string(&context.attributes).unwrap_or_default(),
+        };
+        let mut data = Vec::with_capacity(flag_key.len() + 1 + subject.len());
+        data.extend_from_slice(flag_key.as_bytes());
+        // A separator keeps `("ab", "c")` and `("a", "bc")` apart.
+        data.push(0);
+        data.extend_from_slice(subject.as_bytes());
+        u64::from(murmur3_x86_32(&data, SAMPLING_SEED)) < self.threshold
+    }
+}
@@ -0,0 +1,203 @@
+//! The data scope targeting rules evaluate against.
+//!
+//! The scope is a JSON object holding the context attributes, the
+//! targeting key under `targetingKey`, and the reserved `$flagd` object. It
+//! is built once per evaluation, so its cost is paid on every call: only
+//! the attributes the targeting rule can read are copied into it. A server
+//! evaluating rich contexts against rules reading one or two attributes
+//! copies those, not the whole context.
+//!
+//! The attributes a rule can read are found by walking it: the root of
+//! every `var` path and of every literal `missing` key. A rule reading the
+//! whole scope (`{"var": ""}`) or probing keys it computes gets every
+//! attribute, as before. Rules under `map`, `filter`, `all`, `none`, `some`
+//! and `reduce` read the array elements, not the scope, and are not walked.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value, json};
+
+use crate::eval::EvaluationContext;
+use crate::targeting::{Literal, Rule};
+
+/// Builds the data scope `targeting` evaluates against for `flag_key` in
+/// `context`.
+pub(crate) fn evaluation_scope(
+    flag_key: &str,
+    targeting: &Rule,
+    context: &EvaluationContext,
+) -> Value {
+    let mut scope = Map::new();
+    if !copy_read(targeting, &context.attributes, &mut scope) {
+        for (key, value) in &context.attributes {
+            scope.insert(key.clone(), value.clone());
+        }
+    }
+    if let Some(targeting_key) = &context.targeting_key {
+        scope.insert("targetingKey".to_owned(), targeting_key.clone().into());
+    }
+    scope.insert(
+        "$flagd".to_owned(),
+        json!({ "flagKey": flag_key, "timestamp": context.timestamp }),
+    );
+    Value::Object(scope)
+}
+
+/// Copies into `scope` the attributes `rule` can read, returning `false`
+/// when it can read any of them.
+fn copy_read(
+    rule: &Rule,
+    attributes: &BTreeMap<String, Value>,
+    scope: &mut Map<String, Value>,
+) -> bool {
+    let mut walk = |rule: &Rule| copy_read(rule, attributes, scope);
+    match rule {
+        Rule::Literal(_) | Rule::Ref(_) => true,
+        Rule::Var { path, .. } => {
+            if path.is_empty() {
+                return false;
+            }
+            copy_path(path, attributes, scope);
+            true
+        }
+        Rule::Missing(keys) | Rule::MissingSome { keys, .. } => keys
+            .iter()
+            .all(|key| copy_missing_key(key, attributes, scope)),
+        Rule::Array(rules)
+        | Rule::If(rules)
+        | Rule::And(rules)
+        | Rule::Or(rules)
+        | Rule::Lt(rules)
+        | Rule::Lte(rules)
+        | Rule::Add(rules)
+        | Rule::Sub(rules)
+        | Rule::Mul(rules)
+        | Rule::Min(rules)
+        | Rule::Max(rules)
+        | Rule::Cat(rules)
+        | Rule::Substr(rules)
+        | Rule::Merge(rules) => rules.iter().all(walk),
+        Rule::Not(operand) | Rule::Truthy(operand) => walk(operand),
+        Rule::Eq(left, right)
+        | Rule::StrictEq(left, right)
+        | Rule::Neq(left, right)
+        | Rule::StrictNeq(left, right)
+        | Rule::Gt(left, right)
+        | Rule::Gte(left, right)
+        | Rule::Div(left, right)
+        | Rule::Mod(left, right)
+        | Rule::In(left, right)
+        | Rule::StartsWith(left, right)
+        | Rule::EndsWith(left, right) => walk(left) && walk(right),
+        Rule::Map(array, _)
+        | Rule::Filter(array, _)
+        | Rule::All(array, _)
+        | Rule::None(array, _)
+        | Rule::Some(array, _) => walk(array),
+        Rule::Reduce(array, _, initial) => walk(array) && walk(initial),
+        Rule::InSet { needle, .. } | Rule::Regex { value: needle, .. } => walk(needle),
+        Rule::SemVer { value, version, .. } => walk(value) && walk(version),
+        Rule::Fractional { bucket_by, .. } => bucket_by.as_deref().is_none_or(walk),
+        Rule::ConsistentHash { bucket_by, .. } => walk(bucket_by),
+        Rule::Custom(call) => walk(&call.left) && walk(&call.right),
+    }
+}
+
+/// Copies the attributes a `missing` key can name: a literal string, or a
+/// literal array of them. Returns `false` for a key computed at evaluation.
+fn copy_missing_key(
+    key: &Rule,
+    attributes: &BTreeMap<String, Value>,
+    scope: &mut Map<String, Value>,
+) -> bool {
+    let keys = match key {
+        Rule::Array(keys) => keys.as_slice(),
+        key => std::slice::from_ref(key),
+    };
+    keys.iter().all(|key| {
+        let Rule::Literal(Literal::String(path)) = key else {
+            return false;
+        };
+        copy_path(path, attributes, scope);
+        true
+    })
+}
+
+/// Copies the attributes a dotted `path` can resolve through: the one
+/// named by the whole path, and the one named by its first segment (see
+/// the lookup of `var`).
+fn copy_path(path: &str, attributes: &BTreeMap<String, Value>, scope: &mut Map<String, Value>) {
+    let root = path.split_once('.').map_or(path, |(root, _)| root);
+    for name in [path, root] {
+        if scope.contains_key(name) {
+            continue;
+        }
+        if let Some(value) = attributes.get(name) {
+            scope.insert(name.to_owned(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> EvaluationContext {
+        EvaluationContext {
+            targeting_key: Some("user-1".to_owned()),
+            attributes: BTreeMap::from([
+                ("country".to_owned(), json!("FR")),
+                ("plan".to_owned(), json!("pro")),
+                ("user".to_owned(), json!({ "email": "a@example.com" })),
+                ("app.version".to_owned(), json!("4.2.0")),
+            ]),
+            timestamp: 7,
+        }
+    }
+
+    fn scope_keys(targeting: &str) -> Vec<String> {
+        let rule: Rule = serde_json::from_str(targeting).unwrap();
+        let Value::Object(scope) = evaluation_scope("flag", &rule, &context()) else {
+            unreachable!("the scope is an object")
+        };
+        scope.keys().cloned().collect()
+    }
+
+    #[test]
+    fn only_read_attributes_are_copied() {
+        assert_eq!(
+            scope_keys(r#"{"==": [{"var": "country"}, "FR"]}"#),
+            ["$flagd", "country", "targetingKey"]
+        );
+        assert_eq!(
+            scope_keys(r#"{"in": [{"var": "user.email"}, {"var": "app.version"}]}"#),
+            ["$flagd", "app.version", "targetingKey", "user"]
+        );
+        assert_eq!(
+            scope_keys(r#"{"missing": ["plan", "unknown"]}"#),
+            ["$flagd", "plan", "targetingKey"]
+        );
+    }
+
+    #[test]
+    fn rules_reading_anything_get_every_attribute() {
+        let every = [
+            "$flagd",
+            "app.version",
+            "country",
+            "plan",
+            "targetingKey",
+            "user",
+        ];
+        assert_eq!(scope_keys(r#"{"!!": {"var": ""}}"#), every);
+        assert_eq!(scope_keys(r#"{"missing": [{"cat": ["pl", "an"]}]}"#), every);
+    }
+
+    #[test]
+    fn element_rules_are_not_walked() {
+        assert_eq!(
+            scope_keys(r#"{"some": [{"var": "country"}, {"==": [{"var": ""}, "FR"]}]}"#),
+            ["$flagd", "country", "targetingKey"]
+        );
+    }
+}
@@ -10,11 +10,16 @@
 //! Leading `v`/`V` prefixes are stripped before parsing so both `1.2.3` and
 //! `v1.2.3` are accepted.  An unparseable version yields `Value::Null`
 //! (falsy) rather than propagating an error.
+//!
+//! Build metadata (`+build.5`) is ignored, as SemVer precedence requires:
+//! `1.0.0+a` equals `1.0.0+b`. Pre-releases order before their release
+//! (`1.0.0-alpha < 1.0.0`).
 
-use semver::Version;
+use semver::{BuildMetadata, Version};
 use serde_json::Value;
 
 use crate::eval::EvaluationError;
+use crate::eval::Run;
 use crate::logic::apply;
 use crate::targeting::{Rule, SemVerOp};
 
@@ -27,9 +32,10 @@ pub(crate) fn eval_sem_ver(
     op: SemVerOp,
     version: &Rule,
     data: &Value,
+    run: &Run,
 ) -> Result<Value, EvaluationError> {
-    let lhs_val = apply(value, data)?;
-    let rhs_val = apply(version, data)?;
+    let lhs_val = apply(value, data, run)?;
+    let rhs_val = apply(version, data, run)?;
 
     let (Some(lhs_str), Some(rhs_str)) = (as_str(&lhs_val), as_str(&rhs_val)) else {
         return Ok(Value::Null);
@@ -61,8 +67,31 @@ fn as_str(value: &Value) -> Option<&str> {
     value.as_str()
 }
 
-/// Parses a semantic version string, stripping an optional leading `v`/`V`.
+/// Returns `true` when `raw` is a semantic version, with or without a
+/// leading `v`/`V`, as accepted by `sem_ver` and the app version gate.
+#[must_use]
+pub fn is_sem_ver(raw: &str) -> bool {
+    parse_version(raw).is_some()
+}
+
+/// Returns `true` when `app_version` is older than `min_version`, or is
+/// missing or unparseable: the version gate fails closed. An unparseable
+/// `min_version` gates every version too.
+pub(crate) fn below_min_version(app_version: Option<&Value>, min_version: &str) -> bool {
+    let Some(min) = parse_version(min_version) else {
+        return true;
+    };
+    app_version
+        .and_then(Value::as_str)
+        .and_then(parse_version)
+        .is_none_or(|version| version < min)
+}
+
+/// Parses a semantic version string, stripping an optional leading `v`/`V`
+/// and dropping the build metadata, which `Version` would otherwise compare.
 fn parse_version(raw: &str) -> Option<Version> {
     let stripped = raw.strip_prefix(['v', 'V']).unwrap_or(raw);
-    Version::parse(stripped).ok()
+    let mut version = Version::parse(stripped).ok()?;
+    version.build = BuildMetadata::EMPTY;
+    Some(version)
 }
@@ -7,7 +7,7 @@
 use serde_json::{Map, Value};
 
 use crate::model::{Flag, FlagSet, Metadata, MetadataValue, State, Variants};
-use crate::targeting::{Literal, Rule, SemVerOp};
+use crate::targeting::{Bucket, Literal, Rule, SemVerOp};
 
 pub(crate) fn flag_set_value(set: &FlagSet) -> Value {
     let mut root = Map::new();
@@ -139,6 +139,13 @@ pub(crate) fn rule_value(rule: &Rule) -> Value {
         Rule::Cat(args) => op("cat", args),
         Rule::Substr(args) => op("substr", args),
         Rule::In(a, b) => op2("in", a, b),
+        Rule::InSet { needle, set } => op_value(
+            "in",
+            vec![
+                rule_value(needle),
+                Value::Array(set.items().iter().cloned().map(Value::String).collect()),
+            ],
+        ),
         Rule::Merge(args) => op("merge", args),
         Rule::Map(a, b) => op2("map", a, b),
         Rule::Filter(a, b) => op2("filter", a, b),
@@ -150,6 +157,13 @@ pub(crate) fn rule_value(rule: &Rule) -> Value {
         Rule::Some(a, b) => op2("some", a, b),
         Rule::StartsWith(a, b) => op2("starts_with", a, b),
         Rule::EndsWith(a, b) => op2("ends_with", a, b),
+        Rule::Regex { value, pattern } => op_value(
+            "regex",
+            vec![
+                rule_value(value),
+                Value::String(pattern.as_str().to_owned()),
+            ],
+        ),
         Rule::SemVer { value, op, version } => op_value(
             "sem_ver",
             vec![
@@ -163,18 +177,27 @@ pub(crate) fn rule_value(rule: &Rule) -> Value {
             if let Some(expression) = bucket_by {
                 args.push(rule_value(expression));
             }
-            for bucket in buckets {
-                args.push(Value::Array(vec![
-                    Value::String(bucket.variant.clone()),
-                    Value::from(bucket.weight),
-                ]));
-            }
+            args.extend(buckets.iter().map(bucket_value));
             op_value("fractional", args)
         }
+        Rule::ConsistentHash { bucket_by, ring } => {
+            let mut args = Vec::with_capacity(ring.buckets().len() + 1);
+            args.push(rule_value(bucket_by));
+            args.extend(ring.buckets().iter().map(bucket_value));
+            op_value("consistent_hash", args)
+        }
+        Rule::Custom(call) => op2(&call.name, &call.left, &call.right),
         Rule::Ref(name) => op_scalar("$ref", Value::String(name.clone())),
     }
 }
 
+fn bucket_value(bucket: &Bucket) -> Value {
+    Value::Array(vec![
+        Value::String(bucket.variant.clone()),
+        Value::from(bucket.weight),
+    ])
+}
+
 fn literal_value(literal: &Literal) -> Value {
     match literal {
         Literal::Null => Value::Null,
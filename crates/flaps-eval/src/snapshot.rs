@@ -0,0 +1,112 @@
+//! Compact binary encoding of a flag set, for fast SDK bootstrap.
+//!
+//! A snapshot is one format version byte ([`SNAPSHOT_FORMAT_VERSION`])
+//! followed by the canonical flagd document encoded as `MessagePack`. The
+//! encoding is self-describing, so free-form JSON values (object variants,
+//! rule literals) survive unchanged, and decoding skips the text scanning and
+//! number parsing that dominate JSON load time on large flag sets.
+//!
+//! JSON stays the reference format: a snapshot always decodes to the same
+//! [`FlagSet`] as the JSON document it was produced from, and a reader that
+//! meets an unknown version byte is expected to fall back to JSON.
+
+use crate::error::{ParseError, SnapshotError};
+use crate::model::FlagSet;
+
+/// Format version written as the first byte of every snapshot.
+///
+/// Bumped whenever the payload encoding changes incompatibly.
+pub const SNAPSHOT_FORMAT_VERSION: u8 = 1;
+
+/// Media type under which snapshots are exchanged over HTTP.
+pub const SNAPSHOT_MEDIA_TYPE: &str = "application/x-flaps-snapshot";
+
+impl FlagSet {
+    /// Encodes the flag set as a versioned binary snapshot.
+    #[must_use]
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let value = crate::serialize::flag_set_value(self);
+        let mut bytes = vec![SNAPSHOT_FORMAT_VERSION];
+        // Encoding a `serde_json::Value` into a `Vec` cannot fail: every
+        // value maps onto a MessagePack type and the writer is infallible.
+        rmp_serde::encode::write(&mut bytes, &value)
+            .unwrap_or_else(|err| unreachable!("snapshot encoding failed: {err}"));
+        bytes
+    }
+
+    /// Decodes a binary snapshot produced by [`FlagSet::to_snapshot`].
+    ///
+    /// The decoded document goes through the same strict validation as
+    /// [`FlagSet::from_json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapshotError::UnsupportedVersion`] when the version byte is
+    /// not [`SNAPSHOT_FORMAT_VERSION`], and a decode or parse error when the
+    /// payload is corrupt.
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let (&version, payload) = bytes.split_first().ok_or(SnapshotError::Empty)?;
+        if version != SNAPSHOT_FORMAT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: version,
+                expected: SNAPSHOT_FORMAT_VERSION,
+            });
+        }
+        let value: serde_json::Value = rmp_serde::from_slice(payload)?;
+        crate::parse::flag_set(&value, None).map_err(SnapshotError::from)
+    }
+
+    /// Converts a flagd JSON document straight into a binary snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ParseError`] when the document is not a valid flag set.
+    pub fn json_to_snapshot(document: &str) -> Result<Vec<u8>, ParseError> {
+        Self::from_json(document).map(|set| set.to_snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"{"flags":{"banner":{"state":"ENABLED","defaultVariant":"blue","variants":{"blue":{"color":"blue","sizes":[1,2.5]},"red":{"color":"red","sizes":[]}},"targeting":{"if":[{"starts_with":[{"var":"email"},"admin@"]},"red",null]}}},"metadata":{"env":"prod"}}"#;
+
+    #[test]
+    fn snapshot_round_trips_to_the_same_flag_set() {
+        let set = FlagSet::from_json(DOCUMENT).unwrap();
+        let decoded = FlagSet::from_snapshot(&set.to_snapshot()).unwrap();
+        assert_eq!(decoded, set);
+        assert_eq!(decoded.to_json(), set.to_json());
+    }
+
+    #[test]
+    fn snapshot_starts_with_the_format_version() {
+        let bytes = FlagSet::json_to_snapshot(DOCUMENT).unwrap();
+        assert_eq!(bytes.first(), Some(&SNAPSHOT_FORMAT_VERSION));
+    }
+
+    #[test]
+    fn unknown_version_is_reported() {
+        let mut bytes = FlagSet::json_to_snapshot(DOCUMENT).unwrap();
+        bytes[0] = SNAPSHOT_FORMAT_VERSION + 1;
+        assert!(matches!(
+            FlagSet::from_snapshot(&bytes),
+            Err(SnapshotError::UnsupportedVersion { found, expected })
+                if found == SNAPSHOT_FORMAT_VERSION + 1 && expected == SNAPSHOT_FORMAT_VERSION
+        ));
+    }
+
+    #[test]
+    fn empty_and_truncated_snapshots_are_rejected() {
+        assert!(matches!(
+            FlagSet::from_snapshot(&[]),
+            Err(SnapshotError::Empty)
+        ));
+        let bytes = FlagSet::json_to_snapshot(DOCUMENT).unwrap();
+        assert!(matches!(
+            FlagSet::from_snapshot(&bytes[..bytes.len() / 2]),
+            Err(SnapshotError::Decode(_))
+        ));
+    }
+}
@@ -3,11 +3,14 @@
 //! Both operators evaluate two string operands and compare them.  When either
 //! operand evaluates to a non-string JSON value the result degrades to
 //! `Value::Null` (falsy in JsonLogic) rather than propagating an error,
-//! matching the flagd reference semantics for non-conforming inputs.
+//! matching the flagd reference semantics for non-conforming inputs. In a
+//! strict evaluation a non-null, non-string operand is an
+//! [`EvaluationError::TypeMismatch`] instead.
 
 use serde_json::Value;
 
 use crate::eval::EvaluationError;
+use crate::eval::Run;
 use crate::logic::apply;
 use crate::targeting::Rule;
 
@@ -23,15 +26,18 @@ pub(crate) enum Affix {
 /// Evaluates `starts_with` or `ends_with` by first reducing both operands,
 /// then requiring both to be JSON strings.
 ///
-/// Returns `Value::Null` when either operand is not a string.
+/// Returns `Value::Null` when either operand is not a string, or fails with
+/// [`EvaluationError::TypeMismatch`] in a strict `run` when one is
+/// neither a string nor `null`.
 pub(crate) fn eval_string_comparison(
     affix: Affix,
     left: &Rule,
     right: &Rule,
     data: &Value,
+    run: &Run,
 ) -> Result<Value, EvaluationError> {
-    let left_val = apply(left, data)?;
-    let right_val = apply(right, data)?;
+    let left_val = apply(left, data, run)?;
+    let right_val = apply(right, data, run)?;
 
     match (left_val, right_val) {
         (Value::String(haystack), Value::String(needle)) => {
@@ -41,6 +47,19 @@ pub(crate) fn eval_string_comparison(
             };
             Ok(Value::Bool(result))
         }
-        _ => Ok(Value::Null),
+        (left_val, right_val) => match [left_val, right_val]
+            .into_iter()
+            .find(|operand| run.is_strict() && !operand.is_null() && !operand.is_string())
+        {
+            Some(operand) => Err(EvaluationError::TypeMismatch {
+                flag_key: String::new(),
+                operator: match affix {
+                    Affix::Prefix => "starts_with",
+                    Affix::Suffix => "ends_with",
+                },
+                operand,
+            }),
+            None => Ok(Value::Null),
+        },
     }
 }
@@ -0,0 +1,80 @@
+//! Hashed membership for `in` over a long list of string literals.
+//!
+//! JsonLogic `in` scans its array, and the array operand is rebuilt on every
+//! evaluation: explicit lists of user ids, which can hold thousands of
+//! entries, then cost a copy and a linear scan per evaluation. When the
+//! haystack of an `in` is a literal array of at least
+//! [`StringSet::MIN_LEN`] strings, parsing builds a [`Rule::InSet`] whose
+//! [`StringSet`] is indexed once, and evaluation becomes a hash lookup.
+//!
+//! The semantics are those of `in` over the same array: a string needle is
+//! found when it equals an entry, and any other needle is never found.
+//! Shorter lists stay a plain `in`, which scans them faster than it would
+//! hash the needle.
+//!
+//! [`Rule::InSet`]: crate::targeting::Rule::InSet
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::eval::EvaluationError;
+use crate::eval::Run;
+use crate::logic::apply;
+use crate::targeting::Rule;
+
+/// The string literals of an `in` haystack, indexed for membership.
+///
+/// The entries keep their written order, so a set serializes back to the
+/// array it was parsed from; two sets are equal when their entries are.
+#[derive(Debug, Clone)]
+pub struct StringSet {
+    items: Vec<String>,
+    index: HashSet<String>,
+}
+
+impl StringSet {
+    /// Shortest literal array parsing indexes as a [`StringSet`].
+    pub const MIN_LEN: usize = 32;
+
+    /// Indexes `items`.
+    #[must_use]
+    pub fn new(items: Vec<String>) -> Self {
+        let index = items.iter().cloned().collect();
+        Self { items, index }
+    }
+
+    /// Returns the entries, in written order.
+    #[must_use]
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Whether `value` is one of the entries.
+    #[must_use]
+    pub fn contains(&self, value: &str) -> bool {
+        self.index.contains(value)
+    }
+}
+
+impl PartialEq for StringSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.items == other.items
+    }
+}
+
+impl Eq for StringSet {}
+
+/// Evaluates an `in` rule whose haystack is a [`StringSet`].
+pub(crate) fn eval_in_set(
+    needle: &Rule,
+    set: &StringSet,
+    data: &Value,
+    run: &Run,
+) -> Result<Value, EvaluationError> {
+    let found = match apply(needle, data, run)? {
+        Value::String(needle) => set.contains(&needle),
+        _ => false,
+    };
+    Ok(Value::Bool(found))
+}
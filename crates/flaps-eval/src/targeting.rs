@@ -8,6 +8,11 @@ use serde::de::Deserializer;
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 
+use crate::custom_operator::CustomCall;
+use crate::hash_ring::HashRing;
+use crate::regex_match::Pattern;
+use crate::string_set::StringSet;
+
 /// A single targeting rule node.
 ///
 /// A rule evaluates against an evaluation context and resolves to a JSON
@@ -117,6 +122,18 @@ pub enum Rule {
     /// `in`: membership in a string or an array.
     In(Box<Rule>, Box<Rule>),
 
+    /// `in` over a long literal array of strings, with the array indexed
+    /// as a [`StringSet`]; serialized as a plain `in`.
+    ///
+    /// Parsing produces it in place of [`Rule::In`] for arrays of at least
+    /// [`StringSet::MIN_LEN`] strings, such as explicit user lists.
+    InSet {
+        /// Expression producing the value looked up.
+        needle: Box<Rule>,
+        /// The indexed array.
+        set: StringSet,
+    },
+
     /// `merge`: flattens arrays into a single array.
     Merge(Vec<Rule>),
 
@@ -144,6 +161,21 @@ pub enum Rule {
     /// `ends_with`: the string attribute ends with the given suffix.
     EndsWith(Box<Rule>, Box<Rule>),
 
+    /// `regex`: the string value matches a regular expression, a Flaps
+    /// extension to the flagd schema.
+    ///
+    /// Written `{"regex": [{"var": "email"}, "@example\\.com$"]}`; the
+    /// pattern must be a string literal and is compiled once, at parse time.
+    /// The match is unanchored unless the pattern anchors itself. A value
+    /// that is not a string resolves to `null`, and an invalid pattern
+    /// never matches.
+    Regex {
+        /// Expression producing the string under test.
+        value: Box<Rule>,
+        /// The compiled pattern.
+        pattern: Pattern,
+    },
+
     /// `sem_ver`: semantic version comparison, e.g. `["1.1.2", ">=", "1.0.0"]`.
     SemVer {
         /// The version under test, usually read from the context.
@@ -167,6 +199,27 @@ pub enum Rule {
         buckets: Vec<Bucket>,
     },
 
+    /// `consistent_hash`: weighted variant distribution on a hash ring, a
+    /// Flaps extension to the flagd schema.
+    ///
+    /// Written like `fractional`, with a mandatory bucketing expression:
+    /// `{"consistent_hash": [{"var": "host"}, ["on", 10], ["off", 90]]}`.
+    /// A weight change only moves keys onto or off the variants whose
+    /// weight changed; see [`HashRing`].
+    ConsistentHash {
+        /// Expression producing the bucketing value; a non-string value
+        /// resolves to `null`.
+        bucket_by: Box<Rule>,
+        /// The weighted variants placed on the ring.
+        ring: HashRing,
+    },
+
+    /// A call to an operator registered in [`CustomOperators`], written
+    /// `{"name": [left, right]}` and resolving to a boolean.
+    ///
+    /// [`CustomOperators`]: crate::CustomOperators
+    Custom(CustomCall),
+
     /// `$ref`: reference to a reusable rule declared under `$evaluators`.
     ///
     /// References are resolved and inlined by [`FlagSet::from_json`]; this
@@ -0,0 +1,154 @@
+//! Conversion of resolved variant values to the types SDK accessors serve.
+//!
+//! Variant values are JSON. A typed accessor (`bool`, integer, float, string,
+//! object) reads them through [`FlagValue`], so every accessor applies the
+//! same conversion rules and the same fail-closed policy via
+//! [`Resolution::value_or`](crate::Resolution::value_or).
+//!
+//! What is served instead of a missing value is a [`Fallback`]: the caller's
+//! default, which suits configuration flags, or the type's closed value,
+//! which suits gates. [`Fallbacks`] registers the policy of each flag key.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+/// `i64::MIN` expressed as `f64` (exactly representable).
+pub const I64_MIN_F64: f64 = -9_223_372_036_854_775_808.0_f64;
+
+/// Largest `f64` value that can be cast to `i64` without truncation.
+///
+/// `i64::MAX` (2^63 - 1) rounds up to 2^63 when converted to `f64`, which
+/// overflows on cast back. The previous exactly-representable `f64` is
+/// 2^63 - 1024.
+pub const I64_MAX_SAFE_F64: f64 = 9_223_372_036_854_774_784.0_f64;
+
+/// A type a resolved variant value can be served as.
+pub trait FlagValue: Sized {
+    /// Converts a variant value, or returns `None` on a type mismatch.
+    fn from_flag_value(value: &Value) -> Option<Self>;
+
+    /// The value [`Fallback::Closed`] serves, or `None`, the default, for a
+    /// type that has no notion of off.
+    #[must_use]
+    fn closed() -> Option<Self> {
+        None
+    }
+}
+
+/// Closed is `false`: a gate that cannot be evaluated stays shut.
+impl FlagValue for bool {
+    fn from_flag_value(value: &Value) -> Option<Self> {
+        value.as_bool()
+    }
+
+    fn closed() -> Option<Self> {
+        Some(false)
+    }
+}
+
+/// Accepts integers, and floats that are whole and within `i64` range (a
+/// ruleset may carry `10` as `10.0`).
+impl FlagValue for i64 {
+    fn from_flag_value(value: &Value) -> Option<Self> {
+        if let Some(i) = value.as_i64() {
+            return Some(i);
+        }
+        value.as_f64().and_then(|f| {
+            if f.fract() == 0.0 && (I64_MIN_F64..=I64_MAX_SAFE_F64).contains(&f) {
+                #[allow(clippy::cast_possible_truncation)]
+                // Whole and range-checked above, so the cast is exact.
+                Some(f as i64)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl FlagValue for f64 {
+    fn from_flag_value(value: &Value) -> Option<Self> {
+        value.as_f64()
+    }
+}
+
+impl FlagValue for String {
+    fn from_flag_value(value: &Value) -> Option<Self> {
+        value.as_str().map(ToOwned::to_owned)
+    }
+}
+
+impl FlagValue for Map<String, Value> {
+    fn from_flag_value(value: &Value) -> Option<Self> {
+        value.as_object().cloned()
+    }
+}
+
+/// Any value, unconverted.
+impl FlagValue for Value {
+    fn from_flag_value(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+/// What a typed accessor serves when a flag yields no value of its type: the
+/// flag is missing, disabled or killed, its evaluation failed, or it resolved
+/// to another type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// The default the caller passed: fail safe, for configuration flags
+    /// whose default is a working value.
+    #[default]
+    CallerDefault,
+    /// The closed value of the type ([`FlagValue::closed`], `false` for a
+    /// boolean) whatever the caller passed: fail closed, for gates. Types
+    /// without a closed value serve the caller's default.
+    Closed,
+}
+
+impl Fallback {
+    /// Returns what to serve in place of a missing value, given the caller's
+    /// `default`.
+    #[must_use]
+    pub fn apply<T: FlagValue>(self, default: T) -> T {
+        match self {
+            Self::CallerDefault => default,
+            Self::Closed => T::closed().unwrap_or(default),
+        }
+    }
+}
+
+/// The [`Fallback`] of each flag key, with a default for unregistered keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fallbacks {
+    default: Fallback,
+    flags: HashMap<String, Fallback>,
+}
+
+impl Fallbacks {
+    /// Creates a registry serving [`Fallback::CallerDefault`] for every flag.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the fallback of the flags without one of their own.
+    #[must_use]
+    pub fn with_default(mut self, fallback: Fallback) -> Self {
+        self.default = fallback;
+        self
+    }
+
+    /// Sets the fallback of `flag_key`.
+    #[must_use]
+    pub fn with_flag(mut self, flag_key: impl Into<String>, fallback: Fallback) -> Self {
+        self.flags.insert(flag_key.into(), fallback);
+        self
+    }
+
+    /// Returns the fallback of `flag_key`.
+    #[must_use]
+    pub fn for_flag(&self, flag_key: &str) -> Fallback {
+        self.flags.get(flag_key).copied().unwrap_or(self.default)
+    }
+}
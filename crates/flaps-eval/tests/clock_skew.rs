@@ -0,0 +1,95 @@
+//! Timestamp quantization: evaluators with skewed clocks resolve a
+//! time-based ramp to the same step within a quantization window.
+
+use std::time::Duration;
+
+use flaps_eval::{EvaluationContext, FlagSet};
+
+/// Start of a quantization window (a multiple of 60 seconds).
+const WINDOW: u64 = 1_700_000_040;
+
+/// A ramp stepping up at `WINDOW + 30` and again ten minutes later. Neither
+/// activation time is aligned on a minute.
+fn ramp() -> FlagSet {
+    let step_1 = WINDOW + 30;
+    let step_2 = step_1 + 600;
+    FlagSet::from_json(&format!(
+        r#"{{
+            "flags": {{
+                "ramp": {{
+                    "state": "ENABLED",
+                    "variants": {{ "step-0": 0, "step-1": 10, "step-2": 50 }},
+                    "defaultVariant": "step-0",
+                    "targeting": {{
+                        "if": [
+                            {{">=": [{{"var": "$flagd.timestamp"}}, {step_2}]}}, "step-2",
+                            {{">=": [{{"var": "$flagd.timestamp"}}, {step_1}]}}, "step-1",
+                            "step-0"
+                        ]
+                    }}
+                }}
+            }}
+        }}"#
+    ))
+    .expect("valid flag set")
+}
+
+/// Resolves the ramp on a node whose clock reads `now`.
+fn step_at(now: u64, quantum: Duration) -> String {
+    let context = EvaluationContext {
+        timestamp: now,
+        ..EvaluationContext::default()
+    }
+    .with_timestamp_quantum(quantum);
+    ramp()
+        .evaluate("ramp", &context)
+        .expect("evaluates")
+        .variant
+        .expect("resolves a variant")
+}
+
+#[test]
+fn skewed_clocks_disagree_without_quantization() {
+    // Node B runs three seconds ahead of node A, across the activation time.
+    let node_a = WINDOW + 29;
+    let node_b = node_a + 3;
+    assert_eq!(step_at(node_a, Duration::ZERO), "step-0");
+    assert_eq!(step_at(node_b, Duration::ZERO), "step-1");
+}
+
+#[test]
+fn skewed_clocks_agree_within_the_quantization_window() {
+    let quantum = Duration::from_secs(60);
+    for offset in 0..57 {
+        let node_a = WINDOW + offset;
+        let node_b = node_a + 3;
+        assert_eq!(
+            step_at(node_a, quantum),
+            step_at(node_b, quantum),
+            "clocks {node_a} and {node_b} disagree"
+        );
+    }
+}
+
+#[test]
+fn quantized_ramp_steps_at_the_next_boundary() {
+    let quantum = Duration::from_secs(60);
+    assert_eq!(step_at(WINDOW + 59, quantum), "step-0");
+    assert_eq!(step_at(WINDOW + 60, quantum), "step-1");
+    assert_eq!(step_at(WINDOW + 659, quantum), "step-1");
+    assert_eq!(step_at(WINDOW + 660, quantum), "step-2");
+}
+
+#[test]
+fn sub_second_quantum_leaves_the_timestamp_unchanged() {
+    let context = EvaluationContext {
+        timestamp: WINDOW + 29,
+        ..EvaluationContext::default()
+    };
+    for quantum in [Duration::ZERO, Duration::from_millis(900)] {
+        assert_eq!(
+            context.clone().with_timestamp_quantum(quantum).timestamp,
+            WINDOW + 29
+        );
+    }
+}
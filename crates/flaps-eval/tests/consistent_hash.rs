@@ -0,0 +1,173 @@
+//! The `consistent_hash` operator: hosts keep their variant when the fleet
+//! grows, and a weight change only moves hosts onto or off the variants
+//! whose weight changed, where `fractional` shifts every range after the
+//! resized one.
+
+use std::collections::BTreeMap;
+
+use flaps_eval::{EvaluationContext, FlagSet, ParseError, Reason};
+use serde_json::{Value, json};
+
+const FLEET: usize = 2000;
+
+/// A document with one string flag `deploy` distributed by `operator` over
+/// the `host` attribute with `weights`.
+fn document(operator: &str, weights: &[(&str, u32)]) -> FlagSet {
+    let variants: serde_json::Map<String, Value> = weights
+        .iter()
+        .map(|(name, _)| ((*name).to_owned(), json!(name)))
+        .collect();
+    let mut args = vec![json!({"var": "host"})];
+    args.extend(weights.iter().map(|(name, weight)| json!([name, weight])));
+    let document = json!({
+        "flags": {
+            "deploy": {
+                "state": "ENABLED",
+                "variants": variants,
+                "defaultVariant": weights[0].0,
+                "targeting": { operator: args }
+            }
+        }
+    });
+    FlagSet::from_json(&document.to_string()).unwrap()
+}
+
+fn context(host: Value) -> EvaluationContext {
+    EvaluationContext {
+        attributes: [("host".to_owned(), host)].into(),
+        ..EvaluationContext::default()
+    }
+}
+
+/// Assigns every host of a fleet of `size`.
+fn assign(flag_set: &FlagSet, size: usize) -> Vec<String> {
+    (0..size)
+        .map(|i| {
+            let resolution = flag_set
+                .evaluate("deploy", &context(json!(format!("web-{i}"))))
+                .unwrap();
+            resolution.variant.unwrap()
+        })
+        .collect()
+}
+
+fn count(assignment: &[String], variant: &str) -> usize {
+    assignment.iter().filter(|v| *v == variant).count()
+}
+
+/// Counts the hosts moved from one variant to another, per `(from, to)`.
+fn moves(before: &[String], after: &[String]) -> BTreeMap<(String, String), usize> {
+    let mut moves = BTreeMap::new();
+    for (from, to) in before.iter().zip(after).filter(|(from, to)| from != to) {
+        *moves.entry((from.clone(), to.clone())).or_default() += 1;
+    }
+    moves
+}
+
+#[test]
+fn shares_follow_the_weights() {
+    let flag_set = document("consistent_hash", &[("on", 20), ("off", 80)]);
+    let assignment = assign(&flag_set, FLEET);
+    let on = count(&assignment, "on");
+    assert!((300..500).contains(&on), "{on} of {FLEET} hosts on");
+}
+
+#[test]
+fn adding_a_host_leaves_every_other_host_in_place() {
+    let flag_set = document("consistent_hash", &[("on", 30), ("off", 70)]);
+    let before = assign(&flag_set, FLEET);
+    let after = assign(&flag_set, FLEET + 1);
+    assert_eq!(after[..FLEET], before[..]);
+}
+
+#[test]
+fn ramping_a_variant_only_moves_hosts_onto_it() {
+    let weights = |canary| [("canary", canary), ("stable", 50), ("legacy", 50)];
+    let before = assign(&document("consistent_hash", &weights(5)), FLEET);
+    let after = assign(&document("consistent_hash", &weights(15)), FLEET);
+
+    let moved = moves(&before, &after);
+    assert!(
+        moved.keys().all(|(_, to)| to == "canary"),
+        "unexpected moves: {moved:?}"
+    );
+    let gained: usize = moved.values().sum();
+    assert!(gained > FLEET / 20, "only {gained} hosts ramped");
+}
+
+#[test]
+fn adding_a_variant_only_moves_hosts_onto_it() {
+    let before = assign(
+        &document("consistent_hash", &[("stable", 50), ("legacy", 50)]),
+        FLEET,
+    );
+    let after = assign(
+        &document(
+            "consistent_hash",
+            &[("stable", 50), ("legacy", 50), ("canary", 10)],
+        ),
+        FLEET,
+    );
+    let moved = moves(&before, &after);
+    assert!(
+        moved.keys().all(|(_, to)| to == "canary"),
+        "unexpected moves: {moved:?}"
+    );
+}
+
+#[test]
+fn fractional_reshuffles_untouched_variants_on_the_same_ramp() {
+    let weights = |canary| [("canary", canary), ("stable", 50), ("legacy", 50)];
+    let before = assign(&document("fractional", &weights(5)), FLEET);
+    let after = assign(&document("fractional", &weights(15)), FLEET);
+    let moved = moves(&before, &after);
+    assert!(
+        moved.keys().any(|(_, to)| to != "canary"),
+        "expected fractional to move hosts between untouched variants"
+    );
+}
+
+#[test]
+fn non_string_bucketing_values_serve_the_default() {
+    let flag_set = document("consistent_hash", &[("on", 50), ("off", 50)]);
+    for host in [json!(7), Value::Null] {
+        let resolution = flag_set.evaluate("deploy", &context(host)).unwrap();
+        assert_eq!(resolution.reason, Reason::Default);
+        assert_eq!(resolution.variant.as_deref(), Some("on"));
+    }
+}
+
+#[test]
+fn consistent_hash_requires_a_bucketing_expression_and_bounded_weights() {
+    for targeting in [
+        json!({"consistent_hash": [["on", 50], ["off", 50]]}),
+        json!({"consistent_hash": [{"var": "host"}]}),
+        json!({"consistent_hash": {"var": "host"}}),
+        json!({"consistent_hash": [{"var": "host"}, ["on", 6000], ["off", 6000]]}),
+    ] {
+        let document = json!({
+            "flags": {
+                "deploy": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": targeting
+                }
+            }
+        });
+        let result = FlagSet::from_json(&document.to_string());
+        assert!(
+            matches!(
+                result,
+                Err(ParseError::InvalidArguments { ref operator, .. }) if operator == "consistent_hash"
+            ),
+            "{result:?}"
+        );
+    }
+}
+
+#[test]
+fn consistent_hash_survives_a_round_trip() {
+    let flag_set = document("consistent_hash", &[("on", 10), ("off", 90)]);
+    assert_eq!(FlagSet::from_json(&flag_set.to_json()).unwrap(), flag_set);
+}
@@ -0,0 +1,100 @@
+//! Context guards: over-sized or over-wide contexts are refused with the
+//! offending limit and path, ordinary contexts pass.
+
+use flaps_eval::{ContextLimitError, ContextLimits, EvaluationContext};
+use serde_json::{Value, json};
+
+fn context(attributes: Value) -> EvaluationContext {
+    let Value::Object(attributes) = attributes else {
+        panic!("attributes must be an object");
+    };
+    EvaluationContext {
+        targeting_key: Some("user-1".to_owned()),
+        attributes: attributes.into_iter().collect(),
+        ..EvaluationContext::default()
+    }
+}
+
+/// Small limits so each test can cross exactly one of them.
+fn limits() -> ContextLimits {
+    ContextLimits {
+        max_attributes: 4,
+        max_key_length: 8,
+        max_value_length: 16,
+        max_list_length: 3,
+    }
+}
+
+fn violation(context: &EvaluationContext) -> ContextLimitError {
+    context
+        .validate_limits(&limits())
+        .expect_err("context must be refused")
+}
+
+#[test]
+fn ordinary_context_passes() {
+    let context = context(json!({
+        "plan": "pro",
+        "seats": 12,
+        "tags": ["a", "b", "c"],
+        "org": {"id": "org-1", "eu": true},
+    }));
+    assert_eq!(context.validate_limits(&limits()), Ok(()));
+    assert_eq!(context.validate_limits(&ContextLimits::default()), Ok(()));
+}
+
+#[test]
+fn too_many_attributes_are_refused() {
+    let error = violation(&context(json!({"a": 1, "b": 2, "c": 3, "d": 4, "e": 5})));
+    assert_eq!(
+        error,
+        ContextLimitError {
+            limit: "max_attributes",
+            path: String::new(),
+            max: 4,
+            actual: 5,
+        }
+    );
+}
+
+#[test]
+fn long_attribute_names_are_refused() {
+    let error = violation(&context(json!({"organisation": "acme"})));
+    assert_eq!(
+        (error.limit, error.path.as_str()),
+        ("max_key_length", "organisation")
+    );
+}
+
+#[test]
+fn long_nested_strings_are_refused_with_their_path() {
+    let error = violation(&context(json!({"org": {"tags": ["ok", "x".repeat(17)]}})));
+    assert_eq!(
+        error,
+        ContextLimitError {
+            limit: "max_value_length",
+            path: "org.tags[1]".to_owned(),
+            max: 16,
+            actual: 17,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "context `org.tags[1]` exceeds max_value_length: 17 > 16"
+    );
+}
+
+#[test]
+fn long_lists_are_refused() {
+    let error = violation(&context(json!({"tags": [1, 2, 3, 4]})));
+    assert_eq!((error.limit, error.actual), ("max_list_length", 4));
+}
+
+#[test]
+fn long_targeting_key_is_refused() {
+    let context = EvaluationContext {
+        targeting_key: Some("u".repeat(17)),
+        ..EvaluationContext::default()
+    };
+    assert_eq!(violation(&context).path, "targetingKey");
+}
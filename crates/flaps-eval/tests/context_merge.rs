@@ -0,0 +1,84 @@
+//! Layering evaluation contexts: the other side wins, fill-only merging,
+//! and explicit precedence.
+
+use flaps_eval::{EvaluationContext, MergePrecedence};
+use serde_json::{Value, json};
+
+fn context(targeting_key: Option<&str>, attributes: Value, timestamp: u64) -> EvaluationContext {
+    let Value::Object(attributes) = attributes else {
+        panic!("attributes must be an object");
+    };
+    EvaluationContext {
+        targeting_key: targeting_key.map(str::to_owned),
+        attributes: attributes.into_iter().collect(),
+        timestamp,
+    }
+}
+
+/// Service defaults: a region and a plan, no user, no clock.
+fn defaults() -> EvaluationContext {
+    context(
+        None,
+        json!({ "region": "eu-west", "plan": "free", "meta": { "tier": 1 } }),
+        0,
+    )
+}
+
+/// A request context overriding the plan and adding a country.
+fn request() -> EvaluationContext {
+    context(
+        Some("user-1"),
+        json!({ "plan": "pro", "country": "FR", "meta": { "beta": true } }),
+        1_700_000_000,
+    )
+}
+
+#[test]
+fn merge_lets_the_other_context_win() {
+    let merged = defaults().merge(request());
+
+    assert_eq!(
+        merged,
+        context(
+            Some("user-1"),
+            json!({
+                "region": "eu-west",
+                "plan": "pro",
+                "country": "FR",
+                "meta": { "beta": true },
+            }),
+            1_700_000_000,
+        )
+    );
+}
+
+#[test]
+fn merge_fill_leaves_existing_keys_untouched() {
+    let merged = request().merge_fill(defaults());
+
+    assert_eq!(merged.attributes["plan"], json!("pro"));
+    assert_eq!(merged.attributes["meta"], json!({ "beta": true }));
+    assert_eq!(merged.attributes["region"], json!("eu-west"));
+    assert_eq!(merged.targeting_key.as_deref(), Some("user-1"));
+    assert_eq!(merged.timestamp, 1_700_000_000);
+}
+
+#[test]
+fn base_precedence_keeps_the_base_values() {
+    let merged = defaults().merge_with(request(), MergePrecedence::Base);
+
+    assert_eq!(merged.attributes["plan"], json!("free"));
+    assert_eq!(merged.attributes["meta"], json!({ "tier": 1 }));
+    assert_eq!(merged.attributes["country"], json!("FR"));
+    // Unset on the base, so taken from the other side.
+    assert_eq!(merged.targeting_key.as_deref(), Some("user-1"));
+    assert_eq!(merged.timestamp, 1_700_000_000);
+}
+
+#[test]
+fn the_two_precedences_are_mirror_images() {
+    assert_eq!(
+        defaults().merge_with(request(), MergePrecedence::Other),
+        request().merge_with(defaults(), MergePrecedence::Base)
+    );
+}
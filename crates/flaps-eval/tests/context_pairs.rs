@@ -0,0 +1,165 @@
+//! Evaluation contexts read from and written to flat `key=value` pairs:
+//! type inference, forcing suffixes, lists and the round trip.
+
+use flaps_eval::{ContextPairError, EvaluationContext};
+use serde_json::{Value, json};
+
+fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+    items
+        .iter()
+        .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+        .collect()
+}
+
+fn read(items: &[(&str, &str)]) -> EvaluationContext {
+    EvaluationContext::from_pairs(&pairs(items)).unwrap()
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    read(&[(key, value)])
+        .attributes
+        .into_values()
+        .next()
+        .unwrap()
+}
+
+#[test]
+fn values_are_inferred_from_their_text() {
+    assert_eq!(attribute("beta", "true"), json!(true));
+    assert_eq!(attribute("beta", "false"), json!(false));
+    assert_eq!(attribute("seats", "12"), json!(12));
+    assert_eq!(attribute("ratio", "-0.5"), json!(-0.5));
+    assert_eq!(attribute("plan", "null"), Value::Null);
+    assert_eq!(attribute("plan", "beta"), json!("beta"));
+    assert_eq!(attribute("plan", "\"12\""), json!("12"));
+    assert_eq!(attribute("plan", ""), json!(""));
+    assert_eq!(attribute("zip", "02134"), json!("02134"));
+    assert_eq!(attribute("meta", r#"{"tier":1}"#), json!({"tier": 1}));
+}
+
+#[test]
+fn comma_separated_values_are_lists() {
+    assert_eq!(attribute("tags", "a,b"), json!(["a", "b"]));
+    assert_eq!(attribute("tags", "a,,b"), json!(["a", "", "b"]));
+    assert_eq!(attribute("tags", r#"["a","b"]"#), json!(["a", "b"]));
+    assert_eq!(attribute("tags:list", "solo"), json!(["solo"]));
+    assert_eq!(attribute("tags:list", ""), json!([]));
+    assert_eq!(attribute("ids", "1,2"), json!(["1", "2"]));
+}
+
+#[test]
+fn suffixes_force_the_type() {
+    assert_eq!(attribute("seats:string", "123"), json!("123"));
+    assert_eq!(attribute("flag:string", "true"), json!("true"));
+    assert_eq!(attribute("note:string", "a,b"), json!("a,b"));
+    assert_eq!(attribute("seats:number", "123"), json!(123));
+    assert_eq!(attribute("beta:bool", "true"), json!(true));
+    assert_eq!(attribute("meta:json", "[1,2]"), json!([1, 2]));
+
+    let context = read(&[("seats:string", "123")]);
+    assert!(context.attributes.contains_key("seats"));
+}
+
+#[test]
+fn unknown_suffixes_are_part_of_the_name() {
+    let context = read(&[("app:env", "prod")]);
+    assert_eq!(context.attributes.get("app:env"), Some(&json!("prod")));
+}
+
+#[test]
+fn targeting_key_is_read_as_a_string() {
+    let context = read(&[("targetingKey", "42"), ("plan", "beta")]);
+    assert_eq!(context.targeting_key.as_deref(), Some("42"));
+    assert!(!context.attributes.contains_key("targetingKey"));
+}
+
+#[test]
+fn later_pairs_win() {
+    assert_eq!(
+        read(&[("plan", "free"), ("plan", "beta")]),
+        read(&[("plan", "beta")])
+    );
+}
+
+#[test]
+fn malformed_pairs_are_refused() {
+    for (key, value, expected) in [
+        ("seats:number", "many", "number"),
+        ("seats:number", "\"12\"", "number"),
+        ("beta:bool", "yes", "bool"),
+        ("meta:json", "{", "json"),
+    ] {
+        assert_eq!(
+            EvaluationContext::from_pairs(&pairs(&[(key, value)])),
+            Err(ContextPairError::InvalidValue {
+                key: key.split(':').next().unwrap().to_owned(),
+                value: value.to_owned(),
+                expected,
+            })
+        );
+    }
+    for key in ["", ":string"] {
+        assert!(matches!(
+            EvaluationContext::from_pairs(&pairs(&[(key, "x")])),
+            Err(ContextPairError::EmptyKey(_))
+        ));
+    }
+}
+
+#[test]
+fn to_pairs_only_suffixes_what_inference_would_misread() {
+    let context = EvaluationContext {
+        targeting_key: Some("user-1".to_owned()),
+        attributes: [
+            ("beta".to_owned(), json!(true)),
+            ("plan".to_owned(), json!("beta")),
+            ("seats".to_owned(), json!(12)),
+            ("tags".to_owned(), json!(["a", "b"])),
+            ("solo".to_owned(), json!(["a"])),
+            ("zip".to_owned(), json!("02134")),
+            ("code".to_owned(), json!("123")),
+            ("unset".to_owned(), Value::Null),
+        ]
+        .into(),
+        ..EvaluationContext::default()
+    };
+    assert_eq!(
+        context.to_pairs(),
+        pairs(&[
+            ("targetingKey", "user-1"),
+            ("beta", "true"),
+            ("code:string", "123"),
+            ("plan", "beta"),
+            ("seats", "12"),
+            ("solo:list", "a"),
+            ("tags", "a,b"),
+            ("unset", "null"),
+            ("zip", "02134"),
+        ])
+    );
+}
+
+#[test]
+fn contexts_survive_a_round_trip() {
+    let context = EvaluationContext {
+        targeting_key: Some("user-1".to_owned()),
+        attributes: [
+            ("flag".to_owned(), json!("true")),
+            ("note".to_owned(), json!("a,b")),
+            ("quoted".to_owned(), json!("\"x\"")),
+            ("empty".to_owned(), json!([])),
+            ("blank".to_owned(), json!([""])),
+            ("commas".to_owned(), json!(["a,b", "c"])),
+            ("mixed".to_owned(), json!([1, "a"])),
+            ("meta".to_owned(), json!({"tier": 1})),
+            ("app:string".to_owned(), json!("x")),
+            ("ratio".to_owned(), json!(0.25)),
+        ]
+        .into(),
+        ..EvaluationContext::default()
+    };
+    assert_eq!(
+        EvaluationContext::from_pairs(&context.to_pairs()).unwrap(),
+        context
+    );
+}
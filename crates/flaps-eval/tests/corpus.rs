@@ -127,6 +127,8 @@ fn reason_to_ofrep(reason: Reason) -> &'static str {
         Reason::TargetingMatch => "TARGETING_MATCH",
         Reason::Default => "DEFAULT",
         Reason::Disabled => "DISABLED",
+        Reason::Killed => "KILLED",
+        Reason::VersionGated => "VERSION_GATED",
     }
 }
 
@@ -134,9 +136,11 @@ fn reason_to_ofrep(reason: Reason) -> &'static str {
 fn error_to_code(err: &EvaluationError) -> &'static str {
     match err {
         EvaluationError::FlagNotFound { .. } => "FLAG_NOT_FOUND",
-        EvaluationError::InvalidVariant { .. } | EvaluationError::UnsupportedOperation { .. } => {
-            "VARIANT_NOT_FOUND"
-        }
+        EvaluationError::InvalidVariant { .. }
+        | EvaluationError::UnsupportedOperation { .. }
+        | EvaluationError::BudgetExceeded { .. }
+        | EvaluationError::UnresolvedEvaluator { .. }
+        | EvaluationError::TypeMismatch { .. } => "VARIANT_NOT_FOUND",
     }
 }
 
@@ -1,5 +1,5 @@
 //! Evaluation tests for the flagd custom operators: `starts_with`, `ends_with`,
-//! `sem_ver` and `fractional`.
+//! `sem_ver` and `fractional`, and the Flaps `regex` extension.
 //!
 //! Rules are exercised through flag targeting in the same style as
 //! `eval_logic.rs`: a single boolean probe flag wraps the rule under test and
@@ -179,6 +179,65 @@ fn ends_with_reads_context_attribute() {
     ));
 }
 
+// ---------------------------------------------------------------------------
+// regex
+// ---------------------------------------------------------------------------
+
+/// Without anchors a pattern matches anywhere in the string.
+#[test]
+fn regex_unanchored_matches_a_substring() {
+    let ctx = ctx_attrs(r#"{"email": "ada@example.com.evil.io"}"#);
+    assert!(matches_with(
+        r#"{"regex": [{"var": "email"}, "@example\\.com"]}"#,
+        &ctx
+    ));
+}
+
+/// Anchors pin the match to the whole string.
+#[test]
+fn regex_anchored_matches_the_whole_string_only() {
+    let rule = r#"{"regex": [{"var": "email"}, "^[a-z]+@example\\.com$"]}"#;
+    assert!(matches_with(
+        rule,
+        &ctx_attrs(r#"{"email": "ada@example.com"}"#)
+    ));
+    assert!(!matches_with(
+        rule,
+        &ctx_attrs(r#"{"email": "ada@example.com.evil.io"}"#)
+    ));
+    assert!(!matches_with(
+        rule,
+        &ctx_attrs(r#"{"email": "Ada@example.com"}"#)
+    ));
+}
+
+/// A value that is not a string never matches.
+#[test]
+fn regex_null_when_value_is_not_a_string() {
+    assert!(!matches_with(
+        r#"{"regex": [{"var": "age"}, "4"]}"#,
+        &ctx_attrs(r#"{"age": 42}"#)
+    ));
+    assert!(!matches(r#"{"regex": [{"var": "email"}, ".*"]}"#));
+}
+
+/// A malformed pattern refuses the flag set rather than failing open under
+/// `!`.
+#[test]
+fn regex_malformed_pattern_is_refused() {
+    let document = r#"{
+        "flags": {
+            "probe": {
+                "state": "ENABLED",
+                "variants": { "true": true, "false": false },
+                "defaultVariant": "false",
+                "targeting": {"!": {"regex": [{"var": "email"}, "(unclosed"]}}
+            }
+        }
+    }"#;
+    assert!(FlagSet::from_json(document).is_err());
+}
+
 // ---------------------------------------------------------------------------
 // sem_ver
 // ---------------------------------------------------------------------------
@@ -363,6 +422,53 @@ fn sem_ver_tolerates_v_prefix_in_constraint() {
     ));
 }
 
+/// Components compare numerically, not as strings.
+#[test]
+fn sem_ver_compares_components_numerically() {
+    let ctx = ctx_attrs(r#"{"version": "1.2.10"}"#);
+    assert!(matches_with(
+        r#"{"sem_ver": [{"var": "version"}, ">", "1.2.9"]}"#,
+        &ctx
+    ));
+}
+
+/// A pre-release orders before its release, and pre-release identifiers
+/// order among themselves.
+#[test]
+fn sem_ver_orders_pre_releases_before_their_release() {
+    let ctx = ctx_attrs(r#"{"version": "1.0.0-alpha"}"#);
+    assert!(matches_with(
+        r#"{"sem_ver": [{"var": "version"}, "<", "1.0.0"]}"#,
+        &ctx
+    ));
+    assert!(matches_with(
+        r#"{"sem_ver": [{"var": "version"}, "<", "1.0.0-beta"]}"#,
+        &ctx
+    ));
+    assert!(!matches_with(
+        r#"{"sem_ver": [{"var": "version"}, ">=", "1.0.0"]}"#,
+        &ctx
+    ));
+}
+
+/// Build metadata takes no part in comparisons.
+#[test]
+fn sem_ver_ignores_build_metadata() {
+    let ctx = ctx_attrs(r#"{"version": "1.0.0+build.7"}"#);
+    assert!(matches_with(
+        r#"{"sem_ver": [{"var": "version"}, "=", "1.0.0+build.9"]}"#,
+        &ctx
+    ));
+    assert!(matches_with(
+        r#"{"sem_ver": [{"var": "version"}, "=", "1.0.0"]}"#,
+        &ctx
+    ));
+    assert!(!matches_with(
+        r#"{"sem_ver": [{"var": "version"}, ">", "1.0.0"]}"#,
+        &ctx
+    ));
+}
+
 // ---------------------------------------------------------------------------
 // fractional - general behaviour
 // ---------------------------------------------------------------------------
@@ -261,6 +261,26 @@ fn array_membership_is_strict() {
     assert!(matches(r#"{"in": [2, ["1", 2]]}"#));
 }
 
+#[test]
+fn in_over_a_large_user_list_matches_listed_strings_only() {
+    let ids: Vec<String> = (0..10_000).map(|i| format!("\"user-{i}\"")).collect();
+    let rule = |needle: &str| format!(r#"{{"in": [{needle}, [{}]]}}"#, ids.join(","));
+
+    assert!(matches(&rule(r#""user-0""#)));
+    assert!(matches(&rule(r#""user-9999""#)));
+    assert!(!matches(&rule(r#""user-10000""#)));
+    assert!(!matches(&rule("null")));
+    assert!(!matches(&rule(r#"["user-1"]"#)));
+    assert!(matches_with(
+        &rule(r#"{"var": "id"}"#),
+        &context_with(r#"{"id": "user-4242"}"#)
+    ));
+    assert!(!matches_with(
+        &rule(r#"{"var": "id"}"#),
+        &context_with(r#"{"id": 4242}"#)
+    ));
+}
+
 #[test]
 fn merge_flattens_arrays_and_wraps_scalars() {
     assert!(matches(r#"{"in": [3, {"merge": [[1, 2], [3, 4]]}]}"#));
@@ -340,6 +360,39 @@ fn var_reads_nested_attributes_and_defaults() {
     ));
 }
 
+#[test]
+fn var_paths_fail_closed_on_a_missing_intermediate_key() {
+    let context = context_with(r#"{"subscription": {"tier": "gold"}}"#);
+    assert!(matches_with(
+        r#"{"==": [{"var": "subscription.tier"}, "gold"]}"#,
+        &context
+    ));
+    assert!(matches_with(
+        r#"{"===": [{"var": "billing.tier"}, null]}"#,
+        &context
+    ));
+    assert!(matches_with(
+        r#"{"===": [{"var": ["subscription.tier.name", "none"]}, "none"]}"#,
+        &context
+    ));
+}
+
+#[test]
+fn var_prefers_a_flat_key_containing_dots() {
+    let context = context_with(
+        r#"{"app.version": "2.0.0", "app": {"version": "1.0.0"}, "device": {"os.name": "ios"}}"#,
+    );
+    assert!(matches_with(
+        r#"{"==": [{"var": "app.version"}, "2.0.0"]}"#,
+        &context
+    ));
+    // The literal key wins at any depth, not only at the top level.
+    assert!(matches_with(
+        r#"{"==": [{"var": "device.os.name"}, "ios"]}"#,
+        &context
+    ));
+}
+
 #[test]
 fn targeting_key_is_exposed_as_an_attribute() {
     let context = EvaluationContext {
@@ -3,7 +3,11 @@
 
 use std::collections::BTreeMap;
 
-use flaps_eval::{EvaluationContext, EvaluationError, FlagSet, MetadataValue, Reason};
+use flaps_eval::{
+    APP_VERSION_ATTRIBUTE, EvaluationContext, EvaluationError, EvaluationOptions, Fallback,
+    Fallbacks, FlagSet, KILL_REASON_METADATA, Literal, MIN_APP_VERSION_METADATA, MetadataValue,
+    ParseError, Reason, RolloutPosition, Rule,
+};
 
 /// Parses a flag set document, panicking on invalid fixtures.
 fn flag_set(document: &str) -> FlagSet {
@@ -129,6 +133,96 @@ fn disabled_flags_evaluate_no_rule() {
     assert_eq!(resolution.reason, Reason::Disabled);
 }
 
+#[test]
+fn killed_flags_report_killed_with_their_reason() {
+    let set = flag_set(
+        r#"{
+            "flags": {
+                "killed": {
+                    "state": "DISABLED",
+                    "variants": { "on": true, "off": false },
+                    "defaultVariant": "on",
+                    "metadata": { "killReason": "incident 42" }
+                },
+                "disabled": {
+                    "state": "DISABLED",
+                    "variants": { "on": true, "off": false },
+                    "defaultVariant": "on"
+                },
+                "enabled": {
+                    "state": "ENABLED",
+                    "variants": { "on": true, "off": false },
+                    "defaultVariant": "on",
+                    "metadata": { "killReason": "only read on disabled flags" }
+                }
+            },
+            "metadata": { "killReason": "not a flag entry" }
+        }"#,
+    );
+    let evaluate = |key| {
+        set.evaluate(key, &EvaluationContext::default())
+            .expect("evaluation succeeds")
+    };
+
+    let killed = evaluate("killed");
+    assert_eq!(killed.reason, Reason::Killed);
+    assert!(killed.reason.is_disabled());
+    assert_eq!(killed.value, None);
+    assert_eq!(
+        killed.metadata.get(KILL_REASON_METADATA),
+        Some(&MetadataValue::String("incident 42".to_owned()))
+    );
+
+    let disabled = evaluate("disabled");
+    assert_eq!(disabled.reason, Reason::Disabled);
+    assert!(disabled.reason.is_disabled());
+
+    assert_eq!(evaluate("enabled").reason, Reason::Static);
+    assert!(!Reason::Static.is_disabled());
+}
+
+#[test]
+fn version_gated_flags_serve_only_recent_enough_apps() {
+    let set = flag_set(
+        r#"{
+            "flags": {
+                "new-checkout": {
+                    "state": "ENABLED",
+                    "variants": { "on": true, "off": false },
+                    "defaultVariant": "off",
+                    "targeting": { "if": [{"==": [{"var": "plan"}, "pro"]}, "on", null] },
+                    "metadata": { "minAppVersion": "4.2.0" }
+                }
+            }
+        }"#,
+    );
+    let evaluate = |app_version: Option<&str>| {
+        let context = app_version.map_or_else(EvaluationContext::default, |version| {
+            context_with(APP_VERSION_ATTRIBUTE, version)
+        });
+        set.evaluate("new-checkout", &context)
+            .expect("evaluation succeeds")
+    };
+
+    let outdated = evaluate(Some("4.1.9"));
+    assert_eq!(outdated.reason, Reason::VersionGated);
+    assert!(outdated.reason.is_disabled());
+    assert_eq!(outdated.value, None);
+    assert_eq!(
+        outdated.metadata.get(MIN_APP_VERSION_METADATA),
+        Some(&MetadataValue::String("4.2.0".to_owned()))
+    );
+    assert_eq!(evaluate(Some("4.2.0-beta.1")).reason, Reason::VersionGated);
+
+    // At or above the minimum, the targeting runs as usual.
+    assert_eq!(evaluate(Some("4.2.0")).reason, Reason::Default);
+    assert_eq!(evaluate(Some("v5.0.1")).value, Some(false.into()));
+
+    // Without a usable app version the gate fails closed.
+    assert_eq!(evaluate(None).reason, Reason::VersionGated);
+    assert_eq!(evaluate(Some("latest")).reason, Reason::VersionGated);
+}
+
 #[test]
 fn unknown_flags_are_not_found() {
     let error = color_set()
@@ -352,3 +446,458 @@ fn adversarial_contexts_never_panic() {
     assert_eq!(resolution.reason, Reason::TargetingMatch);
     assert_eq!(resolution.variant.as_deref(), Some("false"));
 }
+
+#[test]
+fn over_deep_targeting_is_refused_when_parsed() {
+    // Hand-crafted document bypassing the compiler: a `!` chain nested past
+    // the evaluator's depth ceiling (but within serde_json's own limit).
+    let depth = flaps_eval::MAX_RULE_DEPTH + 10;
+    let targeting = format!(
+        "{}{{\"var\": \"x\"}}{}",
+        "{\"!\": ".repeat(depth),
+        "}".repeat(depth)
+    );
+    let error = FlagSet::from_json(&format!(
+        r#"{{
+            "flags": {{
+                "deep": {{
+                    "state": "ENABLED",
+                    "variants": {{ "true": true, "false": false }},
+                    "defaultVariant": "false",
+                    "targeting": {targeting}
+                }}
+            }}
+        }}"#
+    ))
+    .expect_err("over-deep targeting is refused");
+
+    assert!(matches!(
+        error,
+        ParseError::RuleTooDeep { ref path, max_depth }
+            if path.starts_with("flags.deep.targeting") && max_depth == flaps_eval::MAX_RULE_DEPTH
+    ));
+}
+
+#[test]
+fn targeting_at_the_depth_limit_still_evaluates() {
+    // The `var` leaf is one level, each `!` adds one.
+    let depth = flaps_eval::MAX_RULE_DEPTH - 1;
+    let targeting = format!(
+        "{}{{\"var\": \"x\"}}{}",
+        "{\"!\": ".repeat(depth),
+        "}".repeat(depth)
+    );
+    let set = flag_set(&format!(
+        r#"{{
+            "flags": {{
+                "deep": {{
+                    "state": "ENABLED",
+                    "variants": {{ "true": true, "false": false }},
+                    "defaultVariant": "false",
+                    "targeting": {targeting}
+                }}
+            }}
+        }}"#
+    ));
+
+    let resolution = set
+        .evaluate("deep", &EvaluationContext::default())
+        .expect("rule within the limit evaluates");
+
+    assert_eq!(resolution.reason, Reason::TargetingMatch);
+}
+
+/// A flag whose targeting scans every cell of a `rows` matrix for `"x"`.
+fn matrix_scan_set() -> FlagSet {
+    flag_set(
+        r#"{
+            "flags": {
+                "scan": {
+                    "state": "ENABLED",
+                    "variants": { "true": true, "false": false },
+                    "defaultVariant": "false",
+                    "targeting": {
+                        "some": [
+                            {"var": "rows"},
+                            {"some": [{"var": ""}, {"==": [{"var": ""}, "x"]}]}
+                        ]
+                    }
+                }
+            }
+        }"#,
+    )
+}
+
+/// An evaluation context whose `rows` attribute is a `side` by `side`
+/// matrix of `"."` cells.
+fn matrix_context(side: usize) -> EvaluationContext {
+    let row = serde_json::Value::from(vec!["."; side]);
+    EvaluationContext {
+        attributes: BTreeMap::from([("rows".to_owned(), vec![row; side].into())]),
+        ..EvaluationContext::default()
+    }
+}
+
+#[test]
+fn nested_iterations_over_a_large_context_exhaust_the_budget() {
+    // 400 x 400 cells, several reductions each: well past the default budget,
+    // though the rule itself is only a few levels deep.
+    let error = matrix_scan_set()
+        .evaluate("scan", &matrix_context(400))
+        .expect_err("the scan is cut short");
+
+    assert!(matches!(
+        error,
+        EvaluationError::BudgetExceeded { ref flag_key, max_steps }
+            if flag_key == "scan" && max_steps == flaps_eval::MAX_EVALUATION_STEPS
+    ));
+}
+
+#[test]
+fn the_evaluation_budget_can_be_set_per_call() {
+    let set = matrix_scan_set();
+    let context = matrix_context(10);
+
+    let resolution = set.evaluate("scan", &context).expect("a small scan fits");
+    assert_eq!(resolution.variant.as_deref(), Some("false"));
+
+    let error = set
+        .evaluate_with_budget("scan", &context, 50)
+        .expect_err("a tighter budget cuts it short");
+    assert!(matches!(
+        error,
+        EvaluationError::BudgetExceeded { max_steps: 50, .. }
+    ));
+}
+
+/// A boolean flag serving `true` to contexts whose `age` exceeds 18 and whose
+/// `email` ends with `@example.com`.
+fn adult_set() -> FlagSet {
+    flag_set(
+        r#"{
+            "flags": {
+                "adult": {
+                    "state": "ENABLED",
+                    "variants": { "true": true, "false": false },
+                    "defaultVariant": "false",
+                    "targeting": {
+                        "and": [
+                            {">": [{"var": "age"}, 18]},
+                            {"ends_with": [{"var": "email"}, "@example.com"]}
+                        ]
+                    }
+                }
+            }
+        }"#,
+    )
+}
+
+#[test]
+fn a_numeric_operator_on_a_non_numeric_string_is_a_non_match_by_default() {
+    let resolution = adult_set()
+        .evaluate("adult", &context_with("age", "twenty"))
+        .expect("lenient evaluation never fails on a type mismatch");
+
+    assert_eq!(resolution.variant.as_deref(), Some("false"));
+    assert_eq!(resolution.reason, Reason::TargetingMatch);
+}
+
+#[test]
+fn a_numeric_operator_on_a_non_numeric_string_fails_strict_evaluation() {
+    let error = adult_set()
+        .evaluate_strict("adult", &context_with("age", "twenty"))
+        .expect_err("strict evaluation refuses the mismatch");
+
+    assert!(matches!(
+        error,
+        EvaluationError::TypeMismatch { ref flag_key, operator: ">", ref operand }
+            if flag_key == "adult" && operand == "twenty"
+    ));
+    assert!(error.to_string().contains("`>`"), "{error}");
+}
+
+#[test]
+fn strictness_is_an_evaluation_option() {
+    let set = adult_set();
+    let context = context_with("age", "twenty");
+
+    let lenient = set
+        .evaluate_with_options("adult", &context, EvaluationOptions::default())
+        .expect("the default options are lenient");
+    assert_eq!(lenient.variant.as_deref(), Some("false"));
+
+    let error = set
+        .evaluate_with_options("adult", &context, EvaluationOptions { strict: true })
+        .expect_err("strict options refuse the mismatch");
+    assert!(matches!(error, EvaluationError::TypeMismatch { .. }));
+}
+
+#[test]
+fn a_string_operator_on_a_number_fails_strict_evaluation() {
+    let context = EvaluationContext {
+        attributes: BTreeMap::from([
+            ("age".to_owned(), 30.into()),
+            ("email".to_owned(), 42.into()),
+        ]),
+        ..EvaluationContext::default()
+    };
+    let set = adult_set();
+
+    let resolution = set.evaluate("adult", &context).expect("lenient");
+    assert_eq!(resolution.variant.as_deref(), Some("false"));
+
+    let error = set.evaluate_strict("adult", &context).expect_err("strict");
+    assert!(matches!(
+        error,
+        EvaluationError::TypeMismatch { operator: "ends_with", ref operand, .. }
+            if *operand == 42
+    ));
+}
+
+#[test]
+fn strict_evaluation_accepts_coercible_and_absent_attributes() {
+    let set = adult_set();
+    let numeric_string = EvaluationContext {
+        attributes: BTreeMap::from([
+            ("age".to_owned(), "30".into()),
+            ("email".to_owned(), "ada@example.com".into()),
+        ]),
+        ..EvaluationContext::default()
+    };
+
+    let resolution = set
+        .evaluate_strict("adult", &numeric_string)
+        .expect("a numeric string coerces");
+    assert_eq!(resolution.variant.as_deref(), Some("true"));
+
+    let resolution = set
+        .evaluate_strict("adult", &EvaluationContext::default())
+        .expect("an absent attribute is not a mismatch");
+    assert_eq!(resolution.variant.as_deref(), Some("false"));
+}
+
+/// A number flag with a default variant, a rule serving `high` to `vip`
+/// users, and `null` (exit to the default) for everyone else.
+fn limit_set(state: &str, default_variant: bool) -> FlagSet {
+    let default_variant = if default_variant {
+        r#""defaultVariant": "low","#
+    } else {
+        ""
+    };
+    flag_set(&format!(
+        r#"{{
+            "flags": {{
+                "limit": {{
+                    "state": "{state}",
+                    "variants": {{ "low": 10, "high": 100 }},
+                    {default_variant}
+                    "targeting": {{ "if": [{{"==": [{{"var": "tier"}}, "vip"]}}, "high", null] }}
+                }},
+                "fixed": {{
+                    "state": "ENABLED",
+                    "variants": {{ "low": 10 }},
+                    "defaultVariant": "low"
+                }}
+            }}
+        }}"#
+    ))
+}
+
+#[test]
+fn value_or_serves_the_resolved_value_for_static_and_targeting_match() {
+    let set = limit_set("ENABLED", true);
+
+    let fixed = set
+        .evaluate("fixed", &EvaluationContext::default())
+        .expect("evaluation succeeds");
+    assert_eq!(fixed.reason, Reason::Static);
+    assert!(!fixed.uses_caller_default());
+    assert_eq!(fixed.value_or(0_i64), 10);
+
+    let matched = set
+        .evaluate("limit", &context_with("tier", "vip"))
+        .expect("evaluation succeeds");
+    assert_eq!(matched.reason, Reason::TargetingMatch);
+    assert_eq!(matched.value_or(0_i64), 100);
+}
+
+#[test]
+fn value_or_serves_the_flag_default_variant_on_default_reason() {
+    let resolution = limit_set("ENABLED", true)
+        .evaluate("limit", &context_with("tier", "free"))
+        .expect("evaluation succeeds");
+    assert_eq!(resolution.reason, Reason::Default);
+    assert!(!resolution.uses_caller_default());
+    assert_eq!(resolution.value_or(0_i64), 10);
+}
+
+#[test]
+fn value_or_serves_the_caller_default_without_a_default_variant() {
+    let resolution = limit_set("ENABLED", false)
+        .evaluate("limit", &context_with("tier", "free"))
+        .expect("evaluation succeeds");
+    assert_eq!(resolution.reason, Reason::Default);
+    assert!(resolution.uses_caller_default());
+    assert_eq!(resolution.value_or(7_i64), 7);
+}
+
+#[test]
+fn value_or_serves_the_caller_default_when_disabled() {
+    // Even a context the rule would match must not leak a variant.
+    let resolution = limit_set("DISABLED", true)
+        .evaluate("limit", &context_with("tier", "vip"))
+        .expect("evaluation succeeds");
+    assert_eq!(resolution.reason, Reason::Disabled);
+    assert!(resolution.uses_caller_default());
+    assert_eq!(resolution.value_or(7_i64), 7);
+}
+
+#[test]
+fn value_or_serves_the_caller_default_on_a_type_mismatch() {
+    let resolution = limit_set("ENABLED", true)
+        .evaluate("limit", &context_with("tier", "vip"))
+        .expect("evaluation succeeds");
+    assert!(!resolution.uses_caller_default());
+    assert!(resolution.value_or(true));
+    assert_eq!(resolution.value_or("fallback".to_owned()), "fallback");
+}
+
+/// A gate (`new-checkout`, disabled) and a configuration flag
+/// (`page-size`, resolving to no variant), both registered.
+fn gate_and_config() -> (FlagSet, Fallbacks) {
+    let set = flag_set(
+        r#"{
+            "flags": {
+                "new-checkout": {
+                    "state": "DISABLED",
+                    "variants": { "on": true, "off": false },
+                    "defaultVariant": "on"
+                },
+                "page-size": {
+                    "state": "ENABLED",
+                    "variants": { "small": 10, "large": 50 },
+                    "targeting": { "if": [{"==": [{"var": "tier"}, "vip"]}, "large", null] }
+                }
+            }
+        }"#,
+    );
+    let fallbacks = Fallbacks::new()
+        .with_flag("new-checkout", Fallback::Closed)
+        .with_flag("missing-gate", Fallback::Closed);
+    (set, fallbacks)
+}
+
+#[test]
+fn gate_flags_fail_closed_whatever_the_caller_default() {
+    let (set, fallbacks) = gate_and_config();
+    let context = EvaluationContext::default();
+
+    assert!(!set.value_or("new-checkout", &context, true, &fallbacks));
+    assert!(!set.value_or("missing-gate", &context, true, &fallbacks));
+    // A gate registered as closed still serves its resolved value.
+    let enabled = Fallbacks::new().with_flag("page-size", Fallback::Closed);
+    assert_eq!(
+        set.value_or("page-size", &context_with("tier", "vip"), 0_i64, &enabled),
+        50
+    );
+}
+
+#[test]
+fn config_flags_serve_the_caller_default_on_error_and_not_found() {
+    let (set, fallbacks) = gate_and_config();
+    let context = EvaluationContext::default();
+
+    assert_eq!(set.value_or("page-size", &context, 25_i64, &fallbacks), 25);
+    assert_eq!(
+        set.value_or("unknown-limit", &context, 25_i64, &fallbacks),
+        25
+    );
+    // A type mismatch is an error too.
+    assert_eq!(
+        set.value_or(
+            "page-size",
+            &context_with("tier", "vip"),
+            "auto".to_owned(),
+            &fallbacks
+        ),
+        "auto"
+    );
+    // Closed has no meaning for a number: the caller default applies.
+    let closed = Fallbacks::new().with_default(Fallback::Closed);
+    assert_eq!(set.value_or("unknown-limit", &context, 25_i64, &closed), 25);
+}
+
+#[test]
+fn unresolved_evaluator_references_name_the_flag_and_the_evaluator() {
+    let mut set = color_set();
+    let flag = set.flags.get_mut("background").expect("flag exists");
+    flag.targeting = Some(Rule::If(vec![
+        Rule::Ref("beta-testers".to_owned()),
+        Rule::Literal(Literal::String("green".to_owned())),
+        Rule::Literal(Literal::Null),
+    ]));
+
+    let error = set
+        .evaluate("background", &context_with("plan", "pro"))
+        .expect_err("an unloaded evaluator fails closed");
+
+    assert_eq!(
+        error,
+        EvaluationError::UnresolvedEvaluator {
+            flag_key: "background".to_owned(),
+            reference: "beta-testers".to_owned(),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "targeting of flag `background` references evaluator `beta-testers`, which is not loaded"
+    );
+}
+
+#[test]
+fn explain_reports_the_matched_rule_and_rollout_bucket() {
+    let set = flag_set(
+        r#"{
+            "flags": {
+                "banner": {
+                    "state": "ENABLED",
+                    "variants": { "on": true, "off": false },
+                    "defaultVariant": "off",
+                    "targeting": {
+                        "if": [
+                            {"==": [{"var": "plan"}, "pro"]}, "on",
+                            {"==": [{"var": "plan"}, "beta"]}, {"fractional": [["on", 25], ["off", 75]]},
+                            null
+                        ]
+                    }
+                }
+            }
+        }"#,
+    );
+    let context = |plan: &str| EvaluationContext {
+        targeting_key: Some("user-7".to_owned()),
+        ..context_with("plan", plan)
+    };
+
+    let pro = set.explain("banner", &context("pro")).expect("explains");
+    assert_eq!(pro.matched_rule, Some(0));
+    assert_eq!(pro.rollout, None);
+    assert_eq!(
+        pro.resolution,
+        set.evaluate("banner", &context("pro")).unwrap()
+    );
+
+    let beta = set.explain("banner", &context("beta")).expect("explains");
+    assert_eq!(beta.matched_rule, Some(1));
+    assert_eq!(
+        beta.rollout,
+        Some(RolloutPosition {
+            bucket: flaps_eval::bucket_for("banneruser-7", 100),
+            total_weight: 100,
+        })
+    );
+
+    let free = set.explain("banner", &context("free")).expect("explains");
+    assert_eq!(free.matched_rule, None);
+    assert_eq!(free.resolution.reason, Reason::Default);
+}
@@ -0,0 +1,122 @@
+//! Deterministic exposure sampling: the sampled fraction matches the rate,
+//! a user is consistently in or out of the sample for a flag, and the sample
+//! is independent of fractional rollout buckets.
+//!
+//! Keys are a fixed sequence `"user-{i}"`, so the tolerances below are
+//! checked against deterministic data and cannot flake.
+
+use flaps_eval::{EvaluationContext, ExposureSampler, FlagSet};
+use serde_json::json;
+
+fn user(i: u32) -> EvaluationContext {
+    EvaluationContext {
+        targeting_key: Some(format!("user-{i}")),
+        ..EvaluationContext::default()
+    }
+}
+
+fn sampled_count(sampler: &ExposureSampler, flag_key: &str, users: u32) -> u32 {
+    (0..users)
+        .map(|i| u32::from(sampler.sampled(flag_key, &user(i))))
+        .sum()
+}
+
+#[test]
+fn sampled_fraction_matches_the_rate() {
+    for (rate, tolerance) in [(0.01, 0.002), (0.1, 0.005), (0.5, 0.01)] {
+        let sampler = ExposureSampler::new(rate).unwrap();
+        let observed = f64::from(sampled_count(&sampler, "checkout", 100_000)) / 100_000.0;
+        assert!(
+            (observed - rate).abs() <= tolerance,
+            "rate {rate}: observed {observed}"
+        );
+    }
+}
+
+#[test]
+fn sampling_is_stable_per_user_and_flag() {
+    let sampler = ExposureSampler::new(0.3).unwrap();
+    for i in 0..1_000 {
+        let first = sampler.sampled("checkout", &user(i));
+        let mut with_attributes = user(i);
+        with_attributes
+            .attributes
+            .insert("plan".to_owned(), json!("pro"));
+        with_attributes.timestamp = 1_700_000_000;
+        assert_eq!(sampler.sampled("checkout", &user(i)), first);
+        assert_eq!(sampler.sampled("checkout", &with_attributes), first);
+    }
+    // Another flag draws an independent sample of the same users.
+    let differing = (0..1_000)
+        .filter(|&i| sampler.sampled("checkout", &user(i)) != sampler.sampled("search", &user(i)))
+        .count();
+    assert!(differing > 100, "{differing}");
+}
+
+#[test]
+fn higher_rates_sample_a_superset() {
+    let low = ExposureSampler::new(0.05).unwrap();
+    let high = ExposureSampler::new(0.2).unwrap();
+    for i in 0..10_000 {
+        if low.sampled("checkout", &user(i)) {
+            assert!(high.sampled("checkout", &user(i)), "user-{i}");
+        }
+    }
+}
+
+#[test]
+fn sample_is_independent_of_rollout_buckets() {
+    let document = json!({
+        "flags": {
+            "rollout": {
+                "state": "ENABLED",
+                "variants": { "on": true, "off": false },
+                "defaultVariant": "off",
+                "targeting": { "fractional": [["on", 10], ["off", 90]] }
+            }
+        }
+    })
+    .to_string();
+    let flag_set = FlagSet::from_json(&document).unwrap();
+    let sampler = ExposureSampler::new(0.1).unwrap();
+    let (mut kept, mut kept_on) = (0_u32, 0_u32);
+    for i in 0..50_000 {
+        let context = user(i);
+        if sampler.sampled("rollout", &context) {
+            kept += 1;
+            let resolution = flag_set.evaluate("rollout", &context).unwrap();
+            kept_on += u32::from(resolution.variant.as_deref() == Some("on"));
+        }
+    }
+    let on_share = f64::from(kept_on) / f64::from(kept);
+    assert!((on_share - 0.1).abs() <= 0.02, "{on_share}");
+}
+
+#[test]
+fn anonymous_contexts_are_spread_by_attributes() {
+    let sampler = ExposureSampler::new(0.5).unwrap();
+    let kept = (0..10_000)
+        .filter(|i| {
+            let mut context = EvaluationContext::default();
+            context.attributes.insert("session".to_owned(), json!(i));
+            sampler.sampled("checkout", &context)
+        })
+        .count();
+    assert!((4_700..=5_300).contains(&kept), "{kept}");
+}
+
+#[test]
+fn boundary_rates_keep_none_or_all() {
+    let none = ExposureSampler::new(0.0).unwrap();
+    let all = ExposureSampler::new(1.0).unwrap();
+    assert_eq!(sampled_count(&none, "checkout", 10_000), 0);
+    assert_eq!(sampled_count(&all, "checkout", 10_000), 10_000);
+    assert!((all.rate() - 1.0).abs() < f64::EPSILON);
+}
+
+#[test]
+fn out_of_range_rates_are_refused() {
+    for rate in [-0.1, 1.5, f64::NAN, f64::INFINITY] {
+        assert_eq!(ExposureSampler::new(rate), None, "{rate}");
+    }
+}
@@ -23,10 +23,12 @@
 //!    **Condition**: monotonicity is only guaranteed when `total_weight` stays
 //!    constant and the order of variants in the bucket list is stable. This
 //!    test always fixes `total_weight = 100` and keeps `"on"` first.
+//! 4. **Public bucket**: [`bucket_for`] over `flagKey + targetingKey` predicts
+//!    the variant evaluation serves.
 
 use std::collections::BTreeMap;
 
-use flaps_eval::{EvaluationContext, FlagSet};
+use flaps_eval::{EvaluationContext, FlagSet, bucket_for};
 use proptest::prelude::*;
 
 // ---------------------------------------------------------------------------
@@ -175,3 +177,23 @@ proptest! {
         }
     }
 }
+
+// ---------------------------------------------------------------------------
+// Property 4: bucket_for predicts evaluation
+// ---------------------------------------------------------------------------
+
+proptest! {
+    /// A key resolves to `"on"` exactly when its public bucket is below the
+    /// weight of `"on"`.
+    #[test]
+    fn bucket_for_predicts_the_served_variant(
+        key in "[a-zA-Z0-9@._-]{1,64}",
+        w in 0u32..=100u32,
+    ) {
+        let flag_set = FlagSet::from_json(&rollout_document(w, 100 - w))
+            .expect("valid rollout document");
+        let bucket = bucket_for(&format!("rollout{key}"), 100);
+        let expected = if bucket < u64::from(w) { "on" } else { "off" };
+        prop_assert_eq!(evaluate_rollout(&flag_set, &key), expected);
+    }
+}
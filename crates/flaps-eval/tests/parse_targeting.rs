@@ -6,7 +6,7 @@
 //! even when they read alike.
 #![allow(clippy::unnecessary_box_returns, clippy::similar_names)]
 
-use flaps_eval::{FlagSet, Literal, ParseError, Rule, SemVerOp};
+use flaps_eval::{FlagSet, Literal, ParseError, Rule, SemVerOp, StringSet};
 
 /// Parses a document with a single flag whose targeting is `targeting_json`
 /// and returns the parsed targeting rule.
@@ -183,6 +183,31 @@ fn parses_in_with_array_of_expressions() {
     assert_eq!(*haystack, Rule::Array(vec![*literal("FR"), *literal("BE")]));
 }
 
+#[test]
+fn indexes_long_literal_string_arrays_of_in() {
+    let ids: Vec<String> = (0..StringSet::MIN_LEN)
+        .map(|i| format!("\"user-{i}\""))
+        .collect();
+    let rule = targeting(&format!(
+        r#"{{"in": [{{"var": "targetingKey"}}, [{}]]}}"#,
+        ids.join(",")
+    ));
+
+    let Rule::InSet { needle, set } = rule else {
+        panic!("expected InSet");
+    };
+    assert_eq!(*needle, *var("targetingKey"));
+    assert_eq!(set.items().len(), StringSet::MIN_LEN);
+    assert!(set.contains("user-0"));
+
+    // An array holding anything but string literals stays a plain `in`.
+    let mixed = targeting(&format!(
+        r#"{{"in": [{{"var": "targetingKey"}}, [{}, 1]]}}"#,
+        ids.join(",")
+    ));
+    assert!(matches!(mixed, Rule::In(_, _)));
+}
+
 #[test]
 fn parses_missing_operators() {
     let missing = targeting(r#"{"missing": ["email", "country"]}"#);
@@ -242,6 +267,28 @@ fn rejects_unknown_sem_ver_operator() {
     );
 }
 
+#[test]
+fn parses_regex_with_a_literal_pattern_only() {
+    let Rule::Regex { value, pattern } = targeting(r#"{"regex": [{"var": "email"}, "^a+$"]}"#)
+    else {
+        panic!("expected Regex");
+    };
+    assert!(matches!(*value, Rule::Var { ref path, .. } if path == "email"));
+    assert_eq!(pattern.as_str(), "^a+$");
+
+    let error = parse_targeting(r#"{"regex": [{"var": "email"}, "(unclosed"]}"#)
+        .expect_err("invalid pattern");
+    assert!(
+        matches!(error, ParseError::InvalidArguments { ref operator, .. } if operator == "regex")
+    );
+
+    let error = parse_targeting(r#"{"regex": [{"var": "email"}, {"var": "pattern"}]}"#)
+        .expect_err("computed pattern");
+    assert!(
+        matches!(error, ParseError::InvalidArguments { ref operator, .. } if operator == "regex")
+    );
+}
+
 #[test]
 fn parses_fractional_with_bucketing_expression() {
     let rule = targeting(
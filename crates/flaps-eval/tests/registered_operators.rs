@@ -0,0 +1,107 @@
+//! Operators registered by the embedder through `CustomOperators`: dispatch,
+//! fail-closed parsing of unregistered names, precedence of built-ins and
+//! round-trip serialization.
+
+use flaps_eval::{CustomOperators, EvaluationContext, FlagSet, ParseError, Reason};
+use serde_json::{Value, json};
+
+/// A document with one boolean flag served `true` when `targeting` holds.
+fn document(targeting: &Value) -> String {
+    json!({
+        "flags": {
+            "probe": {
+                "state": "ENABLED",
+                "variants": { "true": true, "false": false },
+                "defaultVariant": "false",
+                "targeting": { "if": [targeting, "true", "false"] }
+            }
+        }
+    })
+    .to_string()
+}
+
+/// `within_hours`: the hour in the left operand lies in the `[from, to)`
+/// window given as the right operand.
+fn operators() -> CustomOperators {
+    let mut operators = CustomOperators::new();
+    operators.register_operator("within_hours", |hour: &Value, window: &Value| {
+        let window: Vec<f64> = window
+            .as_array()
+            .map(|bounds| bounds.iter().filter_map(Value::as_f64).collect())
+            .unwrap_or_default();
+        match (hour.as_f64(), window.as_slice()) {
+            (Some(hour), [from, to]) => (*from..*to).contains(&hour),
+            _ => false,
+        }
+    });
+    operators
+}
+
+fn context(hour: Value) -> EvaluationContext {
+    EvaluationContext {
+        attributes: [("hour".to_owned(), hour)].into(),
+        ..EvaluationContext::default()
+    }
+}
+
+fn probe(flag_set: &FlagSet, hour: Value) -> bool {
+    let resolution = flag_set.evaluate("probe", &context(hour)).unwrap();
+    assert_eq!(resolution.reason, Reason::TargetingMatch);
+    resolution.value == Some(json!(true))
+}
+
+#[test]
+fn registered_operator_is_dispatched() {
+    let targeting = json!({"within_hours": [{"var": "hour"}, [9, 17]]});
+    let flag_set = FlagSet::from_json_with_operators(&document(&targeting), &operators()).unwrap();
+    assert!(probe(&flag_set, json!(9)));
+    assert!(probe(&flag_set, json!(16)));
+    assert!(!probe(&flag_set, json!(17)));
+    assert!(!probe(&flag_set, json!("noon")));
+    assert!(!probe(&flag_set, Value::Null));
+}
+
+#[test]
+fn registered_operator_composes_with_built_ins() {
+    let targeting = json!({"and": [
+        {"within_hours": [{"var": "hour"}, [9, 17]]},
+        {"!": {"within_hours": [{"var": "hour"}, [12, 13]]}}
+    ]});
+    let flag_set = FlagSet::from_json_with_operators(&document(&targeting), &operators()).unwrap();
+    assert!(probe(&flag_set, json!(10)));
+    assert!(!probe(&flag_set, json!(12)));
+}
+
+#[test]
+fn unregistered_operator_is_refused() {
+    let targeting = json!({"within_days": [{"var": "hour"}, [1, 5]]});
+    for result in [
+        FlagSet::from_json_with_operators(&document(&targeting), &operators()),
+        FlagSet::from_json(&document(
+            &json!({"within_hours": [{"var": "hour"}, [9, 17]]}),
+        )),
+    ] {
+        assert!(
+            matches!(result, Err(ParseError::UnknownOperator { .. })),
+            "{result:?}"
+        );
+    }
+}
+
+#[test]
+fn built_in_operators_take_precedence() {
+    let mut operators = CustomOperators::new();
+    operators.register_operator("==", |_: &Value, _: &Value| true);
+    let targeting = json!({"==": [{"var": "hour"}, 9]});
+    let flag_set = FlagSet::from_json_with_operators(&document(&targeting), &operators).unwrap();
+    assert!(probe(&flag_set, json!(9)));
+    assert!(!probe(&flag_set, json!(10)));
+}
+
+#[test]
+fn custom_calls_survive_a_round_trip() {
+    let targeting = json!({"within_hours": [{"var": "hour"}, [9, 17]]});
+    let flag_set = FlagSet::from_json_with_operators(&document(&targeting), &operators()).unwrap();
+    let reparsed = FlagSet::from_json_with_operators(&flag_set.to_json(), &operators()).unwrap();
+    assert_eq!(reparsed, flag_set);
+}
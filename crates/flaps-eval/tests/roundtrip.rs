@@ -55,6 +55,7 @@ fn roundtrips_every_operator_family() {
                                 {">=": [{"+": [1, 2]}, {"-": [5, 2]}]},
                                 {"in": [{"var": "country"}, ["FR", "BE"]]},
                                 {"starts_with": [{"var": "ip"}, "10."]},
+                                {"regex": [{"var": "email"}, "^[a-z]+@example\\.com$"]},
                                 {"sem_ver": [{"var": "version"}, ">=", "1.2.3"]},
                                 {"missing_some": [1, ["email", "phone"]]},
                                 {"some": [{"merge": [[1], [2]]}, {">": [{"var": ""}, 0]}]},
@@ -90,6 +91,30 @@ fn roundtrips_every_operator_family() {
     );
 }
 
+#[test]
+fn roundtrips_an_indexed_user_list_in_written_order() {
+    let ids: Vec<String> = (0..100).rev().map(|i| format!("\"user-{i}\"")).collect();
+    let document = format!(
+        r#"{{
+            "flags": {{
+                "allow-list": {{
+                    "state": "ENABLED",
+                    "variants": {{ "on": true, "off": false }},
+                    "defaultVariant": "off",
+                    "targeting": {{"if": [{{"in": [{{"var": "targetingKey"}}, [{}]]}}, "on", "off"]}}
+                }}
+            }}
+        }}"#,
+        ids.join(", ")
+    );
+    assert_roundtrip(&document);
+
+    let serialized = FlagSet::from_json(&document)
+        .expect("valid document")
+        .to_json();
+    assert!(serialized.contains(&ids.join(",")));
+}
+
 #[test]
 fn serialization_inlines_evaluators_and_drops_references() {
     let document = r#"{
@@ -114,3 +139,61 @@ fn serialization_inlines_evaluators_and_drops_references() {
     assert!(serialized.contains("ends_with"));
     assert_eq!(parsed, FlagSet::from_json(&serialized).expect("reparses"));
 }
+
+/// Two flags with their keys in the given order, `beta` serving `beta_default`.
+fn two_flags(beta_first: bool, beta_default: &str) -> String {
+    let alpha = r#""alpha": {
+        "variants": { "on": true, "off": false },
+        "state": "ENABLED",
+        "defaultVariant": "on"
+    }"#;
+    let beta = format!(
+        r#""beta": {{
+            "defaultVariant": "{beta_default}",
+            "targeting": {{ "if": [{{ "in": ["@acme.com", {{ "var": "email" }}] }}, "on", null] }},
+            "state": "ENABLED",
+            "variants": {{ "off": false, "on": true }}
+        }}"#
+    );
+    let flags = if beta_first {
+        format!("{beta}, {alpha}")
+    } else {
+        format!("{alpha}, {beta}")
+    };
+    format!(r#"{{ "metadata": {{ "team": "growth" }}, "flags": {{ {flags} }} }}"#)
+}
+
+fn pretty(document: &str) -> String {
+    FlagSet::from_json(document)
+        .expect("valid document")
+        .to_pretty_json()
+}
+
+#[test]
+fn pretty_serialization_is_independent_of_source_order() {
+    let first = pretty(&two_flags(false, "off"));
+    assert_eq!(first, pretty(&two_flags(true, "off")));
+    assert_eq!(pretty(&first), first, "re-serializing is byte-identical");
+    assert!(first.lines().count() > 1, "one entry per line: {first}");
+}
+
+#[test]
+fn pretty_serialization_confines_a_flag_change_to_its_lines() {
+    let before = pretty(&two_flags(false, "off"));
+    let after = pretty(&two_flags(true, "on"));
+    let (before, after): (Vec<&str>, Vec<&str>) =
+        (before.lines().collect(), after.lines().collect());
+    assert_eq!(before.len(), after.len());
+    let changed: Vec<(&str, &str)> = before
+        .into_iter()
+        .zip(after)
+        .filter(|(old, new)| old != new)
+        .collect();
+    assert_eq!(
+        changed,
+        [(
+            r#"      "defaultVariant": "off","#,
+            r#"      "defaultVariant": "on","#
+        )]
+    );
+}
@@ -0,0 +1,92 @@
+//! Binary snapshot tests: fidelity against the JSON representation on a
+//! large flag set, and a parse time comparison with JSON.
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use flaps_eval::FlagSet;
+
+/// Builds a flagd document with `count` flags covering every variant type,
+/// targeting with nested logic, fractional rollouts and metadata.
+fn large_document(count: usize) -> String {
+    let mut flags = String::new();
+    for i in 0..count {
+        if i > 0 {
+            flags.push(',');
+        }
+        let (variants, on, off) = match i % 4 {
+            0 => (r#"{"on":true,"off":false}"#.to_owned(), "on", "off"),
+            1 => (format!(r#"{{"a":"alpha-{i}","b":"beta-{i}"}}"#), "a", "b"),
+            2 => (format!(r#"{{"low":{i},"high":{i}.5}}"#), "low", "high"),
+            _ => (
+                format!(r#"{{"x":{{"limit":{i},"tags":["t{i}",null,true]}},"y":{{}}}}"#),
+                "x",
+                "y",
+            ),
+        };
+        write!(
+            flags,
+            r#""flag-{i}":{{"state":"{state}","variants":{variants},"defaultVariant":"{off}",
+            "targeting":{{"if":[
+                {{"and":[{{"in":[{{"var":"country"}},["FR","DE","IT"]]}},
+                        {{"sem_ver":[{{"var":"app_version"}},">=","2.{i}.0"]}}]}},
+                "{on}",
+                {{"fractional":[{{"cat":[{{"var":"targetingKey"}},"flag-{i}"]}},["{on}",25],["{off}",75]]}}
+            ]}},
+            "metadata":{{"owner":"team-{team}","revision":{i}}}}}"#,
+            state = if i % 7 == 0 { "DISABLED" } else { "ENABLED" },
+            team = i % 5,
+        )
+        .unwrap();
+    }
+    format!(r#"{{"flags":{{{flags}}},"metadata":{{"flagSetId":"bench"}}}}"#)
+}
+
+/// Runs `f` `rounds` times and returns the fastest run.
+fn fastest(rounds: usize, mut f: impl FnMut()) -> Duration {
+    (0..rounds)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+#[test]
+fn snapshot_decodes_to_the_same_flag_set_as_json() {
+    let document = large_document(500);
+    let from_json = FlagSet::from_json(&document).expect("valid document");
+    let snapshot = FlagSet::json_to_snapshot(&document).expect("valid document");
+    let from_snapshot = FlagSet::from_snapshot(&snapshot).expect("valid snapshot");
+
+    assert_eq!(from_snapshot, from_json);
+    assert_eq!(from_snapshot.to_json(), from_json.to_json());
+    assert_eq!(from_snapshot.to_snapshot(), snapshot);
+}
+
+#[test]
+fn snapshot_parse_time_compared_with_json() {
+    // Compare against the canonical document, which is what the server serves.
+    let document = FlagSet::from_json(&large_document(2_000))
+        .expect("valid document")
+        .to_json();
+    let snapshot = FlagSet::json_to_snapshot(&document).expect("valid document");
+
+    let json_time = fastest(5, || {
+        FlagSet::from_json(&document).expect("valid document");
+    });
+    let snapshot_time = fastest(5, || {
+        FlagSet::from_snapshot(&snapshot).expect("valid snapshot");
+    });
+
+    // Timings are reported, not asserted: wall-clock ratios are too noisy on
+    // shared CI runners to gate a build on.
+    eprintln!(
+        "2000 flags: json {} bytes in {json_time:?}, snapshot {} bytes in {snapshot_time:?}",
+        document.len(),
+        snapshot.len(),
+    );
+    assert!(snapshot.len() < document.len());
+}
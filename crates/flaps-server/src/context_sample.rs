@@ -0,0 +1,260 @@
+//! Rolling sample of recent OFREP evaluation contexts, per project.
+//!
+//! Segment previews (`POST /projects/{project}/segment-preview`) estimate how
+//! many real callers a proposed segment would match. They need real contexts,
+//! so when a [`ContextSample`] is configured
+//! ([`AppState::with_context_sample`]) the OFREP handlers record each
+//! validated, geolocated context into a bounded ring buffer of its project:
+//! once full, the oldest context makes room for the newest.
+//!
+//! Recording sits on the OFREP hot path, so it takes no lock shared by all
+//! evaluations. The project map is behind a read-write lock, written only
+//! when a project is first sampled or forgotten. Each buffer slot has a lock
+//! of its own, and an atomic cursor hands each recording its slot.
+//!
+//! Contexts are redacted before they are stored. The targeting key is always
+//! dropped, since it identifies a user; so are the attributes listed in
+//! [`ContextSampleConfig::private_attributes`]. The sample lives in memory
+//! only and is never returned by the API: previews report match counts, not
+//! contexts. A segment reading a redacted attribute, or the targeting key,
+//! therefore sees it as absent in every sampled context.
+//!
+//! [`AppState::with_context_sample`]: crate::state::AppState::with_context_sample
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+
+use flaps_domain::ProjectKey;
+use flaps_eval::EvaluationContext;
+
+/// Configuration of a [`ContextSample`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextSampleConfig {
+    /// Number of recent contexts kept per project.
+    pub capacity: usize,
+    /// Top-level attributes removed from each context before it is stored.
+    pub private_attributes: BTreeSet<String>,
+}
+
+impl ContextSampleConfig {
+    /// Default [`Self::capacity`].
+    pub const DEFAULT_CAPACITY: usize = 1_000;
+}
+
+/// The most recent evaluation contexts of each project, redacted.
+#[derive(Debug)]
+pub struct ContextSample {
+    config: ContextSampleConfig,
+    projects: RwLock<HashMap<ProjectKey, Arc<Ring>>>,
+}
+
+impl ContextSample {
+    /// Creates an empty sample.
+    #[must_use]
+    pub fn new(config: ContextSampleConfig) -> Self {
+        Self {
+            config,
+            projects: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Records a redacted copy of `context` for `project`, evicting the
+    /// project's oldest context when its buffer is full.
+    pub fn record(&self, project: &ProjectKey, context: &EvaluationContext) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        let context = self.redact(context);
+        self.ring(project).push(context);
+    }
+
+    /// Returns the contexts currently sampled for `project`, oldest first.
+    #[must_use]
+    pub fn contexts(&self, project: &ProjectKey) -> Vec<EvaluationContext> {
+        let ring = self
+            .projects
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(project)
+            .cloned();
+        ring.map(|ring| ring.contexts()).unwrap_or_default()
+    }
+
+    /// Drops the contexts sampled for `project`.
+    pub fn forget(&self, project: &ProjectKey) {
+        self.projects
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(project);
+    }
+
+    /// Returns the buffer of `project`, creating it on first use.
+    fn ring(&self, project: &ProjectKey) -> Arc<Ring> {
+        let existing = self
+            .projects
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(project)
+            .cloned();
+        existing.unwrap_or_else(|| {
+            let mut projects = self
+                .projects
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            Arc::clone(
+                projects
+                    .entry(project.clone())
+                    .or_insert_with(|| Arc::new(Ring::new(self.config.capacity))),
+            )
+        })
+    }
+
+    /// Copies `context` without its targeting key and private attributes.
+    fn redact(&self, context: &EvaluationContext) -> EvaluationContext {
+        EvaluationContext {
+            targeting_key: None,
+            attributes: context
+                .attributes
+                .iter()
+                .filter(|(name, _)| !self.config.private_attributes.contains(*name))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            timestamp: context.timestamp,
+        }
+    }
+}
+
+/// A fixed-size ring of contexts whose slots are locked one at a time.
+#[derive(Debug)]
+struct Ring {
+    slots: Vec<Mutex<Option<EvaluationContext>>>,
+    /// Number of contexts pushed so far; the next one goes to slot
+    /// `next % slots.len()`.
+    next: AtomicUsize,
+}
+
+impl Ring {
+    fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity).map(|_| Mutex::new(None)).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Stores `context` over the oldest one.
+    fn push(&self, context: EvaluationContext) {
+        let slot = self.next.fetch_add(1, Ordering::Relaxed) % self.slots.len();
+        *lock(&self.slots[slot]) = Some(context);
+    }
+
+    /// Returns the stored contexts, oldest first.
+    fn contexts(&self) -> Vec<EvaluationContext> {
+        let oldest = self.next.load(Ordering::Relaxed) % self.slots.len();
+        self.slots[oldest..]
+            .iter()
+            .chain(&self.slots[..oldest])
+            .filter_map(|slot| lock(slot).clone())
+            .collect()
+    }
+}
+
+fn lock(slot: &Mutex<Option<EvaluationContext>>) -> MutexGuard<'_, Option<EvaluationContext>> {
+    slot.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn project() -> ProjectKey {
+        ProjectKey::new("shop").unwrap()
+    }
+
+    fn context(email: &str, country: &str) -> EvaluationContext {
+        EvaluationContext {
+            targeting_key: Some(email.to_owned()),
+            attributes: [
+                ("email".to_owned(), json!(email)),
+                ("country".to_owned(), json!(country)),
+            ]
+            .into(),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn recorded_contexts_are_redacted() {
+        let sample = ContextSample::new(ContextSampleConfig {
+            capacity: 10,
+            private_attributes: BTreeSet::from(["email".to_owned()]),
+        });
+        sample.record(&project(), &context("ada@example.com", "FR"));
+
+        let contexts = sample.contexts(&project());
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].targeting_key, None);
+        assert!(!contexts[0].attributes.contains_key("email"));
+        assert_eq!(contexts[0].attributes.get("country"), Some(&json!("FR")));
+    }
+
+    #[test]
+    fn a_full_buffer_evicts_the_oldest_context() {
+        let sample = ContextSample::new(ContextSampleConfig {
+            capacity: 2,
+            private_attributes: BTreeSet::new(),
+        });
+        for country in ["FR", "DE", "IT"] {
+            sample.record(&project(), &context("ada@example.com", country));
+        }
+
+        let countries: Vec<_> = sample
+            .contexts(&project())
+            .into_iter()
+            .map(|c| c.attributes["country"].clone())
+            .collect();
+        assert_eq!(countries, [json!("DE"), json!("IT")]);
+    }
+
+    #[test]
+    fn projects_are_sampled_separately() {
+        let sample = ContextSample::new(ContextSampleConfig {
+            capacity: 10,
+            private_attributes: BTreeSet::new(),
+        });
+        sample.record(&project(), &context("ada@example.com", "FR"));
+
+        assert!(
+            sample
+                .contexts(&ProjectKey::new("blog").unwrap())
+                .is_empty()
+        );
+        sample.forget(&project());
+        assert!(sample.contexts(&project()).is_empty());
+    }
+
+    #[test]
+    fn concurrent_recordings_fill_the_buffer() {
+        let sample = ContextSample::new(ContextSampleConfig {
+            capacity: 32,
+            private_attributes: BTreeSet::new(),
+        });
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let sample = &sample;
+                scope.spawn(move || {
+                    for i in 0..16 {
+                        sample.record(
+                            &project(),
+                            &context("ada@example.com", &format!("{thread}-{i}")),
+                        );
+                    }
+                });
+            }
+        });
+
+        assert_eq!(sample.contexts(&project()).len(), 32);
+    }
+}
@@ -41,6 +41,7 @@ impl From<StoreError> for ApiError {
         match e {
             StoreError::Conflict(msg) => Self::Conflict(msg),
             StoreError::NotFound | StoreError::ForeignKeyViolation => Self::NotFound,
+            StoreError::InvalidCursor => Self::InvalidBody(e.to_string()),
             other => Self::Internal(other.to_string()),
         }
     }
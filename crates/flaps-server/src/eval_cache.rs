@@ -0,0 +1,322 @@
+//! Edge cache of OFREP evaluation results.
+//!
+//! On read-heavy public sites many requests carry the same context (every
+//! anonymous visitor from one country, say), so they evaluate the same flags
+//! to the same results. An [`EvaluationCache`] remembers those results for a
+//! short TTL, keyed by [`EvaluationCacheKey`]: project, environment, flag,
+//! a stable hash of the context and the ruleset version. Including the
+//! version invalidates the cache implicitly: once a recompiled ruleset is
+//! installed, requests compute new keys and the old entries are never read
+//! again.
+//!
+//! The cache is opt-in per environment ([`EvaluationCacheConfig::environments`]):
+//! caching only pays where contexts repeat, and costs memory everywhere else.
+//! It is bounded the way [`GeoLookup`] is: when full it is emptied rather than
+//! evicted entry by entry, which also sweeps expired entries.
+//!
+//! The context hash covers `$flagd.timestamp`, so with whole-second
+//! timestamps identical contexts only share an entry within the same second.
+//! Set an evaluation timestamp quantum to share entries across seconds.
+//!
+//! Only successful evaluations are cached; errors are evaluated again.
+//!
+//! [`GeoLookup`]: crate::geo::GeoLookup
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use flaps_domain::{EnvironmentKey, ProjectKey};
+use flaps_eval::{EvaluationContext, EvaluationError, Resolution};
+use sha2::{Digest as _, Sha256};
+
+/// Stable SHA-256 digest of an [`EvaluationContext`].
+///
+/// Covers the targeting key, the attributes and the timestamp. Attributes are
+/// hashed through their JSON serialization, whose object keys are sorted, so
+/// equal contexts hash equally whatever order their attributes were sent in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContextHash([u8; 32]);
+
+impl ContextHash {
+    /// Hashes `context`.
+    #[must_use]
+    pub fn of(context: &EvaluationContext) -> Self {
+        let encoded = serde_json::to_vec(&(
+            &context.targeting_key,
+            &context.attributes,
+            context.timestamp,
+        ))
+        .unwrap_or_default();
+        Self(Sha256::digest(encoded).into())
+    }
+}
+
+/// Identifies one cached evaluation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EvaluationCacheKey {
+    /// Project of the evaluated ruleset.
+    pub project: ProjectKey,
+    /// Environment of the evaluated ruleset.
+    pub environment: EnvironmentKey,
+    /// The evaluated flag.
+    pub flag_key: String,
+    /// Hash of the evaluation context.
+    pub context: ContextHash,
+    /// Version of the ruleset the flag was evaluated against.
+    pub version: u64,
+}
+
+/// Configuration of an [`EvaluationCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvaluationCacheConfig {
+    /// Environments whose evaluations are cached. Others are always
+    /// evaluated.
+    pub environments: HashSet<EnvironmentKey>,
+    /// How long a cached result is served.
+    pub ttl: Duration,
+    /// Number of results cached before the cache is emptied.
+    pub capacity: usize,
+}
+
+impl EvaluationCacheConfig {
+    /// Default [`Self::ttl`].
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(5);
+
+    /// Default [`Self::capacity`].
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    /// Caches `environments` with the default TTL and capacity.
+    #[must_use]
+    pub fn for_environments(environments: impl IntoIterator<Item = EnvironmentKey>) -> Self {
+        Self {
+            environments: environments.into_iter().collect(),
+            ttl: Self::DEFAULT_TTL,
+            capacity: Self::DEFAULT_CAPACITY,
+        }
+    }
+}
+
+/// Bounded, short-lived cache of successful evaluations.
+#[derive(Debug)]
+pub struct EvaluationCache {
+    config: EvaluationCacheConfig,
+    entries: Mutex<HashMap<EvaluationCacheKey, (Instant, Resolution)>>,
+}
+
+impl EvaluationCache {
+    /// Creates an empty cache.
+    #[must_use]
+    pub fn new(config: EvaluationCacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether evaluations in `environment` go through the cache.
+    #[must_use]
+    pub fn enabled_for(&self, environment: &EnvironmentKey) -> bool {
+        self.config.environments.contains(environment)
+    }
+
+    /// Returns the cached result for `key`, or runs `evaluate` and caches its
+    /// result when it succeeds.
+    ///
+    /// # Errors
+    /// Returns the error of `evaluate`, which is not cached.
+    pub fn get_or_evaluate(
+        &self,
+        key: EvaluationCacheKey,
+        evaluate: impl FnOnce() -> Result<Resolution, EvaluationError>,
+    ) -> Result<Resolution, EvaluationError> {
+        if let Some((stored_at, resolution)) = self.lock().get(&key) {
+            if stored_at.elapsed() < self.config.ttl {
+                return Ok(resolution.clone());
+            }
+        }
+        let resolution = evaluate()?;
+        let mut entries = self.lock();
+        if entries.len() >= self.config.capacity {
+            entries.clear();
+        }
+        entries.insert(key, (Instant::now(), resolution.clone()));
+        Ok(resolution)
+    }
+
+    /// Number of cached results, including expired ones not yet swept.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether the cache holds no result.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<EvaluationCacheKey, (Instant, Resolution)>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
+
+    use flaps_eval::Reason;
+    use serde_json::json;
+
+    use super::*;
+
+    fn config(ttl: Duration, capacity: usize) -> EvaluationCacheConfig {
+        EvaluationCacheConfig {
+            environments: HashSet::from([EnvironmentKey::new("production").unwrap()]),
+            ttl,
+            capacity,
+        }
+    }
+
+    fn context(country: &str) -> EvaluationContext {
+        EvaluationContext {
+            targeting_key: None,
+            attributes: BTreeMap::from([("country".to_owned(), json!(country))]),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    fn key(flag_key: &str, context: &EvaluationContext, version: u64) -> EvaluationCacheKey {
+        EvaluationCacheKey {
+            project: ProjectKey::new("shop").unwrap(),
+            environment: EnvironmentKey::new("production").unwrap(),
+            flag_key: flag_key.to_owned(),
+            context: ContextHash::of(context),
+            version,
+        }
+    }
+
+    fn resolution(variant: &str) -> Resolution {
+        Resolution {
+            value: Some(json!(true)),
+            variant: Some(variant.to_owned()),
+            reason: Reason::TargetingMatch,
+            metadata: flaps_eval::Metadata::new(),
+        }
+    }
+
+    #[test]
+    fn identical_inputs_invoke_the_evaluator_once() {
+        let cache = EvaluationCache::new(config(Duration::from_secs(60), 100));
+        let calls = Cell::new(0);
+        let evaluate = || {
+            calls.set(calls.get() + 1);
+            Ok(resolution("on"))
+        };
+
+        let first = cache.get_or_evaluate(key("banner", &context("FR"), 1), evaluate);
+        let second = cache.get_or_evaluate(key("banner", &context("FR"), 1), evaluate);
+
+        assert_eq!(calls.get(), 1);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn a_config_version_bump_produces_a_new_key() {
+        let ctx = context("FR");
+        assert_ne!(key("banner", &ctx, 1), key("banner", &ctx, 2));
+
+        let cache = EvaluationCache::new(config(Duration::from_secs(60), 100));
+        let calls = Cell::new(0);
+        let evaluate = || {
+            calls.set(calls.get() + 1);
+            Ok(resolution("on"))
+        };
+        cache
+            .get_or_evaluate(key("banner", &ctx, 1), evaluate)
+            .unwrap();
+        cache
+            .get_or_evaluate(key("banner", &ctx, 2), evaluate)
+            .unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn context_hash_depends_on_content_only() {
+        let mut reordered = EvaluationContext {
+            targeting_key: None,
+            attributes: BTreeMap::new(),
+            timestamp: 1_700_000_000,
+        };
+        reordered
+            .attributes
+            .insert("tier".to_owned(), json!("gold"));
+        reordered
+            .attributes
+            .insert("country".to_owned(), json!("FR"));
+        let mut ordered = context("FR");
+        ordered.attributes.insert("tier".to_owned(), json!("gold"));
+
+        assert_eq!(ContextHash::of(&ordered), ContextHash::of(&reordered));
+        assert_ne!(
+            ContextHash::of(&context("FR")),
+            ContextHash::of(&context("DE"))
+        );
+
+        let mut later = context("FR");
+        later.timestamp += 1;
+        assert_ne!(ContextHash::of(&context("FR")), ContextHash::of(&later));
+    }
+
+    #[test]
+    fn expired_results_are_evaluated_again() {
+        let cache = EvaluationCache::new(config(Duration::ZERO, 100));
+        let calls = Cell::new(0);
+        let evaluate = || {
+            calls.set(calls.get() + 1);
+            Ok(resolution("on"))
+        };
+        cache
+            .get_or_evaluate(key("banner", &context("FR"), 1), evaluate)
+            .unwrap();
+        cache
+            .get_or_evaluate(key("banner", &context("FR"), 1), evaluate)
+            .unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let cache = EvaluationCache::new(config(Duration::from_secs(60), 100));
+        let result = cache.get_or_evaluate(key("ghost", &context("FR"), 1), || {
+            Err(EvaluationError::FlagNotFound {
+                flag_key: "ghost".to_owned(),
+            })
+        });
+        assert!(result.is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn a_full_cache_is_emptied_before_inserting() {
+        let cache = EvaluationCache::new(config(Duration::from_secs(60), 2));
+        for country in ["FR", "DE", "IT"] {
+            cache
+                .get_or_evaluate(key("banner", &context(country), 1), || Ok(resolution("on")))
+                .unwrap();
+        }
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn only_listed_environments_are_enabled() {
+        let cache = EvaluationCache::new(config(Duration::from_secs(60), 100));
+        assert!(cache.enabled_for(&EnvironmentKey::new("production").unwrap()));
+        assert!(!cache.enabled_for(&EnvironmentKey::new("staging").unwrap()));
+    }
+}
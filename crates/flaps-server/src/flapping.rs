@@ -0,0 +1,205 @@
+//! Detection of flapping flag configurations.
+//!
+//! A flag configuration changed over and over in a short time, typically
+//! toggled on and off, usually means something is wrong: an incident being
+//! fought by hand, or two automations undoing each other. Detection reads the
+//! audit log: a configuration is *flapping* when it recorded more than
+//! [`FlappingConfig::max_changes`] changes within the last
+//! [`FlappingConfig::window`].
+//!
+//! Flapping configurations are listed by
+//! `GET /projects/{project}/flapping`, and each configuration write that
+//! leaves one flapping emits a `flaps::flapping` warning event, for log-based
+//! alerting.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use flaps_domain::{EnvironmentKey, FlagKey, ProjectKey};
+use flaps_store::{AuditRecord, StoreResult, repository::AuditLogRepository};
+use serde::Serialize;
+
+/// Audit entity type of flag configuration records.
+const CONFIG_ENTITY_TYPE: &str = "flag_env_config";
+
+/// Thresholds of flapping detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlappingConfig {
+    /// Number of changes a configuration may record within [`Self::window`]
+    /// before it is reported as flapping.
+    pub max_changes: usize,
+    /// How far back changes are counted.
+    pub window: Duration,
+}
+
+impl FlappingConfig {
+    /// Default [`Self::max_changes`].
+    pub const DEFAULT_MAX_CHANGES: usize = 5;
+
+    /// Default [`Self::window`] (10 minutes).
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(600);
+}
+
+impl Default for FlappingConfig {
+    fn default() -> Self {
+        Self {
+            max_changes: Self::DEFAULT_MAX_CHANGES,
+            window: Self::DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// A flag configuration changed more often than the threshold allows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FlappingFlag {
+    /// Key of the flag.
+    pub flag: String,
+    /// Environment of the flapping configuration.
+    pub environment: String,
+    /// Number of changes recorded within the window.
+    pub changes: usize,
+    /// When the most recent change was made (RFC 3339, UTC).
+    pub last_changed_at: String,
+}
+
+/// Body of `GET /projects/{project}/flapping`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FlappingReport {
+    /// The [`FlappingConfig::max_changes`] in force.
+    pub max_changes: usize,
+    /// The [`FlappingConfig::window`] in force, in seconds.
+    pub window_secs: u64,
+    /// The flapping configurations, sorted by flag then environment.
+    pub flags: Vec<FlappingFlag>,
+}
+
+/// Returns the configurations of `project` that recorded more than
+/// `max_changes` changes in `records`, sorted by flag then environment.
+///
+/// `records` are `flag_env_config` audit records, oldest first, already
+/// limited to the detection window.
+#[must_use]
+pub fn flapping_flags(
+    records: &[AuditRecord],
+    project: &ProjectKey,
+    max_changes: usize,
+) -> Vec<FlappingFlag> {
+    let prefix = format!("{}/", project.as_str());
+    let mut changes: BTreeMap<(&str, &str), (usize, &str)> = BTreeMap::new();
+    for record in records {
+        let Some((flag, environment)) = record
+            .entity_id
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.split_once('/'))
+        else {
+            continue;
+        };
+        let entry = changes.entry((flag, environment)).or_default();
+        entry.0 += 1;
+        entry.1 = &record.occurred_at;
+    }
+
+    changes
+        .into_iter()
+        .filter(|(_, (count, _))| *count > max_changes)
+        .map(|((flag, environment), (count, last))| FlappingFlag {
+            flag: flag.to_owned(),
+            environment: environment.to_owned(),
+            changes: count,
+            last_changed_at: last.to_owned(),
+        })
+        .collect()
+}
+
+/// Reads the audit log of `store` and returns the flapping configurations of
+/// `project` under `config`.
+///
+/// # Errors
+///
+/// Returns the store error when the audit log cannot be read.
+pub async fn detect<S: AuditLogRepository>(
+    store: &S,
+    project: &ProjectKey,
+    config: FlappingConfig,
+) -> StoreResult<Vec<FlappingFlag>> {
+    let records = store
+        .recent_audit_entries(CONFIG_ENTITY_TYPE, project, config.window)
+        .await?;
+    Ok(flapping_flags(&records, project, config.max_changes))
+}
+
+/// Emits a `flaps::flapping` warning when the configuration of `flag` in
+/// `environment` is flapping after a write.
+///
+/// Detection is best effort: an audit log that cannot be read is logged and
+/// otherwise ignored, the write having already succeeded.
+pub(crate) async fn warn_if_flapping<S: AuditLogRepository>(
+    store: &S,
+    project: &ProjectKey,
+    flag: &FlagKey,
+    environment: &EnvironmentKey,
+    config: FlappingConfig,
+) {
+    let flapping = match detect(store, project, config).await {
+        Ok(flapping) => flapping,
+        Err(error) => {
+            tracing::warn!(%error, "flapping detection could not read the audit log");
+            return;
+        }
+    };
+    let Some(entry) = flapping
+        .iter()
+        .find(|f| f.flag == flag.as_str() && f.environment == environment.as_str())
+    else {
+        return;
+    };
+    tracing::warn!(
+        target: "flaps::flapping",
+        project = project.as_str(),
+        environment = environment.as_str(),
+        flag = flag.as_str(),
+        changes = entry.changes,
+        window_secs = config.window.as_secs(),
+        "flag configuration is flapping"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(entity_id: &str, occurred_at: &str) -> AuditRecord {
+        AuditRecord {
+            actor: "alice".to_owned(),
+            action: "flag_env_config.updated".to_owned(),
+            entity_type: CONFIG_ENTITY_TYPE.to_owned(),
+            entity_id: entity_id.to_owned(),
+            before: None,
+            after: None,
+            occurred_at: occurred_at.to_owned(),
+        }
+    }
+
+    #[test]
+    fn counts_changes_per_configuration_within_the_project() {
+        let records = vec![
+            record("shop/checkout/prod", "2026-01-01T10:00:00Z"),
+            record("shop/checkout/dev", "2026-01-01T10:00:01Z"),
+            record("shop/checkout/prod", "2026-01-01T10:00:02Z"),
+            record("other/checkout/prod", "2026-01-01T10:00:03Z"),
+            record("shop/checkout/prod", "2026-01-01T10:00:04Z"),
+        ];
+        let project = ProjectKey::new("shop").unwrap();
+
+        assert_eq!(
+            flapping_flags(&records, &project, 2),
+            vec![FlappingFlag {
+                flag: "checkout".to_owned(),
+                environment: "prod".to_owned(),
+                changes: 3,
+                last_changed_at: "2026-01-01T10:00:04Z".to_owned(),
+            }]
+        );
+        assert!(flapping_flags(&records, &project, 3).is_empty());
+    }
+}
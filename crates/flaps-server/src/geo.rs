@@ -0,0 +1,297 @@
+//! Geolocation of OFREP callers.
+//!
+//! A [`GeoResolver`] maps an IP address to a [`GeoInfo`]. When one is
+//! configured ([`AppState::with_geo_resolver`]), the OFREP handlers resolve
+//! the connection address of each request through a [`GeoLookup`] and add
+//! the result to the evaluation context as the `country`, `region` and `city`
+//! attributes, so rules can target them like any other attribute. Without a
+//! resolver, the default, contexts are left as sent.
+//!
+//! Only the connection address is used, for the reason given in
+//! [`ClientAddress`]: a forwarded-for header is the caller's to set. Behind
+//! a reverse proxy every request therefore resolves to the proxy's location.
+//!
+//! Geolocation fails open. A lookup error is logged and the context is
+//! evaluated without the attributes, so rules on them simply do not match.
+//!
+//! With the `maxmind` feature, [`MaxMindResolver`] reads a `MaxMind` `GeoIP2`
+//! or `GeoLite2` City database.
+//!
+//! [`AppState::with_geo_resolver`]: crate::state::AppState::with_geo_resolver
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use flaps_eval::EvaluationContext;
+use serde_json::Value;
+
+use crate::preauth::client_address::ClientAddress;
+
+/// Context attribute carrying the ISO 3166-1 alpha-2 country code.
+pub const COUNTRY_ATTRIBUTE: &str = "country";
+
+/// Context attribute carrying the ISO 3166-2 subdivision code.
+pub const REGION_ATTRIBUTE: &str = "region";
+
+/// Context attribute carrying the city name.
+pub const CITY_ATTRIBUTE: &str = "city";
+
+/// Where an IP address is located. Every part is optional: databases often
+/// know the country of an address but not its city.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code, e.g. `FR`.
+    pub country: Option<String>,
+    /// ISO 3166-2 subdivision code without the country prefix, e.g. `IDF`.
+    pub region: Option<String>,
+    /// City name, in English.
+    pub city: Option<String>,
+}
+
+/// A geolocation lookup that failed, as opposed to an address the resolver
+/// knows nothing about.
+#[derive(Debug, thiserror::Error)]
+#[error("geolocation lookup failed: {0}")]
+pub struct GeoError(pub String);
+
+/// Maps IP addresses to locations.
+pub trait GeoResolver: Send + Sync + 'static {
+    /// Locates `address`.
+    ///
+    /// # Errors
+    /// Returns [`GeoError`] when the lookup itself fails. An address the
+    /// resolver has no data for is `Ok(None)`.
+    fn resolve(&self, address: IpAddr) -> Result<Option<GeoInfo>, GeoError>;
+}
+
+/// A [`GeoResolver`] behind a bounded cache of its answers.
+///
+/// Successful lookups, including "unknown address", are cached; errors are
+/// not, so a transient failure is retried on the next request. When the
+/// cache is full it is emptied rather than evicted entry by entry: a fleet
+/// of callers fits comfortably, and a scan over many addresses only costs
+/// fresh lookups.
+pub struct GeoLookup {
+    resolver: Arc<dyn GeoResolver>,
+    cache: Mutex<HashMap<IpAddr, Option<GeoInfo>>>,
+    capacity: usize,
+}
+
+impl GeoLookup {
+    /// Number of addresses cached before the cache is emptied.
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    /// Wraps `resolver` with a cache of [`Self::DEFAULT_CAPACITY`] addresses.
+    #[must_use]
+    pub fn new(resolver: impl GeoResolver) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+            cache: Mutex::new(HashMap::new()),
+            capacity: Self::DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Locates `address`, from the cache when possible. A failed lookup is
+    /// logged and yields `None`.
+    pub fn resolve(&self, address: IpAddr) -> Option<GeoInfo> {
+        if let Some(cached) = self.lock().get(&address) {
+            return cached.clone();
+        }
+        match self.resolver.resolve(address) {
+            Ok(info) => {
+                let mut cache = self.lock();
+                if cache.len() >= self.capacity {
+                    cache.clear();
+                }
+                cache.insert(address, info.clone());
+                info
+            }
+            Err(err) => {
+                tracing::debug!(%address, error = %err, "geolocation skipped");
+                None
+            }
+        }
+    }
+
+    /// Adds the location of `address` to `context`.
+    ///
+    /// Attributes the caller already set are kept: the caller may know the
+    /// end user's location better than the connection address does.
+    pub fn enrich(&self, context: &mut EvaluationContext, address: ClientAddress) {
+        let ClientAddress::Known(address) = address else {
+            return;
+        };
+        let Some(info) = self.resolve(address) else {
+            return;
+        };
+        for (attribute, value) in [
+            (COUNTRY_ATTRIBUTE, info.country),
+            (REGION_ATTRIBUTE, info.region),
+            (CITY_ATTRIBUTE, info.city),
+        ] {
+            if let Some(value) = value {
+                context
+                    .attributes
+                    .entry(attribute.to_owned())
+                    .or_insert(Value::String(value));
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<IpAddr, Option<GeoInfo>>> {
+        self.cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl std::fmt::Debug for GeoLookup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoLookup")
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`GeoResolver`] reading a `MaxMind` City database (`GeoIP2-City` or
+/// `GeoLite2-City`).
+#[cfg(feature = "maxmind")]
+pub struct MaxMindResolver {
+    reader: maxminddb::Reader<Vec<u8>>,
+}
+
+#[cfg(feature = "maxmind")]
+impl MaxMindResolver {
+    /// Loads the database at `path` into memory.
+    ///
+    /// # Errors
+    /// Returns [`GeoError`] when the file cannot be read or is not a `MaxMind`
+    /// database.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, GeoError> {
+        maxminddb::Reader::open_readfile(path)
+            .map(|reader| Self { reader })
+            .map_err(|err| GeoError(err.to_string()))
+    }
+}
+
+#[cfg(feature = "maxmind")]
+impl GeoResolver for MaxMindResolver {
+    fn resolve(&self, address: IpAddr) -> Result<Option<GeoInfo>, GeoError> {
+        let city: maxminddb::geoip2::City<'_> = match self.reader.lookup(address) {
+            Ok(city) => city,
+            Err(maxminddb::MaxMindDBError::AddressNotFoundError(_)) => return Ok(None),
+            Err(err) => return Err(GeoError(err.to_string())),
+        };
+        Ok(Some(GeoInfo {
+            country: city.country.and_then(|c| c.iso_code).map(str::to_owned),
+            region: city
+                .subdivisions
+                .and_then(|subdivisions| subdivisions.into_iter().next())
+                .and_then(|subdivision| subdivision.iso_code)
+                .map(str::to_owned),
+            city: city
+                .city
+                .and_then(|c| c.names)
+                .and_then(|names| names.get("en").map(|name| (*name).to_owned())),
+        }))
+    }
+}
+
+#[cfg(feature = "maxmind")]
+impl std::fmt::Debug for MaxMindResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaxMindResolver")
+            .field("database_type", &self.reader.metadata.database_type)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Counts lookups; fails while `failing` is set.
+    #[derive(Default)]
+    struct Counting {
+        calls: Arc<AtomicUsize>,
+        failing: bool,
+    }
+
+    impl GeoResolver for Counting {
+        fn resolve(&self, _: IpAddr) -> Result<Option<GeoInfo>, GeoError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failing {
+                return Err(GeoError("offline".to_owned()));
+            }
+            Ok(Some(GeoInfo {
+                country: Some("FR".to_owned()),
+                ..GeoInfo::default()
+            }))
+        }
+    }
+
+    fn address(last: u8) -> IpAddr {
+        IpAddr::from([192, 0, 2, last])
+    }
+
+    #[test]
+    fn answers_are_cached_per_address() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let lookup = GeoLookup::new(Counting {
+            calls: Arc::clone(&calls),
+            failing: false,
+        });
+        for _ in 0..3 {
+            assert_eq!(
+                lookup.resolve(address(1)).unwrap().country.as_deref(),
+                Some("FR")
+            );
+        }
+        lookup.resolve(address(2));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn errors_are_not_cached_and_leave_the_context_alone() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let lookup = GeoLookup::new(Counting {
+            calls: Arc::clone(&calls),
+            failing: true,
+        });
+        let mut context = EvaluationContext::default();
+        lookup.enrich(&mut context, ClientAddress::Known(address(1)));
+        lookup.enrich(&mut context, ClientAddress::Known(address(1)));
+        assert!(context.attributes.is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn a_full_cache_is_emptied() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut lookup = GeoLookup::new(Counting {
+            calls: Arc::clone(&calls),
+            failing: false,
+        });
+        lookup.capacity = 2;
+        for last in [1, 2, 3, 1] {
+            lookup.resolve(address(last));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn unknown_connection_addresses_are_not_resolved() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let lookup = GeoLookup::new(Counting {
+            calls: Arc::clone(&calls),
+            failing: false,
+        });
+        let mut context = EvaluationContext::default();
+        lookup.enrich(&mut context, ClientAddress::Unknown);
+        assert!(context.attributes.is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
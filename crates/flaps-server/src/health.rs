@@ -0,0 +1,197 @@
+//! Readiness of the backends the server depends on.
+//!
+//! A [`SystemHealth`] runs a set of [`HealthCheck`]s concurrently, each under
+//! its own timeout, and gathers the outcome in a [`HealthReport`] with the
+//! status and latency of every component. [`AppState`] carries one that
+//! checks the store ([`DatabaseCheck`]); embedders add their own backends
+//! with [`AppState::with_health_check`].
+//!
+//! The report backs `GET /readyz` and the `flapsd status` command. A check
+//! that fails or outlives its timeout marks its component, and the system,
+//! unhealthy; the other checks still run to completion.
+//!
+//! [`AppState`]: crate::state::AppState
+//! [`AppState::with_health_check`]: crate::state::AppState::with_health_check
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::task::JoinSet;
+
+use flaps_store::repository::HealthRepository;
+
+/// The future returned by [`HealthCheck::check`].
+pub type CheckFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+/// One backend whose availability the server depends on.
+pub trait HealthCheck: Send + Sync + 'static {
+    /// Component name, as reported in [`ComponentHealth::name`].
+    fn name(&self) -> &str;
+
+    /// Probes the backend. An error carries a description of the failure.
+    fn check(&self) -> CheckFuture<'_>;
+}
+
+/// Checks the store with [`HealthRepository::ping`].
+#[derive(Debug, Clone)]
+pub struct DatabaseCheck<S>(pub S);
+
+impl<S: HealthRepository + 'static> HealthCheck for DatabaseCheck<S> {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move { self.0.ping().await.map_err(|err| err.to_string()) })
+    }
+}
+
+/// Whether a component, or the whole system, can serve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// The check succeeded within its timeout.
+    Healthy,
+    /// The check failed or timed out.
+    Unhealthy,
+}
+
+/// The outcome of one [`HealthCheck`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentHealth {
+    /// The component name.
+    pub name: String,
+    /// The component status.
+    pub status: HealthStatus,
+    /// Time the check took, in milliseconds; the timeout when it timed out.
+    pub latency_ms: u64,
+    /// Why the check failed.
+    ///
+    /// Backend errors can name hosts and users, so it is not serialized:
+    /// `GET /readyz` is unauthenticated. It is logged instead, and printed
+    /// by `flapsd status`.
+    #[serde(skip_serializing)]
+    pub error: Option<String>,
+}
+
+/// The outcome of every check of a [`SystemHealth`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    /// [`HealthStatus::Healthy`] when every component is.
+    pub status: HealthStatus,
+    /// One entry per check, in registration order.
+    pub components: Vec<ComponentHealth>,
+}
+
+impl HealthReport {
+    /// Returns `true` when every component is healthy.
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthStatus::Healthy
+    }
+}
+
+/// A set of [`HealthCheck`]s, each with its own timeout.
+#[derive(Clone, Default)]
+pub struct SystemHealth {
+    checks: Vec<(Arc<dyn HealthCheck>, Duration)>,
+}
+
+impl SystemHealth {
+    /// Timeout applied by [`Self::with_check`].
+    pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Builds an empty set; its report is healthy.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `check` with [`Self::DEFAULT_CHECK_TIMEOUT`].
+    #[must_use]
+    pub fn with_check(self, check: impl HealthCheck) -> Self {
+        self.with_check_timeout(check, Self::DEFAULT_CHECK_TIMEOUT)
+    }
+
+    /// Adds `check`, reported unhealthy if it takes longer than `timeout`.
+    #[must_use]
+    pub fn with_check_timeout(mut self, check: impl HealthCheck, timeout: Duration) -> Self {
+        self.checks.push((Arc::new(check), timeout));
+        self
+    }
+
+    /// Runs every check concurrently and reports their outcome.
+    ///
+    /// Takes as long as the slowest check, at most the longest timeout.
+    pub async fn report(&self) -> HealthReport {
+        let mut running = JoinSet::new();
+        for (index, (check, timeout)) in self.checks.iter().enumerate() {
+            let check = Arc::clone(check);
+            let timeout = *timeout;
+            running.spawn(async move { (index, run_check(check.as_ref(), timeout).await) });
+        }
+
+        let mut components: Vec<Option<ComponentHealth>> = vec![None; self.checks.len()];
+        while let Some(joined) = running.join_next().await {
+            // A panicking check leaves its slot empty, reported below.
+            if let Ok((index, component)) = joined {
+                components[index] = Some(component);
+            }
+        }
+        let components: Vec<ComponentHealth> = components
+            .into_iter()
+            .zip(&self.checks)
+            .map(|(component, (check, _))| {
+                component.unwrap_or_else(|| ComponentHealth {
+                    name: check.name().to_owned(),
+                    status: HealthStatus::Unhealthy,
+                    latency_ms: 0,
+                    error: Some("health check panicked".to_owned()),
+                })
+            })
+            .collect();
+
+        let status = if components
+            .iter()
+            .all(|component| component.status == HealthStatus::Healthy)
+        {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy
+        };
+        HealthReport { status, components }
+    }
+}
+
+impl std::fmt::Debug for SystemHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.checks.iter().map(|(check, _)| check.name()))
+            .finish()
+    }
+}
+
+/// Runs one check under `timeout`.
+async fn run_check(check: &dyn HealthCheck, timeout: Duration) -> ComponentHealth {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(timeout, check.check()).await;
+    let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let error = match outcome {
+        Ok(Ok(())) => None,
+        Ok(Err(err)) => Some(err),
+        Err(_) => Some(format!("timed out after {} ms", timeout.as_millis())),
+    };
+    ComponentHealth {
+        name: check.name().to_owned(),
+        status: if error.is_none() {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy
+        },
+        latency_ms,
+        error,
+    }
+}
@@ -4,11 +4,18 @@
 //! sync channel with server-sent events distribution.
 
 pub mod auth;
+pub mod context_sample;
 pub mod error;
 pub mod etag;
+pub mod eval_cache;
+pub mod flapping;
+pub mod geo;
+pub mod health;
+pub mod pagination;
 pub mod preauth;
 pub mod rate_limit;
 pub mod recompile;
+pub mod request_id;
 pub mod routes;
 pub mod sse_quota;
 pub mod state;
@@ -23,13 +30,21 @@ use axum::{
 use routes::{
     auth::post_login,
     environment::{delete_environment, get_environment, list_environments, put_environment},
-    flag::{delete_flag, get_flag, list_flags, put_flag},
-    flag_env_config::{delete_flag_env_config, get_flag_env_config, put_flag_env_config},
+    flag::{
+        delete_flag, get_flag, get_flag_definition, list_flags, post_flag_evaluation, put_flag,
+    },
+    flag_env_config::{
+        delete_flag_env_config, get_flag_env_config, get_flapping_flags, put_flag_env_config,
+    },
+    health::get_readyz,
     ofrep::{post_evaluate_flag, post_evaluate_flags},
     project::{delete_project, get_project, list_projects, put_project},
     sdk::get_whoami,
     sdk_key::{delete_sdk_key, list_sdk_keys, post_sdk_key},
-    segment::{delete_segment, get_segment, list_segments, put_segment},
+    segment::{
+        delete_segment, get_segment, list_segments, post_segment_matches, post_segment_preview,
+        put_segment,
+    },
 };
 use state::{AppState, Store};
 use sync::{get_events, get_ruleset};
@@ -40,6 +55,9 @@ use sync::{get_events, get_ruleset};
 ///   - Public: no authentication required.
 ///   - Admin: requires a valid session token (`Authorization: Bearer <token>`).
 ///   - SDK: requires a valid SDK key (`Authorization: Bearer <key>`), rate-limited.
+///
+/// Every route runs under [`request_id::propagate`], which tags the request
+/// with an `X-Request-Id` for log correlation.
 pub fn build_router<S: Store>(state: AppState<S>) -> Router {
     Router::<AppState<S>>::new()
         // ---- Public ----
@@ -49,6 +67,7 @@ pub fn build_router<S: Store>(state: AppState<S>) -> Router {
                 crate::preauth::limits::MAX_LOGIN_BODY_BYTES,
             )),
         )
+        .route("/readyz", get(get_readyz::<S>))
         // ---- Admin: CRUD (projects, environments, flags, segments, configs) ----
         .route("/projects", get(list_projects::<S>))
         .route("/projects/{project}", get(get_project::<S>))
@@ -74,6 +93,14 @@ pub fn build_router<S: Store>(state: AppState<S>) -> Router {
         .route("/projects/{project}/flags/{flag}", get(get_flag::<S>))
         .route("/projects/{project}/flags/{flag}", put(put_flag::<S>))
         .route("/projects/{project}/flags/{flag}", delete(delete_flag::<S>))
+        .route(
+            "/projects/{project}/flags/{flag}/definition",
+            get(get_flag_definition::<S>),
+        )
+        .route(
+            "/projects/{project}/flags/{flag}/evaluate",
+            post(post_flag_evaluation::<S>),
+        )
         .route("/projects/{project}/segments", get(list_segments::<S>))
         .route(
             "/projects/{project}/segments/{segment}",
@@ -87,6 +114,14 @@ pub fn build_router<S: Store>(state: AppState<S>) -> Router {
             "/projects/{project}/segments/{segment}",
             delete(delete_segment::<S>),
         )
+        .route(
+            "/projects/{project}/segment-preview",
+            post(post_segment_preview::<S>),
+        )
+        .route(
+            "/projects/{project}/segment-matches",
+            post(post_segment_matches::<S>),
+        )
         .route(
             "/projects/{project}/flags/{flag}/environments/{env}/config",
             get(get_flag_env_config::<S>),
@@ -99,6 +134,7 @@ pub fn build_router<S: Store>(state: AppState<S>) -> Router {
             "/projects/{project}/flags/{flag}/environments/{env}/config",
             delete(delete_flag_env_config::<S>),
         )
+        .route("/projects/{project}/flapping", get(get_flapping_flags::<S>))
         // ---- Admin: SDK key management ----
         .route(
             "/projects/{project}/environments/{env}/keys",
@@ -124,6 +160,7 @@ pub fn build_router<S: Store>(state: AppState<S>) -> Router {
         .route("/sync/v1/ruleset", get(get_ruleset::<S>))
         .route("/sync/v1/events", get(get_events::<S>))
         .with_state(state)
+        .layer(axum::middleware::from_fn(request_id::propagate))
 }
 
 /// Ensures an initial admin account exists.
@@ -0,0 +1,53 @@
+//! Query parameters of the paginated list endpoints.
+//!
+//! `GET /projects/{project}/flags`, `/environments` and `/segments` answer
+//! with the whole list as a JSON array by default. A request carrying
+//! `?limit=` or `?cursor=` reads one page instead, answered as a
+//! [`Page`](flaps_store::repository::Page):
+//!
+//! ```json
+//! { "items": [ ... ], "next_cursor": "ZmxhZy1i", "total": 120 }
+//! ```
+//!
+//! `next_cursor` is `null` on the last page; otherwise it is passed back as
+//! `?cursor=` to read the next one. Pages are sorted by key.
+
+use flaps_store::repository::{Cursor, DEFAULT_PAGE_LIMIT, PageRequest};
+use serde::Deserialize;
+
+use crate::error::ApiError;
+
+/// `?cursor=&limit=` of a paginated list endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageParams {
+    /// Token of the page to read, from a previous page's `next_cursor`.
+    pub cursor: Option<String>,
+    /// Maximum number of items of the page; defaults to
+    /// [`DEFAULT_PAGE_LIMIT`], capped at
+    /// [`MAX_PAGE_LIMIT`](flaps_store::repository::MAX_PAGE_LIMIT).
+    pub limit: Option<usize>,
+}
+
+impl PageParams {
+    /// Returns the page requested, or `None` when neither parameter is set
+    /// and the whole list is wanted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::InvalidBody`] for a malformed cursor.
+    pub fn request(&self) -> Result<Option<PageRequest>, ApiError> {
+        if self.cursor.is_none() && self.limit.is_none() {
+            return Ok(None);
+        }
+        let after = self
+            .cursor
+            .as_deref()
+            .map(Cursor::decode)
+            .transpose()
+            .map_err(ApiError::from)?;
+        Ok(Some(PageRequest {
+            after,
+            limit: self.limit.unwrap_or(DEFAULT_PAGE_LIMIT),
+        }))
+    }
+}
@@ -3,13 +3,19 @@
 use std::collections::HashMap;
 
 use flaps_compiler::{
-    CompiledRuleset, FlagConfig, Segments, compile_environment, environments_referencing_segment,
+    CompileReport, CompiledRuleset, FlagConfig, Segments, compile_environment_with_limits,
+    environments_referencing_segment, precompile,
 };
 use flaps_domain::{
     Environment, EnvironmentKey, Flag, FlagEnvConfig, FlagKey, ProjectKey, Segment, SegmentKey,
+    inheritance_chain,
 };
 
-use crate::{error::ApiError, state::AppState, state::Store, sync::SyncEvent};
+use crate::{
+    error::ApiError,
+    state::{AppState, CachedRuleset, Store},
+    sync::SyncEvent,
+};
 
 // ---------------------------------------------------------------------------
 // Change overlay
@@ -55,14 +61,31 @@ pub enum Change<'a> {
 // Assemble helpers
 // ---------------------------------------------------------------------------
 
+/// The compiler inputs for one environment, read from the store with the
+/// overlay applied.
+struct AssembledEnvironment {
+    /// Every flag with a config in the environment, paired with that config
+    /// once the targeting it inherits is resolved.
+    flag_configs: Vec<(Flag, FlagEnvConfig)>,
+    /// Every segment of the project.
+    segments: Vec<Segment>,
+    /// The environment's own (flag-set level) metadata.
+    environment_metadata: flaps_domain::Metadata,
+}
+
 /// Reads all flags, their per-env configs, and all segments for a project and
-/// environment from the store, applies the overlay, then compiles.
-async fn compile_env_with_overlay<S: Store>(
+/// environment from the store, and applies the overlay.
+///
+/// A config without rules takes the targeting of the nearest base environment
+/// configuring the flag, and one setting `inherit_default_rule` takes its
+/// default (see [`FlagEnvConfig::inheriting`]), resolved along
+/// [`Environment::inherits_from`]; it keeps its own `enabled`.
+async fn assemble_with_overlay<S: Store>(
     state: &AppState<S>,
     project: &ProjectKey,
     environment: &EnvironmentKey,
     change: &Change<'_>,
-) -> Result<CompiledRuleset, ApiError> {
+) -> Result<AssembledEnvironment, ApiError> {
     // Read all flags for the project.
     let mut flags = state
         .store
@@ -107,74 +130,146 @@ async fn compile_env_with_overlay<S: Store>(
         _ => {}
     }
 
-    // Build the Segments lookup from the (possibly-mutated) segment list.
-    let segment_lookup = Segments::new(segments.iter().map(|s| (s.key.clone(), &s.match_expr)));
+    let environments = environments_with_overlay(state, project, change).await?;
+    let bases = inheritance_chain(environment, &environments)
+        .map_err(|e| ApiError::InvalidBody(e.to_string()))?;
 
-    // Build FlagConfig slice: flags that have a config in this environment.
+    // Build FlagConfig slice: flags that have a config in this environment,
+    // with the targeting inherited from the bases resolved.
     let mut flag_configs: Vec<(Flag, FlagEnvConfig)> = Vec::new();
 
     for flag in &flags {
-        // Determine the config for this (flag, environment), applying the overlay.
-        let config = match change {
-            Change::UpsertFlagEnvConfig {
-                flag: overlay_flag,
-                environment: overlay_env,
-                config,
-            } if *overlay_flag == &flag.key && *overlay_env == environment => {
-                Some((*config).clone())
+        let Some(own) = config_with_overlay(state, project, &flag.key, environment, change).await?
+        else {
+            continue;
+        };
+        // Collect the configs of the bases configuring the flag, up the chain
+        // until one needs nothing from its own base, then resolve them from
+        // the farthest one down. A base without a config is passed over.
+        let mut inherited = Vec::new();
+        for base in &bases {
+            let nearest = inherited.last().unwrap_or(&own);
+            if !nearest.rules.is_empty() && !nearest.inherit_default_rule {
+                break;
             }
-            Change::DeleteFlagEnvConfig {
-                flag: overlay_flag,
-                environment: overlay_env,
-            } if *overlay_flag == &flag.key && *overlay_env == environment => None,
-            _ => {
-                // Read from store.
-                state
-                    .store
-                    .get_flag_env_config(project, &flag.key, environment)
-                    .await
-                    .map_err(ApiError::from)?
+            if let Some(config) =
+                config_with_overlay(state, project, &flag.key, base, change).await?
+            {
+                inherited.push(config);
             }
+        }
+        let effective = match inherited
+            .into_iter()
+            .rev()
+            .reduce(|base, config| config.inheriting(&base))
+        {
+            Some(base) => own.inheriting(&base),
+            None => own,
         };
+        flag_configs.push((flag.clone(), effective));
+    }
+
+    // The environment's own metadata (flag-set level), from the same
+    // overlay-aware listing as the bases.
+    let environment_metadata = environments
+        .into_iter()
+        .find(|env| env.key == *environment)
+        .map(|env| env.metadata)
+        .unwrap_or_default();
+
+    Ok(AssembledEnvironment {
+        flag_configs,
+        segments,
+        environment_metadata,
+    })
+}
+
+/// Reads the config of `flag` in `environment` from the store, or from the
+/// overlay when `change` writes or deletes it.
+async fn config_with_overlay<S: Store>(
+    state: &AppState<S>,
+    project: &ProjectKey,
+    flag: &FlagKey,
+    environment: &EnvironmentKey,
+    change: &Change<'_>,
+) -> Result<Option<FlagEnvConfig>, ApiError> {
+    match change {
+        Change::UpsertFlagEnvConfig {
+            flag: overlay_flag,
+            environment: overlay_env,
+            config,
+        } if *overlay_flag == flag && *overlay_env == environment => Ok(Some((*config).clone())),
+        Change::DeleteFlagEnvConfig {
+            flag: overlay_flag,
+            environment: overlay_env,
+        } if *overlay_flag == flag && *overlay_env == environment => Ok(None),
+        _ => state
+            .store
+            .get_flag_env_config(project, flag, environment)
+            .await
+            .map_err(ApiError::from),
+    }
+}
 
-        if let Some(cfg) = config {
-            flag_configs.push((flag.clone(), cfg));
+/// Lists the project's environments with an environment upsert or deletion
+/// applied.
+async fn environments_with_overlay<S: Store>(
+    state: &AppState<S>,
+    project: &ProjectKey,
+    change: &Change<'_>,
+) -> Result<Vec<Environment>, ApiError> {
+    let mut environments = state
+        .store
+        .list_environments(project)
+        .await
+        .map_err(ApiError::from)?;
+    match change {
+        Change::UpsertEnvironment(env) => {
+            if let Some(pos) = environments.iter().position(|e| e.key == env.key) {
+                environments[pos] = (*env).clone();
+            } else {
+                environments.push((*env).clone());
+            }
         }
+        Change::DeleteEnvironment(key) => environments.retain(|e| &e.key != *key),
+        _ => {}
     }
+    Ok(environments)
+}
 
-    // Borrow the flag_configs as FlagConfig slices.
-    let flag_config_refs: Vec<FlagConfig<'_>> = flag_configs
+/// Assembles the environment with the overlay applied, then compiles it.
+async fn compile_env_with_overlay<S: Store>(
+    state: &AppState<S>,
+    project: &ProjectKey,
+    environment: &EnvironmentKey,
+    change: &Change<'_>,
+) -> Result<CompiledRuleset, ApiError> {
+    let assembled = assemble_with_overlay(state, project, environment, change).await?;
+    let segment_lookup = Segments::new(
+        assembled
+            .segments
+            .iter()
+            .map(|s| (s.key.clone(), &s.match_expr)),
+    );
+    let flag_config_refs: Vec<FlagConfig<'_>> = assembled
+        .flag_configs
         .iter()
         .map(|(f, c)| FlagConfig { flag: f, config: c })
         .collect();
 
-    // Resolve the environment's own metadata (flag-set level), overlay-aware.
-    // This is a single extra read per environment being compiled (not per
-    // flag), so it does not introduce an N+1 query pattern: the overlay case
-    // (`UpsertEnvironment`) needs no read at all, and every other change kind
-    // reads the environment exactly once, same as the flags/segments reads
-    // above.
-    let environment_metadata = match change {
-        Change::UpsertEnvironment(env) if env.key == *environment => env.metadata.clone(),
-        _ => state
-            .store
-            .get_environment(project, environment)
-            .await
-            .map_err(ApiError::from)?
-            .map(|env| env.metadata)
-            .unwrap_or_default(),
-    };
-
     // Get previous compiled ruleset for version monotonicity.
     let cache = state.cache.read().await;
-    let previous = cache.get(&(project.clone(), environment.clone()));
+    let previous = cache
+        .get(&(project.clone(), environment.clone()))
+        .map(|cached| &cached.ruleset);
 
-    compile_environment(
+    compile_environment_with_limits(
         environment,
         &flag_config_refs,
         &segment_lookup,
-        &environment_metadata,
+        &assembled.environment_metadata,
         previous,
+        &state.limits,
     )
     .map_err(ApiError::Validation)
 }
@@ -253,6 +348,28 @@ async fn envs_referencing_seg<S: Store>(
     Ok(affected.into_iter().collect())
 }
 
+/// Extends `affected` with every environment inheriting, directly or not,
+/// from one of them: their effective targeting may have changed too.
+async fn with_inheritors<S: Store>(
+    state: &AppState<S>,
+    project: &ProjectKey,
+    change: &Change<'_>,
+    mut affected: Vec<EnvironmentKey>,
+) -> Result<Vec<EnvironmentKey>, ApiError> {
+    let environments = environments_with_overlay(state, project, change).await?;
+    for env in &environments {
+        if affected.contains(&env.key) {
+            continue;
+        }
+        let inherits = inheritance_chain(&env.key, &environments)
+            .is_ok_and(|bases| bases.iter().any(|base| affected.contains(base)));
+        if inherits {
+            affected.push(env.key.clone());
+        }
+    }
+    Ok(affected)
+}
+
 // ---------------------------------------------------------------------------
 // Public API
 // ---------------------------------------------------------------------------
@@ -265,24 +382,25 @@ pub async fn affected_environments<S: Store>(
     project: &ProjectKey,
     change: &Change<'_>,
 ) -> Result<Vec<EnvironmentKey>, ApiError> {
-    match change {
-        Change::UpsertEnvironment(env) => Ok(vec![env.key.clone()]),
-        Change::DeleteEnvironment(env_key) => Ok(vec![(*env_key).clone()]),
-        Change::UpsertFlag(flag) => envs_with_flag_config(state, project, &flag.key).await,
-        Change::DeleteFlag(flag_key) => envs_with_flag_config(state, project, flag_key).await,
+    let direct = match change {
+        Change::UpsertEnvironment(env) => vec![env.key.clone()],
+        Change::DeleteEnvironment(env_key) => return Ok(vec![(*env_key).clone()]),
+        Change::UpsertFlag(flag) => envs_with_flag_config(state, project, &flag.key).await?,
+        Change::DeleteFlag(flag_key) => envs_with_flag_config(state, project, flag_key).await?,
         Change::UpsertFlagEnvConfig { environment, .. }
-        | Change::DeleteFlagEnvConfig { environment, .. } => Ok(vec![(*environment).clone()]),
-        Change::UpsertSegment(seg) => envs_referencing_seg(state, project, &seg.key).await,
-        Change::DeleteSegment(seg_key) => envs_referencing_seg(state, project, seg_key).await,
+        | Change::DeleteFlagEnvConfig { environment, .. } => vec![(*environment).clone()],
+        Change::UpsertSegment(seg) => envs_referencing_seg(state, project, &seg.key).await?,
+        Change::DeleteSegment(seg_key) => envs_referencing_seg(state, project, seg_key).await?,
         Change::UpsertProject | Change::DeleteProject => {
             let envs = state
                 .store
                 .list_environments(project)
                 .await
                 .map_err(ApiError::from)?;
-            Ok(envs.into_iter().map(|e| e.key).collect())
+            return Ok(envs.into_iter().map(|e| e.key).collect());
         }
-    }
+    };
+    with_inheritors(state, project, change, direct).await
 }
 
 /// Validates a proposed mutation by compiling the affected environments WITH
@@ -313,6 +431,32 @@ pub async fn validate_by_compiling<S: Store>(
     Ok(rulesets)
 }
 
+/// Runs [`precompile`] over the stored state of one environment.
+///
+/// Reports constructs that compile but can never match (dangling segment
+/// references, operator/value type mismatches). Used at daemon startup so
+/// those surface as load-time warnings rather than silent non-matches.
+pub async fn lint_environment<S: Store>(
+    state: &AppState<S>,
+    project: &ProjectKey,
+    environment: &EnvironmentKey,
+) -> Result<CompileReport, ApiError> {
+    let assembled =
+        assemble_with_overlay(state, project, environment, &Change::UpsertProject).await?;
+    let segment_lookup = Segments::new(
+        assembled
+            .segments
+            .iter()
+            .map(|s| (s.key.clone(), &s.match_expr)),
+    );
+    let flag_config_refs: Vec<FlagConfig<'_>> = assembled
+        .flag_configs
+        .iter()
+        .map(|(f, c)| FlagConfig { flag: f, config: c })
+        .collect();
+    Ok(precompile(&flag_config_refs, &segment_lookup))
+}
+
 /// Installs freshly compiled rulesets into the cache (post-commit).
 ///
 /// Each ruleset is inserted into the cache first; then a [`SyncEvent`] is
@@ -328,12 +472,17 @@ pub async fn install_in_cache<S: Store>(
     for ruleset in rulesets {
         let environment = ruleset.environment.clone();
         let version = ruleset.version;
-        cache.insert((project.clone(), environment.clone()), ruleset);
+        let cache_key = ruleset.cache_key();
+        cache.insert(
+            (project.clone(), environment.clone()),
+            CachedRuleset::new(ruleset),
+        );
         // Emit after insert: ordering invariant documented in `crate::sync`.
         let _ = state.events.send(SyncEvent {
             project: project.clone(),
             environment,
             version,
+            cache_key,
         });
     }
 }
@@ -435,8 +584,8 @@ pub async fn recompile_committed<S: Store>(
 #[cfg(test)]
 mod tests {
     use flaps_domain::{
-        Environment, FlagEnvConfig, FlagKey, FlagType, ManagedBy, Project, SegmentKey, ServeTarget,
-        TargetingRule, ValueType, VariantKey, VariantValue, Variants,
+        Environment, FlagEnvConfig, FlagKey, FlagType, ManagedBy, Project, RolloutScope,
+        SegmentKey, ServeTarget, TargetingRule, ValueType, VariantKey, VariantValue, Variants,
     };
     use flaps_store::{
         KeyHasher,
@@ -512,6 +661,7 @@ mod tests {
                     external_ref: None,
                     managed_by: ManagedBy::Local,
                     metadata: flaps_domain::Metadata::new(),
+                    inherits_from: None,
                 },
             )
             .await
@@ -566,6 +716,7 @@ mod tests {
                     external_ref: None,
                     managed_by: ManagedBy::Local,
                     metadata: flaps_domain::Metadata::new(),
+                    inherits_from: None,
                 },
             )
             .await
@@ -582,9 +733,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![SegmentKey::new("ghost-segment").unwrap()],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(VariantKey::new("on").unwrap()),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(VariantKey::new("off").unwrap()),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         store
             .upsert_flag_env_config("test", &project, &flag.key, &env_key, &config)
@@ -593,6 +753,18 @@ mod tests {
 
         let state = AppState::new(store);
 
+        // The lint pass reports the dangling reference instead of failing.
+        let report = lint_environment(&state, &project, &env_key)
+            .await
+            .expect("lint never fails on compile errors");
+        assert_eq!(
+            report.warnings,
+            vec![flaps_compiler::CompileWarning::DanglingSegment {
+                flag: "my-flag".to_owned(),
+                segment: "ghost-segment".to_owned(),
+            }]
+        );
+
         // recompile_environment must fail because "ghost-segment" is absent.
         let result = recompile_environment(&state, &project, &env_key).await;
         assert!(result.is_err(), "expected Err(UnknownSegment), got Ok");
@@ -640,6 +812,7 @@ mod tests {
                         external_ref: None,
                         managed_by: ManagedBy::Local,
                         metadata: flaps_domain::Metadata::new(),
+                        inherits_from: None,
                     },
                 )
                 .await
@@ -650,6 +823,12 @@ mod tests {
             enabled: true,
             rules: vec![],
             default_rule: ServeTarget::Fixed(VariantKey::new("on").unwrap()),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         store
             .upsert_flag_env_config("test", &project, &flag.key, &env_a, &config)
@@ -683,7 +862,7 @@ mod tests {
             .get(&(project.clone(), env_a.clone()))
             .expect("env_a must be present after recompile_committed");
         assert_ne!(
-            recompiled.content_hash, "stale-hash",
+            recompiled.ruleset.content_hash, "stale-hash",
             "recompile_committed must replace the stale document with committed store state"
         );
         // env_b was never listed in `affected`, so it must remain absent.
@@ -732,6 +911,7 @@ mod tests {
                         external_ref: None,
                         managed_by: ManagedBy::Local,
                         metadata: flaps_domain::Metadata::new(),
+                        inherits_from: None,
                     },
                 )
                 .await
@@ -745,9 +925,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![SegmentKey::new("ghost-segment").unwrap()],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(VariantKey::new("on").unwrap()),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(VariantKey::new("off").unwrap()),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         store
             .upsert_flag_env_config("test", &project, &flag.key, &broken_env, &broken_config)
@@ -759,6 +948,12 @@ mod tests {
             enabled: true,
             rules: vec![],
             default_rule: ServeTarget::Fixed(VariantKey::new("on").unwrap()),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         store
             .upsert_flag_env_config("test", &project, &flag.key, &healthy_env, &healthy_config)
@@ -0,0 +1,113 @@
+//! `X-Request-Id` correlation between responses and logs.
+//!
+//! [`propagate`] runs around every route. It keeps the caller's
+//! `X-Request-Id` when it is usable, generates a UUID otherwise, and:
+//!
+//! - runs the request inside a `request` span carrying `request_id`, so every
+//!   event logged while handling it (handlers, compiler, store) carries the
+//!   id;
+//! - echoes the id in the `X-Request-Id` response header;
+//! - adds it as `request_id` to JSON error bodies, problem+json and OFREP
+//!   alike, so a user can quote it to support and support can grep for it.
+//!
+//! A caller-supplied id is kept only when it is 1 to 128 visible ASCII
+//! characters: anything else is replaced rather than written to the logs.
+
+use axum::{
+    body::{Body, HttpBody as _},
+    extract::Request,
+    http::{HeaderName, HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use tracing::Instrument as _;
+
+/// The request and response header carrying the request id.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest caller-supplied id that is kept.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Largest error body rewritten to carry the id; larger bodies pass as-is.
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// The id of the request being handled, available to handlers as a request
+/// extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Returns the id as sent in `X-Request-Id`.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Assigns or propagates the request id; see the [module docs](self).
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_usable(value))
+        .map_or_else(|| uuid::Uuid::new_v4().to_string(), str::to_owned);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut response = next.run(request).instrument(span.clone()).await;
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        span.in_scope(|| tracing::debug!(status = status.as_u16(), "request failed"));
+        response = with_id_in_body(response, &id).await;
+    }
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Whether a caller-supplied id is safe to log and echo.
+fn is_usable(id: &str) -> bool {
+    (1..=MAX_REQUEST_ID_LEN).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Adds `request_id` to a JSON object body; other bodies, and bodies that
+/// may exceed [`MAX_ERROR_BODY_BYTES`], are returned unchanged.
+async fn with_id_in_body(response: Response, id: &str) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| {
+            content_type.starts_with("application/json")
+                || content_type.starts_with("application/problem+json")
+        });
+    let bounded = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len <= MAX_ERROR_BODY_BYTES as u64);
+    if !is_json || !bounded {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await else {
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(Value::Object(mut fields)) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    fields.insert("request_id".to_owned(), Value::String(id.to_owned()));
+    let rewritten = serde_json::to_vec(&fields).unwrap_or_else(|_| bytes.to_vec());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(rewritten))
+}
@@ -2,7 +2,7 @@
 
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State, rejection::QueryRejection},
     http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
@@ -12,23 +12,35 @@ use crate::{
     auth::AdminPrincipal,
     error::ApiError,
     etag::{check_if_match, check_if_none_match, compute_etag, read_precondition_header},
+    pagination::PageParams,
     recompile::{Change, evict_environment_from_cache, recompile_committed, validate_by_compiling},
     state::{AppState, Store},
 };
 
-/// `GET /projects/{project}/environments` -- list environments in a project.
+/// `GET /projects/{project}/environments` -- list environments in a project,
+/// or one page of them (see [`crate::pagination`]).
 pub async fn list_environments<S: Store>(
     State(state): State<AppState<S>>,
     _principal: AdminPrincipal,
     Path(project): Path<String>,
-) -> Result<Json<Vec<Environment>>, ApiError> {
+    params: Result<Query<PageParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::InvalidBody(e.body_text()))?;
     let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+    if let Some(request) = params.request()? {
+        let page = state
+            .store
+            .page_environments(&project_key, &request)
+            .await
+            .map_err(ApiError::from)?;
+        return Ok(Json(page).into_response());
+    }
     let envs = state
         .store
         .list_environments(&project_key)
         .await
         .map_err(ApiError::from)?;
-    Ok(Json(envs))
+    Ok(Json(envs).into_response())
 }
 
 /// `GET /projects/{project}/environments/{env}` -- fetch a single environment with ETag.
@@ -108,6 +120,26 @@ pub async fn put_environment<S: Store>(
     let if_none_match = read_precondition_header(&headers, &header::IF_NONE_MATCH)?;
     check_if_none_match(if_none_match.as_deref(), existing.is_some())?;
 
+    // The base must be a sibling environment; a cycle through it fails the
+    // compile below.
+    if let Some(base) = body
+        .inherits_from
+        .as_ref()
+        .filter(|base| **base != body.key)
+    {
+        let base_exists = state
+            .store
+            .get_environment(&project_key, base)
+            .await
+            .map_err(ApiError::from)?
+            .is_some();
+        if !base_exists {
+            return Err(ApiError::InvalidBody(format!(
+                "base environment `{base}` does not exist"
+            )));
+        }
+    }
+
     // Compile-as-validation: for a new environment there are no configs yet, which
     // means the compile succeeds with an empty flag set (valid).
     let rulesets =
@@ -176,6 +208,23 @@ pub async fn delete_environment<S: Store>(
         return Err(ApiError::NotFound);
     }
 
+    // Deleting a base would silently drop what its inheritors serve.
+    let inheritors: Vec<String> = state
+        .store
+        .list_environments(&project_key)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .filter(|env| env.inherits_from.as_ref() == Some(&env_key))
+        .map(|env| env.key.as_str().to_owned())
+        .collect();
+    if !inheritors.is_empty() {
+        return Err(ApiError::Conflict(format!(
+            "environment `{env_key}` is the base of: {}",
+            inheritors.join(", ")
+        )));
+    }
+
     // No compilation needed for delete-environment.
     validate_by_compiling(&state, &project_key, &Change::DeleteEnvironment(&env_key)).await?;
 
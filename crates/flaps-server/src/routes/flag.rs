@@ -2,33 +2,62 @@
 
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State, rejection::QueryRejection},
     http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
-use flaps_domain::{Flag, FlagKey, ProjectKey};
+use std::collections::BTreeMap;
+
+use flaps_domain::{
+    EnvironmentKey, Flag, FlagEnvConfig, FlagKey, ProjectKey, Segment, TargetingRule,
+};
+use flaps_eval::{EvaluationError, FlagSet};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     auth::AdminPrincipal,
     error::ApiError,
     etag::{check_if_match, check_if_none_match, compute_etag, read_precondition_header},
+    pagination::PageParams,
     recompile::{Change, recompile_committed, validate_by_compiling},
+    routes::ofrep::{ContextDto, SingleSuccessResponse, build_context, map_reason, metadata_field},
     state::{AppState, Store},
 };
 
-/// `GET /projects/{project}/flags` -- list all flags in a project.
+/// `GET /projects/{project}/flags` -- list all flags in a project, or one
+/// page of them (see [`crate::pagination`]).
+///
+/// Rows that cannot be decoded are skipped (and logged by the store) rather
+/// than failing the whole listing; the full listing then reports how many in
+/// `X-Flaps-Skipped`.
 pub async fn list_flags<S: Store>(
     State(state): State<AppState<S>>,
     _principal: AdminPrincipal,
     Path(project): Path<String>,
-) -> Result<Json<Vec<Flag>>, ApiError> {
+    params: Result<Query<PageParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::InvalidBody(e.body_text()))?;
     let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
-    let flags = state
+    if let Some(request) = params.request()? {
+        let page = state
+            .store
+            .page_flags(&project_key, &request)
+            .await
+            .map_err(ApiError::from)?;
+        return Ok(Json(page).into_response());
+    }
+    let (flags, skipped) = state
         .store
-        .list_flags(&project_key)
+        .list_flags_lenient(&project_key)
         .await
         .map_err(ApiError::from)?;
-    Ok(Json(flags))
+    let mut response = Json(flags).into_response();
+    if !skipped.is_empty() {
+        response
+            .headers_mut()
+            .insert("X-Flaps-Skipped", HeaderValue::from(skipped.len()));
+    }
+    Ok(response)
 }
 
 /// `GET /projects/{project}/flags/{flag}` -- fetch a single flag with ETag.
@@ -56,6 +85,231 @@ pub async fn get_flag<S: Store>(
     Ok(response)
 }
 
+/// Response body for `GET /projects/{project}/flags/{flag}/definition`.
+#[derive(Debug, Serialize)]
+pub struct FlagDefinition {
+    /// The flag itself.
+    pub flag: Flag,
+    /// The flag's configuration in every environment that has one, keyed by
+    /// environment key.
+    pub environments: BTreeMap<String, FlagEnvConfig>,
+    /// Every segment referenced by those configurations, sorted by key.
+    /// References to segments that no longer exist are omitted.
+    pub segments: Vec<Segment>,
+}
+
+/// `GET /projects/{project}/flags/{flag}/definition` -- fetch a flag with its
+/// environment configs and referenced segments, for editors.
+///
+/// The `ETag` covers the whole definition, so it changes when any embedded
+/// config or segment does.
+pub async fn get_flag_definition<S: Store>(
+    State(state): State<AppState<S>>,
+    _principal: AdminPrincipal,
+    Path((project, flag)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+    let flag_key = FlagKey::new(flag).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+
+    let flag = state
+        .store
+        .get_flag(&project_key, &flag_key)
+        .await
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::NotFound)?;
+
+    let mut environments = BTreeMap::new();
+    for env in state
+        .store
+        .list_environments(&project_key)
+        .await
+        .map_err(ApiError::from)?
+    {
+        let config = state
+            .store
+            .get_flag_env_config(&project_key, &flag_key, &env.key)
+            .await
+            .map_err(ApiError::from)?;
+        if let Some(config) = config {
+            environments.insert(env.key.as_str().to_owned(), config);
+        }
+    }
+
+    // Keyed by the string form so the segments come out sorted and deduplicated.
+    let referenced: BTreeMap<&str, _> = environments
+        .values()
+        .flat_map(|config| &config.rules)
+        .flat_map(TargetingRule::referenced_segments)
+        .map(|key| (key.as_str(), key))
+        .collect();
+    let mut segments = Vec::with_capacity(referenced.len());
+    for segment_key in referenced.into_values() {
+        if let Some(segment) = state
+            .store
+            .get_segment(&project_key, segment_key)
+            .await
+            .map_err(ApiError::from)?
+        {
+            segments.push(segment);
+        }
+    }
+
+    let definition = FlagDefinition {
+        flag,
+        environments,
+        segments,
+    };
+    let etag = compute_etag(&definition)?;
+    let mut response = Json(definition).into_response();
+    response.headers_mut().insert(
+        header::ETAG,
+        HeaderValue::from_str(&etag).map_err(|e| ApiError::Internal(e.to_string()))?,
+    );
+    Ok(response)
+}
+
+/// Request body for `POST /projects/{project}/flags/{flag}/evaluate`.
+#[derive(Debug, Deserialize)]
+pub struct FlagEvaluationRequest {
+    /// Keys of the environments to evaluate the flag in.
+    pub environments: Vec<String>,
+    /// Evaluation context, in the OFREP shape.
+    pub context: Option<ContextDto>,
+}
+
+/// Response body for `POST /projects/{project}/flags/{flag}/evaluate`.
+#[derive(Debug, Serialize)]
+pub struct FlagEvaluationResponse {
+    /// The evaluated flag key.
+    pub key: String,
+    /// One result per requested environment, keyed by environment key.
+    pub environments: BTreeMap<String, EnvironmentEvaluation>,
+}
+
+/// The outcome of evaluating a flag in one environment.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum EnvironmentEvaluation {
+    /// The flag resolved, as the OFREP single endpoint would return it.
+    Success(SingleSuccessResponse),
+    /// The flag could not be evaluated in this environment.
+    Error(EnvironmentEvaluationError),
+}
+
+/// Why a flag could not be evaluated in one environment.
+#[derive(Debug, Serialize)]
+pub struct EnvironmentEvaluationError {
+    /// Error code.
+    #[serde(rename = "errorCode")]
+    pub error_code: EnvironmentErrorCode,
+    /// Human-readable error description.
+    #[serde(rename = "errorDetails")]
+    pub error_details: String,
+}
+
+/// Error codes of [`EnvironmentEvaluationError`].
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EnvironmentErrorCode {
+    /// The environment does not exist or has no compiled ruleset.
+    EnvironmentNotFound,
+    /// The flag has no configuration in this environment.
+    FlagNotFound,
+    /// The ruleset could not be parsed or the evaluation failed.
+    General,
+}
+
+impl EnvironmentEvaluation {
+    fn error(error_code: EnvironmentErrorCode, error_details: String) -> Self {
+        Self::Error(EnvironmentEvaluationError {
+            error_code,
+            error_details,
+        })
+    }
+}
+
+/// `POST /projects/{project}/flags/{flag}/evaluate` -- evaluate a flag in
+/// several environments for one context, for side-by-side comparison.
+///
+/// Each environment is evaluated from its compiled ruleset, exactly as the
+/// OFREP single endpoint would for an SDK key of that environment. An
+/// environment without a ruleset, or in which the flag has no configuration,
+/// yields an error entry rather than failing the request.
+pub async fn post_flag_evaluation<S: Store>(
+    State(state): State<AppState<S>>,
+    _principal: AdminPrincipal,
+    Path((project, flag)): Path<(String, String)>,
+    Json(body): Json<FlagEvaluationRequest>,
+) -> Result<Json<FlagEvaluationResponse>, ApiError> {
+    let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+    let flag_key = FlagKey::new(flag).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+
+    state
+        .store
+        .get_flag(&project_key, &flag_key)
+        .await
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::NotFound)?;
+
+    let context = build_context(body.context, state.timestamp_quantum);
+    context
+        .validate_limits(&state.context_limits)
+        .map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+
+    // Clone the documents and release the guard before parsing.
+    let documents: Vec<(String, Option<String>)> = {
+        let cache = state.cache.read().await;
+        body.environments
+            .into_iter()
+            .map(|env| {
+                let document = EnvironmentKey::new(env.clone())
+                    .ok()
+                    .and_then(|env_key| cache.get(&(project_key.clone(), env_key)))
+                    .map(|cached| cached.ruleset.document.clone());
+                (env, document)
+            })
+            .collect()
+    };
+
+    let environments = documents
+        .into_iter()
+        .map(|(env, document)| {
+            let result = match document.as_deref().map(FlagSet::from_json) {
+                None => EnvironmentEvaluation::error(
+                    EnvironmentErrorCode::EnvironmentNotFound,
+                    format!("environment `{env}` not found"),
+                ),
+                Some(Err(_)) => EnvironmentEvaluation::error(
+                    EnvironmentErrorCode::General,
+                    "Failed to parse compiled ruleset.".to_owned(),
+                ),
+                Some(Ok(flag_set)) => match flag_set.evaluate(flag_key.as_str(), &context) {
+                    Ok(resolution) => EnvironmentEvaluation::Success(SingleSuccessResponse {
+                        key: flag_key.as_str().to_owned(),
+                        value: resolution.value,
+                        reason: map_reason(resolution.reason),
+                        variant: resolution.variant,
+                        metadata: metadata_field(&resolution.metadata),
+                    }),
+                    Err(EvaluationError::FlagNotFound { .. }) => EnvironmentEvaluation::error(
+                        EnvironmentErrorCode::FlagNotFound,
+                        format!("flag `{flag_key}` is not configured in environment `{env}`"),
+                    ),
+                    Err(err) => {
+                        EnvironmentEvaluation::error(EnvironmentErrorCode::General, err.to_string())
+                    }
+                },
+            };
+            (env, result)
+        })
+        .collect();
+
+    Ok(Json(FlagEvaluationResponse {
+        key: flag_key.as_str().to_owned(),
+        environments,
+    }))
+}
+
 /// `PUT /projects/{project}/flags/{flag}` -- upsert a flag.
 pub async fn put_flag<S: Store>(
     State(state): State<AppState<S>>,
@@ -73,6 +327,9 @@ pub async fn put_flag<S: Store>(
             "Path key does not match body key".to_owned(),
         ));
     }
+    // The declared value type must agree with the variant set.
+    body.validate()
+        .map_err(|e| ApiError::InvalidBody(e.to_string()))?;
 
     // Hold the per-project lock for the whole cycle (issues #105, #108).
     let lock = state.lock_project(&project_key).await;
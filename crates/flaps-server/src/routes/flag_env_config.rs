@@ -12,6 +12,7 @@ use crate::{
     auth::AdminPrincipal,
     error::ApiError,
     etag::{check_if_match, check_if_none_match, compute_etag, read_precondition_header},
+    flapping::{FlappingReport, detect, warn_if_flapping},
     recompile::{Change, recompile_committed, validate_by_compiling},
     state::{AppState, Store},
 };
@@ -67,27 +68,34 @@ pub async fn put_flag_env_config<S: Store>(
         .await
         .map_err(ApiError::from)?
         .is_some();
-    let flag_exists = project_exists
-        && state
+    let flag = if project_exists {
+        state
             .store
             .get_flag(&project_key, &flag_key)
             .await
             .map_err(ApiError::from)?
-            .is_some();
-    let env_exists = flag_exists
+    } else {
+        None
+    };
+    let env_exists = flag.is_some()
         && state
             .store
             .get_environment(&project_key, &env_key)
             .await
             .map_err(ApiError::from)?
             .is_some();
-    if !env_exists {
+    let Some(flag) = flag.filter(|_| env_exists) else {
         // Release the registry entry: otherwise every distinct never-created
         // project key ever mentioned in a PUT would permanently occupy one.
         drop(lock);
         state.release_project_lock_if_unused(&project_key);
         return Err(ApiError::NotFound);
-    }
+    };
+
+    // Every served variant must be one the flag declares, so the value served
+    // always has the flag's type.
+    body.validate_for(&flag)
+        .map_err(|e| ApiError::InvalidBody(e.to_string()))?;
 
     let existing = state
         .store
@@ -119,6 +127,14 @@ pub async fn put_flag_env_config<S: Store>(
         .map_err(ApiError::from)?;
 
     recompile_committed(&state, &project_key, &affected).await;
+    warn_if_flapping(
+        &state.store,
+        &project_key,
+        &flag_key,
+        &env_key,
+        state.flapping,
+    )
+    .await;
 
     let etag = compute_etag(&body)?;
     let status = if is_create {
@@ -181,10 +197,43 @@ pub async fn delete_flag_env_config<S: Store>(
         .map_err(ApiError::from)?;
 
     recompile_committed(&state, &project_key, &affected).await;
+    warn_if_flapping(
+        &state.store,
+        &project_key,
+        &flag_key,
+        &env_key,
+        state.flapping,
+    )
+    .await;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// `GET /projects/{project}/flapping` -- flag configurations changed more
+/// often than the flapping threshold allows within its window.
+pub async fn get_flapping_flags<S: Store>(
+    State(state): State<AppState<S>>,
+    _principal: AdminPrincipal,
+    Path(project): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+    state
+        .store
+        .get_project(&project_key)
+        .await
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::NotFound)?;
+
+    let flags = detect(&state.store, &project_key, state.flapping)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(Json(FlappingReport {
+        max_changes: state.flapping.max_changes,
+        window_secs: state.flapping.window.as_secs(),
+        flags,
+    }))
+}
+
 fn response_with_body<T: serde::Serialize>(
     status: StatusCode,
     body: &T,
@@ -0,0 +1,32 @@
+//! Readiness probe (public).
+
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+
+use crate::state::{AppState, Store};
+
+/// `GET /readyz` - checks every backend of [`AppState::health`].
+///
+/// Answers `200 OK` when all are healthy and `503 Service Unavailable`
+/// otherwise, with the [`HealthReport`] as body either way. Failure details
+/// are logged, not returned.
+///
+/// [`HealthReport`]: crate::health::HealthReport
+pub async fn get_readyz<S: Store>(State(state): State<AppState<S>>) -> impl IntoResponse {
+    let report = state.health.report().await;
+    for component in &report.components {
+        if let Some(error) = &component.error {
+            tracing::warn!(
+                component = %component.name,
+                latency_ms = component.latency_ms,
+                %error,
+                "readiness check failed"
+            );
+        }
+    }
+    let status = if report.is_healthy() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
@@ -4,6 +4,7 @@ pub mod auth;
 pub mod environment;
 pub mod flag;
 pub mod flag_env_config;
+pub mod health;
 pub mod ofrep;
 pub mod project;
 pub mod sdk;
@@ -11,14 +11,39 @@
 //! ## Atomicity guarantee
 //!
 //! The cache is a `HashMap` wrapped in a `RwLock`. Each evaluation acquires a
-//! read guard, clones the required fields (`document`, `content_hash`, `version`),
+//! read guard, clones the required fields (`document`, cache key, `version`),
 //! then releases the guard before parsing. `install_in_cache` acquires a write guard
 //! and replaces the entire entry atomically. A request therefore observes either the
 //! previous complete ruleset or the new complete ruleset; it can never see a partial
 //! update.
+//!
+//! ## Exposures
+//!
+//! When [`AppState::exposure_sampler`] is set, each successful evaluation the
+//! sampler keeps is logged as an `info` event on the `flaps::exposure` target,
+//! carrying the project, environment, flag, variant, reason, ruleset version
+//! and `sample_rate`. Sampling never affects the evaluation itself.
+//!
+//! ## Geolocation
+//!
+//! When [`AppState::geo`] is set, the context is enriched with the location
+//! of the connection address after its size is checked; see [`crate::geo`].
+//!
+//! ## Evaluation cache
+//!
+//! When [`AppState::evaluation_cache`] is set and lists the SDK key's
+//! environment, successful evaluations are served from it for repeated
+//! (flag, context, ruleset version) inputs; see [`crate::eval_cache`].
+//! Exposures are still logged for cached results.
+//!
+//! ## Context sampling
+//!
+//! When [`AppState::context_sample`] is set, each validated, geolocated
+//! context is recorded, redacted, into its project's sample for segment
+//! previews; see [`crate::context_sample`].
 
 use std::collections::BTreeMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use axum::{
     Json,
@@ -26,13 +51,19 @@ use axum::{
     http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
-use flaps_eval::{EvaluationContext, EvaluationError, FlagSet, Reason};
+use flaps_eval::{
+    EvaluationContext, EvaluationError, ExposureSampler, FlagSet, RULESET_VERSION_METADATA, Reason,
+    Resolution,
+};
+use flaps_store::SdkKeyScope;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
     auth::SdkKeyPrincipal,
     error::ApiError,
+    eval_cache::{ContextHash, EvaluationCache, EvaluationCacheKey},
+    preauth::client_address::ClientAddress,
     state::{AppState, Store},
 };
 
@@ -70,6 +101,12 @@ pub enum OfrRepReason {
     Default,
     /// The flag is disabled; the provider serves its own code default.
     Disabled,
+    /// The flag's kill switch is engaged; served like [`Self::Disabled`].
+    /// OFREP reasons are open-ended: this one is Flaps-specific.
+    Killed,
+    /// The client's app version is older than the flag's minimum, or
+    /// unknown; served like [`Self::Disabled`]. Flaps-specific as well.
+    VersionGated,
 }
 
 /// OFREP error codes.
@@ -98,7 +135,8 @@ pub struct SingleSuccessResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub variant: Option<String>,
     /// Flag-set and flag metadata merged (flag entries win on collision),
-    /// omitted entirely when empty.
+    /// plus the ruleset version under `flaps.version` on OFREP responses.
+    /// Omitted entirely when empty.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Map<String, Value>>,
 }
@@ -106,7 +144,9 @@ pub struct SingleSuccessResponse {
 /// Converts a [`Resolution`]'s metadata to the OFREP DTO field: `None` when
 /// empty, `Some` otherwise. Reuses `flaps_eval::metadata_to_json` as the
 /// single source of truth for the JSON conversion.
-fn metadata_field(metadata: &flaps_eval::Metadata) -> Option<serde_json::Map<String, Value>> {
+pub(crate) fn metadata_field(
+    metadata: &flaps_eval::Metadata,
+) -> Option<serde_json::Map<String, Value>> {
     if metadata.is_empty() {
         return None;
     }
@@ -177,16 +217,19 @@ pub(crate) fn map_reason(reason: Reason) -> OfrRepReason {
         Reason::TargetingMatch => OfrRepReason::TargetingMatch,
         Reason::Default => OfrRepReason::Default,
         Reason::Disabled => OfrRepReason::Disabled,
+        Reason::Killed => OfrRepReason::Killed,
+        Reason::VersionGated => OfrRepReason::VersionGated,
     }
 }
 
-/// Extracts an [`EvaluationContext`] from the request DTO.
-fn build_context(dto: Option<ContextDto>) -> EvaluationContext {
+/// Extracts an [`EvaluationContext`] from the request DTO, stamped with the
+/// current time rounded down to `quantum`.
+pub(crate) fn build_context(dto: Option<ContextDto>, quantum: Duration) -> EvaluationContext {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
-    match dto {
+    let context = match dto {
         None => EvaluationContext {
             targeting_key: None,
             attributes: BTreeMap::new(),
@@ -203,7 +246,8 @@ fn build_context(dto: Option<ContextDto>) -> EvaluationContext {
                 timestamp: now,
             }
         }
-    }
+    };
+    context.with_timestamp_quantum(quantum)
 }
 
 /// Maps an [`EvaluationError`] on a single flag to an HTTP status + OFREP error body.
@@ -217,7 +261,11 @@ fn single_eval_error_response(key: &str, err: EvaluationError) -> Response {
             };
             (StatusCode::NOT_FOUND, Json(body)).into_response()
         }
-        EvaluationError::InvalidVariant { .. } | EvaluationError::UnsupportedOperation { .. } => {
+        EvaluationError::InvalidVariant { .. }
+        | EvaluationError::UnsupportedOperation { .. }
+        | EvaluationError::BudgetExceeded { .. }
+        | EvaluationError::UnresolvedEvaluator { .. }
+        | EvaluationError::TypeMismatch { .. } => {
             let body = SingleErrorResponse {
                 key: key.to_owned(),
                 error_code: OfrRepErrorCode::General,
@@ -228,12 +276,12 @@ fn single_eval_error_response(key: &str, err: EvaluationError) -> Response {
     }
 }
 
-/// Formats a strong ETag value from a content hash.
+/// Formats a strong ETag value from a ruleset cache key.
 ///
-/// Aligns with the format used in [`crate::etag`]: the hash is wrapped in double
+/// Aligns with the format used in [`crate::etag`]: the key is wrapped in double
 /// quotes as required by RFC 7232.
-fn format_etag(content_hash: &str) -> String {
-    format!("\"{content_hash}\"")
+fn format_etag(cache_key: &str) -> String {
+    format!("\"{cache_key}\"")
 }
 
 /// Checks the `If-None-Match` header against the current ETag.
@@ -274,19 +322,117 @@ fn rate_limited_response(retry_after: u64) -> Response {
     response
 }
 
+/// Logs the sampled exposures of one evaluation request.
+struct ExposureLog<'a> {
+    sampler: Option<&'a ExposureSampler>,
+    scope: &'a SdkKeyScope,
+    version: u64,
+}
+
+impl ExposureLog<'_> {
+    /// Emits a `flaps::exposure` event when the sampler keeps this exposure.
+    fn record(&self, flag_key: &str, ctx: &EvaluationContext, resolution: &Resolution) {
+        let Some(sampler) = self.sampler else {
+            return;
+        };
+        if !sampler.sampled(flag_key, ctx) {
+            return;
+        }
+        tracing::info!(
+            target: "flaps::exposure",
+            project = self.scope.project_key.as_str(),
+            environment = self.scope.environment_key.as_str(),
+            flag = flag_key,
+            variant = resolution.variant.as_deref(),
+            reason = ?resolution.reason,
+            version = self.version,
+            sample_rate = sampler.rate(),
+            "exposure"
+        );
+    }
+}
+
+/// Evaluates the flags of one request, through the evaluation cache when the
+/// request's environment opted in.
+struct FlagEvaluator<'a> {
+    flag_set: &'a FlagSet,
+    ctx: &'a EvaluationContext,
+    cache: Option<(&'a EvaluationCache, ContextHash)>,
+    scope: &'a SdkKeyScope,
+    version: u64,
+}
+
+impl<'a> FlagEvaluator<'a> {
+    /// Hashes `ctx` once when `cache` is enabled for the scope's environment.
+    fn new(
+        flag_set: &'a FlagSet,
+        ctx: &'a EvaluationContext,
+        cache: Option<&'a EvaluationCache>,
+        scope: &'a SdkKeyScope,
+        version: u64,
+    ) -> Self {
+        let cache = cache
+            .filter(|cache| cache.enabled_for(&scope.environment_key))
+            .map(|cache| (cache, ContextHash::of(ctx)));
+        Self {
+            flag_set,
+            ctx,
+            cache,
+            scope,
+            version,
+        }
+    }
+
+    fn evaluate(&self, flag_key: &str) -> Result<Resolution, EvaluationError> {
+        let evaluate = || self.flag_set.evaluate(flag_key, self.ctx);
+        let Some((cache, context)) = self.cache else {
+            return evaluate();
+        };
+        let key = EvaluationCacheKey {
+            project: self.scope.project_key.clone(),
+            environment: self.scope.environment_key.clone(),
+            flag_key: flag_key.to_owned(),
+            context,
+            version: self.version,
+        };
+        cache.get_or_evaluate(key, evaluate)
+    }
+}
+
+/// Builds the success body of a resolution, its metadata carrying the
+/// `version` of the ruleset it was evaluated against under
+/// [`RULESET_VERSION_METADATA`].
+fn success_response(key: String, resolution: Resolution, version: u64) -> SingleSuccessResponse {
+    let mut metadata = metadata_field(&resolution.metadata).unwrap_or_default();
+    metadata.insert(RULESET_VERSION_METADATA.to_owned(), Value::from(version));
+    SingleSuccessResponse {
+        key,
+        value: resolution.value,
+        reason: map_reason(resolution.reason),
+        variant: resolution.variant,
+        metadata: Some(metadata),
+    }
+}
+
 /// Evaluates all flags in a [`FlagSet`] against `ctx` and returns the bulk entries.
-fn evaluate_all_flags(flag_set: &FlagSet, ctx: &EvaluationContext) -> Vec<BulkFlagEntry> {
-    flag_set
+fn evaluate_all_flags(
+    evaluator: &FlagEvaluator<'_>,
+    ctx: &EvaluationContext,
+    exposures: &ExposureLog<'_>,
+) -> Vec<BulkFlagEntry> {
+    evaluator
+        .flag_set
         .flags
         .keys()
-        .map(|flag_key| match flag_set.evaluate(flag_key, ctx) {
-            Ok(resolution) => BulkFlagEntry::Success(SingleSuccessResponse {
-                key: flag_key.clone(),
-                value: resolution.value,
-                reason: map_reason(resolution.reason),
-                variant: resolution.variant,
-                metadata: metadata_field(&resolution.metadata),
-            }),
+        .map(|flag_key| match evaluator.evaluate(flag_key) {
+            Ok(resolution) => {
+                exposures.record(flag_key, ctx, &resolution);
+                BulkFlagEntry::Success(success_response(
+                    flag_key.clone(),
+                    resolution,
+                    evaluator.version,
+                ))
+            }
             Err(EvaluationError::FlagNotFound { flag_key: fk }) => {
                 BulkFlagEntry::Error(SingleErrorResponse {
                     key: flag_key.clone(),
@@ -311,6 +457,13 @@ fn evaluate_all_flags(flag_set: &FlagSet, ctx: &EvaluationContext) -> Vec<BulkFl
 ///
 /// Authenticated via SDK key (server or client kind). Rate-limited per key prefix.
 /// Reads from the in-memory cache; the database is never queried on this path.
+/// An unknown key is answered from the compiled ruleset too, so a stale flag
+/// key left in code costs no store lookup and needs no negative cache: the
+/// recompile that follows the flag's creation makes it resolvable.
+///
+/// A success carries the version of the ruleset it was evaluated against in
+/// `X-Flaps-Version` and in its `flaps.version` metadata entry, so an
+/// exposure can be attributed to the exact configuration that produced it.
 ///
 /// ## OFREP 0.3.0 status codes
 /// - 200 serverEvaluationSuccess
@@ -323,6 +476,7 @@ pub async fn post_evaluate_flag<S: Store>(
     State(state): State<AppState<S>>,
     principal: Result<SdkKeyPrincipal, (StatusCode, ApiError)>,
     Path(key): Path<String>,
+    address: ClientAddress,
     body: Result<Json<EvaluationRequest>, axum::extract::rejection::JsonRejection>,
 ) -> Response {
     // 1. Authenticate.
@@ -354,12 +508,13 @@ pub async fn post_evaluate_flag<S: Store>(
 
     let entry = {
         let cache = state.cache.read().await;
-        cache
-            .get(&(project_key.clone(), env_key.clone()))
-            .map(|r| (r.document.clone(), r.content_hash.clone(), r.version))
+        cache.get(&(project_key.clone(), env_key.clone())).map(|c| {
+            let r = &c.ruleset;
+            (r.document.clone(), r.content_hash.clone(), r.version)
+        })
     };
 
-    let Some((document, _, _)) = entry else {
+    let Some((document, _, version)) = entry else {
         return (
             StatusCode::NOT_FOUND,
             Json(SingleErrorResponse {
@@ -384,20 +539,49 @@ pub async fn post_evaluate_flag<S: Store>(
             .into_response();
     };
 
-    // 6. Build evaluation context.
-    let ctx = build_context(request.context);
+    // 6. Build evaluation context, check its size, geolocate the caller and
+    //    sample it.
+    let mut ctx = build_context(request.context, state.timestamp_quantum);
+    if let Err(err) = ctx.validate_limits(&state.context_limits) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SingleErrorResponse {
+                key: key.clone(),
+                error_code: OfrRepErrorCode::InvalidContext,
+                error_details: err.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    if let Some(geo) = &state.geo {
+        geo.enrich(&mut ctx, address);
+    }
+    if let Some(sample) = &state.context_sample {
+        sample.record(&project_key, &ctx);
+    }
 
-    // 7. Evaluate.
-    match flag_set.evaluate(&key, &ctx) {
+    // 7. Evaluate, through the evaluation cache when enabled.
+    let evaluator = FlagEvaluator::new(
+        &flag_set,
+        &ctx,
+        state.evaluation_cache.as_deref(),
+        &principal.scope,
+        version,
+    );
+    match evaluator.evaluate(&key) {
         Ok(resolution) => {
-            let body = SingleSuccessResponse {
-                key: key.clone(),
-                value: resolution.value,
-                reason: map_reason(resolution.reason),
-                variant: resolution.variant,
-                metadata: metadata_field(&resolution.metadata),
-            };
-            (StatusCode::OK, Json(body)).into_response()
+            ExposureLog {
+                sampler: state.exposure_sampler.as_ref(),
+                scope: &principal.scope,
+                version,
+            }
+            .record(&key, &ctx, &resolution);
+            let body = success_response(key.clone(), resolution, version);
+            let mut response = (StatusCode::OK, Json(body)).into_response();
+            if let Ok(v) = HeaderValue::from_str(&version.to_string()) {
+                response.headers_mut().insert("X-Flaps-Version", v);
+            }
+            response
         }
         Err(err) => single_eval_error_response(&key, err),
     }
@@ -406,11 +590,14 @@ pub async fn post_evaluate_flag<S: Store>(
 /// `POST /ofrep/v1/evaluate/flags` - bulk evaluate all flags.
 ///
 /// Authenticated via SDK key (server or client kind). Rate-limited per key prefix.
-/// Supports `If-None-Match` / 304 short-circuit based on the ruleset `content_hash`.
-/// The `ETag` response header is always set on 200.
+/// Supports `If-None-Match` / 304 short-circuit based on the ruleset cache key
+/// (version and content hash, see [`flaps_compiler::CompiledRuleset::cache_key`]).
+/// The `ETag` response header is always set on 200, with the ruleset version
+/// in `X-Flaps-Version`, `metadata.version` and every flag's `flaps.version`
+/// metadata entry.
 ///
 /// ## OFREP 0.3.0 status codes
-/// - 200 bulkEvaluationSuccess (ETag header)
+/// - 200 bulkEvaluationSuccess (ETag and X-Flaps-Version headers)
 /// - 304 Not Modified (no body)
 /// - 400 bulkEvaluationFailure (`INVALID_CONTEXT`)
 /// - 401 unauthorized
@@ -420,6 +607,7 @@ pub async fn post_evaluate_flags<S: Store>(
     State(state): State<AppState<S>>,
     principal: Result<SdkKeyPrincipal, (StatusCode, ApiError)>,
     headers: HeaderMap,
+    address: ClientAddress,
     body: Result<Json<EvaluationRequest>, axum::extract::rejection::JsonRejection>,
 ) -> Response {
     // 1. Authenticate.
@@ -450,13 +638,14 @@ pub async fn post_evaluate_flags<S: Store>(
 
     let entry = {
         let cache = state.cache.read().await;
-        cache
-            .get(&(project_key.clone(), env_key.clone()))
-            .map(|r| (r.document.clone(), r.content_hash.clone(), r.version))
+        cache.get(&(project_key.clone(), env_key.clone())).map(|c| {
+            let r = &c.ruleset;
+            (r.document.clone(), r.cache_key(), r.version)
+        })
     };
 
     // 5. Missing cache entry: return empty flags array.
-    let Some((document, content_hash, version)) = entry else {
+    let Some((document, cache_key, version)) = entry else {
         let body = BulkSuccessResponse {
             flags: vec![],
             metadata: BulkMetadata {
@@ -467,7 +656,7 @@ pub async fn post_evaluate_flags<S: Store>(
     };
 
     // 6. ETag / 304 short-circuit.
-    let etag = format_etag(&content_hash);
+    let etag = format_etag(&cache_key);
     if is_not_modified(&headers, &etag) {
         return StatusCode::NOT_MODIFIED.into_response();
     }
@@ -484,9 +673,38 @@ pub async fn post_evaluate_flags<S: Store>(
             .into_response();
     };
 
-    // 8. Build evaluation context and evaluate all flags.
-    let ctx = build_context(request.context);
-    let flags = evaluate_all_flags(&flag_set, &ctx);
+    // 8. Build evaluation context, check its size, geolocate the caller,
+    //    sample it and evaluate all flags.
+    let mut ctx = build_context(request.context, state.timestamp_quantum);
+    if let Err(err) = ctx.validate_limits(&state.context_limits) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(EvaluationFailureResponse {
+                error_code: OfrRepErrorCode::InvalidContext,
+                error_details: err.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    if let Some(geo) = &state.geo {
+        geo.enrich(&mut ctx, address);
+    }
+    if let Some(sample) = &state.context_sample {
+        sample.record(&project_key, &ctx);
+    }
+    let exposures = ExposureLog {
+        sampler: state.exposure_sampler.as_ref(),
+        scope: &principal.scope,
+        version,
+    };
+    let evaluator = FlagEvaluator::new(
+        &flag_set,
+        &ctx,
+        state.evaluation_cache.as_deref(),
+        &principal.scope,
+        version,
+    );
+    let flags = evaluate_all_flags(&evaluator, &ctx, &exposures);
 
     // 9. Build response with ETag header.
     let response_body = BulkSuccessResponse {
@@ -500,6 +718,9 @@ pub async fn post_evaluate_flags<S: Store>(
     if let Ok(v) = HeaderValue::from_str(&etag) {
         response.headers_mut().insert(header::ETAG, v);
     }
+    if let Ok(v) = HeaderValue::from_str(&version.to_string()) {
+        response.headers_mut().insert("X-Flaps-Version", v);
+    }
     response
 }
 
@@ -537,6 +758,8 @@ mod tests {
     #[test]
     fn reason_disabled_maps_to_ofrep_disabled() {
         assert_eq!(map_reason(Reason::Disabled), OfrRepReason::Disabled);
+        assert_eq!(map_reason(Reason::Killed), OfrRepReason::Killed);
+        assert_eq!(map_reason(Reason::VersionGated), OfrRepReason::VersionGated);
     }
 
     // -------------------------------------------------------------------------
@@ -549,7 +772,7 @@ mod tests {
             targeting_key: Some("user-123".to_owned()),
             attributes: BTreeMap::new(),
         });
-        let ctx = build_context(dto);
+        let ctx = build_context(dto, Duration::ZERO);
         assert_eq!(ctx.targeting_key.as_deref(), Some("user-123"));
     }
 
@@ -561,16 +784,24 @@ mod tests {
             targeting_key: None,
             attributes: attrs,
         });
-        let ctx = build_context(dto);
+        let ctx = build_context(dto, Duration::ZERO);
         assert_eq!(
             ctx.attributes.get("tier"),
             Some(&Value::String("beta".to_owned()))
         );
     }
 
+    #[test]
+    fn build_context_keeps_null_attributes() {
+        let dto: ContextDto =
+            serde_json::from_str(r#"{"targetingKey": "user-1", "phone": null}"#).unwrap();
+        let ctx = build_context(Some(dto), Duration::ZERO);
+        assert_eq!(ctx.attributes.get("phone"), Some(&Value::Null));
+    }
+
     #[test]
     fn build_context_none_produces_empty_context() {
-        let ctx = build_context(None);
+        let ctx = build_context(None, Duration::ZERO);
         assert!(ctx.targeting_key.is_none());
         assert!(ctx.attributes.is_empty());
     }
@@ -587,11 +818,18 @@ mod tests {
             targeting_key: Some("user-abc".to_owned()),
             attributes: attrs,
         });
-        let ctx = build_context(dto);
+        let ctx = build_context(dto, Duration::ZERO);
         assert!(!ctx.attributes.contains_key("targetingKey"));
         assert!(ctx.attributes.contains_key("other"));
     }
 
+    #[test]
+    fn build_context_rounds_the_timestamp_down_to_the_quantum() {
+        let ctx = build_context(None, Duration::from_secs(3_600));
+        assert_eq!(ctx.timestamp % 3_600, 0);
+        assert!(ctx.timestamp > 0);
+    }
+
     // -------------------------------------------------------------------------
     // ETag helpers
     // -------------------------------------------------------------------------
@@ -650,5 +888,13 @@ mod tests {
             serde_json::to_string(&OfrRepReason::Disabled).unwrap(),
             "\"DISABLED\""
         );
+        assert_eq!(
+            serde_json::to_string(&OfrRepReason::Killed).unwrap(),
+            "\"KILLED\""
+        );
+        assert_eq!(
+            serde_json::to_string(&OfrRepReason::VersionGated).unwrap(),
+            "\"VERSION_GATED\""
+        );
     }
 }
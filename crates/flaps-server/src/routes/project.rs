@@ -200,6 +200,9 @@ pub async fn delete_project<S: Store>(
 
         // Evict all (project, *) entries from cache.
         evict_project_from_cache(&state, &project_key).await;
+        if let Some(sample) = &state.context_sample {
+            sample.forget(&project_key);
+        }
 
         Ok(())
     }
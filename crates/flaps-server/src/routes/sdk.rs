@@ -18,6 +18,33 @@ pub struct WhoamiResponse {
     pub scope: SdkKeyScopeDto,
     /// Server or client SDK kind.
     pub kind: SdkKeyKind,
+    /// What the key may do within its scope, so an SDK can fail fast on a
+    /// key of the wrong kind.
+    pub permissions: Vec<SdkPermission>,
+}
+
+/// A capability an SDK key grants within its scope.
+///
+/// No SDK key ever grants admin access: the admin API takes session tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SdkPermission {
+    /// Evaluate flags through the OFREP endpoints.
+    Evaluate,
+    /// Download the compiled ruleset and subscribe to its changes through
+    /// the `/sync/v1/*` routes.
+    Sync,
+}
+
+impl SdkPermission {
+    /// The capabilities a key of `kind` grants.
+    #[must_use]
+    pub fn granted_to(kind: SdkKeyKind) -> Vec<Self> {
+        match kind {
+            SdkKeyKind::Server => vec![Self::Evaluate, Self::Sync],
+            SdkKeyKind::Client => vec![Self::Evaluate],
+        }
+    }
 }
 
 /// Serializable DTO for [`SdkKeyScope`].
@@ -39,6 +66,9 @@ impl From<SdkKeyScope> for SdkKeyScopeDto {
 }
 
 /// `GET /sdk/whoami` - identify the SDK key and check rate limit.
+///
+/// Answers with the key's scope, kind and permissions, or 401 for a
+/// missing, unknown or revoked key.
 pub async fn get_whoami<S: Store>(
     State(state): State<AppState<S>>,
     principal: Result<SdkKeyPrincipal, (StatusCode, ApiError)>,
@@ -56,5 +86,6 @@ pub async fn get_whoami<S: Store>(
     Ok(Json(WhoamiResponse {
         scope: principal.scope.into(),
         kind: principal.kind,
+        permissions: SdkPermission::granted_to(principal.kind),
     }))
 }
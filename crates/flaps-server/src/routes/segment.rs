@@ -2,33 +2,63 @@
 
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State, rejection::QueryRejection},
     http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
-use flaps_domain::{ProjectKey, Segment, SegmentKey};
+use flaps_compiler::{SEGMENT_PROBE_FLAG, SegmentMembership, compile_segment_probe};
+use flaps_domain::{EnvironmentKey, FlagEnvConfig, FlagKey, ProjectKey, Segment, SegmentKey};
+use flaps_eval::{EvaluationContext, EvaluationError, FlagSet};
+use flaps_store::repository::WriteSession as _;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     auth::AdminPrincipal,
     error::ApiError,
     etag::{check_if_match, check_if_none_match, compute_etag, read_precondition_header},
+    pagination::PageParams,
     recompile::{Change, recompile_committed, validate_by_compiling},
+    routes::ofrep::{ContextDto, build_context},
     state::{AppState, Store},
 };
 
-/// `GET /projects/{project}/segments` -- list all segments in a project.
+/// `GET /projects/{project}/segments` -- list all segments in a project, with
+/// an ETag over the whole list, or one page of them (see
+/// [`crate::pagination`]), without ETag.
+///
+/// Segments come back with their full match expressions, and the ETag changes
+/// whenever any of them is added, edited or deleted.
 pub async fn list_segments<S: Store>(
     State(state): State<AppState<S>>,
     _principal: AdminPrincipal,
     Path(project): Path<String>,
-) -> Result<Json<Vec<Segment>>, ApiError> {
+    params: Result<Query<PageParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::InvalidBody(e.body_text()))?;
     let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
-    let segments = state
+    if let Some(request) = params.request()? {
+        let page = state
+            .store
+            .page_segments(&project_key, &request)
+            .await
+            .map_err(ApiError::from)?;
+        return Ok(Json(page).into_response());
+    }
+    let mut segments = state
         .store
         .list_segments(&project_key)
         .await
         .map_err(ApiError::from)?;
-    Ok(Json(segments))
+    // The store lists in no particular order; sort so the ETag is stable.
+    segments.sort_by(|a, b| a.key.as_str().cmp(b.key.as_str()));
+
+    let etag = compute_etag(&segments)?;
+    let mut response = Json(segments).into_response();
+    response.headers_mut().insert(
+        header::ETAG,
+        HeaderValue::from_str(&etag).map_err(|e| ApiError::Internal(e.to_string()))?,
+    );
+    Ok(response)
 }
 
 /// `GET /projects/{project}/segments/{segment}` -- fetch a single segment with ETag.
@@ -62,7 +92,7 @@ pub async fn put_segment<S: Store>(
     principal: AdminPrincipal,
     Path((project, segment)): Path<(String, String)>,
     headers: HeaderMap,
-    Json(body): Json<Segment>,
+    Json(mut body): Json<Segment>,
 ) -> Result<impl IntoResponse, ApiError> {
     let actor = principal.username;
     let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
@@ -74,6 +104,10 @@ pub async fn put_segment<S: Store>(
         ));
     }
 
+    // Explicit user lists are stored sorted and deduplicated, so exports and
+    // ETags do not depend on how a client happened to order them.
+    body.match_expr.normalize_user_ids();
+
     // Hold the per-project lock for the whole cycle (issues #105, #108).
     let lock = state.lock_project(&project_key).await;
 
@@ -108,6 +142,14 @@ pub async fn put_segment<S: Store>(
     let if_none_match = read_precondition_header(&headers, &header::IF_NONE_MATCH)?;
     check_if_none_match(if_none_match.as_deref(), existing.is_some())?;
 
+    // A segment no flag references yet is never compiled below, so its depth
+    // is checked up front: an over-deep segment is refused on create, not
+    // only once a rule starts using it.
+    state
+        .limits
+        .check_segment(segment_key.as_str(), &body.match_expr)
+        .map_err(ApiError::Validation)?;
+
     // Compile-as-validation: recompile all envs referencing this segment with the new definition.
     let rulesets =
         validate_by_compiling(&state, &project_key, &Change::UpsertSegment(&body)).await?;
@@ -135,17 +177,44 @@ pub async fn put_segment<S: Store>(
     Ok(response)
 }
 
+/// What deleting a segment does to the flag rules that still reference it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentDeleteStrategy {
+    /// Refuse the deletion while any rule references the segment.
+    #[default]
+    Block,
+    /// Remove the segment from the referencing rules, dropping rules left
+    /// without any segment (see [`FlagEnvConfig::detach_segment`]).
+    DetachRules,
+    /// Delete anyway: rules referencing the segment can no longer match and
+    /// are removed (see [`FlagEnvConfig::remove_rules_with_segment`]).
+    Force,
+}
+
+/// Query parameters of `DELETE /projects/{project}/segments/{segment}`.
+#[derive(Debug, Deserialize)]
+pub struct DeleteSegmentParams {
+    /// How referencing rules are handled; [`SegmentDeleteStrategy::Block`]
+    /// when omitted.
+    #[serde(default)]
+    pub strategy: SegmentDeleteStrategy,
+}
+
 /// `DELETE /projects/{project}/segments/{segment}` -- delete a segment.
 ///
-/// If any environment's flags still reference this segment, the compile
-/// will fail with `UnknownSegment` and the deletion will be refused (400).
+/// `?strategy=` selects what happens to flag rules still referencing the
+/// segment (see [`SegmentDeleteStrategy`]). Under the default `block`, the
+/// compile fails with `UnknownSegment` and the deletion is refused (400).
 pub async fn delete_segment<S: Store>(
     State(state): State<AppState<S>>,
     principal: AdminPrincipal,
     Path((project, segment)): Path<(String, String)>,
+    params: Result<Query<DeleteSegmentParams>, QueryRejection>,
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, ApiError> {
     let actor = principal.username;
+    let Query(params) = params.map_err(|e| ApiError::InvalidBody(e.body_text()))?;
     let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
     let segment_key = SegmentKey::new(segment).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
 
@@ -170,20 +239,98 @@ pub async fn delete_segment<S: Store>(
         return Err(ApiError::NotFound);
     }
 
-    // If any env still references this segment, compilation will fail -> 400, deletion refused.
-    let rulesets =
-        validate_by_compiling(&state, &project_key, &Change::DeleteSegment(&segment_key)).await?;
-    let affected: Vec<_> = rulesets.into_iter().map(|r| r.environment).collect();
+    let affected =
+        delete_segment_safe(&state, &actor, &project_key, &segment_key, params.strategy).await?;
 
-    state
-        .store
-        .delete_segment(&actor, &project_key, &segment_key)
+    recompile_committed(&state, &project_key, &affected).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes a segment according to `strategy` and returns the environments
+/// whose ruleset must be recompiled.
+///
+/// Under `detach_rules` and `force`, the rewritten flag configs and the
+/// deletion are written in one transaction attributed to `actor`, so each
+/// rewrite is audited and the segment is never deleted with a referencing
+/// rule left behind.
+async fn delete_segment_safe<S: Store>(
+    state: &AppState<S>,
+    actor: &str,
+    project: &ProjectKey,
+    segment: &SegmentKey,
+    strategy: SegmentDeleteStrategy,
+) -> Result<Vec<EnvironmentKey>, ApiError> {
+    if strategy == SegmentDeleteStrategy::Block {
+        // If any env still references this segment, compilation will fail -> 400, deletion refused.
+        let rulesets =
+            validate_by_compiling(state, project, &Change::DeleteSegment(segment)).await?;
+        state
+            .store
+            .delete_segment(actor, project, segment)
+            .await
+            .map_err(ApiError::from)?;
+        return Ok(rulesets.into_iter().map(|r| r.environment).collect());
+    }
+
+    let rewritten = rewrite_referencing_configs(state, project, segment, strategy).await?;
+    let mut session = state.store.begin(actor).await.map_err(ApiError::from)?;
+    for (flag, environment, config) in &rewritten {
+        session
+            .upsert_flag_env_config(project, flag, environment, config)
+            .await
+            .map_err(ApiError::from)?;
+    }
+    session
+        .delete_segment(project, segment)
         .await
         .map_err(ApiError::from)?;
+    session.commit().await.map_err(ApiError::from)?;
 
-    recompile_committed(&state, &project_key, &affected).await;
+    let mut affected: Vec<EnvironmentKey> = rewritten.into_iter().map(|(_, env, _)| env).collect();
+    affected.dedup();
+    Ok(affected)
+}
 
-    Ok(StatusCode::NO_CONTENT)
+/// Returns every flag config referencing `segment`, rewritten per `strategy`.
+async fn rewrite_referencing_configs<S: Store>(
+    state: &AppState<S>,
+    project: &ProjectKey,
+    segment: &SegmentKey,
+    strategy: SegmentDeleteStrategy,
+) -> Result<Vec<(FlagKey, EnvironmentKey, FlagEnvConfig)>, ApiError> {
+    let environments = state
+        .store
+        .list_environments(project)
+        .await
+        .map_err(ApiError::from)?;
+    let flags = state
+        .store
+        .list_flags(project)
+        .await
+        .map_err(ApiError::from)?;
+    let mut rewritten = Vec::new();
+    for environment in environments {
+        for flag in &flags {
+            let Some(mut config) = state
+                .store
+                .get_flag_env_config(project, &flag.key, &environment.key)
+                .await
+                .map_err(ApiError::from)?
+            else {
+                continue;
+            };
+            let changed = match strategy {
+                SegmentDeleteStrategy::Block => false,
+                SegmentDeleteStrategy::DetachRules => config.detach_segment(segment),
+                SegmentDeleteStrategy::Force => config.remove_rules_with_segment(segment),
+            };
+            if changed {
+                rewritten.push((flag.key.clone(), environment.key.clone(), config));
+            }
+        }
+    }
+    Ok(rewritten)
 }
 
 fn response_with_body<T: serde::Serialize>(
@@ -198,3 +345,175 @@ fn response_with_body<T: serde::Serialize>(
         .body(axum::body::Body::from(bytes))
         .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response()))
 }
+
+/// Sample size under which a segment preview is flagged as unreliable.
+const MIN_RELIABLE_PREVIEW_SAMPLE: usize = 100;
+
+/// Result of `POST /projects/{project}/segment-preview`.
+#[derive(Debug, Serialize)]
+pub struct SegmentPreviewResponse {
+    /// Number of sampled contexts the segment was evaluated against.
+    pub sample_size: usize,
+    /// Number of sampled contexts in the segment.
+    pub matched: usize,
+    /// `matched / sample_size`, omitted when the sample is empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_rate: Option<f64>,
+    /// Half-width of a conservative 95% confidence interval around
+    /// `match_rate`, omitted when the sample is empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin_of_error: Option<f64>,
+    /// How far the estimate can be trusted, in words.
+    pub note: String,
+}
+
+impl SegmentPreviewResponse {
+    /// Evaluates `probe` (see [`compile_segment_probe`]) against `contexts`.
+    fn estimate(probe: &FlagSet, contexts: &[EvaluationContext]) -> Result<Self, EvaluationError> {
+        let mut matched = 0;
+        for context in contexts {
+            let resolution = probe.evaluate(SEGMENT_PROBE_FLAG, context)?;
+            if resolution.variant.as_deref() == Some("true") {
+                matched += 1;
+            }
+        }
+        let sample_size = contexts.len();
+        if sample_size == 0 {
+            return Ok(Self::empty(
+                "No evaluation context has been sampled for this project yet.",
+            ));
+        }
+        #[allow(clippy::cast_precision_loss)]
+        // Sample sizes are bounded by the configured capacity, far below 2^52.
+        let (n, k) = (sample_size as f64, matched as f64);
+        // Worst case of the normal approximation (p = 0.5): the margin
+        // depends on the sample size only, and never claims a false certainty
+        // for rates near 0 or 1.
+        let margin_of_error = 1.96 * (0.25 / n).sqrt();
+        let note = if sample_size < MIN_RELIABLE_PREVIEW_SAMPLE {
+            format!(
+                "Estimated from only {sample_size} recent contexts: too few for a reliable \
+                 estimate (within {:.1} percentage points at 95% confidence).",
+                margin_of_error * 100.0
+            )
+        } else {
+            format!(
+                "Estimated from {sample_size} recent contexts: within {:.1} percentage points \
+                 at 95% confidence.",
+                margin_of_error * 100.0
+            )
+        };
+        Ok(Self {
+            sample_size,
+            matched,
+            match_rate: Some(k / n),
+            margin_of_error: Some(margin_of_error),
+            note,
+        })
+    }
+
+    fn empty(note: &str) -> Self {
+        Self {
+            sample_size: 0,
+            matched: 0,
+            match_rate: None,
+            margin_of_error: None,
+            note: note.to_owned(),
+        }
+    }
+}
+
+/// `POST /projects/{project}/segment-preview` -- estimate how many recent
+/// callers a proposed segment would match.
+///
+/// Evaluates the segment in the body against the project's sample of recent
+/// OFREP contexts (see [`crate::context_sample`]), without storing it. The
+/// segment is validated as on `PUT`: an invalid predicate or an over-deep
+/// expression is a `validation-error`. When the server samples no contexts,
+/// the response has an empty sample and says so.
+pub async fn post_segment_preview<S: Store>(
+    State(state): State<AppState<S>>,
+    _principal: AdminPrincipal,
+    Path(project): Path<String>,
+    Json(body): Json<Segment>,
+) -> Result<Json<SegmentPreviewResponse>, ApiError> {
+    let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+
+    state
+        .store
+        .get_project(&project_key)
+        .await
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::NotFound)?;
+
+    let probe = compile_segment_probe(&body.key, &body.match_expr, &state.limits)
+        .map_err(ApiError::Validation)?;
+
+    let Some(sample) = &state.context_sample else {
+        return Ok(Json(SegmentPreviewResponse::empty(
+            "Evaluation context sampling is disabled on this server.",
+        )));
+    };
+    let contexts = sample.contexts(&project_key);
+    SegmentPreviewResponse::estimate(&probe, &contexts)
+        .map(Json)
+        .map_err(|e| ApiError::Internal(e.to_string()))
+}
+
+/// Request body for `POST /projects/{project}/segment-matches`.
+#[derive(Debug, Deserialize)]
+pub struct SegmentMatchesRequest {
+    /// Evaluation context, in the OFREP shape.
+    pub context: Option<ContextDto>,
+}
+
+/// Response body for `POST /projects/{project}/segment-matches`.
+#[derive(Debug, Serialize)]
+pub struct SegmentMatchesResponse {
+    /// Keys of the segments the context belongs to, sorted.
+    pub segments: Vec<SegmentKey>,
+}
+
+/// `POST /projects/{project}/segment-matches` -- list the segments of a
+/// project one context belongs to.
+///
+/// A debugging aid, the inverse of flag targeting: every stored segment is
+/// compiled as it would be inside a rule (see [`SegmentMembership`]) and
+/// evaluated against the context, independently of any flag or environment.
+pub async fn post_segment_matches<S: Store>(
+    State(state): State<AppState<S>>,
+    _principal: AdminPrincipal,
+    Path(project): Path<String>,
+    Json(body): Json<SegmentMatchesRequest>,
+) -> Result<Json<SegmentMatchesResponse>, ApiError> {
+    let project_key = ProjectKey::new(project).map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+
+    state
+        .store
+        .get_project(&project_key)
+        .await
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::NotFound)?;
+
+    let context = build_context(body.context, state.timestamp_quantum);
+    context
+        .validate_limits(&state.context_limits)
+        .map_err(|e| ApiError::InvalidBody(e.to_string()))?;
+
+    let segments = state
+        .store
+        .list_segments(&project_key)
+        .await
+        .map_err(ApiError::from)?;
+    let membership = SegmentMembership::compile(
+        segments
+            .iter()
+            .map(|segment| (&segment.key, &segment.match_expr)),
+        &state.limits,
+    )
+    .map_err(ApiError::Validation)?;
+    let segments = membership
+        .matching(&context)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(SegmentMatchesResponse { segments }))
+}
@@ -4,16 +4,23 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex as StdMutex};
 use std::time::Duration;
 
+use axum::body::Bytes;
 use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard, RwLock, broadcast};
 
-use flaps_compiler::CompiledRuleset;
+use flaps_compiler::{CompiledRuleset, Limits};
 use flaps_domain::{EnvironmentKey, ProjectKey};
+use flaps_eval::{ContextLimits, ExposureSampler, FlagSet};
 use flaps_store::repository::{
     AccountRepository, AuditLogRepository, EnvironmentRepository, FlagEnvConfigRepository,
-    FlagRepository, ProjectRepository, SdkKeyRepository, SegmentRepository, SessionRepository,
-    TransactionalStore,
+    FlagRepository, HealthRepository, ProjectRepository, SdkKeyRepository, SegmentRepository,
+    SessionRepository, TransactionalStore,
 };
 
+use crate::context_sample::{ContextSample, ContextSampleConfig};
+use crate::eval_cache::{EvaluationCache, EvaluationCacheConfig};
+use crate::flapping::FlappingConfig;
+use crate::geo::{GeoLookup, GeoResolver};
+use crate::health::{DatabaseCheck, HealthCheck, SystemHealth};
 use crate::preauth::budget::{PreAuthBudget, PreAuthBudgetConfig};
 use crate::preauth::password_pool::PasswordVerificationPool;
 use crate::rate_limit::{RateLimitConfig, RateLimiter};
@@ -35,6 +42,7 @@ pub trait Store:
     + AccountRepository
     + SessionRepository
     + TransactionalStore
+    + HealthRepository
     + Clone
     + Send
     + Sync
@@ -53,6 +61,7 @@ impl<T> Store for T where
         + AccountRepository
         + SessionRepository
         + TransactionalStore
+        + HealthRepository
         + Clone
         + Send
         + Sync
@@ -61,7 +70,46 @@ impl<T> Store for T where
 }
 
 /// Compiled ruleset cache keyed by (project, environment).
-pub type CompiledCache = Arc<RwLock<HashMap<(ProjectKey, EnvironmentKey), CompiledRuleset>>>;
+///
+/// Each entry is one flagd document holding the environment's flags and the
+/// segments they reference (`$evaluators`), so a refresh replaces both in a
+/// single insert: an evaluation never sees a flag without its segments.
+pub type CompiledCache = Arc<RwLock<HashMap<(ProjectKey, EnvironmentKey), CachedRuleset>>>;
+
+/// A [`CompiledRuleset`] as held in the [`CompiledCache`].
+///
+/// The binary snapshot served by `GET /sync/v1/ruleset` is encoded once, when
+/// the ruleset is installed, rather than on every request.
+#[derive(Debug, Clone)]
+pub struct CachedRuleset {
+    /// The compiled ruleset.
+    pub ruleset: CompiledRuleset,
+    /// The ruleset encoded by `flaps_eval::FlagSet::to_snapshot`.
+    ///
+    /// `None` only when encoding failed, which canonical compiler output never
+    /// does; the snapshot representation is then answered with a 500.
+    pub snapshot: Option<Bytes>,
+}
+
+impl CachedRuleset {
+    /// Wraps `ruleset`, encoding its binary snapshot.
+    #[must_use]
+    pub fn new(ruleset: CompiledRuleset) -> Self {
+        let snapshot = match FlagSet::json_to_snapshot(&ruleset.document) {
+            Ok(bytes) => Some(Bytes::from(bytes)),
+            Err(err) => {
+                tracing::error!(
+                    environment = %ruleset.environment,
+                    version = ruleset.version,
+                    error = %err,
+                    "compiled ruleset could not be encoded as a snapshot"
+                );
+                None
+            }
+        };
+        Self { ruleset, snapshot }
+    }
+}
 
 /// Default session TTL in seconds (24 hours).
 ///
@@ -171,6 +219,39 @@ pub struct AppState<S: Store> {
     /// Concurrency quota bounding live `GET /sync/v1/events` subscriptions,
     /// per SDK key and globally (see issue #111).
     pub sse_quota: Arc<SseQuota>,
+    /// Structural limits enforced when compiling flags and segments.
+    pub limits: Limits,
+    /// Granularity of the `$flagd.timestamp` seen by OFREP evaluations.
+    ///
+    /// The evaluation clock is rounded down to a multiple of this interval so
+    /// instances with slightly skewed clocks agree on time-based rules (see
+    /// [`EvaluationContext::with_timestamp_quantum`]). [`Duration::ZERO`], the
+    /// default, keeps whole-second resolution.
+    ///
+    /// [`EvaluationContext::with_timestamp_quantum`]: flaps_eval::EvaluationContext::with_timestamp_quantum
+    pub timestamp_quantum: Duration,
+    /// Size and cardinality ceilings for OFREP evaluation contexts; an
+    /// over-limit context is refused with `INVALID_CONTEXT`.
+    pub context_limits: ContextLimits,
+    /// Sampling of the `flaps::exposure` events logged for OFREP
+    /// evaluations. `None`, the default, logs no exposures.
+    pub exposure_sampler: Option<ExposureSampler>,
+    /// Geolocation of OFREP callers, added to evaluation contexts as the
+    /// `country`, `region` and `city` attributes. `None`, the default,
+    /// leaves contexts as sent.
+    pub geo: Option<Arc<GeoLookup>>,
+    /// Cache of OFREP evaluation results, for the environments it lists.
+    /// `None`, the default, evaluates every request.
+    pub evaluation_cache: Option<Arc<EvaluationCache>>,
+    /// Rolling, redacted sample of recent OFREP contexts per project, read by
+    /// segment previews. `None`, the default, samples nothing.
+    pub context_sample: Option<Arc<ContextSample>>,
+    /// Thresholds of flapping detection, read by `GET /projects/{project}/flapping`
+    /// and checked after each flag configuration write.
+    pub flapping: FlappingConfig,
+    /// Backends checked by `GET /readyz`: the store, plus any added with
+    /// [`Self::with_health_check`].
+    pub health: SystemHealth,
     /// Per-project mutation locks, keyed by project.
     ///
     /// See [`Self::lock_project`] for the concurrency contract and the
@@ -186,6 +267,7 @@ impl<S: Store> AppState<S> {
     #[must_use]
     pub fn new(store: S) -> Self {
         let (events, _) = broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+        let health = SystemHealth::new().with_check(DatabaseCheck(store.clone()));
         Self {
             store,
             cache: Arc::new(RwLock::new(HashMap::new())),
@@ -218,6 +300,15 @@ impl<S: Store> AppState<S> {
                 max_global: DEFAULT_MAX_SSE_SUBSCRIPTIONS_GLOBAL,
                 max_per_key: DEFAULT_MAX_SSE_SUBSCRIPTIONS_PER_KEY,
             })),
+            limits: Limits::default(),
+            timestamp_quantum: Duration::ZERO,
+            context_limits: ContextLimits::default(),
+            exposure_sampler: None,
+            geo: None,
+            evaluation_cache: None,
+            context_sample: None,
+            flapping: FlappingConfig::default(),
+            health,
             mutation_locks: Arc::new(StdMutex::new(HashMap::new())),
         }
     }
@@ -235,6 +326,7 @@ impl<S: Store> AppState<S> {
         session_ttl: Duration,
     ) -> Self {
         let (events, _) = broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+        let health = SystemHealth::new().with_check(DatabaseCheck(store.clone()));
         Self {
             store,
             cache: Arc::new(RwLock::new(HashMap::new())),
@@ -259,6 +351,15 @@ impl<S: Store> AppState<S> {
                 max_global: DEFAULT_MAX_SSE_SUBSCRIPTIONS_GLOBAL,
                 max_per_key: DEFAULT_MAX_SSE_SUBSCRIPTIONS_PER_KEY,
             })),
+            limits: Limits::default(),
+            timestamp_quantum: Duration::ZERO,
+            context_limits: ContextLimits::default(),
+            exposure_sampler: None,
+            geo: None,
+            evaluation_cache: None,
+            context_sample: None,
+            flapping: FlappingConfig::default(),
+            health,
             mutation_locks: Arc::new(StdMutex::new(HashMap::new())),
         }
     }
@@ -276,6 +377,91 @@ impl<S: Store> AppState<S> {
         self
     }
 
+    /// Overrides the default compile-time structural [`Limits`].
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply the configured rule,
+    /// condition and segment depth ceilings.
+    #[must_use]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Overrides the default evaluation [`Self::timestamp_quantum`].
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply the configured quantum.
+    #[must_use]
+    pub fn with_timestamp_quantum(mut self, quantum: Duration) -> Self {
+        self.timestamp_quantum = quantum;
+        self
+    }
+
+    /// Overrides the default OFREP [`Self::context_limits`].
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply the configured ceilings.
+    #[must_use]
+    pub fn with_context_limits(mut self, limits: ContextLimits) -> Self {
+        self.context_limits = limits;
+        self
+    }
+
+    /// Enables exposure logging with the given [`Self::exposure_sampler`].
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply the configured rate.
+    #[must_use]
+    pub fn with_exposure_sampler(mut self, sampler: ExposureSampler) -> Self {
+        self.exposure_sampler = Some(sampler);
+        self
+    }
+
+    /// Enables geolocation of OFREP callers through `resolver`, behind a
+    /// [`GeoLookup`] cache.
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply `geoip_database`.
+    #[must_use]
+    pub fn with_geo_resolver(mut self, resolver: impl GeoResolver) -> Self {
+        self.geo = Some(Arc::new(GeoLookup::new(resolver)));
+        self
+    }
+
+    /// Enables the OFREP [`Self::evaluation_cache`] for the environments
+    /// listed in `config`.
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply `evaluation_cache_environments`.
+    #[must_use]
+    pub fn with_evaluation_cache(mut self, config: EvaluationCacheConfig) -> Self {
+        self.evaluation_cache = Some(Arc::new(EvaluationCache::new(config)));
+        self
+    }
+
+    /// Enables the [`Self::context_sample`] read by segment previews.
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply `context_sample_size`
+    /// and `private_attributes`.
+    #[must_use]
+    pub fn with_context_sample(mut self, config: ContextSampleConfig) -> Self {
+        self.context_sample = Some(Arc::new(ContextSample::new(config)));
+        self
+    }
+
+    /// Overrides the default [`Self::flapping`] detection thresholds.
+    ///
+    /// Used by `flapsd_lib::config::Config` to apply `flapping_max_changes`
+    /// and `flapping_window_secs`.
+    #[must_use]
+    pub fn with_flapping(mut self, config: FlappingConfig) -> Self {
+        self.flapping = config;
+        self
+    }
+
+    /// Adds `check` to the backends reported by `GET /readyz`, with the
+    /// [`SystemHealth::DEFAULT_CHECK_TIMEOUT`].
+    #[must_use]
+    pub fn with_health_check(mut self, check: impl HealthCheck) -> Self {
+        self.health = self.health.with_check(check);
+        self
+    }
+
     /// Acquires the per-project mutation lock, creating it on first use.
     ///
     /// # Single-writer assumption
@@ -530,4 +716,28 @@ mod tests {
             "once the extra reference is gone too, release must remove the entry"
         );
     }
+
+    #[test]
+    fn cached_ruleset_carries_the_encoded_snapshot() {
+        let ruleset = flaps_compiler::CompiledRuleset {
+            environment: flaps_domain::EnvironmentKey::new("prod").unwrap(),
+            document: r#"{"flags":{}}"#.to_owned(),
+            content_hash: "hash".to_owned(),
+            version: 1,
+        };
+        let cached = super::CachedRuleset::new(ruleset);
+        let snapshot = cached.snapshot.expect("canonical document encodes");
+        assert!(flaps_eval::FlagSet::from_snapshot(&snapshot).is_ok());
+    }
+
+    #[test]
+    fn cached_ruleset_without_a_valid_document_has_no_snapshot() {
+        let ruleset = flaps_compiler::CompiledRuleset {
+            environment: flaps_domain::EnvironmentKey::new("prod").unwrap(),
+            document: "not json".to_owned(),
+            content_hash: "hash".to_owned(),
+            version: 1,
+        };
+        assert!(super::CachedRuleset::new(ruleset).snapshot.is_none());
+    }
 }
@@ -8,6 +8,16 @@
 //!   [`EventPayload`] per recompilation. The payload carries only the environment
 //!   key and the new version; it never exposes flag data.
 //!
+//! ## Resuming a stream
+//!
+//! Every frame carries the version as its SSE `id`. A subscriber reconnecting
+//! with `Last-Event-ID` set to the version it holds gets no catch-up frame
+//! when that is still the current version; otherwise, and on a first
+//! connection, the stream opens with a frame announcing the current version,
+//! so a client learns of a change it missed while disconnected without
+//! refetching the ruleset on every reconnect. The full state itself is always
+//! the `GET /sync/v1/ruleset` download (notify-then-fetch).
+//!
 //! ## Ordering invariant
 //!
 //! Each event is emitted **after** the corresponding ruleset is written to the
@@ -28,10 +38,14 @@ use axum::{
     },
 };
 use flaps_domain::{EnvironmentKey, ProjectKey, SdkKeyKind};
+use flaps_eval::SNAPSHOT_MEDIA_TYPE;
 use serde::Serialize;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::{Stream, StreamExt};
 
+/// Header a reconnecting SSE client sets to the last event id it received.
+const LAST_EVENT_ID: &str = "last-event-id";
+
 use crate::{
     auth::SdkKeyPrincipal,
     error::ApiError,
@@ -54,6 +68,8 @@ pub struct SyncEvent {
     pub environment: EnvironmentKey,
     /// Monotone version of the newly installed ruleset.
     pub version: u64,
+    /// Cache key (`{version}-{content_hash}`) of the newly installed ruleset.
+    pub cache_key: String,
 }
 
 // ---------------------------------------------------------------------------
@@ -76,9 +92,57 @@ pub struct EventPayload {
 // Shared helpers
 // ---------------------------------------------------------------------------
 
-/// Formats a strong ETag value (quoted content hash) for the `ETag` header.
-fn format_etag(content_hash: &str) -> String {
-    format!("\"{content_hash}\"")
+/// Formats a strong ETag value (quoted ruleset cache key) for the `ETag` header.
+fn format_etag(cache_key: &str) -> String {
+    format!("\"{cache_key}\"")
+}
+
+/// Formats the strong ETag of the binary snapshot representation.
+///
+/// Distinct from the JSON ETag: the two representations differ byte-wise, so
+/// a cache keyed on the ETag must never serve one for the other.
+fn format_snapshot_etag(cache_key: &str) -> String {
+    format!("\"{cache_key}-snapshot\"")
+}
+
+/// Builds the SSE frame announcing `version` of `environment`, with the
+/// ruleset cache key as its event id.
+///
+/// The id is the cache key rather than the bare version: versions restart
+/// with the server process, so only the key names a ruleset unambiguously
+/// across restarts and replicas.
+fn notification(environment: &EnvironmentKey, version: u64, cache_key: &str) -> Option<Event> {
+    let payload = EventPayload {
+        environment: environment.as_str().to_owned(),
+        version,
+    };
+    Event::default().id(cache_key).json_data(&payload).ok()
+}
+
+/// Returns the ruleset cache key the client resumes from, from
+/// `Last-Event-ID`.
+///
+/// An absent or non-ASCII id reads as `None`: the client holds nothing.
+fn last_event_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(LAST_EVENT_ID)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+}
+
+/// Returns `true` when the client lists the binary snapshot media type in `Accept`.
+fn accepts_snapshot(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|range| {
+            range
+                .split(';')
+                .next()
+                .is_some_and(|media| media.trim().eq_ignore_ascii_case(SNAPSHOT_MEDIA_TYPE))
+        })
 }
 
 /// Returns `true` when the client `If-None-Match` header matches `etag` exactly.
@@ -108,16 +172,25 @@ fn require_server_key(kind: SdkKeyKind) -> Result<(), ApiError> {
 /// Requires a server-kind SDK key. Client keys receive 403.
 ///
 /// ## Caching
-/// The `ETag` header carries the `content_hash` of the ruleset. Clients should
+/// The `ETag` header carries the ruleset cache key, its version and content
+/// hash (see [`flaps_compiler::CompiledRuleset::cache_key`]). Clients should
 /// send `If-None-Match` on subsequent requests; the server returns 304 when the
-/// ruleset is unchanged.
+/// ruleset is unchanged. A version bump always changes the key, so a client
+/// holding the key of an older version misses even if the content reverted.
+///
+/// ## Representations
+/// JSON is the default. A client listing `application/x-flaps-snapshot` in
+/// `Accept` receives the binary snapshot (see `flaps_eval::FlagSet::to_snapshot`)
+/// instead, under its own ETag.
 ///
 /// ## Extra headers
-/// - `ETag`: strong ETag based on the content hash.
+/// - `ETag`: strong ETag based on the content hash and the representation.
 /// - `X-Flaps-Version`: monotone version counter of the ruleset.
+/// - `Vary: accept`.
 ///
 /// ## Status codes
-/// - 200: ruleset JSON body (`application/json`).
+/// - 200: ruleset JSON body (`application/json`) or binary snapshot
+///   (`application/x-flaps-snapshot`).
 /// - 304: not modified (no body).
 /// - 401: missing or invalid SDK key.
 /// - 403: client-kind SDK key.
@@ -148,30 +221,54 @@ pub async fn get_ruleset<S: Store>(
 
     let entry = {
         let cache = state.cache.read().await;
-        cache
-            .get(&(project_key, env_key))
-            .map(|r| (r.document.clone(), r.content_hash.clone(), r.version))
+        cache.get(&(project_key, env_key)).map(|c| {
+            let r = &c.ruleset;
+            (
+                r.document.clone(),
+                c.snapshot.clone(),
+                r.cache_key(),
+                r.version,
+            )
+        })
     };
 
-    let Some((document, content_hash, version)) = entry else {
+    let Some((document, snapshot, cache_key, version)) = entry else {
         return Err(ApiError::NotFound);
     };
 
-    // 5. ETag / 304 short-circuit.
-    let etag = format_etag(&content_hash);
+    // 5. ETag / 304 short-circuit, per negotiated representation.
+    let binary = accepts_snapshot(&headers);
+    let etag = if binary {
+        format_snapshot_etag(&cache_key)
+    } else {
+        format_etag(&cache_key)
+    };
     if is_not_modified(&headers, &etag) {
         return Ok(StatusCode::NOT_MODIFIED.into_response());
     }
 
     // 6. Build 200 response with ETag + X-Flaps-Version headers.
-    let mut response = (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "application/json")],
-        document,
-    )
-        .into_response();
+    let mut response = if binary {
+        // Encoded once when the ruleset was installed in the cache.
+        let snapshot =
+            snapshot.ok_or_else(|| ApiError::Internal("snapshot encoding failed".to_owned()))?;
+        (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, SNAPSHOT_MEDIA_TYPE)],
+            snapshot,
+        )
+            .into_response()
+    } else {
+        (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            document,
+        )
+            .into_response()
+    };
 
     let response_headers = response.headers_mut();
+    response_headers.insert(header::VARY, HeaderValue::from_static("accept"));
     if let Ok(v) = HeaderValue::from_str(&etag) {
         response_headers.insert(header::ETAG, v);
     }
@@ -227,7 +324,14 @@ impl Stream for QuotaBoundStream {
 ///
 /// ## Stream format
 /// Each SSE frame carries a JSON-encoded [`EventPayload`] with `environment`
-/// and `version`. No flag data is ever included.
+/// and `version`, and the ruleset cache key (`{version}-{content_hash}`) as
+/// its `id`. No flag data is ever included.
+///
+/// ## Catch-up frame
+/// When the scope has a compiled ruleset, the stream opens with a frame
+/// announcing it, unless `Last-Event-ID` already names its cache key. An
+/// announcement of that same ruleset, still in flight on the bus when the
+/// stream opened, is dropped rather than sent twice.
 ///
 /// ## Lag handling
 /// If a subscriber cannot keep up with the broadcast buffer, lagged ticks are
@@ -254,6 +358,7 @@ impl Stream for QuotaBoundStream {
 pub async fn get_events<S: Store>(
     State(state): State<AppState<S>>,
     principal: Result<SdkKeyPrincipal, (StatusCode, ApiError)>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, ApiError> {
     // 1. Authenticate.
     let principal = principal.map_err(|(_, e)| e)?;
@@ -298,13 +403,29 @@ pub async fn get_events<S: Store>(
             }
         })?;
 
-    // 4. Subscribe BEFORE releasing the principal (no async gap).
+    // 4. Subscribe BEFORE releasing the principal (no async gap), and
+    //    before reading the current ruleset: an install racing this request
+    //    is then either already in the cache or still ahead on the bus.
     let rx = state.events.subscribe();
     let scope_project = principal.scope.project_key.clone();
     let scope_env = principal.scope.environment_key.clone();
-
-    // 5. Build the filtered event stream.
-    let stream = BroadcastStream::new(rx).filter_map(move |result| {
+    let current = state
+        .cache
+        .read()
+        .await
+        .get(&(scope_project.clone(), scope_env.clone()))
+        .map(|c| (c.ruleset.version, c.ruleset.cache_key()));
+
+    // 5. Open with the catch-up frame, unless the client already holds the
+    //    current ruleset.
+    let catch_up = current
+        .as_ref()
+        .filter(|(_, cache_key)| last_event_id(&headers) != Some(cache_key.as_str()))
+        .and_then(|(version, cache_key)| notification(&scope_env, *version, cache_key));
+    let current_key = current.map(|(_, cache_key)| cache_key);
+
+    // 6. Build the filtered event stream.
+    let changes = BroadcastStream::new(rx).filter_map(move |result| {
         match result {
             // Lagged: skip, do not terminate the stream.
             Err(_) => None,
@@ -312,19 +433,18 @@ pub async fn get_events<S: Store>(
                 if ev.project != scope_project || ev.environment != scope_env {
                     return None;
                 }
-                let payload = EventPayload {
-                    environment: ev.environment.as_str().to_owned(),
-                    version: ev.version,
-                };
-                match Event::default().json_data(&payload) {
-                    Ok(sse_event) => Some(Ok::<_, std::convert::Infallible>(sse_event)),
-                    Err(_) => None,
+                // Already announced (or held) as the current ruleset.
+                if current_key.as_ref() == Some(&ev.cache_key) {
+                    return None;
                 }
+                notification(&ev.environment, ev.version, &ev.cache_key)
+                    .map(Ok::<_, std::convert::Infallible>)
             }
         }
     });
+    let stream = tokio_stream::iter(catch_up.map(Ok)).chain(changes);
 
-    // 6. The quota permit is moved into the stream: dropping the response
+    // 7. The quota permit is moved into the stream: dropping the response
     //    body (for any reason) drops this value and releases the permit.
     let bound_stream = QuotaBoundStream {
         inner: Box::pin(stream),
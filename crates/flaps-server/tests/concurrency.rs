@@ -15,7 +15,7 @@ use axum::{
 };
 use flaps_domain::{
     Environment, EnvironmentKey, Flag, FlagEnvConfig, FlagKey, FlagType, ManagedBy, Project,
-    ProjectKey, ServeTarget, ValueType, VariantKey, VariantValue, Variants,
+    ProjectKey, RolloutScope, ServeTarget, ValueType, VariantKey, VariantValue, Variants,
 };
 use flaps_server::{
     bootstrap_admin, build_router,
@@ -117,6 +117,7 @@ fn bool_environment(key: &str) -> Environment {
         external_ref: None,
         managed_by: ManagedBy::Local,
         metadata: flaps_domain::Metadata::new(),
+        inherits_from: None,
     }
 }
 
@@ -144,6 +145,12 @@ fn config_with_default(variant: &str) -> FlagEnvConfig {
         enabled: true,
         rules: vec![],
         default_rule: ServeTarget::Fixed(variant_key(variant)),
+        inherit_default_rule: false,
+        salt: None,
+        bucket_by: None,
+        rollout_scope: RolloutScope::Shared,
+        kill_reason: None,
+        min_app_version: None,
     }
 }
 
@@ -401,6 +408,7 @@ async fn assert_concurrent_put_flag_env_config_distinct_flags_both_present_in_ca
         cache
             .get(&(project_key(project), env_key(env)))
             .expect("cache must be populated by the setup PUTs")
+            .ruleset
             .version
     };
 
@@ -438,6 +446,7 @@ async fn assert_concurrent_put_flag_env_config_distinct_flags_both_present_in_ca
         cache
             .get(&(project_key(project), env_key(env)))
             .expect("cache entry must still exist after the concurrent updates")
+            .ruleset
             .clone()
     };
 
@@ -533,7 +542,7 @@ async fn delete_flag_with_env_config_recompiles_env_from_committed_state() {
         let ruleset = cache
             .get(&(project_key("proj"), env_key("prod")))
             .expect("cache populated after the config PUT");
-        let doc: serde_json::Value = serde_json::from_str(&ruleset.document).unwrap();
+        let doc: serde_json::Value = serde_json::from_str(&ruleset.ruleset.document).unwrap();
         assert!(
             doc["flags"].get("doomed-flag").is_some(),
             "sanity check: the flag must be present before deletion"
@@ -551,7 +560,7 @@ async fn delete_flag_with_env_config_recompiles_env_from_committed_state() {
     let ruleset = cache
         .get(&(project_key("proj"), env_key("prod")))
         .expect("environment must still be compiled (empty flag set), just without the flag");
-    let doc: serde_json::Value = serde_json::from_str(&ruleset.document).unwrap();
+    let doc: serde_json::Value = serde_json::from_str(&ruleset.ruleset.document).unwrap();
     assert!(
         doc["flags"].get("doomed-flag").is_none(),
         "the cached ruleset must be recompiled from committed store state after the delete, \
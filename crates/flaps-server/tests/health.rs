@@ -0,0 +1,127 @@
+//! `GET /readyz`: the store and every added backend are checked, and one
+//! failing or hanging backend makes the server unready.
+
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use flaps_server::{
+    build_router,
+    health::{CheckFuture, HealthCheck, HealthStatus, SystemHealth},
+    state::AppState,
+};
+use flaps_store::{hash::KeyHasher, sqlite::SqliteStore};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+/// A backend that answers with `outcome`, after `delay`.
+struct Stub {
+    name: &'static str,
+    delay: Duration,
+    outcome: Result<(), &'static str>,
+}
+
+impl HealthCheck for Stub {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn check(&self) -> CheckFuture<'_> {
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            self.outcome.map_err(str::to_owned)
+        })
+    }
+}
+
+async fn make_state() -> AppState<SqliteStore> {
+    let store = SqliteStore::in_memory(KeyHasher::new(b"test-pepper-32-bytes-long-enough"))
+        .await
+        .expect("in-memory store");
+    AppState::new(store)
+}
+
+async fn readyz(state: AppState<SqliteStore>) -> (StatusCode, serde_json::Value) {
+    let request = Request::get("/readyz").body(Body::empty()).unwrap();
+    let response = build_router(state).oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+#[tokio::test]
+async fn all_healthy_backends_make_the_server_ready() {
+    let state = make_state().await.with_health_check(Stub {
+        name: "cache",
+        delay: Duration::ZERO,
+        outcome: Ok(()),
+    });
+    let (status, body) = readyz(state).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "healthy");
+    let components = body["components"].as_array().unwrap();
+    assert_eq!(components.len(), 2, "{body}");
+    assert_eq!(components[0]["name"], "database");
+    assert_eq!(components[1]["name"], "cache");
+    for component in components {
+        assert_eq!(component["status"], "healthy", "{body}");
+        assert!(component["latency_ms"].is_u64(), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn one_failing_backend_makes_the_server_unready() {
+    let state = make_state().await.with_health_check(Stub {
+        name: "cache",
+        delay: Duration::ZERO,
+        outcome: Err("connection refused by 10.0.0.7"),
+    });
+    let (status, body) = readyz(state).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "unhealthy");
+    assert_eq!(body["components"][0]["status"], "healthy");
+    assert_eq!(body["components"][1]["status"], "unhealthy");
+    assert!(
+        !body.to_string().contains("10.0.0.7"),
+        "failure details must not leak: {body}"
+    );
+}
+
+#[tokio::test]
+async fn each_check_is_bounded_by_its_own_timeout() {
+    let health = SystemHealth::new()
+        .with_check_timeout(
+            Stub {
+                name: "slow",
+                delay: Duration::from_secs(30),
+                outcome: Ok(()),
+            },
+            Duration::from_millis(50),
+        )
+        .with_check_timeout(
+            Stub {
+                name: "patient",
+                delay: Duration::from_millis(100),
+                outcome: Ok(()),
+            },
+            Duration::from_secs(5),
+        );
+    let report = tokio::time::timeout(Duration::from_secs(5), health.report())
+        .await
+        .expect("a hanging check does not hang the report");
+
+    assert!(!report.is_healthy());
+    let slow = &report.components[0];
+    assert_eq!(slow.status, HealthStatus::Unhealthy);
+    assert_eq!(slow.error.as_deref(), Some("timed out after 50 ms"));
+    assert_eq!(report.components[1].status, HealthStatus::Healthy);
+}
+
+#[tokio::test]
+async fn an_empty_system_is_healthy() {
+    let report = SystemHealth::new().report().await;
+    assert!(report.is_healthy());
+    assert!(report.components.is_empty());
+}
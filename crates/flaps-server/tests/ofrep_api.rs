@@ -113,12 +113,20 @@ async fn make_store() -> SqliteStore {
 /// The SDK key resolution path requires a real key in the store, so we create
 /// a project+env+sdk key through the admin API first, then swap the cache.
 async fn make_app_with_ruleset(document: &str) -> (axum::Router, String) {
+    make_app_with_state(AppState::new, document).await
+}
+
+/// Same as [`make_app_with_ruleset`], with the app state built by `build`.
+async fn make_app_with_state(
+    build: impl FnOnce(SqliteStore) -> AppState<SqliteStore>,
+    document: &str,
+) -> (axum::Router, String) {
     let store = make_store().await;
     flaps_server::bootstrap_admin(&store, "admin", "admin-pass")
         .await
         .expect("bootstrap");
 
-    let state = AppState::new(store);
+    let state = build(store);
     let app = build_router(state.clone());
 
     // Login as admin.
@@ -760,6 +768,96 @@ async fn bulk_atomicity_concurrent_cache_swap() {
     swap_task.await.expect("swap task must not panic");
 }
 
+/// Flags and the segments they reference are swapped together: each cached
+/// ruleset is one document carrying both the flags and their `$evaluators`.
+/// A refresh task installs rulesets that each add a flag and the new segment
+/// it targets, while evaluator tasks query every flag. An evaluation sees a
+/// flag either not yet (404) or with its segment (a targeting match), never
+/// the flag without the segment.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn a_refresh_never_exposes_a_flag_without_its_segment() {
+    const REFRESHES: usize = 30;
+
+    /// The ruleset of refresh `n`: flags `flag-0` to `flag-n`, each targeting
+    /// its own segment.
+    fn ruleset(n: usize) -> String {
+        let mut flags = serde_json::Map::new();
+        let mut evaluators = serde_json::Map::new();
+        for i in 0..=n {
+            flags.insert(
+                format!("flag-{i}"),
+                serde_json::json!({
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": format!("seg-{i}")}, "on", null]},
+                }),
+            );
+            evaluators.insert(
+                format!("seg-{i}"),
+                serde_json::json!({"===": [{"var": "tier"}, "beta"]}),
+            );
+        }
+        serde_json::json!({"flags": flags, "$evaluators": evaluators}).to_string()
+    }
+
+    let mut state = None;
+    let (app, sdk_key) = make_app_with_state(
+        |store| {
+            // Unthrottled: the evaluators send far more than a minute's budget.
+            let built = AppState::with_config(
+                store,
+                Arc::new(flaps_server::rate_limit::RateLimiter::disabled()),
+                Arc::new(flaps_server::rate_limit::RateLimiter::disabled()),
+                std::time::Duration::from_secs(3600),
+            );
+            state = Some(built.clone());
+            built
+        },
+        &ruleset(0),
+    )
+    .await;
+    let state = state.expect("state built");
+
+    let refresh = tokio::spawn(async move {
+        for n in 1..REFRESHES {
+            install_in_cache(&state, &project_key(), vec![fake_ruleset(&ruleset(n))]).await;
+            tokio::task::yield_now().await;
+        }
+    });
+
+    let evaluators: Vec<_> = (0..3)
+        .map(|_| {
+            let (app, sdk_key) = (app.clone(), sdk_key.clone());
+            tokio::spawn(async move {
+                let ctx = serde_json::json!({"context": {"targetingKey": "u1", "tier": "beta"}});
+                for round in 0..5 {
+                    for i in 0..REFRESHES {
+                        let key = format!("flag-{i}");
+                        let resp = app
+                            .clone()
+                            .oneshot(ofrep_single_req(&key, &sdk_key, &ctx))
+                            .await
+                            .unwrap();
+                        if resp.status() == StatusCode::NOT_FOUND {
+                            continue;
+                        }
+                        assert_eq!(resp.status(), StatusCode::OK, "{key} (round {round})");
+                        let json = body_json(resp).await;
+                        assert_eq!(json["value"], serde_json::json!(true), "{key}: {json}");
+                        assert_eq!(json["reason"], "TARGETING_MATCH", "{key}: {json}");
+                    }
+                }
+            })
+        })
+        .collect();
+
+    refresh.await.expect("refresh task must not panic");
+    for evaluator in evaluators {
+        evaluator.await.expect("evaluator task must not panic");
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Metadata fusion tests (#55): flag and flag-set metadata, flag wins
 // ---------------------------------------------------------------------------
@@ -861,10 +959,10 @@ async fn bulk_evaluation_merges_flag_and_flag_set_metadata_flag_wins() {
     assert_eq!(entry["metadata"]["priority"], serde_json::json!(3));
 }
 
-/// A flag and environment with no metadata at all must omit the `metadata`
-/// field entirely from the OFREP response, not emit an empty object.
+/// A flag and environment with no metadata at all get only the ruleset
+/// version in the OFREP `metadata` field.
 #[tokio::test]
-async fn single_evaluation_omits_metadata_field_when_empty() {
+async fn single_evaluation_metadata_holds_only_the_version_when_flag_has_none() {
     let (app, sdk_key) = make_app_with_ruleset(FLAGD_DOC).await;
     let ctx = serde_json::json!({"context": {}});
     let resp = app
@@ -873,8 +971,460 @@ async fn single_evaluation_omits_metadata_field_when_empty() {
         .unwrap();
     assert_eq!(resp.status(), StatusCode::OK);
     let json = body_json(resp).await;
+    assert_eq!(json["metadata"], serde_json::json!({ "flaps.version": 1 }));
+}
+
+/// An SDK key resolves flags only within its own project: a flag of another
+/// project is `FLAG_NOT_FOUND`, even when the environment key is shared.
+#[tokio::test]
+async fn single_404_for_a_flag_of_another_project() {
+    let store = make_store().await;
+    flaps_server::bootstrap_admin(&store, "admin", "admin-pass")
+        .await
+        .expect("bootstrap");
+    let state = AppState::new(store);
+    let app = build_router(state.clone());
+
+    let token = admin_login(&app).await;
+    for proj in ["tenant-a", "tenant-b"] {
+        create_project(&app, proj, &token).await;
+        create_environment(&app, proj, "prod", &token).await;
+    }
+    create_flag_with_metadata(&app, "tenant-a", "secret", &token, serde_json::json!({})).await;
+    create_flag_env_config(&app, "tenant-a", "secret", "prod", &token, "on").await;
+    // Project B has a compiled ruleset of its own, so the 404 is a scope miss.
+    create_flag_with_metadata(&app, "tenant-b", "public", &token, serde_json::json!({})).await;
+    create_flag_env_config(&app, "tenant-b", "public", "prod", &token, "on").await;
+    let key_a = create_sdk_key(&app, "tenant-a", "prod", &token).await;
+    let key_b = create_sdk_key(&app, "tenant-b", "prod", &token).await;
+
+    let ctx = serde_json::json!({"context": {}});
+    let resp = app
+        .clone()
+        .oneshot(ofrep_single_req("secret", &key_a, &ctx))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app
+        .clone()
+        .oneshot(ofrep_single_req("public", &key_b, &ctx))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app
+        .oneshot(ofrep_single_req("secret", &key_b, &ctx))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        body_json(resp).await["errorCode"].as_str(),
+        Some("FLAG_NOT_FOUND")
+    );
+}
+
+/// An unknown flag is `FLAG_NOT_FOUND` on every lookup, answered from the
+/// compiled ruleset, and resolves as soon as the flag is created and wired.
+#[tokio::test]
+async fn unknown_flag_resolves_once_created() {
+    let store = make_store().await;
+    flaps_server::bootstrap_admin(&store, "admin", "admin-pass")
+        .await
+        .expect("bootstrap");
+    let state = AppState::new(store);
+    let app = build_router(state.clone());
+
+    let token = admin_login(&app).await;
+    create_project(&app, "late-proj", &token).await;
+    create_environment(&app, "late-proj", "prod", &token).await;
+    create_flag_with_metadata(&app, "late-proj", "present", &token, serde_json::json!({})).await;
+    create_flag_env_config(&app, "late-proj", "present", "prod", &token, "on").await;
+    let sdk_key = create_sdk_key(&app, "late-proj", "prod", &token).await;
+
+    let ctx = serde_json::json!({"context": {}});
+    for _ in 0..2 {
+        let resp = app
+            .clone()
+            .oneshot(ofrep_single_req("late-flag", &sdk_key, &ctx))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    create_flag_with_metadata(
+        &app,
+        "late-proj",
+        "late-flag",
+        &token,
+        serde_json::json!({}),
+    )
+    .await;
+    create_flag_env_config(&app, "late-proj", "late-flag", "prod", &token, "on").await;
+    let resp = app
+        .oneshot(ofrep_single_req("late-flag", &sdk_key, &ctx))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(body_json(resp).await["value"], serde_json::json!(true));
+}
+
+/// A context over the default size limits is refused with `INVALID_CONTEXT`
+/// naming the limit, on both endpoints, before any evaluation.
+#[tokio::test]
+async fn oversized_context_is_refused_as_invalid_context() {
+    let (app, sdk_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let oversized = serde_json::json!({"context": {"bio": "x".repeat(5_000)}});
+
+    let resp = app
+        .clone()
+        .oneshot(ofrep_single_req("feature-x", &sdk_key, &oversized))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let json = body_json(resp).await;
+    assert_eq!(json["errorCode"].as_str(), Some("INVALID_CONTEXT"));
+    assert!(
+        json["errorDetails"]
+            .as_str()
+            .unwrap()
+            .contains("max_value_length"),
+        "{json}"
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(ofrep_bulk_req(&sdk_key, &oversized))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body_json(resp).await["errorCode"].as_str(),
+        Some("INVALID_CONTEXT")
+    );
+
+    let ordinary = serde_json::json!({"context": {"targetingKey": "user-1", "bio": "hello"}});
+    let resp = app
+        .oneshot(ofrep_single_req("feature-x", &sdk_key, &ordinary))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+/// A single evaluation reports the ruleset version it was evaluated against,
+/// in its header and metadata, the same version the bulk endpoint reports in
+/// its header, body and flag metadata, and a config edit moves it.
+#[tokio::test]
+async fn evaluations_report_the_ruleset_version() {
+    let store = make_store().await;
+    flaps_server::bootstrap_admin(&store, "admin", "admin-pass")
+        .await
+        .expect("bootstrap");
+    let state = AppState::new(store);
+    let app = build_router(state.clone());
+
+    let token = admin_login(&app).await;
+    create_project(&app, "ver-proj", &token).await;
+    create_environment(&app, "ver-proj", "prod", &token).await;
+    create_flag_with_metadata(&app, "ver-proj", "checkout", &token, serde_json::json!({})).await;
+    create_flag_env_config(&app, "ver-proj", "checkout", "prod", &token, "on").await;
+    let sdk_key = create_sdk_key(&app, "ver-proj", "prod", &token).await;
+
+    let ctx = serde_json::json!({"context": {}});
+    let evaluate = || async {
+        let resp = app
+            .clone()
+            .oneshot(ofrep_single_req("checkout", &sdk_key, &ctx))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let version = resp.headers()["X-Flaps-Version"]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = body_json(resp).await;
+        assert_eq!(body["metadata"]["flaps.version"].to_string(), version);
+        (version, body["value"].clone())
+    };
+
+    let (before, value) = evaluate().await;
+    assert_eq!(value, serde_json::json!(true));
+    let bulk = app
+        .clone()
+        .oneshot(ofrep_bulk_req(&sdk_key, &ctx))
+        .await
+        .unwrap();
+    assert_eq!(bulk.headers()["X-Flaps-Version"], before.as_str());
+    let bulk = body_json(bulk).await;
+    assert_eq!(bulk["metadata"]["version"], before);
+    assert_eq!(
+        bulk["flags"][0]["metadata"]["flaps.version"].to_string(),
+        before
+    );
+
+    create_flag_env_config(&app, "ver-proj", "checkout", "prod", &token, "off").await;
+    let (after, value) = evaluate().await;
+    assert_eq!(value, serde_json::json!(false));
     assert!(
-        json.get("metadata").is_none(),
-        "metadata field must be omitted when empty, got: {json}"
+        after.parse::<u64>().unwrap() > before.parse::<u64>().unwrap(),
+        "{before} -> {after}"
     );
 }
+
+// ---------------------------------------------------------------------------
+// Geolocation
+// ---------------------------------------------------------------------------
+
+/// A flagd document serving `on` to callers located in France.
+const FLAGD_DOC_COUNTRY: &str = r#"{
+    "flags": {
+        "eu-banner": {
+            "state": "ENABLED",
+            "variants": {
+                "on": true,
+                "off": false
+            },
+            "defaultVariant": "off",
+            "targeting": {
+                "if": [
+                    { "===": [{ "var": "country" }, "FR"] },
+                    "on",
+                    null
+                ]
+            }
+        }
+    }
+}"#;
+
+/// Locates `192.0.2.33` in France; every other address is unknown, and
+/// `198.51.100.1` fails.
+struct StubGeoResolver;
+
+impl flaps_server::geo::GeoResolver for StubGeoResolver {
+    fn resolve(
+        &self,
+        address: std::net::IpAddr,
+    ) -> Result<Option<flaps_server::geo::GeoInfo>, flaps_server::geo::GeoError> {
+        match address.to_string().as_str() {
+            "192.0.2.33" => Ok(Some(flaps_server::geo::GeoInfo {
+                country: Some("FR".to_owned()),
+                region: Some("IDF".to_owned()),
+                city: Some("Paris".to_owned()),
+            })),
+            "198.51.100.1" => Err(flaps_server::geo::GeoError("database unavailable".into())),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Sends `request` as if it came from `address`.
+fn from_address(mut request: Request<Body>, address: &str) -> Request<Body> {
+    let address: std::net::SocketAddr = format!("{address}:40000").parse().unwrap();
+    request
+        .extensions_mut()
+        .insert(axum::extract::ConnectInfo(address));
+    request
+}
+
+async fn geo_app() -> (axum::Router, String) {
+    make_app_with_state(
+        |store| AppState::new(store).with_geo_resolver(StubGeoResolver),
+        FLAGD_DOC_COUNTRY,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn caller_country_enables_a_country_rule() {
+    let (app, sdk_key) = geo_app().await;
+    let ctx = serde_json::json!({"context": {"targetingKey": "u1"}});
+    let resp = app
+        .clone()
+        .oneshot(from_address(
+            ofrep_single_req("eu-banner", &sdk_key, &ctx),
+            "192.0.2.33",
+        ))
+        .await
+        .unwrap();
+    let json = body_json(resp).await;
+    assert_eq!(json["value"], serde_json::json!(true), "{json}");
+    assert_eq!(json["reason"].as_str(), Some("TARGETING_MATCH"));
+
+    let bulk = app
+        .oneshot(from_address(ofrep_bulk_req(&sdk_key, &ctx), "192.0.2.33"))
+        .await
+        .unwrap();
+    let json = body_json(bulk).await;
+    assert_eq!(json["flags"][0]["value"], serde_json::json!(true), "{json}");
+}
+
+#[tokio::test]
+async fn unknown_or_failing_geolocation_falls_back_to_the_default() {
+    let (app, sdk_key) = geo_app().await;
+    let ctx = serde_json::json!({"context": {"targetingKey": "u1"}});
+    for address in ["203.0.113.7", "198.51.100.1"] {
+        let resp = app
+            .clone()
+            .oneshot(from_address(
+                ofrep_single_req("eu-banner", &sdk_key, &ctx),
+                address,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{address}");
+        let json = body_json(resp).await;
+        assert_eq!(json["value"], serde_json::json!(false), "{address}: {json}");
+    }
+}
+
+#[tokio::test]
+async fn a_caller_supplied_country_wins_over_geolocation() {
+    let (app, sdk_key) = geo_app().await;
+    let ctx = serde_json::json!({"context": {"targetingKey": "u1", "country": "DE"}});
+    let resp = app
+        .oneshot(from_address(
+            ofrep_single_req("eu-banner", &sdk_key, &ctx),
+            "192.0.2.33",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(body_json(resp).await["value"], serde_json::json!(false));
+}
+
+// ---------------------------------------------------------------------------
+// Evaluation cache
+// ---------------------------------------------------------------------------
+
+/// With the evaluation cache enabled for the key's environment, a repeated
+/// evaluation is served from one cached entry, and a new ruleset version is
+/// evaluated afresh under a new key rather than served stale.
+#[tokio::test]
+async fn evaluation_cache_serves_repeats_and_misses_after_a_version_bump() {
+    let mut captured = None;
+    let (app, sdk_key) = make_app_with_state(
+        |store| {
+            let state = AppState::new(store).with_evaluation_cache(
+                flaps_server::eval_cache::EvaluationCacheConfig::for_environments([env_key()]),
+            );
+            captured = Some(state.clone());
+            state
+        },
+        FLAGD_DOC,
+    )
+    .await;
+    let state = captured.expect("state built");
+    let evaluation_cache = state.evaluation_cache.clone().expect("cache enabled");
+    let ctx = serde_json::json!({"context": {"country": "FR"}});
+
+    for _ in 0..2 {
+        let resp = app
+            .clone()
+            .oneshot(ofrep_single_req("feature-x", &sdk_key, &ctx))
+            .await
+            .unwrap();
+        assert_eq!(body_json(resp).await["value"], serde_json::json!(true));
+    }
+    assert_eq!(evaluation_cache.len(), 1);
+
+    let document = FLAGD_DOC.replacen(r#""defaultVariant": "on""#, r#""defaultVariant": "off""#, 1);
+    let mut ruleset = fake_ruleset(&document);
+    ruleset.version = 2;
+    install_in_cache(&state, &project_key(), vec![ruleset]).await;
+
+    let resp = app
+        .oneshot(ofrep_single_req("feature-x", &sdk_key, &ctx))
+        .await
+        .unwrap();
+    assert_eq!(body_json(resp).await["value"], serde_json::json!(false));
+    assert_eq!(evaluation_cache.len(), 2);
+}
+
+// ---------------------------------------------------------------------------
+// Segment preview
+// ---------------------------------------------------------------------------
+
+fn segment_preview_req(token: &str, attribute: &str, value: &str) -> Request<Body> {
+    let body = serde_json::json!({
+        "key": "preview",
+        "name": "Preview",
+        "match_expr": {
+            "predicate": {"attribute": attribute, "operator": "equals", "values": [value]}
+        },
+    });
+    Request::builder()
+        .method("POST")
+        .uri("/projects/test-proj/segment-preview")
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap()
+}
+
+/// A preview evaluates the proposed segment against the contexts OFREP
+/// callers sent, and sees them redacted: a private attribute is absent.
+#[tokio::test]
+async fn segment_preview_estimates_the_match_rate_from_sampled_contexts() {
+    let (app, sdk_key) = make_app_with_state(
+        |store| {
+            AppState::new(store).with_context_sample(
+                flaps_server::context_sample::ContextSampleConfig {
+                    capacity: 100,
+                    private_attributes: ["email".to_owned()].into(),
+                },
+            )
+        },
+        FLAGD_DOC,
+    )
+    .await;
+    for context in [
+        serde_json::json!({"plan": "pro", "email": "ada@example.com"}),
+        serde_json::json!({"plan": "pro"}),
+        serde_json::json!({"plan": "free"}),
+        serde_json::json!({"plan": "free"}),
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(ofrep_bulk_req(
+                &sdk_key,
+                &serde_json::json!({"context": context}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+    let token = admin_login(&app).await;
+
+    let resp = app
+        .clone()
+        .oneshot(segment_preview_req(&token, "plan", "pro"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp).await;
+    assert_eq!(json["sample_size"], 4);
+    assert_eq!(json["matched"], 2);
+    assert_eq!(json["match_rate"], 0.5);
+    assert!(json["margin_of_error"].as_f64().unwrap() > 0.0);
+    assert!(json["note"].as_str().unwrap().contains("too few"));
+
+    let resp = app
+        .oneshot(segment_preview_req(&token, "email", "ada@example.com"))
+        .await
+        .unwrap();
+    assert_eq!(body_json(resp).await["matched"], 0);
+}
+
+/// Without a configured sample, a preview answers with an empty sample
+/// rather than failing.
+#[tokio::test]
+async fn segment_preview_without_sampling_reports_an_empty_sample() {
+    let (app, _sdk_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let token = admin_login(&app).await;
+    let resp = app
+        .oneshot(segment_preview_req(&token, "plan", "pro"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp).await;
+    assert_eq!(json["sample_size"], 0);
+    assert!(json.get("match_rate").is_none());
+}
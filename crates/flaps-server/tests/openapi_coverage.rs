@@ -169,14 +169,14 @@ fn openapi_contract_matches_build_router_exactly() {
 
 #[test]
 fn build_router_exposes_the_expected_route_count() {
-    // Locks the known route count (28 operations) so an accidental drop in
+    // Locks the known route count (34 operations) so an accidental drop in
     // the AST extraction itself (e.g. a parsing regression) is caught even
     // if it happens to still match a stale contract.
     let routes = routes_from_code();
     assert_eq!(
         routes.len(),
-        28,
-        "expected exactly 28 (method, path) operations in build_router, found {}",
+        34,
+        "expected exactly 34 (method, path) operations in build_router, found {}",
         routes.len()
     );
 }
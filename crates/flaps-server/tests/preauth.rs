@@ -309,7 +309,8 @@ async fn impossible_and_absent_keys_look_the_same() {
 }
 
 /// Returns the status and the parsed problem body of a `GET /sdk/whoami`
-/// attempt carrying `key`.
+/// attempt carrying `key`. Every attempt sends the same `X-Request-Id`, so
+/// the echoed `request_id` is equal too.
 async fn whoami_status_and_body(app: &axum::Router, key: &str) -> (StatusCode, serde_json::Value) {
     use http_body_util::BodyExt as _;
 
@@ -317,6 +318,7 @@ async fn whoami_status_and_body(app: &axum::Router, key: &str) -> (StatusCode, s
         .method("GET")
         .uri("/sdk/whoami")
         .header("Authorization", format!("Bearer {key}"))
+        .header("X-Request-Id", "whoami-probe")
         .body(Body::empty())
         .expect("request");
 
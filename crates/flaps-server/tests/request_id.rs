@@ -0,0 +1,125 @@
+//! `X-Request-Id` correlation: every response echoes an id, JSON error bodies
+//! carry it, and the request's log lines are tagged with it.
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use flaps_server::{build_router, state::AppState};
+use flaps_store::{hash::KeyHasher, sqlite::SqliteStore};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+use tracing_subscriber::fmt::MakeWriter;
+
+async fn make_app() -> Router {
+    let store = SqliteStore::in_memory(KeyHasher::new(b"test-pepper-32-bytes-long-enough"))
+        .await
+        .expect("in-memory store");
+    build_router(AppState::new(store))
+}
+
+/// Sends `request` and returns the echoed request id and the JSON body.
+async fn send(app: Router, request: Request<Body>) -> (StatusCode, String, serde_json::Value) {
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let id = response
+        .headers()
+        .get("x-request-id")
+        .expect("every response carries X-Request-Id")
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, id, serde_json::from_slice(&bytes).unwrap())
+}
+
+/// A `Vec<u8>`-backed writer usable as a `tracing_subscriber` sink.
+#[derive(Clone, Default)]
+struct CapturingWriter(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for CapturingWriter {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn error_bodies_carry_the_request_id_that_was_logged() {
+    let writer = CapturingWriter::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer.clone())
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let request = Request::get("/projects").body(Body::empty()).unwrap();
+    let (status, id, body) = send(make_app().await, request).await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["request_id"], id.as_str());
+    assert_eq!(body["status"], 401, "the problem fields are kept: {body}");
+    assert_eq!(id.len(), 36, "a generated id is a UUID: {id}");
+
+    let logs = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+    let line = logs
+        .lines()
+        .find(|line| line.contains("request failed"))
+        .unwrap_or_else(|| panic!("no failure logged: {logs}"));
+    assert!(line.contains(&format!("request_id={id}")), "{line}");
+}
+
+#[tokio::test]
+async fn an_incoming_request_id_is_preserved() {
+    let request = Request::get("/projects")
+        .header("x-request-id", "support-ticket-4242")
+        .body(Body::empty())
+        .unwrap();
+    let (_, id, body) = send(make_app().await, request).await;
+    assert_eq!(id, "support-ticket-4242");
+    assert_eq!(body["request_id"], "support-ticket-4242");
+}
+
+#[tokio::test]
+async fn an_unusable_incoming_request_id_is_replaced() {
+    for incoming in ["x".repeat(129), "two words".to_owned(), String::new()] {
+        let request = Request::get("/projects")
+            .header("x-request-id", incoming.as_str())
+            .body(Body::empty())
+            .unwrap();
+        let (_, id, body) = send(make_app().await, request).await;
+        assert_ne!(id, incoming);
+        assert_eq!(id.len(), 36, "{id}");
+        assert_eq!(body["request_id"], id.as_str());
+    }
+}
+
+#[tokio::test]
+async fn ofrep_error_bodies_carry_the_request_id() {
+    let request = Request::post("/ofrep/v1/evaluate/flags/feature-x")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("x-request-id", "ofrep-1")
+        .body(Body::from("{}"))
+        .unwrap();
+    let (status, id, body) = send(make_app().await, request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(id, "ofrep-1");
+    assert_eq!(body["request_id"], "ofrep-1");
+    assert_eq!(body["errorCode"], "GENERAL", "{body}");
+}
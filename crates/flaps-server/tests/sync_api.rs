@@ -2,7 +2,8 @@
 //!
 //! Routes under test:
 //! - `GET /sync/v1/ruleset` (download compiled ruleset)
-//! - `GET /sync/v1/events`  (SSE stream of change notifications)
+//! - `GET /sync/v1/events`  (SSE stream of change notifications, resumable
+//!   with `Last-Event-ID`)
 //!
 //! Uses axum `oneshot` (no real network socket) with an in-memory SQLite store.
 //! The compiled ruleset cache is pre-populated directly via `install_in_cache`
@@ -292,6 +293,16 @@ fn ruleset_req_with_if_none_match(sdk_key: &str, etag: &str) -> Request<Body> {
         .unwrap()
 }
 
+fn ruleset_req_accepting(sdk_key: &str, accept: &str) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri("/sync/v1/ruleset")
+        .header("Authorization", format!("Bearer {sdk_key}"))
+        .header(header::ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap()
+}
+
 fn events_req(sdk_key: &str) -> Request<Body> {
     Request::builder()
         .method("GET")
@@ -301,6 +312,27 @@ fn events_req(sdk_key: &str) -> Request<Body> {
         .unwrap()
 }
 
+fn events_req_resuming_from(sdk_key: &str, last_event_id: &str) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri("/sync/v1/events")
+        .header("Authorization", format!("Bearer {sdk_key}"))
+        .header("Last-Event-ID", last_event_id)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Reads the next SSE frame of `body`, or `None` when none arrives within
+/// `wait`.
+async fn next_sse_frame(body: &mut Body, wait: std::time::Duration) -> Option<String> {
+    let frame = tokio::time::timeout(wait, body.frame()).await.ok()?;
+    let data = frame?
+        .expect("stream frame")
+        .into_data()
+        .expect("data frame");
+    Some(String::from_utf8(data.to_vec()).expect("UTF-8 frame"))
+}
+
 fn events_req_no_auth() -> Request<Body> {
     Request::builder()
         .method("GET")
@@ -392,6 +424,112 @@ async fn ruleset_304_on_matching_etag() {
     assert!(body.is_empty(), "304 must have no body");
 }
 
+/// 200: `Accept: application/x-flaps-snapshot` returns the binary snapshot,
+/// decoding to the same flag set as the JSON document, under its own ETag.
+#[tokio::test]
+async fn ruleset_200_binary_snapshot_on_accept() {
+    let (app, _state, server_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let json_resp = app.clone().oneshot(ruleset_req(&server_key)).await.unwrap();
+    let json_etag = json_resp.headers().get(header::ETAG).unwrap().clone();
+
+    let resp = app
+        .clone()
+        .oneshot(ruleset_req_accepting(
+            &server_key,
+            "application/x-flaps-snapshot, application/json;q=0.5",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        flaps_eval::SNAPSHOT_MEDIA_TYPE
+    );
+    assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept");
+    let etag = resp.headers().get(header::ETAG).unwrap().clone();
+    assert_ne!(etag, json_etag, "representations must not share an ETag");
+
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    let decoded = flaps_eval::FlagSet::from_snapshot(&bytes).expect("valid snapshot");
+    assert_eq!(decoded, flaps_eval::FlagSet::from_json(FLAGD_DOC).unwrap());
+
+    // The snapshot ETag revalidates the snapshot representation.
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/sync/v1/ruleset")
+                .header("Authorization", format!("Bearer {server_key}"))
+                .header(header::ACCEPT, flaps_eval::SNAPSHOT_MEDIA_TYPE)
+                .header(header::IF_NONE_MATCH, etag)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+}
+
+/// 200: an `Accept` header without the snapshot type keeps the JSON default.
+#[tokio::test]
+async fn ruleset_200_json_when_snapshot_not_accepted() {
+    let (app, _state, server_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let resp = app
+        .oneshot(ruleset_req_accepting(&server_key, "application/json"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/json"
+    );
+}
+
+/// A version bump produces a new cache key even when the document is identical
+/// (a revert): the ETag of the previous version misses and returns the new
+/// version in full.
+#[tokio::test]
+async fn ruleset_version_bump_invalidates_the_old_etag() {
+    let (app, state, server_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let resp = app.clone().oneshot(ruleset_req(&server_key)).await.unwrap();
+    let old_etag = resp
+        .headers()
+        .get(header::ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    install_in_cache(
+        &state,
+        &project_key(),
+        vec![fake_ruleset(FLAGD_DOC, env_key(), 2)],
+    )
+    .await;
+
+    let resp = app
+        .clone()
+        .oneshot(ruleset_req_with_if_none_match(&server_key, &old_etag))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK, "the old key must miss");
+    assert_eq!(resp.headers().get("X-Flaps-Version").unwrap(), "2");
+    let new_etag = resp
+        .headers()
+        .get(header::ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+    assert_ne!(new_etag, old_etag, "a version bump must write a new key");
+
+    let resp = app
+        .oneshot(ruleset_req_with_if_none_match(&server_key, &new_etag))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+}
+
 /// 404: no cache entry for the scope.
 #[tokio::test]
 async fn ruleset_404_cache_absent() {
@@ -523,6 +661,104 @@ async fn events_200_server_key_opens_stream() {
     );
 }
 
+/// A new subscriber is first told the current version, with the ruleset
+/// cache key as the event id it can resume from.
+#[tokio::test]
+async fn events_open_with_the_current_version() {
+    let (app, _state, server_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let resp = app.oneshot(events_req(&server_key)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut body = resp.into_body();
+
+    let frame = next_sse_frame(&mut body, std::time::Duration::from_secs(1))
+        .await
+        .expect("a catch-up frame");
+    let id = format!("id: 1-{}\n", sha2_hex(FLAGD_DOC));
+    assert!(frame.contains(&id), "frame: {frame:?}");
+    assert!(
+        frame.contains(r#"data: {"environment":"sync-env","version":1}"#),
+        "frame: {frame:?}"
+    );
+}
+
+/// A subscriber resuming at the current ruleset gets no redundant catch-up
+/// frame, only the changes that follow.
+#[tokio::test]
+async fn events_resumed_at_the_current_version_skip_the_catch_up() {
+    let (app, state, server_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let resp = app
+        .oneshot(events_req_resuming_from(
+            &server_key,
+            &format!("1-{}", sha2_hex(FLAGD_DOC)),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut body = resp.into_body();
+
+    assert_eq!(
+        next_sse_frame(&mut body, std::time::Duration::from_millis(200)).await,
+        None,
+        "no catch-up frame at the current version"
+    );
+
+    install_in_cache(
+        &state,
+        &project_key(),
+        vec![fake_ruleset(FLAGD_DOC, env_key(), 2)],
+    )
+    .await;
+    let frame = next_sse_frame(&mut body, std::time::Duration::from_secs(1))
+        .await
+        .expect("the change is announced");
+    let id = format!("id: 2-{}\n", sha2_hex(FLAGD_DOC));
+    assert!(frame.contains(&id), "frame: {frame:?}");
+}
+
+/// A subscriber resuming from an older version is told the current one.
+#[tokio::test]
+async fn events_resumed_from_an_old_version_catch_up() {
+    let (app, state, server_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    install_in_cache(
+        &state,
+        &project_key(),
+        vec![fake_ruleset(FLAGD_DOC, env_key(), 3)],
+    )
+    .await;
+    let resp = app
+        .oneshot(events_req_resuming_from(
+            &server_key,
+            &format!("1-{}", sha2_hex(FLAGD_DOC)),
+        ))
+        .await
+        .unwrap();
+    let mut body = resp.into_body();
+
+    let frame = next_sse_frame(&mut body, std::time::Duration::from_secs(1))
+        .await
+        .expect("a catch-up frame");
+    assert!(frame.contains(r#""version":3"#), "frame: {frame:?}");
+}
+
+/// A subscriber resuming from a ruleset of the same version but other
+/// content, as held after a restart of another process, is told the current
+/// one: the version alone does not name a ruleset.
+#[tokio::test]
+async fn events_resumed_from_other_content_at_the_same_version_catch_up() {
+    let (app, _state, server_key) = make_app_with_ruleset(FLAGD_DOC).await;
+    let resp = app
+        .oneshot(events_req_resuming_from(&server_key, "1-0000"))
+        .await
+        .unwrap();
+    let mut body = resp.into_body();
+
+    let frame = next_sse_frame(&mut body, std::time::Duration::from_secs(1))
+        .await
+        .expect("a catch-up frame");
+    let id = format!("id: 1-{}\n", sha2_hex(FLAGD_DOC));
+    assert!(frame.contains(&id), "frame: {frame:?}");
+}
+
 /// e2e: a subscriber connected before a mutation receives the event, then
 /// re-sync via GET /sync/v1/ruleset returns the new version.
 ///
@@ -580,3 +816,203 @@ async fn e2e_subscriber_receives_event_then_resync_returns_new_version() {
         "re-sync version must match announced version"
     );
 }
+
+/// Sends an authenticated admin `PUT` and asserts it succeeds.
+async fn admin_put(app: &axum::Router, uri: &str, body: &serde_json::Value, token: &str) {
+    let req = Request::builder()
+        .method("PUT")
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(Body::from(serde_json::to_vec(body).unwrap()))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert!(resp.status().is_success(), "PUT {uri}: {}", resp.status());
+}
+
+/// A segment listing user ids, matched on the targeting key.
+fn users_segment(users: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "key": "beta",
+        "name": "beta",
+        "match_expr": {"predicate": {
+            "attribute": "targetingKey",
+            "operator": "in",
+            "values": users,
+        }},
+    })
+}
+
+/// e2e: adding a user to a segment announces a new version for the
+/// environments whose flags reference it, and the ruleset a subscriber then
+/// fetches serves that user the segment's variant. Segments are compiled into
+/// the ruleset, so an SDK picks up a segment edit through the same
+/// notify-then-fetch cycle as a flag edit.
+#[tokio::test]
+async fn segment_edit_is_announced_and_the_added_user_matches() {
+    let store = make_store().await;
+    flaps_server::bootstrap_admin(&store, "admin", "admin-pass")
+        .await
+        .expect("bootstrap");
+
+    let state = AppState::new(store);
+    let app = build_router(state.clone());
+
+    let token = admin_login(&app).await;
+    create_project(&app, "sync-proj", &token).await;
+    create_environment(&app, "sync-proj", "sync-env", &token).await;
+    let server_key = create_sdk_key(&app, "sync-proj", "sync-env", "server", &token).await;
+    admin_put(
+        &app,
+        "/projects/sync-proj/segments/beta",
+        &users_segment(&["user-1"]),
+        &token,
+    )
+    .await;
+    admin_put(
+        &app,
+        "/projects/sync-proj/flags/checkout",
+        &serde_json::json!({
+            "key": "checkout",
+            "name": "checkout",
+            "description": null,
+            "flag_type": "release",
+            "value_type": "boolean",
+            "variants": {
+                "value_type": "boolean",
+                "entries": { "on": { "bool": true }, "off": { "bool": false } }
+            },
+            "metadata": {},
+        }),
+        &token,
+    )
+    .await;
+    admin_put(
+        &app,
+        "/projects/sync-proj/flags/checkout/environments/sync-env/config",
+        &serde_json::json!({
+            "enabled": true,
+            "rules": [{"segments": ["beta"], "serve": {"fixed": "on"}}],
+            "default_rule": {"fixed": "off"},
+        }),
+        &token,
+    )
+    .await;
+
+    let variant_for = |document: &str, user: &str| {
+        let context = flaps_eval::EvaluationContext {
+            targeting_key: Some(user.to_owned()),
+            ..Default::default()
+        };
+        flaps_eval::FlagSet::from_json(document)
+            .unwrap()
+            .evaluate("checkout", &context)
+            .unwrap()
+            .variant
+            .unwrap()
+    };
+    let resp = app.clone().oneshot(ruleset_req(&server_key)).await.unwrap();
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    let before = String::from_utf8(bytes.to_vec()).unwrap();
+    assert_eq!(variant_for(&before, "user-2"), "off");
+
+    let mut rx = state.events.subscribe();
+    admin_put(
+        &app,
+        "/projects/sync-proj/segments/beta",
+        &users_segment(&["user-1", "user-2"]),
+        &token,
+    )
+    .await;
+    let ev = tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv())
+        .await
+        .expect("event must arrive within 1s")
+        .expect("event must be received");
+    assert_eq!(ev.environment, env_key());
+
+    let resp = app.oneshot(ruleset_req(&server_key)).await.unwrap();
+    assert_eq!(
+        resp.headers()["X-Flaps-Version"].to_str().unwrap(),
+        ev.version.to_string()
+    );
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    let after = String::from_utf8(bytes.to_vec()).unwrap();
+    assert_eq!(variant_for(&after, "user-1"), "on");
+    assert_eq!(variant_for(&after, "user-2"), "on");
+    assert_eq!(variant_for(&after, "user-3"), "off");
+}
+
+/// A flag config update is announced for exactly the project and environment
+/// it changed: the admin handler recompiles after the commit and publishes
+/// one event, and an environment whose ruleset did not change hears nothing.
+#[tokio::test]
+async fn flag_config_update_is_announced_for_its_environment_only() {
+    let store = make_store().await;
+    flaps_server::bootstrap_admin(&store, "admin", "admin-pass")
+        .await
+        .expect("bootstrap");
+
+    let state = AppState::new(store);
+    let app = build_router(state.clone());
+
+    let token = admin_login(&app).await;
+    create_project(&app, "sync-proj", &token).await;
+    create_environment(&app, "sync-proj", "sync-env", &token).await;
+    create_environment(&app, "sync-proj", "other-env", &token).await;
+    admin_put(
+        &app,
+        "/projects/sync-proj/flags/checkout",
+        &serde_json::json!({
+            "key": "checkout",
+            "name": "checkout",
+            "description": null,
+            "flag_type": "release",
+            "value_type": "boolean",
+            "variants": {
+                "value_type": "boolean",
+                "entries": { "on": { "bool": true }, "off": { "bool": false } }
+            },
+            "metadata": {},
+        }),
+        &token,
+    )
+    .await;
+    let config = |variant: &str| {
+        serde_json::json!({
+            "enabled": true,
+            "rules": [],
+            "default_rule": {"fixed": variant},
+        })
+    };
+    for env in ["sync-env", "other-env"] {
+        admin_put(
+            &app,
+            &format!("/projects/sync-proj/flags/checkout/environments/{env}/config"),
+            &config("off"),
+            &token,
+        )
+        .await;
+    }
+
+    let mut rx = state.events.subscribe();
+    admin_put(
+        &app,
+        "/projects/sync-proj/flags/checkout/environments/sync-env/config",
+        &config("on"),
+        &token,
+    )
+    .await;
+
+    let ev = rx.try_recv().expect("the update must be announced");
+    assert_eq!(ev.project, project_key());
+    assert_eq!(ev.environment, env_key());
+    assert!(
+        rx.try_recv().is_err(),
+        "the untouched environment must not be announced"
+    );
+    let cache = state.cache.read().await;
+    assert_eq!(
+        cache[&(project_key(), env_key())].ruleset.version,
+        ev.version
+    );
+}
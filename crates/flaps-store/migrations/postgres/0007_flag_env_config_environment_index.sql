@@ -0,0 +1,4 @@
+-- Lets per-environment queries (enabled-flag counts, environment cascades)
+-- seek on (project, environment) instead of scanning every flag of the project.
+CREATE INDEX IF NOT EXISTS idx_flag_env_configs_environment
+    ON flag_env_configs (project_key, environment_key);
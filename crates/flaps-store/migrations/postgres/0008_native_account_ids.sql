@@ -0,0 +1,14 @@
+-- Account ids are UUIDs: store them as native `uuid` rather than text, so
+-- they take 16 bytes, compare as such in the session join, and reject
+-- malformed values. Existing text ids are converted in place; a malformed
+-- one fails the migration instead of being dropped.
+-- SQLite has no uuid type and keeps text ids.
+
+ALTER TABLE sessions DROP CONSTRAINT IF EXISTS sessions_account_id_fkey;
+
+ALTER TABLE accounts ALTER COLUMN id TYPE UUID USING id::uuid;
+ALTER TABLE sessions ALTER COLUMN account_id TYPE UUID USING account_id::uuid;
+
+ALTER TABLE sessions
+    ADD CONSTRAINT sessions_account_id_fkey
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE;
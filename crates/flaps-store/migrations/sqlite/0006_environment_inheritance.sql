@@ -0,0 +1,2 @@
+-- Base environment whose targeting an environment inherits.
+ALTER TABLE environments ADD COLUMN inherits_from TEXT;
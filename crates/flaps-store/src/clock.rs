@@ -7,16 +7,34 @@
 /// [`std::time::SystemTime`] and performs the Gregorian calendar
 /// conversion without any additional dependency.
 pub(crate) fn now_rfc3339() -> String {
+    format_rfc3339(unix_now())
+}
+
+/// Returns the UTC instant `window` before now, in the format of
+/// [`now_rfc3339`].
+///
+/// Timestamps in that format sort chronologically as strings, so the result
+/// can bound an `occurred_at >= ?` query directly.
+pub(crate) fn rfc3339_before(window: std::time::Duration) -> String {
+    format_rfc3339(unix_now().saturating_sub(window.as_secs()))
+}
+
+/// Returns the seconds elapsed since the Unix epoch.
+fn unix_now() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
-    let secs = SystemTime::now()
+    SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
-        .as_secs();
+        .as_secs()
+}
+
+/// Formats `secs` seconds since the Unix epoch in the format of
+/// [`now_rfc3339`].
+fn format_rfc3339(secs: u64) -> String {
     let sec = secs % 60;
     let min = (secs / 60) % 60;
     let hour = (secs / 3600) % 24;
-    let days = secs / 86400;
-    let (year, month, day) = days_to_ymd(days);
+    let (year, month, day) = days_to_ymd(secs / 86400);
     format!("{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}Z")
 }
 
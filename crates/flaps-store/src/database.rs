@@ -0,0 +1,436 @@
+//! Connection settings shared by both backends.
+//!
+//! A [`DatabaseConfig`] is a connection URL plus the pool settings applied
+//! when the store connects. Pool settings can ride on the URL query string
+//! (`postgres://db/flaps?max_connections=50&connect_timeout=10`), the way
+//! many tools accept them: [`DatabaseConfig::from_url`] lifts the known
+//! parameters into fields and strips them, so the driver only ever sees its
+//! own parameters.
+//!
+//! Two timeouts bound two different waits. `acquire_timeout` is how long a
+//! query waits for a pooled connection: under saturation it should be short,
+//! so requests fail fast instead of queueing. `connect_timeout` is how long
+//! the store waits for the database when it starts: a server that is still
+//! booting may need longer. sqlx opens pooled connections inside the acquire
+//! wait, so once the store is up the acquire timeout bounds those too.
+//!
+//! PostgreSQL TLS can be set in code with [`DatabaseConfig::ssl_mode`] and
+//! [`DatabaseConfig::ssl_root_cert`], which are applied to the connect
+//! options directly and win over `sslmode` / `sslrootcert` in the URL.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use sqlx::{ConnectOptions, Connection, Database, Pool, pool::PoolOptions};
+
+/// How a store connects: URL and pool settings.
+///
+/// Every pool setting is optional; `None` keeps the sqlx default. Fields
+/// assigned after [`Self::from_url`] override what the URL carried, so
+/// settings from code or a config file win over the URL.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatabaseConfig {
+    /// Connection URL handed to sqlx, without pool parameters.
+    pub url: String,
+    /// Maximum number of pooled connections (`max_connections`).
+    pub max_connections: Option<u32>,
+    /// Connections kept open even when idle (`min_connections`).
+    pub min_connections: Option<u32>,
+    /// Seconds to wait for the database when the store connects
+    /// (`connect_timeout`). Unset, the store connects as sqlx does, within
+    /// the acquire timeout.
+    pub connect_timeout_secs: Option<u64>,
+    /// Seconds a query waits for a pooled connection before failing with
+    /// [`sqlx::Error::PoolTimedOut`] (`acquire_timeout`).
+    pub acquire_timeout_secs: Option<u64>,
+    /// Seconds after which an idle connection is closed (`idle_timeout`).
+    pub idle_timeout_secs: Option<u64>,
+    /// Seconds after which a connection is recycled (`max_lifetime`).
+    pub max_lifetime_secs: Option<u64>,
+    /// Prepared statements each connection keeps
+    /// (`statement_cache_capacity`).
+    ///
+    /// Every store query is a fixed, parameterized statement, so a cache
+    /// that holds all of them prepares each statement once per connection.
+    /// A smaller cache evicts and re-prepares statements under load; the
+    /// sqlx default (100) covers every statement of the store.
+    pub statement_cache_capacity: Option<usize>,
+    /// TLS mode of PostgreSQL connections; `None` keeps the URL's `sslmode`
+    /// (`prefer` when absent). Ignored by SQLite.
+    pub ssl_mode: Option<SslMode>,
+    /// PEM file of the certificate authority PostgreSQL server certificates
+    /// are verified against, for a server whose CA is not a public root
+    /// (managed databases often use their own). Ignored by SQLite.
+    pub ssl_root_cert: Option<PathBuf>,
+}
+
+/// TLS mode of a PostgreSQL connection, as libpq's `sslmode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SslMode {
+    /// Never use TLS.
+    Disable,
+    /// Use TLS if the server supports it.
+    Prefer,
+    /// Require TLS, without verifying the server certificate.
+    Require,
+    /// Require TLS and a server certificate signed by a trusted CA.
+    VerifyCa,
+    /// As [`Self::VerifyCa`], and the certificate must name the host
+    /// connected to.
+    VerifyFull,
+}
+
+impl From<SslMode> for PgSslMode {
+    fn from(mode: SslMode) -> Self {
+        match mode {
+            SslMode::Disable => Self::Disable,
+            SslMode::Prefer => Self::Prefer,
+            SslMode::Require => Self::Require,
+            SslMode::VerifyCa => Self::VerifyCa,
+            SslMode::VerifyFull => Self::VerifyFull,
+        }
+    }
+}
+
+/// A pool parameter in a database URL is malformed.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DatabaseConfigError {
+    /// A parameter value is not a valid number for its setting.
+    #[error("invalid `{name}` in database URL: `{value}` is not a positive integer")]
+    InvalidValue {
+        /// The parameter name.
+        name: String,
+        /// The rejected value.
+        value: String,
+    },
+    /// A parameter appears more than once.
+    #[error("`{0}` is given more than once in the database URL")]
+    Duplicate(String),
+    /// `min_connections` exceeds `max_connections`.
+    #[error("min_connections ({min}) exceeds max_connections ({max})")]
+    MinAboveMax {
+        /// The requested minimum.
+        min: u32,
+        /// The requested maximum.
+        max: u32,
+    },
+}
+
+impl DatabaseConfig {
+    /// Parses `url`, lifting `max_connections`, `min_connections`,
+    /// `connect_timeout`, `acquire_timeout`, `idle_timeout`, `max_lifetime` and
+    /// `statement_cache_capacity` out of its query string into the matching
+    /// fields. Other parameters stay in
+    /// [`Self::url`], in their original order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseConfigError`] when a pool parameter is not a
+    /// positive integer, is repeated, or `min_connections` exceeds
+    /// `max_connections`.
+    pub fn from_url(url: &str) -> Result<Self, DatabaseConfigError> {
+        let (base, query) = url.split_once('?').unwrap_or((url, ""));
+        let mut config = Self::default();
+        let mut kept = Vec::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let slot = match name {
+                "max_connections" => Slot::U32(&mut config.max_connections),
+                "min_connections" => Slot::U32(&mut config.min_connections),
+                "connect_timeout" => Slot::U64(&mut config.connect_timeout_secs),
+                "acquire_timeout" => Slot::U64(&mut config.acquire_timeout_secs),
+                "idle_timeout" => Slot::U64(&mut config.idle_timeout_secs),
+                "max_lifetime" => Slot::U64(&mut config.max_lifetime_secs),
+                "statement_cache_capacity" => Slot::Usize(&mut config.statement_cache_capacity),
+                _ => {
+                    kept.push(pair);
+                    continue;
+                }
+            };
+            slot.set(name, value)?;
+        }
+        if let (Some(min), Some(max)) = (config.min_connections, config.max_connections) {
+            if min > max {
+                return Err(DatabaseConfigError::MinAboveMax { min, max });
+            }
+        }
+        config.url = if kept.is_empty() {
+            base.to_owned()
+        } else {
+            format!("{base}?{}", kept.join("&"))
+        };
+        Ok(config)
+    }
+
+    /// Returns pool options carrying the configured settings.
+    pub(crate) fn pool_options<DB: Database>(&self) -> PoolOptions<DB> {
+        let mut options = PoolOptions::<DB>::new();
+        if let Some(max) = self.max_connections {
+            options = options.max_connections(max);
+        }
+        if let Some(min) = self.min_connections {
+            options = options.min_connections(min);
+        }
+        if let Some(secs) = self.acquire_timeout_secs {
+            options = options.acquire_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.idle_timeout_secs {
+            options = options.idle_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.max_lifetime_secs {
+            options = options.max_lifetime(Duration::from_secs(secs));
+        }
+        options
+    }
+
+    /// Opens the pool `options` describe with `connect`, waiting up to the
+    /// connect timeout for the database.
+    ///
+    /// Without a connect timeout the pool is opened eagerly, as sqlx does.
+    /// With one, a first connection is opened and closed within it, then the
+    /// pool is opened lazily: the pool's own connections would otherwise be
+    /// bounded by the acquire timeout.
+    pub(crate) async fn open_pool<DB: Database>(
+        &self,
+        options: PoolOptions<DB>,
+        connect: <DB::Connection as Connection>::Options,
+    ) -> Result<Pool<DB>, sqlx::Error> {
+        let Some(secs) = self.connect_timeout_secs else {
+            return options.connect_with(connect).await;
+        };
+        let timeout = Duration::from_secs(secs);
+        let first = async { connect.connect().await?.close().await };
+        tokio::time::timeout(timeout, first).await.map_err(|_| {
+            sqlx::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("no database connection within {secs}s"),
+            ))
+        })??;
+        Ok(options.connect_lazy_with(connect))
+    }
+}
+
+impl DatabaseConfig {
+    /// Returns PostgreSQL connect options for [`Self::url`], carrying the
+    /// statement cache and TLS settings.
+    pub(crate) fn pg_connect_options(&self) -> Result<PgConnectOptions, sqlx::Error> {
+        let mut options = PgConnectOptions::from_str(&self.url)?;
+        if let Some(capacity) = self.statement_cache_capacity {
+            options = options.statement_cache_capacity(capacity);
+        }
+        if let Some(mode) = self.ssl_mode {
+            options = options.ssl_mode(mode.into());
+        }
+        if let Some(path) = &self.ssl_root_cert {
+            options = options.ssl_root_cert(path);
+        }
+        Ok(options)
+    }
+}
+
+/// The field a URL parameter fills.
+enum Slot<'a> {
+    U32(&'a mut Option<u32>),
+    U64(&'a mut Option<u64>),
+    Usize(&'a mut Option<usize>),
+}
+
+impl Slot<'_> {
+    fn set(self, name: &str, value: &str) -> Result<(), DatabaseConfigError> {
+        let invalid = || DatabaseConfigError::InvalidValue {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        };
+        let duplicate = || DatabaseConfigError::Duplicate(name.to_owned());
+        match self {
+            Slot::U32(slot) => {
+                let parsed = value
+                    .parse()
+                    .ok()
+                    .filter(|&n: &u32| n > 0)
+                    .ok_or_else(invalid)?;
+                slot.replace(parsed).map_or(Ok(()), |_| Err(duplicate()))
+            }
+            Slot::U64(slot) => {
+                let parsed = value
+                    .parse()
+                    .ok()
+                    .filter(|&n: &u64| n > 0)
+                    .ok_or_else(invalid)?;
+                slot.replace(parsed).map_or(Ok(()), |_| Err(duplicate()))
+            }
+            Slot::Usize(slot) => {
+                let parsed = value
+                    .parse()
+                    .ok()
+                    .filter(|&n: &usize| n > 0)
+                    .ok_or_else(invalid)?;
+                slot.replace(parsed).map_or(Ok(()), |_| Err(duplicate()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_parameters_are_lifted_out_of_the_url() {
+        let config = DatabaseConfig::from_url(
+            "postgres://flaps@db:5432/flaps?max_connections=50&sslmode=require\
+             &connect_timeout=10&acquire_timeout=2&min_connections=5&idle_timeout=300&max_lifetime=1800\
+             &statement_cache_capacity=250",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            DatabaseConfig {
+                url: "postgres://flaps@db:5432/flaps?sslmode=require".to_owned(),
+                max_connections: Some(50),
+                min_connections: Some(5),
+                connect_timeout_secs: Some(10),
+                acquire_timeout_secs: Some(2),
+                idle_timeout_secs: Some(300),
+                max_lifetime_secs: Some(1800),
+                statement_cache_capacity: Some(250),
+                ssl_mode: None,
+                ssl_root_cert: None,
+            }
+        );
+    }
+
+    #[test]
+    fn urls_without_pool_parameters_are_unchanged() {
+        for url in [
+            "sqlite://flaps.db",
+            "sqlite::memory:",
+            "postgres://db/flaps?sslmode=require&application_name=flaps",
+        ] {
+            assert_eq!(
+                DatabaseConfig::from_url(url).unwrap(),
+                DatabaseConfig {
+                    url: url.to_owned(),
+                    ..DatabaseConfig::default()
+                }
+            );
+        }
+        assert_eq!(
+            DatabaseConfig::from_url("sqlite://flaps.db?max_connections=4")
+                .unwrap()
+                .url,
+            "sqlite://flaps.db"
+        );
+    }
+
+    #[test]
+    fn malformed_pool_parameters_are_rejected() {
+        for (url, name) in [
+            (
+                "postgres://db/flaps?max_connections=lots",
+                "max_connections",
+            ),
+            ("postgres://db/flaps?max_connections=0", "max_connections"),
+            ("postgres://db/flaps?connect_timeout=-1", "connect_timeout"),
+            ("postgres://db/flaps?acquire_timeout=0", "acquire_timeout"),
+            ("postgres://db/flaps?idle_timeout", "idle_timeout"),
+            (
+                "sqlite://flaps.db?statement_cache_capacity=0",
+                "statement_cache_capacity",
+            ),
+        ] {
+            assert!(
+                matches!(
+                    DatabaseConfig::from_url(url),
+                    Err(DatabaseConfigError::InvalidValue { name: ref n, .. }) if n == name
+                ),
+                "{url}"
+            );
+        }
+        assert_eq!(
+            DatabaseConfig::from_url("postgres://db/flaps?max_connections=2&max_connections=3"),
+            Err(DatabaseConfigError::Duplicate("max_connections".to_owned()))
+        );
+        assert_eq!(
+            DatabaseConfig::from_url("postgres://db/flaps?min_connections=8&max_connections=4"),
+            Err(DatabaseConfigError::MinAboveMax { min: 8, max: 4 })
+        );
+    }
+
+    #[test]
+    fn ssl_settings_are_applied_to_the_postgres_connect_options() {
+        // `PgSslMode` has no `PartialEq`; its `Debug` names the variant.
+        for (mode, expected) in [
+            (SslMode::Require, "Require"),
+            (SslMode::VerifyCa, "VerifyCa"),
+            (SslMode::VerifyFull, "VerifyFull"),
+        ] {
+            let config = DatabaseConfig {
+                url: "postgres://flaps@db.example.com/flaps".to_owned(),
+                ssl_mode: Some(mode),
+                ssl_root_cert: Some(PathBuf::from("/etc/flaps/db-ca.pem")),
+                ..DatabaseConfig::default()
+            };
+            let options = config.pg_connect_options().unwrap();
+            assert_eq!(format!("{:?}", options.get_ssl_mode()), expected);
+            assert_eq!(options.get_host(), "db.example.com");
+        }
+    }
+
+    #[test]
+    fn the_configured_ssl_mode_wins_over_the_url() {
+        let mut config = DatabaseConfig::from_url("postgres://db/flaps?sslmode=disable").unwrap();
+        let from_url = config.pg_connect_options().unwrap();
+        assert!(matches!(from_url.get_ssl_mode(), PgSslMode::Disable));
+
+        config.ssl_mode = Some(SslMode::VerifyFull);
+        let overridden = config.pg_connect_options().unwrap();
+        assert!(matches!(overridden.get_ssl_mode(), PgSslMode::VerifyFull));
+    }
+
+    #[test]
+    fn settings_are_applied_to_the_pool_options() {
+        let config =
+            DatabaseConfig::from_url("sqlite::memory:?max_connections=3&acquire_timeout=7")
+                .unwrap();
+        let options = config.pool_options::<sqlx::Sqlite>();
+        assert_eq!(options.get_max_connections(), 3);
+        assert_eq!(options.get_acquire_timeout(), Duration::from_secs(7));
+        // The connect timeout is not an acquire timeout.
+        let connect_only = DatabaseConfig::from_url("sqlite::memory:?connect_timeout=7").unwrap();
+        assert_eq!(
+            connect_only
+                .pool_options::<sqlx::Sqlite>()
+                .get_acquire_timeout(),
+            PoolOptions::<sqlx::Sqlite>::new().get_acquire_timeout()
+        );
+        let defaults = DatabaseConfig::default().pool_options::<sqlx::Sqlite>();
+        assert_eq!(
+            defaults.get_max_connections(),
+            PoolOptions::<sqlx::Sqlite>::new().get_max_connections()
+        );
+    }
+
+    #[tokio::test]
+    async fn a_saturated_pool_fails_at_the_acquire_timeout_not_the_connect_timeout() {
+        let config = DatabaseConfig::from_url(
+            "sqlite::memory:?max_connections=1&acquire_timeout=1&connect_timeout=60",
+        )
+        .unwrap();
+        let pool = config
+            .open_pool(
+                config.pool_options::<sqlx::Sqlite>(),
+                sqlx::sqlite::SqliteConnectOptions::from_str(&config.url).unwrap(),
+            )
+            .await
+            .unwrap();
+        let _held = pool.acquire().await.unwrap();
+
+        let started = std::time::Instant::now();
+        let waited = pool.acquire().await;
+        assert!(matches!(waited, Err(sqlx::Error::PoolTimedOut)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -9,6 +9,9 @@ pub enum StoreError {
     /// A JSON serialization or deserialization error.
     #[error("serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    /// The database URL carries malformed pool settings.
+    #[error("invalid database configuration: {0}")]
+    Config(#[from] crate::database::DatabaseConfigError),
     /// A migration failed.
     #[error("migration error: {0}")]
     Migrate(#[from] sqlx::migrate::MigrateError),
@@ -21,6 +24,12 @@ pub enum StoreError {
     /// A write referenced a parent entity that does not exist (foreign-key violation).
     #[error("referenced entity does not exist")]
     ForeignKeyViolation,
+    /// A stored row could not be decoded into its domain type.
+    #[error("invalid stored row: {0}")]
+    InvalidRow(String),
+    /// A page cursor is malformed or belongs to another list.
+    #[error("invalid page cursor")]
+    InvalidCursor,
 }
 
 /// Convenience alias for `Result<T, StoreError>`.
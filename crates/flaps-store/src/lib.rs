@@ -30,6 +30,7 @@ mod clock;
 
 pub mod account;
 pub mod audit;
+pub mod database;
 pub mod error;
 pub mod hash;
 pub mod postgres;
@@ -39,6 +40,7 @@ pub mod sqlite;
 
 pub use account::{AccountRecord, NewSession};
 pub use audit::AuditRecord;
+pub use database::{DatabaseConfig, DatabaseConfigError, SslMode};
 pub use error::{StoreError, StoreResult};
 pub use hash::KeyHasher;
 pub use sdk_key::{NewSdkKey, SdkKeyRecord, SdkKeyScope};
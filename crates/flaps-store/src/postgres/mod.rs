@@ -9,6 +9,8 @@ use sqlx::{
     Executor, Pool, Postgres, Transaction,
     migrate::{Migration, MigrationType, Migrator},
 };
+use tracing::warn;
+use uuid::Uuid;
 
 use flaps_domain::{
     Environment, EnvironmentKey, ExternalRef, Flag, FlagEnvConfig, FlagKey, ManagedBy, Project,
@@ -18,17 +20,20 @@ use flaps_domain::{
 use crate::{
     account::{AccountRecord, NewSession},
     audit::{AuditRecord, postgres::append_audit},
+    database::DatabaseConfig,
     error::{StoreError, StoreResult},
     hash::KeyHasher,
     repository::{
         account::{AccountRepository, SessionRepository},
         audit_log::AuditLogRepository,
-        environment::EnvironmentRepository,
-        flag::FlagRepository,
+        environment::{EnvironmentRepository, ListEnvironmentsQuery},
+        flag::{FlagRepository, LenientFlagList},
         flag_env_config::FlagEnvConfigRepository,
+        health::HealthRepository,
+        page::{Cursor, Page, PageRequest},
         project::ProjectRepository,
         sdk_key::SdkKeyRepository,
-        segment::SegmentRepository,
+        segment::{ListSegmentsQuery, SegmentRepository},
         transaction::{TransactionalStore, WriteSession},
     },
     sdk_key::{NewSdkKey, SdkKeyRecord, SdkKeyScope},
@@ -115,7 +120,14 @@ fn secs_to_rfc3339(secs: u64) -> String {
 // ---------------------------------------------------------------------------
 
 type ProjectRow = (String, String, Option<String>, Option<String>, String);
-type EnvRow = (String, String, Option<String>, String, serde_json::Value);
+type EnvRow = (
+    String,
+    String,
+    Option<String>,
+    String,
+    serde_json::Value,
+    Option<String>,
+);
 type FlagRow = (
     String,
     String,
@@ -142,17 +154,14 @@ fn managed_by_from_str(s: &str) -> StoreResult<ManagedBy> {
     match s {
         "local" => Ok(ManagedBy::Local),
         "federated" => Ok(ManagedBy::Federated),
-        other => Err(StoreError::Serialization(
-            serde_json::from_str::<serde_json::Value>(&format!("\"unknown managed_by: {other}\""))
-                .unwrap_err(),
-        )),
+        other => Err(StoreError::InvalidRow(format!(
+            "unknown managed_by: {other}"
+        ))),
     }
 }
 
 fn domain_key_err(e: &flaps_domain::DomainError) -> StoreError {
-    StoreError::Serialization(
-        serde_json::from_str::<serde_json::Value>(&format!("\"{e}\"")).unwrap_err(),
-    )
+    StoreError::InvalidRow(e.to_string())
 }
 
 fn row_to_project(
@@ -177,6 +186,7 @@ fn row_to_environment(
     ext_ref: Option<String>,
     mb: &str,
     metadata_json: serde_json::Value,
+    inherits_from: Option<String>,
 ) -> StoreResult<Environment> {
     Ok(Environment {
         key: EnvironmentKey::new(k).map_err(|e| domain_key_err(&e))?,
@@ -184,6 +194,36 @@ fn row_to_environment(
         external_ref: ext_ref.map(ExternalRef::new),
         managed_by: managed_by_from_str(mb)?,
         metadata: serde_json::from_value(metadata_json)?,
+        inherits_from: inherits_from
+            .map(|key| EnvironmentKey::new(key).map_err(|e| domain_key_err(&e)))
+            .transpose()?,
+    })
+}
+
+/// Counts the rows of `table` belonging to `project`. `table` is one of the
+/// project-scoped tables, never caller input.
+async fn count_in_project<'e, E>(executor: E, table: &str, project: &ProjectKey) -> StoreResult<i64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let count = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM {table} WHERE project_key = $1"
+    ))
+    .bind(project.as_str())
+    .fetch_one(executor)
+    .await?;
+    Ok(count)
+}
+
+fn row_to_flag((k, name, desc, ft, vt, vj, mj): FlagRow) -> StoreResult<Flag> {
+    Ok(Flag {
+        key: FlagKey::new(k).map_err(|e| domain_key_err(&e))?,
+        name,
+        description: desc,
+        flag_type: serde_json::from_str(&format!(r#""{ft}""#))?,
+        value_type: serde_json::from_str(&format!(r#""{vt}""#))?,
+        variants: serde_json::from_value(vj)?,
+        metadata: serde_json::from_value(mj)?,
     })
 }
 
@@ -215,15 +255,17 @@ where
     E: Executor<'e, Database = Postgres>,
 {
     let row: Option<EnvRow> = sqlx::query_as(
-        "SELECT key, name, external_ref, managed_by, metadata_json FROM environments WHERE project_key = $1 AND key = $2",
+        "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments WHERE project_key = $1 AND key = $2",
     )
     .bind(project.as_str())
     .bind(key.as_str())
     .fetch_optional(executor)
     .await?;
 
-    row.map(|(k, name, ext_ref, mb, meta)| row_to_environment(k, name, ext_ref, &mb, meta))
-        .transpose()
+    row.map(|(k, name, ext_ref, mb, meta, base)| {
+        row_to_environment(k, name, ext_ref, &mb, meta, base)
+    })
+    .transpose()
 }
 
 async fn do_get_flag<'e, E>(
@@ -243,18 +285,7 @@ where
         .fetch_optional(executor)
         .await?;
 
-    row.map(|(k, name, desc, ft, vt, vj, mj)| {
-        Ok(Flag {
-            key: FlagKey::new(k).map_err(|e| domain_key_err(&e))?,
-            name,
-            description: desc,
-            flag_type: serde_json::from_str(&format!(r#""{ft}""#))?,
-            value_type: serde_json::from_str(&format!(r#""{vt}""#))?,
-            variants: serde_json::from_value(vj)?,
-            metadata: serde_json::from_value(mj)?,
-        })
-    })
-    .transpose()
+    row.map(row_to_flag).transpose()
 }
 
 async fn do_get_segment<'e, E>(
@@ -362,13 +393,14 @@ where
     let now = crate::clock::now_rfc3339();
 
     let result = sqlx::query(
-        r"INSERT INTO environments (project_key, key, name, external_ref, managed_by, metadata_json, created_at, updated_at)
-          VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        r"INSERT INTO environments (project_key, key, name, external_ref, managed_by, metadata_json, inherits_from, created_at, updated_at)
+          VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
           ON CONFLICT(project_key, key) DO UPDATE SET
               name          = EXCLUDED.name,
               external_ref  = EXCLUDED.external_ref,
               managed_by    = EXCLUDED.managed_by,
               metadata_json = EXCLUDED.metadata_json,
+              inherits_from = EXCLUDED.inherits_from,
               updated_at    = EXCLUDED.updated_at",
     )
     .bind(project.as_str())
@@ -377,6 +409,7 @@ where
     .bind(external_ref)
     .bind(managed_by)
     .bind(metadata_json)
+    .bind(env.inherits_from.as_ref().map(EnvironmentKey::as_str))
     .bind(&now)
     .bind(&now)
     .execute(executor)
@@ -569,6 +602,33 @@ fn embedded_migrator() -> Migrator {
                 )),
                 false,
             ),
+            Migration::new(
+                6,
+                Cow::Borrowed("environment_inheritance"),
+                MigrationType::Simple,
+                Cow::Borrowed(include_str!(
+                    "../../migrations/postgres/0006_environment_inheritance.sql"
+                )),
+                false,
+            ),
+            Migration::new(
+                7,
+                Cow::Borrowed("flag_env_config_environment_index"),
+                MigrationType::Simple,
+                Cow::Borrowed(include_str!(
+                    "../../migrations/postgres/0007_flag_env_config_environment_index.sql"
+                )),
+                false,
+            ),
+            Migration::new(
+                8,
+                Cow::Borrowed("native_account_ids"),
+                MigrationType::Simple,
+                Cow::Borrowed(include_str!(
+                    "../../migrations/postgres/0008_native_account_ids.sql"
+                )),
+                false,
+            ),
         ]
     });
 
@@ -595,10 +655,26 @@ pub struct PostgresStore {
 impl PostgresStore {
     /// Connects to the given Postgres URL and runs embedded migrations.
     ///
+    /// Pool settings in the URL query string are applied, see
+    /// [`DatabaseConfig::from_url`].
+    ///
     /// # Errors
-    /// Returns [`StoreError`] if the connection or migrations fail.
+    /// Returns [`StoreError`] if the URL carries malformed pool settings, or
+    /// the connection or migrations fail.
     pub async fn connect(url: &str, hasher: KeyHasher) -> StoreResult<Self> {
-        let pool = sqlx::postgres::PgPoolOptions::new().connect(url).await?;
+        Self::connect_with(&DatabaseConfig::from_url(url)?, hasher).await
+    }
+
+    /// Connects with `config`'s URL, pool, statement cache and TLS settings
+    /// and runs embedded migrations.
+    ///
+    /// # Errors
+    /// Returns [`StoreError`] if the connection or migrations fail.
+    pub async fn connect_with(config: &DatabaseConfig, hasher: KeyHasher) -> StoreResult<Self> {
+        let options = config.pg_connect_options()?;
+        let pool = config
+            .open_pool(config.pool_options::<Postgres>(), options)
+            .await?;
         embedded_migrator().run(&pool).await?;
         Ok(Self {
             pool,
@@ -617,6 +693,17 @@ impl PostgresStore {
     }
 }
 
+// ---------------------------------------------------------------------------
+// HealthRepository for PostgresStore
+// ---------------------------------------------------------------------------
+
+impl HealthRepository for PostgresStore {
+    async fn ping(&self) -> StoreResult<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
 // ---------------------------------------------------------------------------
 // ProjectRepository for PostgresStore
 // ---------------------------------------------------------------------------
@@ -738,17 +825,71 @@ impl EnvironmentRepository for PostgresStore {
 
     async fn list_environments(&self, project: &ProjectKey) -> StoreResult<Vec<Environment>> {
         let rows: Vec<EnvRow> = sqlx::query_as(
-            "SELECT key, name, external_ref, managed_by, metadata_json FROM environments WHERE project_key = $1",
+            "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments WHERE project_key = $1",
         )
         .bind(project.as_str())
         .fetch_all(&self.pool)
         .await?;
 
         rows.into_iter()
-            .map(|(k, name, ext_ref, mb, meta)| row_to_environment(k, name, ext_ref, &mb, meta))
+            .map(|(k, name, ext_ref, mb, meta, base)| {
+                row_to_environment(k, name, ext_ref, &mb, meta, base)
+            })
             .collect()
     }
 
+    async fn query_environments(
+        &self,
+        project: &ProjectKey,
+        query: &ListEnvironmentsQuery,
+    ) -> StoreResult<Vec<Environment>> {
+        let sql = format!(
+            "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments \
+             WHERE project_key = $1 AND ($2::TEXT IS NULL OR managed_by = $2) \
+             AND ($3::TEXT IS NULL OR strpos(key, $3) > 0) {}",
+            query.sort_by.order_by(query.descending)
+        );
+        let rows: Vec<EnvRow> = sqlx::query_as(&sql)
+            .bind(project.as_str())
+            .bind(query.managed_by.map(managed_by_str))
+            .bind(query.key_contains.as_deref().map(str::to_lowercase))
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|(k, name, ext_ref, mb, meta, base)| {
+                row_to_environment(k, name, ext_ref, &mb, meta, base)
+            })
+            .collect()
+    }
+
+    async fn page_environments(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> StoreResult<Page<Environment>> {
+        let rows: Vec<EnvRow> = sqlx::query_as(
+            "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments \
+             WHERE project_key = $1 AND ($2::TEXT IS NULL OR key > $2) ORDER BY key LIMIT $3",
+        )
+        .bind(project.as_str())
+        .bind(request.after_position())
+        .bind(request.fetch_limit())
+        .fetch_all(&self.pool)
+        .await?;
+        let total = count_in_project(&self.pool, "environments", project).await?;
+
+        Page::from_rows(
+            rows,
+            request,
+            total,
+            |row| row.0.clone(),
+            |(k, name, ext_ref, mb, meta, base)| {
+                row_to_environment(k, name, ext_ref, &mb, meta, base)
+            },
+        )
+    }
+
     async fn delete_environment(
         &self,
         actor: &str,
@@ -828,19 +969,69 @@ impl FlagRepository for PostgresStore {
             .fetch_all(&self.pool)
             .await?;
 
-        rows.into_iter()
-            .map(|(k, name, desc, ft, vt, vj, mj)| {
-                Ok(Flag {
-                    key: FlagKey::new(k).map_err(|e| domain_key_err(&e))?,
-                    name,
-                    description: desc,
-                    flag_type: serde_json::from_str(&format!(r#""{ft}""#))?,
-                    value_type: serde_json::from_str(&format!(r#""{vt}""#))?,
-                    variants: serde_json::from_value(vj)?,
-                    metadata: serde_json::from_value(mj)?,
-                })
-            })
-            .collect()
+        rows.into_iter().map(row_to_flag).collect()
+    }
+
+    async fn list_flags_lenient(&self, project: &ProjectKey) -> StoreResult<LenientFlagList> {
+        let rows: Vec<FlagRow> = sqlx::query_as(
+            "SELECT key, name, description, flag_type, value_type, variants_json, metadata_json FROM flags WHERE project_key = $1",
+        )
+        .bind(project.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut flags = Vec::with_capacity(rows.len());
+        let mut skipped = Vec::new();
+        for row in rows {
+            let key = row.0.clone();
+            match row_to_flag(row) {
+                Ok(flag) => flags.push(flag),
+                Err(err) => {
+                    warn!(project = project.as_str(), flag = %key, error = %err, "skipping undecodable flag row");
+                    skipped.push((key, err));
+                }
+            }
+        }
+        Ok((flags, skipped))
+    }
+
+    async fn page_flags(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> StoreResult<Page<Flag>> {
+        let rows: Vec<FlagRow> = sqlx::query_as(
+            "SELECT key, name, description, flag_type, value_type, variants_json, metadata_json FROM flags \
+             WHERE project_key = $1 AND ($2::TEXT IS NULL OR key > $2) ORDER BY key LIMIT $3",
+        )
+        .bind(project.as_str())
+        .bind(request.after_position())
+        .bind(request.fetch_limit())
+        .fetch_all(&self.pool)
+        .await?;
+        let total = count_in_project(&self.pool, "flags", project).await?;
+
+        Page::from_rows(rows, request, total, |row| row.0.clone(), row_to_flag)
+    }
+
+    async fn count_by_project(&self, project: &ProjectKey) -> StoreResult<usize> {
+        let count = count_in_project(&self.pool, "flags", project).await?;
+        Ok(usize::try_from(count).unwrap_or(usize::MAX))
+    }
+
+    async fn count_enabled_in_env(
+        &self,
+        project: &ProjectKey,
+        environment: &EnvironmentKey,
+    ) -> StoreResult<usize> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM flag_env_configs WHERE project_key = $1 AND environment_key = $2 AND (config_json->>'enabled')::boolean AND config_json->>'kill_reason' IS NULL",
+        )
+        .bind(project.as_str())
+        .bind(environment.as_str())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(usize::try_from(count).unwrap_or(usize::MAX))
     }
 
     async fn delete_flag(
@@ -940,6 +1131,64 @@ impl SegmentRepository for PostgresStore {
             .collect()
     }
 
+    async fn query_segments(
+        &self,
+        project: &ProjectKey,
+        query: &ListSegmentsQuery,
+    ) -> StoreResult<Vec<Segment>> {
+        let sql = format!(
+            "SELECT key, name, match_json FROM segments \
+             WHERE project_key = $1 AND ($2::TEXT IS NULL OR strpos(key, $2) > 0) {}",
+            query.sort_by.order_by(query.descending)
+        );
+        let rows: Vec<SegmentRow> = sqlx::query_as(&sql)
+            .bind(project.as_str())
+            .bind(query.key_contains.as_deref().map(str::to_lowercase))
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|(k, name, mj)| {
+                Ok(Segment {
+                    key: SegmentKey::new(k).map_err(|e| domain_key_err(&e))?,
+                    name,
+                    match_expr: serde_json::from_value(mj)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn page_segments(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> StoreResult<Page<Segment>> {
+        let rows: Vec<SegmentRow> = sqlx::query_as(
+            "SELECT key, name, match_json FROM segments \
+             WHERE project_key = $1 AND ($2::TEXT IS NULL OR key > $2) ORDER BY key LIMIT $3",
+        )
+        .bind(project.as_str())
+        .bind(request.after_position())
+        .bind(request.fetch_limit())
+        .fetch_all(&self.pool)
+        .await?;
+        let total = count_in_project(&self.pool, "segments", project).await?;
+
+        Page::from_rows(
+            rows,
+            request,
+            total,
+            |row| row.0.clone(),
+            |(k, name, mj)| {
+                Ok(Segment {
+                    key: SegmentKey::new(k).map_err(|e| domain_key_err(&e))?,
+                    name,
+                    match_expr: serde_json::from_value(mj)?,
+                })
+            },
+        )
+    }
+
     async fn delete_segment(
         &self,
         actor: &str,
@@ -1252,7 +1501,7 @@ impl AccountRepository for PostgresStore {
         username: &str,
         password: &str,
     ) -> StoreResult<AccountRecord> {
-        let id = uuid::Uuid::new_v4().to_string();
+        let id = Uuid::new_v4();
         let password_hash = hash_password(password)?;
         let now = crate::clock::now_rfc3339();
 
@@ -1260,7 +1509,7 @@ impl AccountRepository for PostgresStore {
             r"INSERT INTO accounts (id, username, password_hash, is_active, created_at)
               VALUES ($1, $2, $3, true, $4)",
         )
-        .bind(&id)
+        .bind(id)
         .bind(username)
         .bind(&password_hash)
         .bind(&now)
@@ -1278,7 +1527,7 @@ impl AccountRepository for PostgresStore {
         }
 
         let record = AccountRecord {
-            id: id.clone(),
+            id: id.to_string(),
             username: username.to_owned(),
         };
 
@@ -1286,7 +1535,7 @@ impl AccountRepository for PostgresStore {
             actor: actor.to_owned(),
             action: "account.created".to_owned(),
             entity_type: "account".to_owned(),
-            entity_id: id,
+            entity_id: record.id.clone(),
             before: None,
             after: None,
             occurred_at: now,
@@ -1301,7 +1550,7 @@ impl AccountRepository for PostgresStore {
         username: &str,
         password: &str,
     ) -> StoreResult<Option<AccountRecord>> {
-        let row: Option<(String, String, String, bool)> = sqlx::query_as(
+        let row: Option<(Uuid, String, String, bool)> = sqlx::query_as(
             "SELECT id, username, password_hash, is_active FROM accounts WHERE username = $1",
         )
         .bind(username)
@@ -1322,7 +1571,7 @@ impl AccountRepository for PostgresStore {
 
         if verify_password_off_runtime(password, &hash).await {
             Ok(Some(AccountRecord {
-                id,
+                id: id.to_string(),
                 username: uname,
             }))
         } else {
@@ -1337,6 +1586,9 @@ impl AccountRepository for PostgresStore {
 
 impl SessionRepository for PostgresStore {
     async fn create_session(&self, account_id: &str, ttl: Duration) -> StoreResult<NewSession> {
+        // A malformed id cannot name an account.
+        let account_id =
+            Uuid::parse_str(account_id).map_err(|_| StoreError::ForeignKeyViolation)?;
         let raw_token = generate_token();
         let token_hash = self.hasher.hash(&raw_token);
         let now_secs = std::time::SystemTime::now()
@@ -1366,7 +1618,7 @@ impl SessionRepository for PostgresStore {
         let token_hash = self.hasher.hash(raw_token);
         let now = crate::clock::now_rfc3339();
 
-        let row: Option<(String, String)> = sqlx::query_as(
+        let row: Option<(Uuid, String)> = sqlx::query_as(
             "SELECT a.id, a.username \
              FROM sessions s \
              JOIN accounts a ON a.id = s.account_id \
@@ -1380,7 +1632,10 @@ impl SessionRepository for PostgresStore {
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(row.map(|(id, username)| AccountRecord { id, username }))
+        Ok(row.map(|(id, username)| AccountRecord {
+            id: id.to_string(),
+            username,
+        }))
     }
 
     async fn revoke_session(&self, raw_token: &str) -> StoreResult<()> {
@@ -1412,6 +1667,17 @@ type AuditRow = (
     Option<serde_json::Value>,
     String,
 );
+/// An [`AuditRow`] preceded by its id, the keyset of audit pages.
+type AuditPageRow = (
+    i64,
+    String,
+    String,
+    String,
+    String,
+    Option<serde_json::Value>,
+    Option<serde_json::Value>,
+    String,
+);
 
 fn row_to_audit_record(
     actor: String,
@@ -1460,6 +1726,39 @@ impl AuditLogRepository for PostgresStore {
             .collect())
     }
 
+    async fn page_audit_entries(&self, request: &PageRequest) -> StoreResult<Page<AuditRecord>> {
+        let after = request.after.as_ref().map(Cursor::id).transpose()?;
+        let rows: Vec<AuditPageRow> = sqlx::query_as(
+            "SELECT id, actor, action, entity_type, entity_id, before_json, after_json, occurred_at \
+             FROM audit_log WHERE ($1::BIGINT IS NULL OR id > $1) ORDER BY id LIMIT $2",
+        )
+        .bind(after)
+        .bind(request.fetch_limit())
+        .fetch_all(&self.pool)
+        .await?;
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Page::from_rows(
+            rows,
+            request,
+            total,
+            |row| row.0.to_string(),
+            |(_, actor, action, entity_type, entity_id, before_json, after_json, occurred_at)| {
+                Ok(row_to_audit_record(
+                    actor,
+                    action,
+                    entity_type,
+                    entity_id,
+                    before_json,
+                    after_json,
+                    occurred_at,
+                ))
+            },
+        )
+    }
+
     async fn audit_entries_for(
         &self,
         entity_type: &str,
@@ -1491,6 +1790,45 @@ impl AuditLogRepository for PostgresStore {
             )
             .collect())
     }
+
+    async fn recent_audit_entries(
+        &self,
+        entity_type: &str,
+        project: &ProjectKey,
+        window: Duration,
+    ) -> StoreResult<Vec<AuditRecord>> {
+        let since = crate::clock::rfc3339_before(window);
+        // Project keys are kebab-case, so the pattern holds no wildcard but
+        // its trailing `%`.
+        let entity_ids = format!("{}/%", project.as_str());
+        let rows: Vec<AuditRow> = sqlx::query_as(
+            "SELECT actor, action, entity_type, entity_id, before_json, after_json, occurred_at \
+             FROM audit_log WHERE entity_type = $1 AND entity_id LIKE $2 AND occurred_at >= $3 \
+             ORDER BY id ASC",
+        )
+        .bind(entity_type)
+        .bind(entity_ids)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(actor, action, entity_type, entity_id, before_json, after_json, occurred_at)| {
+                    row_to_audit_record(
+                        actor,
+                        action,
+                        entity_type,
+                        entity_id,
+                        before_json,
+                        after_json,
+                        occurred_at,
+                    )
+                },
+            )
+            .collect())
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -1619,6 +1957,27 @@ impl WriteSession for PostgresWriteSession<'_> {
         append_audit(&mut *self.tx, &record).await
     }
 
+    async fn delete_segment(&mut self, project: &ProjectKey, key: &SegmentKey) -> StoreResult<()> {
+        let Some(before) = do_get_segment(&mut *self.tx, project, key).await? else {
+            return Ok(());
+        };
+        sqlx::query("DELETE FROM segments WHERE project_key = $1 AND key = $2")
+            .bind(project.as_str())
+            .bind(key.as_str())
+            .execute(&mut *self.tx)
+            .await?;
+        let record = AuditRecord {
+            actor: self.actor.clone(),
+            action: "segment.deleted".to_owned(),
+            entity_type: "segment".to_owned(),
+            entity_id: format!("{}/{}", project.as_str(), key.as_str()),
+            before: Some(serde_json::to_value(&before).map_err(StoreError::Serialization)?),
+            after: None,
+            occurred_at: crate::clock::now_rfc3339(),
+        };
+        append_audit(&mut *self.tx, &record).await
+    }
+
     async fn upsert_flag_env_config(
         &mut self,
         project: &ProjectKey,
@@ -1655,6 +2014,43 @@ impl WriteSession for PostgresWriteSession<'_> {
         append_audit(&mut *self.tx, &record).await
     }
 
+    async fn delete_flag_env_config(
+        &mut self,
+        project: &ProjectKey,
+        flag: &FlagKey,
+        environment: &EnvironmentKey,
+    ) -> StoreResult<()> {
+        let Some(before) =
+            do_get_flag_env_config(&mut *self.tx, project, flag, environment).await?
+        else {
+            return Ok(());
+        };
+        sqlx::query(
+            "DELETE FROM flag_env_configs WHERE project_key = $1 AND flag_key = $2 AND environment_key = $3",
+        )
+        .bind(project.as_str())
+        .bind(flag.as_str())
+        .bind(environment.as_str())
+        .execute(&mut *self.tx)
+        .await?;
+        let entity_id = format!(
+            "{}/{}/{}",
+            project.as_str(),
+            flag.as_str(),
+            environment.as_str()
+        );
+        let record = AuditRecord {
+            actor: self.actor.clone(),
+            action: "flag_env_config.deleted".to_owned(),
+            entity_type: "flag_env_config".to_owned(),
+            entity_id,
+            before: Some(serde_json::to_value(&before).map_err(StoreError::Serialization)?),
+            after: None,
+            occurred_at: crate::clock::now_rfc3339(),
+        };
+        append_audit(&mut *self.tx, &record).await
+    }
+
     async fn commit(self) -> StoreResult<()> {
         self.tx.commit().await?;
         Ok(())
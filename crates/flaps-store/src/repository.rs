@@ -7,6 +7,9 @@ pub mod audit_log;
 pub mod environment;
 pub mod flag;
 pub mod flag_env_config;
+pub mod health;
+pub mod list;
+pub mod page;
 pub mod project;
 pub mod sdk_key;
 pub mod segment;
@@ -14,10 +17,13 @@ pub mod transaction;
 
 pub use account::{AccountRepository, SessionRepository};
 pub use audit_log::AuditLogRepository;
-pub use environment::EnvironmentRepository;
-pub use flag::FlagRepository;
+pub use environment::{EnvironmentRepository, ListEnvironmentsQuery};
+pub use flag::{FlagRepository, LenientFlagList};
 pub use flag_env_config::FlagEnvConfigRepository;
+pub use health::HealthRepository;
+pub use list::SortBy;
+pub use page::{Cursor, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT, Page, PageRequest};
 pub use project::ProjectRepository;
 pub use sdk_key::SdkKeyRepository;
-pub use segment::SegmentRepository;
+pub use segment::{ListSegmentsQuery, SegmentRepository};
 pub use transaction::{TransactionalStore, WriteSession};
@@ -5,8 +5,15 @@
 //! transaction as each mutation.
 
 use std::future::Future;
+use std::time::Duration;
 
-use crate::{audit::AuditRecord, error::StoreResult};
+use flaps_domain::ProjectKey;
+
+use crate::{
+    audit::AuditRecord,
+    error::StoreResult,
+    repository::page::{Page, PageRequest},
+};
 
 /// Read-only access to the append-only audit log.
 ///
@@ -16,6 +23,15 @@ pub trait AuditLogRepository: Send + Sync {
     /// Returns all audit records, oldest first.
     fn list_audit_entries(&self) -> impl Future<Output = StoreResult<Vec<AuditRecord>>> + Send;
 
+    /// Returns one page of the audit records, oldest first.
+    ///
+    /// A walk concurrent with writes may miss an entry whose transaction
+    /// commits late; see [the pagination module](super::page).
+    fn page_audit_entries(
+        &self,
+        request: &PageRequest,
+    ) -> impl Future<Output = StoreResult<Page<AuditRecord>>> + Send;
+
     /// Returns audit records for one entity (identified by `entity_type` and
     /// `entity_id`), oldest first.
     fn audit_entries_for(
@@ -23,4 +39,14 @@ pub trait AuditLogRepository: Send + Sync {
         entity_type: &str,
         entity_id: &str,
     ) -> impl Future<Output = StoreResult<Vec<AuditRecord>>> + Send;
+
+    /// Returns the audit records of one entity type within `project` (whose
+    /// entity id starts with `<project>/`) written within the last `window`,
+    /// oldest first.
+    fn recent_audit_entries(
+        &self,
+        entity_type: &str,
+        project: &ProjectKey,
+        window: Duration,
+    ) -> impl Future<Output = StoreResult<Vec<AuditRecord>>> + Send;
 }
@@ -2,9 +2,27 @@
 
 use std::future::Future;
 
-use flaps_domain::{Environment, EnvironmentKey, ProjectKey};
+use flaps_domain::{Environment, EnvironmentKey, ManagedBy, ProjectKey};
 
 use crate::error::StoreResult;
+use crate::repository::list::SortBy;
+use crate::repository::page::{Page, PageRequest};
+
+/// Filters and order for [`EnvironmentRepository::query_environments`].
+///
+/// The default query matches every environment of the project, sorted by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListEnvironmentsQuery {
+    /// Keeps only the environments owned this way.
+    pub managed_by: Option<ManagedBy>,
+    /// Keeps only the environments whose key contains this text,
+    /// ignoring case.
+    pub key_contains: Option<String>,
+    /// Column the result is sorted by.
+    pub sort_by: SortBy,
+    /// Sorts in descending order.
+    pub descending: bool,
+}
 
 /// Async CRUD operations for [`Environment`] aggregates scoped to a project.
 pub trait EnvironmentRepository: Send + Sync {
@@ -32,6 +50,22 @@ pub trait EnvironmentRepository: Send + Sync {
         project: &ProjectKey,
     ) -> impl Future<Output = StoreResult<Vec<Environment>>> + Send;
 
+    /// Returns the environments of `project` matching `query`, in its order.
+    ///
+    /// Filtering and sorting happen in the database.
+    fn query_environments(
+        &self,
+        project: &ProjectKey,
+        query: &ListEnvironmentsQuery,
+    ) -> impl Future<Output = StoreResult<Vec<Environment>>> + Send;
+
+    /// Returns one page of the environments of `project`, sorted by key.
+    fn page_environments(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> impl Future<Output = StoreResult<Page<Environment>>> + Send;
+
     /// Deletes the environment identified by `project` + `key`.
     ///
     /// `actor` identifies the principal performing the mutation; it is recorded
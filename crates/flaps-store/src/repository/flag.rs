@@ -2,11 +2,19 @@
 
 use std::future::Future;
 
-use flaps_domain::{Flag, FlagKey, ProjectKey};
+use flaps_domain::{EnvironmentKey, Flag, FlagKey, ProjectKey};
 
-use crate::error::StoreResult;
+use crate::error::{StoreError, StoreResult};
+use crate::repository::page::{Page, PageRequest};
+
+/// Result of [`FlagRepository::list_flags_lenient`]: the decoded flags, and the
+/// raw key of every skipped row with the reason it could not be decoded.
+pub type LenientFlagList = (Vec<Flag>, Vec<(String, StoreError)>);
 
 /// Async CRUD operations for [`Flag`] aggregates scoped to a project.
+///
+/// Flags are identified by `(project, key)` and every query filters on the
+/// project, so a key never reads, lists or deletes a flag across projects.
 pub trait FlagRepository: Send + Sync {
     /// Inserts or fully replaces the flag within `project`.
     ///
@@ -32,6 +40,49 @@ pub trait FlagRepository: Send + Sync {
         project: &ProjectKey,
     ) -> impl Future<Output = StoreResult<Vec<Flag>>> + Send;
 
+    /// Returns all decodable flags for `project`, skipping corrupt rows.
+    ///
+    /// Unlike [`list_flags`](Self::list_flags), a row that cannot be decoded
+    /// (an invalid key, malformed variants JSON) does not fail the whole
+    /// call: it is logged and reported alongside its raw key in the second
+    /// element. Database errors still fail the call.
+    fn list_flags_lenient(
+        &self,
+        project: &ProjectKey,
+    ) -> impl Future<Output = StoreResult<LenientFlagList>> + Send;
+
+    /// Returns one page of the flags of `project`, sorted by key.
+    ///
+    /// Unlike [`list_flags_lenient`](Self::list_flags_lenient), an
+    /// undecodable row fails the call.
+    fn page_flags(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> impl Future<Output = StoreResult<Page<Flag>>> + Send;
+
+    /// Returns the number of flags in `project`.
+    ///
+    /// Counted in the database: no row is decoded, so this stays cheap for
+    /// dashboards where [`list_flags`](Self::list_flags) would not.
+    fn count_by_project(
+        &self,
+        project: &ProjectKey,
+    ) -> impl Future<Output = StoreResult<usize>> + Send;
+
+    /// Returns the number of flags of `project` enabled in `environment`.
+    ///
+    /// Counts the flag configurations stored for `environment` whose
+    /// `enabled` is set and whose kill switch is released; a killed flag
+    /// serves like a disabled one, and a flag without a configuration there
+    /// is not enabled. Inheritance does not change the count, since an
+    /// inheriting configuration keeps its own `enabled` and kill switch.
+    fn count_enabled_in_env(
+        &self,
+        project: &ProjectKey,
+        environment: &EnvironmentKey,
+    ) -> impl Future<Output = StoreResult<usize>> + Send;
+
     /// Deletes the flag identified by `project` + `key`.
     ///
     /// `actor` identifies the principal performing the mutation; it is recorded
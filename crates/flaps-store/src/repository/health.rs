@@ -0,0 +1,14 @@
+//! Repository trait for backend liveness.
+
+use std::future::Future;
+
+use crate::error::StoreResult;
+
+/// Reports whether the backing database answers queries.
+pub trait HealthRepository: Send + Sync {
+    /// Round-trips a trivial query through the connection pool.
+    ///
+    /// Waits for a pooled connection like any other query: callers bound the
+    /// wait themselves, as an exhausted pool is an unhealthy one.
+    fn ping(&self) -> impl Future<Output = StoreResult<()>> + Send;
+}
@@ -0,0 +1,31 @@
+//! Ordering shared by the filtered list queries
+//! ([`ListEnvironmentsQuery`](super::environment::ListEnvironmentsQuery),
+//! [`ListSegmentsQuery`](super::segment::ListSegmentsQuery)).
+
+/// Column a filtered list is sorted by. Rows that tie are ordered by key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+    /// The entity key (the default).
+    #[default]
+    Key,
+    /// The display name.
+    Name,
+    /// When the entity was first stored.
+    CreatedAt,
+    /// When the entity was last stored.
+    UpdatedAt,
+}
+
+impl SortBy {
+    /// Returns the `ORDER BY` clause sorting by this column, then by key.
+    pub(crate) fn order_by(self, descending: bool) -> String {
+        let direction = if descending { "DESC" } else { "ASC" };
+        let column = match self {
+            Self::Key => return format!("ORDER BY key {direction}"),
+            Self::Name => "name",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+        };
+        format!("ORDER BY {column} {direction}, key {direction}")
+    }
+}
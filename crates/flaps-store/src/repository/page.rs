@@ -0,0 +1,256 @@
+//! Keyset pagination shared by the paginated list queries
+//! ([`FlagRepository::page_flags`](super::flag::FlagRepository::page_flags),
+//! [`EnvironmentRepository::page_environments`](super::environment::EnvironmentRepository::page_environments),
+//! [`SegmentRepository::page_segments`](super::segment::SegmentRepository::page_segments),
+//! [`AuditLogRepository::page_audit_entries`](super::audit_log::AuditLogRepository::page_audit_entries)).
+//!
+//! A page is read after a [`Cursor`] naming the last row of the previous
+//! page (its key, or its id for the audit log), never at an offset: rows
+//! inserted or deleted while a caller walks the pages cannot shift the rows
+//! it has yet to read, so none is skipped or returned twice. A row inserted
+//! before the cursor is simply not seen by this walk.
+//!
+//! The audit log is the exception to "none is skipped": its ids are handed
+//! out when a row is inserted, not when its transaction commits, so on
+//! PostgreSQL an entry committed after a page was read may carry an id
+//! before that page's cursor and be missed by a walk running concurrently
+//! with writes. A walk started after the writes have committed sees every
+//! entry.
+
+use std::fmt;
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{StoreError, StoreResult};
+
+/// Number of rows of a page when the request does not say.
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+
+/// Largest number of rows a page holds; larger limits are lowered to it.
+pub const MAX_PAGE_LIMIT: usize = 500;
+
+/// Opaque position in a paginated list, handed out as
+/// [`Page::next_cursor`] to read the following page.
+///
+/// Serialized as a URL-safe base64 token. Its content is a store detail:
+/// callers only pass back tokens they were given.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    /// Sort value of the last row of the previous page.
+    position: String,
+}
+
+impl Cursor {
+    /// Creates the cursor following the row whose sort value is `position`.
+    pub(crate) fn new(position: impl Into<String>) -> Self {
+        Self {
+            position: position.into(),
+        }
+    }
+
+    /// Returns the sort value of the last row of the previous page.
+    pub(crate) fn position(&self) -> &str {
+        &self.position
+    }
+
+    /// Returns the cursor as an id, for lists sorted by an integer id.
+    pub(crate) fn id(&self) -> StoreResult<i64> {
+        self.position.parse().map_err(|_| StoreError::InvalidCursor)
+    }
+
+    /// Encodes the cursor as its opaque token.
+    #[must_use]
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.position)
+    }
+
+    /// Decodes a token produced by [`Self::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoreError::InvalidCursor`] when `token` is not one.
+    pub fn decode(token: &str) -> StoreResult<Self> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|_| StoreError::InvalidCursor)?;
+        let position = String::from_utf8(bytes).map_err(|_| StoreError::InvalidCursor)?;
+        if position.is_empty() {
+            return Err(StoreError::InvalidCursor);
+        }
+        Ok(Self { position })
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = StoreError;
+
+    fn from_str(token: &str) -> StoreResult<Self> {
+        Self::decode(token)
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.encode())
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let token = String::deserialize(deserializer)?;
+        Self::decode(&token).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Which page of a list to read.
+///
+/// The default request reads the first [`DEFAULT_PAGE_LIMIT`] rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Reads the rows following this cursor; `None` reads the first page.
+    pub after: Option<Cursor>,
+    /// Maximum number of rows of the page, between 1 and
+    /// [`MAX_PAGE_LIMIT`].
+    pub limit: usize,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            after: None,
+            limit: DEFAULT_PAGE_LIMIT,
+        }
+    }
+}
+
+impl PageRequest {
+    /// Requests the first `limit` rows.
+    #[must_use]
+    pub fn first(limit: usize) -> Self {
+        Self { after: None, limit }
+    }
+
+    /// Requests the `limit` rows following `cursor`.
+    #[must_use]
+    pub fn after(cursor: Cursor, limit: usize) -> Self {
+        Self {
+            after: Some(cursor),
+            limit,
+        }
+    }
+
+    /// Returns the limit, clamped to `1..=MAX_PAGE_LIMIT`.
+    pub(crate) fn clamped_limit(&self) -> usize {
+        self.limit.clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    /// Returns the number of rows to fetch: one more than the page holds,
+    /// telling whether a next page exists.
+    pub(crate) fn fetch_limit(&self) -> i64 {
+        i64::try_from(self.clamped_limit() + 1).unwrap_or(i64::MAX)
+    }
+
+    /// Returns the position of [`Self::after`], as bound in a query.
+    pub(crate) fn after_position(&self) -> Option<&str> {
+        self.after.as_ref().map(Cursor::position)
+    }
+}
+
+/// One page of a list.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Page<T> {
+    /// The rows of the page, in list order.
+    pub items: Vec<T>,
+    /// Cursor reading the following page; `None` on the last page.
+    pub next_cursor: Option<Cursor>,
+    /// Number of rows of the whole list when the page was read.
+    pub total: u64,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from the rows fetched for `request` (up to
+    /// [`PageRequest::fetch_limit`]) and the list's `total`, decoding each
+    /// kept row with `decode` and taking the next cursor from the sort value
+    /// of the last one, given by `position`.
+    pub(crate) fn from_rows<R>(
+        mut rows: Vec<R>,
+        request: &PageRequest,
+        total: i64,
+        position: impl Fn(&R) -> String,
+        decode: impl FnMut(R) -> StoreResult<T>,
+    ) -> StoreResult<Self> {
+        let limit = request.clamped_limit();
+        let next_cursor = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(|row| Cursor::new(position(row)))
+        } else {
+            None
+        };
+        Ok(Self {
+            items: rows.into_iter().map(decode).collect::<StoreResult<_>>()?,
+            next_cursor,
+            total: u64::try_from(total).unwrap_or(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips_through_its_token() {
+        let cursor = Cursor::new("checkout-v2");
+        let token = cursor.encode();
+        assert!(!token.contains("checkout"), "the token is opaque");
+        assert_eq!(Cursor::decode(&token).unwrap(), cursor);
+        assert_eq!(token.parse::<Cursor>().unwrap(), cursor);
+
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(json, format!("\"{token}\""));
+        assert_eq!(serde_json::from_str::<Cursor>(&json).unwrap(), cursor);
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "not base64!", "_w"] {
+            assert!(
+                matches!(Cursor::decode(token), Err(StoreError::InvalidCursor)),
+                "{token:?} should be rejected"
+            );
+        }
+        assert!(matches!(
+            Cursor::new("flag-a").id(),
+            Err(StoreError::InvalidCursor)
+        ));
+    }
+
+    #[test]
+    fn the_extra_row_sets_the_next_cursor() {
+        let request = PageRequest::first(2);
+        let page =
+            Page::from_rows(vec!["a", "b", "c"], &request, 3, |r| (*r).to_owned(), Ok).unwrap();
+        assert_eq!(page.items, ["a", "b"]);
+        assert_eq!(page.next_cursor, Some(Cursor::new("b")));
+        assert_eq!(page.total, 3);
+
+        let last = Page::from_rows(vec!["c"], &request, 3, |r| (*r).to_owned(), Ok).unwrap();
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn limits_are_clamped() {
+        assert_eq!(PageRequest::first(0).clamped_limit(), 1);
+        assert_eq!(PageRequest::first(10_000).clamped_limit(), MAX_PAGE_LIMIT);
+        assert_eq!(PageRequest::default().fetch_limit(), 51);
+    }
+}
@@ -5,6 +5,22 @@ use std::future::Future;
 use flaps_domain::{ProjectKey, Segment, SegmentKey};
 
 use crate::error::StoreResult;
+use crate::repository::list::SortBy;
+use crate::repository::page::{Page, PageRequest};
+
+/// Filters and order for [`SegmentRepository::query_segments`].
+///
+/// The default query matches every segment of the project, sorted by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListSegmentsQuery {
+    /// Keeps only the segments whose key contains this text,
+    /// ignoring case.
+    pub key_contains: Option<String>,
+    /// Column the result is sorted by.
+    pub sort_by: SortBy,
+    /// Sorts in descending order.
+    pub descending: bool,
+}
 
 /// Async CRUD operations for [`Segment`] aggregates scoped to a project.
 pub trait SegmentRepository: Send + Sync {
@@ -32,6 +48,22 @@ pub trait SegmentRepository: Send + Sync {
         project: &ProjectKey,
     ) -> impl Future<Output = StoreResult<Vec<Segment>>> + Send;
 
+    /// Returns the segments of `project` matching `query`, in its order.
+    ///
+    /// Filtering and sorting happen in the database.
+    fn query_segments(
+        &self,
+        project: &ProjectKey,
+        query: &ListSegmentsQuery,
+    ) -> impl Future<Output = StoreResult<Vec<Segment>>> + Send;
+
+    /// Returns one page of the segments of `project`, sorted by key.
+    fn page_segments(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> impl Future<Output = StoreResult<Page<Segment>>> + Send;
+
     /// Deletes the segment identified by `project` + `key`.
     ///
     /// `actor` identifies the principal performing the mutation; it is recorded
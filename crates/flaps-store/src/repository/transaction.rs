@@ -12,6 +12,7 @@ use std::future::Future;
 
 use flaps_domain::{
     Environment, EnvironmentKey, Flag, FlagEnvConfig, FlagKey, Project, ProjectKey, Segment,
+    SegmentKey,
 };
 
 use crate::error::StoreResult;
@@ -19,7 +20,7 @@ use crate::error::StoreResult;
 /// A store that can open a write session spanning multiple mutations atomically.
 pub trait TransactionalStore: Send + Sync {
     /// The concrete session type returned by [`begin`](Self::begin).
-    type Session<'a>: WriteSession
+    type Session<'a>: WriteSession + Send
     where
         Self: 'a;
 
@@ -34,33 +35,58 @@ pub trait TransactionalStore: Send + Sync {
 /// A set of mutations bound to one database transaction.
 ///
 /// Dropping without calling [`commit`](Self::commit) rolls back the transaction.
-#[allow(async_fn_in_trait)]
 pub trait WriteSession {
     /// Inserts or fully replaces the project within the transaction.
-    async fn upsert_project(&mut self, project: &Project) -> StoreResult<()>;
+    fn upsert_project(&mut self, project: &Project)
+    -> impl Future<Output = StoreResult<()>> + Send;
 
     /// Inserts or fully replaces the environment within the transaction.
-    async fn upsert_environment(
+    fn upsert_environment(
         &mut self,
         project: &ProjectKey,
         env: &Environment,
-    ) -> StoreResult<()>;
+    ) -> impl Future<Output = StoreResult<()>> + Send;
 
     /// Inserts or fully replaces the flag within the transaction.
-    async fn upsert_flag(&mut self, project: &ProjectKey, flag: &Flag) -> StoreResult<()>;
+    fn upsert_flag(
+        &mut self,
+        project: &ProjectKey,
+        flag: &Flag,
+    ) -> impl Future<Output = StoreResult<()>> + Send;
 
     /// Inserts or fully replaces the segment within the transaction.
-    async fn upsert_segment(&mut self, project: &ProjectKey, segment: &Segment) -> StoreResult<()>;
+    fn upsert_segment(
+        &mut self,
+        project: &ProjectKey,
+        segment: &Segment,
+    ) -> impl Future<Output = StoreResult<()>> + Send;
+
+    /// Deletes the segment within the transaction; a no-op when it does not
+    /// exist.
+    fn delete_segment(
+        &mut self,
+        project: &ProjectKey,
+        key: &SegmentKey,
+    ) -> impl Future<Output = StoreResult<()>> + Send;
 
     /// Inserts or fully replaces the per-environment flag configuration within the transaction.
-    async fn upsert_flag_env_config(
+    fn upsert_flag_env_config(
         &mut self,
         project: &ProjectKey,
         flag: &FlagKey,
         environment: &EnvironmentKey,
         config: &FlagEnvConfig,
-    ) -> StoreResult<()>;
+    ) -> impl Future<Output = StoreResult<()>> + Send;
+
+    /// Deletes the per-environment flag configuration within the
+    /// transaction; a no-op when it does not exist.
+    fn delete_flag_env_config(
+        &mut self,
+        project: &ProjectKey,
+        flag: &FlagKey,
+        environment: &EnvironmentKey,
+    ) -> impl Future<Output = StoreResult<()>> + Send;
 
     /// Commits the transaction, consuming the session.
-    async fn commit(self) -> StoreResult<()>;
+    fn commit(self) -> impl Future<Output = StoreResult<()>> + Send;
 }
@@ -10,6 +10,7 @@ use sqlx::{
     Executor, Pool, Sqlite, Transaction,
     migrate::{Migration, MigrationType, Migrator},
 };
+use tracing::warn;
 
 use flaps_domain::{
     Environment, EnvironmentKey, ExternalRef, Flag, FlagEnvConfig, FlagKey, ManagedBy, Project,
@@ -19,17 +20,20 @@ use flaps_domain::{
 use crate::{
     account::{AccountRecord, NewSession},
     audit::{AuditRecord, sqlite::append_audit},
+    database::DatabaseConfig,
     error::{StoreError, StoreResult},
     hash::KeyHasher,
     repository::{
         account::{AccountRepository, SessionRepository},
         audit_log::AuditLogRepository,
-        environment::EnvironmentRepository,
-        flag::FlagRepository,
+        environment::{EnvironmentRepository, ListEnvironmentsQuery},
+        flag::{FlagRepository, LenientFlagList},
         flag_env_config::FlagEnvConfigRepository,
+        health::HealthRepository,
+        page::{Cursor, Page, PageRequest},
         project::ProjectRepository,
         sdk_key::SdkKeyRepository,
-        segment::SegmentRepository,
+        segment::{ListSegmentsQuery, SegmentRepository},
         transaction::{TransactionalStore, WriteSession},
     },
     sdk_key::{NewSdkKey, SdkKeyRecord, SdkKeyScope},
@@ -118,7 +122,14 @@ fn secs_to_rfc3339(secs: u64) -> String {
 // ---------------------------------------------------------------------------
 
 type ProjectRow = (String, String, Option<String>, Option<String>, String);
-type EnvRow = (String, String, Option<String>, String, String);
+type EnvRow = (
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    Option<String>,
+);
 type FlagRow = (
     String,
     String,
@@ -144,17 +155,14 @@ fn managed_by_from_str(s: &str) -> StoreResult<ManagedBy> {
     match s {
         "local" => Ok(ManagedBy::Local),
         "federated" => Ok(ManagedBy::Federated),
-        other => Err(StoreError::Serialization(
-            serde_json::from_str::<serde_json::Value>(&format!("\"unknown managed_by: {other}\""))
-                .unwrap_err(),
-        )),
+        other => Err(StoreError::InvalidRow(format!(
+            "unknown managed_by: {other}"
+        ))),
     }
 }
 
 fn domain_key_err(e: &flaps_domain::DomainError) -> StoreError {
-    StoreError::Serialization(
-        serde_json::from_str::<serde_json::Value>(&format!("\"{e}\"")).unwrap_err(),
-    )
+    StoreError::InvalidRow(e.to_string())
 }
 
 fn row_to_project(
@@ -179,6 +187,7 @@ fn row_to_environment(
     ext_ref: Option<String>,
     mb: &str,
     metadata_json: &str,
+    inherits_from: Option<String>,
 ) -> StoreResult<Environment> {
     Ok(Environment {
         key: EnvironmentKey::new(k).map_err(|e| domain_key_err(&e))?,
@@ -186,6 +195,36 @@ fn row_to_environment(
         external_ref: ext_ref.map(ExternalRef::new),
         managed_by: managed_by_from_str(mb)?,
         metadata: serde_json::from_str(metadata_json)?,
+        inherits_from: inherits_from
+            .map(|key| EnvironmentKey::new(key).map_err(|e| domain_key_err(&e)))
+            .transpose()?,
+    })
+}
+
+/// Counts the rows of `table` belonging to `project`. `table` is one of the
+/// project-scoped tables, never caller input.
+async fn count_in_project<'e, E>(executor: E, table: &str, project: &ProjectKey) -> StoreResult<i64>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let count = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM {table} WHERE project_key = ?"
+    ))
+    .bind(project.as_str())
+    .fetch_one(executor)
+    .await?;
+    Ok(count)
+}
+
+fn row_to_flag((k, name, desc, ft, vt, vj, mj): FlagRow) -> StoreResult<Flag> {
+    Ok(Flag {
+        key: FlagKey::new(k).map_err(|e| domain_key_err(&e))?,
+        name,
+        description: desc,
+        flag_type: serde_json::from_str(&format!(r#""{ft}""#))?,
+        value_type: serde_json::from_str(&format!(r#""{vt}""#))?,
+        variants: serde_json::from_str(&vj)?,
+        metadata: serde_json::from_str(&mj)?,
     })
 }
 
@@ -217,15 +256,17 @@ where
     E: Executor<'e, Database = Sqlite>,
 {
     let row: Option<EnvRow> = sqlx::query_as(
-        "SELECT key, name, external_ref, managed_by, metadata_json FROM environments WHERE project_key = ? AND key = ?",
+        "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments WHERE project_key = ? AND key = ?",
     )
     .bind(project.as_str())
     .bind(key.as_str())
     .fetch_optional(executor)
     .await?;
 
-    row.map(|(k, name, ext_ref, mb, meta)| row_to_environment(k, name, ext_ref, &mb, &meta))
-        .transpose()
+    row.map(|(k, name, ext_ref, mb, meta, base)| {
+        row_to_environment(k, name, ext_ref, &mb, &meta, base)
+    })
+    .transpose()
 }
 
 async fn do_get_flag<'e, E>(
@@ -244,18 +285,7 @@ where
     .fetch_optional(executor)
     .await?;
 
-    row.map(|(k, name, desc, ft, vt, vj, mj)| {
-        Ok(Flag {
-            key: FlagKey::new(k).map_err(|e| domain_key_err(&e))?,
-            name,
-            description: desc,
-            flag_type: serde_json::from_str(&format!(r#""{ft}""#))?,
-            value_type: serde_json::from_str(&format!(r#""{vt}""#))?,
-            variants: serde_json::from_str(&vj)?,
-            metadata: serde_json::from_str(&mj)?,
-        })
-    })
-    .transpose()
+    row.map(row_to_flag).transpose()
 }
 
 async fn do_get_segment<'e, E>(
@@ -363,13 +393,14 @@ where
     let now = crate::clock::now_rfc3339();
 
     let result = sqlx::query(
-        r"INSERT INTO environments (project_key, key, name, external_ref, managed_by, metadata_json, created_at, updated_at)
-          VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        r"INSERT INTO environments (project_key, key, name, external_ref, managed_by, metadata_json, inherits_from, created_at, updated_at)
+          VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
           ON CONFLICT(project_key, key) DO UPDATE SET
               name          = excluded.name,
               external_ref  = excluded.external_ref,
               managed_by    = excluded.managed_by,
               metadata_json = excluded.metadata_json,
+              inherits_from = excluded.inherits_from,
               updated_at    = excluded.updated_at",
     )
     .bind(project.as_str())
@@ -378,6 +409,7 @@ where
     .bind(external_ref)
     .bind(managed_by)
     .bind(&metadata_json)
+    .bind(env.inherits_from.as_ref().map(EnvironmentKey::as_str))
     .bind(&now)
     .bind(&now)
     .execute(executor)
@@ -570,6 +602,24 @@ fn embedded_migrator() -> Migrator {
                 )),
                 false,
             ),
+            Migration::new(
+                6,
+                Cow::Borrowed("environment_inheritance"),
+                MigrationType::Simple,
+                Cow::Borrowed(include_str!(
+                    "../../migrations/sqlite/0006_environment_inheritance.sql"
+                )),
+                false,
+            ),
+            Migration::new(
+                7,
+                Cow::Borrowed("flag_env_config_environment_index"),
+                MigrationType::Simple,
+                Cow::Borrowed(include_str!(
+                    "../../migrations/sqlite/0007_flag_env_config_environment_index.sql"
+                )),
+                false,
+            ),
         ]
     });
 
@@ -596,21 +646,36 @@ pub struct SqliteStore {
 impl SqliteStore {
     /// Connects to the given SQLite URL, enables foreign keys, and runs migrations.
     ///
+    /// Pool settings in the URL query string are applied, see
+    /// [`DatabaseConfig::from_url`].
+    ///
     /// # Errors
-    /// Returns [`StoreError`] if the connection or migrations fail.
+    /// Returns [`StoreError`] if the URL carries malformed pool settings, or
+    /// the connection or migrations fail.
     pub async fn connect(url: &str, hasher: KeyHasher) -> StoreResult<Self> {
-        let options = sqlx::sqlite::SqliteConnectOptions::from_str(url)?.create_if_missing(true);
-        let pool = sqlx::sqlite::SqlitePoolOptions::new()
-            .after_connect(|conn, _| {
-                Box::pin(async move {
-                    sqlx::query("PRAGMA foreign_keys = ON")
-                        .execute(conn)
-                        .await?;
-                    Ok(())
-                })
+        Self::connect_with(&DatabaseConfig::from_url(url)?, hasher).await
+    }
+
+    /// Connects with `config`'s URL, pool and statement cache settings,
+    /// enables foreign keys, and runs migrations.
+    ///
+    /// # Errors
+    /// Returns [`StoreError`] if the connection or migrations fail.
+    pub async fn connect_with(config: &DatabaseConfig, hasher: KeyHasher) -> StoreResult<Self> {
+        let mut options =
+            sqlx::sqlite::SqliteConnectOptions::from_str(&config.url)?.create_if_missing(true);
+        if let Some(capacity) = config.statement_cache_capacity {
+            options = options.statement_cache_capacity(capacity);
+        }
+        let pool_options = config.pool_options::<Sqlite>().after_connect(|conn, _| {
+            Box::pin(async move {
+                sqlx::query("PRAGMA foreign_keys = ON")
+                    .execute(conn)
+                    .await?;
+                Ok(())
             })
-            .connect_with(options)
-            .await?;
+        });
+        let pool = config.open_pool(pool_options, options).await?;
         embedded_migrator().run(&pool).await?;
         Ok(Self {
             pool,
@@ -656,6 +721,17 @@ impl SqliteStore {
     }
 }
 
+// ---------------------------------------------------------------------------
+// HealthRepository for SqliteStore
+// ---------------------------------------------------------------------------
+
+impl HealthRepository for SqliteStore {
+    async fn ping(&self) -> StoreResult<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
 // ---------------------------------------------------------------------------
 // ProjectRepository for SqliteStore
 // ---------------------------------------------------------------------------
@@ -777,17 +853,70 @@ impl EnvironmentRepository for SqliteStore {
 
     async fn list_environments(&self, project: &ProjectKey) -> StoreResult<Vec<Environment>> {
         let rows: Vec<EnvRow> = sqlx::query_as(
-            "SELECT key, name, external_ref, managed_by, metadata_json FROM environments WHERE project_key = ?",
+            "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments WHERE project_key = ?",
         )
         .bind(project.as_str())
         .fetch_all(&self.pool)
         .await?;
 
         rows.into_iter()
-            .map(|(k, name, ext_ref, mb, meta)| row_to_environment(k, name, ext_ref, &mb, &meta))
+            .map(|(k, name, ext_ref, mb, meta, base)| {
+                row_to_environment(k, name, ext_ref, &mb, &meta, base)
+            })
+            .collect()
+    }
+
+    async fn query_environments(
+        &self,
+        project: &ProjectKey,
+        query: &ListEnvironmentsQuery,
+    ) -> StoreResult<Vec<Environment>> {
+        let sql = format!(
+            "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments \
+             WHERE project_key = ?1 AND (?2 IS NULL OR managed_by = ?2) AND (?3 IS NULL OR instr(key, ?3) > 0) {}",
+            query.sort_by.order_by(query.descending)
+        );
+        let rows: Vec<EnvRow> = sqlx::query_as(&sql)
+            .bind(project.as_str())
+            .bind(query.managed_by.map(managed_by_str))
+            .bind(query.key_contains.as_deref().map(str::to_lowercase))
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|(k, name, ext_ref, mb, meta, base)| {
+                row_to_environment(k, name, ext_ref, &mb, &meta, base)
+            })
             .collect()
     }
 
+    async fn page_environments(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> StoreResult<Page<Environment>> {
+        let rows: Vec<EnvRow> = sqlx::query_as(
+            "SELECT key, name, external_ref, managed_by, metadata_json, inherits_from FROM environments \
+             WHERE project_key = ?1 AND (?2 IS NULL OR key > ?2) ORDER BY key LIMIT ?3",
+        )
+        .bind(project.as_str())
+        .bind(request.after_position())
+        .bind(request.fetch_limit())
+        .fetch_all(&self.pool)
+        .await?;
+        let total = count_in_project(&self.pool, "environments", project).await?;
+
+        Page::from_rows(
+            rows,
+            request,
+            total,
+            |row| row.0.clone(),
+            |(k, name, ext_ref, mb, meta, base)| {
+                row_to_environment(k, name, ext_ref, &mb, &meta, base)
+            },
+        )
+    }
+
     async fn delete_environment(
         &self,
         actor: &str,
@@ -866,19 +995,69 @@ impl FlagRepository for SqliteStore {
         .fetch_all(&self.pool)
         .await?;
 
-        rows.into_iter()
-            .map(|(k, name, desc, ft, vt, vj, mj)| {
-                Ok(Flag {
-                    key: FlagKey::new(k).map_err(|e| domain_key_err(&e))?,
-                    name,
-                    description: desc,
-                    flag_type: serde_json::from_str(&format!(r#""{ft}""#))?,
-                    value_type: serde_json::from_str(&format!(r#""{vt}""#))?,
-                    variants: serde_json::from_str(&vj)?,
-                    metadata: serde_json::from_str(&mj)?,
-                })
-            })
-            .collect()
+        rows.into_iter().map(row_to_flag).collect()
+    }
+
+    async fn list_flags_lenient(&self, project: &ProjectKey) -> StoreResult<LenientFlagList> {
+        let rows: Vec<FlagRow> = sqlx::query_as(
+            "SELECT key, name, description, flag_type, value_type, variants_json, metadata_json FROM flags WHERE project_key = ?",
+        )
+        .bind(project.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut flags = Vec::with_capacity(rows.len());
+        let mut skipped = Vec::new();
+        for row in rows {
+            let key = row.0.clone();
+            match row_to_flag(row) {
+                Ok(flag) => flags.push(flag),
+                Err(err) => {
+                    warn!(project = project.as_str(), flag = %key, error = %err, "skipping undecodable flag row");
+                    skipped.push((key, err));
+                }
+            }
+        }
+        Ok((flags, skipped))
+    }
+
+    async fn page_flags(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> StoreResult<Page<Flag>> {
+        let rows: Vec<FlagRow> = sqlx::query_as(
+            "SELECT key, name, description, flag_type, value_type, variants_json, metadata_json FROM flags \
+             WHERE project_key = ?1 AND (?2 IS NULL OR key > ?2) ORDER BY key LIMIT ?3",
+        )
+        .bind(project.as_str())
+        .bind(request.after_position())
+        .bind(request.fetch_limit())
+        .fetch_all(&self.pool)
+        .await?;
+        let total = count_in_project(&self.pool, "flags", project).await?;
+
+        Page::from_rows(rows, request, total, |row| row.0.clone(), row_to_flag)
+    }
+
+    async fn count_by_project(&self, project: &ProjectKey) -> StoreResult<usize> {
+        let count = count_in_project(&self.pool, "flags", project).await?;
+        Ok(usize::try_from(count).unwrap_or(usize::MAX))
+    }
+
+    async fn count_enabled_in_env(
+        &self,
+        project: &ProjectKey,
+        environment: &EnvironmentKey,
+    ) -> StoreResult<usize> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM flag_env_configs WHERE project_key = ? AND environment_key = ? AND json_extract(config_json, '$.enabled') = 1 AND json_extract(config_json, '$.kill_reason') IS NULL",
+        )
+        .bind(project.as_str())
+        .bind(environment.as_str())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(usize::try_from(count).unwrap_or(usize::MAX))
     }
 
     async fn delete_flag(
@@ -978,6 +1157,64 @@ impl SegmentRepository for SqliteStore {
             .collect()
     }
 
+    async fn query_segments(
+        &self,
+        project: &ProjectKey,
+        query: &ListSegmentsQuery,
+    ) -> StoreResult<Vec<Segment>> {
+        let sql = format!(
+            "SELECT key, name, match_json FROM segments \
+             WHERE project_key = ?1 AND (?2 IS NULL OR instr(key, ?2) > 0) {}",
+            query.sort_by.order_by(query.descending)
+        );
+        let rows: Vec<(String, String, String)> = sqlx::query_as(&sql)
+            .bind(project.as_str())
+            .bind(query.key_contains.as_deref().map(str::to_lowercase))
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|(k, name, mj)| {
+                Ok(Segment {
+                    key: SegmentKey::new(k).map_err(|e| domain_key_err(&e))?,
+                    name,
+                    match_expr: serde_json::from_str(&mj)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn page_segments(
+        &self,
+        project: &ProjectKey,
+        request: &PageRequest,
+    ) -> StoreResult<Page<Segment>> {
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT key, name, match_json FROM segments \
+             WHERE project_key = ?1 AND (?2 IS NULL OR key > ?2) ORDER BY key LIMIT ?3",
+        )
+        .bind(project.as_str())
+        .bind(request.after_position())
+        .bind(request.fetch_limit())
+        .fetch_all(&self.pool)
+        .await?;
+        let total = count_in_project(&self.pool, "segments", project).await?;
+
+        Page::from_rows(
+            rows,
+            request,
+            total,
+            |row| row.0.clone(),
+            |(k, name, mj)| {
+                Ok(Segment {
+                    key: SegmentKey::new(k).map_err(|e| domain_key_err(&e))?,
+                    name,
+                    match_expr: serde_json::from_str(&mj)?,
+                })
+            },
+        )
+    }
+
     async fn delete_segment(
         &self,
         actor: &str,
@@ -1293,6 +1530,17 @@ type AuditRow = (
     Option<String>,
     String,
 );
+/// An [`AuditRow`] preceded by its id, the keyset of audit pages.
+type AuditPageRow = (
+    i64,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+);
 
 fn row_to_audit_record(
     actor: String,
@@ -1348,6 +1596,40 @@ impl AuditLogRepository for SqliteStore {
             .collect()
     }
 
+    async fn page_audit_entries(&self, request: &PageRequest) -> StoreResult<Page<AuditRecord>> {
+        let after = request.after.as_ref().map(Cursor::id).transpose()?;
+        let rows: Vec<AuditPageRow> =
+            sqlx::query_as(
+                "SELECT id, actor, action, entity_type, entity_id, before_json, after_json, occurred_at \
+                 FROM audit_log WHERE (?1 IS NULL OR id > ?1) ORDER BY id LIMIT ?2",
+            )
+            .bind(after)
+            .bind(request.fetch_limit())
+            .fetch_all(&self.pool)
+            .await?;
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Page::from_rows(
+            rows,
+            request,
+            total,
+            |row| row.0.to_string(),
+            |(_, actor, action, entity_type, entity_id, before_json, after_json, occurred_at)| {
+                row_to_audit_record(
+                    actor,
+                    action,
+                    entity_type,
+                    entity_id,
+                    before_json,
+                    after_json,
+                    occurred_at,
+                )
+            },
+        )
+    }
+
     async fn audit_entries_for(
         &self,
         entity_type: &str,
@@ -1378,6 +1660,44 @@ impl AuditLogRepository for SqliteStore {
             )
             .collect()
     }
+
+    async fn recent_audit_entries(
+        &self,
+        entity_type: &str,
+        project: &ProjectKey,
+        window: Duration,
+    ) -> StoreResult<Vec<AuditRecord>> {
+        let since = crate::clock::rfc3339_before(window);
+        // Project keys are kebab-case, so the pattern holds no wildcard but
+        // its trailing `%`.
+        let entity_ids = format!("{}/%", project.as_str());
+        let rows: Vec<AuditRow> = sqlx::query_as(
+            "SELECT actor, action, entity_type, entity_id, before_json, after_json, occurred_at \
+             FROM audit_log WHERE entity_type = ? AND entity_id LIKE ? AND occurred_at >= ? \
+             ORDER BY id ASC",
+        )
+        .bind(entity_type)
+        .bind(entity_ids)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(
+                |(actor, action, entity_type, entity_id, before_json, after_json, occurred_at)| {
+                    row_to_audit_record(
+                        actor,
+                        action,
+                        entity_type,
+                        entity_id,
+                        before_json,
+                        after_json,
+                        occurred_at,
+                    )
+                },
+            )
+            .collect()
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -1664,6 +1984,27 @@ impl WriteSession for SqliteWriteSession<'_> {
         append_audit(&mut *self.tx, &record).await
     }
 
+    async fn delete_segment(&mut self, project: &ProjectKey, key: &SegmentKey) -> StoreResult<()> {
+        let Some(before) = do_get_segment(&mut *self.tx, project, key).await? else {
+            return Ok(());
+        };
+        sqlx::query("DELETE FROM segments WHERE project_key = ? AND key = ?")
+            .bind(project.as_str())
+            .bind(key.as_str())
+            .execute(&mut *self.tx)
+            .await?;
+        let record = AuditRecord {
+            actor: self.actor.clone(),
+            action: "segment.deleted".to_owned(),
+            entity_type: "segment".to_owned(),
+            entity_id: format!("{}/{}", project.as_str(), key.as_str()),
+            before: Some(serde_json::to_value(&before).map_err(StoreError::Serialization)?),
+            after: None,
+            occurred_at: crate::clock::now_rfc3339(),
+        };
+        append_audit(&mut *self.tx, &record).await
+    }
+
     async fn upsert_flag_env_config(
         &mut self,
         project: &ProjectKey,
@@ -1700,6 +2041,43 @@ impl WriteSession for SqliteWriteSession<'_> {
         append_audit(&mut *self.tx, &record).await
     }
 
+    async fn delete_flag_env_config(
+        &mut self,
+        project: &ProjectKey,
+        flag: &FlagKey,
+        environment: &EnvironmentKey,
+    ) -> StoreResult<()> {
+        let Some(before) =
+            do_get_flag_env_config(&mut *self.tx, project, flag, environment).await?
+        else {
+            return Ok(());
+        };
+        sqlx::query(
+            "DELETE FROM flag_env_configs WHERE project_key = ? AND flag_key = ? AND environment_key = ?",
+        )
+        .bind(project.as_str())
+        .bind(flag.as_str())
+        .bind(environment.as_str())
+        .execute(&mut *self.tx)
+        .await?;
+        let entity_id = format!(
+            "{}/{}/{}",
+            project.as_str(),
+            flag.as_str(),
+            environment.as_str()
+        );
+        let record = AuditRecord {
+            actor: self.actor.clone(),
+            action: "flag_env_config.deleted".to_owned(),
+            entity_type: "flag_env_config".to_owned(),
+            entity_id,
+            before: Some(serde_json::to_value(&before).map_err(StoreError::Serialization)?),
+            after: None,
+            occurred_at: crate::clock::now_rfc3339(),
+        };
+        append_audit(&mut *self.tx, &record).await
+    }
+
     async fn commit(self) -> StoreResult<()> {
         self.tx.commit().await?;
         Ok(())
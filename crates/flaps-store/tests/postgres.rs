@@ -6,7 +6,11 @@
 
 mod shared;
 
-use flaps_store::{KeyHasher, postgres::PostgresStore, repository::AccountRepository};
+use flaps_store::{
+    KeyHasher, StoreError,
+    postgres::PostgresStore,
+    repository::{AccountRepository, SessionRepository},
+};
 
 /// Runs the full shared suite against a PostgreSQL instance.
 ///
@@ -71,3 +75,76 @@ async fn inactive_account_cannot_authenticate() {
         "inactive account must return None even with the correct password (anti-enumeration)"
     );
 }
+
+/// Account ids live in native `uuid` columns (migration 0008): accounts and
+/// sessions still round-trip through the string ids of `AccountRecord`, and
+/// text that is not a UUID can no longer be stored as an id.
+///
+/// Skipped silently when `FLAPS_TEST_POSTGRES_URL` is not set.
+#[tokio::test]
+async fn account_ids_are_native_uuids() {
+    let Ok(url) = std::env::var("FLAPS_TEST_POSTGRES_URL") else {
+        return;
+    };
+    let store = PostgresStore::connect(&url, KeyHasher::new(b"uuid-test-pepper".to_vec()))
+        .await
+        .unwrap();
+
+    let account = store
+        .create_account("system", "erin-uuid-pg", "correct-password")
+        .await
+        .unwrap();
+    uuid::Uuid::parse_str(&account.id).expect("account ids are UUIDs");
+    let verified = store
+        .verify_credentials("erin-uuid-pg", "correct-password")
+        .await
+        .unwrap();
+    assert_eq!(verified.as_ref(), Some(&account));
+    let session = store
+        .create_session(&account.id, std::time::Duration::from_secs(60))
+        .await
+        .unwrap();
+    let resolved = store.resolve_session(&session.token).await.unwrap();
+    assert_eq!(resolved, Some(account));
+
+    let err = store
+        .create_session("not-a-uuid", std::time::Duration::from_secs(60))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, StoreError::ForeignKeyViolation),
+        "a malformed account id must be refused, got {err:?}"
+    );
+
+    let raw_pool = sqlx::postgres::PgPoolOptions::new()
+        .connect(&url)
+        .await
+        .unwrap();
+    let column_types: Vec<(String, String)> = sqlx::query_as(
+        "SELECT table_name::text, data_type::text FROM information_schema.columns \
+         WHERE (table_name, column_name) IN (('accounts', 'id'), ('sessions', 'account_id')) \
+         ORDER BY table_name",
+    )
+    .fetch_all(&raw_pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        column_types,
+        [
+            ("accounts".to_owned(), "uuid".to_owned()),
+            ("sessions".to_owned(), "uuid".to_owned()),
+        ]
+    );
+    let insert = sqlx::query(
+        "INSERT INTO accounts (id, username, password_hash, is_active, created_at) \
+         VALUES ($1::text::uuid, 'frank-uuid-pg', 'x', true, '2026-01-01T00:00:00Z')",
+    )
+    .bind("not-a-uuid")
+    .execute(&raw_pool)
+    .await;
+    assert!(
+        insert.is_err(),
+        "malformed text must not be stored as an id"
+    );
+    raw_pool.close().await;
+}
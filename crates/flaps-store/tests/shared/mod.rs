@@ -5,16 +5,17 @@ use std::time::Duration;
 use flaps_domain::SdkKeyKind;
 use flaps_domain::{
     Environment, EnvironmentKey, ExternalRef, Flag, FlagEnvConfig, FlagKey, FlagType, ManagedBy,
-    MatchOperator, Metadata, MetadataValue, Predicate, Project, ProjectKey, Segment, SegmentKey,
-    SegmentMatch, ServeTarget, TargetingRule, ValueType, VariantKey, VariantValue, Variants,
-    WeightedVariant,
+    MatchOperator, Metadata, MetadataValue, Predicate, Project, ProjectKey, RolloutScope, Segment,
+    SegmentKey, SegmentMatch, ServeTarget, TargetingRule, ValueType, VariantKey, VariantValue,
+    Variants, WeightedVariant,
 };
 use flaps_store::{
-    AuditRecord, KeyHasher, NewSdkKey, SdkKeyScope,
+    AuditRecord, KeyHasher, NewSdkKey, SdkKeyScope, StoreError,
     repository::{
-        AccountRepository, AuditLogRepository, EnvironmentRepository, FlagEnvConfigRepository,
-        FlagRepository, ProjectRepository, SdkKeyRepository, SegmentRepository, SessionRepository,
-        TransactionalStore, WriteSession,
+        AccountRepository, AuditLogRepository, Cursor, EnvironmentRepository,
+        FlagEnvConfigRepository, FlagRepository, HealthRepository, ListEnvironmentsQuery,
+        ListSegmentsQuery, Page, PageRequest, ProjectRepository, SdkKeyRepository,
+        SegmentRepository, SessionRepository, SortBy, TransactionalStore, WriteSession,
     },
 };
 
@@ -49,6 +50,7 @@ pub(crate) fn make_env(key: &str) -> Environment {
         external_ref: None,
         managed_by: ManagedBy::Local,
         metadata: Metadata::new(),
+        inherits_from: None,
     }
 }
 
@@ -61,6 +63,7 @@ pub(crate) fn make_env_with_ref(key: &str, ext_ref: &str) -> Environment {
         external_ref: Some(ExternalRef::new(ext_ref)),
         managed_by: ManagedBy::Federated,
         metadata: Metadata::new(),
+        inherits_from: None,
     }
 }
 
@@ -75,6 +78,7 @@ pub(crate) fn make_env_with_metadata(key: &str) -> Environment {
         external_ref: None,
         managed_by: ManagedBy::Local,
         metadata,
+        inherits_from: None,
     }
 }
 
@@ -129,17 +133,20 @@ pub(crate) fn make_segment(key: &str) -> Segment {
                 attribute: "tier".into(),
                 operator: MatchOperator::Equals,
                 values: vec![serde_json::json!("beta")],
+                case_insensitive: false,
             }),
             SegmentMatch::Predicate(Predicate {
                 attribute: "plan".into(),
                 operator: MatchOperator::In,
                 values: vec![serde_json::json!("pro"), serde_json::json!("enterprise")],
+                case_insensitive: false,
             }),
         ]),
         SegmentMatch::Not(Box::new(SegmentMatch::Predicate(Predicate {
             attribute: "blocked".into(),
             operator: MatchOperator::Equals,
             values: vec![serde_json::json!(true)],
+            case_insensitive: false,
         }))),
     ]);
     Segment {
@@ -154,7 +161,10 @@ pub(crate) fn make_flag_env_config() -> FlagEnvConfig {
         enabled: true,
         rules: vec![TargetingRule {
             segments: vec![SegmentKey::new("beta-users").unwrap()],
+            segment_groups: Vec::new(),
             serve: ServeTarget::Fixed(VariantKey::new("on").unwrap()),
+            bucket_by: None,
+            description: None,
         }],
         default_rule: ServeTarget::rollout(vec![
             WeightedVariant {
@@ -167,6 +177,12 @@ pub(crate) fn make_flag_env_config() -> FlagEnvConfig {
             },
         ])
         .unwrap(),
+        inherit_default_rule: false,
+        salt: None,
+        bucket_by: None,
+        rollout_scope: RolloutScope::Shared,
+        kill_reason: None,
+        min_app_version: None,
     }
 }
 
@@ -187,16 +203,20 @@ where
         + SessionRepository
         + AuditLogRepository
         + TransactionalStore
+        + HealthRepository
         + Clone
         + 'static,
     for<'a> <S as TransactionalStore>::Session<'a>: WriteSession,
 {
+    test_ping(&store).await;
     test_project_round_trip(&store).await;
     test_project_upsert_is_idempotent(&store).await;
     test_external_ref_unique(&store).await;
     test_external_ref_null_allowed(&store).await;
     test_environment_round_trip(&store).await;
     test_flag_round_trip(&store).await;
+    test_flag_is_scoped_to_its_project(&store).await;
+    test_flag_counts(&store).await;
     test_segment_round_trip(&store).await;
     test_flag_env_config_round_trip(&store).await;
     test_cascade_delete(&store).await;
@@ -212,7 +232,10 @@ where
     test_failed_mutation_leaves_no_audit(&store).await;
     test_session_commit_audits_each_mutation(&store).await;
     test_session_drop_writes_no_audit(&store).await;
+    test_session_delete_segment_is_transactional(&store).await;
+    test_session_delete_flag_env_config_is_transactional(&store).await;
     test_audit_entries_for_filters_by_entity(&store).await;
+    test_recent_audit_entries_filter_by_type(&store).await;
     test_audit_covers_all_aggregates(&store).await;
     test_audit_is_append_only_api(&store);
     // sdk_key_is_hashed_at_rest is backend-specific (requires direct DB access);
@@ -238,10 +261,27 @@ where
     // #55 flag and flag-set metadata.
     test_flag_metadata_round_trips(&store).await;
     test_environment_metadata_round_trips(&store).await;
+    test_environment_inheritance_round_trips(&store).await;
+    test_query_environments_filters_and_sorts(&store).await;
+    test_query_segments_searches_keys(&store).await;
+    test_page_flags_is_stable_under_inserts(&store).await;
+    test_page_environments_and_segments(&store).await;
+    test_page_audit_entries_walks_the_whole_log(&store).await;
     // #110 typed foreign-key violation mapping.
     test_foreign_key_violation_on_missing_parent(&store).await;
 }
 
+// ---------------------------------------------------------------------------
+// Health: ping
+// ---------------------------------------------------------------------------
+
+async fn test_ping<S: HealthRepository>(store: &S) {
+    store
+        .ping()
+        .await
+        .expect("a connected store answers a ping");
+}
+
 // ---------------------------------------------------------------------------
 // Test 1: project_round_trip
 // ---------------------------------------------------------------------------
@@ -412,6 +452,176 @@ async fn test_flag_round_trip<S: ProjectRepository + FlagRepository>(store: &S)
     store.delete_project("tester", &proj.key).await.unwrap();
 }
 
+/// Flags are keyed by `(project, key)`: the same key in another project is a
+/// different flag, and another project's scope can neither read nor delete it.
+async fn test_flag_is_scoped_to_its_project<S: ProjectRepository + FlagRepository>(store: &S) {
+    let tenant_a = make_project("tenant-a");
+    let tenant_b = make_project("tenant-b");
+    store.upsert_project("tester", &tenant_a).await.unwrap();
+    store.upsert_project("tester", &tenant_b).await.unwrap();
+
+    let secret = make_flag("secret");
+    store
+        .upsert_flag("tester", &tenant_a.key, &secret)
+        .await
+        .unwrap();
+
+    assert!(
+        store
+            .get_flag(&tenant_b.key, &secret.key)
+            .await
+            .unwrap()
+            .is_none(),
+        "a flag must be invisible from another project"
+    );
+    assert!(store.list_flags(&tenant_b.key).await.unwrap().is_empty());
+
+    store
+        .delete_flag("tester", &tenant_b.key, &secret.key)
+        .await
+        .unwrap();
+    assert!(
+        store
+            .get_flag(&tenant_a.key, &secret.key)
+            .await
+            .unwrap()
+            .is_some(),
+        "a delete scoped to another project must not remove the flag"
+    );
+
+    // The same key in both projects: deleting one leaves the other.
+    store
+        .upsert_flag("tester", &tenant_b.key, &secret)
+        .await
+        .unwrap();
+    store
+        .delete_flag("tester", &tenant_b.key, &secret.key)
+        .await
+        .unwrap();
+    assert!(
+        store
+            .get_flag(&tenant_a.key, &secret.key)
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    store.delete_project("tester", &tenant_a.key).await.unwrap();
+    store.delete_project("tester", &tenant_b.key).await.unwrap();
+}
+
+/// Counts match the stored rows: every flag of the project, and only the
+/// configs of the requested environment that are enabled.
+async fn test_flag_counts<
+    S: ProjectRepository + EnvironmentRepository + FlagRepository + FlagEnvConfigRepository,
+>(
+    store: &S,
+) {
+    let proj = make_project("count-proj");
+    let other = make_project("count-other-proj");
+    let production = make_env("prod");
+    let staging = make_env("staging");
+    store.upsert_project("tester", &proj).await.unwrap();
+    store.upsert_project("tester", &other).await.unwrap();
+    for env in [&production, &staging] {
+        store
+            .upsert_environment("tester", &proj.key, env)
+            .await
+            .unwrap();
+    }
+    store
+        .upsert_environment("tester", &other.key, &production)
+        .await
+        .unwrap();
+
+    // Five flags: three enabled in prod, one disabled there, one without a
+    // prod config at all. Staging enables only the disabled one: the last
+    // one is enabled there too, but killed, so it serves as disabled.
+    for (key, in_prod, in_staging) in [
+        ("count-a", Some(true), None),
+        ("count-b", Some(true), None),
+        ("count-c", Some(true), None),
+        ("count-d", Some(false), Some(true)),
+        ("count-e", None, Some(true)),
+    ] {
+        let flag = make_flag(key);
+        store.upsert_flag("tester", &proj.key, &flag).await.unwrap();
+        for (env, enabled) in [(&production, in_prod), (&staging, in_staging)] {
+            let Some(enabled) = enabled else { continue };
+            let config = FlagEnvConfig {
+                enabled,
+                kill_reason: (key == "count-e").then(|| "incident 42".to_owned()),
+                ..make_flag_env_config()
+            };
+            store
+                .upsert_flag_env_config("tester", &proj.key, &flag.key, &env.key, &config)
+                .await
+                .unwrap();
+        }
+    }
+    // Another project's flag, enabled in its own prod, is not counted.
+    let foreign = make_flag("count-a");
+    store
+        .upsert_flag("tester", &other.key, &foreign)
+        .await
+        .unwrap();
+    store
+        .upsert_flag_env_config(
+            "tester",
+            &other.key,
+            &foreign.key,
+            &production.key,
+            &make_flag_env_config(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(store.count_by_project(&proj.key).await.unwrap(), 5);
+    assert_eq!(store.count_by_project(&other.key).await.unwrap(), 1);
+    assert_eq!(
+        store
+            .count_enabled_in_env(&proj.key, &production.key)
+            .await
+            .unwrap(),
+        3
+    );
+    assert_eq!(
+        store
+            .count_enabled_in_env(&proj.key, &staging.key)
+            .await
+            .unwrap(),
+        1
+    );
+
+    // Counts follow updates and deletes.
+    let flag_d = FlagKey::new("count-d").unwrap();
+    store
+        .upsert_flag_env_config(
+            "tester",
+            &proj.key,
+            &flag_d,
+            &production.key,
+            &make_flag_env_config(),
+        )
+        .await
+        .unwrap();
+    store
+        .delete_flag("tester", &proj.key, &FlagKey::new("count-a").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(store.count_by_project(&proj.key).await.unwrap(), 4);
+    assert_eq!(
+        store
+            .count_enabled_in_env(&proj.key, &production.key)
+            .await
+            .unwrap(),
+        3
+    );
+
+    store.delete_project("tester", &proj.key).await.unwrap();
+    store.delete_project("tester", &other.key).await.unwrap();
+}
+
 // ---------------------------------------------------------------------------
 // Test 7: segment_round_trip
 // ---------------------------------------------------------------------------
@@ -878,6 +1088,161 @@ where
     );
 }
 
+// ---------------------------------------------------------------------------
+// Audit test 7b: session_delete_segment_is_transactional
+// ---------------------------------------------------------------------------
+
+async fn test_session_delete_segment_is_transactional<S>(store: &S)
+where
+    S: ProjectRepository + SegmentRepository + AuditLogRepository + TransactionalStore + 'static,
+    for<'a> <S as TransactionalStore>::Session<'a>: WriteSession,
+{
+    let proj = make_project("session-seg-del-proj");
+    let seg = make_segment("session-seg");
+    store.upsert_project("tester", &proj).await.unwrap();
+    store
+        .upsert_segment("tester", &proj.key, &seg)
+        .await
+        .unwrap();
+
+    {
+        let mut session = store.begin("erin").await.unwrap();
+        session.delete_segment(&proj.key, &seg.key).await.unwrap();
+        // dropped without commit -> rollback
+    }
+    assert!(
+        store
+            .get_segment(&proj.key, &seg.key)
+            .await
+            .unwrap()
+            .is_some(),
+        "rolled-back delete must leave the segment"
+    );
+
+    let mut session = store.begin("erin").await.unwrap();
+    session.delete_segment(&proj.key, &seg.key).await.unwrap();
+    session.delete_segment(&proj.key, &seg.key).await.unwrap();
+    session.commit().await.unwrap();
+    assert!(
+        store
+            .get_segment(&proj.key, &seg.key)
+            .await
+            .unwrap()
+            .is_none(),
+        "committed delete must remove the segment"
+    );
+
+    let entity_id = format!("{}/{}", proj.key.as_str(), seg.key.as_str());
+    let entries = store
+        .audit_entries_for("segment", &entity_id)
+        .await
+        .unwrap();
+    let deletions: Vec<&AuditRecord> = entries
+        .iter()
+        .filter(|e| e.action == "segment.deleted")
+        .collect();
+    assert_eq!(
+        deletions.len(),
+        1,
+        "one deletion audited, absent one skipped"
+    );
+    assert_eq!(deletions[0].actor, "erin");
+    assert!(deletions[0].after.is_none());
+
+    store.delete_project("tester", &proj.key).await.unwrap();
+}
+
+async fn test_session_delete_flag_env_config_is_transactional<S>(store: &S)
+where
+    S: ProjectRepository
+        + EnvironmentRepository
+        + FlagRepository
+        + FlagEnvConfigRepository
+        + AuditLogRepository
+        + TransactionalStore
+        + 'static,
+    for<'a> <S as TransactionalStore>::Session<'a>: WriteSession,
+{
+    let proj = make_project("session-cfg-del-proj");
+    let env = make_env("prod");
+    let flag = make_flag("session-cfg");
+    store.upsert_project("tester", &proj).await.unwrap();
+    store
+        .upsert_environment("tester", &proj.key, &env)
+        .await
+        .unwrap();
+    store.upsert_flag("tester", &proj.key, &flag).await.unwrap();
+    store
+        .upsert_flag_env_config(
+            "tester",
+            &proj.key,
+            &flag.key,
+            &env.key,
+            &make_flag_env_config(),
+        )
+        .await
+        .unwrap();
+
+    {
+        let mut session = store.begin("erin").await.unwrap();
+        session
+            .delete_flag_env_config(&proj.key, &flag.key, &env.key)
+            .await
+            .unwrap();
+        // dropped without commit -> rollback
+    }
+    assert!(
+        store
+            .get_flag_env_config(&proj.key, &flag.key, &env.key)
+            .await
+            .unwrap()
+            .is_some(),
+        "rolled-back delete must leave the config"
+    );
+
+    let mut session = store.begin("erin").await.unwrap();
+    session
+        .delete_flag_env_config(&proj.key, &flag.key, &env.key)
+        .await
+        .unwrap();
+    session
+        .delete_flag_env_config(&proj.key, &flag.key, &env.key)
+        .await
+        .unwrap();
+    session.commit().await.unwrap();
+    assert!(
+        store
+            .get_flag_env_config(&proj.key, &flag.key, &env.key)
+            .await
+            .unwrap()
+            .is_none(),
+        "committed delete must remove the config"
+    );
+
+    let entity_id = format!(
+        "{}/{}/{}",
+        proj.key.as_str(),
+        flag.key.as_str(),
+        env.key.as_str()
+    );
+    let entries = store
+        .audit_entries_for("flag_env_config", &entity_id)
+        .await
+        .unwrap();
+    let deletions: Vec<&AuditRecord> = entries
+        .iter()
+        .filter(|e| e.action == "flag_env_config.deleted")
+        .collect();
+    assert_eq!(
+        deletions.len(),
+        1,
+        "one deletion audited, absent one skipped"
+    );
+    assert_eq!(deletions[0].actor, "erin");
+
+    store.delete_project("tester", &proj.key).await.unwrap();
+}
+
 // ---------------------------------------------------------------------------
 // Audit test 8: audit_entries_for_filters_by_entity
 // ---------------------------------------------------------------------------
@@ -915,6 +1280,51 @@ async fn test_audit_entries_for_filters_by_entity<S: ProjectRepository + AuditLo
     store.delete_project("tester", &proj_b.key).await.unwrap();
 }
 
+async fn test_recent_audit_entries_filter_by_type<
+    S: ProjectRepository + EnvironmentRepository + AuditLogRepository,
+>(
+    store: &S,
+) {
+    let proj = make_project("recent-audit-proj");
+    let other = make_project("recent-audit-other");
+    store.upsert_project("tester", &proj).await.unwrap();
+    store.upsert_project("tester", &other).await.unwrap();
+    store
+        .upsert_environment("tester", &proj.key, &make_env("recent-env"))
+        .await
+        .unwrap();
+    store
+        .upsert_environment("tester", &other.key, &make_env("recent-env"))
+        .await
+        .unwrap();
+
+    let environments = store
+        .recent_audit_entries("environment", &proj.key, Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert!(
+        environments
+            .iter()
+            .any(|e| e.entity_id == "recent-audit-proj/recent-env"),
+        "the environment creation is within the window"
+    );
+    assert!(
+        environments.iter().all(
+            |e| e.entity_type == "environment" && e.entity_id.starts_with("recent-audit-proj/")
+        ),
+        "only environment entries of the project are returned"
+    );
+
+    let flags = store
+        .recent_audit_entries("flag", &proj.key, Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert!(flags.is_empty(), "no flag was written in the project");
+
+    store.delete_project("tester", &proj.key).await.unwrap();
+    store.delete_project("tester", &other.key).await.unwrap();
+}
+
 // ---------------------------------------------------------------------------
 // Audit test 9: audit_covers_all_aggregates
 // ---------------------------------------------------------------------------
@@ -1715,6 +2125,320 @@ async fn test_foreign_key_violation_on_missing_parent<
     store.delete_project("tester", &proj.key).await.unwrap();
 }
 
+// ---------------------------------------------------------------------------
+// Environment inheritance round trip
+// ---------------------------------------------------------------------------
+
+async fn test_environment_inheritance_round_trips<S: ProjectRepository + EnvironmentRepository>(
+    store: &S,
+) {
+    let proj = make_project("env-inheritance-proj");
+    store.upsert_project("tester", &proj).await.unwrap();
+
+    let base = make_env("dev");
+    let staging = Environment {
+        inherits_from: Some(base.key.clone()),
+        ..make_env("staging")
+    };
+    for env in [&base, &staging] {
+        store
+            .upsert_environment("tester", &proj.key, env)
+            .await
+            .unwrap();
+    }
+
+    let fetched = store
+        .get_environment(&proj.key, &staging.key)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fetched.inherits_from, Some(base.key.clone()));
+    let listed = store.list_environments(&proj.key).await.unwrap();
+    let listed_base = listed.iter().find(|e| e.key == base.key).unwrap();
+    assert_eq!(listed_base.inherits_from, None);
+
+    let detached = Environment {
+        inherits_from: None,
+        ..staging
+    };
+    store
+        .upsert_environment("tester", &proj.key, &detached)
+        .await
+        .unwrap();
+    let fetched = store
+        .get_environment(&proj.key, &detached.key)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fetched.inherits_from, None, "an upsert clears the base");
+}
+
+// ---------------------------------------------------------------------------
+// Filtered environment and segment lists
+// ---------------------------------------------------------------------------
+
+async fn test_query_environments_filters_and_sorts<S: ProjectRepository + EnvironmentRepository>(
+    store: &S,
+) {
+    let proj = make_project("env-query-proj");
+    let other = make_project("env-query-other");
+    for project in [&proj, &other] {
+        store.upsert_project("tester", project).await.unwrap();
+    }
+    let federated = |key: &str, name: &str| Environment {
+        name: name.into(),
+        managed_by: ManagedBy::Federated,
+        ..make_env(key)
+    };
+    for env in [
+        make_env("dev"),
+        federated("prod-eu", "B Europe"),
+        federated("prod-us", "A Americas"),
+    ] {
+        store
+            .upsert_environment("tester", &proj.key, &env)
+            .await
+            .unwrap();
+    }
+    store
+        .upsert_environment("tester", &other.key, &federated("prod-ap", "Asia"))
+        .await
+        .unwrap();
+
+    let keys = |envs: Vec<Environment>| -> Vec<String> {
+        envs.into_iter()
+            .map(|e| e.key.as_str().to_owned())
+            .collect()
+    };
+    let federated_only = ListEnvironmentsQuery {
+        managed_by: Some(ManagedBy::Federated),
+        ..ListEnvironmentsQuery::default()
+    };
+    let listed = store
+        .query_environments(&proj.key, &federated_only)
+        .await
+        .unwrap();
+    assert_eq!(keys(listed), ["prod-eu", "prod-us"]);
+
+    let by_name = ListEnvironmentsQuery {
+        sort_by: SortBy::Name,
+        ..federated_only.clone()
+    };
+    let listed = store.query_environments(&proj.key, &by_name).await.unwrap();
+    assert_eq!(keys(listed), ["prod-us", "prod-eu"]);
+
+    let searched = ListEnvironmentsQuery {
+        key_contains: Some("D-E".into()),
+        ..ListEnvironmentsQuery::default()
+    };
+    let listed = store
+        .query_environments(&proj.key, &searched)
+        .await
+        .unwrap();
+    assert_eq!(keys(listed), ["prod-eu"], "the search ignores case");
+
+    let everything = ListEnvironmentsQuery {
+        descending: true,
+        ..ListEnvironmentsQuery::default()
+    };
+    let listed = store
+        .query_environments(&proj.key, &everything)
+        .await
+        .unwrap();
+    assert_eq!(keys(listed), ["prod-us", "prod-eu", "dev"]);
+
+    for project in [&proj, &other] {
+        store.delete_project("tester", &project.key).await.unwrap();
+    }
+}
+
+async fn test_query_segments_searches_keys<S: ProjectRepository + SegmentRepository>(store: &S) {
+    let proj = make_project("segment-query-proj");
+    store.upsert_project("tester", &proj).await.unwrap();
+    for key in ["beta-users", "internal-staff", "beta-testers"] {
+        store
+            .upsert_segment("tester", &proj.key, &make_segment(key))
+            .await
+            .unwrap();
+    }
+
+    let keys = |segments: Vec<Segment>| -> Vec<String> {
+        segments
+            .into_iter()
+            .map(|s| s.key.as_str().to_owned())
+            .collect()
+    };
+    let beta = ListSegmentsQuery {
+        key_contains: Some("beta".into()),
+        ..ListSegmentsQuery::default()
+    };
+    let listed = store.query_segments(&proj.key, &beta).await.unwrap();
+    assert_eq!(keys(listed), ["beta-testers", "beta-users"]);
+
+    let newest_first = ListSegmentsQuery {
+        sort_by: SortBy::CreatedAt,
+        descending: true,
+        ..beta
+    };
+    let listed = store
+        .query_segments(&proj.key, &newest_first)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 2);
+
+    let none = ListSegmentsQuery {
+        key_contains: Some("%".into()),
+        ..ListSegmentsQuery::default()
+    };
+    assert!(
+        store
+            .query_segments(&proj.key, &none)
+            .await
+            .unwrap()
+            .is_empty(),
+        "the search is literal"
+    );
+
+    store.delete_project("tester", &proj.key).await.unwrap();
+}
+
+// ---------------------------------------------------------------------------
+// Keyset pagination
+// ---------------------------------------------------------------------------
+
+async fn test_page_flags_is_stable_under_inserts<S: ProjectRepository + FlagRepository>(store: &S) {
+    let proj = make_project("page-flags-proj");
+    store.upsert_project("tester", &proj).await.unwrap();
+    for key in ["flag-b", "flag-d", "flag-f"] {
+        store
+            .upsert_flag("tester", &proj.key, &make_flag(key))
+            .await
+            .unwrap();
+    }
+    let keys = |page: &Page<Flag>| -> Vec<String> {
+        page.items
+            .iter()
+            .map(|f| f.key.as_str().to_owned())
+            .collect()
+    };
+
+    let first = store
+        .page_flags(&proj.key, &PageRequest::first(2))
+        .await
+        .unwrap();
+    assert_eq!(keys(&first), ["flag-b", "flag-d"]);
+    assert_eq!(first.total, 3);
+    let cursor = first.next_cursor.clone().expect("a second page exists");
+
+    // One flag lands before the cursor, one after it, mid-iteration.
+    for key in ["flag-a", "flag-e"] {
+        store
+            .upsert_flag("tester", &proj.key, &make_flag(key))
+            .await
+            .unwrap();
+    }
+    let token = cursor.to_string();
+    let resumed: Cursor = token.parse().unwrap();
+    let second = store
+        .page_flags(&proj.key, &PageRequest::after(resumed, 2))
+        .await
+        .unwrap();
+    assert_eq!(
+        keys(&second),
+        ["flag-e", "flag-f"],
+        "no row is skipped or returned twice"
+    );
+    assert_eq!(second.total, 5);
+
+    assert_eq!(second.next_cursor, None, "the last page has no cursor");
+
+    store.delete_project("tester", &proj.key).await.unwrap();
+}
+
+async fn test_page_environments_and_segments<
+    S: ProjectRepository + EnvironmentRepository + SegmentRepository,
+>(
+    store: &S,
+) {
+    let proj = make_project("page-lists-proj");
+    store.upsert_project("tester", &proj).await.unwrap();
+    for key in ["staging", "dev", "prod"] {
+        store
+            .upsert_environment("tester", &proj.key, &make_env(key))
+            .await
+            .unwrap();
+        store
+            .upsert_segment("tester", &proj.key, &make_segment(key))
+            .await
+            .unwrap();
+    }
+
+    let mut request = PageRequest::first(1);
+    let mut environments = Vec::new();
+    loop {
+        let page = store.page_environments(&proj.key, &request).await.unwrap();
+        assert_eq!(page.total, 3);
+        environments.extend(page.items.into_iter().map(|e| e.key.as_str().to_owned()));
+        let Some(cursor) = page.next_cursor else {
+            break;
+        };
+        request = PageRequest::after(cursor, 1);
+    }
+    assert_eq!(environments, ["dev", "prod", "staging"]);
+
+    let segments = store
+        .page_segments(&proj.key, &PageRequest::default())
+        .await
+        .unwrap();
+    let keys: Vec<&str> = segments.items.iter().map(|s| s.key.as_str()).collect();
+    assert_eq!(keys, ["dev", "prod", "staging"]);
+    assert_eq!(segments.next_cursor, None);
+
+    store.delete_project("tester", &proj.key).await.unwrap();
+}
+
+async fn test_page_audit_entries_walks_the_whole_log<S: ProjectRepository + AuditLogRepository>(
+    store: &S,
+) {
+    let before = store.list_audit_entries().await.unwrap();
+    let mut walked = Vec::new();
+    let mut request = PageRequest::first(3);
+    let mut appended = false;
+    loop {
+        let page = store.page_audit_entries(&request).await.unwrap();
+        walked.extend(page.items);
+        if !appended {
+            // An entry appended mid-iteration is read on a later page.
+            store
+                .upsert_project("tester", &make_project("page-audit-proj"))
+                .await
+                .unwrap();
+            appended = true;
+        }
+        let Some(cursor) = page.next_cursor else {
+            break;
+        };
+        request = PageRequest::after(cursor, 3);
+    }
+    assert_eq!(walked.len(), before.len() + 1);
+    assert_eq!(walked[..before.len()], before[..]);
+    assert_eq!(walked.last().unwrap().entity_id, "page-audit-proj");
+
+    // "ZmxhZy1h" is the cursor following a flag key, not an audit id.
+    let flag_cursor = Cursor::decode("ZmxhZy1h").unwrap();
+    assert!(matches!(
+        store
+            .page_audit_entries(&PageRequest::after(flag_cursor, 3))
+            .await,
+        Err(StoreError::InvalidCursor)
+    ));
+
+    store
+        .delete_project("tester", &ProjectKey::new("page-audit-proj").unwrap())
+        .await
+        .unwrap();
+}
+
 // ---------------------------------------------------------------------------
 // #55 case 2: environment_metadata_round_trips
 // ---------------------------------------------------------------------------
@@ -4,8 +4,11 @@ mod shared;
 
 use flaps_domain::{EnvironmentKey, ProjectKey, SdkKeyKind};
 use flaps_store::{
-    KeyHasher, NewSdkKey, SdkKeyScope,
-    repository::{AccountRepository, EnvironmentRepository, ProjectRepository, SdkKeyRepository},
+    KeyHasher, NewSdkKey, SdkKeyScope, StoreError,
+    repository::{
+        AccountRepository, EnvironmentRepository, FlagRepository, ProjectRepository,
+        SdkKeyRepository,
+    },
     sqlite::SqliteStore,
 };
 
@@ -145,3 +148,124 @@ async fn inactive_account_cannot_authenticate() {
 
     let _ = std::fs::remove_file(&db_path);
 }
+
+/// A corrupt flag row fails the strict `list_flags` but is skipped and
+/// reported by `list_flags_lenient`, which still returns every valid flag.
+#[tokio::test]
+async fn lenient_flag_listing_skips_corrupt_rows() {
+    let db_path = std::env::temp_dir().join(format!("flaps-test-{}.sqlite3", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}?mode=rwc", db_path.display());
+
+    let store = SqliteStore::connect(&url, KeyHasher::new(b"lenient-test-pepper".to_vec()))
+        .await
+        .unwrap();
+
+    let proj = shared::make_project("lenient-proj");
+    store.upsert_project("tester", &proj).await.unwrap();
+    for key in ["alpha", "beta"] {
+        store
+            .upsert_flag("tester", &proj.key, &shared::make_flag(key))
+            .await
+            .unwrap();
+    }
+
+    // Write rows the domain model would never produce, bypassing the store.
+    let raw_pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .connect(&url)
+        .await
+        .unwrap();
+    for (key, variants_json) in [("Bad_Key", r#"{"on":true}"#), ("broken", "not json")] {
+        sqlx::query(
+            "INSERT INTO flags (project_key, key, name, description, flag_type, value_type, variants_json, created_at, updated_at)
+             VALUES ('lenient-proj', ?, 'corrupt', NULL, 'release', 'boolean', ?, '', '')",
+        )
+        .bind(key)
+        .bind(variants_json)
+        .execute(&raw_pool)
+        .await
+        .unwrap();
+    }
+    raw_pool.close().await;
+
+    let strict = store.list_flags(&proj.key).await;
+    assert!(
+        matches!(
+            strict,
+            Err(StoreError::InvalidRow(_) | StoreError::Serialization(_))
+        ),
+        "strict listing must fail on a corrupt row, got {strict:?}"
+    );
+
+    let (flags, skipped) = store.list_flags_lenient(&proj.key).await.unwrap();
+    let mut listed: Vec<&str> = flags.iter().map(|f| f.key.as_str()).collect();
+    listed.sort_unstable();
+    assert_eq!(listed, ["alpha", "beta"]);
+
+    let mut skipped_keys: Vec<&str> = skipped.iter().map(|(k, _)| k.as_str()).collect();
+    skipped_keys.sort_unstable();
+    assert_eq!(skipped_keys, ["Bad_Key", "broken"]);
+    assert!(
+        skipped
+            .iter()
+            .any(|(k, e)| k == "Bad_Key" && matches!(e, StoreError::InvalidRow(_)))
+    );
+    assert!(
+        skipped
+            .iter()
+            .any(|(k, e)| k == "broken" && matches!(e, StoreError::Serialization(_)))
+    );
+
+    let _ = std::fs::remove_file(&db_path);
+}
+
+/// Times `rounds` alternating flag reads and listings, the hot read path,
+/// against an in-memory store keeping `capacity` prepared statements per
+/// connection.
+async fn hot_path_time(capacity: usize, rounds: u32) -> std::time::Duration {
+    let config = flaps_store::DatabaseConfig {
+        max_connections: Some(1),
+        statement_cache_capacity: Some(capacity),
+        ..flaps_store::DatabaseConfig::from_url("sqlite::memory:").unwrap()
+    };
+    let store = SqliteStore::connect_with(&config, KeyHasher::new(b"bench-pepper".to_vec()))
+        .await
+        .unwrap();
+    let proj = shared::make_project("bench-proj");
+    let flag = shared::make_flag("bench-flag");
+    store.upsert_project("tester", &proj).await.unwrap();
+    store.upsert_flag("tester", &proj.key, &flag).await.unwrap();
+
+    let started = std::time::Instant::now();
+    for _ in 0..rounds {
+        assert!(
+            store
+                .get_flag(&proj.key, &flag.key)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(store.list_flags(&proj.key).await.unwrap().len(), 1);
+    }
+    started.elapsed()
+}
+
+/// A statement cache holding the hot queries prepares them once; a
+/// one-statement cache re-prepares on every alternation.
+///
+/// A timing comparison, so it is not run by default:
+/// `cargo test -p flaps-store --test sqlite -- --ignored --nocapture`.
+#[tokio::test]
+#[ignore = "benchmark; run manually with --ignored"]
+async fn sized_statement_cache_reduces_per_query_overhead() {
+    const ROUNDS: u32 = 5_000;
+    // Warm up the allocator and the SQLite library before timing.
+    hot_path_time(100, 100).await;
+    let tiny = hot_path_time(1, ROUNDS).await;
+    let sized = hot_path_time(100, ROUNDS).await;
+    println!(
+        "per round: capacity 1: {:?}, capacity 100: {:?}",
+        tiny / ROUNDS,
+        sized / ROUNDS
+    );
+    assert!(sized < tiny, "sized {sized:?} vs tiny {tiny:?}");
+}
@@ -13,7 +13,7 @@ use std::{future::Future, pin::Pin, time::Duration};
 
 use anyhow::{Context as _, Result};
 use flaps_server::{
-    recompile::recompile_environment,
+    recompile::{lint_environment, recompile_environment},
     state::{AppState, Store},
 };
 use flaps_store::StoreError;
@@ -100,6 +100,10 @@ pub async fn connect_store_with_retry<S>(connector: ConnectorFn<S>) -> Result<S>
 /// skipped. The daemon still starts; the environment is served as 404 until the
 /// data is corrected and the cache is refreshed by a mutation.
 ///
+/// Each environment is also linted first (see
+/// [`flaps_server::recompile::lint_environment`]): rules that compile but can
+/// never match are logged as warnings.
+///
 /// The entire warm-up pass completes **before** the HTTP listener is opened.
 pub async fn warm_up_cache<S: Store>(state: &AppState<S>) {
     let projects = match state.store.list_projects().await {
@@ -120,6 +124,28 @@ pub async fn warm_up_cache<S: Store>(state: &AppState<S>) {
         };
 
         for env in &environments {
+            // Surface rules that compile but can never match as load-time
+            // warnings, before they turn into silent non-matches at runtime.
+            match lint_environment(state, &project.key, &env.key).await {
+                Ok(report) => {
+                    for warning in &report.warnings {
+                        warn!(
+                            project = %project.key.as_str(),
+                            env = %env.key.as_str(),
+                            "{warning}"
+                        );
+                    }
+                }
+                Err(e) => {
+                    debug!(
+                        project = %project.key.as_str(),
+                        env = %env.key.as_str(),
+                        error = ?e,
+                        "environment could not be linted"
+                    );
+                }
+            }
+
             match recompile_environment(state, &project.key, &env.key).await {
                 Ok(()) => {
                     debug!(
@@ -201,8 +227,8 @@ mod tests {
     };
     use flaps_domain::{
         Environment, EnvironmentKey, FlagEnvConfig, FlagKey, FlagType, ManagedBy, Project,
-        ProjectKey, SdkKeyKind, SegmentKey, ServeTarget, TargetingRule, ValueType, VariantKey,
-        VariantValue, Variants,
+        ProjectKey, RolloutScope, SdkKeyKind, SegmentKey, ServeTarget, TargetingRule, ValueType,
+        VariantKey, VariantValue, Variants,
     };
     use flaps_server::{build_router, state::AppState};
     use flaps_store::{
@@ -254,6 +280,7 @@ mod tests {
                     external_ref: None,
                     managed_by: ManagedBy::Local,
                     metadata: flaps_domain::Metadata::new(),
+                    inherits_from: None,
                 },
             )
             .await
@@ -306,39 +333,27 @@ mod tests {
             .unwrap();
 
         // env-good: no flags, compiles to an empty ruleset (success).
-        let good_env = EnvironmentKey::new("env-good").unwrap();
-        store
-            .upsert_environment(
-                "test",
-                &project,
-                &Environment {
-                    key: good_env.clone(),
-                    name: "Good".into(),
-                    external_ref: None,
-                    managed_by: ManagedBy::Local,
-                    metadata: flaps_domain::Metadata::new(),
-                },
-            )
-            .await
-            .unwrap();
-
         // env-corrupt: has a FlagEnvConfig referencing a segment that does not
         // exist, which triggers CompileError::UnknownSegment.
+        let good_env = EnvironmentKey::new("env-good").unwrap();
         let bad_env = EnvironmentKey::new("env-corrupt").unwrap();
-        store
-            .upsert_environment(
-                "test",
-                &project,
-                &Environment {
-                    key: bad_env.clone(),
-                    name: "Corrupt".into(),
-                    external_ref: None,
-                    managed_by: ManagedBy::Local,
-                    metadata: flaps_domain::Metadata::new(),
-                },
-            )
-            .await
-            .unwrap();
+        for (key, name) in [(&good_env, "Good"), (&bad_env, "Corrupt")] {
+            store
+                .upsert_environment(
+                    "test",
+                    &project,
+                    &Environment {
+                        key: key.clone(),
+                        name: name.into(),
+                        external_ref: None,
+                        managed_by: ManagedBy::Local,
+                        metadata: flaps_domain::Metadata::new(),
+                        inherits_from: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
 
         // Seed a flag and a corrupt config (missing segment "ghost").
         let flag_key = FlagKey::new("feat").unwrap();
@@ -373,9 +388,18 @@ mod tests {
             enabled: true,
             rules: vec![TargetingRule {
                 segments: vec![SegmentKey::new("ghost").unwrap()],
+                segment_groups: Vec::new(),
                 serve: ServeTarget::Fixed(vk_on),
+                bucket_by: None,
+                description: None,
             }],
             default_rule: ServeTarget::Fixed(vk_off),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         store
             .upsert_flag_env_config("test", &project, &flag_key, &bad_env, &corrupt_config)
@@ -546,6 +570,7 @@ mod tests {
                     external_ref: None,
                     managed_by: ManagedBy::Local,
                     metadata: flaps_domain::Metadata::new(),
+                    inherits_from: None,
                 },
             )
             .await
@@ -587,6 +612,12 @@ mod tests {
             enabled: false,
             rules: vec![],
             default_rule: ServeTarget::Fixed(vk_off),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
         };
         store
             .upsert_flag_env_config(
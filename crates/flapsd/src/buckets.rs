@@ -0,0 +1,272 @@
+//! `flapsd buckets`: exports the rollout bucket of a list of users.
+//!
+//! For each user the report gives the bucket, out of 100, that the flag's
+//! percentage rollout assigns them ([`bucket_for`], the function evaluation
+//! itself uses) and whether they are included at each of a range of rollout
+//! percentages. A user is included at `p`% when their bucket is below `p`,
+//! so the export shows that a staged rollout is deterministic (the bucket
+//! depends only on the flag and the user) and monotonic (raising the
+//! percentage only adds users), as compliance reviews ask.
+//!
+//! The bucketing value is the one the compiler emits for a rollout: the
+//! flag's `salt` when its environment sets one, the flag key otherwise,
+//! followed by the rule's namespace under a per-rule rollout scope, then the
+//! user id.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use flaps_eval::bucket_for;
+use serde::Serialize;
+
+/// Total weight the buckets are drawn from: one bucket per percentage point.
+const TOTAL_WEIGHT: u64 = 100;
+
+/// Output format of `flapsd buckets`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BucketFormat {
+    /// One row per user: `user,bucket`, then one column per percentage.
+    #[default]
+    Csv,
+    /// A JSON array with one object per user.
+    Json,
+}
+
+/// Arguments of the `buckets` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct BucketsArgs {
+    /// Key of the flag whose rollout is exported.
+    #[arg(long)]
+    pub flag: String,
+
+    /// Salt of the flag's environment configuration, when it sets one.
+    #[arg(long)]
+    pub salt: Option<String>,
+
+    /// Bucketing namespace of a rule's rollout under the per-rule rollout
+    /// scope, e.g. `/rule:beta/`.
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// File listing the user ids (targeting keys), one per line.
+    #[arg(long)]
+    pub users: PathBuf,
+
+    /// Rollout percentages to report inclusion at, comma-separated.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_values_t = [10, 25, 50, 100],
+        value_parser = clap::value_parser!(u8).range(0..=100),
+    )]
+    pub percentages: Vec<u8>,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: BucketFormat,
+}
+
+/// Errors of a bucket export.
+#[derive(Debug, thiserror::Error)]
+pub enum BucketsError {
+    /// The user list could not be read.
+    #[error("cannot read users {path:?}: {source}")]
+    Read {
+        /// The user list path.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// The export could not be serialized as JSON.
+    #[error("cannot serialize the export: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The bucket of one user and their inclusion at each percentage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserBucket {
+    /// The user id.
+    pub user: String,
+    /// The bucket, in `[0, 100)`.
+    pub bucket: u64,
+    /// Whether the user is included, per rollout percentage.
+    pub included: BTreeMap<u8, bool>,
+}
+
+/// Computes the bucket of every user of `users` for the rollout of `flag`.
+///
+/// `percentages` are reported in ascending order, without duplicates.
+#[must_use]
+pub fn compute(
+    flag: &str,
+    salt: Option<&str>,
+    namespace: Option<&str>,
+    users: &[String],
+    percentages: &[u8],
+) -> Vec<UserBucket> {
+    let prefix = format!("{}{}", salt.unwrap_or(flag), namespace.unwrap_or(""));
+    users
+        .iter()
+        .map(|user| {
+            let bucket = bucket_for(&format!("{prefix}{user}"), TOTAL_WEIGHT);
+            UserBucket {
+                user: user.clone(),
+                bucket,
+                included: percentages
+                    .iter()
+                    .map(|&percentage| (percentage, bucket < u64::from(percentage)))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Reads the user list named by `args` and renders the export in its format.
+///
+/// Blank lines and surrounding whitespace in the user list are ignored.
+///
+/// # Errors
+///
+/// Returns [`BucketsError`] when the user list cannot be read.
+pub fn export(args: &BucketsArgs) -> Result<String, BucketsError> {
+    let users = read_users(&args.users).map_err(|source| BucketsError::Read {
+        path: args.users.clone(),
+        source,
+    })?;
+    let rows = compute(
+        &args.flag,
+        args.salt.as_deref(),
+        args.namespace.as_deref(),
+        &users,
+        &args.percentages,
+    );
+    match args.format {
+        BucketFormat::Csv => Ok(to_csv(&rows)),
+        BucketFormat::Json => Ok(serde_json::to_string_pretty(&rows)? + "\n"),
+    }
+}
+
+/// Reads a user list, one id per line, ignoring blank lines and surrounding
+/// whitespace.
+pub(crate) fn read_users(path: &Path) -> std::io::Result<Vec<String>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|user| !user.is_empty())
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Renders `rows` as CSV with a header line.
+#[must_use]
+pub fn to_csv(rows: &[UserBucket]) -> String {
+    let mut csv = String::from("user,bucket");
+    if let Some(first) = rows.first() {
+        for percentage in first.included.keys() {
+            let _ = write!(csv, ",{percentage}%");
+        }
+    }
+    csv.push('\n');
+    for row in rows {
+        csv.push_str(&csv_field(&row.user));
+        let _ = write!(csv, ",{}", row.bucket);
+        for included in row.included.values() {
+            let _ = write!(csv, ",{included}");
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+
+    const PERCENTAGES: [u8; 4] = [10, 25, 50, 100];
+
+    fn users() -> Vec<String> {
+        (0..50).map(|i| format!("user-{i}")).collect()
+    }
+
+    #[test]
+    fn buckets_are_stable_and_inclusion_is_monotonic() {
+        let first = compute("checkout", None, None, &users(), &PERCENTAGES);
+        assert_eq!(
+            first,
+            compute("checkout", None, None, &users(), &PERCENTAGES)
+        );
+
+        for row in &first {
+            assert!(row.bucket < 100, "{row:?}");
+            let included: Vec<bool> = row.included.values().copied().collect();
+            assert!(
+                included.windows(2).all(|pair| pair[0] <= pair[1]),
+                "a user left the rollout as it grew: {row:?}"
+            );
+            assert!(row.included[&100], "everyone is in at 100%");
+        }
+        assert!(first.iter().any(|row| !row.included[&10]));
+    }
+
+    #[test]
+    fn the_salt_replaces_the_flag_key() {
+        let user = ["user-1".to_owned()];
+        let salted = compute("checkout", Some("spring"), None, &user, &PERCENTAGES);
+        assert_eq!(
+            salted[0].bucket,
+            bucket_for("springuser-1", 100),
+            "buckets on salt + user"
+        );
+        let unsalted = compute("checkout", None, Some("/rule:beta/"), &user, &PERCENTAGES);
+        assert_eq!(
+            unsalted[0].bucket,
+            bucket_for("checkout/rule:beta/user-1", 100)
+        );
+    }
+
+    #[test]
+    fn exports_csv_and_json() {
+        let mut list = tempfile::NamedTempFile::new().expect("tempfile");
+        writeln!(list, "alice\n\n  bob,jr  ").expect("write");
+        let mut args = BucketsArgs {
+            flag: "checkout".to_owned(),
+            salt: None,
+            namespace: None,
+            users: list.path().to_owned(),
+            percentages: vec![10, 100],
+            format: BucketFormat::Csv,
+        };
+        let rows = compute("checkout", None, None, &["alice".to_owned()], &[10, 100]);
+
+        let csv = export(&args).expect("exports");
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "user,bucket,10%,100%");
+        assert_eq!(
+            lines[1],
+            format!("alice,{},{},true", rows[0].bucket, rows[0].included[&10])
+        );
+        assert!(lines[2].starts_with("\"bob,jr\","), "{csv}");
+        assert_eq!(lines.len(), 3);
+
+        args.format = BucketFormat::Json;
+        let json: serde_json::Value =
+            serde_json::from_str(&export(&args).expect("exports")).expect("valid JSON");
+        assert_eq!(json[0]["user"], "alice");
+        assert_eq!(json[0]["bucket"], rows[0].bucket);
+        assert_eq!(json[1]["included"]["100"], true);
+    }
+}
@@ -20,7 +20,8 @@ pub struct Config {
     /// Database connection URL.
     ///
     /// Must start with `sqlite:` / `sqlite://` for SQLite, or `postgres://`
-    /// for PostgreSQL.
+    /// for PostgreSQL. Pool settings may be given as query parameters, see
+    /// [`flaps_store::DatabaseConfig::from_url`].
     pub database_url: String,
 
     /// Address to bind the HTTP listener on (e.g. `"127.0.0.1:8080"`).
@@ -76,6 +77,134 @@ pub struct Config {
     /// value with the default applied. A zero value is rejected by
     /// [`Config::load`] as [`ConfigError::InvalidMaxSseSubscriptionsGlobal`].
     pub max_sse_subscriptions_global: Option<usize>,
+
+    /// Maximum number of targeting rules in one flag environment config
+    /// (default:
+    /// [`DEFAULT_MAX_RULES_PER_CONFIG`](flaps_compiler::limits::DEFAULT_MAX_RULES_PER_CONFIG)
+    /// when omitted).
+    ///
+    /// Over-limit configs are rejected by the admin API with a validation
+    /// error. A zero value is rejected by [`Config::load`] as
+    /// [`ConfigError::InvalidLimit`].
+    pub max_rules_per_config: Option<usize>,
+
+    /// Maximum number of segments one targeting rule may combine (default:
+    /// [`DEFAULT_MAX_CONDITIONS_PER_RULE`](flaps_compiler::limits::DEFAULT_MAX_CONDITIONS_PER_RULE)
+    /// when omitted). A zero value is rejected by [`Config::load`].
+    pub max_conditions_per_rule: Option<usize>,
+
+    /// Maximum nesting depth of a segment match expression (default:
+    /// [`DEFAULT_MAX_SEGMENT_DEPTH`](flaps_compiler::limits::DEFAULT_MAX_SEGMENT_DEPTH)
+    /// when omitted). A zero value is rejected by [`Config::load`].
+    pub max_segment_depth: Option<usize>,
+
+    /// Maximum length, in bytes, of a `regex` segment predicate's pattern
+    /// (default:
+    /// [`DEFAULT_MAX_PATTERN_LENGTH`](flaps_compiler::limits::DEFAULT_MAX_PATTERN_LENGTH)
+    /// when omitted). A zero value is rejected by [`Config::load`].
+    pub max_pattern_length: Option<usize>,
+
+    /// Granularity, in seconds, of the `$flagd.timestamp` seen by OFREP
+    /// evaluations (default: `0`, whole-second resolution, when omitted).
+    ///
+    /// The evaluation clock is rounded down to a multiple of this interval so
+    /// instances whose clocks are a few seconds apart agree on time-based
+    /// targeting: a rule activating at a given time flips at the next
+    /// boundary rather than at each instance's own notion of that time. Use
+    /// [`Config::effective_timestamp_quantum`] to read the value.
+    pub evaluation_timestamp_quantum_secs: Option<u64>,
+
+    /// Maximum number of attributes in an OFREP evaluation context, and of
+    /// members in any nested object (default:
+    /// [`DEFAULT_MAX_CONTEXT_ATTRIBUTES`](flaps_eval::DEFAULT_MAX_CONTEXT_ATTRIBUTES)
+    /// when omitted).
+    ///
+    /// Over-limit contexts are refused with `400 INVALID_CONTEXT` before
+    /// evaluation. A zero value is rejected by [`Config::load`] as
+    /// [`ConfigError::InvalidLimit`]; the same holds for the three context
+    /// limits below. Use [`Config::effective_context_limits`] to read them.
+    pub context_max_attributes: Option<usize>,
+
+    /// Maximum length, in bytes, of a context attribute name (default:
+    /// [`DEFAULT_MAX_CONTEXT_KEY_LENGTH`](flaps_eval::DEFAULT_MAX_CONTEXT_KEY_LENGTH)
+    /// when omitted).
+    pub context_max_key_length: Option<usize>,
+
+    /// Maximum length, in bytes, of a context string value or targeting key
+    /// (default:
+    /// [`DEFAULT_MAX_CONTEXT_VALUE_LENGTH`](flaps_eval::DEFAULT_MAX_CONTEXT_VALUE_LENGTH)
+    /// when omitted).
+    pub context_max_value_length: Option<usize>,
+
+    /// Maximum number of elements in a context list value (default:
+    /// [`DEFAULT_MAX_CONTEXT_LIST_LENGTH`](flaps_eval::DEFAULT_MAX_CONTEXT_LIST_LENGTH)
+    /// when omitted).
+    pub context_max_list_length: Option<usize>,
+
+    /// Fraction of OFREP evaluations logged as `flaps::exposure` events,
+    /// between `0.0` and `1.0` (default: no exposure logging when omitted).
+    ///
+    /// Sampling is deterministic per user and flag, and each event carries
+    /// the rate so counts can be scaled back up. A value outside `[0, 1]` is
+    /// rejected by [`Config::load`] as [`ConfigError::InvalidExposureSampleRate`].
+    /// Use [`Config::effective_exposure_sampler`] to read it.
+    pub exposure_sample_rate: Option<f64>,
+
+    /// Path to a `MaxMind` City database (`GeoIP2-City` or `GeoLite2-City`)
+    /// used to add `country`, `region` and `city` attributes to OFREP
+    /// evaluation contexts (default: no geolocation when omitted).
+    ///
+    /// Requires `flapsd` built with the `maxmind` feature; otherwise setting
+    /// it is rejected by [`Config::load`] as
+    /// [`ConfigError::GeoIpUnsupported`].
+    pub geoip_database: Option<std::path::PathBuf>,
+
+    /// Environment keys whose OFREP evaluation results are cached (default:
+    /// no caching when omitted).
+    ///
+    /// A cached result is reused for identical flag, context and ruleset
+    /// version inputs until `evaluation_cache_ttl_secs` elapses; a recompile
+    /// bumps the version and so bypasses older entries. The keys apply to
+    /// every project. An invalid key is rejected by [`Config::load`] as
+    /// [`ConfigError::InvalidEvaluationCacheEnvironment`]. Use
+    /// [`Config::effective_evaluation_cache`] to read the cache settings.
+    pub evaluation_cache_environments: Option<Vec<String>>,
+
+    /// How long, in seconds, a cached evaluation result is served (default:
+    /// [`DEFAULT_TTL`](flaps_server::eval_cache::EvaluationCacheConfig::DEFAULT_TTL)
+    /// when omitted). A zero value is rejected by [`Config::load`].
+    pub evaluation_cache_ttl_secs: Option<u64>,
+
+    /// Number of evaluation results cached before the cache is emptied
+    /// (default:
+    /// [`DEFAULT_CAPACITY`](flaps_server::eval_cache::EvaluationCacheConfig::DEFAULT_CAPACITY)
+    /// when omitted). A zero value is rejected by [`Config::load`].
+    pub evaluation_cache_capacity: Option<usize>,
+
+    /// Number of recent OFREP evaluation contexts kept per project for
+    /// segment previews (default: no sampling when omitted).
+    ///
+    /// Sampled contexts are redacted: the targeting key and
+    /// [`Self::private_attributes`] are dropped. A zero value is rejected by
+    /// [`Config::load`] as [`ConfigError::InvalidLimit`]. Use
+    /// [`Config::effective_context_sample`] to read the sample settings.
+    pub context_sample_size: Option<usize>,
+
+    /// Context attributes never stored in the segment preview sample
+    /// (default: none).
+    pub private_attributes: Option<Vec<String>>,
+
+    /// Number of changes a flag configuration may record within
+    /// `flapping_window_secs` before it is reported as flapping (default:
+    /// [`DEFAULT_MAX_CHANGES`](flaps_server::flapping::FlappingConfig::DEFAULT_MAX_CHANGES)
+    /// when omitted). Use [`Config::effective_flapping`] to read it.
+    pub flapping_max_changes: Option<usize>,
+
+    /// How far back, in seconds, flag configuration changes are counted by
+    /// flapping detection (default:
+    /// [`DEFAULT_WINDOW`](flaps_server::flapping::FlappingConfig::DEFAULT_WINDOW)
+    /// when omitted). A zero value is rejected by [`Config::load`].
+    pub flapping_window_secs: Option<u64>,
 }
 
 /// Errors that can occur when loading or validating the configuration.
@@ -167,6 +296,27 @@ pub enum ConfigError {
         /// The rejected value.
         value: usize,
     },
+
+    /// One of the compile-time structural limits (`max_rules_per_config`,
+    /// `max_conditions_per_rule`, `max_segment_depth`, `max_pattern_length`)
+    /// is set to zero.
+    #[error("invalid {field}: must be greater than zero (omit the field to use the default)")]
+    InvalidLimit {
+        /// Name of the offending configuration key.
+        field: &'static str,
+    },
+
+    /// `exposure_sample_rate` is outside `[0, 1]`.
+    #[error("invalid exposure_sample_rate {0}: must be between 0.0 and 1.0")]
+    InvalidExposureSampleRate(f64),
+
+    /// `geoip_database` is set but this build has no `MaxMind` support.
+    #[error("geoip_database requires flapsd built with the `maxmind` feature")]
+    GeoIpUnsupported,
+
+    /// An `evaluation_cache_environments` entry is not a valid environment key.
+    #[error("invalid evaluation_cache_environments entry {0:?}: not a valid environment key")]
+    InvalidEvaluationCacheEnvironment(String),
 }
 
 impl Config {
@@ -209,6 +359,8 @@ impl Config {
                 "unrecognised scheme in {url:?}; expected sqlite: or postgres://"
             )));
         }
+        flaps_store::DatabaseConfig::from_url(url)
+            .map_err(|e| ConfigError::InvalidDatabaseUrl(e.to_string()))?;
 
         // Validate rate_limit_per_minute: zero has no documented meaning, so it
         // is rejected rather than silently accepted as "no traffic allowed".
@@ -246,6 +398,55 @@ impl Config {
             }
         }
 
+        // Validate the compile-time and context limits: a ceiling of zero
+        // would reject every flag with a targeting rule, every segment or
+        // every non-empty context, so it is refused rather than read as
+        // "unlimited".
+        for (field, value) in [
+            ("max_rules_per_config", self.max_rules_per_config),
+            ("max_conditions_per_rule", self.max_conditions_per_rule),
+            ("max_segment_depth", self.max_segment_depth),
+            ("max_pattern_length", self.max_pattern_length),
+            ("context_max_attributes", self.context_max_attributes),
+            ("context_max_key_length", self.context_max_key_length),
+            ("context_max_value_length", self.context_max_value_length),
+            ("context_max_list_length", self.context_max_list_length),
+            ("evaluation_cache_capacity", self.evaluation_cache_capacity),
+            ("context_sample_size", self.context_sample_size),
+        ] {
+            if value == Some(0) {
+                return Err(ConfigError::InvalidLimit { field });
+            }
+        }
+
+        if let Some(rate) = self.exposure_sample_rate {
+            if flaps_eval::ExposureSampler::new(rate).is_none() {
+                return Err(ConfigError::InvalidExposureSampleRate(rate));
+            }
+        }
+
+        if cfg!(not(feature = "maxmind")) && self.geoip_database.is_some() {
+            return Err(ConfigError::GeoIpUnsupported);
+        }
+
+        if self.evaluation_cache_ttl_secs == Some(0) {
+            return Err(ConfigError::InvalidLimit {
+                field: "evaluation_cache_ttl_secs",
+            });
+        }
+        if self.flapping_window_secs == Some(0) {
+            return Err(ConfigError::InvalidLimit {
+                field: "flapping_window_secs",
+            });
+        }
+        for environment in self.evaluation_cache_environments.iter().flatten() {
+            if flaps_domain::EnvironmentKey::new(environment).is_err() {
+                return Err(ConfigError::InvalidEvaluationCacheEnvironment(
+                    environment.clone(),
+                ));
+            }
+        }
+
         Ok(())
     }
 
@@ -295,6 +496,127 @@ impl Config {
             .unwrap_or(flaps_server::state::DEFAULT_MAX_SSE_SUBSCRIPTIONS_GLOBAL)
     }
 
+    /// Returns the effective compile-time structural limits.
+    ///
+    /// Each omitted field falls back to its
+    /// [`flaps_compiler::Limits::default`] value.
+    #[must_use]
+    pub fn effective_limits(&self) -> flaps_compiler::Limits {
+        let defaults = flaps_compiler::Limits::default();
+        flaps_compiler::Limits {
+            max_rules_per_config: self
+                .max_rules_per_config
+                .unwrap_or(defaults.max_rules_per_config),
+            max_conditions_per_rule: self
+                .max_conditions_per_rule
+                .unwrap_or(defaults.max_conditions_per_rule),
+            max_segment_depth: self.max_segment_depth.unwrap_or(defaults.max_segment_depth),
+            max_pattern_length: self
+                .max_pattern_length
+                .unwrap_or(defaults.max_pattern_length),
+        }
+    }
+
+    /// Returns the effective evaluation timestamp quantum.
+    ///
+    /// [`Duration::ZERO`] (no quantization) when
+    /// [`Self::evaluation_timestamp_quantum_secs`] is omitted.
+    #[must_use]
+    pub fn effective_timestamp_quantum(&self) -> Duration {
+        Duration::from_secs(self.evaluation_timestamp_quantum_secs.unwrap_or(0))
+    }
+
+    /// Returns the effective OFREP evaluation context limits.
+    ///
+    /// Each omitted field falls back to its
+    /// [`flaps_eval::ContextLimits::default`] value.
+    #[must_use]
+    pub fn effective_context_limits(&self) -> flaps_eval::ContextLimits {
+        let defaults = flaps_eval::ContextLimits::default();
+        flaps_eval::ContextLimits {
+            max_attributes: self
+                .context_max_attributes
+                .unwrap_or(defaults.max_attributes),
+            max_key_length: self
+                .context_max_key_length
+                .unwrap_or(defaults.max_key_length),
+            max_value_length: self
+                .context_max_value_length
+                .unwrap_or(defaults.max_value_length),
+            max_list_length: self
+                .context_max_list_length
+                .unwrap_or(defaults.max_list_length),
+        }
+    }
+
+    /// Returns the OFREP exposure sampler.
+    ///
+    /// `None`, no exposure logging, when [`Self::exposure_sample_rate`] is
+    /// omitted.
+    #[must_use]
+    pub fn effective_exposure_sampler(&self) -> Option<flaps_eval::ExposureSampler> {
+        self.exposure_sample_rate
+            .and_then(flaps_eval::ExposureSampler::new)
+    }
+
+    /// Returns the OFREP evaluation cache settings.
+    ///
+    /// `None`, no caching, when [`Self::evaluation_cache_environments`] is
+    /// omitted or empty. Invalid environment keys are skipped; [`Config::load`]
+    /// has already refused them.
+    #[must_use]
+    pub fn effective_evaluation_cache(
+        &self,
+    ) -> Option<flaps_server::eval_cache::EvaluationCacheConfig> {
+        use flaps_server::eval_cache::EvaluationCacheConfig;
+
+        let environments = self.evaluation_cache_environments.as_ref()?;
+        if environments.is_empty() {
+            return None;
+        }
+        Some(EvaluationCacheConfig {
+            environments: environments
+                .iter()
+                .filter_map(|environment| flaps_domain::EnvironmentKey::new(environment).ok())
+                .collect(),
+            ttl: self
+                .evaluation_cache_ttl_secs
+                .map_or(EvaluationCacheConfig::DEFAULT_TTL, Duration::from_secs),
+            capacity: self
+                .evaluation_cache_capacity
+                .unwrap_or(EvaluationCacheConfig::DEFAULT_CAPACITY),
+        })
+    }
+
+    /// Returns the segment preview context sample settings.
+    ///
+    /// `None`, no sampling, when [`Self::context_sample_size`] is omitted.
+    #[must_use]
+    pub fn effective_context_sample(
+        &self,
+    ) -> Option<flaps_server::context_sample::ContextSampleConfig> {
+        Some(flaps_server::context_sample::ContextSampleConfig {
+            capacity: self.context_sample_size?,
+            private_attributes: self.private_attributes.iter().flatten().cloned().collect(),
+        })
+    }
+
+    /// Returns the flapping detection thresholds.
+    ///
+    /// Each omitted field falls back to its
+    /// [`FlappingConfig::default`](flaps_server::flapping::FlappingConfig::default)
+    /// value.
+    #[must_use]
+    pub fn effective_flapping(&self) -> flaps_server::flapping::FlappingConfig {
+        let defaults = flaps_server::flapping::FlappingConfig::default();
+        flaps_server::flapping::FlappingConfig {
+            max_changes: self.flapping_max_changes.unwrap_or(defaults.max_changes),
+            window: self
+                .flapping_window_secs
+                .map_or(defaults.window, Duration::from_secs),
+        }
+    }
+
     /// Returns the `bind_addr` parsed as a [`SocketAddr`].
     ///
     /// # Errors
@@ -426,6 +748,21 @@ bind_addr    = "127.0.0.1:8080"
         );
     }
 
+    #[test]
+    fn load_malformed_database_pool_parameter_returns_err() {
+        let f = write_toml(
+            r#"
+database_url = "postgres://db/flaps?max_connections=many"
+bind_addr    = "127.0.0.1:8080"
+"#,
+        );
+        let result = Config::load(f.path().to_str().unwrap());
+        assert!(
+            matches!(result, Err(ConfigError::InvalidDatabaseUrl(ref m)) if m.contains("max_connections")),
+            "expected InvalidDatabaseUrl for a malformed pool parameter: {result:?}"
+        );
+    }
+
     #[test]
     fn load_missing_file_returns_io_error() {
         let result = Config::load("/this/path/does/not/exist.toml");
@@ -652,6 +989,331 @@ max_sse_subscriptions_per_key  = {max}
         assert_eq!(cfg.effective_max_sse_subscriptions_per_key(), max);
     }
 
+    // -- compile-time limits --
+
+    #[test]
+    fn load_omitted_limits_use_documented_defaults() {
+        let f = write_toml(
+            r#"
+database_url = "sqlite://flaps.db"
+bind_addr    = "127.0.0.1:8080"
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(cfg.effective_limits(), flaps_compiler::Limits::default());
+    }
+
+    #[test]
+    fn load_explicit_limits_are_applied() {
+        let f = write_toml(
+            r#"
+database_url            = "sqlite://flaps.db"
+bind_addr               = "127.0.0.1:8080"
+max_rules_per_config    = 10
+max_conditions_per_rule = 4
+max_segment_depth       = 3
+max_pattern_length      = 64
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        let limits = cfg.effective_limits();
+        assert_eq!(limits.max_rules_per_config, 10);
+        assert_eq!(limits.max_conditions_per_rule, 4);
+        assert_eq!(limits.max_segment_depth, 3);
+        assert_eq!(limits.max_pattern_length, 64);
+    }
+
+    #[test]
+    fn load_flapping_thresholds_default_and_apply_when_set() {
+        let f = write_toml(
+            r#"
+database_url = "sqlite://flaps.db"
+bind_addr    = "127.0.0.1:8080"
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(
+            cfg.effective_flapping(),
+            flaps_server::flapping::FlappingConfig::default()
+        );
+
+        let f = write_toml(
+            r#"
+database_url         = "sqlite://flaps.db"
+bind_addr            = "127.0.0.1:8080"
+flapping_max_changes = 3
+flapping_window_secs = 60
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(cfg.effective_flapping().max_changes, 3);
+        assert_eq!(cfg.effective_flapping().window, Duration::from_secs(60));
+
+        let f = write_toml(
+            r#"
+database_url         = "sqlite://flaps.db"
+bind_addr            = "127.0.0.1:8080"
+flapping_window_secs = 0
+"#,
+        );
+        assert!(matches!(
+            Config::load(f.path().to_str().unwrap()),
+            Err(ConfigError::InvalidLimit {
+                field: "flapping_window_secs"
+            })
+        ));
+    }
+
+    #[test]
+    fn load_timestamp_quantum_defaults_to_zero_and_applies_when_set() {
+        let f = write_toml(
+            r#"
+database_url = "sqlite://flaps.db"
+bind_addr    = "127.0.0.1:8080"
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(cfg.effective_timestamp_quantum(), Duration::ZERO);
+
+        let f = write_toml(
+            r#"
+database_url                      = "sqlite://flaps.db"
+bind_addr                         = "127.0.0.1:8080"
+evaluation_timestamp_quantum_secs = 60
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(cfg.effective_timestamp_quantum(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn load_context_limits_default_and_apply_when_set() {
+        let f = write_toml(
+            r#"
+database_url = "sqlite://flaps.db"
+bind_addr    = "127.0.0.1:8080"
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(
+            cfg.effective_context_limits(),
+            flaps_eval::ContextLimits::default()
+        );
+
+        let f = write_toml(
+            r#"
+database_url             = "sqlite://flaps.db"
+bind_addr                = "127.0.0.1:8080"
+context_max_attributes   = 32
+context_max_value_length = 512
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        let limits = cfg.effective_context_limits();
+        assert_eq!(limits.max_attributes, 32);
+        assert_eq!(limits.max_value_length, 512);
+        assert_eq!(
+            limits.max_key_length,
+            flaps_eval::DEFAULT_MAX_CONTEXT_KEY_LENGTH
+        );
+    }
+
+    #[test]
+    fn load_exposure_sample_rate_is_optional_and_bounded() {
+        let f = write_toml(
+            r#"
+database_url = "sqlite://flaps.db"
+bind_addr    = "127.0.0.1:8080"
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(cfg.effective_exposure_sampler(), None);
+
+        let f = write_toml(
+            r#"
+database_url         = "sqlite://flaps.db"
+bind_addr            = "127.0.0.1:8080"
+exposure_sample_rate = 0.01
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(
+            cfg.effective_exposure_sampler(),
+            flaps_eval::ExposureSampler::new(0.01)
+        );
+
+        let f = write_toml(
+            r#"
+database_url         = "sqlite://flaps.db"
+bind_addr            = "127.0.0.1:8080"
+exposure_sample_rate = 1.5
+"#,
+        );
+        let err = Config::load(f.path().to_str().unwrap()).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::InvalidExposureSampleRate(_)),
+            "expected InvalidExposureSampleRate, got {err:?}"
+        );
+    }
+
+    #[test]
+    fn load_evaluation_cache_is_opt_in_per_environment() {
+        let f = write_toml(
+            r#"
+database_url = "sqlite://flaps.db"
+bind_addr    = "127.0.0.1:8080"
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(cfg.effective_evaluation_cache(), None);
+
+        let f = write_toml(
+            r#"
+database_url                  = "sqlite://flaps.db"
+bind_addr                     = "127.0.0.1:8080"
+evaluation_cache_environments = ["production"]
+evaluation_cache_ttl_secs     = 2
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        let cache = cfg.effective_evaluation_cache().expect("cache enabled");
+        assert!(
+            cache
+                .environments
+                .contains(&flaps_domain::EnvironmentKey::new("production").unwrap())
+        );
+        assert_eq!(cache.environments.len(), 1);
+        assert_eq!(cache.ttl, Duration::from_secs(2));
+        assert_eq!(
+            cache.capacity,
+            flaps_server::eval_cache::EvaluationCacheConfig::DEFAULT_CAPACITY
+        );
+
+        let f = write_toml(
+            r#"
+database_url                  = "sqlite://flaps.db"
+bind_addr                     = "127.0.0.1:8080"
+evaluation_cache_environments = ["Not A Key"]
+"#,
+        );
+        let err = Config::load(f.path().to_str().unwrap()).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::InvalidEvaluationCacheEnvironment(_)),
+            "expected InvalidEvaluationCacheEnvironment, got {err:?}"
+        );
+
+        let f = write_toml(
+            r#"
+database_url                  = "sqlite://flaps.db"
+bind_addr                     = "127.0.0.1:8080"
+evaluation_cache_environments = ["production"]
+evaluation_cache_ttl_secs     = 0
+"#,
+        );
+        let err = Config::load(f.path().to_str().unwrap()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ConfigError::InvalidLimit {
+                    field: "evaluation_cache_ttl_secs"
+                }
+            ),
+            "expected InvalidLimit, got {err:?}"
+        );
+    }
+
+    #[test]
+    fn load_context_sample_is_opt_in_and_carries_private_attributes() {
+        let f = write_toml(
+            r#"
+database_url = "sqlite://flaps.db"
+bind_addr    = "127.0.0.1:8080"
+private_attributes = ["email"]
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        assert_eq!(cfg.effective_context_sample(), None);
+
+        let f = write_toml(
+            r#"
+database_url        = "sqlite://flaps.db"
+bind_addr           = "127.0.0.1:8080"
+context_sample_size = 500
+private_attributes  = ["email", "phone"]
+"#,
+        );
+        let cfg = Config::load(f.path().to_str().unwrap()).expect("load");
+        let sample = cfg.effective_context_sample().expect("sampling enabled");
+        assert_eq!(sample.capacity, 500);
+        assert_eq!(
+            sample.private_attributes,
+            ["email".to_owned(), "phone".to_owned()].into()
+        );
+
+        let f = write_toml(
+            r#"
+database_url        = "sqlite://flaps.db"
+bind_addr           = "127.0.0.1:8080"
+context_sample_size = 0
+"#,
+        );
+        let err = Config::load(f.path().to_str().unwrap()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ConfigError::InvalidLimit {
+                    field: "context_sample_size"
+                }
+            ),
+            "expected InvalidLimit, got {err:?}"
+        );
+    }
+
+    #[test]
+    fn load_geoip_database_requires_the_maxmind_feature() {
+        let f = write_toml(
+            r#"
+database_url   = "sqlite://flaps.db"
+bind_addr      = "127.0.0.1:8080"
+geoip_database = "/var/lib/GeoIP/GeoLite2-City.mmdb"
+"#,
+        );
+        let result = Config::load(f.path().to_str().unwrap());
+        if cfg!(feature = "maxmind") {
+            let cfg = result.expect("load");
+            assert_eq!(
+                cfg.geoip_database.as_deref(),
+                Some(std::path::Path::new("/var/lib/GeoIP/GeoLite2-City.mmdb"))
+            );
+        } else {
+            assert!(
+                matches!(result, Err(ConfigError::GeoIpUnsupported)),
+                "expected GeoIpUnsupported, got {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn load_zero_limit_returns_err() {
+        let f = write_toml(
+            r#"
+database_url         = "sqlite://flaps.db"
+bind_addr            = "127.0.0.1:8080"
+max_rules_per_config = 0
+"#,
+        );
+        let err = Config::load(f.path().to_str().unwrap()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ConfigError::InvalidLimit {
+                    field: "max_rules_per_config"
+                }
+            ),
+            "expected InvalidLimit, got {err:?}"
+        );
+    }
+
     // -- read_pepper --
 
     #[test]
@@ -0,0 +1,177 @@
+//! `flapsd diff`: compares the flag configurations of two environments.
+//!
+//! The diff lists what would turn the target environment's configurations
+//! into the source's, flag by flag: configurations added or removed, and the
+//! field-level changes of the others, rendered like the flag history
+//! ("enabled: false → true"). `--output patch` prints the same diff as an
+//! [`EnvironmentPatch`] in JSON instead, which `flapsd import` applies to the
+//! target later (see [`crate::import`]). Stored configurations are compared
+//! as they are, without resolving environment inheritance.
+
+use std::fmt::Write as _;
+
+use flaps_domain::{DomainError, EnvironmentKey, EnvironmentPatch, FlagKey, FlagPatch, ProjectKey};
+use flaps_store::{
+    StoreError,
+    repository::{
+        EnvironmentRepository, FlagEnvConfigRepository, FlagRepository, ProjectRepository,
+    },
+};
+
+/// Output format of `flapsd diff`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiffOutput {
+    /// One line per change.
+    #[default]
+    Text,
+    /// An environment patch, as JSON, for `flapsd import`.
+    Patch,
+}
+
+/// Arguments of `flapsd diff`.
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// Key of the project.
+    pub project: String,
+
+    /// Environment whose configurations are wanted.
+    pub source: String,
+
+    /// Environment compared with the source, and patched by the diff.
+    pub target: String,
+
+    /// Only compares this flag; repeat for several. Defaults to every flag.
+    #[arg(long = "flag")]
+    pub flags: Vec<String>,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub output: DiffOutput,
+}
+
+/// Errors of an environment diff.
+#[derive(Debug, thiserror::Error)]
+pub enum DiffError {
+    /// A project, environment or flag key is not kebab-case.
+    #[error(transparent)]
+    InvalidKey(#[from] DomainError),
+
+    /// The project does not exist.
+    #[error("project {0} not found")]
+    ProjectNotFound(ProjectKey),
+
+    /// An environment does not exist in the project.
+    #[error("environment {0} not found")]
+    EnvironmentNotFound(EnvironmentKey),
+
+    /// A flag named with `--flag` does not exist in the project.
+    #[error("flag {0} not found")]
+    FlagNotFound(FlagKey),
+
+    /// The store could not be read.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    /// The patch could not be serialized as JSON.
+    #[error("cannot serialize the patch: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Builds the patch making the configurations of `target` match those of
+/// `source`, for `flags` or, when empty, every flag of the project.
+///
+/// # Errors
+///
+/// Returns [`DiffError::ProjectNotFound`], [`DiffError::EnvironmentNotFound`]
+/// or [`DiffError::FlagNotFound`] for a key that names nothing, and
+/// [`DiffError::Store`] when the store cannot be read.
+pub async fn environment_patch<S>(
+    store: &S,
+    project: &ProjectKey,
+    source: &EnvironmentKey,
+    target: &EnvironmentKey,
+    flags: &[FlagKey],
+) -> Result<EnvironmentPatch, DiffError>
+where
+    S: ProjectRepository + EnvironmentRepository + FlagRepository + FlagEnvConfigRepository,
+{
+    if store.get_project(project).await?.is_none() {
+        return Err(DiffError::ProjectNotFound(project.clone()));
+    }
+    for environment in [source, target] {
+        if store.get_environment(project, environment).await?.is_none() {
+            return Err(DiffError::EnvironmentNotFound(environment.clone()));
+        }
+    }
+    let mut keys = if flags.is_empty() {
+        store
+            .list_flags(project)
+            .await?
+            .into_iter()
+            .map(|flag| flag.key)
+            .collect()
+    } else {
+        for flag in flags {
+            if store.get_flag(project, flag).await?.is_none() {
+                return Err(DiffError::FlagNotFound(flag.clone()));
+            }
+        }
+        flags.to_vec()
+    };
+    keys.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    keys.dedup();
+
+    let mut patches = Vec::new();
+    for flag in keys {
+        let before = store.get_flag_env_config(project, &flag, target).await?;
+        let after = store.get_flag_env_config(project, &flag, source).await?;
+        patches.extend(FlagPatch::new(flag, before, after));
+    }
+    Ok(EnvironmentPatch {
+        project: project.clone(),
+        environment: target.clone(),
+        flags: patches,
+    })
+}
+
+/// Diffs what `args` asks for and renders it in its format.
+///
+/// # Errors
+///
+/// Returns [`DiffError::InvalidKey`] for a key that is not kebab-case, and
+/// the errors of [`environment_patch`].
+pub async fn render<S>(store: &S, args: &DiffArgs) -> Result<String, DiffError>
+where
+    S: ProjectRepository + EnvironmentRepository + FlagRepository + FlagEnvConfigRepository,
+{
+    let project = ProjectKey::new(&args.project)?;
+    let source = EnvironmentKey::new(&args.source)?;
+    let target = EnvironmentKey::new(&args.target)?;
+    let flags = args
+        .flags
+        .iter()
+        .map(FlagKey::new)
+        .collect::<Result<Vec<_>, _>>()?;
+    let patch = environment_patch(store, &project, &source, &target, &flags).await?;
+    match args.output {
+        DiffOutput::Text => {
+            let mut out = String::new();
+            for flag in &patch.flags {
+                for line in describe(flag) {
+                    let _ = writeln!(out, "{}: {line}", flag.flag.as_str());
+                }
+            }
+            Ok(out)
+        }
+        DiffOutput::Patch => Ok(serde_json::to_string_pretty(&patch)? + "\n"),
+    }
+}
+
+/// Describes the change of one flag, one line per field-level change.
+pub(crate) fn describe(patch: &FlagPatch) -> Vec<String> {
+    match (&patch.before, &patch.after) {
+        (None, Some(_)) => vec!["configuration added".to_owned()],
+        (Some(_), None) => vec!["configuration removed".to_owned()],
+        _ => patch.changes().iter().map(ToString::to_string).collect(),
+    }
+}
@@ -0,0 +1,645 @@
+//! `flapsd export`: dumps the flags of one project from the configured store.
+//!
+//! Two formats are offered. `json` is the whole project (environments,
+//! segments, flags and their per-environment configurations) as stored, for
+//! tooling. `review` flattens the evaluation rules into one block per flag
+//! per environment, for reading in a pull request: keys are sorted, every
+//! block has the same shape, and serve targets read as they do in the flag
+//! history (`on:10, off:90`), so two exports of a GitOps repository diff line
+//! by line. The review format is lossy on purpose (no variant values, no
+//! segment definitions) and is not meant to be read back.
+//!
+//! The JSON format is versioned: every export records its
+//! [`EXPORT_SCHEMA_VERSION`] and the Flaps version that wrote it, and
+//! [`ProjectExport::from_json`] reads exports of the current and older
+//! versions, migrating the older ones, and refuses those of a newer Flaps.
+//! Exports written before the version was recorded read as version 1.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use flaps_domain::{
+    DomainError, Environment, Flag, FlagEnvConfig, Project, ProjectKey, Segment, SegmentKey,
+    ServeTarget,
+};
+use flaps_store::{
+    StoreError,
+    repository::{
+        EnvironmentRepository, FlagEnvConfigRepository, FlagRepository, ProjectRepository,
+        SegmentRepository,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Version of the JSON export format this build writes and reads.
+///
+/// Bump it when a change to the exported types is not readable by the
+/// previous serde definitions (a renamed or restructured field), and add
+/// the step upgrading the previous version to [`MIGRATIONS`]. Fields added
+/// with a serde default need no bump: older exports lack them and still
+/// load.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades of a raw export, one per schema version: `MIGRATIONS[i]` turns
+/// version `i + 1` into version `i + 2`.
+const MIGRATIONS: [fn(&mut Map<String, Value>); EXPORT_SCHEMA_VERSION as usize - 1] = [];
+
+/// Output format of `flapsd export`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// The whole project as JSON.
+    #[default]
+    Json,
+    /// Flattened evaluation rules, one YAML block per flag per environment.
+    Review,
+}
+
+/// Arguments of `flapsd export`.
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    /// Key of the project to export.
+    pub project: String,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ExportFormat,
+}
+
+/// Errors of a project export.
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    /// The project key is not kebab-case.
+    #[error(transparent)]
+    InvalidKey(#[from] DomainError),
+
+    /// The project does not exist.
+    #[error("project {0} not found")]
+    ProjectNotFound(ProjectKey),
+
+    /// The store could not be read.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    /// The export could not be serialized or parsed as JSON.
+    #[error("invalid export JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The export was written in a schema version this build cannot read.
+    #[error(
+        "export schema version {found} is not supported (this Flaps reads 1 to {supported}); \
+         it was written by Flaps {written_by}"
+    )]
+    UnsupportedSchemaVersion {
+        /// Schema version of the export.
+        found: u64,
+        /// Newest schema version this build reads.
+        supported: u32,
+        /// Flaps version that wrote the export, or `unknown`.
+        written_by: String,
+    },
+}
+
+/// A project with everything that decides how its flags evaluate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectExport {
+    /// Version of the export format, [`EXPORT_SCHEMA_VERSION`] when written.
+    pub schema_version: u32,
+    /// Version of Flaps that wrote the export, for diagnostics; `None` for
+    /// exports written before it was recorded.
+    #[serde(default)]
+    pub flaps_version: Option<String>,
+    /// The project itself.
+    pub project: Project,
+    /// Its environments, sorted by key.
+    pub environments: Vec<Environment>,
+    /// Its segments, sorted by key.
+    pub segments: Vec<Segment>,
+    /// Its flags, sorted by key.
+    pub flags: Vec<FlagExport>,
+}
+
+/// A flag and its configuration in each environment that has one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlagExport {
+    /// The flag definition.
+    pub flag: Flag,
+    /// Its configurations, keyed by environment.
+    pub environments: BTreeMap<String, FlagEnvConfig>,
+}
+
+/// Reads the project `key` from `store`.
+///
+/// # Errors
+///
+/// Returns [`ExportError::InvalidKey`] for a key that is not kebab-case,
+/// [`ExportError::ProjectNotFound`] when the project does not exist and
+/// [`ExportError::Store`] when the store cannot be read.
+pub async fn export_project<S>(store: &S, key: &str) -> Result<ProjectExport, ExportError>
+where
+    S: ProjectRepository
+        + EnvironmentRepository
+        + SegmentRepository
+        + FlagRepository
+        + FlagEnvConfigRepository,
+{
+    let key = ProjectKey::new(key)?;
+    let Some(project) = store.get_project(&key).await? else {
+        return Err(ExportError::ProjectNotFound(key));
+    };
+    let mut environments = store.list_environments(&key).await?;
+    environments.sort_by(|a, b| a.key.as_str().cmp(b.key.as_str()));
+    let mut segments = store.list_segments(&key).await?;
+    segments.sort_by(|a, b| a.key.as_str().cmp(b.key.as_str()));
+    let mut flags = store.list_flags(&key).await?;
+    flags.sort_by(|a, b| a.key.as_str().cmp(b.key.as_str()));
+
+    let mut exports = Vec::with_capacity(flags.len());
+    for flag in flags {
+        let mut configs = BTreeMap::new();
+        for environment in &environments {
+            if let Some(config) = store
+                .get_flag_env_config(&key, &flag.key, &environment.key)
+                .await?
+            {
+                configs.insert(environment.key.as_str().to_owned(), config);
+            }
+        }
+        exports.push(FlagExport {
+            flag,
+            environments: configs,
+        });
+    }
+
+    Ok(ProjectExport {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        flaps_version: Some(env!("CARGO_PKG_VERSION").to_owned()),
+        project,
+        environments,
+        segments,
+        flags: exports,
+    })
+}
+
+impl ProjectExport {
+    /// Reads a JSON export written by this or an earlier Flaps, migrating it
+    /// to the current schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExportError::UnsupportedSchemaVersion`] for an export of a
+    /// newer schema than [`EXPORT_SCHEMA_VERSION`] (or of version 0), and
+    /// [`ExportError::Json`] for a document that is not a valid export.
+    pub fn from_json(json: &str) -> Result<Self, ExportError> {
+        let mut value: Value = serde_json::from_str(json)?;
+        if let Value::Object(export) = &mut value {
+            let version = match export.get("schema_version") {
+                None => 1,
+                Some(version) => version.as_u64().unwrap_or(0),
+            };
+            if version == 0 || version > u64::from(EXPORT_SCHEMA_VERSION) {
+                let written_by = export
+                    .get("flaps_version")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown")
+                    .to_owned();
+                return Err(ExportError::UnsupportedSchemaVersion {
+                    found: version,
+                    supported: EXPORT_SCHEMA_VERSION,
+                    written_by,
+                });
+            }
+            // The version was checked against the bound of MIGRATIONS above.
+            #[allow(clippy::cast_possible_truncation)]
+            for migrate in &MIGRATIONS[version as usize - 1..] {
+                migrate(export);
+            }
+            export.insert("schema_version".to_owned(), EXPORT_SCHEMA_VERSION.into());
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Exports what `args` asks for and renders it in its format.
+///
+/// # Errors
+///
+/// See [`export_project`].
+pub async fn render<S>(store: &S, args: &ExportArgs) -> Result<String, ExportError>
+where
+    S: ProjectRepository
+        + EnvironmentRepository
+        + SegmentRepository
+        + FlagRepository
+        + FlagEnvConfigRepository,
+{
+    let export = export_project(store, &args.project).await?;
+    match args.format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(&export)? + "\n"),
+        ExportFormat::Review => Ok(to_review(&export)),
+    }
+}
+
+/// Renders the review format: a YAML mapping from `flag/environment` to the
+/// flattened configuration.
+fn to_review(export: &ProjectExport) -> String {
+    let mut out = format!(
+        "# Evaluation rules of project {}, for review only: not importable.\n",
+        export.project.key.as_str()
+    );
+    for flag in &export.flags {
+        for (environment, config) in &flag.environments {
+            let _ = writeln!(out, "\n{}/{}:", flag.flag.key.as_str(), environment);
+            let _ = writeln!(out, "  enabled: {}", config.enabled);
+            if let Some(reason) = &config.kill_reason {
+                let _ = writeln!(out, "  killed: {}", quoted(reason));
+            }
+            if let Some(version) = &config.min_app_version {
+                let _ = writeln!(out, "  min_app_version: {}", quoted(version));
+            }
+            if let Some(salt) = &config.salt {
+                let _ = writeln!(out, "  salt: {}", quoted(salt));
+            }
+            if let Some(attribute) = &config.bucket_by {
+                let _ = writeln!(out, "  bucket_by: {}", quoted(attribute));
+            }
+            let _ = writeln!(out, "  rollout_scope: {}", config.rollout_scope);
+            if config.rules.is_empty() {
+                out.push_str("  rules: []\n");
+            } else {
+                out.push_str("  rules:\n");
+            }
+            for rule in &config.rules {
+                let segments: Vec<&str> = rule.segments.iter().map(SegmentKey::as_str).collect();
+                let _ = writeln!(out, "    - segments: [{}]", segments.join(", "));
+                if !rule.segment_groups.is_empty() {
+                    let groups: Vec<String> = rule
+                        .segment_groups
+                        .iter()
+                        .map(|group| {
+                            let keys: Vec<&str> = group.iter().map(SegmentKey::as_str).collect();
+                            format!("[{}]", keys.join(", "))
+                        })
+                        .collect();
+                    let _ = writeln!(out, "      segment_groups: [{}]", groups.join(", "));
+                }
+                if let Some(description) = &rule.description {
+                    let _ = writeln!(out, "      description: {}", quoted(description));
+                }
+                let _ = writeln!(out, "      serve: {}", serve(&rule.serve));
+                if let Some(attribute) = &rule.bucket_by {
+                    let _ = writeln!(out, "      bucket_by: {}", quoted(attribute));
+                }
+            }
+            let _ = writeln!(out, "  default: {}", serve(&config.default_rule));
+            if config.inherit_default_rule {
+                out.push_str("  inherit_default_rule: true\n");
+            }
+        }
+    }
+    out
+}
+
+/// Renders a serve target as a quoted YAML scalar.
+fn serve(target: &ServeTarget) -> String {
+    quoted(&target.to_string())
+}
+
+/// Quotes a string as YAML: a JSON string is a valid double-quoted scalar.
+fn quoted(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_domain::{
+        EnvironmentKey, FlagKey, FlagType, Metadata, RolloutScope, TargetingRule, ValueType,
+        VariantKey, VariantValue, Variants, WeightedVariant,
+    };
+    use flaps_store::{KeyHasher, sqlite::SqliteStore};
+
+    use super::*;
+    use crate::provision::{CreateProjectArgs, create_project};
+
+    const ACTOR: &str = "test";
+
+    async fn make_store() -> SqliteStore {
+        SqliteStore::in_memory(KeyHasher::new(b"test-pepper-32-bytes-minimum-len!"))
+            .await
+            .expect("in-memory store")
+    }
+
+    fn variant(key: &str) -> VariantKey {
+        VariantKey::new(key).unwrap()
+    }
+
+    fn boolean_flag(key: &str) -> Flag {
+        Flag {
+            key: FlagKey::new(key).unwrap(),
+            name: key.to_owned(),
+            description: None,
+            flag_type: FlagType::Release,
+            value_type: ValueType::Boolean,
+            variants: Variants::new(
+                ValueType::Boolean,
+                [
+                    (variant("on"), VariantValue::Bool(true)),
+                    (variant("off"), VariantValue::Bool(false)),
+                ],
+            )
+            .unwrap(),
+            metadata: Metadata::new(),
+        }
+    }
+
+    fn config(enabled: bool, default_rule: ServeTarget) -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled,
+            rules: Vec::new(),
+            default_rule,
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    /// A `shop` project with two flags configured in some environments,
+    /// written in an order unrelated to their keys.
+    async fn seeded_store() -> SqliteStore {
+        let store = make_store().await;
+        create_project(
+            &store,
+            &CreateProjectArgs {
+                key: "shop".to_owned(),
+                name: None,
+                description: None,
+            },
+        )
+        .await
+        .unwrap();
+        let project = ProjectKey::new("shop").unwrap();
+        let env = |key: &str| EnvironmentKey::new(key).unwrap();
+
+        for flag in ["search", "checkout"] {
+            store
+                .upsert_flag(ACTOR, &project, &boolean_flag(flag))
+                .await
+                .unwrap();
+        }
+        let checkout = FlagKey::new("checkout").unwrap();
+        let mut prod = config(
+            true,
+            ServeTarget::rollout(vec![
+                WeightedVariant {
+                    variant: variant("on"),
+                    weight: 10,
+                },
+                WeightedVariant {
+                    variant: variant("off"),
+                    weight: 90,
+                },
+            ])
+            .unwrap(),
+        );
+        prod.rules.push(TargetingRule {
+            segments: vec![
+                SegmentKey::new("staff").unwrap(),
+                SegmentKey::new("eu").unwrap(),
+            ],
+            segment_groups: vec![
+                vec![SegmentKey::new("beta").unwrap()],
+                vec![
+                    SegmentKey::new("pro").unwrap(),
+                    SegmentKey::new("mobile").unwrap(),
+                ],
+            ],
+            serve: ServeTarget::Fixed(variant("on")),
+            bucket_by: None,
+            description: Some("EU staff try it first".to_owned()),
+        });
+        prod.salt = Some("checkout-v2".to_owned());
+        prod.bucket_by = Some("company_id".to_owned());
+        prod.rollout_scope = RolloutScope::PerRule;
+        store
+            .upsert_flag_env_config(ACTOR, &project, &checkout, &env("prod"), &prod)
+            .await
+            .unwrap();
+        store
+            .upsert_flag_env_config(
+                ACTOR,
+                &project,
+                &checkout,
+                &env("dev"),
+                &config(true, ServeTarget::Fixed(variant("on"))),
+            )
+            .await
+            .unwrap();
+        let mut search = config(true, ServeTarget::Fixed(variant("on")));
+        search.kill_reason = Some("INC-42: \"slow\" queries".to_owned());
+        store
+            .upsert_flag_env_config(
+                ACTOR,
+                &project,
+                &FlagKey::new("search").unwrap(),
+                &env("staging"),
+                &search,
+            )
+            .await
+            .unwrap();
+        store
+    }
+
+    fn review_args() -> ExportArgs {
+        ExportArgs {
+            project: "shop".to_owned(),
+            format: ExportFormat::Review,
+        }
+    }
+
+    #[tokio::test]
+    async fn review_flattens_one_block_per_flag_and_environment() {
+        let store = seeded_store().await;
+
+        assert_eq!(
+            render(&store, &review_args()).await.unwrap(),
+            "# Evaluation rules of project shop, for review only: not importable.\n\
+             \n\
+             checkout/dev:\n\
+             \x20 enabled: true\n\
+             \x20 rollout_scope: shared\n\
+             \x20 rules: []\n\
+             \x20 default: \"on\"\n\
+             \n\
+             checkout/prod:\n\
+             \x20 enabled: true\n\
+             \x20 salt: \"checkout-v2\"\n\
+             \x20 bucket_by: \"company_id\"\n\
+             \x20 rollout_scope: per_rule\n\
+             \x20 rules:\n\
+             \x20   - segments: [staff, eu]\n\
+             \x20     segment_groups: [[beta], [pro, mobile]]\n\
+             \x20     description: \"EU staff try it first\"\n\
+             \x20     serve: \"on\"\n\
+             \x20 default: \"on:10, off:90\"\n\
+             \n\
+             search/staging:\n\
+             \x20 enabled: true\n\
+             \x20 killed: \"INC-42: \\\"slow\\\" queries\"\n\
+             \x20 rollout_scope: shared\n\
+             \x20 rules: []\n\
+             \x20 default: \"on\"\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn review_is_stable_across_exports_and_rewrites() {
+        let store = seeded_store().await;
+        let first = render(&store, &review_args()).await.unwrap();
+        assert_eq!(render(&store, &review_args()).await.unwrap(), first);
+
+        // Rewriting a configuration unchanged leaves the export untouched.
+        let project = ProjectKey::new("shop").unwrap();
+        let checkout = FlagKey::new("checkout").unwrap();
+        let prod = EnvironmentKey::new("prod").unwrap();
+        let config = store
+            .get_flag_env_config(&project, &checkout, &prod)
+            .await
+            .unwrap()
+            .unwrap();
+        store
+            .upsert_flag_env_config(ACTOR, &project, &checkout, &prod, &config)
+            .await
+            .unwrap();
+        assert_eq!(render(&store, &review_args()).await.unwrap(), first);
+    }
+
+    #[tokio::test]
+    async fn json_export_carries_the_whole_project() {
+        let store = seeded_store().await;
+        let export = export_project(&store, "shop").await.unwrap();
+
+        let environments: Vec<&str> = export
+            .environments
+            .iter()
+            .map(|env| env.key.as_str())
+            .collect();
+        assert_eq!(environments, ["dev", "prod", "staging"]);
+        let flags: Vec<&str> = export.flags.iter().map(|f| f.flag.key.as_str()).collect();
+        assert_eq!(flags, ["checkout", "search"]);
+        let checkout_envs: Vec<&String> = export.flags[0].environments.keys().collect();
+        assert_eq!(checkout_envs, ["dev", "prod"]);
+
+        let json = render(
+            &store,
+            &ExportArgs {
+                project: "shop".to_owned(),
+                format: ExportFormat::Json,
+            },
+        )
+        .await
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["project"]["key"], "shop");
+        assert_eq!(
+            value["flags"][0]["environments"]["prod"]["salt"],
+            "checkout-v2"
+        );
+    }
+
+    #[tokio::test]
+    async fn json_export_is_versioned_and_reads_back() {
+        let store = seeded_store().await;
+        let export = export_project(&store, "shop").await.unwrap();
+        assert_eq!(export.schema_version, EXPORT_SCHEMA_VERSION);
+        assert_eq!(
+            export.flaps_version.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+
+        let json = serde_json::to_string(&export).unwrap();
+        assert_eq!(ProjectExport::from_json(&json).unwrap(), export);
+    }
+
+    /// An export as written before the format was versioned: no schema or
+    /// Flaps version, and configurations without the fields added since.
+    const V1_EXPORT: &str = r#"{
+        "project": {
+            "key": "shop",
+            "name": "Shop",
+            "description": null,
+            "external_ref": null,
+            "managed_by": "local"
+        },
+        "environments": [],
+        "segments": [],
+        "flags": [{
+            "flag": {
+                "key": "checkout",
+                "name": "Checkout",
+                "description": null,
+                "flag_type": "release",
+                "value_type": "number",
+                "variants": {
+                    "value_type": "number",
+                    "entries": { "low": { "number": 1.0 }, "high": { "number": 2.5 } }
+                }
+            },
+            "environments": {
+                "prod": { "enabled": true, "rules": [], "default_rule": { "fixed": "high" } }
+            }
+        }]
+    }"#;
+
+    #[test]
+    fn a_v1_export_still_loads() {
+        let export = ProjectExport::from_json(V1_EXPORT).unwrap();
+        assert_eq!(export.schema_version, EXPORT_SCHEMA_VERSION);
+        assert_eq!(export.flaps_version, None);
+        assert_eq!(export.project.key.as_str(), "shop");
+        let checkout = &export.flags[0];
+        assert_eq!(
+            checkout.flag.variants.get(&variant("high")),
+            Some(&VariantValue::Number(2.5))
+        );
+        let prod = &checkout.environments["prod"];
+        assert_eq!(prod.default_rule, ServeTarget::Fixed(variant("high")));
+        assert_eq!(prod.rollout_scope, RolloutScope::default());
+        assert_eq!(prod.min_app_version, None);
+    }
+
+    #[test]
+    fn an_export_from_a_newer_flaps_is_refused() {
+        let mut future: serde_json::Value = serde_json::from_str(V1_EXPORT).unwrap();
+        future["schema_version"] = (EXPORT_SCHEMA_VERSION + 1).into();
+        future["flaps_version"] = "9.0.0".into();
+        let err = ProjectExport::from_json(&future.to_string()).unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                ExportError::UnsupportedSchemaVersion { found, written_by, .. }
+                    if *found == u64::from(EXPORT_SCHEMA_VERSION) + 1 && written_by == "9.0.0"
+            ),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("written by Flaps 9.0.0"), "{err}");
+
+        future["schema_version"] = 0.into();
+        let err = ProjectExport::from_json(&future.to_string()).unwrap_err();
+        assert!(
+            matches!(err, ExportError::UnsupportedSchemaVersion { found: 0, .. }),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_project_is_refused() {
+        let store = make_store().await;
+        let err = export_project(&store, "ghost").await.unwrap_err();
+        assert!(matches!(err, ExportError::ProjectNotFound(_)), "{err:?}");
+        let err = export_project(&store, "Not A Key").await.unwrap_err();
+        assert!(matches!(err, ExportError::InvalidKey(_)), "{err:?}");
+    }
+}
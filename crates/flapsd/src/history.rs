@@ -0,0 +1,430 @@
+//! `flapsd flag history`: lists the recorded changes of one flag.
+//!
+//! The history is read from the store's audit log. It covers the flag itself
+//! (`flag` records: name, description, variants...) and its configuration in
+//! every environment (`flag_env_config` records), including environments
+//! deleted since. Each entry says who changed what, and when; configuration
+//! updates are rendered with [`diff_configs`] ("enabled: true → false"), flag
+//! updates name the fields that changed.
+//!
+//! Entries are listed oldest first. `--limit` keeps the most recent ones and
+//! `--offset` skips that many of the most recent first, so successive pages
+//! walk back through the history.
+
+use std::fmt::Write as _;
+
+use flaps_domain::{DomainError, EnvironmentKey, FlagEnvConfig, FlagKey, ProjectKey, diff_configs};
+use flaps_store::{AuditRecord, StoreError, repository::AuditLogRepository};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Output format of `flapsd flag history`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum HistoryFormat {
+    /// Aligned columns, one line per change.
+    #[default]
+    Table,
+    /// A JSON array with one object per entry.
+    Json,
+}
+
+/// `flapsd flag` actions.
+#[derive(Debug, clap::Subcommand)]
+pub enum FlagCommand {
+    /// Lists the recorded changes of a flag, across environments.
+    History(HistoryArgs),
+}
+
+/// Arguments of `flag history`.
+#[derive(Debug, clap::Args)]
+pub struct HistoryArgs {
+    /// Key of the flag.
+    pub key: String,
+
+    /// Key of the project the flag belongs to.
+    #[arg(long)]
+    pub project: String,
+
+    /// Only lists the configuration changes of this environment.
+    #[arg(long)]
+    pub env: Option<String>,
+
+    /// Number of entries listed, the most recent ones.
+    #[arg(long, default_value_t = 50)]
+    pub limit: usize,
+
+    /// Number of most recent entries skipped, to page back.
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: HistoryFormat,
+}
+
+/// Errors of a history listing.
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    /// A project, flag or environment key is not kebab-case.
+    #[error(transparent)]
+    InvalidKey(#[from] DomainError),
+
+    /// The audit log could not be read.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    /// The listing could not be serialized as JSON.
+    #[error("cannot serialize the history: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// One recorded change of the flag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HistoryEntry {
+    /// When the change was made (RFC 3339, UTC).
+    pub occurred_at: String,
+    /// Who made it.
+    pub actor: String,
+    /// The audit action, e.g. `flag_env_config.updated`.
+    pub action: String,
+    /// The environment whose configuration changed; `None` for changes to
+    /// the flag itself.
+    pub environment: Option<String>,
+    /// What changed, one line per change.
+    pub changes: Vec<String>,
+}
+
+/// Reads the history of the flag named by `args` from `store`.
+///
+/// # Errors
+///
+/// Returns [`HistoryError::InvalidKey`] for a key that is not kebab-case and
+/// [`HistoryError::Store`] when the audit log cannot be read.
+pub async fn history<S: AuditLogRepository>(
+    store: &S,
+    args: &HistoryArgs,
+) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let project = ProjectKey::new(&args.project)?;
+    let flag = FlagKey::new(&args.key)?;
+    let environment = args.env.as_deref().map(EnvironmentKey::new).transpose()?;
+
+    let flag_id = format!("{}/{}", project.as_str(), flag.as_str());
+    let config_prefix = format!("{flag_id}/");
+    let entries: Vec<HistoryEntry> = store
+        .list_audit_entries()
+        .await?
+        .into_iter()
+        .filter_map(|record| match record.entity_type.as_str() {
+            "flag" if environment.is_none() && record.entity_id == flag_id => {
+                Some(entry(record, None))
+            }
+            "flag_env_config" => {
+                let env = record.entity_id.strip_prefix(&config_prefix)?.to_owned();
+                environment
+                    .as_ref()
+                    .is_none_or(|wanted| wanted.as_str() == env)
+                    .then(|| entry(record, Some(env)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let end = entries.len().saturating_sub(args.offset);
+    let start = end.saturating_sub(args.limit);
+    Ok(entries[start..end].to_vec())
+}
+
+/// Lists what `args` asks for and renders it in its format.
+///
+/// # Errors
+///
+/// See [`history`].
+pub async fn render<S: AuditLogRepository>(
+    store: &S,
+    args: &HistoryArgs,
+) -> Result<String, HistoryError> {
+    let entries = history(store, args).await?;
+    match args.format {
+        HistoryFormat::Table => Ok(to_table(&entries)),
+        HistoryFormat::Json => Ok(serde_json::to_string_pretty(&entries)? + "\n"),
+    }
+}
+
+/// Builds the entry of one audit record.
+fn entry(record: AuditRecord, environment: Option<String>) -> HistoryEntry {
+    let changes = match (&record.before, &record.after) {
+        (None, _) => vec!["created".to_owned()],
+        (_, None) => vec!["deleted".to_owned()],
+        (Some(before), Some(after)) if environment.is_some() => config_changes(before, after),
+        (Some(before), Some(after)) => flag_changes(before, after),
+    };
+    HistoryEntry {
+        occurred_at: record.occurred_at,
+        actor: record.actor,
+        action: record.action,
+        environment,
+        changes,
+    }
+}
+
+/// Lists the changes between two configuration snapshots.
+fn config_changes(before: &Value, after: &Value) -> Vec<String> {
+    let parse = |value: &Value| serde_json::from_value::<FlagEnvConfig>(value.clone());
+    let (Ok(before), Ok(after)) = (parse(before), parse(after)) else {
+        // A snapshot from an older schema: say so rather than guess.
+        return vec!["updated (snapshot not comparable)".to_owned()];
+    };
+    let changes: Vec<String> = diff_configs(&before, &after)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    if !changes.is_empty() {
+        changes
+    } else if before == after {
+        vec!["no effective change".to_owned()]
+    } else {
+        // `diff_configs` matches rules by segment set, not by position.
+        vec!["rules reordered".to_owned()]
+    }
+}
+
+/// Lists the top-level fields that differ between two flag snapshots,
+/// with their values when they are scalars.
+fn flag_changes(before: &Value, after: &Value) -> Vec<String> {
+    let empty = serde_json::Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let (from, to) = (before.get(field), after.get(field));
+            if from == to {
+                return None;
+            }
+            Some(match (scalar(from), scalar(to)) {
+                (Some(from), Some(to)) => format!("{field}: {from} → {to}"),
+                _ => format!("{field} changed"),
+            })
+        })
+        .collect()
+}
+
+/// Renders an absent, null or scalar field value; `None` for arrays and
+/// objects.
+fn scalar(value: Option<&Value>) -> Option<String> {
+    match value {
+        None | Some(Value::Null) => Some("none".to_owned()),
+        Some(Value::String(text)) => Some(text.clone()),
+        Some(Value::Bool(_) | Value::Number(_)) => value.map(ToString::to_string),
+        Some(Value::Array(_) | Value::Object(_)) => None,
+    }
+}
+
+/// Renders `entries` as aligned columns with a header line. An entry with
+/// several changes takes one line per change.
+#[must_use]
+pub fn to_table(entries: &[HistoryEntry]) -> String {
+    const HEADER: [&str; 4] = ["WHEN", "ACTOR", "ENV", "CHANGE"];
+    let rows: Vec<[&str; 4]> = entries
+        .iter()
+        .flat_map(|entry| {
+            entry.changes.iter().enumerate().map(move |(i, change)| {
+                if i == 0 {
+                    [
+                        entry.occurred_at.as_str(),
+                        entry.actor.as_str(),
+                        entry.environment.as_deref().unwrap_or("-"),
+                        change.as_str(),
+                    ]
+                } else {
+                    ["", "", "", change.as_str()]
+                }
+            })
+        })
+        .collect();
+    let mut widths = HEADER.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&HEADER).chain(&rows) {
+        let _ = writeln!(
+            table,
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        );
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_store::StoreResult;
+    use flaps_store::repository::{Page, PageRequest};
+    use serde_json::json;
+
+    use super::*;
+
+    /// An audit backend serving fixed records.
+    struct RecordedAudit(Vec<AuditRecord>);
+
+    impl AuditLogRepository for RecordedAudit {
+        async fn list_audit_entries(&self) -> StoreResult<Vec<AuditRecord>> {
+            Ok(self.0.clone())
+        }
+
+        async fn page_audit_entries(
+            &self,
+            _request: &PageRequest,
+        ) -> StoreResult<Page<AuditRecord>> {
+            Ok(Page {
+                items: self.0.clone(),
+                next_cursor: None,
+                total: self.0.len() as u64,
+            })
+        }
+
+        async fn audit_entries_for(
+            &self,
+            entity_type: &str,
+            entity_id: &str,
+        ) -> StoreResult<Vec<AuditRecord>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|r| r.entity_type == entity_type && r.entity_id == entity_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn recent_audit_entries(
+            &self,
+            entity_type: &str,
+            project: &ProjectKey,
+            _window: std::time::Duration,
+        ) -> StoreResult<Vec<AuditRecord>> {
+            let prefix = format!("{}/", project.as_str());
+            Ok(self
+                .0
+                .iter()
+                .filter(|r| r.entity_type == entity_type && r.entity_id.starts_with(&prefix))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn config(enabled: bool) -> Value {
+        json!({
+            "enabled": enabled,
+            "rules": [],
+            "default_rule": {"fixed": "on"},
+        })
+    }
+
+    fn record(
+        at: &str,
+        entity_type: &str,
+        entity_id: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> AuditRecord {
+        AuditRecord {
+            actor: "alice".to_owned(),
+            action: format!("{entity_type}.updated"),
+            entity_type: entity_type.to_owned(),
+            entity_id: entity_id.to_owned(),
+            before,
+            after,
+            occurred_at: at.to_owned(),
+        }
+    }
+
+    fn audit() -> RecordedAudit {
+        RecordedAudit(vec![
+            record(
+                "2026-10-01T09:00:00Z",
+                "flag",
+                "shop/checkout",
+                Some(json!({"name": "Checkout", "variants": {}})),
+                Some(json!({"name": "New checkout", "variants": {}})),
+            ),
+            record(
+                "2026-10-02T09:00:00Z",
+                "flag_env_config",
+                "shop/checkout/prod",
+                Some(config(true)),
+                Some(config(false)),
+            ),
+            record(
+                "2026-10-03T09:00:00Z",
+                "flag_env_config",
+                "shop/checkout-v2/prod",
+                Some(config(true)),
+                Some(config(false)),
+            ),
+            record(
+                "2026-10-04T09:00:00Z",
+                "flag_env_config",
+                "shop/checkout/dev",
+                None,
+                Some(config(true)),
+            ),
+        ])
+    }
+
+    fn args(env: Option<&str>) -> HistoryArgs {
+        HistoryArgs {
+            key: "checkout".to_owned(),
+            project: "shop".to_owned(),
+            env: env.map(str::to_owned),
+            limit: 50,
+            offset: 0,
+            format: HistoryFormat::Table,
+        }
+    }
+
+    #[tokio::test]
+    async fn renders_the_recorded_changes_of_the_flag() {
+        let table = render(&audit(), &args(None)).await.unwrap();
+        assert_eq!(
+            table,
+            "\
+WHEN                  ACTOR  ENV   CHANGE
+2026-10-01T09:00:00Z  alice  -     name: Checkout → New checkout
+2026-10-02T09:00:00Z  alice  prod  enabled: true → false
+2026-10-04T09:00:00Z  alice  dev   created
+"
+        );
+    }
+
+    #[tokio::test]
+    async fn scopes_to_one_environment_and_pages() {
+        let prod = history(&audit(), &args(Some("prod"))).await.unwrap();
+        assert_eq!(prod.len(), 1);
+        assert_eq!(prod[0].changes, ["enabled: true → false"]);
+
+        let mut paged = args(None);
+        paged.limit = 1;
+        paged.offset = 1;
+        let page = history(&audit(), &paged).await.unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].occurred_at, "2026-10-02T09:00:00Z");
+
+        paged.format = HistoryFormat::Json;
+        let json: Value = serde_json::from_str(&render(&audit(), &paged).await.unwrap()).unwrap();
+        assert_eq!(json[0]["environment"], "prod");
+        assert_eq!(json[0]["changes"][0], "enabled: true → false");
+    }
+}
@@ -0,0 +1,468 @@
+//! `flapsd import`: applies an environment patch to the store.
+//!
+//! The patch is a file written by `flapsd diff --output patch` (see
+//! [`crate::diff`]). Each flag of the patch is checked against the
+//! environment first: a flag still configured as the patch expects is
+//! pending, one already configured as the patch installs is applied, and any
+//! other is a conflict, the environment having changed since the patch was
+//! made. A patch with a conflict is refused as a whole; otherwise its pending
+//! flags are written in one [`WriteSession`], so the environment never ends
+//! up half-patched, and importing the same patch again changes nothing.
+//! `--dry-run` prints the same report without writing anything.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use flaps_domain::{
+    DomainError, EnvironmentKey, EnvironmentPatch, FlagKey, PatchStatus, ProjectKey,
+};
+use flaps_store::{
+    StoreError,
+    repository::{
+        EnvironmentRepository, FlagEnvConfigRepository, FlagRepository, ProjectRepository,
+        TransactionalStore, WriteSession,
+    },
+};
+
+use crate::diff::describe;
+
+/// Actor recorded in the audit log for imported changes.
+const ACTOR: &str = "system";
+
+/// Arguments of `flapsd import`.
+#[derive(Debug, clap::Args)]
+pub struct ImportArgs {
+    /// Path of the patch, as written by `flapsd diff --output patch`.
+    pub patch: PathBuf,
+
+    /// Prints what importing the patch would change, without writing.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Errors of a patch import.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    /// The patch file could not be read.
+    #[error("cannot read the patch: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The patch file is not an environment patch.
+    #[error("invalid patch: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The project does not exist.
+    #[error("project {0} not found")]
+    ProjectNotFound(ProjectKey),
+
+    /// The patched environment does not exist in the project.
+    #[error("environment {0} not found")]
+    EnvironmentNotFound(EnvironmentKey),
+
+    /// A flag of the patch does not exist in the project.
+    #[error("flag {0} not found")]
+    FlagNotFound(FlagKey),
+
+    /// A configuration of the patch does not fit its flag.
+    #[error("invalid configuration for flag {flag}: {source}")]
+    InvalidConfig {
+        /// The flag.
+        flag: FlagKey,
+        /// Why the configuration does not fit.
+        source: DomainError,
+    },
+
+    /// Flags changed in the environment since the patch was made; nothing
+    /// was written.
+    #[error("the environment changed since the patch was made, for: {}", keys(.0))]
+    Conflict(Vec<FlagKey>),
+
+    /// The store could not be read or written.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// Joins flag keys with commas.
+fn keys(flags: &[FlagKey]) -> String {
+    let keys: Vec<&str> = flags.iter().map(FlagKey::as_str).collect();
+    keys.join(", ")
+}
+
+/// What an import did, or would do under `--dry-run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportReport {
+    /// The patch imported.
+    pub patch: EnvironmentPatch,
+    /// Status of each flag of the patch before the import, in patch order.
+    pub statuses: Vec<PatchStatus>,
+    /// Whether nothing was written.
+    pub dry_run: bool,
+}
+
+impl ImportReport {
+    /// Number of flags the import changes.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.statuses
+            .iter()
+            .filter(|status| **status == PatchStatus::Pending)
+            .count()
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.dry_run {
+            "would change"
+        } else {
+            "changed"
+        };
+        writeln!(
+            f,
+            "{}/{}: {verb} {} flag(s)",
+            self.patch.project.as_str(),
+            self.patch.environment.as_str(),
+            self.pending()
+        )?;
+        for (flag, status) in self.patch.flags.iter().zip(&self.statuses) {
+            let key = flag.flag.as_str();
+            match status {
+                PatchStatus::Pending => {
+                    for line in describe(flag) {
+                        writeln!(f, "  {key}: {line}")?;
+                    }
+                }
+                PatchStatus::Applied => writeln!(f, "  {key}: already applied")?,
+                PatchStatus::Conflict => {
+                    writeln!(f, "  {key}: conflict, changed since the patch was made")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the patch named by `args` and imports it.
+///
+/// # Errors
+///
+/// Returns [`ImportError::Io`] or [`ImportError::Json`] for an unreadable
+/// patch, and the errors of [`import_patch`].
+pub async fn run<S>(store: &S, args: &ImportArgs) -> Result<ImportReport, ImportError>
+where
+    S: ProjectRepository
+        + EnvironmentRepository
+        + FlagRepository
+        + FlagEnvConfigRepository
+        + TransactionalStore,
+{
+    let patch: EnvironmentPatch = serde_json::from_str(&std::fs::read_to_string(&args.patch)?)?;
+    import_patch(store, patch, args.dry_run).await
+}
+
+/// Applies the pending flags of `patch` in one transaction, or only reports
+/// them when `dry_run` is set.
+///
+/// # Errors
+///
+/// Returns [`ImportError::ProjectNotFound`],
+/// [`ImportError::EnvironmentNotFound`] or [`ImportError::FlagNotFound`] for
+/// a key that names nothing, [`ImportError::InvalidConfig`] for a
+/// configuration its flag rejects, [`ImportError::Conflict`] when flags
+/// changed since the patch was made (not under `dry_run`, which reports
+/// them), and [`ImportError::Store`] when the store fails.
+pub async fn import_patch<S>(
+    store: &S,
+    patch: EnvironmentPatch,
+    dry_run: bool,
+) -> Result<ImportReport, ImportError>
+where
+    S: ProjectRepository
+        + EnvironmentRepository
+        + FlagRepository
+        + FlagEnvConfigRepository
+        + TransactionalStore,
+{
+    let (project, environment) = (&patch.project, &patch.environment);
+    if store.get_project(project).await?.is_none() {
+        return Err(ImportError::ProjectNotFound(project.clone()));
+    }
+    if store.get_environment(project, environment).await?.is_none() {
+        return Err(ImportError::EnvironmentNotFound(environment.clone()));
+    }
+
+    let mut statuses = Vec::with_capacity(patch.flags.len());
+    for flag_patch in &patch.flags {
+        let flag = &flag_patch.flag;
+        let Some(definition) = store.get_flag(project, flag).await? else {
+            return Err(ImportError::FlagNotFound(flag.clone()));
+        };
+        if let Some(config) = &flag_patch.after {
+            config
+                .validate_for(&definition)
+                .map_err(|source| ImportError::InvalidConfig {
+                    flag: flag.clone(),
+                    source,
+                })?;
+        }
+        let current = store
+            .get_flag_env_config(project, flag, environment)
+            .await?;
+        statuses.push(flag_patch.status(current.as_ref()));
+    }
+
+    let report = ImportReport {
+        patch,
+        statuses,
+        dry_run,
+    };
+    if dry_run {
+        return Ok(report);
+    }
+    let conflicts: Vec<FlagKey> = report
+        .patch
+        .flags
+        .iter()
+        .zip(&report.statuses)
+        .filter(|(_, status)| **status == PatchStatus::Conflict)
+        .map(|(flag, _)| flag.flag.clone())
+        .collect();
+    if !conflicts.is_empty() {
+        return Err(ImportError::Conflict(conflicts));
+    }
+
+    let (project, environment) = (&report.patch.project, &report.patch.environment);
+    let mut session = store.begin(ACTOR).await?;
+    for (flag, status) in report.patch.flags.iter().zip(&report.statuses) {
+        if *status != PatchStatus::Pending {
+            continue;
+        }
+        match &flag.after {
+            Some(config) => {
+                session
+                    .upsert_flag_env_config(project, &flag.flag, environment, config)
+                    .await?;
+            }
+            None => {
+                session
+                    .delete_flag_env_config(project, &flag.flag, environment)
+                    .await?;
+            }
+        }
+    }
+    session.commit().await?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_domain::{
+        Flag, FlagEnvConfig, FlagType, Metadata, RolloutScope, ServeTarget, ValueType, VariantKey,
+        VariantValue, Variants,
+    };
+    use flaps_store::{KeyHasher, sqlite::SqliteStore};
+
+    use super::*;
+    use crate::diff::environment_patch;
+    use crate::provision::{CreateProjectArgs, create_project};
+
+    async fn make_store() -> SqliteStore {
+        SqliteStore::in_memory(KeyHasher::new(b"test-pepper-32-bytes-minimum-len!"))
+            .await
+            .expect("in-memory store")
+    }
+
+    fn variant(key: &str) -> VariantKey {
+        VariantKey::new(key).unwrap()
+    }
+
+    fn flag_key(key: &str) -> FlagKey {
+        FlagKey::new(key).unwrap()
+    }
+
+    fn env(key: &str) -> EnvironmentKey {
+        EnvironmentKey::new(key).unwrap()
+    }
+
+    fn boolean_flag(key: &str) -> Flag {
+        Flag {
+            key: flag_key(key),
+            name: key.to_owned(),
+            description: None,
+            flag_type: FlagType::Release,
+            value_type: ValueType::Boolean,
+            variants: Variants::new(
+                ValueType::Boolean,
+                [
+                    (variant("on"), VariantValue::Bool(true)),
+                    (variant("off"), VariantValue::Bool(false)),
+                ],
+            )
+            .unwrap(),
+            metadata: Metadata::new(),
+        }
+    }
+
+    fn config(enabled: bool, serve: &str) -> FlagEnvConfig {
+        FlagEnvConfig {
+            enabled,
+            rules: Vec::new(),
+            default_rule: ServeTarget::Fixed(variant(serve)),
+            inherit_default_rule: false,
+            salt: None,
+            bucket_by: None,
+            rollout_scope: RolloutScope::Shared,
+            kill_reason: None,
+            min_app_version: None,
+        }
+    }
+
+    /// A `shop` project whose `staging` and `prod` differ on three flags:
+    /// `checkout` is configured differently, `search` only in staging and
+    /// `legacy` only in prod. `banner` is the same in both.
+    async fn seeded_store() -> (SqliteStore, ProjectKey) {
+        let store = make_store().await;
+        create_project(
+            &store,
+            &CreateProjectArgs {
+                key: "shop".to_owned(),
+                name: None,
+                description: None,
+            },
+        )
+        .await
+        .unwrap();
+        let project = ProjectKey::new("shop").unwrap();
+        for flag in ["banner", "checkout", "legacy", "search"] {
+            store
+                .upsert_flag(ACTOR, &project, &boolean_flag(flag))
+                .await
+                .unwrap();
+        }
+        let configs = [
+            ("banner", "staging", config(true, "on")),
+            ("banner", "prod", config(true, "on")),
+            ("checkout", "staging", config(true, "on")),
+            ("checkout", "prod", config(false, "off")),
+            ("search", "staging", config(true, "on")),
+            ("legacy", "prod", config(true, "off")),
+        ];
+        for (flag, environment, config) in configs {
+            store
+                .upsert_flag_env_config(
+                    ACTOR,
+                    &project,
+                    &flag_key(flag),
+                    &env(environment),
+                    &config,
+                )
+                .await
+                .unwrap();
+        }
+        (store, project)
+    }
+
+    async fn staging_to_prod(store: &SqliteStore, project: &ProjectKey) -> EnvironmentPatch {
+        environment_patch(store, project, &env("staging"), &env("prod"), &[])
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn an_imported_patch_makes_the_target_match_the_source() {
+        let (store, project) = seeded_store().await;
+        let patch = staging_to_prod(&store, &project).await;
+        let flags: Vec<&str> = patch.flags.iter().map(|f| f.flag.as_str()).collect();
+        assert_eq!(flags, ["checkout", "legacy", "search"]);
+
+        // The patch goes through its file format.
+        let patch = serde_json::from_str(&serde_json::to_string(&patch).unwrap()).unwrap();
+        let report = import_patch(&store, patch, false).await.unwrap();
+        assert_eq!(report.pending(), 3);
+
+        assert!(staging_to_prod(&store, &project).await.is_empty());
+        assert_eq!(
+            store
+                .get_flag_env_config(&project, &flag_key("legacy"), &env("prod"))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn a_dry_run_reports_without_writing() {
+        let (store, project) = seeded_store().await;
+        let patch = staging_to_prod(&store, &project).await;
+
+        let report = import_patch(&store, patch.clone(), true).await.unwrap();
+        assert_eq!(
+            report.to_string(),
+            "shop/prod: would change 3 flag(s)\n\
+             \x20 checkout: enabled: false → true\n\
+             \x20 checkout: default: off → on\n\
+             \x20 legacy: configuration removed\n\
+             \x20 search: configuration added\n"
+        );
+        assert_eq!(staging_to_prod(&store, &project).await, patch);
+    }
+
+    #[tokio::test]
+    async fn importing_a_patch_again_changes_nothing() {
+        let (store, project) = seeded_store().await;
+        let patch = staging_to_prod(&store, &project).await;
+        import_patch(&store, patch.clone(), false).await.unwrap();
+
+        let again = import_patch(&store, patch, false).await.unwrap();
+        assert_eq!(again.pending(), 0);
+        assert!(
+            again
+                .statuses
+                .iter()
+                .all(|status| *status == PatchStatus::Applied)
+        );
+    }
+
+    #[tokio::test]
+    async fn a_patch_whose_target_moved_on_is_refused_whole() {
+        let (store, project) = seeded_store().await;
+        let patch = staging_to_prod(&store, &project).await;
+        store
+            .upsert_flag_env_config(
+                ACTOR,
+                &project,
+                &flag_key("checkout"),
+                &env("prod"),
+                &config(true, "off"),
+            )
+            .await
+            .unwrap();
+
+        let error = import_patch(&store, patch, false).await.unwrap_err();
+        assert!(
+            matches!(&error, ImportError::Conflict(flags) if flags == &[flag_key("checkout")]),
+            "{error}"
+        );
+        assert!(
+            store
+                .get_flag_env_config(&project, &flag_key("search"), &env("prod"))
+                .await
+                .unwrap()
+                .is_none(),
+            "nothing is written"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_config_naming_an_undeclared_variant_is_rejected() {
+        let (store, project) = seeded_store().await;
+        let mut patch = staging_to_prod(&store, &project).await;
+        patch.flags[0].after = Some(config(true, "maybe"));
+
+        let error = import_patch(&store, patch, true).await.unwrap_err();
+        assert!(
+            matches!(&error, ImportError::InvalidConfig { flag, .. } if flag.as_str() == "checkout"),
+            "{error}"
+        );
+    }
+}
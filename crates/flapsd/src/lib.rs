@@ -1,7 +1,22 @@
 //! Internal library for the `flapsd` daemon.
 //!
-//! Exposes the boot primitives (`config`, `bootstrap`) as testable units.
+//! Exposes the boot primitives (`config`, `bootstrap`), the `eval`
+//! subcommand (`local_eval`), the `buckets` subcommand (`buckets`), the
+//! `rollout-impact` subcommand (`rollout_impact`), the
+//! `project`/`env` subcommands (`provision`), the `status` subcommand
+//! (`status`), the `flag history` subcommand (`history`), the `export`
+//! subcommand (`export`), the `diff` subcommand (`diff`) and the `import`
+//! subcommand (`import`) as testable units.
 //! The `main` binary wires them together and delegates all orchestration here.
 
 pub mod bootstrap;
+pub mod buckets;
 pub mod config;
+pub mod diff;
+pub mod export;
+pub mod history;
+pub mod import;
+pub mod local_eval;
+pub mod provision;
+pub mod rollout_impact;
+pub mod status;
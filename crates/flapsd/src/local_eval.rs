@@ -0,0 +1,293 @@
+//! `flapsd eval`: evaluates a flag from a ruleset file, without a server.
+//!
+//! The file is the compiled flagd document an environment serves on
+//! `GET /sync/v1/ruleset`, saved to disk. Evaluation goes through
+//! [`FlagSet::explain`], which resolves like the [`FlagSet::evaluate`] the
+//! server and the in-process client use, so a targeting rule can be debugged
+//! on a laptop or asserted in CI against a checked-in ruleset.
+
+use std::{fmt, path::PathBuf};
+
+use flaps_eval::{
+    ContextPairError, EvaluationContext, EvaluationError, Explanation, FlagSet, ParseError,
+    Resolution,
+};
+
+/// Arguments of the `eval` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct EvalArgs {
+    /// Path to a ruleset saved from `GET /sync/v1/ruleset`.
+    #[arg(long)]
+    pub ruleset: PathBuf,
+
+    /// Key of the flag to evaluate.
+    #[arg(long)]
+    pub flag: String,
+
+    /// Targeting key of the evaluation context (the user id).
+    #[arg(long)]
+    pub targeting_key: Option<String>,
+
+    /// Context attribute as `key=value`, repeatable. The value type is
+    /// inferred (`42`, `true`, `a,b` as a list, `["a","b"]`) unless the key
+    /// forces it (`zip:string=02134`); see [`EvaluationContext::from_pairs`].
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_attribute)]
+    pub attributes: Vec<(String, String)>,
+}
+
+/// Errors of a local evaluation.
+#[derive(Debug, thiserror::Error)]
+pub enum LocalEvalError {
+    /// The ruleset file could not be read.
+    #[error("cannot read ruleset {path:?}: {source}")]
+    Read {
+        /// The ruleset path.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// The ruleset file is not a valid flagd document.
+    #[error("invalid ruleset {path:?}: {source}")]
+    Parse {
+        /// The ruleset path.
+        path: PathBuf,
+        /// The parse error.
+        source: ParseError,
+    },
+
+    /// A `--set` value does not parse as the type its key forces.
+    #[error(transparent)]
+    Context(#[from] ContextPairError),
+
+    /// The flag could not be evaluated.
+    #[error(transparent)]
+    Evaluation(#[from] EvaluationError),
+}
+
+/// Splits a `--set key=value` argument; the value is typed later, by
+/// [`EvaluationContext::from_pairs`].
+///
+/// # Errors
+///
+/// Returns a message when the argument has no `=` or an empty key.
+pub fn parse_attribute(arg: &str) -> Result<(String, String), String> {
+    let Some((key, raw)) = arg.split_once('=') else {
+        return Err(format!("expected KEY=VALUE, got `{arg}`"));
+    };
+    if key.is_empty() {
+        return Err(format!("empty attribute name in `{arg}`"));
+    }
+    Ok((key.to_owned(), raw.to_owned()))
+}
+
+/// Loads the ruleset named by `args` and evaluates the flag against the
+/// context built from the targeting key and attributes, stamped with the
+/// current time.
+///
+/// # Errors
+///
+/// Returns [`LocalEvalError`] when the file cannot be read or parsed, a
+/// `--set` value does not match its forced type, or the flag cannot be
+/// evaluated (unknown key, invalid variant).
+pub fn evaluate(args: &EvalArgs) -> Result<LocalEvaluation, LocalEvalError> {
+    let document =
+        std::fs::read_to_string(&args.ruleset).map_err(|source| LocalEvalError::Read {
+            path: args.ruleset.clone(),
+            source,
+        })?;
+    let flag_set = FlagSet::from_json(&document).map_err(|source| LocalEvalError::Parse {
+        path: args.ruleset.clone(),
+        source,
+    })?;
+    let mut context = EvaluationContext::from_pairs(&args.attributes)?;
+    if args.targeting_key.is_some() {
+        context.targeting_key.clone_from(&args.targeting_key);
+    }
+    context.timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let explanation = flag_set.explain(&args.flag, &context)?;
+    Ok(LocalEvaluation {
+        flag: args.flag.clone(),
+        explanation,
+    })
+}
+
+/// An explained resolution together with the flag it was produced for.
+///
+/// Displays as `key: value` lines (`flag`, `value`, `variant`, `reason`,
+/// `rule`, `bucket`), the report printed by `flapsd eval`. Rules are
+/// numbered from 1 in their configured order; a bucket reads as
+/// `bucket/total weight`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalEvaluation {
+    /// The evaluated flag key.
+    pub flag: String,
+    /// The evaluation outcome, with the matched rule and rollout bucket.
+    pub explanation: Explanation,
+}
+
+impl fmt::Display for LocalEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Explanation {
+            resolution:
+                Resolution {
+                    value,
+                    variant,
+                    reason,
+                    ..
+                },
+            matched_rule,
+            rollout,
+        } = &self.explanation;
+        writeln!(f, "flag:    {}", self.flag)?;
+        match value {
+            Some(value) => writeln!(f, "value:   {value}")?,
+            None => writeln!(f, "value:   (caller default)")?,
+        }
+        writeln!(f, "variant: {}", variant.as_deref().unwrap_or("-"))?;
+        writeln!(f, "reason:  {reason:?}")?;
+        match matched_rule {
+            Some(index) => writeln!(f, "rule:    {}", index + 1)?,
+            None => writeln!(f, "rule:    -")?,
+        }
+        match rollout {
+            Some(position) => write!(f, "bucket:  {}/{}", position.bucket, position.total_weight),
+            None => write!(f, "bucket:  -"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+
+    /// A compiled ruleset whose `checkout` flag serves `on` to the `beta`
+    /// plan and `off` otherwise.
+    const RULESET: &str = r#"{
+        "flags": {
+            "checkout": {
+                "state": "ENABLED",
+                "variants": { "on": true, "off": false },
+                "defaultVariant": "off",
+                "targeting": { "if": [ { "==": [ { "var": "plan" }, "beta" ] }, "on", null ] }
+            }
+        }
+    }"#;
+
+    fn write_ruleset(content: &str) -> tempfile::NamedTempFile {
+        let mut f = tempfile::NamedTempFile::new().expect("tempfile");
+        f.write_all(content.as_bytes()).expect("write");
+        f
+    }
+
+    fn args(ruleset: &tempfile::NamedTempFile, flag: &str, set: &[&str]) -> EvalArgs {
+        EvalArgs {
+            ruleset: ruleset.path().to_owned(),
+            flag: flag.to_owned(),
+            targeting_key: Some("user-1".to_owned()),
+            attributes: set
+                .iter()
+                .map(|arg| parse_attribute(arg).unwrap())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn matching_attributes_report_the_targeting_match() {
+        let ruleset = write_ruleset(RULESET);
+        let report = evaluate(&args(&ruleset, "checkout", &["plan=beta"]))
+            .expect("evaluates")
+            .to_string();
+        assert_eq!(
+            report,
+            "flag:    checkout\nvalue:   true\nvariant: on\nreason:  TargetingMatch\n\
+             rule:    1\nbucket:  -"
+        );
+    }
+
+    #[test]
+    fn non_matching_attributes_report_the_default() {
+        let ruleset = write_ruleset(RULESET);
+        let report = evaluate(&args(&ruleset, "checkout", &["plan=free"]))
+            .expect("evaluates")
+            .to_string();
+        assert_eq!(
+            report,
+            "flag:    checkout\nvalue:   false\nvariant: off\nreason:  Default\n\
+             rule:    -\nbucket:  -"
+        );
+    }
+
+    #[test]
+    fn a_rollout_reports_its_bucket() {
+        let ruleset = write_ruleset(
+            r#"{
+                "flags": {
+                    "checkout": {
+                        "state": "ENABLED",
+                        "variants": { "on": true, "off": false },
+                        "targeting": { "if": [
+                            { "==": [ { "var": "plan" }, "beta" ] }, "on",
+                            { "fractional": [ ["on", 50], ["off", 50] ] }
+                        ] }
+                    }
+                }
+            }"#,
+        );
+        let evaluation = evaluate(&args(&ruleset, "checkout", &["plan=free"])).expect("evaluates");
+        assert_eq!(evaluation.explanation.matched_rule, None);
+        let position = evaluation.explanation.rollout.expect("bucketed");
+        assert_eq!(position.total_weight, 100);
+        assert_eq!(
+            position.bucket,
+            flaps_eval::bucket_for("checkoutuser-1", 100)
+        );
+        assert!(
+            evaluation
+                .to_string()
+                .ends_with(&format!("rule:    -\nbucket:  {}/100", position.bucket)),
+            "{evaluation}"
+        );
+    }
+
+    #[test]
+    fn unknown_flag_is_an_error() {
+        let ruleset = write_ruleset(RULESET);
+        let err = evaluate(&args(&ruleset, "missing", &[])).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                LocalEvalError::Evaluation(EvaluationError::FlagNotFound { .. })
+            ),
+            "{err:?}"
+        );
+    }
+
+    fn attribute(arg: &str) -> serde_json::Value {
+        let pair = parse_attribute(arg).unwrap();
+        let context = EvaluationContext::from_pairs(&[pair]).unwrap();
+        context.attributes.into_values().next().unwrap()
+    }
+
+    #[test]
+    fn attributes_are_typed_from_their_text() {
+        assert_eq!(attribute("seats=12"), serde_json::json!(12));
+        assert_eq!(attribute("tags=[\"a\"]"), serde_json::json!(["a"]));
+        assert_eq!(attribute("tags=a,b"), serde_json::json!(["a", "b"]));
+        assert_eq!(attribute("zip:string=02134"), serde_json::json!("02134"));
+        assert_eq!(attribute("plan=beta"), serde_json::json!("beta"));
+        assert_eq!(attribute("plan=null"), serde_json::Value::Null);
+        assert!(parse_attribute("plan").is_err());
+        assert!(parse_attribute("=beta").is_err());
+    }
+
+    #[test]
+    fn a_value_that_misses_its_forced_type_is_an_error() {
+        let ruleset = write_ruleset(RULESET);
+        let err = evaluate(&args(&ruleset, "checkout", &["seats:number=many"])).unwrap_err();
+        assert!(matches!(err, LocalEvalError::Context(_)), "{err:?}");
+    }
+}
@@ -3,6 +3,22 @@
 //! Parses `--config <path>`, initialises structured logging, connects to the
 //! store with retry, warms up the compiled ruleset cache, bootstraps the admin
 //! account on first boot, then starts the HTTP server with graceful shutdown.
+//! `flapsd eval` instead evaluates a flag from a ruleset file and exits (see
+//! [`flapsd_lib::local_eval`]); `flapsd buckets` exports the rollout bucket
+//! of a list of users and exits (see [`flapsd_lib::buckets`]); `flapsd
+//! rollout-impact` previews how many users a bucketing change would move in
+//! or out of a rollout and exits (see [`flapsd_lib::rollout_impact`]);
+//! `flapsd project create` and `flapsd env create` provision projects and
+//! environments in the configured store and exit (see
+//! [`flapsd_lib::provision`]); `flapsd status` checks the
+//! configured backends, prints their health and exits non-zero when one is
+//! unhealthy (see [`flapsd_lib::status`]); `flapsd flag history` lists the
+//! recorded changes of a flag from the audit log (see
+//! [`flapsd_lib::history`]); `flapsd export` prints the flags of a project,
+//! as JSON or as a flat rules file for review (see [`flapsd_lib::export`]);
+//! `flapsd diff` compares the flag configurations of two environments, as
+//! text or as an importable patch (see [`flapsd_lib::diff`]); `flapsd
+//! import` applies such a patch (see [`flapsd_lib::import`]).
 //!
 //! All heavy logic lives in [`flapsd_lib::bootstrap`] and [`flapsd_lib::config`]
 //! so it can be unit-tested without spawning a real process.
@@ -10,9 +26,10 @@
 use std::sync::Arc;
 
 use anyhow::{Context as _, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use flaps_server::{
     build_router,
+    health::{DatabaseCheck, HealthReport, SystemHealth},
     rate_limit::{RateLimitConfig, RateLimiter},
     sse_quota::{SseQuota, SseQuotaConfig},
     state::{AppState, Store},
@@ -22,7 +39,16 @@ use tokio::net::TcpListener;
 
 use flapsd_lib::{
     bootstrap::{bootstrap_admin_once, connect_store_with_retry, warm_up_cache},
+    buckets::{self, BucketsArgs},
     config::{Config, read_pepper},
+    diff::{self, DiffArgs},
+    export::{self, ExportArgs},
+    history::{self, FlagCommand, HistoryArgs},
+    import::{self, ImportArgs},
+    local_eval::{self, EvalArgs},
+    provision::{self, ProvisionCommand},
+    rollout_impact::{self, RolloutImpactArgs},
+    status::StatusReport,
 };
 
 /// Command-line arguments for `flapsd`.
@@ -32,12 +58,101 @@ struct Args {
     /// Path to the TOML configuration file.
     #[arg(long, default_value = "flapsd.toml")]
     config: String,
+
+    /// Runs a one-off command instead of the server.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// One-off `flapsd` commands.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Evaluates a flag locally from a saved ruleset, without a server.
+    Eval(EvalArgs),
+
+    /// Exports the rollout bucket of a list of users, and whether each is
+    /// included at a range of rollout percentages.
+    Buckets(BucketsArgs),
+
+    /// Previews how many users a change of a rollout's bucketing (salt,
+    /// namespace or algorithm) would move in or out of it.
+    RolloutImpact(RolloutImpactArgs),
+
+    /// Creates projects and environments in the configured store.
+    #[command(flatten)]
+    Provision(ProvisionCommand),
+
+    /// Checks the configured backends and prints their health.
+    Status,
+
+    /// Inspects flags in the configured store.
+    Flag {
+        /// The flag action.
+        #[command(subcommand)]
+        command: FlagCommand,
+    },
+
+    /// Prints the flags of a project, as JSON or as a flat rules file for
+    /// review.
+    Export(ExportArgs),
+
+    /// Compares the flag configurations of two environments, as text or as
+    /// a patch making the target match the source.
+    Diff(DiffArgs),
+
+    /// Applies a patch written by `flapsd diff --output patch`, in one
+    /// transaction.
+    Import(ImportArgs),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    match args.command {
+        Some(Command::Eval(eval)) => {
+            println!("{}", local_eval::evaluate(&eval)?);
+            return Ok(());
+        }
+        Some(Command::Buckets(buckets)) => {
+            print!("{}", buckets::export(&buckets)?);
+            return Ok(());
+        }
+        Some(Command::RolloutImpact(impact)) => {
+            print!("{}", rollout_impact::report(&impact)?);
+            return Ok(());
+        }
+        Some(Command::Provision(command)) => {
+            print!("{}", provision_store(&args.config, &command).await?);
+            return Ok(());
+        }
+        Some(Command::Status) => {
+            let report = check_status(&args.config).await?;
+            print!("{}", StatusReport(report.clone()));
+            anyhow::ensure!(report.is_healthy(), "one or more backends are unhealthy");
+            return Ok(());
+        }
+        Some(Command::Flag {
+            command: FlagCommand::History(history),
+        }) => {
+            print!("{}", flag_history(&args.config, &history).await?);
+            return Ok(());
+        }
+        Some(Command::Export(export)) => {
+            print!("{}", project_export(&args.config, &export).await?);
+            return Ok(());
+        }
+        Some(Command::Diff(diff)) => {
+            print!("{}", environment_diff(&args.config, &diff).await?);
+            return Ok(());
+        }
+        Some(Command::Import(import)) => {
+            print!("{}", patch_import(&args.config, &import).await?);
+            return Ok(());
+        }
+        None => {}
+    }
+
     // Initialise structured logging from the RUST_LOG environment variable.
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
@@ -108,14 +223,154 @@ pub async fn run(config_path: String) -> Result<()> {
     }
 }
 
+/// Runs a provisioning command against the store named in the config file.
+///
+/// Connects once, without the boot retry: a one-off command should fail fast
+/// when the database is unreachable.
+async fn provision_store(
+    config_path: &str,
+    command: &ProvisionCommand,
+) -> Result<provision::Provisioned> {
+    let config = Config::load(config_path)
+        .with_context(|| format!("failed to load config from {config_path:?}"))?;
+    let hasher = KeyHasher::new(read_pepper().context("pepper configuration")?);
+    let url = &config.database_url;
+    let provisioned = if url.starts_with("sqlite:") {
+        let store = SqliteStore::connect(url, hasher)
+            .await
+            .context("connecting to SQLite store")?;
+        provision::execute(&store, command).await?
+    } else {
+        let store = flaps_store::postgres::PostgresStore::connect(url, hasher)
+            .await
+            .context("connecting to PostgreSQL store")?;
+        provision::execute(&store, command).await?
+    };
+    Ok(provisioned)
+}
+
+/// Renders the history of a flag from the audit log of the configured store.
+///
+/// Connects once, without the boot retry, like [`provision_store`].
+async fn flag_history(config_path: &str, args: &HistoryArgs) -> Result<String> {
+    let config = Config::load(config_path)
+        .with_context(|| format!("failed to load config from {config_path:?}"))?;
+    let hasher = KeyHasher::new(read_pepper().context("pepper configuration")?);
+    let url = &config.database_url;
+    let rendered = if url.starts_with("sqlite:") {
+        let store = SqliteStore::connect(url, hasher)
+            .await
+            .context("connecting to SQLite store")?;
+        history::render(&store, args).await?
+    } else {
+        let store = flaps_store::postgres::PostgresStore::connect(url, hasher)
+            .await
+            .context("connecting to PostgreSQL store")?;
+        history::render(&store, args).await?
+    };
+    Ok(rendered)
+}
+
+/// Renders the export of a project from the configured store.
+///
+/// Connects once, without the boot retry, like [`provision_store`].
+async fn project_export(config_path: &str, args: &ExportArgs) -> Result<String> {
+    let config = Config::load(config_path)
+        .with_context(|| format!("failed to load config from {config_path:?}"))?;
+    let hasher = KeyHasher::new(read_pepper().context("pepper configuration")?);
+    let url = &config.database_url;
+    let rendered = if url.starts_with("sqlite:") {
+        let store = SqliteStore::connect(url, hasher)
+            .await
+            .context("connecting to SQLite store")?;
+        export::render(&store, args).await?
+    } else {
+        let store = flaps_store::postgres::PostgresStore::connect(url, hasher)
+            .await
+            .context("connecting to PostgreSQL store")?;
+        export::render(&store, args).await?
+    };
+    Ok(rendered)
+}
+
+/// Renders the diff of two environments from the configured store.
+///
+/// Connects once, without the boot retry, like [`provision_store`].
+async fn environment_diff(config_path: &str, args: &DiffArgs) -> Result<String> {
+    let config = Config::load(config_path)
+        .with_context(|| format!("failed to load config from {config_path:?}"))?;
+    let hasher = KeyHasher::new(read_pepper().context("pepper configuration")?);
+    let url = &config.database_url;
+    let rendered = if url.starts_with("sqlite:") {
+        let store = SqliteStore::connect(url, hasher)
+            .await
+            .context("connecting to SQLite store")?;
+        diff::render(&store, args).await?
+    } else {
+        let store = flaps_store::postgres::PostgresStore::connect(url, hasher)
+            .await
+            .context("connecting to PostgreSQL store")?;
+        diff::render(&store, args).await?
+    };
+    Ok(rendered)
+}
+
+/// Imports a patch into the configured store.
+///
+/// Connects once, without the boot retry, like [`provision_store`].
+async fn patch_import(config_path: &str, args: &ImportArgs) -> Result<import::ImportReport> {
+    let config = Config::load(config_path)
+        .with_context(|| format!("failed to load config from {config_path:?}"))?;
+    let hasher = KeyHasher::new(read_pepper().context("pepper configuration")?);
+    let url = &config.database_url;
+    let report = if url.starts_with("sqlite:") {
+        let store = SqliteStore::connect(url, hasher)
+            .await
+            .context("connecting to SQLite store")?;
+        import::run(&store, args).await?
+    } else {
+        let store = flaps_store::postgres::PostgresStore::connect(url, hasher)
+            .await
+            .context("connecting to PostgreSQL store")?;
+        import::run(&store, args).await?
+    };
+    Ok(report)
+}
+
+/// Connects to the configured store and runs the checks behind `GET /readyz`.
+async fn check_status(config_path: &str) -> Result<HealthReport> {
+    let config = Config::load(config_path)
+        .with_context(|| format!("failed to load config from {config_path:?}"))?;
+    let hasher = KeyHasher::new(read_pepper().context("pepper configuration")?);
+    let url = &config.database_url;
+    let health = if url.starts_with("sqlite:") {
+        let store = SqliteStore::connect(url, hasher)
+            .await
+            .context("connecting to SQLite store")?;
+        SystemHealth::new().with_check(DatabaseCheck(store))
+    } else {
+        let store = flaps_store::postgres::PostgresStore::connect(url, hasher)
+            .await
+            .context("connecting to PostgreSQL store")?;
+        SystemHealth::new().with_check(DatabaseCheck(store))
+    };
+    Ok(health.report().await)
+}
+
 /// Builds application state from the daemon configuration.
 ///
 /// Applies [`Config::effective_rate_limit_per_minute`] to the SDK rate
 /// limiter, [`Config::effective_session_ttl`] to the admin session TTL, and
 /// [`Config::effective_max_sse_subscriptions_per_key`] /
 /// [`Config::effective_max_sse_subscriptions_global`] to the `GET
-/// /sync/v1/events` concurrency quota, for both the SQLite and PostgreSQL
-/// storage backends. The login rate limiter is not operator-configurable: it
+/// /sync/v1/events` concurrency quota, and [`Config::effective_limits`] to
+/// the compile-time rule and segment limits, and
+/// [`Config::effective_timestamp_quantum`] and
+/// [`Config::effective_context_limits`] and
+/// [`Config::effective_exposure_sampler`],
+/// [`Config::effective_evaluation_cache`] and
+/// [`Config::effective_context_sample`] to OFREP evaluations, for both the
+/// SQLite and PostgreSQL storage backends. The login rate limiter is not operator-configurable: it
 /// keeps the documented default (see
 /// [`flaps_server::state::DEFAULT_LOGIN_RATE_LIMIT_CAPACITY`]).
 fn build_app_state<S: Store>(store: S, config: &Config) -> AppState<S> {
@@ -135,13 +390,29 @@ fn build_app_state<S: Store>(store: S, config: &Config) -> AppState<S> {
         max_per_key: config.effective_max_sse_subscriptions_per_key(),
     }));
 
-    AppState::with_config(
+    let state = AppState::with_config(
         store,
         rate_limiter,
         login_rate_limiter,
         config.effective_session_ttl(),
     )
     .with_sse_quota(sse_quota)
+    .with_limits(config.effective_limits())
+    .with_timestamp_quantum(config.effective_timestamp_quantum())
+    .with_context_limits(config.effective_context_limits())
+    .with_flapping(config.effective_flapping());
+    let state = match config.effective_exposure_sampler() {
+        Some(sampler) => state.with_exposure_sampler(sampler),
+        None => state,
+    };
+    let state = match config.effective_evaluation_cache() {
+        Some(cache) => state.with_evaluation_cache(cache),
+        None => state,
+    };
+    match config.effective_context_sample() {
+        Some(sample) => state.with_context_sample(sample),
+        None => state,
+    }
 }
 
 /// Logs the effective, non-secret configuration values at startup.
@@ -156,15 +427,64 @@ fn log_effective_config(config: &Config) {
         session_ttl_secs = config.effective_session_ttl().as_secs(),
         max_sse_subscriptions_per_key = config.effective_max_sse_subscriptions_per_key(),
         max_sse_subscriptions_global = config.effective_max_sse_subscriptions_global(),
+        max_rules_per_config = config.effective_limits().max_rules_per_config,
+        max_conditions_per_rule = config.effective_limits().max_conditions_per_rule,
+        max_segment_depth = config.effective_limits().max_segment_depth,
+        max_pattern_length = config.effective_limits().max_pattern_length,
+        evaluation_timestamp_quantum_secs = config.effective_timestamp_quantum().as_secs(),
+        context_max_attributes = config.effective_context_limits().max_attributes,
+        context_max_key_length = config.effective_context_limits().max_key_length,
+        context_max_value_length = config.effective_context_limits().max_value_length,
+        context_max_list_length = config.effective_context_limits().max_list_length,
+        exposure_sample_rate = config.effective_exposure_sampler().map(|s| s.rate()),
+        geoip_database = config.geoip_database.as_deref().map(|p| p.display().to_string()),
+        evaluation_cache_environments = ?config.evaluation_cache_environments,
+        evaluation_cache_ttl_secs = config
+            .effective_evaluation_cache()
+            .map(|cache| cache.ttl.as_secs()),
+        evaluation_cache_capacity = config
+            .effective_evaluation_cache()
+            .map(|cache| cache.capacity),
+        context_sample_size = config.context_sample_size,
+        private_attributes = ?config.private_attributes,
+        flapping_max_changes = config.effective_flapping().max_changes,
+        flapping_window_secs = config.effective_flapping().window.as_secs(),
         "effective flapsd configuration"
     );
 }
 
+/// Enables OFREP caller geolocation from the `geoip_database` named in the
+/// config, when set.
+///
+/// # Errors
+/// Returns an error when the database cannot be opened: a configured but
+/// unreadable database fails the boot rather than silently disabling
+/// geolocation.
+#[cfg(feature = "maxmind")]
+fn attach_geo_resolver<S: Store>(state: AppState<S>, config: &Config) -> Result<AppState<S>> {
+    let Some(path) = &config.geoip_database else {
+        return Ok(state);
+    };
+    let resolver = flaps_server::geo::MaxMindResolver::open(path)
+        .with_context(|| format!("opening GeoIP database {}", path.display()))?;
+    tracing::info!(path = %path.display(), "OFREP caller geolocation enabled");
+    Ok(state.with_geo_resolver(resolver))
+}
+
+/// Without the `maxmind` feature, [`Config::load`] refuses `geoip_database`,
+/// so there is nothing to attach.
+#[cfg(not(feature = "maxmind"))]
+#[allow(clippy::unnecessary_wraps)]
+fn attach_geo_resolver<S: Store>(state: AppState<S>, _config: &Config) -> Result<AppState<S>> {
+    Ok(state)
+}
+
 /// Completes the boot sequence once a store is connected.
 async fn boot<S: flaps_server::state::Store>(
     state: flaps_server::state::AppState<S>,
     config: Config,
 ) -> Result<()> {
+    let state = attach_geo_resolver(state, &config)?;
     warm_up_cache(&state).await;
 
     bootstrap_admin_once(&state.store, &config.admin_username)
@@ -224,6 +544,24 @@ mod tests {
             session_ttl_secs,
             max_sse_subscriptions_per_key: None,
             max_sse_subscriptions_global: None,
+            max_rules_per_config: None,
+            max_conditions_per_rule: None,
+            max_segment_depth: None,
+            max_pattern_length: None,
+            evaluation_timestamp_quantum_secs: None,
+            context_max_attributes: None,
+            context_max_key_length: None,
+            context_max_value_length: None,
+            context_max_list_length: None,
+            exposure_sample_rate: None,
+            geoip_database: None,
+            evaluation_cache_environments: None,
+            evaluation_cache_ttl_secs: None,
+            evaluation_cache_capacity: None,
+            context_sample_size: None,
+            private_attributes: None,
+            flapping_max_changes: None,
+            flapping_window_secs: None,
         }
     }
 
@@ -261,6 +599,7 @@ mod tests {
                     external_ref: None,
                     managed_by: ManagedBy::Local,
                     metadata: flaps_domain::Metadata::new(),
+                    inherits_from: None,
                 },
             )
             .await
@@ -561,6 +900,24 @@ mod tests {
             session_ttl_secs: Some(120),
             max_sse_subscriptions_per_key: Some(3),
             max_sse_subscriptions_global: Some(50),
+            max_rules_per_config: None,
+            max_conditions_per_rule: None,
+            max_segment_depth: None,
+            max_pattern_length: None,
+            evaluation_timestamp_quantum_secs: None,
+            context_max_attributes: None,
+            context_max_key_length: None,
+            context_max_value_length: None,
+            context_max_list_length: None,
+            exposure_sample_rate: None,
+            geoip_database: None,
+            evaluation_cache_environments: None,
+            evaluation_cache_ttl_secs: None,
+            evaluation_cache_capacity: None,
+            context_sample_size: None,
+            private_attributes: None,
+            flapping_max_changes: None,
+            flapping_window_secs: None,
         };
 
         tracing::subscriber::with_default(subscriber, || {
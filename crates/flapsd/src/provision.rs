@@ -0,0 +1,376 @@
+//! `flapsd project create` and `flapsd env create`: provisions projects and
+//! environments directly in the store.
+//!
+//! A new project comes with the standard `dev`, `staging` and `prod`
+//! environments ([`Environment::defaults`]). The project and its environments
+//! are written in one [`WriteSession`], so a failure part-way leaves no
+//! project behind rather than one missing some of its environments.
+
+use std::fmt;
+
+use flaps_domain::{DomainError, Environment, EnvironmentKey, ManagedBy, Project, ProjectKey};
+use flaps_store::{
+    StoreError,
+    repository::{EnvironmentRepository, ProjectRepository, TransactionalStore, WriteSession},
+};
+
+/// Actor recorded in the audit log for provisioning commands.
+const ACTOR: &str = "system";
+
+/// Provisioning subcommands.
+#[derive(Debug, clap::Subcommand)]
+pub enum ProvisionCommand {
+    /// Manages projects.
+    Project {
+        /// The project action.
+        #[command(subcommand)]
+        command: ProjectCommand,
+    },
+    /// Manages environments.
+    Env {
+        /// The environment action.
+        #[command(subcommand)]
+        command: EnvCommand,
+    },
+}
+
+/// `flapsd project` actions.
+#[derive(Debug, clap::Subcommand)]
+pub enum ProjectCommand {
+    /// Creates a project with the `dev`, `staging` and `prod` environments.
+    Create(CreateProjectArgs),
+}
+
+/// `flapsd env` actions.
+#[derive(Debug, clap::Subcommand)]
+pub enum EnvCommand {
+    /// Adds an environment to an existing project.
+    Create(CreateEnvironmentArgs),
+}
+
+/// Arguments of `project create`.
+#[derive(Debug, clap::Args)]
+pub struct CreateProjectArgs {
+    /// Key of the new project (kebab-case).
+    pub key: String,
+
+    /// Display name; defaults to the key.
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Optional description.
+    #[arg(long)]
+    pub description: Option<String>,
+}
+
+/// Arguments of `env create`.
+#[derive(Debug, clap::Args)]
+pub struct CreateEnvironmentArgs {
+    /// Key of the project the environment belongs to.
+    #[arg(long)]
+    pub project: String,
+
+    /// Key of the new environment (kebab-case).
+    pub key: String,
+
+    /// Display name; defaults to the key.
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+/// Errors of a provisioning command.
+#[derive(Debug, thiserror::Error)]
+pub enum ProvisionError {
+    /// A project or environment key is not kebab-case.
+    #[error(transparent)]
+    InvalidKey(#[from] DomainError),
+
+    /// `project create` targets a project that already exists.
+    #[error("project `{0}` already exists")]
+    ProjectExists(ProjectKey),
+
+    /// `env create` targets a project that does not exist.
+    #[error("project `{0}` not found")]
+    ProjectNotFound(ProjectKey),
+
+    /// `env create` targets an environment that already exists.
+    #[error("environment `{environment}` already exists in project `{project}`")]
+    EnvironmentExists {
+        /// The project key.
+        project: ProjectKey,
+        /// The environment key.
+        environment: EnvironmentKey,
+    },
+
+    /// The store rejected the write.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// What a provisioning command created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provisioned {
+    /// The project created, or the one the environment was added to.
+    pub project: ProjectKey,
+    /// The environments created.
+    pub environments: Vec<EnvironmentKey>,
+    /// Whether the project itself was created.
+    pub project_created: bool,
+}
+
+/// One created id per line: `project <key>` when the project was created,
+/// then `environment <project>/<key>` per environment.
+impl fmt::Display for Provisioned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.project_created {
+            writeln!(f, "project {}", self.project.as_str())?;
+        }
+        for environment in &self.environments {
+            writeln!(
+                f,
+                "environment {}/{}",
+                self.project.as_str(),
+                environment.as_str()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a provisioning command against `store`.
+///
+/// # Errors
+///
+/// See [`create_project`] and [`create_environment`].
+pub async fn execute<S>(
+    store: &S,
+    command: &ProvisionCommand,
+) -> Result<Provisioned, ProvisionError>
+where
+    S: ProjectRepository + EnvironmentRepository + TransactionalStore,
+{
+    match command {
+        ProvisionCommand::Project {
+            command: ProjectCommand::Create(args),
+        } => create_project(store, args).await,
+        ProvisionCommand::Env {
+            command: EnvCommand::Create(args),
+        } => create_environment(store, args).await,
+    }
+}
+
+/// Creates a project and its default environments in one transaction.
+///
+/// # Errors
+///
+/// Returns [`ProvisionError::InvalidKey`] for a key that is not kebab-case,
+/// [`ProvisionError::ProjectExists`] when the project already exists, and
+/// [`ProvisionError::Store`] when the write fails, in which case nothing is
+/// created.
+pub async fn create_project<S>(
+    store: &S,
+    args: &CreateProjectArgs,
+) -> Result<Provisioned, ProvisionError>
+where
+    S: ProjectRepository + TransactionalStore,
+{
+    let key = ProjectKey::new(&args.key)?;
+    if store.get_project(&key).await?.is_some() {
+        return Err(ProvisionError::ProjectExists(key));
+    }
+    let project = Project {
+        name: args.name.clone().unwrap_or_else(|| args.key.clone()),
+        description: args.description.clone(),
+        external_ref: None,
+        managed_by: ManagedBy::Local,
+        key,
+    };
+    let environments = Environment::defaults();
+
+    let mut session = store.begin(ACTOR).await?;
+    session.upsert_project(&project).await?;
+    for environment in &environments {
+        session
+            .upsert_environment(&project.key, environment)
+            .await?;
+    }
+    session.commit().await?;
+
+    Ok(Provisioned {
+        project: project.key,
+        environments: environments.into_iter().map(|env| env.key).collect(),
+        project_created: true,
+    })
+}
+
+/// Adds a locally managed environment to an existing project.
+///
+/// # Errors
+///
+/// Returns [`ProvisionError::InvalidKey`] for a key that is not kebab-case,
+/// [`ProvisionError::ProjectNotFound`] when the project does not exist,
+/// [`ProvisionError::EnvironmentExists`] when the environment does, and
+/// [`ProvisionError::Store`] when the write fails.
+pub async fn create_environment<S>(
+    store: &S,
+    args: &CreateEnvironmentArgs,
+) -> Result<Provisioned, ProvisionError>
+where
+    S: ProjectRepository + EnvironmentRepository,
+{
+    let project = ProjectKey::new(&args.project)?;
+    let key = EnvironmentKey::new(&args.key)?;
+    if store.get_project(&project).await?.is_none() {
+        return Err(ProvisionError::ProjectNotFound(project));
+    }
+    if store.get_environment(&project, &key).await?.is_some() {
+        return Err(ProvisionError::EnvironmentExists {
+            project,
+            environment: key,
+        });
+    }
+    let name = args.name.clone().unwrap_or_else(|| args.key.clone());
+    store
+        .upsert_environment(ACTOR, &project, &Environment::local(key.clone(), name))
+        .await?;
+    Ok(Provisioned {
+        project,
+        environments: vec![key],
+        project_created: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_store::{KeyHasher, sqlite::SqliteStore};
+
+    use super::*;
+
+    async fn make_store() -> SqliteStore {
+        SqliteStore::in_memory(KeyHasher::new(b"test-pepper-32-bytes-minimum-len!"))
+            .await
+            .expect("in-memory store")
+    }
+
+    fn project_args(key: &str) -> CreateProjectArgs {
+        CreateProjectArgs {
+            key: key.to_owned(),
+            name: None,
+            description: None,
+        }
+    }
+
+    fn env_args(project: &str, key: &str) -> CreateEnvironmentArgs {
+        CreateEnvironmentArgs {
+            project: project.to_owned(),
+            key: key.to_owned(),
+            name: Some("Preview".to_owned()),
+        }
+    }
+
+    fn keys(environments: &[Environment]) -> Vec<&str> {
+        environments.iter().map(|env| env.key.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn new_project_comes_with_the_default_environments() {
+        let store = make_store().await;
+        let provisioned = create_project(&store, &project_args("my-app"))
+            .await
+            .unwrap();
+
+        let project = ProjectKey::new("my-app").unwrap();
+        let stored = store.get_project(&project).await.unwrap().unwrap();
+        assert_eq!(stored.name, "my-app");
+        let environments = store.list_environments(&project).await.unwrap();
+        assert_eq!(keys(&environments), ["dev", "prod", "staging"]);
+        assert!(
+            Environment::defaults()
+                .iter()
+                .all(|env| environments.contains(env))
+        );
+        assert_eq!(
+            provisioned.to_string(),
+            "project my-app\nenvironment my-app/dev\n\
+             environment my-app/staging\nenvironment my-app/prod\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn existing_project_is_not_reprovisioned() {
+        let store = make_store().await;
+        create_project(&store, &project_args("my-app"))
+            .await
+            .unwrap();
+        store
+            .delete_environment(
+                ACTOR,
+                &ProjectKey::new("my-app").unwrap(),
+                &EnvironmentKey::new("dev").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let err = create_project(&store, &project_args("my-app"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProvisionError::ProjectExists(_)), "{err:?}");
+        let environments = store
+            .list_environments(&ProjectKey::new("my-app").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(keys(&environments), ["prod", "staging"]);
+    }
+
+    #[tokio::test]
+    async fn invalid_keys_are_refused() {
+        let store = make_store().await;
+        let err = create_project(&store, &project_args("My App"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProvisionError::InvalidKey(_)), "{err:?}");
+        assert!(store.list_projects().await.unwrap().is_empty());
+
+        create_project(&store, &project_args("my-app"))
+            .await
+            .unwrap();
+        let err = create_environment(&store, &env_args("my-app", "Preview_1"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProvisionError::InvalidKey(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn env_create_adds_a_custom_environment() {
+        let store = make_store().await;
+        create_project(&store, &project_args("my-app"))
+            .await
+            .unwrap();
+        let provisioned = create_environment(&store, &env_args("my-app", "preview"))
+            .await
+            .unwrap();
+        assert_eq!(provisioned.to_string(), "environment my-app/preview\n");
+
+        let project = ProjectKey::new("my-app").unwrap();
+        let environments = store.list_environments(&project).await.unwrap();
+        assert_eq!(keys(&environments), ["dev", "preview", "prod", "staging"]);
+        assert_eq!(environments[1].name, "Preview");
+
+        let err = create_environment(&store, &env_args("my-app", "preview"))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ProvisionError::EnvironmentExists { .. }),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn env_create_requires_the_project() {
+        let store = make_store().await;
+        let err = create_environment(&store, &env_args("ghost", "preview"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProvisionError::ProjectNotFound(_)), "{err:?}");
+    }
+}
@@ -0,0 +1,437 @@
+//! `flapsd rollout-impact`: previews the churn of a change to how a rollout
+//! buckets users.
+//!
+//! Changing the bucketing of a percentage rollout (its salt, its namespace,
+//! or the algorithm itself) reshuffles users even when the percentage stays
+//! put: some users leave the rollout and others take their place. Given a
+//! list of users and the current rollout percentage, the report tells, for
+//! each user, whether the current and the proposed bucketing include them,
+//! and sums up how many would move in, move out, and what share of the list
+//! would flip.
+//!
+//! Both bucketings use the public functions evaluation itself uses:
+//! [`bucket_for`] for a `fractional` rollout, where a user is included at
+//! `p`% when their bucket is below `p`, and [`HashRing`] for a
+//! `consistent_hash` rollout, where a user is included when the ring places
+//! them on the served variant. The bucketing value is built as the compiler
+//! builds it (see [`crate::buckets`]).
+
+use std::{fmt, path::PathBuf};
+
+use flaps_eval::{Bucket, HashRing, bucket_for};
+use serde::Serialize;
+
+/// Total weight the buckets are drawn from: one bucket per percentage point.
+const TOTAL_WEIGHT: u64 = 100;
+
+/// A rollout bucketing algorithm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    /// The flagd `fractional` operator: cumulative weight ranges.
+    #[default]
+    Fractional,
+    /// The `consistent_hash` operator: variants placed on a hash ring.
+    ConsistentHash,
+}
+
+/// Output format of `flapsd rollout-impact`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImpactFormat {
+    /// A human-readable summary.
+    #[default]
+    Text,
+    /// The summary and every user's inclusion, as JSON.
+    Json,
+}
+
+/// Arguments of the `rollout-impact` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct RolloutImpactArgs {
+    /// Key of the flag whose rollout is previewed.
+    #[arg(long)]
+    pub flag: String,
+
+    /// File listing the user ids (targeting keys), one per line.
+    #[arg(long)]
+    pub users: PathBuf,
+
+    /// Current rollout percentage.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub percentage: u8,
+
+    /// Current bucketing algorithm.
+    #[arg(long, value_enum, default_value_t)]
+    pub algorithm: Algorithm,
+
+    /// Current salt of the flag's environment configuration, when it sets
+    /// one.
+    #[arg(long)]
+    pub salt: Option<String>,
+
+    /// Current bucketing namespace of the rule, e.g. `/rule:beta/`.
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// Proposed bucketing algorithm (default: the current one).
+    #[arg(long, value_enum)]
+    pub proposed_algorithm: Option<Algorithm>,
+
+    /// Proposed salt (default: the current one).
+    #[arg(long)]
+    pub proposed_salt: Option<String>,
+
+    /// Proposed bucketing namespace (default: the current one).
+    #[arg(long)]
+    pub proposed_namespace: Option<String>,
+
+    /// Variant served to included users; a consistent-hash ring places its
+    /// nodes by variant name.
+    #[arg(long, default_value = "on")]
+    pub on_variant: String,
+
+    /// Variant served to the other users.
+    #[arg(long, default_value = "off")]
+    pub off_variant: String,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ImpactFormat,
+}
+
+/// Errors of a rollout impact preview.
+#[derive(Debug, thiserror::Error)]
+pub enum RolloutImpactError {
+    /// The user list could not be read.
+    #[error("cannot read users {path:?}: {source}")]
+    Read {
+        /// The user list path.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// The report could not be serialized as JSON.
+    #[error("cannot serialize the report: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// How a rollout buckets users.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Bucketing {
+    /// The algorithm.
+    pub algorithm: Algorithm,
+    /// The salt replacing the flag key, if any.
+    pub salt: Option<String>,
+    /// The namespace following the salt or flag key, if any.
+    pub namespace: Option<String>,
+}
+
+/// A percentage rollout serving `on` to `percentage`% of users and `off` to
+/// the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rollout<'a> {
+    /// The flag key.
+    pub flag: &'a str,
+    /// The rollout percentage.
+    pub percentage: u8,
+    /// The variant served to included users.
+    pub on: &'a str,
+    /// The variant served to the other users.
+    pub off: &'a str,
+}
+
+/// Whether the current and proposed bucketings include one user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserImpact {
+    /// The user id.
+    pub user: String,
+    /// Included under the current bucketing.
+    pub included_now: bool,
+    /// Included under the proposed bucketing.
+    pub included_after: bool,
+}
+
+/// The churn of a bucketing change over a user list.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RolloutImpact {
+    /// The rollout percentage.
+    pub percentage: u8,
+    /// The current bucketing.
+    pub current: Bucketing,
+    /// The proposed bucketing.
+    pub proposed: Bucketing,
+    /// Number of users in the list.
+    pub users: usize,
+    /// Users included under the current bucketing.
+    pub included_now: usize,
+    /// Users included under the proposed bucketing.
+    pub included_after: usize,
+    /// Users the change would add to the rollout.
+    pub moved_in: usize,
+    /// Users the change would remove from the rollout.
+    pub moved_out: usize,
+    /// Share of the list, in percent, whose inclusion would flip.
+    pub churn_percent: f64,
+    /// Every user's inclusion, in list order.
+    pub details: Vec<UserImpact>,
+}
+
+impl Bucketing {
+    /// Returns whether this bucketing includes a user in `rollout`.
+    ///
+    /// A consistent-hash ring is built once, here, for every user tested.
+    pub fn inclusion<'a>(&'a self, rollout: &'a Rollout<'a>) -> impl Fn(&str) -> bool + 'a {
+        let prefix = format!(
+            "{}{}",
+            self.salt.as_deref().unwrap_or(rollout.flag),
+            self.namespace.as_deref().unwrap_or("")
+        );
+        let ring = (self.algorithm == Algorithm::ConsistentHash).then(|| {
+            HashRing::new(vec![
+                Bucket {
+                    variant: rollout.on.to_owned(),
+                    weight: u32::from(rollout.percentage),
+                },
+                Bucket {
+                    variant: rollout.off.to_owned(),
+                    weight: 100 - u32::from(rollout.percentage),
+                },
+            ])
+        });
+        move |user| {
+            let value = format!("{prefix}{user}");
+            match &ring {
+                None => bucket_for(&value, TOTAL_WEIGHT) < u64::from(rollout.percentage),
+                Some(ring) => ring.locate(&value) == Some(rollout.on),
+            }
+        }
+    }
+}
+
+/// Compares the inclusion of every user of `users` in `rollout` under the
+/// `current` and `proposed` bucketings.
+#[must_use]
+pub fn compute(
+    rollout: &Rollout<'_>,
+    current: &Bucketing,
+    proposed: &Bucketing,
+    users: &[String],
+) -> RolloutImpact {
+    let (now, after) = (current.inclusion(rollout), proposed.inclusion(rollout));
+    let details: Vec<UserImpact> = users
+        .iter()
+        .map(|user| UserImpact {
+            user: user.clone(),
+            included_now: now(user),
+            included_after: after(user),
+        })
+        .collect();
+    let count = |pred: fn(&UserImpact) -> bool| details.iter().filter(|u| pred(u)).count();
+    let moved_in = count(|u| !u.included_now && u.included_after);
+    let moved_out = count(|u| u.included_now && !u.included_after);
+    #[allow(clippy::cast_precision_loss)]
+    let churn_percent = if users.is_empty() {
+        0.0
+    } else {
+        (moved_in + moved_out) as f64 * 100.0 / users.len() as f64
+    };
+    RolloutImpact {
+        percentage: rollout.percentage,
+        current: current.clone(),
+        proposed: proposed.clone(),
+        users: users.len(),
+        included_now: count(|u| u.included_now),
+        included_after: count(|u| u.included_after),
+        moved_in,
+        moved_out,
+        churn_percent,
+        details,
+    }
+}
+
+/// Reads the user list named by `args` and renders the report in its format.
+///
+/// The proposed bucketing defaults, field by field, to the current one.
+///
+/// # Errors
+///
+/// Returns [`RolloutImpactError`] when the user list cannot be read.
+pub fn report(args: &RolloutImpactArgs) -> Result<String, RolloutImpactError> {
+    let users =
+        crate::buckets::read_users(&args.users).map_err(|source| RolloutImpactError::Read {
+            path: args.users.clone(),
+            source,
+        })?;
+    let current = Bucketing {
+        algorithm: args.algorithm,
+        salt: args.salt.clone(),
+        namespace: args.namespace.clone(),
+    };
+    let proposed = Bucketing {
+        algorithm: args.proposed_algorithm.unwrap_or(args.algorithm),
+        salt: args.proposed_salt.clone().or_else(|| args.salt.clone()),
+        namespace: args
+            .proposed_namespace
+            .clone()
+            .or_else(|| args.namespace.clone()),
+    };
+    let rollout = Rollout {
+        flag: &args.flag,
+        percentage: args.percentage,
+        on: &args.on_variant,
+        off: &args.off_variant,
+    };
+    let impact = compute(&rollout, &current, &proposed, &users);
+    match args.format {
+        ImpactFormat::Text => Ok(impact.to_string()),
+        ImpactFormat::Json => Ok(serde_json::to_string_pretty(&impact)? + "\n"),
+    }
+}
+
+impl fmt::Display for RolloutImpact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "users:          {}", self.users)?;
+        writeln!(f, "rollout:        {}%", self.percentage)?;
+        writeln!(f, "included now:   {}", self.included_now)?;
+        writeln!(f, "included after: {}", self.included_after)?;
+        writeln!(f, "moved in:       {}", self.moved_in)?;
+        writeln!(f, "moved out:      {}", self.moved_out)?;
+        writeln!(f, "churn:          {:.1}%", self.churn_percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+
+    fn users() -> Vec<String> {
+        (0..1_000).map(|i| format!("user-{i}")).collect()
+    }
+
+    fn rollout(percentage: u8) -> Rollout<'static> {
+        Rollout {
+            flag: "checkout",
+            percentage,
+            on: "on",
+            off: "off",
+        }
+    }
+
+    fn fractional(salt: Option<&str>) -> Bucketing {
+        Bucketing {
+            algorithm: Algorithm::Fractional,
+            salt: salt.map(str::to_owned),
+            namespace: None,
+        }
+    }
+
+    #[test]
+    fn an_unchanged_bucketing_moves_no_one() {
+        let impact = compute(&rollout(25), &fractional(None), &fractional(None), &users());
+        assert_eq!(impact.moved_in, 0);
+        assert_eq!(impact.moved_out, 0);
+        assert!(impact.churn_percent.abs() < f64::EPSILON);
+        assert_eq!(impact.included_now, impact.included_after);
+    }
+
+    #[test]
+    fn a_new_salt_reports_the_users_whose_bucket_crosses_the_percentage() {
+        let users = users();
+        let impact = compute(
+            &rollout(25),
+            &fractional(None),
+            &fractional(Some("spring")),
+            &users,
+        );
+
+        let expected_flips = users
+            .iter()
+            .filter(|user| {
+                (bucket_for(&format!("checkout{user}"), 100) < 25)
+                    != (bucket_for(&format!("spring{user}"), 100) < 25)
+            })
+            .count();
+        assert_eq!(impact.moved_in + impact.moved_out, expected_flips);
+        assert!(expected_flips > 0);
+        #[allow(clippy::cast_precision_loss)]
+        let expected_churn = expected_flips as f64 / 10.0;
+        assert!((impact.churn_percent - expected_churn).abs() < 1e-9);
+        assert_eq!(
+            impact.included_after + impact.moved_out,
+            impact.included_now + impact.moved_in
+        );
+        // An independent reshuffle at 25% flips about 2 * 0.25 * 0.75 of
+        // the users.
+        assert!((30.0..45.0).contains(&impact.churn_percent), "{impact}");
+    }
+
+    #[test]
+    fn switching_to_consistent_hash_uses_the_ring() {
+        let proposed = Bucketing {
+            algorithm: Algorithm::ConsistentHash,
+            salt: None,
+            namespace: None,
+        };
+        let impact = compute(&rollout(50), &fractional(None), &proposed, &users());
+
+        let ring = HashRing::new(vec![
+            Bucket {
+                variant: "on".to_owned(),
+                weight: 50,
+            },
+            Bucket {
+                variant: "off".to_owned(),
+                weight: 50,
+            },
+        ]);
+        for user in &impact.details {
+            assert_eq!(
+                user.included_after,
+                ring.locate(&format!("checkout{}", user.user)) == Some("on")
+            );
+        }
+        assert!(impact.moved_in > 0 && impact.moved_out > 0, "{impact}");
+
+        let everyone = compute(&rollout(100), &fractional(None), &proposed, &users());
+        assert!(everyone.churn_percent.abs() < f64::EPSILON);
+        let no_one = compute(&rollout(0), &fractional(None), &proposed, &users());
+        assert_eq!(no_one.included_after, 0);
+    }
+
+    #[test]
+    fn reports_text_and_json() {
+        let mut list = tempfile::NamedTempFile::new().expect("tempfile");
+        writeln!(list, "alice\n\n  bob  ").expect("write");
+        let mut args = RolloutImpactArgs {
+            flag: "checkout".to_owned(),
+            users: list.path().to_owned(),
+            percentage: 50,
+            algorithm: Algorithm::Fractional,
+            salt: None,
+            namespace: None,
+            proposed_algorithm: None,
+            proposed_salt: Some("spring".to_owned()),
+            proposed_namespace: None,
+            on_variant: "on".to_owned(),
+            off_variant: "off".to_owned(),
+            format: ImpactFormat::Text,
+        };
+
+        let text = report(&args).expect("reports");
+        assert!(
+            text.starts_with("users:          2\nrollout:        50%\n"),
+            "{text}"
+        );
+        assert!(text.contains("churn:"), "{text}");
+
+        args.format = ImpactFormat::Json;
+        let json: serde_json::Value =
+            serde_json::from_str(&report(&args).expect("reports")).expect("valid JSON");
+        assert_eq!(json["users"], 2);
+        assert_eq!(json["proposed"]["salt"], "spring");
+        assert_eq!(json["proposed"]["algorithm"], "fractional");
+        assert_eq!(json["details"][1]["user"], "bob");
+    }
+}
@@ -0,0 +1,62 @@
+//! `flapsd status`: checks the backends named by the config file and prints
+//! their health, as `GET /readyz` would report it.
+
+use std::fmt;
+
+use flaps_server::health::{HealthReport, HealthStatus};
+
+/// A [`HealthReport`] printed one component per line, failure details
+/// included.
+#[derive(Debug)]
+pub struct StatusReport(pub HealthReport);
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "status: {}", label(self.0.status))?;
+        for component in &self.0.components {
+            write!(
+                f,
+                "{:<12} {:<9} {} ms",
+                component.name,
+                label(component.status),
+                component.latency_ms
+            )?;
+            if let Some(error) = &component.error {
+                write!(f, "  {error}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn label(status: HealthStatus) -> &'static str {
+    match status {
+        HealthStatus::Healthy => "healthy",
+        HealthStatus::Unhealthy => "unhealthy",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flaps_server::health::ComponentHealth;
+
+    use super::*;
+
+    #[test]
+    fn failures_are_printed_with_their_details() {
+        let report = StatusReport(HealthReport {
+            status: HealthStatus::Unhealthy,
+            components: vec![ComponentHealth {
+                name: "database".to_owned(),
+                status: HealthStatus::Unhealthy,
+                latency_ms: 2000,
+                error: Some("timed out after 2000 ms".to_owned()),
+            }],
+        });
+        assert_eq!(
+            report.to_string(),
+            "status: unhealthy\ndatabase     unhealthy 2000 ms  timed out after 2000 ms\n"
+        );
+    }
+}